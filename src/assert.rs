@@ -0,0 +1,662 @@
+//! Declarative response assertions.
+//!
+//! In request files assertions are lines starting with `??` anywhere after
+//! the request line, and on the command line they are given with
+//! `--expect`. The syntax is `SUBJECT OP [VALUE]`:
+//!
+//! ```text
+//! ?? status == 200
+//! ?? status == 2xx
+//! ?? header Content-Type matches ^application/json
+//! ?? header X-Request-Id exists
+//! ?? body contains "ok"
+//! ?? json $.items[0].id == 42
+//! ?? duration < 500ms
+//! ?? event done within 5s
+//! ?? event progress contains 100%
+//! ```
+//!
+//! Operators are `==`, `!=`, `<`, `<=`, `>`, `>=`, `contains`, `matches`
+//! (regular expression), `exists` and `!exists`. Values are templates and
+//! may reference variables.
+//!
+//! `event TYPE` checks the events of a `text/event-stream` response, `*`
+//! standing for any type: `within` a duration after the request was sent,
+//! `exists` and `!exists`, or the other operators on the data of an event.
+
+use std::fmt;
+use std::time::Duration;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::diff::diff_lines;
+use crate::error::ParseError;
+use crate::http::Response;
+use crate::jsonpath::{value_to_string, JsonPath};
+use crate::sse;
+use crate::template::{Template, Variables};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Subject {
+	Status,
+	Header(String),
+	Body,
+	Json(String, JsonPath),
+	Duration,
+	/// Events of a type, `*` for any.
+	Event(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+	Eq,
+	Ne,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+	Contains,
+	Matches,
+	Exists,
+	NotExists,
+	/// An event arrived in time.
+	Within,
+}
+
+impl Op {
+	pub(crate) fn parse(text: &str) -> Option<Op> {
+		Some(match text {
+			"==" => Op::Eq,
+			"!=" => Op::Ne,
+			"<" => Op::Lt,
+			"<=" => Op::Le,
+			">" => Op::Gt,
+			">=" => Op::Ge,
+			"contains" => Op::Contains,
+			"matches" => Op::Matches,
+			"exists" => Op::Exists,
+			"!exists" => Op::NotExists,
+			"within" => Op::Within,
+			_ => return None,
+		})
+	}
+
+	pub(crate) fn takes_value(self) -> bool {
+		!matches!(self, Op::Exists | Op::NotExists)
+	}
+}
+
+impl fmt::Display for Op {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Op::Eq => "==",
+			Op::Ne => "!=",
+			Op::Lt => "<",
+			Op::Le => "<=",
+			Op::Gt => ">",
+			Op::Ge => ">=",
+			Op::Contains => "contains",
+			Op::Matches => "matches",
+			Op::Exists => "exists",
+			Op::NotExists => "!exists",
+			Op::Within => "within",
+		})
+	}
+}
+
+/// A single expectation on a response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assertion {
+	/// Source text, used in reports.
+	pub text: String,
+	pub line: usize,
+	pub subject: Subject,
+	pub op: Op,
+	pub value: Option<Template>,
+}
+
+/// Result of evaluating an assertion against a response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssertionResult {
+	pub assertion: String,
+	pub line: usize,
+	pub passed: bool,
+	/// Explanation of a failure, empty when the assertion passed.
+	pub message: String,
+	/// Diff between the expected and the actual value, for multi-line
+	/// mismatches.
+	pub diff: Option<String>,
+}
+
+impl Assertion {
+	/// Parses an assertion starting at the given position of a source.
+	pub fn parse(text: &str, line: usize, column: usize) -> Result<Assertion, ParseError> {
+		let text = text.trim();
+		let mut rest = text;
+		let mut word = || {
+			let trimmed = rest.trim_start();
+			let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+			let (word, after) = trimmed.split_at(end);
+			rest = after;
+			word
+		};
+		let error = |message: String| ParseError::new(line, column, message);
+
+		let subject = match word() {
+			"status" => Subject::Status,
+			"body" => Subject::Body,
+			"duration" => Subject::Duration,
+			"event" => match word() {
+				"" => return Err(error("missing event type".into())),
+				kind => Subject::Event(kind.to_string()),
+			},
+			"header" => match word() {
+				"" => return Err(error("missing header name".into())),
+				name => Subject::Header(name.to_string()),
+			},
+			"json" => {
+				let path = word();
+				if path.is_empty() {
+					return Err(error("missing JSON path".into()));
+				}
+				Subject::Json(path.to_string(), JsonPath::parse(path).map_err(error)?)
+			}
+			"" => return Err(error("empty assertion".into())),
+			other => {
+				return Err(error(format!(
+					"unknown assertion subject `{other}`, expected status, header, body, json, duration or event"
+				)))
+			}
+		};
+		let op_text = word();
+		let op =
+			Op::parse(op_text).ok_or_else(|| error(format!("unknown operator `{op_text}`")))?;
+
+		let value_text = rest.trim();
+		let value = match (op.takes_value(), value_text.is_empty()) {
+			(true, true) => return Err(error(format!("missing value for `{op}`"))),
+			(false, false) => return Err(error(format!("unexpected value after `{op}`"))),
+			(false, true) => None,
+			(true, false) => {
+				let offset = text.len() - rest.trim_start().len();
+				Some(Template::parse(
+					value_text,
+					line,
+					column + text[..offset].chars().count(),
+				)?)
+			}
+		};
+		let presence = !op.takes_value();
+		let within = op == Op::Within;
+		if (presence && matches!(subject, Subject::Status | Subject::Duration))
+			|| (within && !matches!(subject, Subject::Event(_)))
+		{
+			return Err(error(format!("`{op}` is not supported for this subject")));
+		}
+
+		Ok(Assertion {
+			text: text.to_string(),
+			line,
+			subject,
+			op,
+			value,
+		})
+	}
+
+	/// Checks the assertion against a response received after `elapsed`.
+	pub fn evaluate(
+		&self,
+		response: &Response,
+		elapsed: Duration,
+		vars: &Variables,
+	) -> AssertionResult {
+		let outcome = match &self.value {
+			Some(template) => match template.render(vars) {
+				Ok(expected) if matches!(self.subject, Subject::Json(..)) => {
+					self.check(response, elapsed, Some(&expected))
+				}
+				Ok(expected) => self.check(response, elapsed, Some(&unquote(&expected))),
+				Err(err) => Err((err.to_string(), None)),
+			},
+			None => self.check(response, elapsed, None),
+		};
+		let (passed, message, diff) = match outcome {
+			Ok(()) => (true, String::new(), None),
+			Err((message, diff)) => (false, message, diff),
+		};
+		AssertionResult {
+			assertion: self.text.clone(),
+			line: self.line,
+			passed,
+			message,
+			diff,
+		}
+	}
+
+	fn check(&self, response: &Response, elapsed: Duration, expected: Option<&str>) -> Failure {
+		let op = self.op;
+		let expected = expected.unwrap_or_default();
+		match &self.subject {
+			Subject::Status => {
+				let actual = response.status;
+				let passed = match status_class(expected) {
+					Some(class) if op == Op::Eq => actual / 100 == class,
+					Some(class) if op == Op::Ne => actual / 100 != class,
+					_ => compare_text(op, &actual.to_string(), expected)?,
+				};
+				expect(passed, || {
+					format!("expected status {op} {expected}, got {actual}")
+				})
+			}
+			Subject::Duration => {
+				let limit = parse_millis(expected)
+					.ok_or_else(|| (format!("invalid duration `{expected}`"), None))?;
+				let actual = elapsed.as_secs_f64() * 1000.0;
+				let passed = compare_numbers(op, actual, limit)?;
+				expect(passed, || {
+					format!("expected duration {op} {limit} ms, took {actual:.0} ms")
+				})
+			}
+			Subject::Header(name) => {
+				let actual = response.headers.get(name);
+				match (op, actual) {
+					(Op::Exists, actual) => expect(actual.is_some(), || {
+						format!("expected header {name} to exist")
+					}),
+					(Op::NotExists, actual) => expect(actual.is_none(), || {
+						format!(
+							"expected header {name} to be absent, got `{}`",
+							actual.unwrap_or_default()
+						)
+					}),
+					(_, None) => Err((format!("header {name} is missing"), None)),
+					(_, Some(actual)) => {
+						let passed = compare_text(op, actual, expected)?;
+						expect(passed, || {
+							format!("expected header {name} {op} `{expected}`, got `{actual}`")
+						})
+					}
+				}
+			}
+			Subject::Body => {
+				let actual = response.body_text();
+				let passed = compare_text(op, &actual, expected)?;
+				if passed {
+					Ok(())
+				} else if op == Op::Eq && (actual.contains('\n') || expected.contains('\n')) {
+					Err((
+						"body does not match".into(),
+						Some(diff_lines(expected, &actual)),
+					))
+				} else {
+					Err((
+						format!(
+							"expected body {op} `{expected}`, got `{}`",
+							excerpt(&actual)
+						),
+						None,
+					))
+				}
+			}
+			Subject::Json(path, json_path) => {
+				let document: Value = serde_json::from_slice(&response.body)
+					.map_err(|err| (format!("response body is not valid JSON: {err}"), None))?;
+				let actual = json_path.select(&document);
+				match (op, actual) {
+					(Op::Exists, actual) => {
+						expect(actual.is_some(), || format!("expected {path} to exist"))
+					}
+					(Op::NotExists, actual) => {
+						expect(actual.is_none(), || format!("expected {path} to be absent"))
+					}
+					(_, None) => Err((format!("{path} not found in response"), None)),
+					(Op::Eq | Op::Ne, Some(actual)) => {
+						let wanted = serde_json::from_str(expected)
+							.unwrap_or_else(|_| Value::String(expected.into()));
+						let passed = (actual == wanted) == (op == Op::Eq);
+						if passed {
+							Ok(())
+						} else if op == Op::Eq && (actual.is_object() || actual.is_array()) {
+							let pretty =
+								|v: &Value| serde_json::to_string_pretty(v).unwrap_or_default();
+							Err((
+								format!("{path} does not match"),
+								Some(diff_lines(&pretty(&wanted), &pretty(&actual))),
+							))
+						} else {
+							Err((format!("expected {path} {op} {wanted}, got {actual}"), None))
+						}
+					}
+					(Op::Contains, Some(Value::Array(items))) => {
+						let wanted = serde_json::from_str(expected)
+							.unwrap_or_else(|_| Value::String(expected.into()));
+						expect(items.contains(&wanted), || {
+							format!("expected {path} to contain {wanted}")
+						})
+					}
+					(_, Some(actual)) => {
+						let text = value_to_string(&actual);
+						let passed = compare_text(op, &text, expected)?;
+						expect(passed, || {
+							format!("expected {path} {op} {expected}, got {actual}")
+						})
+					}
+				}
+			}
+			Subject::Event(kind) => {
+				let events = match response.events.is_empty() && sse::is_event_stream(response) {
+					true => sse::parse(&response.body, elapsed),
+					false => response.events.clone(),
+				};
+				let mut matching = events
+					.iter()
+					.filter(|event| kind == "*" || event.event == *kind);
+				let millis = |time: Duration| time.as_secs_f64() * 1000.0;
+				match op {
+					Op::Exists => expect(matching.next().is_some(), || {
+						format!("expected an event {kind}")
+					}),
+					Op::NotExists => match matching.next() {
+						Some(event) => Err((
+							format!(
+								"expected no event {kind}, got one after {:.0} ms",
+								millis(event.received)
+							),
+							None,
+						)),
+						None => Ok(()),
+					},
+					Op::Within => {
+						let limit = parse_millis(expected)
+							.ok_or_else(|| (format!("invalid duration `{expected}`"), None))?;
+						match matching.next() {
+							Some(event) if millis(event.received) <= limit => Ok(()),
+							Some(event) => Err((
+								format!(
+									"expected an event {kind} within {limit} ms, the first came after {:.0} ms",
+									millis(event.received)
+								),
+								None,
+							)),
+							None => Err((
+								format!("expected an event {kind} within {limit} ms, got none"),
+								None,
+							)),
+						}
+					}
+					_ => {
+						for event in matching {
+							if compare_text(op, &event.data, expected)? {
+								return Ok(());
+							}
+						}
+						Err((
+							format!("expected an event {kind} with data {op} `{expected}`"),
+							None,
+						))
+					}
+				}
+			}
+		}
+	}
+
+	/// Returns true for an assertion on the events of a response.
+	pub fn is_event(&self) -> bool {
+		matches!(self.subject, Subject::Event(_))
+	}
+
+	/// Time allowed by a `within` assertion, `None` for other assertions
+	/// and invalid durations.
+	pub fn time_limit(&self, vars: &Variables) -> Option<Duration> {
+		if self.op != Op::Within {
+			return None;
+		}
+		let text = self.value.as_ref()?.render(vars).ok()?;
+		let millis = parse_millis(&unquote(&text))?;
+		Some(Duration::from_secs_f64(millis.max(0.0) / 1000.0))
+	}
+}
+
+/// Failure message and optional diff.
+type Failure = Result<(), (String, Option<String>)>;
+
+fn expect(passed: bool, message: impl FnOnce() -> String) -> Failure {
+	if passed {
+		Ok(())
+	} else {
+		Err((message(), None))
+	}
+}
+
+pub(crate) fn compare_text(
+	op: Op,
+	actual: &str,
+	expected: &str,
+) -> Result<bool, (String, Option<String>)> {
+	Ok(match op {
+		Op::Eq => actual == expected,
+		Op::Ne => actual != expected,
+		Op::Contains => actual.contains(expected),
+		Op::Matches => Regex::new(expected)
+			.map_err(|err| (format!("invalid regular expression: {err}"), None))?
+			.is_match(actual),
+		Op::Lt | Op::Le | Op::Gt | Op::Ge => {
+			let number = |text: &str| {
+				text.trim()
+					.parse::<f64>()
+					.map_err(|_| (format!("`{text}` is not a number"), None))
+			};
+			compare_numbers(op, number(actual)?, number(expected)?)?
+		}
+		Op::Within => return Err(("`within` only applies to events".into(), None)),
+		Op::Exists | Op::NotExists => unreachable!("presence is checked by the caller"),
+	})
+}
+
+fn compare_numbers(op: Op, actual: f64, expected: f64) -> Result<bool, (String, Option<String>)> {
+	Ok(match op {
+		Op::Eq => actual == expected,
+		Op::Ne => actual != expected,
+		Op::Lt => actual < expected,
+		Op::Le => actual <= expected,
+		Op::Gt => actual > expected,
+		Op::Ge => actual >= expected,
+		_ => return Err((format!("`{op}` is not supported for numbers"), None)),
+	})
+}
+
+/// Parses `2xx`-style status classes.
+fn status_class(text: &str) -> Option<u16> {
+	let text = text.to_ascii_lowercase();
+	let digit = text.strip_suffix("xx")?;
+	match digit.parse::<u16>() {
+		Ok(class @ 1..=5) if digit.len() == 1 => Some(class),
+		_ => None,
+	}
+}
+
+/// Parses `500`, `500ms` or `1.5s` into milliseconds.
+fn parse_millis(text: &str) -> Option<f64> {
+	let text = text.trim();
+	if let Some(ms) = text.strip_suffix("ms") {
+		ms.trim().parse().ok()
+	} else if let Some(secs) = text.strip_suffix('s') {
+		secs.trim().parse::<f64>().ok().map(|s| s * 1000.0)
+	} else {
+		text.parse().ok()
+	}
+}
+
+/// Removes surrounding double quotes, so `"a b"` can express values with
+/// leading or trailing spaces. Not used for JSON values, where quotes
+/// distinguish strings from other types.
+pub(crate) fn unquote(text: &str) -> String {
+	if text.len() >= 2
+		&& text.starts_with('"')
+		&& text.ends_with('"')
+		&& !text[1..text.len() - 1].contains('"')
+	{
+		text[1..text.len() - 1].to_string()
+	} else {
+		text.to_string()
+	}
+}
+
+fn excerpt(text: &str) -> String {
+	const MAX: usize = 200;
+	match text.char_indices().nth(MAX) {
+		Some((index, _)) => format!("{}...", &text[..index]),
+		None => text.to_string(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::http::Headers;
+
+	fn response(status: u16, headers: &[(&str, &str)], body: &str) -> Response {
+		Response {
+			version: "HTTP/1.1".into(),
+			status,
+			reason: String::new(),
+			headers: headers.iter().map(|(k, v)| (*k, *v)).collect::<Headers>(),
+			body: body.as_bytes().to_vec(),
+			..Default::default()
+		}
+	}
+
+	fn check(text: &str, response: &Response) -> AssertionResult {
+		let mut vars = Variables::new();
+		vars.set("id", "42");
+		Assertion::parse(text, 1, 1)
+			.unwrap()
+			.evaluate(response, Duration::from_millis(120), &vars)
+	}
+
+	fn passes(text: &str, response: &Response) -> bool {
+		check(text, response).passed
+	}
+
+	#[test]
+	fn checks_status() {
+		let res = response(404, &[], "");
+		assert!(passes("status == 404", &res));
+		assert!(passes("status == 4xx", &res));
+		assert!(passes("status != 2xx", &res));
+		assert!(passes("status >= 400", &res));
+		let result = check("status == 200", &res);
+		assert!(!result.passed);
+		assert_eq!(result.message, "expected status == 200, got 404");
+	}
+
+	#[test]
+	fn checks_headers() {
+		let res = response(
+			200,
+			&[("Content-Type", "application/json; charset=utf-8")],
+			"",
+		);
+		assert!(passes("header content-type exists", &res));
+		assert!(passes("header X-Missing !exists", &res));
+		assert!(passes(
+			"header Content-Type matches ^application/json",
+			&res
+		));
+		assert!(passes("header Content-Type contains charset", &res));
+		assert!(!passes("header Content-Type == text/html", &res));
+		assert_eq!(
+			check("header X-Id == 1", &res).message,
+			"header X-Id is missing"
+		);
+	}
+
+	#[test]
+	fn checks_body_with_diff() {
+		let res = response(200, &[], "line 1\nline 2\n");
+		assert!(passes("body contains line 2", &res));
+		assert!(passes("body matches ^line \\d", &res));
+		let result = check("body == line 1", &res);
+		assert!(!result.passed);
+		assert_eq!(result.diff.as_deref(), Some("  line 1\n+ line 2\n"));
+	}
+
+	#[test]
+	fn checks_json_paths() {
+		let res = response(
+			200,
+			&[],
+			r#"{"id": 42, "name": "a b", "tags": ["x", "y"], "obj": {"k": 1}}"#,
+		);
+		assert!(passes("json $.id == {{id}}", &res));
+		assert!(passes("json $.id > 40", &res));
+		assert!(passes("json $.name == \"a b\"", &res));
+		assert!(passes("json $.name == a b", &res));
+		assert!(passes("json $.tags contains y", &res));
+		assert!(passes("json $.tags.length == 2", &res));
+		assert!(passes("json $.missing !exists", &res));
+		assert!(!passes("json $.id == \"42\"", &res));
+
+		let result = check("json $.obj == {\"k\": 2}", &res);
+		assert!(!result.passed);
+		assert!(result.diff.unwrap().contains("-   \"k\": 2\n+   \"k\": 1"));
+
+		let result = check("json $.id == 1", &response(200, &[], "<html>"));
+		assert!(result
+			.message
+			.starts_with("response body is not valid JSON"));
+	}
+
+	#[test]
+	fn checks_duration() {
+		let res = response(200, &[], "");
+		assert!(passes("duration < 500ms", &res));
+		assert!(passes("duration < 1s", &res));
+		assert!(!passes("duration < 100", &res));
+	}
+
+	#[test]
+	fn checks_events() {
+		let mut res = response(
+			200,
+			&[("Content-Type", "text/event-stream")],
+			"event: progress\ndata: 50%\n\nevent: done\ndata: {}\n\n",
+		);
+		assert!(passes("event done within 1s", &res));
+		assert!(passes("event * exists", &res));
+		assert!(passes("event error !exists", &res));
+		assert!(passes("event progress contains 50", &res));
+		assert!(!passes("event progress == 100%", &res));
+		assert_eq!(
+			check("event done within 100ms", &res).message,
+			"expected an event done within 100 ms, the first came after 120 ms"
+		);
+
+		// streamed events carry their own arrival time
+		res.events = sse::parse(b"event: done\ndata: x\n\n", Duration::from_millis(30));
+		assert!(passes("event done within 100ms", &res));
+		assert_eq!(
+			check("event error within 1s", &res).message,
+			"expected an event error within 1000 ms, got none"
+		);
+		let assertion = Assertion::parse("event done within 2.5s", 1, 1).unwrap();
+		assert_eq!(
+			assertion.time_limit(&Variables::new()),
+			Some(Duration::from_millis(2500))
+		);
+		assert!(Assertion::parse("status within 1s", 1, 1).is_err());
+		assert!(Assertion::parse("event within 1s", 1, 1).is_err());
+	}
+
+	#[test]
+	fn rejects_invalid_syntax() {
+		assert!(Assertion::parse("status", 1, 1).is_err());
+		assert!(Assertion::parse("status ~ 200", 1, 1).is_err());
+		assert!(Assertion::parse("status exists", 1, 1).is_err());
+		assert!(Assertion::parse("header X exists 1", 1, 1).is_err());
+		assert!(Assertion::parse("cookie x == 1", 1, 1).is_err());
+		let err = Assertion::parse("body == {{x", 3, 4).unwrap_err();
+		assert_eq!((err.line, err.column), (3, 12));
+	}
+}
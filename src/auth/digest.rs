@@ -0,0 +1,342 @@
+//! HTTP Digest authentication (RFC 7616), with the MD5 and SHA-256
+//! algorithms and their `-sess` variants.
+
+use crate::http::{Request, Response};
+use crate::util::random_u64;
+
+/// `WWW-Authenticate: Digest` challenge of a server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Challenge {
+	pub realm: String,
+	pub nonce: String,
+	pub opaque: Option<String>,
+	/// `MD5` when the server does not name one.
+	pub algorithm: String,
+	/// Offered qualities of protection, `auth` and `auth-int`.
+	pub qop: Vec<String>,
+}
+
+impl Challenge {
+	/// Parses a `Digest ...` header value.
+	pub fn parse(value: &str) -> Option<Challenge> {
+		let (scheme, rest) = value.trim().split_once(char::is_whitespace)?;
+		if !scheme.eq_ignore_ascii_case("digest") {
+			return None;
+		}
+		let params = auth_params(rest);
+		let param = |name: &str| {
+			params
+				.iter()
+				.find(|(key, _)| key.eq_ignore_ascii_case(name))
+				.map(|(_, value)| value.clone())
+		};
+		Some(Challenge {
+			realm: param("realm").unwrap_or_default(),
+			nonce: param("nonce")?,
+			opaque: param("opaque"),
+			algorithm: param("algorithm").unwrap_or_else(|| "MD5".into()),
+			qop: param("qop")
+				.map(|qop| qop.split(',').map(|q| q.trim().to_string()).collect())
+				.unwrap_or_default(),
+		})
+	}
+
+	/// Strongest supported Digest challenge of a 401 response.
+	pub fn from_response(response: &Response) -> Option<Challenge> {
+		let mut challenges: Vec<Challenge> = response
+			.headers
+			.get_all("WWW-Authenticate")
+			.filter_map(Challenge::parse)
+			.filter(|challenge| hash_fn(&challenge.algorithm).is_some())
+			.collect();
+		challenges.sort_by_key(|challenge| {
+			!challenge
+				.algorithm
+				.to_ascii_uppercase()
+				.starts_with("SHA-256")
+		});
+		challenges.into_iter().next()
+	}
+
+	/// `Authorization` header answering the challenge for a request.
+	pub fn authorization(&self, user: &str, password: &str, request: &Request) -> String {
+		let cnonce = format!("{:016x}", random_u64());
+		self.authorization_with(user, password, request, &cnonce)
+	}
+
+	fn authorization_with(
+		&self,
+		user: &str,
+		password: &str,
+		request: &Request,
+		cnonce: &str,
+	) -> String {
+		let qop = if self.qop.iter().any(|q| q == "auth") {
+			Some("auth")
+		} else if self.qop.iter().any(|q| q == "auth-int") {
+			Some("auth-int")
+		} else {
+			None
+		};
+		let uri = &request.url.path;
+		let response = compute(&Answer {
+			user,
+			password,
+			realm: &self.realm,
+			nonce: &self.nonce,
+			algorithm: &self.algorithm,
+			method: &request.method,
+			uri,
+			qop,
+			nc: "00000001",
+			cnonce,
+			body: &request.body,
+		})
+		.unwrap_or_default();
+		let mut header = format!(
+			"Digest username=\"{user}\", realm=\"{}\", nonce=\"{}\", uri=\"{uri}\", algorithm={}, response=\"{response}\"",
+			self.realm, self.nonce, self.algorithm
+		);
+		if let Some(qop) = qop {
+			header.push_str(&format!(", qop={qop}, nc=00000001, cnonce=\"{cnonce}\""));
+		}
+		if let Some(opaque) = &self.opaque {
+			header.push_str(&format!(", opaque=\"{opaque}\""));
+		}
+		header
+	}
+}
+
+/// Challenges sent by the mock server, strongest first.
+pub fn challenges(realm: &str) -> Vec<String> {
+	let nonce = format!("{:016x}{:016x}", random_u64(), random_u64());
+	["SHA-256", "MD5"]
+		.iter()
+		.map(|algorithm| {
+			format!(
+				"Digest realm=\"{realm}\", qop=\"auth\", algorithm={algorithm}, nonce=\"{nonce}\""
+			)
+		})
+		.collect()
+}
+
+/// Checks the `Authorization: Digest` header of a request against the
+/// credentials. Any nonce is accepted.
+pub fn verify(user: &str, password: &str, request: &Request) -> bool {
+	let Some(header) = request.headers.get("Authorization") else {
+		return false;
+	};
+	let Some(params) = header.trim().strip_prefix("Digest ").map(auth_params) else {
+		return false;
+	};
+	let param = |name: &str| {
+		params
+			.iter()
+			.find(|(key, _)| key.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str())
+	};
+	if param("username") != Some(user) || param("uri") != Some(request.url.path.as_str()) {
+		return false;
+	}
+	let expected = compute(&Answer {
+		user,
+		password,
+		realm: param("realm").unwrap_or_default(),
+		nonce: param("nonce").unwrap_or_default(),
+		algorithm: param("algorithm").unwrap_or("MD5"),
+		method: &request.method,
+		uri: &request.url.path,
+		qop: param("qop"),
+		nc: param("nc").unwrap_or_default(),
+		cnonce: param("cnonce").unwrap_or_default(),
+		body: &request.body,
+	});
+	expected.is_some() && expected.as_deref() == param("response")
+}
+
+/// Inputs of the `response` value.
+struct Answer<'a> {
+	user: &'a str,
+	password: &'a str,
+	realm: &'a str,
+	nonce: &'a str,
+	algorithm: &'a str,
+	method: &'a str,
+	uri: &'a str,
+	qop: Option<&'a str>,
+	nc: &'a str,
+	cnonce: &'a str,
+	body: &'a [u8],
+}
+
+/// Computes the `response` value, `None` for an unknown algorithm.
+fn compute(answer: &Answer) -> Option<String> {
+	let hash = hash_fn(answer.algorithm)?;
+	let mut ha1 = hash(format!("{}:{}:{}", answer.user, answer.realm, answer.password).as_bytes());
+	if answer.algorithm.to_ascii_lowercase().ends_with("-sess") {
+		ha1 = hash(format!("{ha1}:{}:{}", answer.nonce, answer.cnonce).as_bytes());
+	}
+	let ha2 = match answer.qop {
+		Some("auth-int") => {
+			hash(format!("{}:{}:{}", answer.method, answer.uri, hash(answer.body)).as_bytes())
+		}
+		_ => hash(format!("{}:{}", answer.method, answer.uri).as_bytes()),
+	};
+	Some(match answer.qop {
+		Some(qop) => hash(
+			format!(
+				"{ha1}:{}:{}:{}:{qop}:{ha2}",
+				answer.nonce, answer.nc, answer.cnonce
+			)
+			.as_bytes(),
+		),
+		None => hash(format!("{ha1}:{}:{ha2}", answer.nonce).as_bytes()),
+	})
+}
+
+fn hash_fn(algorithm: &str) -> Option<fn(&[u8]) -> String> {
+	let algorithm = algorithm.to_ascii_uppercase();
+	match algorithm.strip_suffix("-SESS").unwrap_or(&algorithm) {
+		"MD5" => Some(|data| hex(&md5(data))),
+		"SHA-256" => Some(|data| hex(ring::digest::digest(&ring::digest::SHA256, data).as_ref())),
+		_ => None,
+	}
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Splits comma separated `key=value` parameters with optionally quoted
+/// values.
+pub(crate) fn auth_params(text: &str) -> Vec<(String, String)> {
+	let mut params = Vec::new();
+	let mut rest = text.trim();
+	while let Some((key, after)) = rest.split_once('=') {
+		let key = key.trim().trim_start_matches(',').trim().to_string();
+		let after = after.trim_start();
+		let (value, after) = match after.strip_prefix('"') {
+			Some(quoted) => {
+				let mut value = String::new();
+				let mut chars = quoted.char_indices();
+				let mut end = quoted.len();
+				while let Some((index, c)) = chars.next() {
+					match c {
+						'\\' => value.extend(chars.next().map(|(_, c)| c)),
+						'"' => {
+							end = index + 1;
+							break;
+						}
+						c => value.push(c),
+					}
+				}
+				(value, &quoted[end..])
+			}
+			None => {
+				let end = after.find(',').unwrap_or(after.len());
+				(after[..end].trim().to_string(), &after[end..])
+			}
+		};
+		params.push((key, value));
+		rest = after.trim_start().trim_start_matches(',');
+	}
+	params
+}
+
+/// MD5 (RFC 1321), still the most common Digest algorithm.
+fn md5(data: &[u8]) -> [u8; 16] {
+	const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+	let constants: Vec<u32> = (0..64)
+		.map(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32)
+		.collect();
+	let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+	let mut message = data.to_vec();
+	message.push(0x80);
+	while message.len() % 64 != 56 {
+		message.push(0);
+	}
+	message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+	for block in message.chunks(64) {
+		let words: Vec<u32> = block
+			.chunks(4)
+			.map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+			.collect();
+		let [mut a, mut b, mut c, mut d] = state;
+		for i in 0..64 {
+			let (f, g) = match i / 16 {
+				0 => ((b & c) | (!b & d), i),
+				1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+				2 => (b ^ c ^ d, (3 * i + 5) % 16),
+				_ => (c ^ (b | !d), (7 * i) % 16),
+			};
+			let rotated = a
+				.wrapping_add(f)
+				.wrapping_add(constants[i])
+				.wrapping_add(words[g])
+				.rotate_left(SHIFTS[i / 16 * 4 + i % 4]);
+			a = d;
+			d = c;
+			c = b;
+			b = b.wrapping_add(rotated);
+		}
+		for (value, add) in state.iter_mut().zip([a, b, c, d]) {
+			*value = value.wrapping_add(add);
+		}
+	}
+	let mut digest = [0; 16];
+	for (chunk, value) in digest.chunks_mut(4).zip(state) {
+		chunk.copy_from_slice(&value.to_le_bytes());
+	}
+	digest
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::url::Url;
+
+	#[test]
+	fn hashes_md5() {
+		assert_eq!(hex(&md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+		assert_eq!(
+			hex(&md5(b"The quick brown fox jumps over the lazy dog")),
+			"9e107d9d372bb6826bd81d3542a419d6"
+		);
+		assert_eq!(hex(&md5(&[b'a'; 200])), "887f30b43b2867f4a9accceee7d16e6c");
+	}
+
+	#[test]
+	fn answers_challenges() {
+		// example of RFC 2617, section 3.5
+		let challenge = Challenge::parse(
+			"Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", \
+			 nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"",
+		)
+		.unwrap();
+		assert_eq!(challenge.qop, ["auth", "auth-int"]);
+		let url = Url::parse("http://www.nowhere.org/dir/index.html").unwrap();
+		let mut request = Request::new("GET", url);
+		let header = challenge.authorization_with("Mufasa", "Circle Of Life", &request, "0a4f113b");
+		assert!(header.contains("response=\"6629fae49393a05397450978507c4ef1\""));
+		assert!(header.ends_with("opaque=\"5ccc069c403ebaf9f0171e9517f40e41\""));
+
+		request.headers.set("Authorization", header);
+		assert!(verify("Mufasa", "Circle Of Life", &request));
+		assert!(!verify("Mufasa", "Circle of Life", &request));
+
+		let mut response = Response::default();
+		for value in challenges("webcat") {
+			response.headers.append("WWW-Authenticate", value);
+		}
+		response.headers.append(
+			"WWW-Authenticate",
+			"Digest nonce=\"n\", algorithm=SHA-512-256",
+		);
+		assert_eq!(
+			Challenge::from_response(&response).unwrap().algorithm,
+			"SHA-256"
+		);
+	}
+}
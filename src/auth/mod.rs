@@ -0,0 +1,248 @@
+//! Authentication schemes. An `Authorization` header can name a scheme with
+//! its credentials instead of the final value, which is computed when the
+//! request is sent:
+//!
+//! ```text
+//! Authorization: Basic USER PASSWORD
+//! Authorization: Digest USER PASSWORD
+//! Authorization: AWS access_key=AKID secret_key=SECRET region=eu-west-1 service=execute-api
+//! Authorization: OAuth2 token_url=https://idp.local/token client_id=ID client_secret=SECRET
+//! ```
+//!
+//! Other values, such as `Bearer TOKEN` or an already encoded `Basic`
+//! header, are sent as they are. The same syntax sets the scheme of all
+//! requests with `--auth`, and of a mock route with `# @auth`.
+
+mod digest;
+mod oauth2;
+mod sigv4;
+
+pub use digest::Challenge;
+pub use oauth2::{OAuth2, TokenCache};
+pub use sigv4::AwsSigV4;
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::http::Request;
+use crate::util::base64_encode;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Auth {
+	Basic {
+		user: String,
+		password: String,
+	},
+	/// Answers the `WWW-Authenticate: Digest` challenge of a first attempt.
+	Digest {
+		user: String,
+		password: String,
+	},
+	Bearer(String),
+	Aws(AwsSigV4),
+	/// Bearer token fetched from a token endpoint.
+	OAuth2(OAuth2),
+}
+
+impl Auth {
+	/// Parses `SCHEME CREDENTIALS`, where the scheme is case-insensitive.
+	pub fn parse(text: &str) -> std::result::Result<Auth, String> {
+		let (scheme, rest) = split_scheme(text);
+		match scheme.to_ascii_lowercase().as_str() {
+			"basic" | "digest" => {
+				let (user, password) = user_password(rest)
+					.ok_or_else(|| format!("expected `{scheme} USER PASSWORD`"))?;
+				Ok(if scheme.eq_ignore_ascii_case("basic") {
+					Auth::Basic { user, password }
+				} else {
+					Auth::Digest { user, password }
+				})
+			}
+			"bearer" if !rest.is_empty() => Ok(Auth::Bearer(rest.to_string())),
+			"bearer" => Err("expected `Bearer TOKEN`".into()),
+			"aws" => AwsSigV4::from_params(params(rest)?).map(Auth::Aws),
+			"oauth2" => OAuth2::from_params(params(rest)?).map(Auth::OAuth2),
+			"" => Err("missing authentication scheme".into()),
+			_ => Err(format!(
+				"unknown authentication scheme `{scheme}`, expected Basic, Digest, Bearer, AWS or OAuth2"
+			)),
+		}
+	}
+
+	/// Scheme named by an `Authorization` header value, `None` for a value
+	/// to send as it is.
+	pub fn from_header(value: &str) -> Option<std::result::Result<Auth, String>> {
+		let (scheme, rest) = split_scheme(value);
+		let named = match scheme.to_ascii_lowercase().as_str() {
+			// `Basic dXNlcjpwYXNz` is already encoded
+			"basic" => user_password(rest).is_some(),
+			"digest" => !rest.contains("username="),
+			"aws" | "oauth2" => true,
+			_ => false,
+		};
+		named.then(|| Auth::parse(value))
+	}
+
+	/// Sets the `Authorization` header. Digest authentication needs the
+	/// challenge of a response and is left to [`Client::send`].
+	pub fn apply(&self, client: &Client, request: &mut Request) -> Result<()> {
+		match self {
+			Auth::Basic { user, password } => {
+				request
+					.headers
+					.set("Authorization", basic_header(user, password));
+			}
+			Auth::Digest { .. } => {}
+			Auth::Bearer(token) => request
+				.headers
+				.set("Authorization", format!("Bearer {token}")),
+			Auth::Aws(aws) => aws.sign(request, crate::util::unix_time())?,
+			Auth::OAuth2(config) => {
+				let token = client.tokens.token(client, config)?;
+				request
+					.headers
+					.set("Authorization", format!("Bearer {token}"));
+			}
+		}
+		Ok(())
+	}
+
+	/// Checks the credentials of a request as a server would.
+	pub fn verify(&self, request: &Request) -> bool {
+		let header = request.headers.get("Authorization").unwrap_or_default();
+		match self {
+			Auth::Basic { user, password } => header == basic_header(user, password),
+			Auth::Digest { user, password } => digest::verify(user, password, request),
+			Auth::Bearer(token) => header == format!("Bearer {token}"),
+			Auth::Aws(aws) => aws.verify(request),
+			Auth::OAuth2(_) => false,
+		}
+	}
+
+	/// `WWW-Authenticate` values answering a request that failed
+	/// [`Auth::verify`].
+	pub fn challenges(&self) -> Vec<String> {
+		match self {
+			Auth::Basic { .. } => vec!["Basic realm=\"webcat\"".into()],
+			Auth::Digest { .. } => digest::challenges("webcat"),
+			Auth::Bearer(_) | Auth::OAuth2(_) => vec!["Bearer realm=\"webcat\"".into()],
+			Auth::Aws(_) => Vec::new(),
+		}
+	}
+}
+
+/// Error for an `Authorization` header naming a scheme with invalid
+/// credentials.
+pub(crate) fn header_error(message: String) -> Error {
+	Error::Usage(format!("invalid `Authorization` header: {message}"))
+}
+
+pub(crate) fn basic_header(user: &str, password: &str) -> String {
+	format!(
+		"Basic {}",
+		base64_encode(format!("{user}:{password}").as_bytes())
+	)
+}
+
+fn split_scheme(text: &str) -> (&str, &str) {
+	let text = text.trim();
+	match text.split_once(char::is_whitespace) {
+		Some((scheme, rest)) => (scheme, rest.trim()),
+		None => (text, ""),
+	}
+}
+
+/// Splits `USER:PASSWORD` or `USER PASSWORD`, where the password may
+/// contain spaces.
+fn user_password(text: &str) -> Option<(String, String)> {
+	let first = text.split_whitespace().next()?;
+	let (user, password) = match first.contains(':') {
+		true => text.split_once(':')?,
+		false => text.split_once(char::is_whitespace)?,
+	};
+	Some((user.to_string(), password.trim().to_string()))
+}
+
+/// Parses whitespace separated `key=value` pairs, values may be quoted.
+fn params(text: &str) -> std::result::Result<Vec<(String, String)>, String> {
+	let mut pairs = Vec::new();
+	let mut rest = text.trim_start();
+	while !rest.is_empty() {
+		let (key, after) = rest
+			.split_once('=')
+			.filter(|(key, _)| !key.is_empty() && !key.contains(char::is_whitespace))
+			.ok_or_else(|| {
+				let word = rest.split_whitespace().next().unwrap_or_default();
+				format!("expected `key=value`, got `{word}`")
+			})?;
+		let (value, after) = match after.strip_prefix('"') {
+			Some(quoted) => quoted
+				.split_once('"')
+				.ok_or_else(|| format!("unterminated quote in `{key}`"))?,
+			None => after.split_once(char::is_whitespace).unwrap_or((after, "")),
+		};
+		pairs.push((key.to_ascii_lowercase(), value.to_string()));
+		rest = after.trim_start();
+	}
+	Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::url::Url;
+
+	#[test]
+	fn parses_schemes() {
+		assert_eq!(
+			Auth::parse("Basic jo:secret word").unwrap(),
+			Auth::Basic {
+				user: "jo".into(),
+				password: "secret word".into()
+			}
+		);
+		assert_eq!(
+			Auth::parse("digest jo secret").unwrap(),
+			Auth::Digest {
+				user: "jo".into(),
+				password: "secret".into()
+			}
+		);
+		assert_eq!(
+			Auth::parse("Bearer abc").unwrap(),
+			Auth::Bearer("abc".into())
+		);
+		let Auth::OAuth2(config) = Auth::parse(
+			"OAuth2 token_url=http://idp/token client_id=app client_secret=s scope=\"read write\"",
+		)
+		.unwrap() else {
+			panic!("expected OAuth2");
+		};
+		assert_eq!(config.scope.as_deref(), Some("read write"));
+		assert_eq!(
+			Auth::parse("OAuth2 client_id").unwrap_err(),
+			"expected `key=value`, got `client_id`"
+		);
+		assert!(Auth::parse("Negotiate x").is_err());
+
+		assert!(Auth::from_header("Basic dXNlcjpwYXNz").is_none());
+		assert!(Auth::from_header("Bearer abc").is_none());
+		assert!(Auth::from_header("Digest username=\"jo\", realm=\"r\"").is_none());
+		assert!(matches!(
+			Auth::from_header("Basic jo secret"),
+			Some(Ok(Auth::Basic { .. }))
+		));
+	}
+
+	#[test]
+	fn verifies_basic_and_bearer_credentials() {
+		let mut request = Request::new("GET", Url::parse("http://h/").unwrap());
+		let basic = Auth::parse("Basic Aladdin open sesame").unwrap();
+		basic.apply(&Client::new(), &mut request).unwrap();
+		assert_eq!(
+			request.headers.get("Authorization"),
+			Some("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==")
+		);
+		assert!(basic.verify(&request));
+		assert!(!Auth::parse("Bearer t").unwrap().verify(&request));
+	}
+}
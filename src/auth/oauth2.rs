@@ -0,0 +1,170 @@
+//! OAuth 2.0 access tokens from the client credentials and refresh token
+//! grants (RFC 6749, sections 4.4 and 6), cached until they expire.
+
+use std::sync::Mutex;
+
+use serde_json::Value;
+
+use super::basic_header;
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::http::Request;
+use crate::url::{form_encode, Url};
+use crate::util::unix_time;
+
+/// Seconds before the expiry at which a token is renewed, so it does not
+/// expire on the way to the server.
+const EXPIRY_MARGIN: u64 = 30;
+
+/// Token endpoint and client credentials.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OAuth2 {
+	pub token_url: String,
+	pub client_id: String,
+	pub client_secret: String,
+	pub scope: Option<String>,
+	/// Uses the refresh token grant instead of client credentials.
+	pub refresh_token: Option<String>,
+	/// Sends the client credentials in the form instead of a `Basic`
+	/// header, for servers that do not support the latter.
+	pub credentials_in_body: bool,
+}
+
+impl OAuth2 {
+	pub(crate) fn from_params(params: Vec<(String, String)>) -> std::result::Result<Self, String> {
+		let mut token_url = None;
+		let mut client_id = None;
+		let mut config = OAuth2 {
+			token_url: String::new(),
+			client_id: String::new(),
+			client_secret: String::new(),
+			scope: None,
+			refresh_token: None,
+			credentials_in_body: false,
+		};
+		for (key, value) in params {
+			match key.as_str() {
+				"token_url" => token_url = Some(value),
+				"client_id" => client_id = Some(value),
+				"client_secret" => config.client_secret = value,
+				"scope" => config.scope = Some(value),
+				"refresh_token" => config.refresh_token = Some(value),
+				"client_auth" => {
+					config.credentials_in_body = match value.as_str() {
+						"basic" => false,
+						"body" => true,
+						_ => {
+							return Err(format!(
+								"expected `client_auth=basic` or `body`, got `{value}`"
+							))
+						}
+					}
+				}
+				_ => return Err(format!("unknown OAuth2 parameter `{key}`")),
+			}
+		}
+		config.token_url = token_url.ok_or("missing OAuth2 `token_url`")?;
+		config.client_id = client_id.ok_or("missing OAuth2 `client_id`")?;
+		Ok(config)
+	}
+}
+
+/// Access tokens by configuration, shared by the clones of a client.
+#[derive(Debug, Default)]
+pub struct TokenCache {
+	tokens: Mutex<Vec<(OAuth2, Token)>>,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+	access_token: String,
+	/// Unix time after which the token is fetched again.
+	expires_at: Option<u64>,
+	/// Refresh token returned with the access token.
+	refresh_token: Option<String>,
+}
+
+impl TokenCache {
+	/// Returns a cached access token or fetches a new one. An expired token
+	/// is renewed with the refresh token that came with it, if any.
+	pub fn token(&self, client: &Client, config: &OAuth2) -> Result<String> {
+		// holding the lock makes concurrent requests wait for one fetch
+		let mut tokens = self.tokens.lock().unwrap_or_else(|err| err.into_inner());
+		let now = unix_time();
+		let cached = tokens.iter().position(|(key, _)| key == config);
+		let mut refresh_token = config.refresh_token.clone();
+		if let Some(index) = cached {
+			let token = &tokens[index].1;
+			if token.expires_at.is_none_or(|expires| now < expires) {
+				return Ok(token.access_token.clone());
+			}
+			refresh_token = token.refresh_token.clone().or(refresh_token);
+		}
+		let token = fetch(client, config, refresh_token.as_deref(), now)?;
+		let access_token = token.access_token.clone();
+		match cached {
+			Some(index) => tokens[index].1 = token,
+			None => tokens.push((config.clone(), token)),
+		}
+		Ok(access_token)
+	}
+}
+
+/// Requests a token from the endpoint.
+fn fetch(client: &Client, config: &OAuth2, refresh_token: Option<&str>, now: u64) -> Result<Token> {
+	let mut form = match refresh_token {
+		Some(token) => vec![
+			("grant_type".to_string(), "refresh_token".to_string()),
+			("refresh_token".to_string(), token.to_string()),
+		],
+		None => vec![("grant_type".to_string(), "client_credentials".to_string())],
+	};
+	if let Some(scope) = &config.scope {
+		form.push(("scope".into(), scope.clone()));
+	}
+	let mut request = Request::new("POST", Url::parse(&config.token_url)?);
+	request
+		.headers
+		.set("Content-Type", "application/x-www-form-urlencoded");
+	request.headers.set("Accept", "application/json");
+	if config.credentials_in_body {
+		form.push(("client_id".into(), config.client_id.clone()));
+		form.push(("client_secret".into(), config.client_secret.clone()));
+	} else {
+		request.headers.set(
+			"Authorization",
+			basic_header(&config.client_id, &config.client_secret),
+		);
+	}
+	request.body = form_encode(&form).into_bytes();
+
+	let response = client.send_once(&request)?;
+	let error = |message: String| {
+		Error::Protocol(format!(
+			"token request to {} failed: {message}",
+			config.token_url
+		))
+	};
+	let json: Value = serde_json::from_slice(&response.body)
+		.map_err(|_| error(format!("status {} without a JSON body", response.status)))?;
+	if !(200..300).contains(&response.status) {
+		let code = json["error"].as_str().unwrap_or("unknown error");
+		return Err(error(match json["error_description"].as_str() {
+			Some(description) => format!("{code}: {description}"),
+			None => code.to_string(),
+		}));
+	}
+	let access_token = json["access_token"]
+		.as_str()
+		.ok_or_else(|| error("no `access_token` in the response".into()))?;
+	// `expires_in` is a number, some servers send it as a string
+	let expires_in = match &json["expires_in"] {
+		Value::String(text) => text.parse().ok(),
+		value => value.as_u64(),
+	};
+	Ok(Token {
+		access_token: access_token.to_string(),
+		expires_at: expires_in.map(|secs| now + secs.saturating_sub(EXPIRY_MARGIN.min(secs / 2))),
+		refresh_token: json["refresh_token"].as_str().map(str::to_string),
+	})
+}
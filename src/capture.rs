@@ -0,0 +1,209 @@
+//! Values taken from a response into variables, so a request can use the
+//! result of an earlier one, e.g. the token returned by a login.
+//!
+//! In request files captures are lines starting with `>>` anywhere after
+//! the request line, in the form `NAME = SOURCE`:
+//!
+//! ```text
+//! >> token = json $.access_token
+//! >> location = header Location
+//! >> session = cookie SESSIONID
+//! >> csrf = regex name="csrf" value="([^"]+)"
+//! >> code = status
+//! >> raw = body
+//! ```
+//!
+//! A regular expression captures its first group, or the whole match
+//! without groups. Captured variables are available to the following
+//! requests of the same run, including those in later files, and take
+//! precedence over the variables of the files.
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::assert::AssertionResult;
+use crate::error::ParseError;
+use crate::http::Response;
+use crate::jsonpath::{value_to_string, JsonPath};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+	Status,
+	Header(String),
+	Cookie(String),
+	Body,
+	Json(String, JsonPath),
+	/// Pattern matched against the body.
+	Regex(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capture {
+	/// Source text, used in reports.
+	pub text: String,
+	pub line: usize,
+	pub name: String,
+	pub source: Source,
+}
+
+impl Capture {
+	/// Parses a capture starting at the given position of a source.
+	pub fn parse(text: &str, line: usize, column: usize) -> Result<Capture, ParseError> {
+		let text = text.trim();
+		let error = |message: String| ParseError::new(line, column, message);
+		let (name, source) = text
+			.split_once('=')
+			.ok_or_else(|| error("expected `>> name = source`".into()))?;
+		let name = name.trim();
+		if name.is_empty()
+			|| !name
+				.chars()
+				.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+		{
+			return Err(error(format!("invalid variable name `{name}`")));
+		}
+
+		let source = source.trim();
+		let (kind, argument) = match source.split_once(char::is_whitespace) {
+			Some((kind, argument)) => (kind, argument.trim()),
+			None => (source, ""),
+		};
+		let source = match (kind, argument) {
+			("status", "") => Source::Status,
+			("body", "") => Source::Body,
+			("status" | "body", _) => {
+				return Err(error(format!("unexpected text after `{kind}`")));
+			}
+			("header" | "cookie" | "json" | "regex", "") => {
+				return Err(error(format!("missing argument for `{kind}`")));
+			}
+			("header", name) => Source::Header(name.to_string()),
+			("cookie", name) => Source::Cookie(name.to_string()),
+			("json", path) => Source::Json(path.to_string(), JsonPath::parse(path).map_err(error)?),
+			("regex", pattern) => {
+				Regex::new(pattern)
+					.map_err(|err| error(format!("invalid regular expression: {err}")))?;
+				Source::Regex(pattern.to_string())
+			}
+			("", _) => return Err(error("missing capture source".into())),
+			(other, _) => {
+				return Err(error(format!(
+					"unknown capture source `{other}`, expected status, header, cookie, body, json or regex"
+				)))
+			}
+		};
+		Ok(Capture {
+			text: text.to_string(),
+			line,
+			name: name.to_string(),
+			source,
+		})
+	}
+
+	/// Takes the value from a response.
+	pub fn extract(&self, response: &Response) -> Result<String, String> {
+		match &self.source {
+			Source::Status => Ok(response.status.to_string()),
+			Source::Header(name) => response
+				.headers
+				.get(name)
+				.map(str::to_string)
+				.ok_or_else(|| format!("header {name} is missing")),
+			Source::Cookie(name) => response
+				.headers
+				.get_all("Set-Cookie")
+				.filter_map(|cookie| {
+					let pair = cookie.split(';').next()?;
+					let (key, value) = pair.split_once('=')?;
+					(key.trim() == name).then(|| value.trim().trim_matches('"').to_string())
+				})
+				.last()
+				.ok_or_else(|| format!("no cookie {name} is set")),
+			Source::Body => Ok(response.body_text()),
+			Source::Json(path, json_path) => {
+				let document: Value = serde_json::from_slice(&response.body)
+					.map_err(|err| format!("response body is not valid JSON: {err}"))?;
+				json_path
+					.select(&document)
+					.map(|value| value_to_string(&value))
+					.ok_or_else(|| format!("{path} not found in response"))
+			}
+			Source::Regex(pattern) => {
+				let regex = Regex::new(pattern).map_err(|err| err.to_string())?;
+				let body = response.body_text();
+				let captures = regex
+					.captures(&body)
+					.ok_or_else(|| format!("no match for `{pattern}` in the body"))?;
+				let matched = captures.get(1).or_else(|| captures.get(0));
+				Ok(matched.map_or("", |m| m.as_str()).to_string())
+			}
+		}
+	}
+
+	/// Result reported for a capture that could not be taken.
+	pub fn failure(&self, message: String) -> AssertionResult {
+		AssertionResult {
+			assertion: format!(">> {}", self.text),
+			line: self.line,
+			passed: false,
+			message,
+			diff: None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::http::Headers;
+
+	fn extract(text: &str, response: &Response) -> Result<String, String> {
+		Capture::parse(text, 1, 1).unwrap().extract(response)
+	}
+
+	#[test]
+	fn extracts_values() {
+		let headers: Headers = [
+			("Location", "/items/7"),
+			("Set-Cookie", "theme=dark"),
+			("Set-Cookie", "SESSIONID=\"abc\"; Path=/; HttpOnly"),
+		]
+		.into_iter()
+		.collect();
+		let response = Response {
+			status: 201,
+			headers,
+			body: br#"{"token": "t0k", "user": {"id": 7}}"#.to_vec(),
+			..Default::default()
+		};
+		assert_eq!(extract("token = json $.token", &response).unwrap(), "t0k");
+		assert_eq!(extract("id = json $.user.id", &response).unwrap(), "7");
+		assert_eq!(extract("code = status", &response).unwrap(), "201");
+		assert_eq!(
+			extract("to = header location", &response).unwrap(),
+			"/items/7"
+		);
+		assert_eq!(extract("s = cookie SESSIONID", &response).unwrap(), "abc");
+		assert_eq!(extract("n = regex \"id\": (\\d+)", &response).unwrap(), "7");
+		assert_eq!(extract("n = regex t0k", &response).unwrap(), "t0k");
+		assert_eq!(
+			extract("x = json $.missing", &response).unwrap_err(),
+			"$.missing not found in response"
+		);
+		assert_eq!(
+			extract("x = cookie other", &response).unwrap_err(),
+			"no cookie other is set"
+		);
+	}
+
+	#[test]
+	fn rejects_invalid_syntax() {
+		let error = |text: &str| Capture::parse(text, 1, 1).unwrap_err().message;
+		assert_eq!(error("token json $.a"), "expected `>> name = source`");
+		assert_eq!(error("a b = status"), "invalid variable name `a b`");
+		assert_eq!(error("a = header"), "missing argument for `header`");
+		assert_eq!(error("a = status 1"), "unexpected text after `status`");
+		assert!(error("a = regex (").starts_with("invalid regular expression"));
+		assert!(error("a = xml /a").starts_with("unknown capture source `xml`"));
+	}
+}
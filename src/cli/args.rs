@@ -0,0 +1,126 @@
+use crate::error::{Error, Result};
+
+/// Single command line item.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Arg {
+	/// An option such as `-H` or `--header`, including the dashes.
+	Flag(String),
+	/// A positional argument.
+	Value(String),
+}
+
+/// Minimal command line scanner. Supports `--flag value`, `--flag=value`,
+/// `-f value` and `--` to end option parsing.
+pub struct Args<'a> {
+	items: &'a [String],
+	index: usize,
+	inline: Option<String>,
+	flag: String,
+	only_values: bool,
+}
+
+impl<'a> Args<'a> {
+	pub fn new(items: &'a [String]) -> Self {
+		Args {
+			items,
+			index: 0,
+			inline: None,
+			flag: String::new(),
+			only_values: false,
+		}
+	}
+
+	pub fn next_arg(&mut self) -> Result<Option<Arg>> {
+		if let Some(value) = self.inline.take() {
+			return Err(Error::Usage(format!(
+				"option `{}` does not take a value (got `{value}`)",
+				self.flag
+			)));
+		}
+		let Some(item) = self.items.get(self.index) else {
+			return Ok(None);
+		};
+		self.index += 1;
+		if self.only_values || item == "-" || !item.starts_with('-') {
+			return Ok(Some(Arg::Value(item.clone())));
+		}
+		if item == "--" {
+			self.only_values = true;
+			return self.next_arg();
+		}
+		let flag = match item.split_once('=') {
+			Some((flag, value)) if flag.starts_with("--") => {
+				self.inline = Some(value.to_string());
+				flag.to_string()
+			}
+			_ => item.clone(),
+		};
+		self.flag = flag.clone();
+		Ok(Some(Arg::Flag(flag)))
+	}
+
+	/// Returns the value for the last flag returned by [`Args::next_arg`].
+	pub fn value(&mut self) -> Result<String> {
+		if let Some(value) = self.inline.take() {
+			return Ok(value);
+		}
+		match self.items.get(self.index) {
+			Some(value) => {
+				self.index += 1;
+				Ok(value.clone())
+			}
+			None => Err(Error::Usage(format!(
+				"option `{}` requires a value",
+				self.flag
+			))),
+		}
+	}
+
+	/// Parses the value for the last flag.
+	pub fn parse<T: std::str::FromStr>(&mut self) -> Result<T> {
+		let value = self.value()?;
+		value
+			.parse()
+			.map_err(|_| Error::Usage(format!("invalid value `{value}` for `{}`", self.flag)))
+	}
+}
+
+/// Error for an unrecognized option.
+pub fn unknown(flag: &str) -> Error {
+	Error::Usage(format!("unknown option `{flag}`, see `webcat --help`"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn strings(list: &[&str]) -> Vec<String> {
+		list.iter().map(|s| s.to_string()).collect()
+	}
+
+	#[test]
+	fn scans_flags_and_values() {
+		let list = strings(&["GET", "-H", "A: b", "--data=x=1", "--", "-v"]);
+		let mut args = Args::new(&list);
+		assert_eq!(args.next_arg().unwrap(), Some(Arg::Value("GET".into())));
+		assert_eq!(args.next_arg().unwrap(), Some(Arg::Flag("-H".into())));
+		assert_eq!(args.value().unwrap(), "A: b");
+		assert_eq!(args.next_arg().unwrap(), Some(Arg::Flag("--data".into())));
+		assert_eq!(args.value().unwrap(), "x=1");
+		assert_eq!(args.next_arg().unwrap(), Some(Arg::Value("-v".into())));
+		assert_eq!(args.next_arg().unwrap(), None);
+	}
+
+	#[test]
+	fn reports_missing_and_unexpected_values() {
+		let list = strings(&["-H"]);
+		let mut args = Args::new(&list);
+		args.next_arg().unwrap();
+		assert!(args.value().is_err());
+
+		let list = strings(&["--help=1"]);
+		let mut args = Args::new(&list);
+		args.next_arg().unwrap();
+		assert!(args.next_arg().is_err());
+	}
+}
@@ -0,0 +1,97 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use super::args::{unknown, Arg, Args};
+use crate::cookie::{Cookie, CookieJar};
+use crate::error::{Error, Result};
+use crate::url::Url;
+use crate::util::{format_rfc3339, unix_time};
+
+/// `webcat cookies [OPTIONS] <FILE>` lists the cookies of a jar file.
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let mut file = None;
+	let mut url = None;
+
+	let mut args = Args::new(list);
+	while let Some(arg) = args.next_arg()? {
+		match arg {
+			Arg::Value(value) if file.is_none() => file = Some(PathBuf::from(value)),
+			Arg::Value(value) => {
+				return Err(Error::Usage(format!("unexpected argument `{value}`")))
+			}
+			Arg::Flag(flag) => match flag.as_str() {
+				"-u" | "--url" => url = Some(Url::parse(&args.value()?)?),
+				_ => return Err(unknown(&flag)),
+			},
+		}
+	}
+	let file = file.ok_or_else(|| Error::Usage("missing cookie file".into()))?;
+	if !file.exists() {
+		return Err(Error::Usage(format!("cannot read `{}`", file.display())));
+	}
+	let jar = CookieJar::open(&file)?;
+
+	let now = unix_time();
+	let cookies: Vec<&Cookie> = match &url {
+		Some(url) => jar.matching(url, now),
+		None => jar
+			.cookies()
+			.iter()
+			.filter(|cookie| !cookie.is_expired(now))
+			.collect(),
+	};
+	let mut out = std::io::stdout().lock();
+	write_cookies(&mut out, &cookies)?;
+	Ok(ExitCode::SUCCESS)
+}
+
+/// Prints one aligned line per cookie: domain, path, expiry, flags and
+/// `name=value`. A leading dot marks cookies also sent to subdomains.
+fn write_cookies<W: Write>(out: &mut W, cookies: &[&Cookie]) -> Result<()> {
+	let rows: Vec<[String; 5]> = cookies
+		.iter()
+		.map(|cookie| {
+			let dot = if cookie.host_only { "" } else { "." };
+			let expires = match cookie.expires {
+				Some(secs) => format_rfc3339(secs),
+				None => "session".to_string(),
+			};
+			let mut flags = Vec::new();
+			if cookie.secure {
+				flags.push("Secure".to_string());
+			}
+			if cookie.http_only {
+				flags.push("HttpOnly".to_string());
+			}
+			if let Some(same_site) = cookie.same_site {
+				flags.push(format!("SameSite={same_site}"));
+			}
+			[
+				format!("{dot}{}", cookie.domain),
+				cookie.path.clone(),
+				expires,
+				if flags.is_empty() {
+					"-".to_string()
+				} else {
+					flags.join(",")
+				},
+				format!("{}={}", cookie.name, cookie.value),
+			]
+		})
+		.collect();
+	let mut widths = [0; 4];
+	for row in &rows {
+		for (width, field) in widths.iter_mut().zip(row) {
+			*width = (*width).max(field.chars().count());
+		}
+	}
+	for row in &rows {
+		for (width, field) in widths.iter().zip(row) {
+			write!(out, "{field:<width$}  ")?;
+		}
+		writeln!(out, "{}", row[4])?;
+	}
+	out.flush()?;
+	Ok(())
+}
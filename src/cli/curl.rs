@@ -0,0 +1,46 @@
+use std::io::{IsTerminal, Write};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+use super::send::{print_response, Details};
+use crate::client::Client;
+use crate::curl;
+use crate::error::Result;
+use crate::redirect::RedirectPolicy;
+
+/// `webcat curl <CURL ARGUMENTS>...` sends a pasted curl command.
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let words: Vec<Vec<u8>> = list.iter().map(|arg| arg.as_bytes().to_vec()).collect();
+	let mut command = curl::from_args(&words)?;
+	let mut client = Client::new();
+	client.progress = !command.silent && std::io::stderr().is_terminal();
+	client.tls.insecure = command.insecure;
+	// like curl, responses are only asked for compressed with `--compressed`
+	client.decode = command.compressed;
+	if command.follow_redirects {
+		let mut policy = RedirectPolicy::default();
+		policy.max_hops = command.max_redirects.unwrap_or(policy.max_hops);
+		client.redirects = Some(policy);
+	}
+	if command.timeout.is_some() {
+		client.timeout = command.timeout;
+	}
+	if let Some(jar) = command.cookies.take() {
+		client.cookies = Some(Arc::new(Mutex::new(jar)));
+	}
+
+	let details = Details::for_output(std::io::stdout().is_terminal());
+	let mut out = std::io::stdout().lock();
+	for (index, request) in command.requests.iter().enumerate() {
+		if index > 0 {
+			writeln!(out)?;
+		}
+		if command.requests.len() > 1 {
+			writeln!(out, "### {} {}", request.method, request.url)?;
+		}
+		let response = client.send(request)?;
+		print_response(&mut out, &response, details)?;
+	}
+	client.save_cookies()?;
+	Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,73 @@
+use std::path::Path;
+use std::process::ExitCode;
+
+use super::args::{unknown, Arg, Args};
+use super::EnvArgs;
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::export::{self, Tool};
+use crate::httpfile::RequestFile;
+use crate::template::Variables;
+
+/// `webcat export [OPTIONS] <FILE>`.
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let mut file = None;
+	let mut tool = Tool::Curl;
+	let mut only = None;
+	let mut variables = Variables::new();
+	let mut env = EnvArgs::default();
+
+	let mut args = Args::new(list);
+	while let Some(arg) = args.next_arg()? {
+		match arg {
+			Arg::Value(value) if file.is_none() => file = Some(value),
+			Arg::Value(value) => {
+				return Err(Error::Usage(format!("unexpected argument `{value}`")))
+			}
+			Arg::Flag(flag) => match flag.as_str() {
+				"-t" | "--to" => tool = args.parse()?,
+				"-n" | "--name" => only = Some(args.value()?),
+				"--var" => {
+					let pair = args.value()?;
+					let (name, value) = pair.split_once('=').ok_or_else(|| {
+						Error::Usage(format!("expected NAME=VALUE, got `{pair}`"))
+					})?;
+					variables.set(name.trim(), value);
+				}
+				_ if env.option(&flag, &mut args)? => {}
+				_ => return Err(unknown(&flag)),
+			},
+		}
+	}
+	let file = file.ok_or_else(|| Error::Usage("missing request file".into()))?;
+	env.apply(Path::new(&file), &mut variables)?;
+	let file = RequestFile::load(&file)?;
+	let selected = match &only {
+		Some(name) => vec![file.find(name).ok_or_else(|| {
+			Error::Usage(format!("no request named `{name}` in {}", file.source))
+		})?],
+		None => file.requests.iter().collect(),
+	};
+	// values captured by earlier requests are only known while running
+	let captured: Vec<&str> = file
+		.captured_names()
+		.filter(|name| !variables.contains(name))
+		.collect();
+	file.resolve_variables_except(&mut variables, &captured)?;
+
+	// fetches the OAuth2 tokens of the requests
+	let client = Client::new();
+	for (index, def) in selected.iter().enumerate() {
+		let mut request = file.build(def, &variables)?;
+		request.load_upload()?;
+		if index > 0 {
+			println!();
+		}
+		// a comment in both shells and PowerShell
+		if selected.len() > 1 {
+			println!("# {}", def.label());
+		}
+		println!("{}", export::command(&request, tool, &client)?);
+	}
+	Ok(ExitCode::SUCCESS)
+}
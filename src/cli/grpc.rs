@@ -0,0 +1,210 @@
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use serde_json::Value;
+
+use super::args::{unknown, Arg, Args};
+use super::send::{print_response, Details};
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::grpc::reflection::Reflection;
+use crate::grpc::schema::{self, Schema};
+use crate::grpc::{self, codec, proto};
+use crate::http::{parse_header_line, Headers, Part};
+use crate::render::{self, colors};
+use crate::url::Url;
+
+/// gRPC call: `webcat grpc [OPTIONS] <URL> [SERVICE/METHOD] [JSON]`. The
+/// messages of the response are printed as JSON as they arrive, followed
+/// by the trailers and the status. Without a method, lists the services.
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let mut client = Client::new();
+	let mut details = Details::for_output(std::io::stdout().is_terminal());
+	let mut positional = Vec::new();
+	let mut protos: Vec<PathBuf> = Vec::new();
+	let mut import_paths: Vec<PathBuf> = Vec::new();
+	let mut headers = Headers::new();
+	let mut data = None;
+	let mut web = false;
+
+	let mut args = Args::new(list);
+	while let Some(arg) = args.next_arg()? {
+		match arg {
+			Arg::Value(value) => positional.push(value),
+			Arg::Flag(flag) => match flag.as_str() {
+				"--proto" => protos.push(args.value()?.into()),
+				"-I" | "--import-path" => import_paths.push(args.value()?.into()),
+				"--web" => web = true,
+				"-d" | "--data" => data = Some(super::read_body_arg(&args.value()?)?),
+				"-H" | "--header" => {
+					let line = args.value()?;
+					let (name, value) = parse_header_line(&line)
+						.ok_or_else(|| Error::Usage(format!("invalid header `{line}`")))?;
+					headers.append(name, value);
+				}
+				_ if details.option(&flag) => {}
+				_ if super::client_option(&mut client, &flag, &mut args)? => {}
+				_ => return Err(unknown(&flag)),
+			},
+		}
+	}
+	let (url, method, json) = match positional.as_slice() {
+		[] => return Err(Error::Usage("missing URL, see `webcat --help`".into())),
+		[url] => (url, None, None),
+		[url, method] => (url, Some(method), None),
+		[url, method, json] => (url, Some(method), Some(json)),
+		[.., extra] => return Err(Error::Usage(format!("unexpected argument `{extra}`"))),
+	};
+	let url = Url::parse(url)?;
+	let input = match (json, data) {
+		(Some(_), Some(_)) => {
+			return Err(Error::Usage(
+				"the message is given twice, as an argument and with `--data`".into(),
+			))
+		}
+		(Some(json), None) => json.as_bytes().to_vec(),
+		(None, Some(data)) => data,
+		(None, None) => Vec::new(),
+	};
+
+	let mut out = std::io::stdout().lock();
+	let Some(path) = method else {
+		let schema = load_schema(&client, &url, web, &headers, &protos, &import_paths, None)?;
+		for service in schema.services.values() {
+			writeln!(out, "{}", service.name)?;
+			for method in &service.methods {
+				writeln!(out, "    {}", method.signature())?;
+			}
+		}
+		return Ok(ExitCode::SUCCESS);
+	};
+	let schema = load_schema(
+		&client,
+		&url,
+		web,
+		&headers,
+		&protos,
+		&import_paths,
+		Some(path),
+	)?;
+	let (service, method) = schema.method(path).map_err(Error::Usage)?;
+
+	let values = serde_json::Deserializer::from_slice(&input)
+		.into_iter::<Value>()
+		.collect::<std::result::Result<Vec<_>, _>>()
+		.map_err(|err| Error::Usage(format!("invalid JSON message: {err}")))?;
+	let values = match values.is_empty() {
+		true => vec![Value::Object(Default::default())],
+		false => values,
+	};
+	if values.len() > 1 && !method.client_streaming {
+		return Err(Error::Usage(format!(
+			"`{}` takes a single message, got {}",
+			method.name,
+			values.len()
+		)));
+	}
+	let messages = values
+		.iter()
+		.map(|value| codec::encode(&schema, &method.input, value))
+		.collect::<std::result::Result<Vec<_>, _>>()
+		.map_err(|err| Error::Usage(format!("invalid `{}` message: {err}", method.input)))?;
+
+	let mut request = grpc::request(
+		&url,
+		&format!("{}/{}", service.name, method.name),
+		&messages,
+		web,
+	);
+	for (name, value) in headers.iter() {
+		request.headers.append(name, value);
+	}
+	let color = details.style.color;
+	let (response, status) = grpc::call(&client, &request, web, &mut |part| {
+		match part {
+			// the timing is only known at the end
+			Part::Head(head) => print_response(
+				&mut out,
+				head,
+				Details {
+					timing: false,
+					..details
+				},
+			)?,
+			Part::Data(bytes) => {
+				let value = codec::decode(&schema, &method.output, bytes).map_err(|err| {
+					Error::Protocol(format!("invalid `{}` message: {err}", method.output))
+				})?;
+				print_message(&mut out, &value, details)?;
+			}
+		}
+		Ok(())
+	})?;
+
+	let mut tail = String::new();
+	if !response.trailers.is_empty() {
+		tail.push('\n');
+	}
+	for (name, value) in response.trailers.iter() {
+		render::paint(&mut tail, name, colors::HEADER, color);
+		tail.push_str(&format!(": {value}\n"));
+	}
+	tail.push('\n');
+	let code = if status.is_ok() { "32" } else { "31" };
+	render::paint(&mut tail, &format!("gRPC status: {status}"), code, color);
+	writeln!(out, "{tail}")?;
+	if details.timing {
+		writeln!(out)?;
+		write!(out, "{}", response.timing.waterfall())?;
+	}
+	out.flush()?;
+	Ok(match status.is_ok() {
+		true => ExitCode::SUCCESS,
+		false => ExitCode::FAILURE,
+	})
+}
+
+/// Types from the `.proto` files given, or else from server reflection.
+/// With reflection only the files of the service called are loaded, or of
+/// all the services for a listing.
+fn load_schema(
+	client: &Client,
+	url: &Url,
+	web: bool,
+	headers: &Headers,
+	protos: &[PathBuf],
+	import_paths: &[PathBuf],
+	method: Option<&String>,
+) -> Result<Schema> {
+	if !protos.is_empty() {
+		return proto::load(protos, import_paths);
+	}
+	let mut reflection = Reflection::new(client, url, web, headers);
+	let mut services = reflection.list_services()?;
+	if let Some(path) = method {
+		let wanted = schema::split_method(path).map_or(path.as_str(), |(service, _)| service);
+		services.retain(|name| name == wanted || name.ends_with(&format!(".{wanted}")));
+		if services.is_empty() {
+			return Err(Error::Usage(format!(
+				"unknown service `{wanted}`, see `webcat grpc {url}`"
+			)));
+		}
+	}
+	reflection.load(&services)
+}
+
+/// Prints a message rendered as JSON, or on a single line when the style
+/// is raw.
+fn print_message<W: Write>(out: &mut W, value: &Value, details: Details) -> Result<()> {
+	let text = value.to_string();
+	if details.style.pretty {
+		let json: Headers = [("Content-Type", "application/json")].into_iter().collect();
+		let rendered = render::render_body(&json, text.as_bytes(), details.style.color);
+		writeln!(out, "{}", rendered.trim_end())?;
+	} else {
+		writeln!(out, "{text}")?;
+	}
+	out.flush()?;
+	Ok(())
+}
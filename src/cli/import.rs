@@ -0,0 +1,121 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use super::args::{unknown, Arg, Args};
+use crate::error::{Error, Result};
+use crate::openapi::{self, Spec};
+use crate::recording::Recorder;
+use crate::{curl, har};
+
+/// `webcat import [OPTIONS] <FILE>`.
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let mut file = None;
+	let mut output = None;
+
+	let mut args = Args::new(list);
+	while let Some(arg) = args.next_arg()? {
+		match arg {
+			Arg::Value(value) if file.is_none() => file = Some(PathBuf::from(value)),
+			Arg::Value(value) => {
+				return Err(Error::Usage(format!("unexpected argument `{value}`")))
+			}
+			Arg::Flag(flag) => match flag.as_str() {
+				"-o" | "--output" => output = Some(PathBuf::from(args.value()?)),
+				_ => return Err(unknown(&flag)),
+			},
+		}
+	}
+	let file = file.ok_or_else(|| Error::Usage("missing file to import".into()))?;
+	let stdin = file.as_os_str() == "-";
+	let dir = match output {
+		Some(dir) => dir,
+		None if stdin => {
+			return Err(Error::Usage(
+				"`--output` is required when importing from standard input".into(),
+			))
+		}
+		None => default_dir(&file),
+	};
+
+	let (source, text) = if stdin {
+		let mut text = String::new();
+		std::io::stdin().read_to_string(&mut text)?;
+		("<stdin>".to_string(), text)
+	} else {
+		let text = std::fs::read_to_string(&file)
+			.map_err(|err| Error::Usage(format!("cannot read `{}`: {err}", file.display())))?;
+		(file.display().to_string(), text)
+	};
+
+	// documents become one request file per operation rather than a session
+	let is_har_file = file
+		.extension()
+		.is_some_and(|ext| ext.eq_ignore_ascii_case("har"));
+	if !is_har_file && Spec::detect(&text) {
+		let spec = Spec::parse(&source, &text)?;
+		let files = openapi::generate(&spec);
+		std::fs::create_dir_all(&dir)?;
+		for generated in &files {
+			std::fs::write(
+				dir.join(format!("{}.http", generated.name)),
+				&generated.text,
+			)?;
+		}
+		println!(
+			"Generated {} request files for {} in {}",
+			files.len(),
+			spec.title(),
+			dir.display()
+		);
+		return Ok(ExitCode::SUCCESS);
+	}
+
+	let recorder = Recorder::create(&dir)?;
+	let mut count = 0;
+	let is_har = is_har_file || text.trim_start().starts_with('{');
+	if is_har {
+		for entry in har::parse(&source, &text)? {
+			let note = match entry.response {
+				Some(_) => format!("recorded {}", entry.started),
+				None => format!("no response, recorded {}", entry.started),
+			};
+			recorder.write(&entry.request, entry.response.as_ref(), &note)?;
+			count += 1;
+		}
+	} else {
+		for command in curl::parse_file(&source, &text)? {
+			for flag in dropped_flags(&command) {
+				eprintln!("webcat: `{flag}` has no request file equivalent and was not imported");
+			}
+			for request in &command.requests {
+				recorder.write(request, None, &format!("imported from {source}"))?;
+				count += 1;
+			}
+		}
+	}
+	println!(
+		"Imported {count} requests into {}",
+		recorder.session_path().display()
+	);
+	Ok(ExitCode::SUCCESS)
+}
+
+/// Options of a curl command that request files cannot express.
+fn dropped_flags(command: &curl::CurlCommand) -> Vec<&'static str> {
+	let flags = [
+		(command.insecure, "--insecure"),
+		(command.compressed, "--compressed"),
+		(command.follow_redirects, "--location"),
+	];
+	flags
+		.into_iter()
+		.filter_map(|(set, flag)| set.then_some(flag))
+		.collect()
+}
+
+/// Directory named after the file, next to it.
+fn default_dir(file: &Path) -> PathBuf {
+	let stem = file.file_stem().unwrap_or(file.as_os_str());
+	file.with_file_name(stem)
+}
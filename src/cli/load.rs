@@ -0,0 +1,117 @@
+use std::path::Path;
+use std::process::ExitCode;
+
+use super::args::{unknown, Arg, Args};
+use super::send::RequestArgs;
+use super::EnvArgs;
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::httpfile::RequestFile;
+use crate::load::{self, LoadConfig};
+use crate::template::Variables;
+use crate::util::parse_duration;
+
+/// `webcat load [OPTIONS] [METHOD] <URL>` or `webcat load [OPTIONS] <FILE>`.
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let mut client = Client::new();
+	let mut request = RequestArgs::default();
+	let mut config = LoadConfig::default();
+	let mut duration = None;
+	let mut only = None;
+	let mut variables = Variables::new();
+	let mut json = false;
+	let mut env = EnvArgs::default();
+
+	let mut args = Args::new(list);
+	while let Some(arg) = args.next_arg()? {
+		match arg {
+			Arg::Value(value) => request.positional.push(value),
+			Arg::Flag(flag) => match flag.as_str() {
+				"-c" | "--concurrency" => config.concurrency = args.parse()?,
+				"-r" | "--rate" => config.rate = Some(args.parse()?),
+				"-z" | "--duration" => {
+					let value = args.value()?;
+					duration = Some(
+						parse_duration(&value)
+							.ok_or_else(|| Error::Usage(format!("invalid duration `{value}`")))?,
+					);
+				}
+				"--requests" => config.requests = Some(args.parse()?),
+				"--name" => only = Some(args.value()?),
+				"--var" => {
+					let pair = args.value()?;
+					let (name, value) = pair.split_once('=').ok_or_else(|| {
+						Error::Usage(format!("expected NAME=VALUE, got `{pair}`"))
+					})?;
+					variables.set(name.trim(), value);
+				}
+				"--json" => json = true,
+				_ if request.option(&flag, &mut args)? => {}
+				_ if env.option(&flag, &mut args)? => {}
+				_ if super::client_option(&mut client, &flag, &mut args)? => {}
+				_ => return Err(unknown(&flag)),
+			},
+		}
+	}
+	if config
+		.rate
+		.is_some_and(|rate| !rate.is_finite() || rate <= 0.0)
+	{
+		return Err(Error::Usage("`--rate` must be positive".into()));
+	}
+	// a request count alone runs until all requests are done
+	if duration.is_some() || config.requests.is_some() {
+		config.duration = duration;
+	}
+
+	let requests = match request.positional.as_slice() {
+		[path] if path.ends_with(".http") || path.ends_with(".rest") => {
+			let file = RequestFile::load(path)?;
+			env.apply(Path::new(path), &mut variables)?;
+			// values captured by earlier requests are only known while running
+			let captured: Vec<&str> = file
+				.captured_names()
+				.filter(|name| !variables.contains(name))
+				.collect();
+			file.resolve_variables_except(&mut variables, &captured)?;
+			let defs = match &only {
+				Some(name) => vec![file.find(name).ok_or_else(|| {
+					Error::Usage(format!("no request named `{name}` in {}", file.source))
+				})?],
+				None => file.requests.iter().collect(),
+			};
+			defs.into_iter()
+				.map(|def| file.build(def, &variables))
+				.collect::<Result<Vec<_>>>()?
+		}
+		_ => vec![request.build()?],
+	};
+	if requests.is_empty() {
+		return Err(Error::Usage("no requests to send".into()));
+	}
+
+	if !json {
+		let target = match &requests[..] {
+			[request] => format!("{} {}", request.method, request.url),
+			all => format!("{} requests", all.len()),
+		};
+		let limit = match (config.duration, config.requests) {
+			(Some(duration), _) => format!("for {:.1} s", duration.as_secs_f64()),
+			(None, count) => format!("{} times", count.unwrap_or_default()),
+		};
+		println!("Sending {target} {limit}\n");
+	}
+	let report = load::run(&client, &requests, &config);
+	client.save_cookies()?;
+	if json {
+		println!("{:#}", report.to_json());
+	} else {
+		print!("{report}");
+	}
+
+	Ok(if report.failures() > 0 {
+		ExitCode::FAILURE
+	} else {
+		ExitCode::SUCCESS
+	})
+}
@@ -0,0 +1,374 @@
+//! Command line interface for the `webcat` binary.
+
+mod args;
+mod cookies;
+mod curl;
+mod export;
+mod grpc;
+mod import;
+mod load;
+mod record;
+mod run;
+mod send;
+mod serve;
+mod sse;
+mod tui;
+mod ws;
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+use crate::auth::Auth;
+use crate::client::{Client, HttpVersion};
+use crate::cookie::CookieJar;
+use crate::environment::{self, Environment};
+use crate::error::Error;
+use crate::redirect::RedirectPolicy;
+use crate::template::Variables;
+use crate::tls::{self, ClientCert};
+
+pub use args::{Arg, Args};
+pub(crate) use run::print_assertions;
+
+const USAGE: &str = "\
+webcat - lightning fast tool to help developers test Web/HTTP requests
+
+USAGE:
+    webcat [OPTIONS] [METHOD] <URL>
+    webcat run [OPTIONS] <FILE>...
+    webcat load [OPTIONS] [METHOD] <URL>
+    webcat load [OPTIONS] <FILE>
+    webcat serve [OPTIONS] <FILE>
+    webcat record [OPTIONS] <DIR>
+    webcat import [OPTIONS] <FILE>
+    webcat export [OPTIONS] <FILE>
+    webcat curl <CURL ARGUMENTS>...
+    webcat cookies [OPTIONS] <FILE>
+    webcat sse [OPTIONS] [METHOD] <URL>
+    webcat ws [OPTIONS] <URL>
+    webcat grpc [OPTIONS] <URL> [SERVICE/METHOD] [JSON]
+    webcat tui [OPTIONS] [PATH]...
+
+COMMANDS:
+    run                         Execute the requests in `.http` request files
+    load                        Load test a request or the requests of a file
+    serve                       Start a mock server answering from a route file
+    record                      Run a proxy recording the traffic as request files
+    import                      Convert a HAR archive, curl commands or an OpenAPI spec to request files
+    export                      Print the requests of a file as curl, wget or PowerShell commands
+    curl                        Send a request given as curl arguments
+    cookies                     List the cookies of a cookies.txt jar file
+    sse                         Print the events of a Server-Sent Events stream as they arrive
+    ws                          Open an interactive WebSocket session
+    grpc                        Call a gRPC method, or list the services without one
+    tui                         Browse, edit and send the requests of request files in a terminal UI
+
+OPTIONS:
+    -H, --header <NAME:VALUE>   Add a request header (repeatable)
+    -d, --data <BODY>           Request body, `@path` streams it from a file
+    -F, --form <NAME=VALUE>     Add a multipart/form-data field, `NAME=@path` uploads a file (repeatable)
+    -f, --field <NAME=VALUE>    Add an application/x-www-form-urlencoded field (repeatable)
+    -e, --expect <ASSERTION>    Check the response, e.g. `status == 200` (repeatable)
+        --timeout <SECS>        Connect and read timeout, 0 disables it
+        --pretty                Render bodies by content type, the default on a terminal
+        --raw                   Print bodies as received, without colors
+        --color, --no-color     Color the output, by default on a terminal unless NO_COLOR is set
+        --tls-info              Print the TLS version, cipher and certificate chain
+        --http1.1               Only use HTTP/1.1
+        --http2                 Require HTTP/2, with prior knowledge for http:// URLs
+        --h2-info               Print the HTTP/2 stream, SETTINGS and header sizes
+        --timing                Print the DNS, connect, TLS, TTFB and transfer times
+        --timing-json <PATH>    Write the timing breakdown as JSON, `-` for standard output
+        --har <PATH>            Write the exchanges as a HAR archive, `-` for standard output
+        --export <TOOL>         Print the request as a curl, wget or powershell command instead
+    -a, --auth <SCHEME CREDS>   Authenticate requests without an Authorization header, e.g. `Digest jo secret`
+        --cookies <PATH>        Send and store cookies in a Netscape cookies.txt jar file
+        --no-cookies            Do not keep the cookies set by responses during a run
+        --no-progress           Do not show the progress of file uploads on a terminal
+        --no-decode             Do not ask for compressed responses or decode them
+    -L, --follow                Follow redirects, printing each hop with its timing
+        --max-redirects <N>     Redirects followed before giving up, implies --follow [default: 10]
+        --same-origin           Refuse redirects to another origin, implies --follow
+        --no-downgrade          Refuse redirects from https to http, implies --follow
+        --wire                  Print the response exactly as received over HTTP/1.1
+        --graphql <QUERY>       Send a GraphQL query, `@path` reads it from a file
+        --variables <JSON>      Variables of the GraphQL query, `@path` reads them from a file
+        --operation <NAME>      Operation to run when the GraphQL query has several
+        --no-validate           Send GraphQL queries without checking them against the schema
+    -h, --help                  Print this help
+    -V, --version               Print the version
+
+TLS OPTIONS:
+        --cacert <PATH>         Trust the certificates in a PEM bundle (repeatable)
+        --cert <PATH>           Client certificate, PEM or PKCS#12 (.p12/.pfx)
+        --key <PATH>            Private key for a PEM client certificate
+        --cert-password <PASS>  Password of a PKCS#12 client certificate
+        --pin <SHA256>          Require the server certificate fingerprint (repeatable)
+    -k, --insecure              Skip server certificate verification
+
+RUN OPTIONS:
+    -n, --name <NAME>           Only run the request with the given name
+        --var <NAME=VALUE>      Set a variable, overriding the file (repeatable)
+        --env <NAME>            Use the variables of an environment, e.g. `dev`
+        --env-file <PATH>       Environment file [default: webcat.env.json or http-client.env.json]
+        --junit <PATH>          Write a JUnit XML report, `-` for standard output
+        --tap <PATH>            Write a TAP report, `-` for standard output
+        --openapi <SPEC>        Check responses against an OpenAPI 3 document (JSON or YAML)
+
+LOAD OPTIONS:
+    -c, --concurrency <N>       Concurrent workers, or maximum in flight with --rate [default: 10]
+    -r, --rate <RPS>            Open-loop mode sending a fixed number of requests per second
+    -z, --duration <TIME>       Test duration, e.g. `30s` or `2m` [default: 10s]
+        --requests <N>          Stop after N requests
+        --name <NAME>           Only send the request with the given name of a file
+        --var <NAME=VALUE>      Set a variable of the request file (repeatable)
+        --env <NAME>            Use the variables of an environment, see RUN OPTIONS
+        --json                  Print the results as JSON
+
+SERVE OPTIONS:
+    -p, --port <PORT>           Port to listen on, 0 picks a free one [default: 8080]
+        --host <ADDR>           Address to listen on [default: 127.0.0.1]
+        --var <NAME=VALUE>      Set a variable of the route file (repeatable)
+
+RECORD OPTIONS:
+    -p, --port <PORT>           Port to listen on, 0 picks a free one [default: 8888]
+        --host <ADDR>           Address to listen on [default: 127.0.0.1]
+        --mitm                  Intercept HTTPS with certificates from a local CA
+        --ca-dir <DIR>          Directory of the CA files [default: the recording directory]
+
+IMPORT OPTIONS:
+    -o, --output <DIR>          Directory to write to [default: the file name without extension]
+
+EXPORT OPTIONS:
+    -t, --to <TOOL>             curl, wget or powershell [default: curl]
+    -n, --name <NAME>           Only export the request with the given name
+        --var <NAME=VALUE>      Set a variable, overriding the file (repeatable)
+        --env <NAME>            Use the variables of an environment, see RUN OPTIONS
+
+COOKIES OPTIONS:
+    -u, --url <URL>             Only list the cookies that would be sent to the URL
+
+SSE OPTIONS:
+        --last-event-id <ID>    Resume the stream after the event with the given ID
+        --reconnect             Reconnect when the stream ends, sending the last event ID
+
+WS OPTIONS:
+    -H, --header <NAME:VALUE>   Add a handshake header, e.g. `Sec-WebSocket-Protocol: chat` (repeatable)
+        --no-compress           Do not offer permessage-deflate compression
+    Input lines are sent as text messages, `/binary <HEX>`, `/ping [TEXT]` and
+    `/close [CODE [REASON]]` send other messages and `//` starts a text with `/`.
+
+GRPC OPTIONS:
+        --proto <FILE>          Types of the service from a .proto file (repeatable), server reflection without one
+    -I, --import-path <DIR>     Directory searched for imports (repeatable) [default: the directories of the files]
+    -d, --data <JSON>           Request message, `@path` reads it from a file, several make a client stream
+    -H, --header <NAME:VALUE>   Add request metadata (repeatable)
+        --web                   Call through gRPC-Web, over HTTP/1.1 unless the server selects HTTP/2
+
+TUI OPTIONS:
+        --var <NAME=VALUE>      Set a variable, overriding the files (repeatable)
+        --env <NAME>            Use the variables of an environment, see RUN OPTIONS
+    The paths are directories searched for .http and .rest files, or request files
+    [default: the current directory]. Tab moves between the panes, Ctrl-R sends the
+    request being edited, Ctrl-S saves it and Ctrl-Q quits.
+";
+
+/// Runs the command line and returns the process exit code.
+pub fn main(args: &[String]) -> ExitCode {
+	match run(args) {
+		Ok(code) => code,
+		Err(Error::Usage(msg)) => {
+			eprintln!("webcat: {msg}");
+			ExitCode::from(2)
+		}
+		Err(err) => {
+			eprintln!("webcat: {err}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+fn run(args: &[String]) -> crate::Result<ExitCode> {
+	match args.first().map(|s| s.as_str()) {
+		None | Some("-h" | "--help" | "help") => {
+			print!("{USAGE}");
+			Ok(ExitCode::SUCCESS)
+		}
+		Some("-V" | "--version") => {
+			println!("webcat {}", env!("CARGO_PKG_VERSION"));
+			Ok(ExitCode::SUCCESS)
+		}
+		Some("run") => run::run(&args[1..]),
+		Some("load") => load::run(&args[1..]),
+		Some("serve") => serve::run(&args[1..]),
+		Some("record") => record::run(&args[1..]),
+		Some("import") => import::run(&args[1..]),
+		Some("export") => export::run(&args[1..]),
+		Some("curl") => curl::run(&args[1..]),
+		Some("cookies") => cookies::run(&args[1..]),
+		Some("sse") => sse::run(&args[1..]),
+		Some("ws") => ws::run(&args[1..]),
+		Some("grpc") => grpc::run(&args[1..]),
+		Some("tui") => tui::run(&args[1..]),
+		_ => send::run(args),
+	}
+}
+
+/// Reads a body argument, where `@path` loads the body from a file and
+/// `@-` from standard input.
+pub(crate) fn read_body_arg(value: &str) -> crate::Result<Vec<u8>> {
+	match value.strip_prefix('@') {
+		Some("-") => {
+			let mut body = Vec::new();
+			std::io::Read::read_to_end(&mut std::io::stdin(), &mut body)?;
+			Ok(body)
+		}
+		Some(path) => {
+			std::fs::read(path).map_err(|err| Error::Usage(format!("cannot read `{path}`: {err}")))
+		}
+		None => Ok(value.as_bytes().to_vec()),
+	}
+}
+
+/// Environment selected with `--env`, read from `--env-file` or the
+/// environment file found next to the request file.
+#[derive(Debug, Default)]
+pub(crate) struct EnvArgs {
+	name: Option<String>,
+	file: Option<PathBuf>,
+}
+
+impl EnvArgs {
+	/// Handles the environment options. Returns false for other flags.
+	pub fn option(&mut self, flag: &str, args: &mut Args) -> crate::Result<bool> {
+		match flag {
+			"--env" => self.name = Some(args.value()?),
+			"--env-file" => self.file = Some(args.value()?.into()),
+			_ => return Ok(false),
+		}
+		Ok(true)
+	}
+
+	/// Sets the variables of the environment missing from `vars`, looking
+	/// for the environment file from the directory of `request_file`.
+	pub fn apply(&self, request_file: &Path, vars: &mut Variables) -> crate::Result<()> {
+		let Some(name) = &self.name else {
+			return match self.file {
+				Some(_) => Err(Error::Usage("`--env-file` requires `--env`".into())),
+				None => Ok(()),
+			};
+		};
+		let path = match &self.file {
+			Some(path) => path.clone(),
+			None => {
+				let dir = std::env::current_dir()?.join(request_file);
+				let dir = dir.parent().unwrap_or(&dir);
+				Environment::find_file(dir).ok_or_else(|| {
+					Error::Usage(format!(
+						"no {} found for `--env {name}`",
+						environment::FILE_NAMES.join(" or ")
+					))
+				})?
+			}
+		};
+		Environment::load(&path, name)?.apply(vars)
+	}
+}
+
+/// Handles options shared by all commands that send requests. Returns false
+/// if the flag is not a client option.
+pub(crate) fn client_option(
+	client: &mut Client,
+	flag: &str,
+	args: &mut Args,
+) -> crate::Result<bool> {
+	let tls = &mut client.tls;
+	match flag {
+		"--timeout" => {
+			let secs: f64 = args.parse()?;
+			client.timeout = (secs > 0.0).then(|| std::time::Duration::from_secs_f64(secs));
+		}
+		"--http1.1" => client.version = HttpVersion::Http1,
+		"--http2" => client.version = HttpVersion::Http2,
+		"--cacert" => tls.ca_files.push(args.value()?.into()),
+		"--cert" => {
+			let path = PathBuf::from(args.value()?);
+			let pkcs12 = path.extension().is_some_and(|ext| {
+				ext.eq_ignore_ascii_case("p12") || ext.eq_ignore_ascii_case("pfx")
+			});
+			tls.client_cert = Some(match tls.client_cert.take() {
+				_ if pkcs12 => ClientCert::Pkcs12 {
+					path,
+					password: String::new(),
+				},
+				// `--key` given first
+				Some(ClientCert::Pem { key, .. }) => ClientCert::Pem { cert: path, key },
+				_ => ClientCert::Pem {
+					cert: path.clone(),
+					key: path,
+				},
+			});
+		}
+		"--key" => {
+			let key = PathBuf::from(args.value()?);
+			tls.client_cert = Some(match tls.client_cert.take() {
+				Some(ClientCert::Pem { cert, .. }) => ClientCert::Pem { cert, key },
+				_ => ClientCert::Pem {
+					cert: PathBuf::new(),
+					key,
+				},
+			});
+		}
+		"--cert-password" => {
+			let value = args.value()?;
+			match &mut tls.client_cert {
+				Some(ClientCert::Pkcs12 { password, .. }) => *password = value,
+				_ => {
+					return Err(Error::Usage(
+						"`--cert-password` requires a PKCS#12 `--cert` first".into(),
+					))
+				}
+			}
+		}
+		"--pin" => tls.pins.push(tls::parse_pin(&args.value()?)?),
+		"-k" | "--insecure" => tls.insecure = true,
+		"--cookies" => {
+			let jar = CookieJar::open(Path::new(&args.value()?))?;
+			client.cookies = Some(Arc::new(Mutex::new(jar)));
+		}
+		"--no-cookies" => client.cookies = None,
+		"--no-progress" => client.progress = false,
+		"--no-decode" => client.decode = false,
+		"-L" | "--follow" => {
+			client.redirects.get_or_insert_with(RedirectPolicy::default);
+		}
+		"--max-redirects" => {
+			let max_hops = args.parse()?;
+			client
+				.redirects
+				.get_or_insert_with(RedirectPolicy::default)
+				.max_hops = max_hops;
+		}
+		"--same-origin" => {
+			client
+				.redirects
+				.get_or_insert_with(RedirectPolicy::default)
+				.same_origin = true;
+		}
+		"--no-downgrade" => {
+			client
+				.redirects
+				.get_or_insert_with(RedirectPolicy::default)
+				.no_downgrade = true;
+		}
+		"-a" | "--auth" => {
+			let value = args.value()?;
+			let auth = Auth::parse(&value)
+				.map_err(|message| Error::Usage(format!("invalid `--auth`: {message}")))?;
+			client.auth = Some(auth);
+		}
+		_ => return Ok(false),
+	}
+	Ok(true)
+}
@@ -0,0 +1,61 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use super::args::{unknown, Arg, Args};
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::proxy::{Ca, Proxy};
+use crate::recording::Recorder;
+
+/// `webcat record [OPTIONS] <DIR>`.
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let mut client = Client::new();
+	let mut dir = None;
+	let mut host = "127.0.0.1".to_string();
+	let mut port: u16 = 8888;
+	let mut mitm = false;
+	let mut ca_dir = None;
+
+	let mut args = Args::new(list);
+	while let Some(arg) = args.next_arg()? {
+		match arg {
+			Arg::Value(value) if dir.is_none() => dir = Some(PathBuf::from(value)),
+			Arg::Value(value) => {
+				return Err(Error::Usage(format!("unexpected argument `{value}`")))
+			}
+			Arg::Flag(flag) => match flag.as_str() {
+				"-p" | "--port" => port = args.parse()?,
+				"--host" => host = args.value()?,
+				"--mitm" => mitm = true,
+				"--ca-dir" => ca_dir = Some(PathBuf::from(args.value()?)),
+				_ if super::client_option(&mut client, &flag, &mut args)? => {}
+				_ => return Err(unknown(&flag)),
+			},
+		}
+	}
+	let dir = dir.ok_or_else(|| Error::Usage("missing recording directory".into()))?;
+	if ca_dir.is_some() && !mitm {
+		return Err(Error::Usage("`--ca-dir` requires `--mitm`".into()));
+	}
+
+	let recorder = Recorder::create(&dir)?;
+	let ca = match mitm {
+		true => {
+			let ca_dir = ca_dir.unwrap_or_else(|| dir.clone());
+			let ca = Ca::load_or_create(&ca_dir)?;
+			let (cert, _) = Ca::paths(&ca_dir);
+			println!("Intercepting HTTPS, clients must trust {}", cert.display());
+			Some(ca)
+		}
+		false => None,
+	};
+	let session = recorder.session_path();
+	let proxy = Proxy::bind((host.as_str(), port), client, recorder, ca)?;
+	println!(
+		"Recording to {} through proxy http://{}",
+		session.display(),
+		proxy.local_addr()?
+	);
+	proxy.run(|entry| println!("{entry}"))?;
+	Ok(ExitCode::SUCCESS)
+}
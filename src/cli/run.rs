@@ -0,0 +1,158 @@
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+use super::args::{unknown, Arg, Args};
+use super::send::{print_response, Details};
+use super::EnvArgs;
+use crate::assert::AssertionResult;
+use crate::client::Client;
+use crate::cookie::CookieJar;
+use crate::error::{Error, Result};
+use crate::httpfile::RequestFile;
+use crate::openapi::Spec;
+use crate::report::{self, Format, Suite};
+use crate::runner::{Outcome, Runner};
+
+/// `webcat run [OPTIONS] <FILE>...`
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let mut runner = Runner::new(Client::new());
+	// requests of a run share cookies unless `--no-cookies` is given
+	runner.client.cookies = Some(Arc::new(Mutex::new(CookieJar::new())));
+	runner.client.progress = std::io::stderr().is_terminal();
+	let mut files = Vec::new();
+	let mut only = None;
+	let mut reports = Vec::new();
+	let mut details = Details::for_output(std::io::stdout().is_terminal());
+	let mut env = EnvArgs::default();
+
+	let mut args = Args::new(list);
+	while let Some(arg) = args.next_arg()? {
+		match arg {
+			Arg::Value(path) => files.push(path),
+			Arg::Flag(flag) => match flag.as_str() {
+				"-n" | "--name" => only = Some(args.value()?),
+				"--junit" => reports.push((Format::Junit, args.value()?)),
+				"--tap" => reports.push((Format::Tap, args.value()?)),
+				"--timing-json" => reports.push((Format::Timing, args.value()?)),
+				"--har" => reports.push((Format::Har, args.value()?)),
+				"--openapi" => runner.spec = Some(Spec::load(args.value()?)?),
+				"--no-validate" => runner.schemas = None,
+				"--var" => {
+					let pair = args.value()?;
+					let (name, value) = pair.split_once('=').ok_or_else(|| {
+						Error::Usage(format!("expected NAME=VALUE, got `{pair}`"))
+					})?;
+					runner.variables.set(name.trim(), value);
+				}
+				_ if details.option(&flag) => {}
+				_ if env.option(&flag, &mut args)? => {}
+				_ if super::client_option(&mut runner.client, &flag, &mut args)? => {}
+				_ => return Err(unknown(&flag)),
+			},
+		}
+	}
+	if files.is_empty() {
+		return Err(Error::Usage(
+			"missing request file, see `webcat --help`".into(),
+		));
+	}
+	env.apply(Path::new(&files[0]), &mut runner.variables)?;
+
+	let files = files
+		.iter()
+		.map(RequestFile::load)
+		.collect::<Result<Vec<_>>>()?;
+	// a report written to standard output replaces the regular output
+	let quiet = reports.iter().any(|(_, path)| path == "-");
+	let mut results = Vec::new();
+	for file in &files {
+		let outcomes = runner.run_file(file, only.as_deref(), |outcome| {
+			if !quiet {
+				let _ = print_outcome(&mut std::io::stdout().lock(), outcome, details);
+			}
+		})?;
+		results.push(outcomes);
+	}
+	runner.client.save_cookies()?;
+
+	let total: usize = results.iter().map(|outcomes| outcomes.len()).sum();
+	let failed = results.iter().flatten().filter(|o| !o.is_success()).count();
+	if !quiet {
+		println!(
+			"{total} requests, {} passed, {failed} failed",
+			total - failed
+		);
+	}
+
+	let suites: Vec<Suite> = files
+		.iter()
+		.zip(&results)
+		.map(|(file, outcomes)| Suite {
+			name: &file.source,
+			outcomes,
+		})
+		.collect();
+	for (format, path) in reports {
+		write_report_file(format, &path, &suites)?;
+	}
+
+	Ok(if failed > 0 {
+		ExitCode::FAILURE
+	} else {
+		ExitCode::SUCCESS
+	})
+}
+
+/// Writes a report to a file, or to standard output for `-`.
+pub fn write_report_file(format: Format, path: &str, suites: &[Suite]) -> Result<()> {
+	if path == "-" {
+		report::write_report(&mut std::io::stdout().lock(), format, suites)?;
+	} else {
+		let mut output = std::io::BufWriter::new(std::fs::File::create(path)?);
+		report::write_report(&mut output, format, suites)?;
+		output.flush()?;
+	}
+	Ok(())
+}
+
+fn print_outcome<W: Write>(out: &mut W, outcome: &Outcome, details: Details) -> Result<()> {
+	writeln!(out, "### {}", outcome.name)?;
+	writeln!(
+		out,
+		"{} {} ({} ms)",
+		outcome.request.method,
+		outcome.request.url,
+		outcome.elapsed.as_millis()
+	)?;
+	match &outcome.response {
+		Ok(response) => print_response(out, response, details)?,
+		Err(err) => writeln!(out, "error: {err}")?,
+	}
+	if !outcome.assertions.is_empty() {
+		writeln!(out)?;
+		print_assertions(out, &outcome.assertions)?;
+	}
+	writeln!(out)?;
+	Ok(())
+}
+
+/// Prints one line per assertion, followed by the failure details.
+pub fn print_assertions<W: Write>(out: &mut W, results: &[AssertionResult]) -> Result<()> {
+	for result in results {
+		let status = if result.passed { "PASS" } else { "FAIL" };
+		if result.line > 0 && !result.passed {
+			writeln!(out, "{status}  {} (line {})", result.assertion, result.line)?;
+		} else {
+			writeln!(out, "{status}  {}", result.assertion)?;
+		}
+		if !result.passed {
+			writeln!(out, "      {}", result.message)?;
+			for line in result.diff.iter().flat_map(|diff| diff.lines()) {
+				writeln!(out, "      {line}")?;
+			}
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,349 @@
+use std::io::{IsTerminal, Write};
+use std::process::ExitCode;
+
+use super::args::{unknown, Arg, Args};
+use crate::assert::Assertion;
+use crate::client::{Client, HttpVersion};
+use crate::error::{Error, ParseError, Result};
+use crate::export::{self, Tool};
+use crate::graphql::Query;
+use crate::http::{parse_header_line, Request, Response};
+use crate::multipart::{Multipart, Part};
+use crate::render::{self, colors, Style};
+use crate::report::{Format, Suite};
+use crate::runner::Runner;
+use crate::template::Variables;
+use crate::upload::Upload;
+use crate::url::{form_encode, Url};
+
+/// One-shot request: `webcat [OPTIONS] [METHOD] <URL>`.
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let mut client = Client::new();
+	client.progress = std::io::stderr().is_terminal();
+	let mut request = RequestArgs::default();
+	let mut assertions = Vec::new();
+	let mut details = Details::for_output(std::io::stdout().is_terminal());
+	let mut reports = Vec::new();
+	let mut export = None;
+	let mut wire = false;
+	let mut validate = true;
+
+	let mut args = Args::new(list);
+	while let Some(arg) = args.next_arg()? {
+		match arg {
+			Arg::Value(value) => request.positional.push(value),
+			Arg::Flag(flag) => match flag.as_str() {
+				"--timing-json" => reports.push((Format::Timing, args.value()?)),
+				"--har" => reports.push((Format::Har, args.value()?)),
+				"--export" => export = Some(args.parse::<Tool>()?),
+				"--wire" => wire = true,
+				"--no-validate" => validate = false,
+				"-e" | "--expect" => {
+					let text = args.value()?;
+					let assertion = Assertion::parse(&text, 0, 1).map_err(|err| {
+						Error::Usage(format!("invalid assertion `{text}`: {}", err.message))
+					})?;
+					assertions.push(assertion);
+				}
+				_ if details.option(&flag) => {}
+				_ if request.option(&flag, &mut args)? => {}
+				_ if super::client_option(&mut client, &flag, &mut args)? => {}
+				_ => return Err(unknown(&flag)),
+			},
+		}
+	}
+
+	let graphql = request.graphql_source();
+	let mut request = request.build()?;
+	if let Some(tool) = export {
+		request.load_upload()?;
+		println!("{}", export::command(&request, tool, &client)?);
+		return Ok(ExitCode::SUCCESS);
+	}
+	if wire {
+		if !assertions.is_empty() || !reports.is_empty() {
+			return Err(Error::Usage(
+				"`--wire` cannot be combined with assertions or reports".into(),
+			));
+		}
+		if client.version == HttpVersion::Http2 {
+			return Err(Error::Usage("`--wire` only supports HTTP/1.1".into()));
+		}
+		let response = client.send_wire(&request)?;
+		let mut out = std::io::stdout().lock();
+		out.write_all(&response.bytes)?;
+		out.flush()?;
+		// the bytes received are still worth seeing when they are invalid
+		return match response.error {
+			Some(err) => Err(err),
+			None => Ok(ExitCode::SUCCESS),
+		};
+	}
+	let mut runner = Runner::new(client);
+	if !validate {
+		runner.schemas = None;
+	}
+	let vars = Variables::new();
+	let outcome = match graphql {
+		Some(source) => {
+			let locate = |err: ParseError| err.in_source(&source).into();
+			runner.execute_graphql(String::new(), request, &assertions, &vars, 0, locate)
+		}
+		None => runner.execute(String::new(), request, &assertions, &vars),
+	};
+	runner.client.save_cookies()?;
+	let suites = [Suite {
+		name: "",
+		outcomes: std::slice::from_ref(&outcome),
+	}];
+	for (format, path) in reports {
+		super::run::write_report_file(format, &path, &suites)?;
+	}
+	let response = outcome.response?;
+	let mut out = std::io::stdout().lock();
+	print_response(&mut out, &response, details)?;
+	if !outcome.assertions.is_empty() {
+		writeln!(out)?;
+		super::run::print_assertions(&mut out, &outcome.assertions)?;
+	}
+
+	let passed = outcome.assertions.iter().all(|result| result.passed);
+	Ok(if passed {
+		ExitCode::SUCCESS
+	} else {
+		ExitCode::FAILURE
+	})
+}
+
+/// Request given on the command line as `[METHOD] <URL>` with `-H` and
+/// body options.
+#[derive(Debug, Default)]
+pub struct RequestArgs {
+	pub positional: Vec<String>,
+	headers: Vec<(String, String)>,
+	body: Option<Upload>,
+	/// `-F` parts of a multipart form.
+	form: Vec<Part>,
+	/// `-f` fields of a URL-encoded form.
+	fields: Vec<(String, String)>,
+	/// `--graphql` query, with the name of its source for errors.
+	graphql: Option<(String, String)>,
+	/// `--variables` of the query.
+	variables: Option<serde_json::Value>,
+	/// `--operation` of the query to run.
+	operation: Option<String>,
+}
+
+impl RequestArgs {
+	/// Handles the request options. Returns false for other flags.
+	pub fn option(&mut self, flag: &str, args: &mut Args) -> Result<bool> {
+		match flag {
+			"-H" | "--header" => {
+				let line = args.value()?;
+				let header = parse_header_line(&line)
+					.ok_or_else(|| Error::Usage(format!("invalid header `{line}`")))?;
+				self.headers.push(header);
+			}
+			"-d" | "--data" => {
+				let value = args.value()?;
+				let mut body = Upload::new();
+				match value.strip_prefix('@').filter(|path| *path != "-") {
+					// streamed, however large the file
+					Some(path) => body
+						.push_file(path)
+						.map_err(|err| Error::Usage(format!("cannot read `{path}`: {err}")))?,
+					None => body.push_bytes(&super::read_body_arg(&value)?),
+				}
+				self.body = Some(body);
+			}
+			"-F" | "--form" => self.form.push(Part::parse(&args.value()?)?),
+			"-f" | "--field" => {
+				let spec = args.value()?;
+				let (name, value) = spec.split_once('=').ok_or_else(|| {
+					Error::Usage(format!("invalid form field `{spec}`, expected NAME=VALUE"))
+				})?;
+				let value = super::read_body_arg(value)?;
+				let value = String::from_utf8(value)
+					.map_err(|_| Error::Usage(format!("form field `{name}` is not valid UTF-8")))?;
+				self.fields.push((name.to_string(), value));
+			}
+			"--graphql" => {
+				let value = args.value()?;
+				let source = match value.strip_prefix('@') {
+					Some(path) if path != "-" => path.to_string(),
+					_ => "query".to_string(),
+				};
+				let query = String::from_utf8_lossy(&super::read_body_arg(&value)?).into_owned();
+				self.graphql = Some((source, query));
+			}
+			"--variables" => {
+				let value = super::read_body_arg(&args.value()?)?;
+				let variables: serde_json::Value = serde_json::from_slice(&value)
+					.map_err(|err| Error::Usage(format!("invalid GraphQL variables: {err}")))?;
+				if !variables.is_object() {
+					return Err(Error::Usage(
+						"GraphQL variables must be a JSON object".into(),
+					));
+				}
+				self.variables = Some(variables);
+			}
+			"--operation" => self.operation = Some(args.value()?),
+			_ => return Ok(false),
+		}
+		Ok(true)
+	}
+
+	/// Name of the source of the `--graphql` query, used to locate its
+	/// problems.
+	pub fn graphql_source(&self) -> Option<String> {
+		self.graphql.as_ref().map(|(source, _)| source.clone())
+	}
+
+	pub fn build(self) -> Result<Request> {
+		let bodies = [
+			self.body.is_some(),
+			!self.form.is_empty(),
+			!self.fields.is_empty(),
+			self.graphql.is_some(),
+		];
+		let has_body = match bodies.iter().filter(|given| **given).count() {
+			0 => false,
+			1 => true,
+			_ => {
+				return Err(Error::Usage(
+					"`--data`, `--form`, `--field` and `--graphql` cannot be combined".into(),
+				))
+			}
+		};
+		if self.graphql.is_none() && (self.variables.is_some() || self.operation.is_some()) {
+			return Err(Error::Usage(
+				"`--variables` and `--operation` require `--graphql`".into(),
+			));
+		}
+		let positional = &self.positional;
+		let (method, url) = match positional.as_slice() {
+			[url] => (if has_body { "POST" } else { "GET" }, url),
+			[method, url] => (method.as_str(), url),
+			[] => return Err(Error::Usage("missing URL, see `webcat --help`".into())),
+			_ => {
+				return Err(Error::Usage(format!(
+					"unexpected argument `{}`",
+					positional[2]
+				)))
+			}
+		};
+		let mut request = Request::new(method, Url::parse(url)?);
+		for (name, value) in self.headers {
+			request.headers.append(name, value);
+		}
+		if !self.form.is_empty() {
+			let form = Multipart {
+				parts: self.form,
+				..Multipart::new()
+			};
+			form.apply(&mut request)?;
+		} else if !self.fields.is_empty() {
+			if !request.headers.contains("Content-Type") {
+				request
+					.headers
+					.set("Content-Type", "application/x-www-form-urlencoded");
+			}
+			request.body = form_encode(&self.fields).into_bytes();
+		} else if let Some((_, query)) = self.graphql {
+			let query = Query {
+				query,
+				variables: self.variables,
+				operation_name: self.operation,
+			};
+			query.apply(&mut request);
+		} else if let Some(body) = self.body {
+			request.set_upload(body);
+		}
+		Ok(request)
+	}
+}
+
+/// Optional details printed along with a response, and how it is styled.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Details {
+	pub tls: bool,
+	pub h2: bool,
+	pub timing: bool,
+	pub style: Style,
+}
+
+impl Details {
+	/// Details for output to a terminal or elsewhere: bodies are rendered
+	/// and colored on a terminal unless `NO_COLOR` is set, and written raw
+	/// otherwise.
+	pub fn for_output(terminal: bool) -> Details {
+		let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+		Details {
+			style: Style {
+				pretty: terminal,
+				color: terminal && !no_color,
+			},
+			..Details::default()
+		}
+	}
+
+	/// Handles the flags enabling details and choosing the style. Returns
+	/// false for other flags.
+	pub fn option(&mut self, flag: &str) -> bool {
+		match flag {
+			"--tls-info" => self.tls = true,
+			"--h2-info" => self.h2 = true,
+			"--timing" => self.timing = true,
+			"--pretty" => self.style.pretty = true,
+			"--raw" => self.style = Style::default(),
+			"--color" => self.style.color = true,
+			"--no-color" => self.style.color = false,
+			_ => return false,
+		}
+		true
+	}
+}
+
+/// Prints the selected connection details, the status line, headers and
+/// body, followed by the timing waterfall when requested. The body is
+/// written raw unless the style asks for it to be rendered.
+pub fn print_response<W: Write>(out: &mut W, response: &Response, details: Details) -> Result<()> {
+	if let Some(tls) = response.tls.as_ref().filter(|_| details.tls) {
+		write!(out, "{tls}")?;
+	}
+	if let Some(h2) = response.h2.as_ref().filter(|_| details.h2) {
+		write!(out, "{h2}")?;
+	}
+	let color = details.style.color;
+	let mut head = String::new();
+	for hop in &response.redirects {
+		head.push_str(&format!("{hop}\n"));
+	}
+	let status = response.status_line();
+	render::paint(
+		&mut head,
+		&status,
+		render::status_color(response.status),
+		color,
+	);
+	head.push('\n');
+	for (name, value) in response.headers.iter() {
+		render::paint(&mut head, name, colors::HEADER, color);
+		head.push_str(&format!(": {value}\n"));
+	}
+	writeln!(out, "{head}")?;
+	if details.style.pretty {
+		out.write_all(render::render_body(&response.headers, &response.body, color).as_bytes())?;
+	} else {
+		out.write_all(&response.body)?;
+		if !response.body.is_empty() && !response.body.ends_with(b"\n") {
+			writeln!(out)?;
+		}
+	}
+	if details.timing {
+		writeln!(out)?;
+		write!(out, "{}", response.timing.waterfall())?;
+	}
+	out.flush()?;
+	Ok(())
+}
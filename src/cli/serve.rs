@@ -0,0 +1,45 @@
+use std::process::ExitCode;
+
+use super::args::{unknown, Arg, Args};
+use crate::error::{Error, Result};
+use crate::mock::{MockServer, RouteFile};
+use crate::template::Variables;
+
+/// `webcat serve [OPTIONS] <FILE>`.
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let mut file = None;
+	let mut host = "127.0.0.1".to_string();
+	let mut port: u16 = 8080;
+	let mut variables = Variables::new();
+
+	let mut args = Args::new(list);
+	while let Some(arg) = args.next_arg()? {
+		match arg {
+			Arg::Value(value) if file.is_none() => file = Some(value),
+			Arg::Value(value) => {
+				return Err(Error::Usage(format!("unexpected argument `{value}`")))
+			}
+			Arg::Flag(flag) => match flag.as_str() {
+				"-p" | "--port" => port = args.parse()?,
+				"--host" => host = args.value()?,
+				"--var" => {
+					let pair = args.value()?;
+					let (name, value) = pair.split_once('=').ok_or_else(|| {
+						Error::Usage(format!("expected NAME=VALUE, got `{pair}`"))
+					})?;
+					variables.set(name.trim(), value);
+				}
+				_ => return Err(unknown(&flag)),
+			},
+		}
+	}
+	let file = file.ok_or_else(|| Error::Usage("missing route file".into()))?;
+
+	let routes = RouteFile::load(&file, variables)?;
+	let count = routes.routes.len();
+	let server = MockServer::bind((host.as_str(), port), routes)?;
+	let addr = server.local_addr()?;
+	println!("Serving {count} routes from {file} on http://{addr}");
+	server.run(|exchange| println!("{exchange}"))?;
+	Ok(ExitCode::SUCCESS)
+}
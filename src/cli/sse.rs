@@ -0,0 +1,53 @@
+use std::io::{IsTerminal, Write};
+use std::process::ExitCode;
+
+use super::args::{unknown, Arg, Args};
+use super::send::{print_response, Details, RequestArgs};
+use crate::client::Client;
+use crate::error::Result;
+use crate::http::Response;
+use crate::sse::{self, EventSource};
+use crate::util::time_of_day;
+
+/// Event stream viewer: `webcat sse [OPTIONS] [METHOD] <URL>`. Events are
+/// printed as they arrive, with the time they came in.
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let mut client = Client::new();
+	let mut details = Details::for_output(std::io::stdout().is_terminal());
+	let mut request = RequestArgs::default();
+	let mut last_event_id = None;
+	let mut reconnect = false;
+
+	let mut args = Args::new(list);
+	while let Some(arg) = args.next_arg()? {
+		match arg {
+			Arg::Value(value) => request.positional.push(value),
+			Arg::Flag(flag) => match flag.as_str() {
+				"--last-event-id" => last_event_id = Some(args.value()?),
+				"--reconnect" => reconnect = true,
+				_ if details.option(&flag) => {}
+				_ if request.option(&flag, &mut args)? => {}
+				_ if super::client_option(&mut client, &flag, &mut args)? => {}
+				_ => return Err(unknown(&flag)),
+			},
+		}
+	}
+	let request = request.build()?;
+
+	let mut source = EventSource::connect(&client, &request, last_event_id, reconnect)?;
+	let mut out = std::io::stdout().lock();
+	if !sse::is_event_stream(&source.response) {
+		print_response(&mut out, &source.response, details)?;
+		return Ok(ExitCode::FAILURE);
+	}
+	let head = Response {
+		body: Vec::new(),
+		..source.response.clone()
+	};
+	print_response(&mut out, &head, details)?;
+	while let Some(event) = source.next(None)? {
+		writeln!(out, "{} {event}", time_of_day())?;
+		out.flush()?;
+	}
+	Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,62 @@
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+use super::args::{unknown, Arg, Args};
+use super::EnvArgs;
+use crate::client::Client;
+use crate::cookie::CookieJar;
+use crate::error::{Error, Result};
+use crate::runner::Runner;
+use crate::tui::{self, App};
+
+/// `webcat tui [OPTIONS] [PATH]...`
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let mut runner = Runner::new(Client::new());
+	// requests of a session share cookies, as those of a run
+	runner.client.cookies = Some(Arc::new(Mutex::new(CookieJar::new())));
+	let mut paths = Vec::new();
+	let mut env = EnvArgs::default();
+
+	let mut args = Args::new(list);
+	while let Some(arg) = args.next_arg()? {
+		match arg {
+			Arg::Value(path) => paths.push(PathBuf::from(path)),
+			Arg::Flag(flag) => match flag.as_str() {
+				"--no-validate" => runner.schemas = None,
+				"--var" => {
+					let pair = args.value()?;
+					let (name, value) = pair.split_once('=').ok_or_else(|| {
+						Error::Usage(format!("expected NAME=VALUE, got `{pair}`"))
+					})?;
+					runner.variables.set(name.trim(), value);
+				}
+				_ if env.option(&flag, &mut args)? => {}
+				_ if super::client_option(&mut runner.client, &flag, &mut args)? => {}
+				_ => return Err(unknown(&flag)),
+			},
+		}
+	}
+	if paths.is_empty() {
+		paths.push(PathBuf::from("."));
+	}
+	if let Some(path) = paths.iter().find(|path| !path.exists()) {
+		return Err(Error::Usage(format!("cannot find `{}`", path.display())));
+	}
+	// the environment file is looked for next to the first file, or in
+	// the first directory
+	let anchor = match paths[0].is_dir() {
+		true => paths[0].join("requests.http"),
+		false => paths[0].clone(),
+	};
+	env.apply(Path::new(&anchor), &mut runner.variables)?;
+	if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+		return Err(Error::Usage("`webcat tui` needs a terminal".into()));
+	}
+	// progress lines would be drawn over the panes
+	runner.client.progress = false;
+
+	tui::run(App::new(paths, runner))?;
+	Ok(ExitCode::SUCCESS)
+}
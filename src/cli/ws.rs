@@ -0,0 +1,199 @@
+use std::io::{BufRead, IsTerminal, Write};
+use std::process::ExitCode;
+use std::sync::mpsc::{self, TryRecvError};
+use std::time::Duration;
+
+use super::args::{unknown, Arg, Args};
+use super::send::{print_response, Details};
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::http::{parse_header_line, Request};
+use crate::url::Url;
+use crate::util::time_of_day;
+use crate::ws::{Message, WebSocket};
+
+/// How long the session waits for messages before checking the input.
+const POLL: Duration = Duration::from_millis(20);
+
+/// Interactive session: `webcat ws [OPTIONS] <URL>`. Lines read from
+/// standard input are sent as text messages, and the messages of both
+/// sides are printed with the time they were sent or received.
+pub fn run(list: &[String]) -> Result<ExitCode> {
+	let mut client = Client::new();
+	let mut details = Details::for_output(std::io::stdout().is_terminal());
+	let mut url = None;
+	let mut headers = Vec::new();
+	let mut compress = true;
+
+	let mut args = Args::new(list);
+	while let Some(arg) = args.next_arg()? {
+		match arg {
+			Arg::Value(value) if url.is_none() => url = Some(Url::parse(&value)?),
+			Arg::Value(value) => {
+				return Err(Error::Usage(format!("unexpected argument `{value}`")))
+			}
+			Arg::Flag(flag) => match flag.as_str() {
+				"-H" | "--header" => {
+					let line = args.value()?;
+					let header = parse_header_line(&line)
+						.ok_or_else(|| Error::Usage(format!("invalid header `{line}`")))?;
+					headers.push(header);
+				}
+				"--no-compress" => compress = false,
+				_ if details.option(&flag) => {}
+				_ if super::client_option(&mut client, &flag, &mut args)? => {}
+				_ => return Err(unknown(&flag)),
+			},
+		}
+	}
+	let url = url.ok_or_else(|| Error::Usage("missing URL, see `webcat --help`".into()))?;
+	let mut request = Request::new("GET", url);
+	for (name, value) in headers {
+		request.headers.append(name, value);
+	}
+
+	let mut socket = WebSocket::connect(&client, &request, compress)?;
+	let mut out = std::io::stdout().lock();
+	print_response(&mut out, &socket.response, details)?;
+
+	// what is typed on a terminal is already on the screen
+	let echo = !std::io::stdin().is_terminal();
+	let lines = read_lines();
+	let mut closing = false;
+	while !socket.is_closed() {
+		match lines.try_recv() {
+			Ok(line) if !closing => {
+				// a mistyped command does not end the session
+				let message = match parse_line(&line) {
+					Ok(Some(message)) => message,
+					Ok(None) => continue,
+					Err(err) => {
+						eprintln!("webcat: {err}");
+						continue;
+					}
+				};
+				closing |= matches!(message, Message::Close(_));
+				socket.send(&message)?;
+				if echo {
+					writeln!(out, "{} > {message}", time_of_day())?;
+					out.flush()?;
+				}
+				continue;
+			}
+			Err(TryRecvError::Disconnected) if !closing => {
+				closing = true;
+				socket.send(&Message::Close(Some((1000, String::new()))))?;
+			}
+			// nothing is sent after a close message
+			Ok(_) | Err(_) => {}
+		}
+		// waits for the close message as long as for any read
+		let timeout = match closing {
+			true => client.timeout,
+			false => Some(POLL),
+		};
+		match socket.receive(timeout)? {
+			Some(message) => {
+				writeln!(out, "{} < {message}", time_of_day())?;
+				out.flush()?;
+			}
+			None if closing => break,
+			None => {}
+		}
+	}
+	Ok(ExitCode::SUCCESS)
+}
+
+/// Reads standard input on a thread, so that messages are printed while
+/// waiting for a line.
+fn read_lines() -> mpsc::Receiver<String> {
+	let (sender, receiver) = mpsc::channel();
+	std::thread::spawn(move || {
+		for line in std::io::stdin().lock().lines() {
+			let Ok(line) = line else { break };
+			if sender.send(line).is_err() {
+				break;
+			}
+		}
+	});
+	receiver
+}
+
+/// Message of an input line: text, or one of the `/binary <HEX>`,
+/// `/ping [TEXT]` and `/close [CODE [REASON]]` commands. A text starting
+/// with `/` is written `//`. Empty lines send nothing.
+fn parse_line(line: &str) -> Result<Option<Message>> {
+	if line.is_empty() {
+		return Ok(None);
+	}
+	let Some(command) = line.strip_prefix('/') else {
+		return Ok(Some(Message::Text(line.to_string())));
+	};
+	if command.starts_with('/') {
+		return Ok(Some(Message::Text(command.to_string())));
+	}
+	let (name, rest) = command.split_once(' ').unwrap_or((command, ""));
+	let message = match name {
+		"binary" => Message::Binary(parse_hex(rest)?),
+		"ping" => Message::Ping(rest.as_bytes().to_vec()),
+		"close" => {
+			let (code, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+			Message::Close(match code {
+				"" => None,
+				code => Some((
+					code.parse()
+						.map_err(|_| Error::Usage(format!("invalid close code `{code}`")))?,
+					reason.to_string(),
+				)),
+			})
+		}
+		_ => {
+			return Err(Error::Usage(format!(
+				"unknown command `/{name}`, expected /binary, /ping or /close"
+			)))
+		}
+	};
+	Ok(Some(message))
+}
+
+/// Decodes hexadecimal bytes, with or without spaces between them.
+fn parse_hex(text: &str) -> Result<Vec<u8>> {
+	let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+	let invalid = || Error::Usage(format!("invalid hexadecimal bytes `{text}`"));
+	if !digits.len().is_multiple_of(2) {
+		return Err(invalid());
+	}
+	digits
+		.chunks(2)
+		.map(|pair| {
+			let pair: String = pair.iter().collect();
+			u8::from_str_radix(&pair, 16).map_err(|_| invalid())
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_input_lines() {
+		let parse = |line| parse_line(line).unwrap();
+		assert_eq!(parse(""), None);
+		assert_eq!(parse("hi there"), Some(Message::Text("hi there".into())));
+		assert_eq!(parse("//path"), Some(Message::Text("/path".into())));
+		assert_eq!(
+			parse("/binary 00ff 1a"),
+			Some(Message::Binary(vec![0, 0xff, 0x1a]))
+		);
+		assert_eq!(parse("/ping"), Some(Message::Ping(Vec::new())));
+		assert_eq!(parse("/close"), Some(Message::Close(None)));
+		assert_eq!(
+			parse("/close 4000 going away"),
+			Some(Message::Close(Some((4000, "going away".into()))))
+		);
+		assert!(parse_line("/binary abc").is_err());
+		assert!(parse_line("/close x").is_err());
+		assert!(parse_line("/quit").is_err());
+	}
+}
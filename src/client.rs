@@ -0,0 +1,628 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rustls::ClientConfig;
+
+use crate::auth::{Auth, Challenge, TokenCache};
+use crate::cookie::CookieJar;
+use crate::error::{Error, Result};
+use crate::h1::BodyReader;
+use crate::http::{Part, PartHandler, Request, Response};
+use crate::redirect::{Hop, RedirectPolicy};
+use crate::timing::Timings;
+use crate::tls::{self, TlsConfig, TlsInfo, TlsStream};
+use crate::url::Url;
+use crate::{encoding, h1, h2, util};
+
+/// Protocol version used for requests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+	/// HTTP/2 when a https server selects it through ALPN, HTTP/1.1
+	/// otherwise.
+	#[default]
+	Auto,
+	Http1,
+	/// HTTP/2 only: required through ALPN for https, sent with prior
+	/// knowledge (h2c) for http.
+	Http2,
+}
+
+/// Sends requests. Each request uses a fresh connection, unless the client
+/// keeps them open, see [`Client::keep_alive`].
+#[derive(Clone, Debug)]
+pub struct Client {
+	/// Timeout for connecting and for each individual socket read/write.
+	pub timeout: Option<Duration>,
+	pub tls: TlsConfig,
+	pub version: HttpVersion,
+	/// Jar sending and storing cookies, shared by the clones of the client.
+	pub cookies: Option<Arc<Mutex<CookieJar>>>,
+	/// Authentication of requests without an `Authorization` header.
+	pub auth: Option<Auth>,
+	/// OAuth2 access tokens, shared by the clones of the client.
+	pub tokens: Arc<TokenCache>,
+	/// Draws the progress of bodies streamed from files on standard error.
+	pub progress: bool,
+	/// Asks for compressed responses with `Accept-Encoding`, unless the
+	/// request has one, and decodes their bodies.
+	pub decode: bool,
+	/// Follows redirects by the policy, none are followed without one.
+	pub redirects: Option<RedirectPolicy>,
+	/// Connection and TLS configuration kept between requests.
+	pub keep_alive: Option<Arc<KeepAlive>>,
+}
+
+impl Default for Client {
+	fn default() -> Self {
+		Client {
+			timeout: Some(Duration::from_secs(30)),
+			tls: TlsConfig::default(),
+			version: HttpVersion::Auto,
+			cookies: None,
+			auth: None,
+			tokens: Arc::default(),
+			progress: false,
+			decode: true,
+			redirects: None,
+			keep_alive: None,
+		}
+	}
+}
+
+/// State kept between the requests of a client made by
+/// [`Client::keep_alive`].
+#[derive(Default)]
+pub struct KeepAlive {
+	/// TLS configuration without protocols, built by the first handshake
+	/// and shared with the copies of the client.
+	tls: Arc<Mutex<Option<Arc<ClientConfig>>>>,
+	/// HTTP/1.1 connection left open by the last response.
+	idle: Mutex<Option<Idle>>,
+}
+
+impl fmt::Debug for KeepAlive {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("KeepAlive").finish_non_exhaustive()
+	}
+}
+
+/// Open connection waiting for the next request to its origin.
+struct Idle {
+	origin: Url,
+	reader: BufReader<Stream>,
+	tls: Option<TlsInfo>,
+}
+
+/// Response of [`Client::send_wire`].
+#[derive(Debug)]
+pub struct WireResponse {
+	/// Bytes as read from the connection.
+	pub bytes: Vec<u8>,
+	/// Why the bytes do not form a complete response, if they do not.
+	pub error: Option<Error>,
+}
+
+/// Reader keeping a copy of the bytes read.
+struct Tee<R> {
+	inner: R,
+	bytes: Vec<u8>,
+}
+
+impl<R: Read> Read for Tee<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let count = self.inner.read(buf)?;
+		self.bytes.extend_from_slice(&buf[..count]);
+		Ok(count)
+	}
+}
+
+/// Plain or TLS connection.
+pub enum Stream {
+	Plain(TcpStream),
+	Tls(Box<TlsStream>),
+}
+
+impl Stream {
+	/// Socket under the connection.
+	pub fn socket(&self) -> &TcpStream {
+		match self {
+			Stream::Plain(stream) => stream,
+			Stream::Tls(stream) => &stream.sock,
+		}
+	}
+}
+
+impl Read for Stream {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match self {
+			Stream::Plain(stream) => stream.read(buf),
+			// many servers close without sending close_notify
+			Stream::Tls(stream) => match stream.read(buf) {
+				Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(0),
+				other => other,
+			},
+		}
+	}
+}
+
+impl Write for Stream {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		match self {
+			Stream::Plain(stream) => stream.write(buf),
+			Stream::Tls(stream) => stream.write(buf),
+		}
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		match self {
+			Stream::Plain(stream) => stream.flush(),
+			Stream::Tls(stream) => stream.flush(),
+		}
+	}
+}
+
+impl Client {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Copy of the client keeping its connections open, for one thread of
+	/// requests such as a load test worker. HTTP/1.1 requests ask the server
+	/// to keep the connection, and the next request to the same origin is
+	/// sent over it. A new connection is opened when the server closed it,
+	/// and after a failed request. HTTP/2 connections are not kept. The TLS
+	/// configuration is built once, for this client and its own copies.
+	pub fn keep_alive(&self) -> Client {
+		let tls = self
+			.keep_alive
+			.as_ref()
+			.map_or_else(Arc::default, |keep| keep.tls.clone());
+		Client {
+			keep_alive: Some(Arc::new(KeepAlive {
+				tls,
+				idle: Mutex::default(),
+			})),
+			..self.clone()
+		}
+	}
+
+	/// Sends a request with the cookies of the jar and its authentication,
+	/// and stores the cookies of the response. Digest authentication sends
+	/// the request a second time when the server challenges the first.
+	/// Redirects are followed by the policy of the client, each hop with
+	/// the cookies of the jar for its URL.
+	pub fn send(&self, request: &Request) -> Result<Response> {
+		let Some(policy) = &self.redirects else {
+			return self.exchange(request);
+		};
+		let origin = request.url.clone();
+		let mut request = request.clone();
+		let mut hops = Vec::new();
+		loop {
+			// the default authentication only goes to the first origin
+			let foreign;
+			let client = match request.url.same_origin(&origin) {
+				true => self,
+				false => {
+					foreign = Client {
+						auth: None,
+						..self.clone()
+					};
+					&foreign
+				}
+			};
+			let mut response = client.exchange(&request)?;
+			let next = match policy.next(&request, &response, hops.len()) {
+				Ok(Some(next)) => next,
+				Ok(None) => {
+					response.redirects = hops;
+					return Ok(response);
+				}
+				Err(message) => {
+					hops.push(Hop::new(&request, &response));
+					return Err(Error::Redirect { message, hops });
+				}
+			};
+			hops.push(Hop::new(&request, &response));
+			request = next;
+		}
+	}
+
+	/// Sends a single request, without following redirects.
+	fn exchange(&self, request: &Request) -> Result<Response> {
+		let mut request = request.clone();
+		self.prepare(&mut request);
+		self.authorize(&mut request)?;
+		let digest = match self.auth_of(&request)? {
+			Some(Auth::Digest { user, password }) => {
+				request.headers.remove("Authorization");
+				Some((user, password))
+			}
+			_ => None,
+		};
+		let mut response = self.send_once(&request)?;
+		self.store_cookies(&request, &response);
+		if let Some((user, password)) = digest.filter(|_| response.status == 401) {
+			if let Some(challenge) = Challenge::from_response(&response) {
+				let header = challenge.authorization(&user, &password, &request);
+				request.headers.set("Authorization", header);
+				response = self.send_once(&request)?;
+				self.store_cookies(&request, &response);
+			}
+		}
+		Ok(response)
+	}
+
+	/// Sends a request over HTTP/1.1 and returns the response as received:
+	/// the bytes read from the connection, with chunk framing and compressed
+	/// bodies left alone. The bytes are returned up to the end of the
+	/// response or the point at which it could not be read any further,
+	/// along with the error that stopped it.
+	pub fn send_wire(&self, request: &Request) -> Result<WireResponse> {
+		let mut request = request.clone();
+		self.prepare(&mut request);
+		self.authorize(&mut request)?;
+		let url = &request.url;
+		let addrs = resolve(&url.host, url.port)?;
+		let socket = self.connect(&url.host, &addrs)?;
+		let (stream, _) = self.handshake(url, socket, &["http/1.1"])?;
+		let mut reader = BufReader::new(Tee {
+			inner: stream,
+			bytes: Vec::new(),
+		});
+		h1::write_request(&mut reader.get_mut().inner, &request)?;
+		let result = h1::read_response(&mut reader, &request.method);
+		let bytes = std::mem::take(&mut reader.get_mut().bytes);
+		match result {
+			Err(err) if bytes.is_empty() => Err(err),
+			result => Ok(WireResponse {
+				bytes,
+				error: result.err(),
+			}),
+		}
+	}
+
+	/// Sends a request over HTTP/1.1 with the cookies of the jar and its
+	/// authentication, and reads the head of the response. The connection
+	/// is returned open after it, to read a streamed body as it arrives or
+	/// for the protocol a `101` response switched to. Redirects are not
+	/// followed and responses are not decoded.
+	pub fn open(&self, request: &Request) -> Result<(Response, BufReader<Stream>)> {
+		let mut request = request.clone();
+		self.add_cookies(&mut request);
+		self.authorize(&mut request)?;
+		let url = &request.url;
+		let start = Instant::now();
+		let addrs = resolve(&url.host, url.port)?;
+		let resolved = Instant::now();
+		let socket = self.connect(&url.host, &addrs)?;
+		let connected = Instant::now();
+		let (stream, tls) = self.handshake(url, socket, &["http/1.1"])?;
+		let handshaken = match tls {
+			Some(_) => Instant::now(),
+			None => connected,
+		};
+		let mut reader = BufReader::new(stream);
+		h1::write_request(reader.get_mut(), &request)?;
+		reader.fill_buf()?;
+		let first_byte = Instant::now();
+		let mut response = h1::read_response_head(&mut reader)?;
+		let done = Instant::now();
+		self.store_cookies(&request, &response);
+		response.tls = tls;
+		response.timing = Timings {
+			dns: resolved - start,
+			connect: connected - resolved,
+			tls: handshaken - connected,
+			ttfb: first_byte - handshaken,
+			transfer: done - first_byte,
+		};
+		Ok((response, reader))
+	}
+
+	/// Adds the cookies of the jar, and `Accept-Encoding` when responses are
+	/// decoded.
+	pub(crate) fn prepare(&self, request: &mut Request) {
+		if let Some(upload) = &mut request.upload {
+			upload.progress |= self.progress;
+		}
+		if self.decode && !request.headers.contains("Accept-Encoding") {
+			request
+				.headers
+				.set("Accept-Encoding", encoding::ACCEPT_ENCODING);
+		}
+		self.add_cookies(request);
+	}
+
+	/// Replaces an `Authorization` header naming a scheme, or sets the one
+	/// of the client's default scheme, except for Digest authentication
+	/// which needs a challenge first.
+	pub fn authorize(&self, request: &mut Request) -> Result<()> {
+		match self.auth_of(request)? {
+			Some(auth) => auth.apply(self, request),
+			None => Ok(()),
+		}
+	}
+
+	/// Scheme the request is authenticated with, `None` for a literal or
+	/// no `Authorization` header.
+	pub(crate) fn auth_of(&self, request: &Request) -> Result<Option<Auth>> {
+		match request.headers.get("Authorization") {
+			Some(value) => Auth::from_header(value)
+				.transpose()
+				.map_err(crate::auth::header_error),
+			None => Ok(self.auth.clone()),
+		}
+	}
+
+	fn store_cookies(&self, request: &Request, response: &Response) {
+		if let Some(jar) = &self.cookies {
+			let mut jar = jar.lock().unwrap_or_else(|err| err.into_inner());
+			jar.store_response(&request.url, response, util::unix_time());
+		}
+	}
+
+	/// Adds the cookies of the jar to the `Cookie` header, to show them in
+	/// the request as recorded. Requests are sent without them, as sending
+	/// adds those of the jar for each hop.
+	pub fn add_cookies(&self, request: &mut Request) {
+		if let Some(jar) = &self.cookies {
+			let jar = jar.lock().unwrap_or_else(|err| err.into_inner());
+			jar.add_to(request, util::unix_time());
+		}
+	}
+
+	/// Writes the cookie jar back to its file, if it has one.
+	pub fn save_cookies(&self) -> Result<()> {
+		match &self.cookies {
+			Some(jar) => jar.lock().unwrap_or_else(|err| err.into_inner()).save(),
+			None => Ok(()),
+		}
+	}
+
+	/// Sends a request with the cookies of the jar and its authentication,
+	/// passing the head and then the body to `on_part` as they arrive, for
+	/// responses streaming messages. The response is returned with the whole
+	/// body, which is not decoded, and redirects are not followed.
+	pub fn stream(&self, request: &Request, on_part: &mut PartHandler<'_>) -> Result<Response> {
+		let mut request = request.clone();
+		self.add_cookies(&mut request);
+		self.authorize(&mut request)?;
+		let response = self.transfer(&request, Some(on_part))?;
+		self.store_cookies(&request, &response);
+		Ok(response)
+	}
+
+	/// Sends a request as it is.
+	pub(crate) fn send_once(&self, request: &Request) -> Result<Response> {
+		let mut response = self.transfer(request, None)?;
+		if self.decode {
+			encoding::decode_response(&mut response)?;
+		}
+		Ok(response)
+	}
+
+	/// Sends a request and reads its response, passing it to `on_part` as
+	/// it arrives when given.
+	fn transfer(
+		&self,
+		request: &Request,
+		on_part: Option<&mut PartHandler<'_>>,
+	) -> Result<Response> {
+		if on_part.is_none() {
+			if let Some(idle) = self.take_idle(&request.url) {
+				if let Some(response) = self.reuse(idle, request)? {
+					return Ok(response);
+				}
+			}
+		}
+		let url = &request.url;
+		let start = Instant::now();
+		let addrs = resolve(&url.host, url.port)?;
+		let resolved = Instant::now();
+		let socket = self.connect(&url.host, &addrs)?;
+		let connected = Instant::now();
+		let alpn: &[&str] = match self.version {
+			HttpVersion::Auto => &["h2", "http/1.1"],
+			HttpVersion::Http1 => &["http/1.1"],
+			HttpVersion::Http2 => &["h2"],
+		};
+		let (stream, tls) = self.handshake(url, socket, alpn)?;
+		let handshaken = match tls {
+			Some(_) => Instant::now(),
+			None => connected,
+		};
+
+		let use_h2 = match &tls {
+			Some(info) => info.alpn.as_deref() == Some("h2"),
+			None => self.version == HttpVersion::Http2,
+		};
+		if self.version == HttpVersion::Http2 && !use_h2 {
+			return Err(Error::Protocol("server did not select HTTP/2".into()));
+		}
+		let mut first_byte = None;
+		let mut response = if use_h2 {
+			h2::send(stream, request, &mut first_byte, on_part)?
+		} else {
+			let mut reader = BufReader::new(stream);
+			match &self.keep_alive {
+				Some(_) => h1::write_keep_alive_request(reader.get_mut(), request)?,
+				None => h1::write_request(reader.get_mut(), request)?,
+			}
+			reader.fill_buf()?;
+			first_byte = Some(Instant::now());
+			match on_part {
+				Some(on_part) => read_streamed(reader, &request.method, on_part)?,
+				None => {
+					let response = h1::read_response(&mut reader, &request.method)?;
+					self.keep(url, reader, tls.clone(), &response, &request.method);
+					response
+				}
+			}
+		};
+		let done = Instant::now();
+		let first_byte = first_byte.unwrap_or(done);
+
+		response.tls = tls;
+		response.timing = Timings {
+			dns: resolved - start,
+			connect: connected - resolved,
+			tls: handshaken - connected,
+			ttfb: first_byte - handshaken,
+			transfer: done - first_byte,
+		};
+		Ok(response)
+	}
+
+	/// Sends a request over a kept connection. Returns `None` when the
+	/// server closed the connection before answering, as it may do with an
+	/// idle connection at any time.
+	fn reuse(&self, idle: Idle, request: &Request) -> Result<Option<Response>> {
+		let Idle {
+			origin,
+			mut reader,
+			tls,
+		} = idle;
+		let closed = |err: &io::Error| {
+			matches!(
+				err.kind(),
+				io::ErrorKind::ConnectionReset
+					| io::ErrorKind::ConnectionAborted
+					| io::ErrorKind::BrokenPipe
+					| io::ErrorKind::UnexpectedEof
+			)
+		};
+		let start = Instant::now();
+		match h1::write_keep_alive_request(reader.get_mut(), request) {
+			Err(Error::Io(err)) if closed(&err) => return Ok(None),
+			result => result?,
+		}
+		match reader.fill_buf() {
+			Ok([]) => return Ok(None),
+			Err(err) if closed(&err) => return Ok(None),
+			result => result?,
+		};
+		let first_byte = Instant::now();
+		let mut response = h1::read_response(&mut reader, &request.method)?;
+		let done = Instant::now();
+		self.keep(&origin, reader, tls.clone(), &response, &request.method);
+		response.tls = tls;
+		response.timing = Timings {
+			ttfb: first_byte - start,
+			transfer: done - first_byte,
+			..Timings::default()
+		};
+		Ok(Some(response))
+	}
+
+	/// Kept connection to the origin of a URL, if any.
+	fn take_idle(&self, url: &Url) -> Option<Idle> {
+		let keep = self.keep_alive.as_ref()?;
+		let mut idle = keep.idle.lock().unwrap_or_else(|err| err.into_inner());
+		idle.take().filter(|idle| idle.origin.same_origin(url))
+	}
+
+	/// Keeps a connection for the next request when the client keeps them
+	/// and the response leaves it usable.
+	fn keep(
+		&self,
+		origin: &Url,
+		reader: BufReader<Stream>,
+		tls: Option<TlsInfo>,
+		response: &Response,
+		method: &str,
+	) {
+		let Some(keep) = &self.keep_alive else {
+			return;
+		};
+		if h1::keeps_connection(response, method) {
+			*keep.idle.lock().unwrap_or_else(|err| err.into_inner()) = Some(Idle {
+				origin: origin.clone(),
+				reader,
+				tls,
+			});
+		}
+	}
+
+	/// TLS configuration offering the given protocols, built once for a
+	/// client keeping its connections.
+	fn tls_config(&self, alpn: &[&str]) -> Result<Arc<ClientConfig>> {
+		let Some(keep) = &self.keep_alive else {
+			return self.tls.client_config(alpn);
+		};
+		let mut cached = keep.tls.lock().unwrap_or_else(|err| err.into_inner());
+		let base = match &*cached {
+			Some(config) => config.clone(),
+			None => cached.insert(self.tls.client_config(&[])?).clone(),
+		};
+		let mut config = ClientConfig::clone(&base);
+		config.alpn_protocols = alpn.iter().map(|p| p.as_bytes().to_vec()).collect();
+		Ok(Arc::new(config))
+	}
+
+	/// Starts TLS on https connections, offering the given protocols.
+	fn handshake(
+		&self,
+		url: &Url,
+		socket: TcpStream,
+		alpn: &[&str],
+	) -> Result<(Stream, Option<TlsInfo>)> {
+		if !url.is_https() {
+			return Ok((Stream::Plain(socket), None));
+		}
+		let config = self.tls_config(alpn)?;
+		let stream = tls::connect(config, &url.host, socket)?;
+		let info = tls::session_info(&stream.conn);
+		Ok((Stream::Tls(Box::new(stream)), Some(info)))
+	}
+
+	fn connect(&self, host: &str, addrs: &[SocketAddr]) -> Result<TcpStream> {
+		let mut last_err = None;
+		for addr in addrs {
+			let stream = match self.timeout {
+				Some(timeout) => TcpStream::connect_timeout(addr, timeout),
+				None => TcpStream::connect(addr),
+			};
+			match stream {
+				Ok(stream) => {
+					stream.set_read_timeout(self.timeout)?;
+					stream.set_write_timeout(self.timeout)?;
+					stream.set_nodelay(true)?;
+					return Ok(stream);
+				}
+				Err(err) => last_err = Some(err),
+			}
+		}
+		Err(match last_err {
+			Some(err) => err.into(),
+			None => Error::Url(format!("could not resolve `{host}`")),
+		})
+	}
+}
+
+/// Reads an HTTP/1.1 response, passing it to `on_part` as it arrives.
+fn read_streamed(
+	mut reader: BufReader<Stream>,
+	method: &str,
+	on_part: &mut PartHandler<'_>,
+) -> Result<Response> {
+	let mut response = h1::read_response_head(&mut reader)?;
+	on_part(Part::Head(&response))?;
+	let mut body = BodyReader::new(reader, &response, method)?;
+	let mut buffer = [0; 16 * 1024];
+	loop {
+		let count = body.read(&mut buffer)?;
+		if count == 0 {
+			return Ok(response);
+		}
+		on_part(Part::Data(&buffer[..count]))?;
+		response.body.extend_from_slice(&buffer[..count]);
+	}
+}
+
+fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+	Ok((host, port).to_socket_addrs()?.collect())
+}
@@ -0,0 +1,528 @@
+//! Cookie jar following RFC 6265, with the additions of its revision
+//! (draft-ietf-httpbis-rfc6265bis): `SameSite`, the `__Secure-` and
+//! `__Host-` prefixes, and secure cookies only set over secure connections.
+//!
+//! Jars are saved in the Netscape `cookies.txt` format read and written by
+//! curl and by the cookie export extensions of browsers, see [`netscape`].
+//! The format has no field for `SameSite`, which is lost when saving.
+
+pub mod netscape;
+mod public_suffix;
+
+use std::fmt;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::http::{Request, Response};
+use crate::url::Url;
+use crate::util;
+
+pub use public_suffix::{is_public_suffix, public_suffix};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+	Strict,
+	Lax,
+	None,
+}
+
+impl fmt::Display for SameSite {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			SameSite::Strict => "Strict",
+			SameSite::Lax => "Lax",
+			SameSite::None => "None",
+		})
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cookie {
+	pub name: String,
+	pub value: String,
+	/// Lower case, without a leading dot.
+	pub domain: String,
+	/// Only sent to `domain` itself, not to its subdomains, because the
+	/// cookie was set without a `Domain` attribute.
+	pub host_only: bool,
+	pub path: String,
+	pub secure: bool,
+	pub http_only: bool,
+	pub same_site: Option<SameSite>,
+	/// Expiry as Unix time, `None` for a session cookie.
+	pub expires: Option<u64>,
+}
+
+impl Cookie {
+	pub fn is_expired(&self, now: u64) -> bool {
+		self.expires.is_some_and(|expires| expires <= now)
+	}
+
+	/// Returns true if the cookie is sent with requests to `url`.
+	pub fn matches(&self, url: &Url) -> bool {
+		let domain = if self.host_only {
+			url.host == self.domain
+		} else {
+			domain_matches(&url.host, &self.domain)
+		};
+		domain && path_matches(url.path_only(), &self.path) && (!self.secure || is_secure(url))
+	}
+}
+
+/// Cookies received in responses, sent back with the requests they match.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CookieJar {
+	/// In creation order, which orders cookies with paths of the same
+	/// length.
+	cookies: Vec<Cookie>,
+	/// File the jar is saved to.
+	pub path: Option<PathBuf>,
+}
+
+impl CookieJar {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Jar saved to `path`, with the cookies of the file if it exists.
+	pub fn open(path: impl AsRef<Path>) -> Result<CookieJar> {
+		let path = path.as_ref();
+		let mut jar = match path.exists() {
+			true => netscape::load(path)?,
+			false => CookieJar::new(),
+		};
+		jar.path = Some(path.to_path_buf());
+		Ok(jar)
+	}
+
+	/// Writes the jar to its file, if it has one, without the expired
+	/// cookies.
+	pub fn save(&self) -> Result<()> {
+		if let Some(path) = &self.path {
+			std::fs::write(path, netscape::write(self, util::unix_time()))?;
+		}
+		Ok(())
+	}
+
+	pub fn cookies(&self) -> &[Cookie] {
+		&self.cookies
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.cookies.is_empty()
+	}
+
+	/// Adds a cookie, replacing the one with the same name, domain and path.
+	pub fn insert(&mut self, cookie: Cookie) {
+		let existing = self.cookies.iter_mut().find(|other| {
+			other.name == cookie.name && other.domain == cookie.domain && other.path == cookie.path
+		});
+		match existing {
+			// the replacement keeps the creation order of the old cookie
+			Some(existing) => *existing = cookie,
+			None => self.cookies.push(cookie),
+		}
+	}
+
+	pub fn remove_expired(&mut self, now: u64) {
+		self.cookies.retain(|cookie| !cookie.is_expired(now));
+	}
+
+	/// Stores the cookie of a `Set-Cookie` header received from `url`.
+	/// Returns the reason why it was ignored.
+	pub fn store(
+		&mut self,
+		url: &Url,
+		set_cookie: &str,
+		now: u64,
+	) -> std::result::Result<(), String> {
+		let mut parts = set_cookie.split(';');
+		let pair = parts.next().unwrap_or_default();
+		let (name, value) = pair
+			.split_once('=')
+			.ok_or_else(|| "no `=` in the name-value pair".to_string())?;
+		let (name, value) = (name.trim(), value.trim());
+		if name.is_empty() {
+			return Err("empty cookie name".into());
+		}
+
+		let mut domain = None;
+		let mut path = None;
+		let mut secure = false;
+		let mut http_only = false;
+		let mut same_site = None;
+		let mut expires = None;
+		let mut max_age: Option<i64> = None;
+		for attribute in parts {
+			let (key, argument) = match attribute.split_once('=') {
+				Some((key, argument)) => (key.trim(), argument.trim()),
+				None => (attribute.trim(), ""),
+			};
+			match key.to_ascii_lowercase().as_str() {
+				"expires" => {
+					if let Some(time) = parse_date(argument) {
+						expires = Some(time);
+					}
+				}
+				"max-age" => {
+					let digits = argument.strip_prefix('-').unwrap_or(argument);
+					if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+						max_age = Some(argument.parse().unwrap_or(i64::MAX));
+					}
+				}
+				"domain" if !argument.is_empty() => {
+					domain = Some(argument.trim_start_matches('.').to_ascii_lowercase());
+				}
+				"path" => path = argument.starts_with('/').then(|| argument.to_string()),
+				"secure" => secure = true,
+				"httponly" => http_only = true,
+				"samesite" => {
+					same_site = match argument.to_ascii_lowercase().as_str() {
+						"strict" => Some(SameSite::Strict),
+						"lax" => Some(SameSite::Lax),
+						"none" => Some(SameSite::None),
+						_ => None,
+					}
+				}
+				_ => {}
+			}
+		}
+		// Max-Age wins over Expires
+		let expires = match max_age {
+			Some(seconds) if seconds <= 0 => Some(0),
+			Some(seconds) => Some(now.saturating_add(seconds as u64)),
+			None => expires.map(|time: i64| time.max(0) as u64),
+		};
+
+		let (domain, host_only) = match domain {
+			Some(domain) if is_public_suffix(&domain) && domain != url.host => {
+				return Err(format!("domain {domain} is a public suffix"));
+			}
+			Some(domain) if domain == url.host => (domain, false),
+			Some(domain) if domain_matches(&url.host, &domain) => (domain, false),
+			Some(domain) => {
+				return Err(format!("domain {domain} does not match host {}", url.host));
+			}
+			None => (url.host.clone(), true),
+		};
+		// a public suffix may only set cookies for itself
+		let host_only = host_only || is_public_suffix(&domain);
+		let path = path.unwrap_or_else(|| default_path(url.path_only()));
+
+		if secure && !is_secure(url) {
+			return Err("secure cookie from an insecure origin".into());
+		}
+		if same_site == Some(SameSite::None) && !secure {
+			return Err("SameSite=None requires Secure".into());
+		}
+		if name.starts_with("__Secure-") && !secure {
+			return Err("__Secure- prefix requires Secure".into());
+		}
+		if name.starts_with("__Host-") && (!secure || !host_only || path != "/") {
+			return Err("__Host- prefix requires Secure, no Domain and Path=/".into());
+		}
+		// insecure origins cannot replace secure cookies
+		if !is_secure(url) {
+			let shadowed = self.cookies.iter().any(|other| {
+				other.secure
+					&& other.name == name
+					&& (domain_matches(&domain, &other.domain)
+						|| domain_matches(&other.domain, &domain))
+					&& path_matches(&path, &other.path)
+			});
+			if shadowed {
+				return Err(format!("would replace the secure cookie {name}"));
+			}
+		}
+
+		let cookie = Cookie {
+			name: name.to_string(),
+			value: value.to_string(),
+			domain,
+			host_only,
+			path,
+			secure,
+			http_only,
+			same_site,
+			expires,
+		};
+		if cookie.is_expired(now) {
+			self.cookies.retain(|other| {
+				!(other.name == cookie.name
+					&& other.domain == cookie.domain
+					&& other.path == cookie.path)
+			});
+		} else {
+			self.insert(cookie);
+		}
+		Ok(())
+	}
+
+	/// Stores the cookies set by a response to a request for `url`.
+	pub fn store_response(&mut self, url: &Url, response: &Response, now: u64) {
+		for set_cookie in response.headers.get_all("Set-Cookie") {
+			let _ = self.store(url, set_cookie, now);
+		}
+	}
+
+	/// Cookies to send to `url`, longest paths first.
+	pub fn matching(&self, url: &Url, now: u64) -> Vec<&Cookie> {
+		let mut cookies: Vec<&Cookie> = self
+			.cookies
+			.iter()
+			.filter(|cookie| !cookie.is_expired(now) && cookie.matches(url))
+			.collect();
+		cookies.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+		cookies
+	}
+
+	/// Value of the `Cookie` header for `url`.
+	pub fn header(&self, url: &Url, now: u64) -> Option<String> {
+		let pairs: Vec<String> = self
+			.matching(url, now)
+			.iter()
+			.map(|cookie| format!("{}={}", cookie.name, cookie.value))
+			.collect();
+		(!pairs.is_empty()).then(|| pairs.join("; "))
+	}
+
+	/// Adds the matching cookies to the `Cookie` header of a request,
+	/// keeping the cookies it already has.
+	pub fn add_to(&self, request: &mut Request, now: u64) {
+		let existing = request
+			.headers
+			.get("Cookie")
+			.unwrap_or_default()
+			.to_string();
+		let given: Vec<&str> = existing
+			.split(';')
+			.filter_map(|pair| pair.split_once('=').map(|(name, _)| name.trim()))
+			.collect();
+		let pairs: Vec<String> = self
+			.matching(&request.url, now)
+			.iter()
+			.filter(|cookie| !given.contains(&cookie.name.as_str()))
+			.map(|cookie| format!("{}={}", cookie.name, cookie.value))
+			.collect();
+		if pairs.is_empty() {
+			return;
+		}
+		let value = match existing.trim() {
+			"" => pairs.join("; "),
+			existing => format!("{existing}; {}", pairs.join("; ")),
+		};
+		request.headers.set("Cookie", value);
+	}
+}
+
+/// Secure origins: https, and the loopback host as browsers treat it.
+fn is_secure(url: &Url) -> bool {
+	url.is_https()
+		|| url.host == "localhost"
+		|| url.host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Domain matching of RFC 6265 section 5.1.3.
+fn domain_matches(host: &str, domain: &str) -> bool {
+	host == domain
+		|| host
+			.strip_suffix(domain)
+			.is_some_and(|rest| rest.ends_with('.'))
+			&& host.parse::<IpAddr>().is_err()
+}
+
+/// Path matching of RFC 6265 section 5.1.4.
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+	match request_path.strip_prefix(cookie_path) {
+		Some(rest) => rest.is_empty() || cookie_path.ends_with('/') || rest.starts_with('/'),
+		None => false,
+	}
+}
+
+/// Directory of the request path, the default cookie path.
+fn default_path(path: &str) -> String {
+	match path.rfind('/') {
+		Some(0) | None => "/".into(),
+		Some(index) => path[..index].to_string(),
+	}
+}
+
+/// Parses a date with the lenient algorithm of RFC 6265 section 5.1.1,
+/// which accepts the formats seen in `Expires` attributes. Returns Unix
+/// time, negative before 1970.
+pub fn parse_date(text: &str) -> Option<i64> {
+	const MONTHS: [&str; 12] = [
+		"jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+	];
+	let delimiter = |c: char| matches!(c, '\t' | ' '..='/' | ';'..='@' | '['..='`' | '{'..='~');
+	// leading digits of a token, when followed by nothing or a non-digit
+	fn number(token: &str, min: usize, max: usize) -> Option<u32> {
+		let digits = token.bytes().take_while(u8::is_ascii_digit).count();
+		if !(min..=max).contains(&digits) {
+			return None;
+		}
+		token[..digits].parse().ok()
+	}
+
+	let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+	for token in text.split(delimiter).filter(|token| !token.is_empty()) {
+		if time.is_none() {
+			let mut fields = token.splitn(3, ':');
+			if let (Some(h), Some(m), Some(s)) = (fields.next(), fields.next(), fields.next()) {
+				if let (Some(h), Some(m), Some(s)) =
+					(number(h, 1, 2), number(m, 1, 2), number(s, 1, 2))
+				{
+					time = Some((h, m, s));
+					continue;
+				}
+			}
+		}
+		if day.is_none() {
+			if let Some(value) = number(token, 1, 2) {
+				day = Some(value);
+				continue;
+			}
+		}
+		if month.is_none() && token.len() >= 3 {
+			let prefix = token[..3].to_ascii_lowercase();
+			if let Some(index) = MONTHS.iter().position(|name| *name == prefix) {
+				month = Some(index as u32 + 1);
+				continue;
+			}
+		}
+		if year.is_none() {
+			if let Some(value) = number(token, 2, 4) {
+				year = Some(value);
+			}
+		}
+	}
+
+	let (hour, minute, second) = time?;
+	let (day, month, mut year) = (day?, month?, year?);
+	year += match year {
+		70..=99 => 1900,
+		0..=69 => 2000,
+		_ => 0,
+	};
+	if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+		return None;
+	}
+	let days = util::days_from_civil(i64::from(year), month, day);
+	Some(days * 86400 + i64::from(hour * 3600 + minute * 60 + second))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const NOW: u64 = 1_700_000_000;
+
+	fn url(text: &str) -> Url {
+		Url::parse(text).unwrap()
+	}
+
+	fn jar(url_text: &str, headers: &[&str]) -> CookieJar {
+		let mut jar = CookieJar::new();
+		for header in headers {
+			jar.store(&url(url_text), header, NOW).unwrap();
+		}
+		jar
+	}
+
+	#[test]
+	fn parses_cookie_dates() {
+		let expected = Some(784_111_777);
+		assert_eq!(parse_date("Sun, 06 Nov 1994 08:49:37 GMT"), expected);
+		assert_eq!(parse_date("Sunday, 06-Nov-94 08:49:37 GMT"), expected);
+		assert_eq!(parse_date("Sun Nov  6 08:49:37 1994"), expected);
+		assert_eq!(parse_date("6 november 1994 8:49:37"), expected);
+		assert_eq!(parse_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+		assert_eq!(parse_date("Wed, 32 Nov 2000 08:49:37 GMT"), None);
+		assert_eq!(parse_date("soon"), None);
+	}
+
+	#[test]
+	fn matches_domains_and_paths() {
+		let jar = jar(
+			"https://www.example.com/app/login",
+			&[
+				"sid=1; HttpOnly",
+				"theme=dark; Domain=.example.com; Path=/",
+				"cart=3; Path=/app/cart; Secure",
+			],
+		);
+		let cookies = jar.cookies();
+		assert_eq!(cookies[0].path, "/app");
+		assert!(cookies[0].host_only && cookies[0].http_only);
+		assert!(!cookies[1].host_only);
+
+		let header = |text: &str| jar.header(&url(text), NOW);
+		assert_eq!(
+			header("https://www.example.com/app/cart/1").as_deref(),
+			Some("cart=3; sid=1; theme=dark")
+		);
+		assert_eq!(
+			header("https://api.example.com/app").as_deref(),
+			Some("theme=dark")
+		);
+		assert_eq!(
+			header("http://www.example.com/app/cart").as_deref(),
+			Some("sid=1; theme=dark")
+		);
+		assert_eq!(
+			header("https://www.example.com/application").as_deref(),
+			Some("theme=dark")
+		);
+		assert_eq!(header("https://example.org/"), None);
+	}
+
+	#[test]
+	fn rejects_invalid_cookies() {
+		let mut jar = CookieJar::new();
+		let mut store = |url_text: &str, header: &str| jar.store(&url(url_text), header, NOW);
+		assert_eq!(
+			store("https://a.example.co.uk/", "x=1; Domain=co.uk").unwrap_err(),
+			"domain co.uk is a public suffix"
+		);
+		assert!(store("https://example.com/", "x=1; Domain=other.com").is_err());
+		assert!(store("http://example.com/", "x=1; Secure").is_err());
+		assert!(store("https://example.com/", "x=1; SameSite=None").is_err());
+		assert!(store("https://example.com/", "__Host-x=1; Secure; Path=/app").is_err());
+		assert!(store("https://example.com/", "__Host-x=1; Secure; Path=/").is_ok());
+		assert!(store("https://example.com/", "token").is_err());
+		// secure cookies may be set over http on the loopback host
+		assert!(store("http://localhost:8080/", "y=1; Secure").is_ok());
+	}
+
+	#[test]
+	fn expires_and_replaces_cookies() {
+		let mut jar = jar(
+			"https://example.com/",
+			&[
+				"a=1; Max-Age=60",
+				"b=1; Expires=Sun, 06 Nov 1994 08:49:37 GMT",
+				"c=1; Max-Age=60; Expires=Sun, 06 Nov 1994 08:49:37 GMT",
+			],
+		);
+		let names: Vec<&str> = jar.cookies().iter().map(|c| c.name.as_str()).collect();
+		assert_eq!(names, ["a", "c"]);
+		assert_eq!(jar.cookies()[0].expires, Some(NOW + 60));
+
+		jar.store(&url("https://example.com/"), "a=2", NOW).unwrap();
+		assert_eq!(jar.cookies()[0].value, "2");
+		assert_eq!(jar.cookies()[0].expires, None);
+		jar.store(&url("https://example.com/"), "a=; Max-Age=0", NOW)
+			.unwrap();
+		assert_eq!(jar.cookies().len(), 1);
+		assert_eq!(jar.header(&url("https://example.com/"), NOW + 61), None);
+
+		let mut request = Request::new("GET", url("https://example.com/"));
+		request.headers.append("Cookie", "c=given; d=4");
+		jar.add_to(&mut request, NOW);
+		assert_eq!(request.headers.get("Cookie"), Some("c=given; d=4"));
+		jar.store(&url("https://example.com/"), "e=5", NOW).unwrap();
+		jar.add_to(&mut request, NOW);
+		assert_eq!(request.headers.get("Cookie"), Some("c=given; d=4; e=5"));
+	}
+}
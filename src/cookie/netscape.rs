@@ -0,0 +1,152 @@
+//! Netscape `cookies.txt` files, one cookie per line with seven tab
+//! separated fields (shown aligned with spaces):
+//!
+//! ```text
+//! # Netscape HTTP Cookie File
+//! .example.com  TRUE  /  FALSE  1767225600  theme  dark
+//! #HttpOnly_www.example.com  FALSE  /app  TRUE  0  sid  31d4d96e
+//! ```
+//!
+//! The fields are the domain, whether subdomains match, the path, whether
+//! the cookie is secure, the expiry as Unix time (0 for session cookies),
+//! the name and the value. curl marks HttpOnly cookies with the
+//! `#HttpOnly_` prefix, other lines starting with `#` are comments.
+
+use std::path::Path;
+
+use super::{Cookie, CookieJar};
+use crate::error::{Error, ParseError, Result};
+
+const HEADER: &str = "\
+# Netscape HTTP Cookie File
+# https://curl.se/docs/http-cookies.html
+# Written by webcat, edit at your own risk.
+
+";
+
+pub fn load(path: &Path) -> Result<CookieJar> {
+	let text = std::fs::read_to_string(path)
+		.map_err(|err| Error::Usage(format!("cannot read `{}`: {err}", path.display())))?;
+	parse(&path.display().to_string(), &text)
+}
+
+/// Parses the text of a cookie file. `source` names the file in errors.
+pub fn parse(source: &str, text: &str) -> Result<CookieJar> {
+	let mut jar = CookieJar::new();
+	for (index, line) in text.lines().enumerate() {
+		let line_no = index + 1;
+		let (http_only, line) = match line.strip_prefix("#HttpOnly_") {
+			Some(rest) => (true, rest),
+			None => (false, line),
+		};
+		if line.trim().is_empty() || line.starts_with('#') {
+			continue;
+		}
+		let error = |column: usize, message: &str| {
+			Error::from(ParseError::new(line_no, column, message).in_source(source))
+		};
+		let fields: Vec<&str> = line.split('\t').collect();
+		// curl writes six fields for an empty value
+		let [domain, subdomains, path, secure, expires, name, value] = match fields[..] {
+			[a, b, c, d, e, f] => [a, b, c, d, e, f, ""],
+			[a, b, c, d, e, f, g] => [a, b, c, d, e, f, g],
+			_ => return Err(error(1, "expected 7 tab separated fields")),
+		};
+		let column = |field: usize| -> usize {
+			fields[..field]
+				.iter()
+				.map(|f| f.chars().count() + 1)
+				.sum::<usize>()
+				+ 1
+		};
+		let flag = |field: usize, text: &str| match text {
+			"TRUE" => Ok(true),
+			"FALSE" => Ok(false),
+			_ => Err(error(column(field), "expected TRUE or FALSE")),
+		};
+		let expires: u64 = expires
+			.trim()
+			.parse()
+			.map_err(|_| error(column(4), "invalid expiry time"))?;
+		let include_subdomains = flag(1, subdomains)?;
+		jar.insert(Cookie {
+			name: name.to_string(),
+			value: value.to_string(),
+			domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+			host_only: !include_subdomains,
+			path: path.to_string(),
+			secure: flag(3, secure)?,
+			http_only,
+			same_site: None,
+			expires: (expires != 0).then_some(expires),
+		});
+	}
+	Ok(jar)
+}
+
+/// Text of the cookie file for the cookies of the jar not expired at `now`.
+pub fn write(jar: &CookieJar, now: u64) -> String {
+	let mut text = HEADER.to_string();
+	for cookie in jar
+		.cookies()
+		.iter()
+		.filter(|cookie| !cookie.is_expired(now))
+	{
+		let bool_text = |value: bool| if value { "TRUE" } else { "FALSE" };
+		text.push_str(&format!(
+			"{}{}{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+			if cookie.http_only { "#HttpOnly_" } else { "" },
+			if cookie.host_only { "" } else { "." },
+			cookie.domain,
+			bool_text(!cookie.host_only),
+			cookie.path,
+			bool_text(cookie.secure),
+			cookie.expires.unwrap_or(0),
+			cookie.name,
+			cookie.value
+		));
+	}
+	text
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::url::Url;
+
+	#[test]
+	fn reads_and_writes_cookie_files() {
+		let text = "# Netscape HTTP Cookie File\n\
+			\n\
+			.example.com\tTRUE\t/\tFALSE\t1767225600\ttheme\tdark\n\
+			#HttpOnly_www.example.com\tFALSE\t/app\tTRUE\t0\tsid\t31d4d96e\n\
+			www.example.com\tFALSE\t/\tFALSE\t0\tempty\n\
+			old.example.com\tFALSE\t/\tFALSE\t1000\tgone\tx\n";
+		let jar = parse("cookies.txt", text).unwrap();
+		assert_eq!(jar.cookies().len(), 4);
+		let sid = &jar.cookies()[1];
+		assert!(sid.http_only && sid.secure && sid.host_only);
+		assert_eq!(sid.expires, None);
+		assert_eq!(jar.cookies()[2].value, "");
+
+		let url = Url::parse("https://www.example.com/app/x").unwrap();
+		assert_eq!(
+			jar.header(&url, 1_700_000_000).as_deref(),
+			Some("sid=31d4d96e; theme=dark; empty=")
+		);
+		let written = write(&jar, 1_700_000_000);
+		assert!(written.starts_with("# Netscape HTTP Cookie File\n"));
+		assert_eq!(
+			written.lines().skip(4).collect::<Vec<_>>(),
+			[
+				".example.com\tTRUE\t/\tFALSE\t1767225600\ttheme\tdark",
+				"#HttpOnly_www.example.com\tFALSE\t/app\tTRUE\t0\tsid\t31d4d96e",
+				"www.example.com\tFALSE\t/\tFALSE\t0\tempty\t",
+			]
+		);
+		assert_eq!(parse("again", &written).unwrap().cookies().len(), 3);
+
+		let err = parse("cookies.txt", "a.com\tTRUE\t/\tYES\t0\tn\tv\n").unwrap_err();
+		assert_eq!(err.to_string(), "cookies.txt:1:14: expected TRUE or FALSE");
+	}
+}
@@ -0,0 +1,68 @@
+//! Public suffixes, under which registrants can take names (`com`,
+//! `co.uk`, `github.io`). A cookie may not be set for a public suffix, or
+//! one site could set cookies for all others below it.
+
+/// Rules in the format of the Public Suffix List: one suffix per line,
+/// `*` matching any label and `!` marking exceptions.
+const RULES: &str = include_str!("public_suffix.dat");
+
+/// Returns true if `domain` is a public suffix.
+pub fn is_public_suffix(domain: &str) -> bool {
+	public_suffix(domain).len() == domain.len()
+}
+
+/// Longest public suffix of `domain` (lower case, without a trailing dot),
+/// using the prevailing rule of the list and `*` if none matches.
+pub fn public_suffix(domain: &str) -> &str {
+	let labels: Vec<&str> = domain.split('.').collect();
+	let mut prevailing = 1;
+	for rule in RULES.lines().map(str::trim) {
+		if rule.is_empty() || rule.starts_with("//") {
+			continue;
+		}
+		let (exception, rule) = match rule.strip_prefix('!') {
+			Some(rule) => (true, rule),
+			None => (false, rule),
+		};
+		let rule_labels: Vec<&str> = rule.split('.').collect();
+		if rule_labels.len() > labels.len() {
+			continue;
+		}
+		let tail = &labels[labels.len() - rule_labels.len()..];
+		let matches = rule_labels
+			.iter()
+			.zip(tail)
+			.all(|(rule, label)| *rule == "*" || rule == label);
+		if !matches {
+			continue;
+		}
+		if exception {
+			// an exception wins over everything, its suffix drops the first label
+			prevailing = rule_labels.len() - 1;
+			break;
+		}
+		prevailing = prevailing.max(rule_labels.len());
+	}
+	let start = labels.len() - prevailing.min(labels.len());
+	let offset: usize = labels[..start].iter().map(|label| label.len() + 1).sum();
+	&domain[offset..]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn finds_public_suffixes() {
+		assert_eq!(public_suffix("www.example.com"), "com");
+		assert_eq!(public_suffix("example.co.uk"), "co.uk");
+		assert_eq!(public_suffix("site.github.io"), "github.io");
+		assert_eq!(public_suffix("a.b.kawasaki.jp"), "b.kawasaki.jp");
+		assert_eq!(public_suffix("www.city.kawasaki.jp"), "kawasaki.jp");
+		assert_eq!(public_suffix("localhost"), "localhost");
+		assert!(is_public_suffix("co.uk"));
+		assert!(is_public_suffix("anything"));
+		assert!(!is_public_suffix("example.com"));
+		assert!(!is_public_suffix("www.ck"));
+	}
+}
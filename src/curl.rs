@@ -0,0 +1,838 @@
+//! Import of curl command lines, as found in API documentation or copied
+//! from the network tab of a browser.
+//!
+//! The command is split into words the way a POSIX shell does, including
+//! single, double and `$'...'` quotes and backslash line continuations,
+//! then the common curl options are mapped onto [`Request`]s. URLs are
+//! expanded like curl's globbing, so `/items/[1-3]` yields three requests.
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::cookie::{netscape, CookieJar};
+use crate::error::{Error, ParseError, Result};
+use crate::http::{parse_header_line, Request};
+use crate::multipart::{Multipart, Part};
+use crate::upload::Upload;
+use crate::url::{has_scheme, percent_encode, Url};
+use crate::util::{base64_encode, unix_time};
+
+/// Maximum number of URLs a glob may expand to.
+const MAX_GLOB: usize = 1000;
+
+/// Requests of a curl command with the options that concern the client.
+#[derive(Clone, Debug, Default)]
+pub struct CurlCommand {
+	/// One request per URL after globbing.
+	pub requests: Vec<Request>,
+	/// `-k`: skip certificate verification.
+	pub insecure: bool,
+	/// `--compressed`: ask for a compressed response.
+	pub compressed: bool,
+	/// `-L`: follow redirects.
+	pub follow_redirects: bool,
+	/// `--max-redirs`: redirects followed before giving up.
+	pub max_redirects: Option<usize>,
+	/// `-s`: no upload progress.
+	pub silent: bool,
+	/// `-m`: limit for the whole transfer.
+	pub timeout: Option<Duration>,
+	/// Jar of the cookie engine, enabled by `-b FILE` which reads the
+	/// cookies of a file, or `-c FILE` which saves them to the jar path.
+	pub cookies: Option<CookieJar>,
+}
+
+/// Parses a single curl command.
+pub fn parse(text: &str) -> Result<CurlCommand> {
+	let mut commands = split_commands(text).map_err(Error::Parse)?;
+	match commands.len() {
+		1 => from_args(&commands.remove(0).1),
+		0 => Err(Error::Usage("empty curl command".into())),
+		_ => Err(Error::Usage("expected a single curl command".into())),
+	}
+}
+
+/// Parses the curl commands of a file, one per line unless continued with
+/// a backslash. Lines starting with `#` are ignored.
+pub fn parse_file(source: &str, text: &str) -> Result<Vec<CurlCommand>> {
+	let commands = split_commands(text).map_err(|err| Error::Parse(err.in_source(source)))?;
+	commands
+		.into_iter()
+		.map(|(line, words)| {
+			from_args(&words).map_err(|err| match err {
+				Error::Usage(message) => {
+					Error::Parse(ParseError::new(line, 1, message).in_source(source))
+				}
+				err => err,
+			})
+		})
+		.collect()
+}
+
+/// Maps curl arguments onto requests. A leading `curl` is skipped.
+pub fn from_args(args: &[Vec<u8>]) -> Result<CurlCommand> {
+	let args = match args.first() {
+		Some(first) if first == b"curl" => &args[1..],
+		_ => args,
+	};
+	let mut options = Options::default();
+	let mut command = CurlCommand::default();
+	let mut index = 0;
+	while index < args.len() {
+		let arg = &args[index];
+		index += 1;
+		let mut value = |inline: &[u8]| -> Result<Vec<u8>> {
+			if !inline.is_empty() {
+				return Ok(inline.to_vec());
+			}
+			let value = args
+				.get(index)
+				.cloned()
+				.ok_or_else(|| Error::Usage(format!("option `{}` requires a value", text(arg))))?;
+			index += 1;
+			Ok(value)
+		};
+		if arg.starts_with(b"--") {
+			let flag = text(arg);
+			if takes_value(&flag) {
+				let value = value(b"")?;
+				options.apply(&flag, Some(value), &mut command)?;
+			} else {
+				options.apply(&flag, None, &mut command)?;
+			}
+		} else if arg.len() > 1 && arg[0] == b'-' {
+			// short options may be combined, as in `-sSL`, and take their
+			// value from the rest of the word, as in `-XPOST`
+			for (position, &letter) in arg.iter().enumerate().skip(1) {
+				let flag = format!("-{}", letter as char);
+				if takes_value(&flag) {
+					let value = value(&arg[position + 1..])?;
+					options.apply(&flag, Some(value), &mut command)?;
+					break;
+				}
+				options.apply(&flag, None, &mut command)?;
+			}
+		} else {
+			options.urls.push(text(arg));
+		}
+	}
+	command.requests = options.build()?;
+	if let Some(jar) = &command.cookies {
+		for request in &mut command.requests {
+			jar.add_to(request, unix_time());
+		}
+	}
+	Ok(command)
+}
+
+/// Options collected before the requests are built.
+#[derive(Default)]
+struct Options {
+	urls: Vec<String>,
+	method: Option<String>,
+	headers: Vec<(String, String)>,
+	/// `-d` values, joined with `&`.
+	data: Vec<Vec<u8>>,
+	form: Vec<Part>,
+	upload: Option<Upload>,
+	cookies: Vec<String>,
+	/// `-u` value, sent with Basic, Digest or AWS authentication.
+	user: Option<Vec<u8>>,
+	digest: bool,
+	/// `--aws-sigv4 aws:amz[:REGION[:SERVICE]]`.
+	aws_sigv4: Option<String>,
+	get: bool,
+	head: bool,
+	globoff: bool,
+}
+
+fn takes_value(flag: &str) -> bool {
+	matches!(
+		flag,
+		"-X" | "--request"
+			| "-H" | "--header"
+			| "-d" | "--data"
+			| "--data-ascii"
+			| "--data-raw"
+			| "--data-binary"
+			| "--data-urlencode"
+			| "-F" | "--form"
+			| "--form-string"
+			| "-u" | "--user"
+			| "--oauth2-bearer"
+			| "--aws-sigv4"
+			| "-b" | "--cookie"
+			| "-A" | "--user-agent"
+			| "-e" | "--referer"
+			| "-T" | "--upload-file"
+			| "--url" | "-m"
+			| "--max-time"
+			| "--connect-timeout"
+			| "-o" | "--output"
+			| "-w" | "--write-out"
+			| "-c" | "--cookie-jar"
+			| "--retry"
+			| "--max-redirs"
+	)
+}
+
+impl Options {
+	fn apply(
+		&mut self,
+		flag: &str,
+		value: Option<Vec<u8>>,
+		command: &mut CurlCommand,
+	) -> Result<()> {
+		let value = value.unwrap_or_default();
+		match flag {
+			"-X" | "--request" => self.method = Some(text(&value).to_ascii_uppercase()),
+			"-H" | "--header" => {
+				let line = text(&value);
+				// `Name;` sends an empty header, `Name:` removes one curl
+				// would add by itself
+				if let Some(name) = line.strip_suffix(';').filter(|name| !name.contains(':')) {
+					self.headers.push((name.trim().to_string(), String::new()));
+				} else if let Some(header) = parse_header_line(&line) {
+					if !header.1.is_empty() {
+						self.headers.push(header);
+					}
+				} else {
+					return Err(Error::Usage(format!("invalid header `{line}`")));
+				}
+			}
+			"-d" | "--data" | "--data-ascii" => {
+				let mut data = read_at(&value)?;
+				if value.starts_with(b"@") {
+					data.retain(|&byte| byte != b'\r' && byte != b'\n');
+				}
+				self.data.push(data);
+			}
+			"--data-raw" => self.data.push(value),
+			"--data-binary" => self.data.push(read_at(&value)?),
+			"--data-urlencode" => self.data.push(urlencode_data(&value)?),
+			"-F" | "--form" => self.form.push(Part::parse(&text(&value))?),
+			"--form-string" => self.form.push(form_string(&text(&value))?),
+			"-u" | "--user" => self.user = Some(value),
+			"--basic" => self.digest = false,
+			"--digest" => self.digest = true,
+			"--aws-sigv4" => self.aws_sigv4 = Some(text(&value)),
+			"--oauth2-bearer" => self
+				.headers
+				.push(("Authorization".into(), format!("Bearer {}", text(&value)))),
+			"-b" | "--cookie" => {
+				let cookie = text(&value);
+				if cookie.contains('=') {
+					self.cookies.push(cookie);
+				} else {
+					// like curl, a missing file only enables the cookie engine
+					let jar = command.cookies.get_or_insert_with(CookieJar::new);
+					if Path::new(&cookie).exists() {
+						for cookie in netscape::load(Path::new(&cookie))?.cookies() {
+							jar.insert(cookie.clone());
+						}
+					}
+				}
+			}
+			"-c" | "--cookie-jar" => {
+				let jar = command.cookies.get_or_insert_with(CookieJar::new);
+				jar.path = Some(text(&value).into());
+			}
+			"-A" | "--user-agent" => self.headers.push(("User-Agent".into(), text(&value))),
+			"-e" | "--referer" => self.headers.push(("Referer".into(), text(&value))),
+			"-T" | "--upload-file" => {
+				let mut upload = Upload::new();
+				match text(&value).as_str() {
+					"-" => upload.push_bytes(&crate::cli::read_body_arg("@-")?),
+					path => upload
+						.push_file(path)
+						.map_err(|err| Error::Usage(format!("cannot read `{path}`: {err}")))?,
+				}
+				self.upload = Some(upload);
+			}
+			"--url" => self.urls.push(text(&value)),
+			"-m" | "--max-time" => {
+				let secs: f64 = text(&value)
+					.parse()
+					.map_err(|_| Error::Usage(format!("invalid time `{}`", text(&value))))?;
+				command.timeout = (secs > 0.0).then(|| Duration::from_secs_f64(secs));
+			}
+			"-G" | "--get" => self.get = true,
+			"-I" | "--head" => self.head = true,
+			"-g" | "--globoff" => self.globoff = true,
+			"-k" | "--insecure" => command.insecure = true,
+			"-L" | "--location" => command.follow_redirects = true,
+			"--max-redirs" => {
+				let max = text(&value);
+				command.max_redirects = Some(
+					max.parse()
+						.map_err(|_| Error::Usage(format!("invalid `--max-redirs {max}`")))?,
+				);
+			}
+			"--compressed" => command.compressed = true,
+			"-s" | "--silent" => command.silent = true,
+			// options about curl's own output and behavior
+			"--connect-timeout" | "-o" | "--output" | "-w" | "--write-out" | "--retry" | "-S"
+			| "--show-error" | "-v" | "--verbose" | "-i" | "--include" | "-f" | "--fail"
+			| "--fail-with-body" | "-N" | "--no-buffer" | "-#" | "--progress-bar" | "-O"
+			| "--remote-name" => {}
+			_ => return Err(Error::Usage(format!("unsupported curl option `{flag}`"))),
+		}
+		Ok(())
+	}
+
+	/// `Authorization` header for `-u`, naming the scheme for Digest and
+	/// AWS authentication which are computed when sending.
+	fn authorization(&self) -> Option<String> {
+		let user = self.user.as_ref()?;
+		if let Some(provider) = &self.aws_sigv4 {
+			let text = text(user);
+			let (access_key, secret_key) = text.split_once(':').unwrap_or((&text, ""));
+			let mut value = format!("AWS access_key={access_key} secret_key={secret_key}");
+			let mut scope = provider.split(':').skip(2);
+			if let Some(region) = scope.next() {
+				value.push_str(&format!(" region={region}"));
+			}
+			if let Some(service) = scope.next() {
+				value.push_str(&format!(" service={service}"));
+			}
+			return Some(value);
+		}
+		Some(match self.digest {
+			true => format!("Digest {}", text(user)),
+			false => format!("Basic {}", base64_encode(user)),
+		})
+	}
+
+	fn build(self) -> Result<Vec<Request>> {
+		if self.urls.is_empty() {
+			return Err(Error::Usage("missing URL in curl command".into()));
+		}
+		let mut urls = Vec::new();
+		for url in &self.urls {
+			match self.globoff {
+				true => urls.push(url.clone()),
+				false => urls.extend(expand_glob(url).map_err(Error::Usage)?),
+			}
+		}
+
+		let data = self.data.join(&b'&');
+		let mut requests = Vec::new();
+		for mut url in urls {
+			if !has_scheme(&url) {
+				url.insert_str(0, "http://");
+			}
+			if self.get && !self.data.is_empty() {
+				url.push(if url.contains('?') { '&' } else { '?' });
+				url.push_str(&text(&data));
+			}
+			let mut request = Request::new("GET", Url::parse(&url)?);
+			for (name, value) in &self.headers {
+				request.headers.append(name, value);
+			}
+			if !self.cookies.is_empty() {
+				request.headers.append("Cookie", self.cookies.join("; "));
+			}
+			if let Some(value) = self.authorization() {
+				request.headers.set("Authorization", value);
+			}
+
+			let mut method = "GET";
+			if !self.form.is_empty() {
+				let form = Multipart {
+					parts: self.form.clone(),
+					..Multipart::new()
+				};
+				form.apply(&mut request)?;
+				method = "POST";
+			} else if let Some(upload) = &self.upload {
+				request.set_upload(upload.clone());
+				method = "PUT";
+			} else if !self.data.is_empty() && !self.get {
+				if !request.headers.contains("Content-Type") {
+					request
+						.headers
+						.append("Content-Type", "application/x-www-form-urlencoded");
+				}
+				request.body = data.clone();
+				method = "POST";
+			}
+			if self.head {
+				method = "HEAD";
+			}
+			request.method = self.method.clone().unwrap_or_else(|| method.into());
+			requests.push(request);
+		}
+		Ok(requests)
+	}
+}
+
+/// Reads `@path` arguments from a file, `@-` from standard input.
+fn read_at(value: &[u8]) -> Result<Vec<u8>> {
+	match value.strip_prefix(b"@") {
+		Some(_) => crate::cli::read_body_arg(&text(value)),
+		None => Ok(value.to_vec()),
+	}
+}
+
+/// `--data-urlencode` in its `content`, `=content`, `name=content`,
+/// `@file` and `name@file` forms.
+fn urlencode_data(value: &[u8]) -> Result<Vec<u8>> {
+	let value = text(value);
+	let (name, content) = match value.find(['=', '@']) {
+		Some(at) if value[at..].starts_with('@') => {
+			let content = read_at(&value.as_bytes()[at..])?;
+			(&value[..at], String::from_utf8_lossy(&content).into_owned())
+		}
+		Some(at) => (&value[..at], value[at + 1..].to_string()),
+		None => ("", value.clone()),
+	};
+	let encoded = percent_encode(&content);
+	Ok(match name {
+		"" => encoded.into_bytes(),
+		name => format!("{name}={encoded}").into_bytes(),
+	})
+}
+
+/// `--form-string name=value`, taking the value literally.
+fn form_string(spec: &str) -> Result<Part> {
+	let (name, value) = spec
+		.split_once('=')
+		.ok_or_else(|| Error::Usage(format!("invalid form field `{spec}`, expected NAME=VALUE")))?;
+	Ok(Part::text(name, value))
+}
+
+/// Expands `{a,b}` alternatives and `[1-10]`, `[001-100:5]` or `[a-z]`
+/// ranges into every combination, in order.
+pub fn expand_glob(url: &str) -> std::result::Result<Vec<String>, String> {
+	let mut results = vec![String::new()];
+	let mut rest = url;
+	while let Some(start) = rest.find(['{', '[']) {
+		let close = if rest[start..].starts_with('{') {
+			'}'
+		} else {
+			']'
+		};
+		let Some(end) = rest[start..].find(close).map(|end| start + end) else {
+			return Err(format!(
+				"unmatched `{}` in `{url}`",
+				&rest[start..start + 1]
+			));
+		};
+		let inner = &rest[start + 1..end];
+		let choices: Vec<String> = match close {
+			'}' => inner.split(',').map(str::to_string).collect(),
+			// not a range, e.g. an IPv6 address
+			_ => range(inner, url)?.unwrap_or_else(|| vec![format!("[{inner}]")]),
+		};
+		if results.len().saturating_mul(choices.len()) > MAX_GLOB {
+			return Err(too_many(url));
+		}
+		let literal = &rest[..start];
+		results = results
+			.iter()
+			.flat_map(|prefix| {
+				choices
+					.iter()
+					.map(move |choice| format!("{prefix}{literal}{choice}"))
+			})
+			.collect();
+		rest = &rest[end + 1..];
+	}
+	for result in &mut results {
+		result.push_str(rest);
+	}
+	Ok(results)
+}
+
+fn too_many(url: &str) -> String {
+	format!("`{url}` expands to more than {MAX_GLOB} URLs")
+}
+
+/// Values of a `[first-last:step]` range, `None` when the text is not a
+/// range. The values are counted before they are built, so that a huge
+/// range fails without being allocated.
+fn range(spec: &str, url: &str) -> std::result::Result<Option<Vec<String>>, String> {
+	let (bounds, step) = match spec.split_once(':') {
+		Some((bounds, step)) => match step.parse::<u64>().ok().filter(|&s| s > 0) {
+			Some(step) => (bounds, step),
+			None => return Ok(None),
+		},
+		None => (spec, 1),
+	};
+	let Some((first, last)) = bounds.split_once('-') else {
+		return Ok(None);
+	};
+	let (start, end, letters) = match (first.parse::<u64>(), last.parse::<u64>()) {
+		(Ok(start), Ok(end)) => (start, end, false),
+		_ => match (first.as_bytes(), last.as_bytes()) {
+			([start], [end]) if start.is_ascii_alphabetic() && end.is_ascii_alphabetic() => {
+				(u64::from(*start), u64::from(*end), true)
+			}
+			_ => return Ok(None),
+		},
+	};
+	if start > end {
+		return Err(format!("reversed range `[{spec}]` in `{url}`"));
+	}
+	if (end - start) / step + 1 > MAX_GLOB as u64 {
+		return Err(too_many(url));
+	}
+	// a leading zero pads all numbers to the width of the first
+	let width = match first.starts_with('0') {
+		true => first.len(),
+		false => 0,
+	};
+	let values = (start..=end).step_by(usize::try_from(step).unwrap_or(usize::MAX));
+	Ok(Some(match letters {
+		true => values.map(|c| char::from(c as u8).to_string()).collect(),
+		false => values.map(|n| format!("{n:0width$}")).collect(),
+	}))
+}
+
+fn text(bytes: &[u8]) -> String {
+	String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Line a command starts on with its words, which may hold any bytes
+/// through `$'\xNN'`.
+type Words = (usize, Vec<Vec<u8>>);
+
+/// Splits text into commands of shell words. A newline outside of quotes
+/// that is not escaped ends a command.
+fn split_commands(text: &str) -> std::result::Result<Vec<Words>, ParseError> {
+	let mut commands = Vec::new();
+	let mut words: Vec<Vec<u8>> = Vec::new();
+	let mut word: Option<Vec<u8>> = None;
+	let mut start_line = 1;
+	let (mut line, mut column) = (1, 0);
+	let mut chars = text.chars().peekable();
+
+	// advances the position over a character
+	macro_rules! next {
+		() => {
+			chars.next().inspect(|&c| {
+				if c == '\n' {
+					line += 1;
+					column = 0;
+				} else {
+					column += 1;
+				}
+			})
+		};
+	}
+
+	while let Some(c) = next!() {
+		match c {
+			' ' | '\t' | '\r' => words.extend(word.take()),
+			'\n' => {
+				words.extend(word.take());
+				if !words.is_empty() {
+					commands.push((start_line, std::mem::take(&mut words)));
+				}
+				start_line = line;
+			}
+			'#' if word.is_none() => {
+				while chars.peek().is_some_and(|&c| c != '\n') {
+					next!();
+				}
+			}
+			'\\' => match next!() {
+				// line continuation
+				Some('\n') => {}
+				Some('\r') if chars.peek() == Some(&'\n') => {
+					next!();
+				}
+				Some(c) => push_char(word.get_or_insert_with(Vec::new), c),
+				None => {}
+			},
+			'\'' => {
+				let (quote_line, quote_column) = (line, column);
+				let word = word.get_or_insert_with(Vec::new);
+				loop {
+					match next!() {
+						Some('\'') => break,
+						Some(c) => push_char(word, c),
+						None => return Err(unterminated(quote_line, quote_column)),
+					}
+				}
+			}
+			'"' => {
+				let (quote_line, quote_column) = (line, column);
+				let word = word.get_or_insert_with(Vec::new);
+				loop {
+					match next!() {
+						Some('"') => break,
+						Some('\\') => match next!() {
+							Some('\n') => {}
+							Some(c @ ('$' | '`' | '"' | '\\')) => push_char(word, c),
+							Some(c) => {
+								word.push(b'\\');
+								push_char(word, c);
+							}
+							None => return Err(unterminated(quote_line, quote_column)),
+						},
+						Some(c) => push_char(word, c),
+						None => return Err(unterminated(quote_line, quote_column)),
+					}
+				}
+			}
+			'$' if chars.peek() == Some(&'\'') => {
+				let (quote_line, quote_column) = (line, column);
+				next!();
+				let word = word.get_or_insert_with(Vec::new);
+				loop {
+					match next!() {
+						Some('\'') => break,
+						Some('\\') => {
+							let Some(escape) = next!() else {
+								return Err(unterminated(quote_line, quote_column));
+							};
+							let mut digits = |radix: u32, max: usize, mut value: u32| {
+								for _ in 0..max {
+									match chars.peek().and_then(|c| c.to_digit(radix)) {
+										Some(digit) => {
+											value = value * radix + digit;
+											next!();
+										}
+										None => break,
+									}
+								}
+								value
+							};
+							match escape {
+								'n' => word.push(b'\n'),
+								't' => word.push(b'\t'),
+								'r' => word.push(b'\r'),
+								'a' => word.push(0x07),
+								'b' => word.push(0x08),
+								'e' | 'E' => word.push(0x1b),
+								'f' => word.push(0x0c),
+								'v' => word.push(0x0b),
+								'x' => word.push(digits(16, 2, 0) as u8),
+								'0'..='7' => {
+									let first = escape.to_digit(8).unwrap_or(0);
+									word.push(digits(8, 2, first) as u8);
+								}
+								'u' | 'U' => {
+									let max = if escape == 'u' { 4 } else { 8 };
+									let c =
+										char::from_u32(digits(16, max, 0)).unwrap_or('\u{fffd}');
+									push_char(word, c);
+								}
+								c => push_char(word, c),
+							}
+						}
+						Some(c) => push_char(word, c),
+						None => return Err(unterminated(quote_line, quote_column)),
+					}
+				}
+			}
+			c => push_char(word.get_or_insert_with(Vec::new), c),
+		}
+	}
+	words.extend(word);
+	if !words.is_empty() {
+		commands.push((start_line, words));
+	}
+	Ok(commands)
+}
+
+fn push_char(word: &mut Vec<u8>, c: char) {
+	let mut buffer = [0; 4];
+	word.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+}
+
+fn unterminated(line: usize, column: usize) -> ParseError {
+	ParseError::new(line, column, "unterminated quote")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn words(text: &str) -> Vec<String> {
+		let mut commands = split_commands(text).unwrap();
+		assert_eq!(commands.len(), 1);
+		commands
+			.remove(0)
+			.1
+			.iter()
+			.map(|word| self::text(word))
+			.collect()
+	}
+
+	#[test]
+	fn splits_shell_words() {
+		assert_eq!(
+			words("curl 'a b' \"c \\\"d\\\" \\$e\" f\\ g \\\n  $'h\\ti\\x41\\'' \"\" x\"y\"z"),
+			["curl", "a b", "c \"d\" $e", "f g", "h\tiA'", "", "xyz"]
+		);
+		let commands = split_commands("# comment\ncurl a\n\ncurl \\\n b\ncurl c # note\n").unwrap();
+		assert_eq!(
+			commands,
+			[
+				(2, vec![b"curl".to_vec(), b"a".to_vec()]),
+				(4, vec![b"curl".to_vec(), b"b".to_vec()]),
+				(6, vec![b"curl".to_vec(), b"c".to_vec()]),
+			]
+		);
+		assert_eq!(
+			split_commands("curl $'\\xff\\101'").unwrap()[0].1[1],
+			[0xff, b'A']
+		);
+
+		let err = split_commands("curl\n  'open").unwrap_err();
+		assert_eq!(
+			(err.line, err.column, err.message.as_str()),
+			(2, 3, "unterminated quote")
+		);
+	}
+
+	#[test]
+	fn maps_browser_copy() {
+		let command = parse(
+			"curl 'https://api.example.com/items?page=1' \\\n\
+			 -H 'accept: application/json' \\\n\
+			 -H 'content-type: application/json' \\\n\
+			 -b 'session=abc; theme=dark' \\\n\
+			 --data-raw '{\"name\":\"widget\"}' \\\n\
+			 --compressed -k",
+		)
+		.unwrap();
+		assert!(command.insecure && command.compressed && !command.follow_redirects);
+		let [request] = command.requests.as_slice() else {
+			panic!("expected one request");
+		};
+		assert_eq!(request.method, "POST");
+		assert_eq!(
+			request.url.to_string(),
+			"https://api.example.com/items?page=1"
+		);
+		assert_eq!(
+			request.headers.iter().collect::<Vec<_>>(),
+			[
+				("accept", "application/json"),
+				("content-type", "application/json"),
+				("Cookie", "session=abc; theme=dark"),
+			]
+		);
+		assert_eq!(request.body, br#"{"name":"widget"}"#);
+		assert!(command.cookies.is_none());
+
+		// a missing cookie file enables the cookie engine, `-c` saves it
+		let command = parse("curl -b /nonexistent/cookies.txt -c jar.txt http://h/").unwrap();
+		let jar = command.cookies.unwrap();
+		assert!(jar.is_empty());
+		assert_eq!(jar.path.as_deref(), Some(Path::new("jar.txt")));
+	}
+
+	#[test]
+	fn maps_options() {
+		let command =
+			parse("curl -sSL --max-redirs 3 -XPATCH -u jo:pw -d a=1 -d b=2 example.com/x").unwrap();
+		assert!(command.follow_redirects);
+		assert_eq!(command.max_redirects, Some(3));
+		let request = &command.requests[0];
+		assert_eq!(request.method, "PATCH");
+		assert_eq!(request.url.to_string(), "http://example.com/x");
+		assert_eq!(request.headers.get("Authorization"), Some("Basic am86cHc="));
+		let digest = parse("curl --digest -u jo:pw http://h/").unwrap();
+		assert_eq!(
+			digest.requests[0].headers.get("Authorization"),
+			Some("Digest jo:pw")
+		);
+		let aws = parse("curl --aws-sigv4 aws:amz:eu-west-1:s3 -u AK:SK http://h/").unwrap();
+		assert_eq!(
+			aws.requests[0].headers.get("Authorization"),
+			Some("AWS access_key=AK secret_key=SK region=eu-west-1 service=s3")
+		);
+		assert_eq!(
+			request.headers.get("Content-Type"),
+			Some("application/x-www-form-urlencoded")
+		);
+		assert_eq!(request.body, b"a=1&b=2");
+
+		let command = parse("curl -G http://h/s --data-urlencode 'q=a b&c' -d n=5").unwrap();
+		let request = &command.requests[0];
+		assert_eq!(request.method, "GET");
+		assert_eq!(request.url.to_string(), "http://h/s?q=a%20b%26c&n=5");
+		assert!(request.body.is_empty());
+
+		let command = parse("curl -F 'title=hi;type=text/plain' http://h/upload").unwrap();
+		let request = &command.requests[0];
+		assert_eq!(request.method, "POST");
+		let content_type = request.headers.get("Content-Type").unwrap();
+		assert!(content_type.starts_with("multipart/form-data; boundary="));
+		let body = String::from_utf8(request.body.clone()).unwrap();
+		assert!(body.contains("name=\"title\"\r\nContent-Type: text/plain\r\n\r\nhi\r\n"));
+
+		// the boundary of a given `Content-Type` is the one used
+		let command = parse(
+			"curl -H 'Content-Type: multipart/form-data; boundary=b1' -F 'f=@Cargo.toml' http://h/",
+		)
+		.unwrap();
+		let upload = command.requests[0].upload.as_ref().unwrap();
+		let body = String::from_utf8(upload.read_all().unwrap()).unwrap();
+		assert!(body.starts_with(
+			"--b1\r\nContent-Disposition: form-data; name=\"f\"; filename=\"Cargo.toml\""
+		));
+		assert!(body.ends_with("\r\n--b1--\r\n"));
+
+		assert_eq!(
+			parse("curl -I http://h/").unwrap().requests[0].method,
+			"HEAD"
+		);
+		let err = parse("curl --proxy http://p http://h/").unwrap_err();
+		assert_eq!(err.to_string(), "unsupported curl option `--proxy`");
+		assert!(parse("curl -H").is_err());
+	}
+
+	#[test]
+	fn expands_globs() {
+		assert_eq!(
+			expand_glob("http://h/{a,b}/[1-2]").unwrap(),
+			[
+				"http://h/a/1",
+				"http://h/a/2",
+				"http://h/b/1",
+				"http://h/b/2"
+			]
+		);
+		assert_eq!(
+			expand_glob("http://h/[08-12:2]").unwrap(),
+			["http://h/08", "http://h/10", "http://h/12"]
+		);
+		assert_eq!(expand_glob("http://h/[a-c]").unwrap().len(), 3);
+		assert_eq!(
+			expand_glob("http://[::1]:8080/").unwrap(),
+			["http://[::1]:8080/"]
+		);
+		assert!(expand_glob("http://h/{a").is_err());
+		assert!(expand_glob("http://h/[1-100]/[1-100]").is_err());
+		assert_eq!(
+			expand_glob("http://h/[1-1000000000]").unwrap_err(),
+			"`http://h/[1-1000000000]` expands to more than 1000 URLs"
+		);
+		assert_eq!(
+			expand_glob("http://h/[1-1000000000:1000000]")
+				.unwrap()
+				.len(),
+			1000
+		);
+		assert_eq!(
+			expand_glob("http://h/[5-1]").unwrap_err(),
+			"reversed range `[5-1]` in `http://h/[5-1]`"
+		);
+		assert!(expand_glob("http://h/[z-a]").is_err());
+
+		let command = parse("curl -g 'http://h/{a,b}'").unwrap();
+		assert_eq!(command.requests.len(), 1);
+
+		// a URL in the query is not the scheme of the URL
+		let command = parse("curl 'example.com/cb?next=http://x'").unwrap();
+		assert_eq!(
+			command.requests[0].url.to_string(),
+			"http://example.com/cb?next=http://x"
+		);
+	}
+}
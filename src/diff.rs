@@ -0,0 +1,52 @@
+//! Line based diff used to report mismatches.
+
+/// Maximum number of lines compared; longer texts are truncated.
+const MAX_LINES: usize = 2000;
+
+/// Returns a unified-style diff from `expected` to `actual`, where removed
+/// lines start with `-`, added lines with `+` and common lines with a space.
+pub fn diff_lines(expected: &str, actual: &str) -> String {
+	let old: Vec<&str> = expected.lines().take(MAX_LINES).collect();
+	let new: Vec<&str> = actual.lines().take(MAX_LINES).collect();
+
+	// longest common subsequence lengths of the suffixes
+	let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+	for i in (0..old.len()).rev() {
+		for j in (0..new.len()).rev() {
+			lcs[i][j] = if old[i] == new[j] {
+				lcs[i + 1][j + 1] + 1
+			} else {
+				lcs[i + 1][j].max(lcs[i][j + 1])
+			};
+		}
+	}
+
+	let mut output = String::new();
+	let (mut i, mut j) = (0, 0);
+	while i < old.len() || j < new.len() {
+		if i < old.len() && j < new.len() && old[i] == new[j] {
+			output.push_str(&format!("  {}\n", old[i]));
+			i += 1;
+			j += 1;
+		} else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+			output.push_str(&format!("- {}\n", old[i]));
+			i += 1;
+		} else {
+			output.push_str(&format!("+ {}\n", new[j]));
+			j += 1;
+		}
+	}
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn diffs_lines() {
+		let diff = diff_lines("a\nb\nc\n", "a\nx\nc\nd\n");
+		assert_eq!(diff, "  a\n- b\n+ x\n  c\n+ d\n");
+		assert_eq!(diff_lines("same", "same"), "  same\n");
+	}
+}
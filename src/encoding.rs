@@ -0,0 +1,122 @@
+//! Content and transfer codings of bodies (RFC 9110, 8.4): gzip, deflate,
+//! brotli and zstd.
+
+use std::io::{self, Read};
+
+use crate::error::{Error, Result};
+use crate::http::Response;
+
+/// Codings requested with `Accept-Encoding` by clients that decode
+/// responses.
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br, zstd";
+
+/// Removes the codings listed in a `Content-Encoding` or
+/// `Transfer-Encoding` value, the last one applied first.
+pub fn decode(body: &[u8], codings: &str) -> Result<Vec<u8>> {
+	let mut body = body.to_vec();
+	for coding in codings.rsplit(',') {
+		let coding = coding.trim().to_ascii_lowercase();
+		if coding.is_empty() || coding == "identity" {
+			continue;
+		}
+		body = decode_one(&body, &coding).map_err(|err| match err.kind() {
+			io::ErrorKind::Unsupported => Error::Protocol(format!("unsupported coding `{coding}`")),
+			_ => Error::Protocol(format!("invalid {coding} body: {err}")),
+		})?;
+	}
+	Ok(body)
+}
+
+/// Decodes the body of a response by its `Content-Encoding`. The header is
+/// kept, to show what the server sent.
+pub fn decode_response(response: &mut Response) -> Result<()> {
+	if let Some(codings) = response.headers.get("Content-Encoding") {
+		if !response.body.is_empty() {
+			response.body = decode(&response.body, codings)?;
+		}
+	}
+	Ok(())
+}
+
+fn decode_one(body: &[u8], coding: &str) -> io::Result<Vec<u8>> {
+	let mut out = Vec::new();
+	match coding {
+		"gzip" | "x-gzip" => {
+			flate2::read::MultiGzDecoder::new(body).read_to_end(&mut out)?;
+		}
+		// zlib framed as specified, though some servers send raw deflate
+		"deflate" if is_zlib(body) => {
+			flate2::read::ZlibDecoder::new(body).read_to_end(&mut out)?;
+		}
+		"deflate" => {
+			flate2::read::DeflateDecoder::new(body).read_to_end(&mut out)?;
+		}
+		"br" => {
+			brotli_decompressor::Decompressor::new(body, 4096).read_to_end(&mut out)?;
+		}
+		"zstd" => {
+			let mut rest = body;
+			// a body may hold several frames
+			while !rest.is_empty() {
+				ruzstd::decoding::StreamingDecoder::new(&mut rest)
+					.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+					.read_to_end(&mut out)?;
+			}
+		}
+		_ => return Err(io::ErrorKind::Unsupported.into()),
+	}
+	Ok(out)
+}
+
+/// Tells a zlib header (RFC 1950) from raw deflate data.
+fn is_zlib(data: &[u8]) -> bool {
+	match data {
+		[cmf, flg, ..] => cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0,
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Write;
+
+	fn gzip(data: &[u8]) -> Vec<u8> {
+		let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+		encoder.write_all(data).unwrap();
+		encoder.finish().unwrap()
+	}
+
+	#[test]
+	fn decodes_codings() {
+		assert_eq!(decode(&gzip(b"hello"), "gzip").unwrap(), b"hello");
+		assert_eq!(
+			decode(&gzip(&gzip(b"twice")), "gzip, x-gzip").unwrap(),
+			b"twice"
+		);
+		assert_eq!(decode(b"as is", " identity ").unwrap(), b"as is");
+
+		let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::fast());
+		zlib.write_all(b"zlib").unwrap();
+		assert_eq!(decode(&zlib.finish().unwrap(), "deflate").unwrap(), b"zlib");
+		let mut raw = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+		raw.write_all(b"raw").unwrap();
+		assert_eq!(decode(&raw.finish().unwrap(), "deflate").unwrap(), b"raw");
+
+		// `brotli -c` and `zstd -c` of "hello\n"
+		let br = b"\x8b\x02\x80\x68\x65\x6c\x6c\x6f\x0a\x03";
+		assert_eq!(decode(br, "br").unwrap(), b"hello\n");
+		let zstd = b"\x28\xb5\x2f\xfd\x04\x58\x31\x00\x00\x68\x65\x6c\x6c\x6f\x0a\x53\x88\xbd\x91";
+		assert_eq!(decode(zstd, "zstd").unwrap(), b"hello\n");
+
+		let err = decode(b"plain", "gzip").unwrap_err();
+		assert!(err
+			.to_string()
+			.starts_with("protocol error: invalid gzip body"));
+		let err = decode(b"x", "compress").unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"protocol error: unsupported coding `compress`"
+		);
+	}
+}
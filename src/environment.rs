@@ -0,0 +1,238 @@
+//! Named sets of variables such as `dev` or `staging`, kept in an
+//! environment file next to the request files:
+//!
+//! ```json
+//! {
+//!   "$shared": {"version": "v1"},
+//!   "dev": {"host": "localhost:8080"},
+//!   "staging": {"host": "staging.example.com", "token": "{{$processEnv STAGING_TOKEN}}"}
+//! }
+//! ```
+//!
+//! The file is `webcat.env.json`, or `http-client.env.json` as used by the
+//! JetBrains HTTP client, searched from the directory of the request file
+//! upwards. Secrets go in the private file next to it (`webcat.env.private.json`
+//! or `http-client.private.env.json`), which is kept out of version control,
+//! or are read from the process environment with `$processEnv`. Values of
+//! the private file override the shared file, and values of the environment
+//! override `$shared`.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::error::{Error, ParseError, Result};
+use crate::template::{Template, Variables};
+
+/// Environment file names, in order of preference.
+pub const FILE_NAMES: [&str; 2] = ["webcat.env.json", "http-client.env.json"];
+
+/// Environment holding the variables common to all environments.
+const SHARED: &str = "$shared";
+
+/// Variables of one environment, with `$shared` merged in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Environment {
+	pub name: String,
+	/// Environment file the variables come from.
+	pub source: PathBuf,
+	pub variables: Vec<(String, Template)>,
+}
+
+impl Environment {
+	/// Loads the environment `name` from `path` and the private file next to
+	/// it.
+	pub fn load(path: &Path, name: &str) -> Result<Environment> {
+		let public = read_file(path)?;
+		let private_file = private_path(path);
+		let private = match private_file.exists() {
+			true => Some(read_file(&private_file)?),
+			false => None,
+		};
+
+		let files = std::iter::once(&public).chain(&private);
+		if !files.clone().any(|file| file.contains_key(name)) || name == SHARED {
+			let mut names: Vec<&str> = Vec::new();
+			for key in files.flat_map(|file| file.keys()) {
+				if key != SHARED && !names.contains(&key.as_str()) {
+					names.push(key);
+				}
+			}
+			return Err(Error::Usage(format!(
+				"no environment `{name}` in {}, expected one of: {}",
+				path.display(),
+				names.join(", ")
+			)));
+		}
+
+		let mut variables: Vec<(String, Template)> = Vec::new();
+		for section in [SHARED, name] {
+			for (file, source) in [(Some(&public), path), (private.as_ref(), &private_file)] {
+				let Some(values) = file.and_then(|file| file.get(section)) else {
+					continue;
+				};
+				let context = format!("{}: environment `{section}`", source.display());
+				let values = values
+					.as_object()
+					.ok_or_else(|| Error::Usage(format!("{context} is not an object")))?;
+				for (key, value) in values {
+					let template = variable(value)
+						.ok_or_else(|| {
+							Error::Usage(format!("{context}: `{key}` must be a string or number"))
+						})
+						.and_then(|text| {
+							Template::parse(&text, 1, 1).map_err(|err| error(&context, key, err))
+						})?;
+					variables.retain(|(name, _)| name != key);
+					variables.push((key.clone(), template));
+				}
+			}
+		}
+		Ok(Environment {
+			name: name.to_string(),
+			source: path.to_path_buf(),
+			variables,
+		})
+	}
+
+	/// Finds the environment file in `dir` or its closest ancestor having
+	/// one.
+	pub fn find_file(dir: &Path) -> Option<PathBuf> {
+		dir.ancestors().find_map(|dir| {
+			FILE_NAMES
+				.iter()
+				.map(|name| dir.join(name))
+				.find(|path| path.is_file())
+		})
+	}
+
+	/// Evaluates the variables and sets those not already in `vars`, so
+	/// values given on the command line take precedence. A value may use
+	/// any other variable of the environment, wherever it is defined.
+	pub fn apply(&self, vars: &mut Variables) -> Result<()> {
+		let context = format!("{}: environment `{}`", self.source.display(), self.name);
+		let mut pending: Vec<&(String, Template)> = self
+			.variables
+			.iter()
+			.filter(|(name, _)| !vars.contains(name))
+			.collect();
+		while !pending.is_empty() {
+			let waiting = |name: &str| pending.iter().any(|(other, _)| other == name);
+			let ready = pending
+				.iter()
+				.position(|(_, template)| template.variables().all(|used| !waiting(used)));
+			// with a cycle, rendering the first value reports it
+			let (name, template) = pending.remove(ready.unwrap_or(0));
+			let value = template
+				.render(vars)
+				.map_err(|err| error(&context, name, err))?;
+			vars.set(name.clone(), value);
+		}
+		Ok(())
+	}
+}
+
+/// Private counterpart of an environment file.
+fn private_path(path: &Path) -> PathBuf {
+	let name = path
+		.file_name()
+		.and_then(|name| name.to_str())
+		.unwrap_or("");
+	let private = match name {
+		"http-client.env.json" => "http-client.private.env.json".to_string(),
+		_ => match name.strip_suffix(".json") {
+			Some(stem) => format!("{stem}.private.json"),
+			None => format!("{name}.private"),
+		},
+	};
+	path.with_file_name(private)
+}
+
+fn read_file(path: &Path) -> Result<Map<String, Value>> {
+	let text = std::fs::read_to_string(path)
+		.map_err(|err| Error::Usage(format!("cannot read `{}`: {err}", path.display())))?;
+	let source = path.display().to_string();
+	let value: Value = serde_json::from_str(&text).map_err(|err| {
+		ParseError::new(err.line(), err.column(), err.to_string()).in_source(&source)
+	})?;
+	match value {
+		Value::Object(map) => Ok(map),
+		_ => Err(Error::Usage(format!(
+			"{source}: expected an object of environments"
+		))),
+	}
+}
+
+fn variable(value: &Value) -> Option<String> {
+	match value {
+		Value::String(text) => Some(text.clone()),
+		Value::Number(_) | Value::Bool(_) => Some(value.to_string()),
+		_ => None,
+	}
+}
+
+fn error(context: &str, name: &str, err: ParseError) -> Error {
+	Error::Usage(format!("{context}: `{name}`: {}", err.message))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn temp_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("webcat-env-{name}-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(dir.join("api")).unwrap();
+		dir
+	}
+
+	#[test]
+	fn loads_environments() {
+		let dir = temp_dir("load");
+		std::fs::write(
+			dir.join("http-client.env.json"),
+			r#"{
+				"$shared": {"version": "v1", "base": "http://{{host}}/{{version}}"},
+				"dev": {"host": "localhost:8080", "retries": 3},
+				"staging": {"host": "staging.example.com"}
+			}"#,
+		)
+		.unwrap();
+		std::fs::write(
+			dir.join("http-client.private.env.json"),
+			r#"{"dev": {"token": "secret", "version": "v2"}}"#,
+		)
+		.unwrap();
+
+		let path = Environment::find_file(&dir.join("api")).unwrap();
+		assert_eq!(path, dir.join("http-client.env.json"));
+		let env = Environment::load(&path, "dev").unwrap();
+		let names: Vec<&str> = env
+			.variables
+			.iter()
+			.map(|(name, _)| name.as_str())
+			.collect();
+		assert_eq!(names, ["base", "host", "retries", "token", "version"]);
+		let mut vars = Variables::new();
+		vars.set("token", "given");
+		env.apply(&mut vars).unwrap();
+		assert_eq!(vars.get("base"), Some("http://localhost:8080/v2"));
+		assert_eq!(vars.get("retries"), Some("3"));
+		assert_eq!(vars.get("token"), Some("given"));
+
+		let env = Environment::load(&path, "staging").unwrap();
+		let mut vars = Variables::new();
+		vars.set("version", "v3");
+		env.apply(&mut vars).unwrap();
+		assert_eq!(vars.get("base"), Some("http://staging.example.com/v3"));
+
+		let err = Environment::load(&path, "prod").unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			format!(
+				"no environment `prod` in {}, expected one of: dev, staging",
+				path.display()
+			)
+		);
+	}
+}
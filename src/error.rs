@@ -0,0 +1,111 @@
+use std::fmt;
+
+use crate::redirect::Hop;
+
+/// Result type used throughout the crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors produced by webcat.
+#[derive(Debug)]
+pub enum Error {
+	/// Underlying I/O failure (socket, file system, terminal).
+	Io(std::io::Error),
+	/// The given URL could not be parsed or is not supported.
+	Url(String),
+	/// Malformed data received from the peer.
+	Protocol(String),
+	/// Data received from the peer over the size a reader accepts.
+	TooLarge(String),
+	/// TLS configuration or handshake failure.
+	Tls(String),
+	/// Invalid command line usage.
+	Usage(String),
+	/// Syntax or evaluation error at a known position of a source file.
+	Parse(ParseError),
+	/// Redirect refused by the policy of the client, with the redirects
+	/// received up to and including that one.
+	Redirect { message: String, hops: Vec<Hop> },
+}
+
+/// Error located at a line and column (both 1-based) of a named source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+	pub source: String,
+	pub line: usize,
+	pub column: usize,
+	pub message: String,
+}
+
+impl ParseError {
+	pub fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+		ParseError {
+			source: String::new(),
+			line,
+			column,
+			message: message.into(),
+		}
+	}
+
+	/// Sets the source name used when displaying the error.
+	pub fn in_source(mut self, source: &str) -> Self {
+		self.source = source.to_string();
+		self
+	}
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let source = if self.source.is_empty() {
+			"<input>"
+		} else {
+			&self.source
+		};
+		write!(
+			f,
+			"{source}:{}:{}: {}",
+			self.line, self.column, self.message
+		)
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::Io(err) => write!(f, "{err}"),
+			Error::Url(msg) => write!(f, "invalid URL: {msg}"),
+			Error::Protocol(msg) => write!(f, "protocol error: {msg}"),
+			Error::TooLarge(msg) => write!(f, "too large: {msg}"),
+			Error::Tls(msg) => write!(f, "TLS error: {msg}"),
+			Error::Usage(msg) => write!(f, "{msg}"),
+			Error::Parse(err) => write!(f, "{err}"),
+			Error::Redirect { message, hops } => {
+				write!(f, "stopped following redirects: {message}")?;
+				for hop in hops {
+					write!(f, "\n{hop}")?;
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Io(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<std::io::Error> for Error {
+	fn from(err: std::io::Error) -> Self {
+		Error::Io(err)
+	}
+}
+
+impl From<ParseError> for Error {
+	fn from(err: ParseError) -> Self {
+		Error::Parse(err)
+	}
+}
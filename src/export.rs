@@ -0,0 +1,446 @@
+//! Export of requests as commands for other tools, to share them with
+//! someone who does not use webcat.
+
+use std::fmt::{self, Write as _};
+use std::str::FromStr;
+
+use crate::auth::Auth;
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::http::Request;
+use crate::util::base64_encode;
+
+/// Headers the tools compute themselves.
+const SKIPPED_HEADERS: [&str; 2] = ["Content-Length", "Connection"];
+
+/// Methods PowerShell accepts for `-Method`, others need `-CustomMethod`.
+const POWERSHELL_METHODS: [&str; 9] = [
+	"GET", "HEAD", "POST", "PUT", "DELETE", "TRACE", "OPTIONS", "MERGE", "PATCH",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+	Curl,
+	Wget,
+	/// `Invoke-WebRequest`.
+	PowerShell,
+}
+
+impl FromStr for Tool {
+	type Err = Error;
+
+	fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
+		match text.to_ascii_lowercase().as_str() {
+			"curl" => Ok(Tool::Curl),
+			"wget" => Ok(Tool::Wget),
+			"powershell" | "pwsh" => Ok(Tool::PowerShell),
+			_ => Err(Error::Usage(format!(
+				"unknown export format `{text}`, expected curl, wget or powershell"
+			))),
+		}
+	}
+}
+
+impl fmt::Display for Tool {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Tool::Curl => "curl",
+			Tool::Wget => "wget",
+			Tool::PowerShell => "PowerShell",
+		})
+	}
+}
+
+/// Command sending the request with the given tool, split over several
+/// lines. The scheme named by the `Authorization` header, or the default
+/// one of the client, is written the way the tool authenticates: OAuth2
+/// tokens are fetched with the client and sent as `Bearer`, Digest and AWS
+/// credentials go to the tool's own options. A scheme the tool cannot
+/// express is an error rather than a header leaking the credentials.
+pub fn command(request: &Request, tool: Tool, client: &Client) -> Result<String> {
+	let mut request = request.clone();
+	let auth = match client.auth_of(&request)? {
+		Some(auth @ (Auth::Digest { .. } | Auth::Aws(_))) => {
+			request.headers.remove("Authorization");
+			Some(auth)
+		}
+		Some(auth) => {
+			auth.apply(client, &mut request)?;
+			None
+		}
+		None => None,
+	};
+	match tool {
+		Tool::Curl => Ok(curl(&request, auth.as_ref())),
+		Tool::Wget => wget(&request, auth.as_ref()),
+		Tool::PowerShell => powershell(&request, auth.as_ref()),
+	}
+}
+
+/// Error for a scheme a tool cannot authenticate with.
+fn unsupported(tool: Tool, scheme: &str) -> Error {
+	Error::Usage(format!(
+		"{tool} cannot authenticate with {scheme}, export to curl instead"
+	))
+}
+
+fn headers(request: &Request) -> impl Iterator<Item = (&str, &str)> {
+	request
+		.headers
+		.iter()
+		.filter(|(name, _)| !SKIPPED_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)))
+}
+
+fn curl(request: &Request, auth: Option<&Auth>) -> String {
+	let mut words = vec!["curl".to_string()];
+	let has_body = !request.body.is_empty();
+	match request.method.as_str() {
+		"GET" if !has_body => {}
+		"POST" if has_body => {}
+		"HEAD" => words.push("--head".into()),
+		method => words.push(format!("-X {method}")),
+	}
+	let url = request.url.to_string();
+	if url.contains(['[', ']', '{', '}']) {
+		words.push("--globoff".into());
+	}
+	words.push(sh_quote(&url));
+	// curl answers Digest challenges and signs AWS requests itself
+	match auth {
+		Some(Auth::Digest { user, password }) => {
+			words.push(format!(
+				"--digest -u {}",
+				sh_quote(&format!("{user}:{password}"))
+			));
+		}
+		Some(Auth::Aws(aws)) => {
+			let scope = [&aws.region, &aws.service].into_iter().flatten().cloned();
+			let provider = ["aws", "amz"].into_iter().map(str::to_string).chain(scope);
+			words.push(format!(
+				"--aws-sigv4 {}",
+				sh_quote(&provider.collect::<Vec<_>>().join(":"))
+			));
+			words.push(format!(
+				"-u {}",
+				sh_quote(&format!("{}:{}", aws.access_key, aws.secret_key))
+			));
+			if let Some(token) = &aws.session_token {
+				words.push(format!(
+					"-H {}",
+					sh_quote(&format!("X-Amz-Security-Token: {token}"))
+				));
+			}
+		}
+		_ => {}
+	}
+	for (name, value) in headers(request) {
+		words.push(format!("-H {}", sh_quote(&format!("{name}: {value}"))));
+	}
+	if !has_body {
+		return words.join(" \\\n  ");
+	}
+	// curl would label the body as a form otherwise
+	if !request.headers.contains("Content-Type") {
+		words.push("-H 'Content-Type:'".into());
+	}
+	match shell_text(&request.body) {
+		Some(text) => {
+			words.push(format!("--data-raw {}", sh_quote(text)));
+			words.join(" \\\n  ")
+		}
+		None => {
+			words.push("--data-binary @-".into());
+			format!("{} | {}", printf(&request.body), words.join(" \\\n  "))
+		}
+	}
+}
+
+fn wget(request: &Request, auth: Option<&Auth>) -> Result<String> {
+	let mut words = vec!["wget -O -".to_string()];
+	if request.method != "GET" {
+		words.push(format!("--method={}", request.method));
+	}
+	// wget answers Basic and Digest challenges with these
+	match auth {
+		Some(Auth::Digest { user, password }) => {
+			words.push(sh_quote(&format!("--user={user}")));
+			words.push(sh_quote(&format!("--password={password}")));
+		}
+		Some(Auth::Aws(_)) => return Err(unsupported(Tool::Wget, "AWS Signature Version 4")),
+		_ => {}
+	}
+	for (name, value) in headers(request) {
+		words.push(sh_quote(&format!("--header={name}: {value}")));
+	}
+	let mut prefix = String::new();
+	if !request.body.is_empty() {
+		match shell_text(&request.body) {
+			Some(text) => words.push(sh_quote(&format!("--body-data={text}"))),
+			None => {
+				prefix = format!("{} | ", printf(&request.body));
+				words.push("--body-file=/dev/stdin".into());
+			}
+		}
+	}
+	words.push(sh_quote(&request.url.to_string()));
+	Ok(format!("{prefix}{}", words.join(" \\\n  ")))
+}
+
+fn powershell(request: &Request, auth: Option<&Auth>) -> Result<String> {
+	let mut words = vec![format!(
+		"Invoke-WebRequest -Uri {}",
+		ps_quote(&request.url.to_string())
+	)];
+	match request.method.as_str() {
+		"GET" => {}
+		method if POWERSHELL_METHODS.contains(&method) => words.push(format!("-Method {method}")),
+		method => words.push(format!("-CustomMethod {}", ps_quote(method))),
+	}
+	// the credentials answer the Digest challenge of the server, which
+	// PowerShell 7 only sends over plain HTTP when allowed to
+	match auth {
+		Some(Auth::Digest { user, password }) => {
+			words.push(format!(
+				"-Credential (New-Object System.Management.Automation.PSCredential({}, \
+				 (ConvertTo-SecureString {} -AsPlainText -Force)))",
+				ps_quote(user),
+				ps_quote(password)
+			));
+			if request.url.scheme == "http" {
+				words.push("-AllowUnencryptedAuthentication".into());
+			}
+		}
+		Some(Auth::Aws(_)) => return Err(unsupported(Tool::PowerShell, "AWS Signature Version 4")),
+		_ => {}
+	}
+	// the content type has its own parameter, and a hash table cannot hold
+	// the same name twice
+	let mut fields: Vec<(String, String)> = Vec::new();
+	let mut content_type = None;
+	for (name, value) in headers(request) {
+		if name.eq_ignore_ascii_case("Content-Type") {
+			content_type = Some(value);
+		} else if let Some(field) = fields
+			.iter_mut()
+			.find(|(n, _)| n.eq_ignore_ascii_case(name))
+		{
+			field.1 = format!("{}, {value}", field.1);
+		} else {
+			fields.push((name.to_string(), value.to_string()));
+		}
+	}
+	if !fields.is_empty() {
+		let table: Vec<String> = fields
+			.iter()
+			.map(|(name, value)| format!("{} = {}", ps_quote(name), ps_quote(value)))
+			.collect();
+		words.push(format!("-Headers @{{ {} }}", table.join("; ")));
+	}
+	if let Some(content_type) = content_type {
+		words.push(format!("-ContentType {}", ps_quote(content_type)));
+	}
+	if !request.body.is_empty() {
+		match shell_text(&request.body) {
+			Some(text) => words.push(format!("-Body {}", ps_quote(text))),
+			None => words.push(format!(
+				"-Body ([System.Convert]::FromBase64String('{}'))",
+				base64_encode(&request.body)
+			)),
+		}
+	}
+	Ok(words.join(" `\n  "))
+}
+
+/// Body as text if it can be passed as a quoted argument: UTF-8 without
+/// control characters other than line breaks and tabs.
+fn shell_text(body: &[u8]) -> Option<&str> {
+	let text = std::str::from_utf8(body).ok()?;
+	let plain = text
+		.chars()
+		.all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'));
+	plain.then_some(text)
+}
+
+/// Quotes a word for POSIX shells unless it only has safe characters.
+fn sh_quote(word: &str) -> String {
+	let safe = !word.is_empty()
+		&& word
+			.bytes()
+			.all(|b| b.is_ascii_alphanumeric() || b"-_./:=@%+,".contains(&b));
+	if safe {
+		return word.to_string();
+	}
+	format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+fn ps_quote(word: &str) -> String {
+	format!("'{}'", word.replace('\'', "''"))
+}
+
+/// `printf` writing the bytes of a binary body, using octal escapes as
+/// they are understood by every POSIX `printf`.
+fn printf(body: &[u8]) -> String {
+	let mut format = String::new();
+	for &byte in body {
+		match byte {
+			b'%' => format.push_str("%%"),
+			b'\\' => format.push_str(r"\\"),
+			b'\'' => format.push_str(r"'\''"),
+			b' '..=b'~' => format.push(byte as char),
+			_ => {
+				let _ = write!(format, "\\{byte:03o}");
+			}
+		}
+	}
+	format!("printf '{format}'")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::curl;
+	use crate::url::Url;
+
+	fn request() -> Request {
+		let mut request = Request::new(
+			"PUT",
+			Url::parse("https://api.example.com/items/1?x=[1]").unwrap(),
+		);
+		request.headers.append("Accept", "application/json");
+		request.headers.append("Accept", "text/plain");
+		request.headers.append("Content-Type", "application/json");
+		request.headers.append("Content-Length", "16");
+		request.body = br#"{"name": "it's"}"#.to_vec();
+		request
+	}
+
+	#[test]
+	fn exports_curl() {
+		let request = request();
+		let text = command(&request, Tool::Curl, &Client::new()).unwrap();
+		assert_eq!(
+			text,
+			"curl \\\n  -X PUT \\\n  --globoff \\\n  'https://api.example.com/items/1?x=[1]' \\\n  \
+			 -H 'Accept: application/json' \\\n  -H 'Accept: text/plain' \\\n  \
+			 -H 'Content-Type: application/json' \\\n  --data-raw '{\"name\": \"it'\\''s\"}'"
+		);
+
+		// the importer reads back the same request
+		let imported = curl::parse(&text).unwrap();
+		let imported = &imported.requests[0];
+		assert_eq!(imported.method, request.method);
+		assert_eq!(imported.url, request.url);
+		assert_eq!(imported.headers.len(), 3);
+		assert_eq!(imported.body, request.body);
+
+		let mut binary = Request::new("POST", Url::parse("http://h/").unwrap());
+		binary.body = vec![0, b'%', b'a', 0xff];
+		assert_eq!(
+			command(&binary, Tool::Curl, &Client::new()).unwrap(),
+			"printf '\\000%%a\\377' | curl \\\n  http://h/ \\\n  -H 'Content-Type:' \\\n  --data-binary @-"
+		);
+	}
+
+	#[test]
+	fn exports_named_auth_schemes() {
+		let client = Client::new();
+		let export = |auth: &str, tool| {
+			let mut request = Request::new("GET", Url::parse("http://h/").unwrap());
+			request.headers.set("Authorization", auth);
+			command(&request, tool, &client)
+		};
+		let aws = "AWS access_key=AK secret_key=SK region=eu-west-1 service=s3";
+
+		for tool in [Tool::Curl, Tool::Wget, Tool::PowerShell] {
+			let text = export("Basic jo secret", tool).unwrap();
+			assert!(
+				text.contains("Authorization: Basic am86c2VjcmV0")
+					|| text.contains("'Authorization' = 'Basic am86c2VjcmV0'"),
+				"{text}"
+			);
+			let text = export("Digest jo secret", tool).unwrap();
+			assert!(!text.contains("Authorization"), "{text}");
+			// invalid credentials are not exported either
+			assert!(matches!(export("Digest jo", tool), Err(Error::Usage(_))));
+		}
+
+		assert_eq!(
+			export("Digest jo secret", Tool::Curl).unwrap(),
+			"curl \\\n  http://h/ \\\n  --digest -u jo:secret"
+		);
+		assert_eq!(
+			export("Digest jo secret", Tool::Wget).unwrap(),
+			"wget -O - \\\n  --user=jo \\\n  --password=secret \\\n  http://h/"
+		);
+		assert_eq!(
+			export("Digest jo secret", Tool::PowerShell).unwrap(),
+			"Invoke-WebRequest -Uri 'http://h/' `\n  \
+			 -Credential (New-Object System.Management.Automation.PSCredential('jo', \
+			 (ConvertTo-SecureString 'secret' -AsPlainText -Force))) `\n  \
+			 -AllowUnencryptedAuthentication"
+		);
+
+		let text = export(aws, Tool::Curl).unwrap();
+		assert!(text.ends_with("--aws-sigv4 aws:amz:eu-west-1:s3 \\\n  -u AK:SK"));
+		let imported = curl::parse(&text).unwrap();
+		assert_eq!(imported.requests[0].headers.get("Authorization"), Some(aws));
+		for tool in [Tool::Wget, Tool::PowerShell] {
+			let err = export(aws, tool).unwrap_err();
+			assert!(
+				matches!(&err, Error::Usage(message) if message.contains("AWS")),
+				"{err}"
+			);
+		}
+	}
+
+	#[test]
+	fn exports_default_auth_of_client() {
+		let client = Client {
+			auth: Some(Auth::Bearer("t0k".into())),
+			..Client::new()
+		};
+		let request = Request::new("GET", Url::parse("http://h/").unwrap());
+		assert_eq!(
+			command(&request, Tool::Wget, &client).unwrap(),
+			"wget -O - \\\n  '--header=Authorization: Bearer t0k' \\\n  http://h/"
+		);
+		let client = Client {
+			auth: Some(Auth::Digest {
+				user: "jo".into(),
+				password: "secret".into(),
+			}),
+			..Client::new()
+		};
+		assert_eq!(
+			command(&request, Tool::Curl, &client).unwrap(),
+			"curl \\\n  http://h/ \\\n  --digest -u jo:secret"
+		);
+	}
+
+	#[test]
+	fn exports_wget() {
+		assert_eq!(
+			command(&request(), Tool::Wget, &Client::new()).unwrap(),
+			"wget -O - \\\n  --method=PUT \\\n  '--header=Accept: application/json' \\\n  \
+			 '--header=Accept: text/plain' \\\n  '--header=Content-Type: application/json' \\\n  \
+			 '--body-data={\"name\": \"it'\\''s\"}' \\\n  'https://api.example.com/items/1?x=[1]'"
+		);
+	}
+
+	#[test]
+	fn exports_powershell() {
+		assert_eq!(
+			command(&request(), Tool::PowerShell, &Client::new()).unwrap(),
+			"Invoke-WebRequest -Uri 'https://api.example.com/items/1?x=[1]' `\n  -Method PUT `\n  \
+			 -Headers @{ 'Accept' = 'application/json, text/plain' } `\n  \
+			 -ContentType 'application/json' `\n  -Body '{\"name\": \"it''s\"}'"
+		);
+		let mut binary = Request::new("PURGE", Url::parse("http://h/").unwrap());
+		binary.body = vec![0, 1];
+		assert_eq!(
+			command(&binary, Tool::PowerShell, &Client::new()).unwrap(),
+			"Invoke-WebRequest -Uri 'http://h/' `\n  -CustomMethod 'PURGE' `\n  \
+			 -Body ([System.Convert]::FromBase64String('AAE='))"
+		);
+	}
+}
@@ -0,0 +1,795 @@
+//! Parser of GraphQL executable documents: operations and fragments.
+
+use std::fmt;
+
+use super::schema::TypeRef;
+use crate::error::ParseError;
+
+/// Line and column (both 1-based) in the query.
+pub type Position = (usize, usize);
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Document {
+	pub operations: Vec<Operation>,
+	pub fragments: Vec<Fragment>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperationKind {
+	Query,
+	Mutation,
+	Subscription,
+}
+
+impl OperationKind {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			OperationKind::Query => "query",
+			OperationKind::Mutation => "mutation",
+			OperationKind::Subscription => "subscription",
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Operation {
+	pub kind: OperationKind,
+	pub name: Option<String>,
+	pub variables: Vec<VariableDef>,
+	pub directives: Vec<Directive>,
+	pub selections: Vec<Selection>,
+	pub position: Position,
+}
+
+impl Operation {
+	/// `query Name`, or `query` for an anonymous operation.
+	pub fn label(&self) -> String {
+		match &self.name {
+			Some(name) => format!("{} {name}", self.kind.as_str()),
+			None => self.kind.as_str().to_string(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariableDef {
+	pub name: String,
+	pub ty: TypeRef,
+	pub default: Option<Value>,
+	pub position: Position,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fragment {
+	pub name: String,
+	pub type_condition: String,
+	pub directives: Vec<Directive>,
+	pub selections: Vec<Selection>,
+	pub position: Position,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Selection {
+	Field(Field),
+	/// `...Name`
+	Spread {
+		name: String,
+		directives: Vec<Directive>,
+		position: Position,
+	},
+	/// `... on Type { }`, or `... { }` without a type condition.
+	Inline {
+		type_condition: Option<String>,
+		directives: Vec<Directive>,
+		selections: Vec<Selection>,
+		position: Position,
+	},
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+	pub alias: Option<String>,
+	pub name: String,
+	pub arguments: Vec<Argument>,
+	pub directives: Vec<Directive>,
+	pub selections: Vec<Selection>,
+	pub position: Position,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Argument {
+	pub name: String,
+	pub value: Value,
+	pub position: Position,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Directive {
+	pub name: String,
+	pub arguments: Vec<Argument>,
+	pub position: Position,
+}
+
+/// Value written in a query.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+	Variable(String),
+	Int(String),
+	Float(String),
+	String(String),
+	Boolean(bool),
+	Null,
+	Enum(String),
+	List(Vec<Value>),
+	Object(Vec<(String, Value)>),
+}
+
+/// The value as written in GraphQL.
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::Variable(name) => write!(f, "${name}"),
+			Value::Int(text) | Value::Float(text) | Value::Enum(text) => f.write_str(text),
+			Value::String(text) => write!(f, "{}", serde_json::Value::from(text.as_str())),
+			Value::Boolean(value) => write!(f, "{value}"),
+			Value::Null => f.write_str("null"),
+			Value::List(items) => {
+				f.write_str("[")?;
+				for (index, item) in items.iter().enumerate() {
+					if index > 0 {
+						f.write_str(", ")?;
+					}
+					write!(f, "{item}")?;
+				}
+				f.write_str("]")
+			}
+			Value::Object(fields) => {
+				f.write_str("{")?;
+				for (index, (name, value)) in fields.iter().enumerate() {
+					if index > 0 {
+						f.write_str(",")?;
+					}
+					write!(f, " {name}: {value}")?;
+				}
+				f.write_str(" }")
+			}
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+	/// One of `!$&()...:=@[]{|}`, with `...` as a single token.
+	Punct(&'static str),
+	Name(String),
+	Int(String),
+	Float(String),
+	String(String),
+}
+
+impl fmt::Display for Token {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Token::Punct(punct) => write!(f, "`{punct}`"),
+			Token::Name(name) => write!(f, "`{name}`"),
+			Token::Int(text) | Token::Float(text) => write!(f, "`{text}`"),
+			Token::String(_) => f.write_str("a string"),
+		}
+	}
+}
+
+/// Parses a query document.
+pub fn parse(text: &str) -> Result<Document, ParseError> {
+	let tokens = tokenize(text)?;
+	let end = text.lines().count().max(1);
+	let mut parser = Parser {
+		tokens,
+		index: 0,
+		end: (
+			end,
+			text.lines().last().map_or(0, |line| line.chars().count()) + 1,
+		),
+	};
+	let mut document = Document::default();
+	while parser.peek().is_some() {
+		match parser.peek() {
+			Some(Token::Name(name)) if name == "fragment" => {
+				document.fragments.push(parser.fragment()?)
+			}
+			_ => document.operations.push(parser.operation()?),
+		}
+	}
+	if document.operations.is_empty() && document.fragments.is_empty() {
+		return Err(ParseError::new(1, 1, "empty query"));
+	}
+	Ok(document)
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, Position)>, ParseError> {
+	let chars: Vec<char> = text.chars().collect();
+	let mut tokens = Vec::new();
+	let (mut line, mut column) = (1, 1);
+	let mut i = 0;
+	while i < chars.len() {
+		let c = chars[i];
+		let start = (line, column);
+		let begin = i;
+		match c {
+			'\n' => {
+				line += 1;
+				column = 1;
+				i += 1;
+				continue;
+			}
+			c if c.is_whitespace() || c == ',' || c == '\u{feff}' => {
+				i += 1;
+				column += 1;
+				continue;
+			}
+			'#' => {
+				while i < chars.len() && chars[i] != '\n' {
+					i += 1;
+				}
+				continue;
+			}
+			'.' => {
+				if chars[i..].starts_with(&['.', '.', '.']) {
+					tokens.push((Token::Punct("..."), start));
+					i += 3;
+				} else {
+					return Err(ParseError::new(line, column, "expected `...`"));
+				}
+			}
+			'!' | '$' | '&' | '(' | ')' | ':' | '=' | '@' | '[' | ']' | '{' | '|' | '}' => {
+				let punct = match c {
+					'!' => "!",
+					'$' => "$",
+					'&' => "&",
+					'(' => "(",
+					')' => ")",
+					':' => ":",
+					'=' => "=",
+					'@' => "@",
+					'[' => "[",
+					']' => "]",
+					'{' => "{",
+					'|' => "|",
+					_ => "}",
+				};
+				tokens.push((Token::Punct(punct), start));
+				i += 1;
+			}
+			'"' => {
+				let (value, next) = if chars[i..].starts_with(&['"', '"', '"']) {
+					block_string(&chars, i + 3)
+				} else {
+					string(&chars, i + 1)
+				}
+				.ok_or_else(|| ParseError::new(line, column, "unterminated string"))?;
+				tokens.push((Token::String(value), start));
+				i = next;
+			}
+			c if c.is_ascii_alphabetic() || c == '_' => {
+				while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+					i += 1;
+				}
+				tokens.push((Token::Name(chars[begin..i].iter().collect()), start));
+			}
+			c if c.is_ascii_digit() || c == '-' => {
+				i += 1;
+				let mut float = false;
+				while i < chars.len() {
+					match chars[i] {
+						'0'..='9' => {}
+						'.' | 'e' | 'E' => float = true,
+						'+' | '-' if matches!(chars[i - 1], 'e' | 'E') => {}
+						_ => break,
+					}
+					i += 1;
+				}
+				let number: String = chars[begin..i].iter().collect();
+				let valid = match float {
+					true => number.parse::<f64>().is_ok(),
+					false => number.parse::<i128>().is_ok(),
+				};
+				if !valid {
+					return Err(ParseError::new(
+						line,
+						column,
+						format!("invalid number `{number}`"),
+					));
+				}
+				tokens.push((
+					match float {
+						true => Token::Float(number),
+						false => Token::Int(number),
+					},
+					start,
+				));
+			}
+			c => {
+				return Err(ParseError::new(
+					line,
+					column,
+					format!("unexpected character `{c}`"),
+				))
+			}
+		}
+		// tokens other than strings stay on a line
+		for &c in &chars[begin..i] {
+			if c == '\n' {
+				line += 1;
+				column = 1;
+			} else {
+				column += 1;
+			}
+		}
+	}
+	Ok(tokens)
+}
+
+/// Reads a `"` string from after its opening quote, returning its value and
+/// the index after the closing quote.
+fn string(chars: &[char], mut i: usize) -> Option<(String, usize)> {
+	let mut value = String::new();
+	loop {
+		match *chars.get(i)? {
+			'"' => return Some((value, i + 1)),
+			'\n' => return None,
+			'\\' => {
+				i += 1;
+				match *chars.get(i)? {
+					'n' => value.push('\n'),
+					't' => value.push('\t'),
+					'r' => value.push('\r'),
+					'b' => value.push('\u{8}'),
+					'f' => value.push('\u{c}'),
+					'u' => {
+						let hex: String = chars.get(i + 1..i + 5)?.iter().collect();
+						value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+						i += 4;
+					}
+					c => value.push(c),
+				}
+			}
+			c => value.push(c),
+		}
+		i += 1;
+	}
+}
+
+/// Reads a `"""` block string from after its opening quotes.
+fn block_string(chars: &[char], mut i: usize) -> Option<(String, usize)> {
+	let mut raw = String::new();
+	loop {
+		if chars[i..].starts_with(&['"', '"', '"']) {
+			break;
+		}
+		if chars[i..].starts_with(&['\\', '"', '"', '"']) {
+			raw.push_str("\"\"\"");
+			i += 4;
+			continue;
+		}
+		raw.push(*chars.get(i)?);
+		i += 1;
+	}
+	// common indentation and blank first and last lines are removed
+	let lines: Vec<&str> = raw.lines().collect();
+	let indent = lines
+		.iter()
+		.skip(1)
+		.filter(|line| !line.trim().is_empty())
+		.map(|line| line.len() - line.trim_start().len())
+		.min()
+		.unwrap_or(0);
+	let mut lines: Vec<&str> = lines
+		.iter()
+		.enumerate()
+		.map(|(index, line)| match index {
+			0 => line,
+			_ => line.get(indent..).unwrap_or(""),
+		})
+		.collect();
+	while lines.first().is_some_and(|line| line.trim().is_empty()) {
+		lines.remove(0);
+	}
+	while lines.last().is_some_and(|line| line.trim().is_empty()) {
+		lines.pop();
+	}
+	Some((lines.join("\n"), i + 3))
+}
+
+struct Parser {
+	tokens: Vec<(Token, Position)>,
+	index: usize,
+	/// Position of the end of the text.
+	end: Position,
+}
+
+impl Parser {
+	fn peek(&self) -> Option<&Token> {
+		self.tokens.get(self.index).map(|(token, _)| token)
+	}
+
+	fn position(&self) -> Position {
+		self.tokens
+			.get(self.index)
+			.map_or(self.end, |(_, position)| *position)
+	}
+
+	fn is(&self, punct: &str) -> bool {
+		matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+	}
+
+	/// Consumes the punctuator if it comes next.
+	fn eat(&mut self, punct: &str) -> bool {
+		let found = self.is(punct);
+		if found {
+			self.index += 1;
+		}
+		found
+	}
+
+	fn error(&self, expected: &str) -> ParseError {
+		let (line, column) = self.position();
+		let found = match self.peek() {
+			Some(token) => token.to_string(),
+			None => "the end of the query".to_string(),
+		};
+		ParseError::new(line, column, format!("expected {expected}, got {found}"))
+	}
+
+	fn expect(&mut self, punct: &str) -> Result<(), ParseError> {
+		match self.eat(punct) {
+			true => Ok(()),
+			false => Err(self.error(&format!("`{punct}`"))),
+		}
+	}
+
+	fn name(&mut self) -> Result<String, ParseError> {
+		match self.peek() {
+			Some(Token::Name(name)) => {
+				let name = name.clone();
+				self.index += 1;
+				Ok(name)
+			}
+			_ => Err(self.error("a name")),
+		}
+	}
+
+	/// Consumes the keyword if it comes next.
+	fn keyword(&mut self, keyword: &str) -> bool {
+		let found = matches!(self.peek(), Some(Token::Name(name)) if name == keyword);
+		if found {
+			self.index += 1;
+		}
+		found
+	}
+
+	fn operation(&mut self) -> Result<Operation, ParseError> {
+		let position = self.position();
+		if self.is("{") {
+			return Ok(Operation {
+				kind: OperationKind::Query,
+				name: None,
+				variables: Vec::new(),
+				directives: Vec::new(),
+				selections: self.selection_set()?,
+				position,
+			});
+		}
+		let kind = match self.peek() {
+			Some(Token::Name(name)) if name == "query" => OperationKind::Query,
+			Some(Token::Name(name)) if name == "mutation" => OperationKind::Mutation,
+			Some(Token::Name(name)) if name == "subscription" => OperationKind::Subscription,
+			_ => return Err(self.error("`query`, `mutation`, `subscription`, `fragment` or `{`")),
+		};
+		self.index += 1;
+		let name = match self.peek() {
+			Some(Token::Name(_)) => Some(self.name()?),
+			_ => None,
+		};
+		let mut variables = Vec::new();
+		if self.eat("(") {
+			while !self.eat(")") {
+				variables.push(self.variable_def()?);
+			}
+		}
+		Ok(Operation {
+			kind,
+			name,
+			variables,
+			directives: self.directives()?,
+			selections: self.selection_set()?,
+			position,
+		})
+	}
+
+	fn variable_def(&mut self) -> Result<VariableDef, ParseError> {
+		let position = self.position();
+		self.expect("$")?;
+		let name = self.name()?;
+		self.expect(":")?;
+		let ty = self.type_ref()?;
+		let default = match self.eat("=") {
+			true => Some(self.value(true)?),
+			false => None,
+		};
+		// directives of variables have no use here
+		self.directives()?;
+		Ok(VariableDef {
+			name,
+			ty,
+			default,
+			position,
+		})
+	}
+
+	fn type_ref(&mut self) -> Result<TypeRef, ParseError> {
+		let ty = if self.eat("[") {
+			let item = self.type_ref()?;
+			self.expect("]")?;
+			TypeRef::List(Box::new(item))
+		} else {
+			TypeRef::Named(self.name()?)
+		};
+		Ok(match self.eat("!") {
+			true => TypeRef::NonNull(Box::new(ty)),
+			false => ty,
+		})
+	}
+
+	fn fragment(&mut self) -> Result<Fragment, ParseError> {
+		let position = self.position();
+		self.keyword("fragment");
+		let name_position = self.position();
+		let name = self.name()?;
+		if name == "on" {
+			let (line, column) = name_position;
+			return Err(ParseError::new(line, column, "expected a fragment name"));
+		}
+		if !self.keyword("on") {
+			return Err(self.error("`on`"));
+		}
+		Ok(Fragment {
+			name,
+			type_condition: self.name()?,
+			directives: self.directives()?,
+			selections: self.selection_set()?,
+			position,
+		})
+	}
+
+	fn selection_set(&mut self) -> Result<Vec<Selection>, ParseError> {
+		self.expect("{")?;
+		let mut selections = Vec::new();
+		while !self.eat("}") {
+			if self.peek().is_none() {
+				return Err(self.error("`}`"));
+			}
+			selections.push(self.selection()?);
+		}
+		if selections.is_empty() {
+			let (line, column) = self.tokens[self.index - 1].1;
+			return Err(ParseError::new(line, column, "expected a field, got `}`"));
+		}
+		Ok(selections)
+	}
+
+	fn selection(&mut self) -> Result<Selection, ParseError> {
+		let position = self.position();
+		if self.eat("...") {
+			let on = matches!(self.peek(), Some(Token::Name(name)) if name == "on");
+			return Ok(match self.peek() {
+				Some(Token::Name(_)) if !on => Selection::Spread {
+					name: self.name()?,
+					directives: self.directives()?,
+					position,
+				},
+				_ => {
+					let type_condition = match self.keyword("on") {
+						true => Some(self.name()?),
+						false => None,
+					};
+					Selection::Inline {
+						type_condition,
+						directives: self.directives()?,
+						selections: self.selection_set()?,
+						position,
+					}
+				}
+			});
+		}
+		let mut name = self.name()?;
+		let mut alias = None;
+		if self.eat(":") {
+			alias = Some(name);
+			name = self.name()?;
+		}
+		let arguments = self.arguments(false)?;
+		let directives = self.directives()?;
+		let selections = match self.is("{") {
+			true => self.selection_set()?,
+			false => Vec::new(),
+		};
+		Ok(Selection::Field(Field {
+			alias,
+			name,
+			arguments,
+			directives,
+			selections,
+			position,
+		}))
+	}
+
+	fn arguments(&mut self, constant: bool) -> Result<Vec<Argument>, ParseError> {
+		let mut arguments = Vec::new();
+		if self.eat("(") {
+			while !self.eat(")") {
+				let position = self.position();
+				let name = self.name()?;
+				self.expect(":")?;
+				arguments.push(Argument {
+					name,
+					value: self.value(constant)?,
+					position,
+				});
+			}
+		}
+		Ok(arguments)
+	}
+
+	fn directives(&mut self) -> Result<Vec<Directive>, ParseError> {
+		let mut directives = Vec::new();
+		while self.is("@") {
+			let position = self.position();
+			self.index += 1;
+			directives.push(Directive {
+				name: self.name()?,
+				arguments: self.arguments(false)?,
+				position,
+			});
+		}
+		Ok(directives)
+	}
+
+	/// Parses a value, without variables in it when `constant`.
+	fn value(&mut self, constant: bool) -> Result<Value, ParseError> {
+		let position = self.position();
+		let Some(token) = self.peek().cloned() else {
+			return Err(self.error("a value"));
+		};
+		let value = match token {
+			Token::Punct("$") if !constant => {
+				self.index += 1;
+				return Ok(Value::Variable(self.name()?));
+			}
+			Token::Punct("[") => {
+				self.index += 1;
+				let mut items = Vec::new();
+				while !self.eat("]") {
+					items.push(self.value(constant)?);
+				}
+				return Ok(Value::List(items));
+			}
+			Token::Punct("{") => {
+				self.index += 1;
+				let mut fields = Vec::new();
+				while !self.eat("}") {
+					let name = self.name()?;
+					self.expect(":")?;
+					fields.push((name, self.value(constant)?));
+				}
+				return Ok(Value::Object(fields));
+			}
+			Token::Int(text) => Value::Int(text),
+			Token::Float(text) => Value::Float(text),
+			Token::String(text) => Value::String(text),
+			Token::Name(name) => match name.as_str() {
+				"true" => Value::Boolean(true),
+				"false" => Value::Boolean(false),
+				"null" => Value::Null,
+				_ => Value::Enum(name),
+			},
+			Token::Punct(_) => {
+				let (line, column) = position;
+				let message = match constant && token == Token::Punct("$") {
+					true => "variables are not allowed here".to_string(),
+					false => format!("expected a value, got {token}"),
+				};
+				return Err(ParseError::new(line, column, message));
+			}
+		};
+		self.index += 1;
+		Ok(value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_operations_and_fragments() {
+		let text = r#"
+# items of a page
+query Items($first: Int = 10, $tags: [String!]!) @cached {
+  items(first: $first, filter: {tags: $tags, text: "a \"b\""}) {
+    ...ItemFields
+    ... on Book { pages }
+    total: count
+  }
+}
+
+fragment ItemFields on Item { id name @include(if: true) }
+"#;
+		let document = parse(text).unwrap();
+		let operation = &document.operations[0];
+		assert_eq!(operation.label(), "query Items");
+		assert_eq!(operation.position, (3, 1));
+		assert_eq!(operation.variables[0].ty.to_string(), "Int");
+		assert_eq!(
+			operation.variables[0].default,
+			Some(Value::Int("10".into()))
+		);
+		assert_eq!(operation.variables[1].ty.to_string(), "[String!]!");
+		let Selection::Field(items) = &operation.selections[0] else {
+			panic!("expected a field");
+		};
+		assert_eq!(items.position, (4, 3));
+		assert_eq!(
+			items.arguments[1].value.to_string(),
+			r#"{ tags: $tags, text: "a \"b\"" }"#
+		);
+		assert!(
+			matches!(&items.selections[0], Selection::Spread { name, .. } if name == "ItemFields")
+		);
+		assert!(matches!(
+			&items.selections[1],
+			Selection::Inline { type_condition: Some(ty), position: (6, 5), .. } if ty == "Book"
+		));
+		assert!(matches!(
+			&items.selections[2],
+			Selection::Field(Field { alias: Some(alias), name, .. }) if alias == "total" && name == "count"
+		));
+		assert_eq!(document.fragments[0].type_condition, "Item");
+
+		let document = parse("{ a(text: \"\"\"\n    one\n      two\n  \"\"\") }").unwrap();
+		let Selection::Field(field) = &document.operations[0].selections[0] else {
+			panic!("expected a field");
+		};
+		assert_eq!(field.arguments[0].value, Value::String("one\n  two".into()));
+	}
+
+	#[test]
+	fn reports_syntax_errors() {
+		let error = |text: &str| {
+			let err = parse(text).unwrap_err();
+			(err.line, err.column, err.message)
+		};
+		assert_eq!(
+			error("{ item(id: 1 }"),
+			(1, 14, "expected a name, got `}`".into())
+		);
+		assert_eq!(
+			error("query {\n  item {\n"),
+			(2, 9, "expected `}`, got the end of the query".into())
+		);
+		assert_eq!(error("{ }"), (1, 3, "expected a field, got `}`".into()));
+		assert_eq!(
+			error("query ($a: Int = $b) { a }"),
+			(1, 18, "variables are not allowed here".into())
+		);
+		assert_eq!(error("{ a(b: \"c) }"), (1, 8, "unterminated string".into()));
+		assert_eq!(error(""), (1, 1, "empty query".into()));
+		assert_eq!(
+			error("type Item { id: ID }"),
+			(
+				1,
+				1,
+				"expected `query`, `mutation`, `subscription`, `fragment` or `{`, got `type`"
+					.into()
+			)
+		);
+	}
+}
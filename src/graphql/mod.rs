@@ -0,0 +1,404 @@
+//! GraphQL requests: a query with its variables and operation name, sent
+//! as a JSON body. Queries are validated before sending against the schema
+//! of the endpoint, fetched with the introspection query and cached on
+//! disk, and the `errors` of a response are reported as a failed check.
+
+pub mod document;
+pub mod schema;
+pub mod validate;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Map, Value};
+
+use crate::assert::AssertionResult;
+use crate::client::Client;
+use crate::error::{Error, ParseError, Result};
+use crate::http::{Request, Response};
+use crate::url::Url;
+
+pub use schema::Schema;
+
+/// Introspection query reading the types of a schema and their fields.
+pub const INTROSPECTION: &str = "\
+query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types {
+      kind
+      name
+      fields(includeDeprecated: true) { name args { ...InputValue } type { ...TypeRef } }
+      inputFields { ...InputValue }
+      possibleTypes { name }
+      enumValues(includeDeprecated: true) { name }
+    }
+    directives { name args { ...InputValue } }
+  }
+}
+
+fragment InputValue on __InputValue {
+  name
+  type { ...TypeRef }
+  defaultValue
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name } } } } } }
+}
+";
+
+/// Body of a GraphQL request.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Query {
+	pub query: String,
+	/// Object of the variable values.
+	pub variables: Option<Value>,
+	/// Operation to run when the query has several.
+	pub operation_name: Option<String>,
+}
+
+impl Query {
+	pub fn new(query: impl Into<String>) -> Self {
+		Query {
+			query: query.into(),
+			..Query::default()
+		}
+	}
+
+	/// `{"query": ..., "variables": ..., "operationName": ...}`
+	pub fn to_json(&self) -> String {
+		let mut body = Map::new();
+		body.insert("query".into(), self.query.clone().into());
+		if let Some(variables) = &self.variables {
+			body.insert("variables".into(), variables.clone());
+		}
+		if let Some(name) = &self.operation_name {
+			body.insert("operationName".into(), name.clone().into());
+		}
+		Value::Object(body).to_string()
+	}
+
+	/// Reads the JSON body of a GraphQL request.
+	pub fn from_json(body: &[u8]) -> Option<Query> {
+		let body: Value = serde_json::from_slice(body).ok()?;
+		Some(Query {
+			query: body["query"].as_str()?.to_string(),
+			variables: body
+				.get("variables")
+				.filter(|value| !value.is_null())
+				.cloned(),
+			operation_name: body["operationName"].as_str().map(str::to_string),
+		})
+	}
+
+	/// Sets the query as the JSON body of a request.
+	pub fn apply(&self, request: &mut Request) {
+		if !request.headers.contains("Content-Type") {
+			request.headers.set("Content-Type", "application/json");
+		}
+		if !request.headers.contains("Accept") {
+			request.headers.set("Accept", "application/json");
+		}
+		request.body = self.to_json().into_bytes();
+	}
+
+	/// Parses the query and checks it against a schema, returning the
+	/// first problem found.
+	pub fn check(&self, schema: &Schema) -> std::result::Result<(), ParseError> {
+		let document = document::parse(&self.query)?;
+		if let Some(err) = validate::validate(schema, &document).into_iter().next() {
+			return Err(err);
+		}
+		let named = |name: &str| {
+			document
+				.operations
+				.iter()
+				.any(|operation| operation.name.as_deref() == Some(name))
+		};
+		match &self.operation_name {
+			Some(name) if !named(name) => Err(ParseError::new(
+				1,
+				1,
+				format!("no operation named `{name}` in the query"),
+			)),
+			None if document.operations.len() > 1 => Err(ParseError::new(
+				1,
+				1,
+				"the query has several operations, name the one to run",
+			)),
+			_ => Ok(()),
+		}
+	}
+}
+
+/// Result of the check that a response has no `errors`, with a line per
+/// error as details when it failed.
+pub fn check_errors(response: &Response, line: usize) -> AssertionResult {
+	let body: Value = serde_json::from_slice(&response.body).unwrap_or_default();
+	let errors: Vec<String> = body["errors"]
+		.as_array()
+		.into_iter()
+		.flatten()
+		.map(describe_error)
+		.collect();
+	let message = match errors.as_slice() {
+		[] => String::new(),
+		[error] => error.clone(),
+		[first, rest @ ..] => format!("{first} (and {} more)", rest.len()),
+	};
+	AssertionResult {
+		assertion: "no GraphQL errors".into(),
+		line,
+		passed: errors.is_empty(),
+		message,
+		diff: (errors.len() > 1).then(|| errors.join("\n")),
+	}
+}
+
+/// `message at items.0.name (2:3)`
+fn describe_error(error: &Value) -> String {
+	let mut text = match error["message"].as_str() {
+		Some(message) => message.to_string(),
+		None => error.to_string(),
+	};
+	if let Some(path) = error["path"].as_array().filter(|path| !path.is_empty()) {
+		let path: Vec<String> = path
+			.iter()
+			.map(|part| match part {
+				Value::String(name) => name.clone(),
+				other => other.to_string(),
+			})
+			.collect();
+		text.push_str(&format!(" at {}", path.join(".")));
+	}
+	if let Some(location) = error["locations"].get(0) {
+		text.push_str(&format!(" ({}:{})", location["line"], location["column"]));
+	}
+	text
+}
+
+/// Schemas of GraphQL endpoints, kept for a run in memory and between runs
+/// in a cache directory. A query found invalid with a schema from the cache
+/// is checked again with a fresh one, in case the schema changed.
+#[derive(Debug)]
+pub struct Schemas {
+	/// Cache directory, `None` to only keep the schemas in memory.
+	pub dir: Option<PathBuf>,
+	/// Schemas by endpoint, with true for those fetched during the run.
+	loaded: Mutex<HashMap<String, (Arc<Schema>, bool)>>,
+}
+
+impl Default for Schemas {
+	fn default() -> Self {
+		Schemas::new(cache_dir())
+	}
+}
+
+impl Schemas {
+	pub fn new(dir: Option<PathBuf>) -> Self {
+		Schemas {
+			dir,
+			loaded: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Checks the query of a request against the schema of its endpoint,
+	/// which is introspected with the same headers. The outer error is a
+	/// failure to get the schema.
+	pub fn check(
+		&self,
+		client: &Client,
+		request: &Request,
+		query: &Query,
+	) -> Result<std::result::Result<(), ParseError>> {
+		let (schema, fresh) = self.get(client, request, false)?;
+		let result = query.check(&schema);
+		if result.is_ok() || fresh {
+			return Ok(result);
+		}
+		let (schema, _) = self.get(client, request, true)?;
+		Ok(query.check(&schema))
+	}
+
+	/// Schema of the endpoint of a request, from memory, the cache or
+	/// introspection, with true if it was introspected during this run.
+	fn get(
+		&self,
+		client: &Client,
+		request: &Request,
+		refresh: bool,
+	) -> Result<(Arc<Schema>, bool)> {
+		let key = endpoint(&request.url);
+		if let Some(found) = self
+			.loaded
+			.lock()
+			.unwrap_or_else(|err| err.into_inner())
+			.get(&key)
+		{
+			if !refresh || found.1 {
+				return Ok(found.clone());
+			}
+		}
+		let path = self
+			.dir
+			.as_ref()
+			.map(|dir| dir.join(file_name(&request.url)));
+		let cached = match (&path, refresh) {
+			(Some(path), false) => std::fs::read(path)
+				.ok()
+				.and_then(|data| serde_json::from_slice::<Value>(&data).ok())
+				.and_then(|result| Schema::from_introspection(&result).ok()),
+			_ => None,
+		};
+		let found = match cached {
+			Some(schema) => (Arc::new(schema), false),
+			None => {
+				let (result, schema) = introspect(client, request)?;
+				if let Some(path) = &path {
+					// the cache only saves time, failing to write it is harmless
+					if let Some(dir) = path.parent() {
+						let _ = std::fs::create_dir_all(dir);
+					}
+					let _ = std::fs::write(path, result.to_string());
+				}
+				(Arc::new(schema), true)
+			}
+		};
+		self.loaded
+			.lock()
+			.unwrap_or_else(|err| err.into_inner())
+			.insert(key, found.clone());
+		Ok(found)
+	}
+}
+
+/// Sends the introspection query to the endpoint of a request, with its
+/// headers, returning the result and the schema read from it.
+fn introspect(client: &Client, request: &Request) -> Result<(Value, Schema)> {
+	let mut introspection = Request::new("POST", request.url.clone());
+	for (name, value) in request.headers.iter() {
+		if !name.eq_ignore_ascii_case("Content-Length") {
+			introspection.headers.append(name, value);
+		}
+	}
+	Query {
+		operation_name: Some("IntrospectionQuery".into()),
+		..Query::new(INTROSPECTION)
+	}
+	.apply(&mut introspection);
+	let response = client.send(&introspection)?;
+	let failed = |message: String| {
+		Error::Protocol(format!(
+			"cannot get the GraphQL schema of {}: {message}",
+			endpoint(&request.url)
+		))
+	};
+	if !(200..300).contains(&response.status) {
+		return Err(failed(format!("status {}", response.status)));
+	}
+	let result: Value = serde_json::from_slice(&response.body)
+		.map_err(|err| failed(format!("invalid JSON: {err}")))?;
+	if let Some(error) = result["errors"].get(0) {
+		if result["data"].is_null() {
+			return Err(failed(describe_error(error)));
+		}
+	}
+	let schema = Schema::from_introspection(&result).map_err(failed)?;
+	Ok((json!({ "data": result["data"] }), schema))
+}
+
+/// URL of an endpoint, without the query string.
+fn endpoint(url: &Url) -> String {
+	format!("{}://{}{}", url.scheme, url.authority(), url.path_only())
+}
+
+/// Name of the cache file of an endpoint, e.g. `api.local_8080_graphql.json`.
+fn file_name(url: &Url) -> String {
+	let name = format!("{}_{}{}", url.host, url.port, url.path_only());
+	let name: String = name
+		.chars()
+		.map(
+			|c| match c.is_ascii_alphanumeric() || matches!(c, '.' | '-') {
+				true => c,
+				false => '_',
+			},
+		)
+		.collect();
+	format!("{}.json", name.trim_end_matches('_'))
+}
+
+/// `$XDG_CACHE_HOME/webcat/graphql`, or `~/.cache/webcat/graphql`.
+fn cache_dir() -> Option<PathBuf> {
+	let base = match std::env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+		Some(dir) => PathBuf::from(dir),
+		None => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+	};
+	Some(base.join("webcat").join("graphql"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn response(body: &str) -> Response {
+		Response {
+			status: 200,
+			body: body.as_bytes().to_vec(),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn reports_errors_of_responses() {
+		let result = check_errors(&response(r#"{"data": {"items": []}}"#), 3);
+		assert!(result.passed && result.diff.is_none());
+		let body = r#"{"data": null, "errors": [
+			{"message": "not found", "path": ["item", 0, "owner"], "locations": [{"line": 2, "column": 5}]},
+			{"message": "denied"}
+		]}"#;
+		let result = check_errors(&response(body), 3);
+		assert!(!result.passed);
+		assert_eq!(
+			result.message,
+			"not found at item.0.owner (2:5) (and 1 more)"
+		);
+		assert_eq!(
+			result.diff.as_deref(),
+			Some("not found at item.0.owner (2:5)\ndenied")
+		);
+	}
+
+	#[test]
+	fn checks_the_operation_to_run() {
+		let schema = Schema::from_introspection(&json!({
+			"__schema": {
+				"queryType": {"name": "Query"},
+				"types": [
+					{"kind": "OBJECT", "name": "Query", "fields": [
+						{"name": "ok", "args": [], "type": {"kind": "SCALAR", "name": "Boolean"}}
+					]},
+					{"kind": "SCALAR", "name": "Boolean"}
+				]
+			}
+		}))
+		.unwrap();
+		let mut query = Query::new("query A { ok }\nquery B { ok }");
+		let err = query.check(&schema).unwrap_err();
+		assert_eq!(
+			err.message,
+			"the query has several operations, name the one to run"
+		);
+		query.operation_name = Some("C".into());
+		let err = query.check(&schema).unwrap_err();
+		assert_eq!(err.message, "no operation named `C` in the query");
+		query.operation_name = Some("B".into());
+		assert!(query.check(&schema).is_ok());
+		assert_eq!(Query::from_json(query.to_json().as_bytes()), Some(query));
+	}
+}
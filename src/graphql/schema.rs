@@ -0,0 +1,215 @@
+//! Schema of a GraphQL endpoint, read from the result of the introspection
+//! query.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::Value;
+
+/// Type of a field, argument or variable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeRef {
+	Named(String),
+	List(Box<TypeRef>),
+	NonNull(Box<TypeRef>),
+}
+
+impl TypeRef {
+	/// Name of the innermost type, e.g. `Int` of `[Int!]!`.
+	pub fn name(&self) -> &str {
+		match self {
+			TypeRef::Named(name) => name,
+			TypeRef::List(inner) | TypeRef::NonNull(inner) => inner.name(),
+		}
+	}
+
+	pub fn is_non_null(&self) -> bool {
+		matches!(self, TypeRef::NonNull(_))
+	}
+
+	/// Reads an introspection type reference, `{kind, name, ofType}`.
+	fn from_introspection(value: &Value) -> Result<TypeRef, String> {
+		let of_type = || TypeRef::from_introspection(&value["ofType"]).map(Box::new);
+		match value["kind"].as_str() {
+			Some("NON_NULL") => Ok(TypeRef::NonNull(of_type()?)),
+			Some("LIST") => Ok(TypeRef::List(of_type()?)),
+			_ => match value["name"].as_str() {
+				Some(name) => Ok(TypeRef::Named(name.to_string())),
+				None => Err(format!("invalid type reference {value}")),
+			},
+		}
+	}
+}
+
+/// `[Int!]!`
+impl fmt::Display for TypeRef {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			TypeRef::Named(name) => f.write_str(name),
+			TypeRef::List(inner) => write!(f, "[{inner}]"),
+			TypeRef::NonNull(inner) => write!(f, "{inner}!"),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TypeKind {
+	Scalar,
+	Object,
+	Interface,
+	Union,
+	Enum,
+	InputObject,
+}
+
+impl TypeKind {
+	/// Returns true for the types with fields to select.
+	pub fn is_composite(self) -> bool {
+		matches!(
+			self,
+			TypeKind::Object | TypeKind::Interface | TypeKind::Union
+		)
+	}
+
+	/// Returns true for the types of arguments and variables.
+	pub fn is_input(self) -> bool {
+		matches!(
+			self,
+			TypeKind::Scalar | TypeKind::Enum | TypeKind::InputObject
+		)
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Type {
+	pub name: String,
+	pub kind: TypeKind,
+	pub fields: Vec<Field>,
+	/// Fields of an input object.
+	pub input_fields: Vec<InputValue>,
+	/// Object types of an interface or union.
+	pub possible_types: Vec<String>,
+	pub enum_values: Vec<String>,
+}
+
+impl Type {
+	pub fn field(&self, name: &str) -> Option<&Field> {
+		self.fields.iter().find(|field| field.name == name)
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+	pub name: String,
+	pub args: Vec<InputValue>,
+	pub ty: TypeRef,
+}
+
+/// Argument, or field of an input object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputValue {
+	pub name: String,
+	pub ty: TypeRef,
+	pub has_default: bool,
+}
+
+impl InputValue {
+	/// Returns true if the value must be given.
+	pub fn is_required(&self) -> bool {
+		self.ty.is_non_null() && !self.has_default
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Schema {
+	pub query: Option<String>,
+	pub mutation: Option<String>,
+	pub subscription: Option<String>,
+	pub types: BTreeMap<String, Type>,
+	/// Arguments of the directives by name.
+	pub directives: BTreeMap<String, Vec<InputValue>>,
+}
+
+impl Schema {
+	/// Reads the result of the introspection query, with or without its
+	/// `data` member.
+	pub fn from_introspection(result: &Value) -> Result<Schema, String> {
+		let schema = match result.get("data") {
+			Some(data) => &data["__schema"],
+			None => &result["__schema"],
+		};
+		if !schema.is_object() {
+			return Err("no `__schema` in the introspection result".into());
+		}
+		let root = |name: &str| schema[name]["name"].as_str().map(str::to_string);
+		let mut types = BTreeMap::new();
+		for ty in array(&schema["types"]) {
+			let name = ty["name"].as_str().unwrap_or_default().to_string();
+			let kind = match ty["kind"].as_str().unwrap_or_default() {
+				"SCALAR" => TypeKind::Scalar,
+				"OBJECT" => TypeKind::Object,
+				"INTERFACE" => TypeKind::Interface,
+				"UNION" => TypeKind::Union,
+				"ENUM" => TypeKind::Enum,
+				"INPUT_OBJECT" => TypeKind::InputObject,
+				other => return Err(format!("unknown kind `{other}` of type `{name}`")),
+			};
+			let fields = array(&ty["fields"])
+				.map(|field| {
+					Ok(Field {
+						name: string(&field["name"]),
+						args: input_values(&field["args"])?,
+						ty: TypeRef::from_introspection(&field["type"])?,
+					})
+				})
+				.collect::<Result<_, String>>()?;
+			let value = Type {
+				name: name.clone(),
+				kind,
+				fields,
+				input_fields: input_values(&ty["inputFields"])?,
+				possible_types: array(&ty["possibleTypes"])
+					.map(|ty| string(&ty["name"]))
+					.collect(),
+				enum_values: array(&ty["enumValues"])
+					.map(|value| string(&value["name"]))
+					.collect(),
+			};
+			types.insert(name, value);
+		}
+		let mut directives = BTreeMap::new();
+		for directive in array(&schema["directives"]) {
+			directives.insert(
+				string(&directive["name"]),
+				input_values(&directive["args"])?,
+			);
+		}
+		Ok(Schema {
+			query: root("queryType"),
+			mutation: root("mutationType"),
+			subscription: root("subscriptionType"),
+			types,
+			directives,
+		})
+	}
+}
+
+fn input_values(value: &Value) -> Result<Vec<InputValue>, String> {
+	array(value)
+		.map(|input| {
+			Ok(InputValue {
+				name: string(&input["name"]),
+				ty: TypeRef::from_introspection(&input["type"])?,
+				has_default: !input["defaultValue"].is_null(),
+			})
+		})
+		.collect()
+}
+
+fn array(value: &Value) -> impl Iterator<Item = &Value> {
+	value.as_array().into_iter().flatten()
+}
+
+fn string(value: &Value) -> String {
+	value.as_str().unwrap_or_default().to_string()
+}
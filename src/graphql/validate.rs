@@ -0,0 +1,745 @@
+//! Validation of a query document against a schema: the fields, arguments,
+//! fragments and variables it uses, and the values written in it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::document::{
+	Argument, Directive, Document, Fragment, Operation, OperationKind, Position, Selection, Value,
+};
+use super::schema::{InputValue, Schema, Type, TypeKind, TypeRef};
+use crate::error::ParseError;
+
+/// Returns the problems of a document, in the order of their positions.
+pub fn validate(schema: &Schema, document: &Document) -> Vec<ParseError> {
+	let mut validator = Validator {
+		schema,
+		fragments: BTreeMap::new(),
+		errors: Vec::new(),
+	};
+	for fragment in &document.fragments {
+		if validator
+			.fragments
+			.insert(&fragment.name, fragment)
+			.is_some()
+		{
+			validator.error(
+				fragment.position,
+				format!("fragment `{}` is defined twice", fragment.name),
+			);
+		}
+	}
+	let mut names = BTreeSet::new();
+	for operation in &document.operations {
+		match &operation.name {
+			None if document.operations.len() > 1 => validator.error(
+				operation.position,
+				"an anonymous operation must be the only one of the query",
+			),
+			Some(name) if !names.insert(name) => validator.error(
+				operation.position,
+				format!("operation `{name}` is defined twice"),
+			),
+			_ => {}
+		}
+		validator.operation(operation);
+	}
+	let mut used = BTreeSet::new();
+	for operation in &document.operations {
+		spreads(&operation.selections, &mut used);
+	}
+	for fragment in &document.fragments {
+		validator.fragment(fragment);
+		spreads(&fragment.selections, &mut used);
+	}
+	for fragment in &document.fragments {
+		if !used.contains(fragment.name.as_str()) {
+			validator.error(
+				fragment.position,
+				format!("fragment `{}` is never used", fragment.name),
+			);
+		}
+	}
+	let mut errors = validator.errors;
+	errors.sort_by_key(|err| (err.line, err.column));
+	errors.dedup();
+	errors
+}
+
+struct Validator<'a> {
+	schema: &'a Schema,
+	fragments: BTreeMap<&'a str, &'a Fragment>,
+	errors: Vec<ParseError>,
+}
+
+impl<'a> Validator<'a> {
+	fn error(&mut self, (line, column): Position, message: impl Into<String>) {
+		self.errors.push(ParseError::new(line, column, message));
+	}
+
+	fn operation(&mut self, operation: &'a Operation) {
+		let root = match operation.kind {
+			OperationKind::Query => &self.schema.query,
+			OperationKind::Mutation => &self.schema.mutation,
+			OperationKind::Subscription => &self.schema.subscription,
+		};
+		let label = operation.label();
+		let mut defined = BTreeSet::new();
+		for variable in &operation.variables {
+			if !defined.insert(variable.name.as_str()) {
+				self.error(
+					variable.position,
+					format!("variable `${}` is defined twice", variable.name),
+				);
+			}
+			let name = variable.ty.name();
+			match self.schema.types.get(name) {
+				None => self.error(variable.position, format!("unknown type `{name}`")),
+				Some(ty) if !ty.kind.is_input() => self.error(
+					variable.position,
+					format!(
+						"variable `${}` cannot be of type `{name}`, which is not an input type",
+						variable.name
+					),
+				),
+				Some(_) => {
+					if let Some(default) = &variable.default {
+						let context = format!("variable `${}`", variable.name);
+						self.value(default, &variable.ty, variable.position, &context);
+					}
+				}
+			}
+		}
+		self.directives(&operation.directives);
+		match root.as_ref().and_then(|name| self.schema.types.get(name)) {
+			Some(root) => self.selections(root, &operation.selections),
+			None => self.error(
+				operation.position,
+				format!("the schema has no {} type", operation.kind.as_str()),
+			),
+		}
+
+		let mut used = Vec::new();
+		for directive in &operation.directives {
+			argument_variables(&directive.arguments, &mut used);
+		}
+		self.variables(&operation.selections, &mut BTreeSet::new(), &mut used);
+		let mut reported = BTreeSet::new();
+		for (name, position) in &used {
+			if !defined.contains(name.as_str()) && reported.insert(name) {
+				self.error(
+					*position,
+					format!("variable `${name}` is not defined by `{label}`"),
+				);
+			}
+		}
+		for variable in &operation.variables {
+			if !used.iter().any(|(name, _)| *name == variable.name) {
+				self.error(
+					variable.position,
+					format!("variable `${}` is never used in `{label}`", variable.name),
+				);
+			}
+		}
+	}
+
+	fn fragment(&mut self, fragment: &'a Fragment) {
+		self.directives(&fragment.directives);
+		if let Some(ty) = self.condition(&fragment.type_condition, fragment.position) {
+			self.selections(ty, &fragment.selections);
+		}
+		if self.spreads_itself(&fragment.name, &fragment.selections, &mut BTreeSet::new()) {
+			self.error(
+				fragment.position,
+				format!("fragment `{}` spreads itself", fragment.name),
+			);
+		}
+	}
+
+	/// Returns true if the selections lead to a spread of `name`.
+	fn spreads_itself(
+		&self,
+		name: &str,
+		selections: &'a [Selection],
+		visited: &mut BTreeSet<&'a str>,
+	) -> bool {
+		selections.iter().any(|selection| match selection {
+			Selection::Field(field) => self.spreads_itself(name, &field.selections, visited),
+			Selection::Inline { selections, .. } => self.spreads_itself(name, selections, visited),
+			Selection::Spread { name: spread, .. } if spread == name => true,
+			Selection::Spread { name: spread, .. } => {
+				visited.insert(spread)
+					&& self.fragments.get(spread.as_str()).is_some_and(|fragment| {
+						self.spreads_itself(name, &fragment.selections, visited)
+					})
+			}
+		})
+	}
+
+	/// Type of a fragment condition, if it has fields.
+	fn condition(&mut self, name: &str, position: Position) -> Option<&'a Type> {
+		match self.schema.types.get(name) {
+			None => {
+				self.error(position, format!("unknown type `{name}`"));
+				None
+			}
+			Some(ty) if !ty.kind.is_composite() => {
+				self.error(
+					position,
+					format!("fragments cannot be on `{name}`, which has no fields"),
+				);
+				None
+			}
+			Some(ty) => Some(ty),
+		}
+	}
+
+	fn selections(&mut self, parent: &'a Type, selections: &'a [Selection]) {
+		for selection in selections {
+			match selection {
+				Selection::Field(field) => {
+					self.directives(&field.directives);
+					let definition = match field.name.as_str() {
+						"__typename" => {
+							self.leaf(&field.name, "String", &field.selections, field.position);
+							continue;
+						}
+						"__schema" | "__type"
+							if Some(&parent.name) == self.schema.query.as_ref() =>
+						{
+							// introspection fields of the query type, left unchecked
+							continue;
+						}
+						_ if parent.kind == TypeKind::Union => {
+							self.error(
+								field.position,
+								format!(
+									"cannot select `{}` on union `{}`, only on its types with fragments",
+									field.name, parent.name
+								),
+							);
+							continue;
+						}
+						name => match parent.field(name) {
+							Some(definition) => definition,
+							None => {
+								self.error(
+									field.position,
+									format!("unknown field `{name}` on type `{}`", parent.name),
+								);
+								continue;
+							}
+						},
+					};
+					let owner = format!("{}.{}", parent.name, field.name);
+					self.arguments(&field.arguments, &definition.args, &owner, field.position);
+					let name = definition.ty.name();
+					match self.schema.types.get(name) {
+						Some(ty) if ty.kind.is_composite() => {
+							if field.selections.is_empty() {
+								self.error(
+									field.position,
+									format!(
+										"field `{}` of type `{}` needs a selection of subfields",
+										field.name, definition.ty
+									),
+								);
+							} else {
+								self.selections(ty, &field.selections);
+							}
+						}
+						_ => self.leaf(
+							&field.name,
+							&definition.ty.to_string(),
+							&field.selections,
+							field.position,
+						),
+					}
+				}
+				Selection::Spread {
+					name,
+					directives,
+					position,
+				} => {
+					self.directives(directives);
+					match self.fragments.get(name.as_str()) {
+						None => self.error(*position, format!("unknown fragment `{name}`")),
+						Some(fragment) => {
+							if let Some(ty) = self.schema.types.get(&fragment.type_condition) {
+								self.applies(parent, ty, &format!("fragment `{name}`"), *position);
+							}
+						}
+					}
+				}
+				Selection::Inline {
+					type_condition,
+					directives,
+					selections,
+					position,
+				} => {
+					self.directives(directives);
+					let ty = match type_condition {
+						None => parent,
+						Some(name) => match self.condition(name, *position) {
+							Some(ty) => {
+								self.applies(parent, ty, "fragment", *position);
+								ty
+							}
+							None => continue,
+						},
+					};
+					self.selections(ty, selections);
+				}
+			}
+		}
+	}
+
+	/// Checks that a field of a type without fields has no subfields.
+	fn leaf(&mut self, name: &str, ty: &str, selections: &[Selection], position: Position) {
+		if !selections.is_empty() {
+			self.error(
+				position,
+				format!("field `{name}` of type `{ty}` has no subfields"),
+			);
+		}
+	}
+
+	/// Checks that a fragment on `ty` can apply to `parent`, that is that
+	/// some object type has both.
+	fn applies(&mut self, parent: &Type, ty: &Type, label: &str, position: Position) {
+		let objects = |ty: &Type| -> BTreeSet<String> {
+			match ty.kind {
+				TypeKind::Object => BTreeSet::from([ty.name.clone()]),
+				_ => ty.possible_types.iter().cloned().collect(),
+			}
+		};
+		if objects(parent).is_disjoint(&objects(ty)) {
+			self.error(
+				position,
+				format!(
+					"{label} on `{}` can never apply to `{}`",
+					ty.name, parent.name
+				),
+			);
+		}
+	}
+
+	fn arguments(
+		&mut self,
+		arguments: &[Argument],
+		definitions: &[InputValue],
+		owner: &str,
+		position: Position,
+	) {
+		for argument in arguments {
+			match definitions.iter().find(|def| def.name == argument.name) {
+				Some(definition) => {
+					let context = format!("argument `{}` of `{owner}`", argument.name);
+					self.value(&argument.value, &definition.ty, argument.position, &context);
+				}
+				None => self.error(
+					argument.position,
+					format!("unknown argument `{}` of `{owner}`", argument.name),
+				),
+			}
+		}
+		for definition in definitions {
+			if definition.is_required() && !arguments.iter().any(|arg| arg.name == definition.name)
+			{
+				self.error(
+					position,
+					format!(
+						"missing argument `{}: {}` of `{owner}`",
+						definition.name, definition.ty
+					),
+				);
+			}
+		}
+	}
+
+	fn directives(&mut self, directives: &[Directive]) {
+		for directive in directives {
+			// servers without directives in their introspection result
+			if self.schema.directives.is_empty() {
+				return;
+			}
+			match self.schema.directives.get(&directive.name) {
+				Some(definitions) => {
+					let owner = format!("@{}", directive.name);
+					self.arguments(
+						&directive.arguments,
+						definitions,
+						&owner,
+						directive.position,
+					);
+				}
+				None => self.error(
+					directive.position,
+					format!("unknown directive `@{}`", directive.name),
+				),
+			}
+		}
+	}
+
+	/// Checks a value written in the query against the type expected.
+	/// Variables are checked by the server once their values are known.
+	fn value(&mut self, value: &Value, ty: &TypeRef, position: Position, context: &str) {
+		let mismatch = |this: &mut Self| {
+			this.error(position, format!("{context} expects `{ty}`, got {value}"));
+		};
+		match (ty, value) {
+			(_, Value::Variable(_)) => {}
+			(TypeRef::NonNull(_), Value::Null) => mismatch(self),
+			(TypeRef::NonNull(inner), _) => self.value(value, inner, position, context),
+			(_, Value::Null) => {}
+			(TypeRef::List(inner), Value::List(items)) => {
+				for item in items {
+					self.value(item, inner, position, context);
+				}
+			}
+			// a single value stands for a list of one
+			(TypeRef::List(inner), _) => self.value(value, inner, position, context),
+			(TypeRef::Named(name), _) => {
+				let Some(named) = self.schema.types.get(name) else {
+					return;
+				};
+				match (named.kind, value) {
+					(TypeKind::Scalar, _) => {
+						let valid = match name.as_str() {
+							"Int" => matches!(value, Value::Int(_)),
+							"Float" => matches!(value, Value::Int(_) | Value::Float(_)),
+							"String" => matches!(value, Value::String(_)),
+							"Boolean" => matches!(value, Value::Boolean(_)),
+							"ID" => matches!(value, Value::String(_) | Value::Int(_)),
+							// custom scalars take any literal
+							_ => true,
+						};
+						if !valid {
+							mismatch(self);
+						}
+					}
+					(TypeKind::Enum, Value::Enum(item)) if named.enum_values.contains(item) => {}
+					(TypeKind::Enum, Value::Enum(item)) => self.error(
+						position,
+						format!("{context}: `{item}` is not a value of `{name}`"),
+					),
+					(TypeKind::InputObject, Value::Object(fields)) => {
+						for (field, item) in fields {
+							match named.input_fields.iter().find(|def| def.name == *field) {
+								Some(def) => {
+									let context = format!("field `{field}` of `{name}`");
+									self.value(item, &def.ty, position, &context);
+								}
+								None => self.error(
+									position,
+									format!("{context}: unknown field `{field}` of `{name}`"),
+								),
+							}
+						}
+						for def in &named.input_fields {
+							if def.is_required()
+								&& !fields.iter().any(|(field, _)| *field == def.name)
+							{
+								self.error(
+									position,
+									format!(
+										"{context}: missing field `{}: {}` of `{name}`",
+										def.name, def.ty
+									),
+								);
+							}
+						}
+					}
+					_ => mismatch(self),
+				}
+			}
+		}
+	}
+
+	/// Collects the variables used by selections and the fragments they
+	/// spread, each fragment once.
+	fn variables(
+		&self,
+		selections: &'a [Selection],
+		visited: &mut BTreeSet<&'a str>,
+		used: &mut Vec<(String, Position)>,
+	) {
+		for selection in selections {
+			match selection {
+				Selection::Field(field) => {
+					argument_variables(&field.arguments, used);
+					for directive in &field.directives {
+						argument_variables(&directive.arguments, used);
+					}
+					self.variables(&field.selections, visited, used);
+				}
+				Selection::Spread {
+					name, directives, ..
+				} => {
+					for directive in directives {
+						argument_variables(&directive.arguments, used);
+					}
+					if let Some(fragment) = self.fragments.get(name.as_str()) {
+						if visited.insert(name) {
+							for directive in &fragment.directives {
+								argument_variables(&directive.arguments, used);
+							}
+							self.variables(&fragment.selections, visited, used);
+						}
+					}
+				}
+				Selection::Inline {
+					directives,
+					selections,
+					..
+				} => {
+					for directive in directives {
+						argument_variables(&directive.arguments, used);
+					}
+					self.variables(selections, visited, used);
+				}
+			}
+		}
+	}
+}
+
+fn argument_variables(arguments: &[Argument], used: &mut Vec<(String, Position)>) {
+	fn collect(value: &Value, position: Position, used: &mut Vec<(String, Position)>) {
+		match value {
+			Value::Variable(name) => used.push((name.clone(), position)),
+			Value::List(items) => items.iter().for_each(|item| collect(item, position, used)),
+			Value::Object(fields) => fields
+				.iter()
+				.for_each(|(_, item)| collect(item, position, used)),
+			_ => {}
+		}
+	}
+	for argument in arguments {
+		collect(&argument.value, argument.position, used);
+	}
+}
+
+/// Names of the fragments spread by selections.
+fn spreads<'a>(selections: &'a [Selection], names: &mut BTreeSet<&'a str>) {
+	for selection in selections {
+		match selection {
+			Selection::Field(field) => spreads(&field.selections, names),
+			Selection::Spread { name, .. } => {
+				names.insert(name);
+			}
+			Selection::Inline { selections, .. } => spreads(selections, names),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::{json, Value};
+
+	use super::*;
+	use crate::graphql::document;
+
+	/// Introspection reference of a type written as in GraphQL.
+	fn type_ref(text: &str) -> Value {
+		if let Some(inner) = text.strip_suffix('!') {
+			return json!({ "kind": "NON_NULL", "name": null, "ofType": type_ref(inner) });
+		}
+		if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+			return json!({ "kind": "LIST", "name": null, "ofType": type_ref(inner) });
+		}
+		json!({ "kind": "SCALAR", "name": text, "ofType": null })
+	}
+
+	/// Input values written `name: Type` or `name: Type = default`.
+	fn inputs(list: &str) -> Value {
+		let values: Vec<Value> = list
+			.split(',')
+			.filter(|input| !input.trim().is_empty())
+			.map(|input| {
+				let (name, rest) = input.split_once(':').unwrap();
+				let (ty, default) = match rest.split_once('=') {
+					Some((ty, default)) => (ty, Value::from(default.trim())),
+					None => (rest, Value::Null),
+				};
+				json!({ "name": name.trim(), "type": type_ref(ty.trim()), "defaultValue": default })
+			})
+			.collect();
+		Value::from(values)
+	}
+
+	/// Type whose members are fields written `name(args): Type`, input
+	/// fields, possible types or enum values depending on its kind.
+	fn ty(kind: &str, name: &str, members: &[&str]) -> Value {
+		let fields = || -> Vec<Value> {
+			members
+				.iter()
+				.map(|field| {
+					let (head, ty) = field.rsplit_once("): ").unwrap_or_else(|| {
+						let (name, ty) = field.split_once(": ").unwrap();
+						(name, ty)
+					});
+					let (name, args) = head.split_once('(').unwrap_or((head, ""));
+					json!({ "name": name, "args": inputs(args), "type": type_ref(ty) })
+				})
+				.collect()
+		};
+		let names = |members: &[&str]| -> Value {
+			members.iter().map(|name| json!({ "name": name })).collect()
+		};
+		match kind {
+			"UNION" => json!({ "kind": kind, "name": name, "possibleTypes": names(members) }),
+			"ENUM" => json!({ "kind": kind, "name": name, "enumValues": names(members) }),
+			"INPUT_OBJECT" => {
+				json!({ "kind": kind, "name": name, "inputFields": inputs(&members.join(",")) })
+			}
+			"INTERFACE" => json!({
+				"kind": kind,
+				"name": name,
+				"fields": fields(),
+				"possibleTypes": names(&["Item", "User"]),
+			}),
+			_ => json!({ "kind": kind, "name": name, "fields": fields() }),
+		}
+	}
+
+	fn schema() -> Schema {
+		let types = vec![
+			ty(
+				"OBJECT",
+				"Query",
+				&[
+					"item(id: ID!): Item",
+					"items(first: Int = 10, filter: ItemFilter): [Item!]!",
+					"search(text: String!): [Result!]!",
+					"node(id: ID!): Node",
+				],
+			),
+			ty(
+				"OBJECT",
+				"Item",
+				&[
+					"id: ID!",
+					"name: String!",
+					"price: Float",
+					"color: Color",
+					"owner: User",
+				],
+			),
+			ty("OBJECT", "User", &["id: ID!", "name: String!"]),
+			ty("INTERFACE", "Node", &["id: ID!"]),
+			ty("UNION", "Result", &["Item", "User"]),
+			ty("ENUM", "Color", &["RED", "GREEN"]),
+			ty(
+				"INPUT_OBJECT",
+				"ItemFilter",
+				&["text: String!", "colors: [Color!]"],
+			),
+			ty("SCALAR", "ID", &[]),
+			ty("SCALAR", "String", &[]),
+			ty("SCALAR", "Int", &[]),
+			ty("SCALAR", "Float", &[]),
+			ty("SCALAR", "Boolean", &[]),
+		];
+		let result = json!({
+			"data": {
+				"__schema": {
+					"queryType": { "name": "Query" },
+					"mutationType": null,
+					"subscriptionType": null,
+					"types": types,
+					"directives": [
+						{ "name": "include", "args": inputs("if: Boolean!") },
+						{ "name": "skip", "args": inputs("if: Boolean!") },
+					],
+				}
+			}
+		});
+		Schema::from_introspection(&result).unwrap()
+	}
+
+	fn problems(query: &str) -> Vec<(usize, usize, String)> {
+		let document = document::parse(query).unwrap();
+		validate(&schema(), &document)
+			.into_iter()
+			.map(|err| (err.line, err.column, err.message))
+			.collect()
+	}
+
+	#[test]
+	fn accepts_valid_queries() {
+		let query = r#"
+query Items($first: Int, $colors: [Color!] = [RED], $full: Boolean!) {
+  items(first: $first, filter: {text: "lamp", colors: $colors}) {
+    ...ItemFields
+    owner @include(if: $full) { name }
+  }
+  search(text: "a") {
+    __typename
+    ... on Item { id price }
+    ... on User { name }
+  }
+  node(id: 7) { id ... on Item { color } }
+  first: item(id: "1") { name }
+}
+
+fragment ItemFields on Item { id name color }
+"#;
+		assert_eq!(problems(query), []);
+		assert_eq!(problems("{ items(first: 1.5e0 ) { id } }").len(), 1);
+		assert_eq!(problems("{ __schema { types { name } } }"), []);
+	}
+
+	#[test]
+	fn reports_problems_at_their_position() {
+		let cases = [
+			("{ item(id: 1) { nme } }", (1, 17, "unknown field `nme` on type `Item`")),
+			("{ items { id } item { id } }", (1, 16, "missing argument `id: ID!` of `Query.item`")),
+			("{ item(id: 1, size: 2) { id } }", (1, 15, "unknown argument `size` of `Query.item`")),
+			("{ item(id: 1) }", (1, 3, "field `item` of type `Item` needs a selection of subfields")),
+			("{ item(id: 1) { name { x } } }", (1, 17, "field `name` of type `String!` has no subfields")),
+			(
+				"{ items(first: \"ten\") { id } }",
+				(1, 9, "argument `first` of `Query.items` expects `Int`, got \"ten\""),
+			),
+			(
+				"{ items(filter: {colors: [BLUE]}) { id } }",
+				(1, 9, "field `colors` of `ItemFilter`: `BLUE` is not a value of `Color`"),
+			),
+			(
+				"{ items(filter: {}) { id } }",
+				(1, 9, "argument `filter` of `Query.items`: missing field `text: String!` of `ItemFilter`"),
+			),
+			("{ item(id: null) { id } }", (1, 8, "argument `id` of `Query.item` expects `ID!`, got null")),
+			(
+				"{ search(text: \"a\") { id } }",
+				(1, 23, "cannot select `id` on union `Result`, only on its types with fragments"),
+			),
+			("{ item(id: 1) { ...Missing } }", (1, 17, "unknown fragment `Missing`")),
+			(
+				"{ item(id: 1) { ... on User { id } } }",
+				(1, 17, "fragment on `User` can never apply to `Item`"),
+			),
+			("{ item(id: 1) { ... on Thing { id } } }", (1, 17, "unknown type `Thing`")),
+			("query Q { item(id: $id) { id } }", (1, 16, "variable `$id` is not defined by `query Q`")),
+			("query Q($id: ID, $n: Int) { item(id: $id) { id } }", (1, 18, "variable `$n` is never used in `query Q`")),
+			("query ($i: Item) { item(id: 1) { id } }", (1, 8, "variable `$i` cannot be of type `Item`, which is not an input type")),
+			("mutation { item(id: 1) { id } }", (1, 1, "the schema has no mutation type")),
+			("{ item(id: 1) { id @cached } }", (1, 20, "unknown directive `@cached`")),
+			("{ item(id: 1) { id @skip } }", (1, 20, "missing argument `if: Boolean!` of `@skip`")),
+			("{ a: item(id: 1) { id } }\n{ item(id: 2) { id } }", (1, 1, "an anonymous operation must be the only one of the query")),
+			("query A { item(id: 1) { id } }\nquery A { item(id: 2) { id } }", (2, 1, "operation `A` is defined twice")),
+			("{ item(id: 1) { id } }\nfragment F on Item { id }", (2, 1, "fragment `F` is never used")),
+			(
+				"{ item(id: 1) { ...F } }\nfragment F on Item { owner { ...G } }\nfragment G on User { ... on User { ...F } }",
+				(2, 1, "fragment `F` spreads itself"),
+			),
+		];
+		for (query, (line, column, message)) in cases {
+			let found = problems(query);
+			assert_eq!(
+				found.first(),
+				Some(&(line, column, message.to_string())),
+				"{query}: {found:?}"
+			);
+		}
+	}
+}
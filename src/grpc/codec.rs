@@ -0,0 +1,697 @@
+//! Protobuf binary encoding of messages written as JSON, and decoding back
+//! to JSON, with the proto3 JSON mapping: fields by their JSON or proto
+//! name, 64-bit integers as strings, bytes as base64, enums by name and
+//! maps as objects. Decoded messages only have the fields present on the
+//! wire, in the order they are declared.
+
+use serde_json::{Map, Number, Value};
+
+use super::schema::{Field, Kind, Message, Schema};
+use crate::util::{base64_decode, base64_encode};
+
+const VARINT: u64 = 0;
+const FIXED64: u64 = 1;
+const LEN: u64 = 2;
+const START_GROUP: u64 = 3;
+const END_GROUP: u64 = 4;
+const FIXED32: u64 = 5;
+
+/// Encodes a JSON object as the message `name` of the schema.
+pub fn encode(schema: &Schema, name: &str, value: &Value) -> Result<Vec<u8>, String> {
+	let mut out = Vec::new();
+	encode_message(schema, name, value, "", &mut out)?;
+	Ok(out)
+}
+
+/// Decodes the message `name` of the schema to JSON.
+pub fn decode(schema: &Schema, name: &str, bytes: &[u8]) -> Result<Value, String> {
+	decode_message(schema, name, bytes, "")
+}
+
+fn message<'a>(schema: &'a Schema, name: &str) -> Result<&'a Message, String> {
+	schema
+		.messages
+		.get(name)
+		.ok_or_else(|| format!("unknown message `{name}`"))
+}
+
+/// `path.name` of a field for error messages.
+fn child(path: &str, name: &str) -> String {
+	match path {
+		"" => name.to_string(),
+		path => format!("{path}.{name}"),
+	}
+}
+
+fn encode_message(
+	schema: &Schema,
+	name: &str,
+	value: &Value,
+	path: &str,
+	out: &mut Vec<u8>,
+) -> Result<(), String> {
+	let message = message(schema, name)?;
+	let object = value.as_object().ok_or_else(|| {
+		let at = if path.is_empty() {
+			String::new()
+		} else {
+			format!("`{path}`: ")
+		};
+		format!("{at}expected an object for `{name}`, got {value}")
+	})?;
+	for (key, value) in object {
+		let field = message
+			.fields
+			.iter()
+			.find(|field| field.json_name == *key || field.name == *key)
+			.ok_or_else(|| format!("`{}`: no such field in `{name}`", child(path, key)))?;
+		if value.is_null() {
+			continue;
+		}
+		let path = child(path, key);
+		if !field.repeated {
+			encode_field(schema, field, value, &path, out)?;
+			continue;
+		}
+		if let Kind::Message(entry) = &field.kind {
+			if message_is_map(schema, entry) {
+				let map = value
+					.as_object()
+					.ok_or_else(|| format!("`{path}`: expected an object, got {value}"))?;
+				for (key, value) in map {
+					let entry_value = serde_json::json!({ "key": key, "value": value });
+					encode_field(schema, field, &entry_value, &child(&path, key), out)?;
+				}
+				continue;
+			}
+		}
+		let items = value
+			.as_array()
+			.ok_or_else(|| format!("`{path}`: expected an array, got {value}"))?;
+		if field.packed {
+			let mut packed = Vec::new();
+			for (index, item) in items.iter().enumerate() {
+				encode_scalar(
+					schema,
+					&field.kind,
+					item,
+					&format!("{path}[{index}]"),
+					&mut packed,
+				)?;
+			}
+			write_tag(out, field.number, LEN);
+			write_varint(out, packed.len() as u64);
+			out.extend_from_slice(&packed);
+		} else {
+			for (index, item) in items.iter().enumerate() {
+				encode_field(schema, field, item, &format!("{path}[{index}]"), out)?;
+			}
+		}
+	}
+	Ok(())
+}
+
+fn message_is_map(schema: &Schema, name: &str) -> bool {
+	schema
+		.messages
+		.get(name)
+		.is_some_and(|entry| entry.map_entry)
+}
+
+/// Writes a single value of a field with its tag.
+fn encode_field(
+	schema: &Schema,
+	field: &Field,
+	value: &Value,
+	path: &str,
+	out: &mut Vec<u8>,
+) -> Result<(), String> {
+	write_tag(out, field.number, wire_type(&field.kind));
+	match &field.kind {
+		Kind::Message(name) => {
+			let mut nested = Vec::new();
+			encode_message(schema, name, value, path, &mut nested)?;
+			write_varint(out, nested.len() as u64);
+			out.extend_from_slice(&nested);
+			Ok(())
+		}
+		kind => encode_scalar(schema, kind, value, path, out),
+	}
+}
+
+/// Writes a value that is not a message, without a tag.
+fn encode_scalar(
+	schema: &Schema,
+	kind: &Kind,
+	value: &Value,
+	path: &str,
+	out: &mut Vec<u8>,
+) -> Result<(), String> {
+	let invalid = |expected: &str| format!("`{path}`: expected {expected}, got {value}");
+	let integer = |min: i128, max: i128| {
+		integer(value)
+			.filter(|number| (min..=max).contains(number))
+			.ok_or_else(|| invalid("an integer in range"))
+	};
+	match kind {
+		Kind::Int32 => write_varint(
+			out,
+			integer(i32::MIN.into(), i32::MAX.into())? as i64 as u64,
+		),
+		Kind::Int64 => write_varint(
+			out,
+			integer(i64::MIN.into(), i64::MAX.into())? as i64 as u64,
+		),
+		Kind::Uint32 => write_varint(out, integer(0, u32::MAX.into())? as u64),
+		Kind::Uint64 => write_varint(out, integer(0, u64::MAX.into())? as u64),
+		Kind::Sint32 | Kind::Sint64 => {
+			let number = match kind {
+				Kind::Sint32 => integer(i32::MIN.into(), i32::MAX.into())?,
+				_ => integer(i64::MIN.into(), i64::MAX.into())?,
+			} as i64;
+			write_varint(out, ((number << 1) ^ (number >> 63)) as u64);
+		}
+		Kind::Fixed32 => {
+			out.extend_from_slice(&(integer(0, u32::MAX.into())? as u32).to_le_bytes())
+		}
+		Kind::Sfixed32 => out
+			.extend_from_slice(&(integer(i32::MIN.into(), i32::MAX.into())? as i32).to_le_bytes()),
+		Kind::Fixed64 => {
+			out.extend_from_slice(&(integer(0, u64::MAX.into())? as u64).to_le_bytes())
+		}
+		Kind::Sfixed64 => out
+			.extend_from_slice(&(integer(i64::MIN.into(), i64::MAX.into())? as i64).to_le_bytes()),
+		Kind::Double => out.extend_from_slice(
+			&float(value)
+				.ok_or_else(|| invalid("a number"))?
+				.to_le_bytes(),
+		),
+		Kind::Float => {
+			let number = float(value).ok_or_else(|| invalid("a number"))? as f32;
+			out.extend_from_slice(&number.to_le_bytes());
+		}
+		Kind::Bool => {
+			let flag = match value {
+				Value::Bool(flag) => *flag,
+				// map keys
+				Value::String(text) if text == "true" || text == "false" => text == "true",
+				_ => return Err(invalid("a boolean")),
+			};
+			write_varint(out, flag as u64);
+		}
+		Kind::String => {
+			let text = value.as_str().ok_or_else(|| invalid("a string"))?;
+			write_varint(out, text.len() as u64);
+			out.extend_from_slice(text.as_bytes());
+		}
+		Kind::Bytes => {
+			let bytes = value
+				.as_str()
+				.and_then(|text| {
+					let text = text.replace('-', "+").replace('_', "/");
+					let padding = (4 - text.len() % 4) % 4;
+					base64_decode(&format!("{text}{}", "=".repeat(padding)))
+				})
+				.ok_or_else(|| invalid("base64"))?;
+			write_varint(out, bytes.len() as u64);
+			out.extend_from_slice(&bytes);
+		}
+		Kind::Enum(name) => {
+			let number = match value {
+				Value::String(text) => schema
+					.enums
+					.get(name)
+					.and_then(|values| values.number_of(text))
+					.ok_or_else(|| format!("`{path}`: `{text}` is not a value of `{name}`"))?,
+				_ => integer(i32::MIN.into(), i32::MAX.into())? as i32,
+			};
+			write_varint(out, number as i64 as u64);
+		}
+		Kind::Message(_) | Kind::Named(_) => return Err(invalid("a scalar")),
+	}
+	Ok(())
+}
+
+/// Integer given as a JSON number or a string.
+fn integer(value: &Value) -> Option<i128> {
+	match value {
+		Value::Number(number) => match (number.as_i64(), number.as_u64(), number.as_f64()) {
+			(Some(number), _, _) => Some(number.into()),
+			(_, Some(number), _) => Some(number.into()),
+			(_, _, Some(number)) if number.fract() == 0.0 && number.abs() < 1e38 => {
+				Some(number as i128)
+			}
+			_ => None,
+		},
+		Value::String(text) => text.trim().parse().ok(),
+		_ => None,
+	}
+}
+
+/// Floating point number, with `NaN` and `Infinity` given as strings.
+fn float(value: &Value) -> Option<f64> {
+	match value {
+		Value::Number(number) => number.as_f64(),
+		Value::String(text) => match text.as_str() {
+			"NaN" => Some(f64::NAN),
+			"Infinity" => Some(f64::INFINITY),
+			"-Infinity" => Some(f64::NEG_INFINITY),
+			text => text.trim().parse().ok(),
+		},
+		_ => None,
+	}
+}
+
+fn wire_type(kind: &Kind) -> u64 {
+	match kind {
+		Kind::Double | Kind::Fixed64 | Kind::Sfixed64 => FIXED64,
+		Kind::Float | Kind::Fixed32 | Kind::Sfixed32 => FIXED32,
+		Kind::String | Kind::Bytes | Kind::Message(_) | Kind::Named(_) => LEN,
+		_ => VARINT,
+	}
+}
+
+fn write_tag(out: &mut Vec<u8>, number: u32, wire_type: u64) {
+	write_varint(out, ((number as u64) << 3) | wire_type);
+}
+
+pub fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+	while value >= 0x80 {
+		out.push(value as u8 | 0x80);
+		value >>= 7;
+	}
+	out.push(value as u8);
+}
+
+/// Reader of the records of a message.
+struct Reader<'a> {
+	bytes: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn at_end(&self) -> bool {
+		self.pos >= self.bytes.len()
+	}
+
+	fn varint(&mut self) -> Result<u64, String> {
+		let mut value = 0u64;
+		for shift in (0..64).step_by(7) {
+			let byte = *self.bytes.get(self.pos).ok_or("truncated varint")?;
+			self.pos += 1;
+			value |= ((byte & 0x7f) as u64) << shift;
+			if byte < 0x80 {
+				return Ok(value);
+			}
+		}
+		Err("varint too long".into())
+	}
+
+	fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+		let end = self
+			.pos
+			.checked_add(len)
+			.filter(|end| *end <= self.bytes.len())
+			.ok_or("truncated message")?;
+		let bytes = &self.bytes[self.pos..end];
+		self.pos = end;
+		Ok(bytes)
+	}
+
+	fn fixed<const N: usize>(&mut self) -> Result<[u8; N], String> {
+		Ok(self.take(N)?.try_into().unwrap_or([0; N]))
+	}
+
+	/// Skips a value of a field the schema does not know.
+	fn skip(&mut self, wire_type: u64, number: u64) -> Result<(), String> {
+		match wire_type {
+			VARINT => drop(self.varint()?),
+			FIXED64 => drop(self.take(8)?),
+			LEN => {
+				let len = self.varint()?;
+				self.take(len as usize)?;
+			}
+			START_GROUP => loop {
+				let key = self.varint()?;
+				if key & 7 == END_GROUP {
+					if key >> 3 != number {
+						return Err("mismatched end of group".into());
+					}
+					break;
+				}
+				self.skip(key & 7, key >> 3)?;
+			},
+			FIXED32 => drop(self.take(4)?),
+			other => return Err(format!("invalid wire type {other}")),
+		}
+		Ok(())
+	}
+}
+
+fn decode_message(schema: &Schema, name: &str, bytes: &[u8], path: &str) -> Result<Value, String> {
+	let message = message(schema, name)?;
+	let mut values: Vec<(usize, Value)> = Vec::new();
+	let mut reader = Reader { bytes, pos: 0 };
+	while !reader.at_end() {
+		let key = reader.varint()?;
+		let (number, wire) = (key >> 3, key & 7);
+		let Some(index) = message
+			.fields
+			.iter()
+			.position(|field| field.number as u64 == number)
+		else {
+			reader.skip(wire, number)?;
+			continue;
+		};
+		let field = &message.fields[index];
+		let path = child(path, &field.json_name);
+		let slot = match values.iter().position(|(found, _)| *found == index) {
+			Some(slot) => slot,
+			None => {
+				let empty = match field.repeated {
+					true if message_is_map(schema, kind_name(&field.kind)) => {
+						Value::Object(Map::new())
+					}
+					true => Value::Array(Vec::new()),
+					false => Value::Null,
+				};
+				values.push((index, empty));
+				values.len() - 1
+			}
+		};
+		let value = &mut values[slot].1;
+		if field.repeated && wire == LEN && wire_type(&field.kind) != LEN {
+			// packed scalars
+			let len = reader.varint()? as usize;
+			let mut packed = Reader {
+				bytes: reader.take(len)?,
+				pos: 0,
+			};
+			while !packed.at_end() {
+				let item = decode_value(
+					schema,
+					&field.kind,
+					wire_type(&field.kind),
+					&mut packed,
+					&path,
+				)?;
+				push(value, item);
+			}
+			continue;
+		}
+		if wire != wire_type(&field.kind) {
+			return Err(format!(
+				"`{path}`: wire type {wire} does not match {}",
+				kind_name_or_scalar(&field.kind)
+			));
+		}
+		let item = decode_value(schema, &field.kind, wire, &mut reader, &path)?;
+		match value {
+			Value::Object(map) => {
+				let key = match &item["key"] {
+					Value::String(key) => key.clone(),
+					Value::Null => String::new(),
+					key => key.to_string(),
+				};
+				let entry = item.get("value").cloned();
+				let entry = entry.unwrap_or_else(|| {
+					default_value(schema, entry_value_kind(schema, &field.kind))
+				});
+				map.insert(key, entry);
+			}
+			Value::Array(_) => push(value, item),
+			_ => *value = item,
+		}
+	}
+	values.sort_by_key(|(index, _)| *index);
+	let object = values
+		.into_iter()
+		.map(|(index, value)| (message.fields[index].json_name.clone(), value))
+		.collect();
+	Ok(Value::Object(object))
+}
+
+fn push(array: &mut Value, item: Value) {
+	if let Value::Array(items) = array {
+		items.push(item);
+	}
+}
+
+fn kind_name(kind: &Kind) -> &str {
+	match kind {
+		Kind::Message(name) | Kind::Enum(name) | Kind::Named(name) => name,
+		_ => "",
+	}
+}
+
+fn kind_name_or_scalar(kind: &Kind) -> String {
+	match kind {
+		Kind::Message(name) | Kind::Enum(name) | Kind::Named(name) => format!("`{name}`"),
+		scalar => format!("{scalar:?}").to_ascii_lowercase(),
+	}
+}
+
+/// Type of the values of a map field.
+fn entry_value_kind<'a>(schema: &'a Schema, kind: &Kind) -> Option<&'a Kind> {
+	let entry = schema.messages.get(kind_name(kind))?;
+	entry
+		.fields
+		.iter()
+		.find(|field| field.number == 2)
+		.map(|field| &field.kind)
+}
+
+/// Value of a map entry without one on the wire.
+fn default_value(schema: &Schema, kind: Option<&Kind>) -> Value {
+	match kind {
+		Some(Kind::Message(_)) | None => Value::Object(Map::new()),
+		Some(Kind::Enum(name)) => schema
+			.enums
+			.get(name)
+			.and_then(|values| values.name_of(0))
+			.map_or(Value::from(0), Value::from),
+		Some(Kind::String) | Some(Kind::Bytes) => Value::from(""),
+		Some(Kind::Bool) => Value::Bool(false),
+		Some(Kind::Int64 | Kind::Uint64 | Kind::Sint64 | Kind::Fixed64 | Kind::Sfixed64) => {
+			Value::from("0")
+		}
+		Some(_) => Value::from(0),
+	}
+}
+
+fn decode_value(
+	schema: &Schema,
+	kind: &Kind,
+	wire: u64,
+	reader: &mut Reader<'_>,
+	path: &str,
+) -> Result<Value, String> {
+	let value = match wire {
+		VARINT => {
+			let raw = reader.varint()?;
+			match kind {
+				Kind::Int32 => Value::from(raw as i32),
+				Kind::Int64 => Value::from((raw as i64).to_string()),
+				Kind::Uint32 => Value::from(raw as u32),
+				Kind::Uint64 => Value::from(raw.to_string()),
+				Kind::Sint32 => Value::from(((raw >> 1) as i64 ^ -((raw & 1) as i64)) as i32),
+				Kind::Sint64 => Value::from(((raw >> 1) as i64 ^ -((raw & 1) as i64)).to_string()),
+				Kind::Bool => Value::Bool(raw != 0),
+				Kind::Enum(name) => {
+					let number = raw as i32;
+					match schema
+						.enums
+						.get(name)
+						.and_then(|values| values.name_of(number))
+					{
+						Some(name) => Value::from(name),
+						None => Value::from(number),
+					}
+				}
+				_ => Value::Null,
+			}
+		}
+		FIXED64 => {
+			let bytes = reader.fixed::<8>()?;
+			match kind {
+				Kind::Fixed64 => Value::from(u64::from_le_bytes(bytes).to_string()),
+				Kind::Sfixed64 => Value::from(i64::from_le_bytes(bytes).to_string()),
+				_ => float_value(f64::from_le_bytes(bytes)),
+			}
+		}
+		FIXED32 => {
+			let bytes = reader.fixed::<4>()?;
+			match kind {
+				Kind::Fixed32 => Value::from(u32::from_le_bytes(bytes)),
+				Kind::Sfixed32 => Value::from(i32::from_le_bytes(bytes)),
+				// the shortest decimal of the f32, not of its f64 widening
+				_ => {
+					let number = f32::from_le_bytes(bytes);
+					float_value(number.to_string().parse().unwrap_or(number as f64))
+				}
+			}
+		}
+		_ => {
+			let len = reader.varint()? as usize;
+			let bytes = reader.take(len)?;
+			match kind {
+				Kind::String => Value::from(
+					std::str::from_utf8(bytes).map_err(|_| format!("`{path}`: invalid UTF-8"))?,
+				),
+				Kind::Message(name) => decode_message(schema, name, bytes, path)?,
+				_ => Value::from(base64_encode(bytes)),
+			}
+		}
+	};
+	Ok(value)
+}
+
+fn float_value(number: f64) -> Value {
+	match Number::from_f64(number) {
+		Some(number) => Value::Number(number),
+		None if number.is_nan() => Value::from("NaN"),
+		None if number > 0.0 => Value::from("Infinity"),
+		None => Value::from("-Infinity"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::grpc::proto;
+
+	fn schema() -> Schema {
+		let mut schema = Schema::new();
+		proto::parse(
+			r#"
+			syntax = "proto3";
+			package t;
+			message All {
+				int32 i32 = 1;
+				int64 i64 = 2;
+				uint64 u64 = 3;
+				sint32 s32 = 4;
+				sint64 s64 = 5;
+				fixed32 f32 = 6;
+				sfixed64 sf64 = 7;
+				double dbl = 8;
+				float flt = 9;
+				bool on = 10;
+				string text = 11;
+				bytes data = 12;
+				Color color = 13;
+				repeated int32 numbers = 14;
+				repeated string names = 15;
+				map<string, Inner> by_name = 16;
+				map<int32, Color> by_id = 17;
+				Inner inner = 18;
+				repeated Inner inners = 19;
+			}
+			message Inner { string name = 1; }
+			enum Color { RED = 0; GREEN = 1; }
+			"#,
+			&mut schema,
+		)
+		.unwrap();
+		schema.resolve().unwrap();
+		schema
+	}
+
+	#[test]
+	fn encodes_and_decodes_the_json_mapping() {
+		let schema = schema();
+		let value = json!({
+			"i32": -1,
+			"i64": "-9007199254740993",
+			"u64": 18446744073709551615u64,
+			"s32": -2,
+			"s64": "-3",
+			"f32": 7,
+			"sf64": -8,
+			"dbl": "NaN",
+			"flt": 0.1,
+			"on": true,
+			"text": "héllo",
+			"data": "AP8",
+			"color": "GREEN",
+			"numbers": [1, 300],
+			"names": ["a", "b"],
+			"byName": {"x": {"name": "y"}, "z": {}},
+			"by_id": {"1": "GREEN", "2": 0},
+			"inner": {"name": "n"},
+			"inners": [{}, {"name": "m"}],
+		});
+		let bytes = encode(&schema, "t.All", &value).unwrap();
+		// -1 as int32 takes ten bytes, packed numbers one record
+		assert_eq!(
+			bytes[..11],
+			[0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+		);
+		let packed = [0x72, 0x03, 0x01, 0xac, 0x02];
+		assert!(bytes.windows(5).any(|window| window == packed));
+
+		let decoded = decode(&schema, "t.All", &bytes).unwrap();
+		assert_eq!(
+			decoded,
+			json!({
+				"i32": -1,
+				"i64": "-9007199254740993",
+				"u64": "18446744073709551615",
+				"s32": -2,
+				"s64": "-3",
+				"f32": 7,
+				"sf64": "-8",
+				"dbl": "NaN",
+				"flt": 0.1,
+				"on": true,
+				"text": "héllo",
+				"data": "AP8=",
+				"color": "GREEN",
+				"numbers": [1, 300],
+				"names": ["a", "b"],
+				"byName": {"x": {"name": "y"}, "z": {}},
+				"byId": {"1": "GREEN", "2": "RED"},
+				"inner": {"name": "n"},
+				"inners": [{}, {"name": "m"}],
+			})
+		);
+		let keys: Vec<&String> = decoded.as_object().unwrap().keys().collect();
+		assert_eq!(keys[..3], ["i32", "i64", "u64"]);
+
+		// unpacked numbers, unknown fields and unknown enum numbers
+		let bytes = [0x70, 0x05, 0x70, 0x06, 0xa0, 0x06, 0x01, 0x68, 0x07];
+		assert_eq!(
+			decode(&schema, "t.All", &bytes).unwrap(),
+			json!({"color": 7, "numbers": [5, 6]})
+		);
+	}
+
+	#[test]
+	fn reports_invalid_values() {
+		let schema = schema();
+		let error = |value| encode(&schema, "t.All", &value).unwrap_err();
+		assert_eq!(
+			error(json!({"nope": 1})),
+			"`nope`: no such field in `t.All`"
+		);
+		assert_eq!(
+			error(json!({"i32": 2147483648u64})),
+			"`i32`: expected an integer in range, got 2147483648"
+		);
+		assert_eq!(
+			error(json!({"inners": [{"name": 1}]})),
+			"`inners[0].name`: expected a string, got 1"
+		);
+		assert_eq!(
+			error(json!({"color": "BLUE"})),
+			"`color`: `BLUE` is not a value of `t.Color`"
+		);
+		assert_eq!(error(json!([])), "expected an object for `t.All`, got []");
+		assert_eq!(
+			decode(&schema, "t.All", &[0x5a, 0x05, b'a']).unwrap_err(),
+			"truncated message"
+		);
+	}
+}
@@ -0,0 +1,236 @@
+//! gRPC and gRPC-Web calls. Messages are encoded from JSON with the types
+//! of `.proto` files or of server reflection, framed with a 5-byte prefix
+//! and sent over HTTP/2, or over any HTTP version for gRPC-Web. The status
+//! of a call comes in the `grpc-status` and `grpc-message` trailers, which
+//! gRPC-Web sends in a last frame of the body.
+
+pub mod codec;
+pub mod proto;
+pub mod reflection;
+pub mod schema;
+
+use std::fmt;
+
+use crate::client::{Client, HttpVersion};
+use crate::encoding;
+use crate::error::{Error, Result};
+use crate::http::{parse_header_line, Headers, Part, PartHandler, Request, Response};
+use crate::url::{percent_decode, Url};
+
+/// Flag of a compressed message.
+const COMPRESSED: u8 = 0x01;
+
+/// Flag of the gRPC-Web frame carrying the trailers.
+const TRAILERS: u8 = 0x80;
+
+/// Status codes by number.
+const CODES: [&str; 17] = [
+	"OK",
+	"CANCELLED",
+	"UNKNOWN",
+	"INVALID_ARGUMENT",
+	"DEADLINE_EXCEEDED",
+	"NOT_FOUND",
+	"ALREADY_EXISTS",
+	"PERMISSION_DENIED",
+	"RESOURCE_EXHAUSTED",
+	"FAILED_PRECONDITION",
+	"ABORTED",
+	"OUT_OF_RANGE",
+	"UNIMPLEMENTED",
+	"INTERNAL",
+	"UNAVAILABLE",
+	"DATA_LOSS",
+	"UNAUTHENTICATED",
+];
+
+pub const UNKNOWN: u32 = 2;
+pub const UNIMPLEMENTED: u32 = 12;
+
+/// Status of a call.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Status {
+	pub code: u32,
+	pub message: String,
+}
+
+impl Status {
+	pub fn is_ok(&self) -> bool {
+		self.code == 0
+	}
+
+	/// Name of the code, e.g. `NOT_FOUND`.
+	pub fn name(&self) -> &str {
+		CODES.get(self.code as usize).copied().unwrap_or("UNKNOWN")
+	}
+
+	/// Status in the `grpc-status` and `grpc-message` fields, if present.
+	pub fn from_headers(headers: &Headers) -> Option<Status> {
+		let code = headers.get("grpc-status")?;
+		Some(Status {
+			code: code.trim().parse().unwrap_or(UNKNOWN),
+			message: percent_decode(headers.get("grpc-message").unwrap_or_default()),
+		})
+	}
+
+	/// Status of a response without one, from its HTTP status (gRPC over
+	/// HTTP/2, HTTP to gRPC status code mapping).
+	fn from_http(response: &Response) -> Status {
+		let code = match response.status {
+			400 => 13,
+			401 => 16,
+			403 => 7,
+			404 => UNIMPLEMENTED,
+			429 | 502..=504 => 14,
+			_ => UNKNOWN,
+		};
+		let message = match response.status {
+			200 => match response.headers.get("Content-Type") {
+				Some(kind) if !kind.starts_with("application/grpc") => {
+					format!("unexpected content type `{kind}`")
+				}
+				_ => "missing grpc-status".into(),
+			},
+			status => format!("HTTP status {status}"),
+		};
+		Status { code, message }
+	}
+}
+
+/// `5 NOT_FOUND: message`.
+impl fmt::Display for Status {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} {}", self.code, self.name())?;
+		if !self.message.is_empty() {
+			write!(f, ": {}", self.message)?;
+		}
+		Ok(())
+	}
+}
+
+/// Frames a message.
+pub fn frame(message: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(message.len() + 5);
+	out.push(0);
+	out.extend_from_slice(&(message.len() as u32).to_be_bytes());
+	out.extend_from_slice(message);
+	out
+}
+
+/// Splits a body into its frames as it arrives.
+#[derive(Debug, Default)]
+pub struct Deframer {
+	buffer: Vec<u8>,
+}
+
+impl Deframer {
+	/// Adds the bytes following the previous ones, returning the flags and
+	/// payload of the frames they complete.
+	pub fn feed(&mut self, bytes: &[u8]) -> Vec<(u8, Vec<u8>)> {
+		self.buffer.extend_from_slice(bytes);
+		let mut frames = Vec::new();
+		while self.buffer.len() >= 5 {
+			let len = u32::from_be_bytes([
+				self.buffer[1],
+				self.buffer[2],
+				self.buffer[3],
+				self.buffer[4],
+			]) as usize;
+			if self.buffer.len() < 5 + len {
+				break;
+			}
+			let payload = self.buffer[5..5 + len].to_vec();
+			frames.push((self.buffer[0], payload));
+			self.buffer.drain(..5 + len);
+		}
+		frames
+	}
+
+	/// Returns true when no partial frame is left.
+	pub fn is_empty(&self) -> bool {
+		self.buffer.is_empty()
+	}
+}
+
+/// Request calling `method` (`package.Service/Method`) of the service at
+/// `url`, with framed messages as the body.
+pub fn request(url: &Url, method: &str, messages: &[Vec<u8>], web: bool) -> Request {
+	let mut url = url.clone();
+	url.path = format!("{}/{method}", url.path_only().trim_end_matches('/'));
+	let mut request = Request::new("POST", url);
+	if web {
+		request
+			.headers
+			.set("Content-Type", "application/grpc-web+proto");
+		request.headers.set("Accept", "application/grpc-web+proto");
+		request.headers.set("X-Grpc-Web", "1");
+	} else {
+		request
+			.headers
+			.set("Content-Type", "application/grpc+proto");
+		request.headers.set("TE", "trailers");
+	}
+	request.headers.set("grpc-accept-encoding", "gzip");
+	request.body = messages.iter().flat_map(|message| frame(message)).collect();
+	request
+}
+
+/// Sends a call, passing the head and then each message of the response to
+/// `on_part` as they arrive. gRPC goes over HTTP/2, with prior knowledge for
+/// http URLs. The trailers of gRPC-Web are moved from the body to those of
+/// the response.
+pub fn call(
+	client: &Client,
+	request: &Request,
+	web: bool,
+	on_part: &mut PartHandler<'_>,
+) -> Result<(Response, Status)> {
+	let mut client = client.clone();
+	if !web {
+		if client.version == HttpVersion::Http1 {
+			return Err(Error::Usage(
+				"gRPC needs HTTP/2, gRPC-Web works over HTTP/1.1".into(),
+			));
+		}
+		client.version = HttpVersion::Http2;
+	}
+	let mut deframer = Deframer::default();
+	let mut trailers = Headers::new();
+	// false once the body turns out not to be frames, e.g. an error page
+	let mut framed = true;
+	let mut response = client.stream(request, &mut |part| {
+		let bytes = match part {
+			Part::Head(head) => return on_part(Part::Head(head)),
+			Part::Data(_) if !framed => return Ok(()),
+			Part::Data(bytes) => bytes,
+		};
+		for (flags, payload) in deframer.feed(bytes) {
+			if flags & !(COMPRESSED | TRAILERS) != 0 {
+				framed = false;
+				return Ok(());
+			}
+			if web && flags & TRAILERS != 0 {
+				let text = String::from_utf8_lossy(&payload).into_owned();
+				for (name, value) in text.lines().filter_map(parse_header_line) {
+					trailers.append(name, value);
+				}
+			} else if flags & COMPRESSED != 0 {
+				// the only encoding accepted
+				on_part(Part::Data(&encoding::decode(&payload, "gzip")?))?;
+			} else {
+				on_part(Part::Data(&payload))?;
+			}
+		}
+		Ok(())
+	})?;
+	for (name, value) in trailers.iter() {
+		response.trailers.append(name, value);
+	}
+	if framed && !deframer.is_empty() && response.status == 200 {
+		return Err(Error::Protocol("response ended inside a message".into()));
+	}
+	let status = Status::from_headers(&response.trailers)
+		.or_else(|| Status::from_headers(&response.headers))
+		.unwrap_or_else(|| Status::from_http(&response));
+	Ok((response, status))
+}
@@ -0,0 +1,720 @@
+//! Parser of `.proto` files (proto2, proto3 and editions), keeping what
+//! calls need: messages with their fields, enums and services. Options are
+//! skipped except `packed` and `json_name`, and groups and extensions are
+//! not supported.
+
+use std::path::{Path, PathBuf};
+
+use super::schema::{self, Enum, Field, Kind, Message, Method, Schema, Service};
+use crate::error::{Error, ParseError, Result};
+
+/// Loads `.proto` files and the files they import, then resolves the type
+/// names. Imports are searched in the import paths, by default the
+/// directories of the files, and then among the well-known types bundled
+/// with webcat.
+pub fn load(files: &[PathBuf], import_paths: &[PathBuf]) -> Result<Schema> {
+	let mut schema = Schema::new();
+	let mut search = import_paths.to_vec();
+	if search.is_empty() {
+		for file in files {
+			let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+			if !search.contains(&dir) {
+				search.push(dir);
+			}
+		}
+	}
+	for file in files {
+		let name = file.to_string_lossy().into_owned();
+		let text = std::fs::read_to_string(file)
+			.map_err(|err| Error::Usage(format!("cannot read `{name}`: {err}")))?;
+		load_text(&mut schema, &name, &text, &search)?;
+	}
+	schema.resolve().map_err(Error::Usage)?;
+	Ok(schema)
+}
+
+/// Adds a file and its imports to the schema, without resolving names.
+fn load_text(schema: &mut Schema, name: &str, text: &str, search: &[PathBuf]) -> Result<()> {
+	if !schema.files.insert(name.to_string()) {
+		return Ok(());
+	}
+	let imports = parse(text, schema).map_err(|err| err.in_source(name))?;
+	for (import, line) in imports {
+		if schema.files.contains(&import) {
+			continue;
+		}
+		let found = search
+			.iter()
+			.map(|dir| dir.join(&import))
+			.find(|path| path.is_file());
+		match found {
+			Some(path) => {
+				let text = std::fs::read_to_string(&path)?;
+				load_text(schema, &import, &text, search)?;
+			}
+			None => match bundled(&import) {
+				Some(text) => load_text(schema, &import, text, search)?,
+				None => {
+					let message = format!("cannot find import `{import}`");
+					return Err(ParseError::new(line, 1, message).in_source(name).into());
+				}
+			},
+		}
+	}
+	Ok(())
+}
+
+/// Bundled `.proto` files: the well-known types and the definitions used
+/// by server reflection.
+pub fn bundled(name: &str) -> Option<&'static str> {
+	Some(match name {
+		"google/protobuf/any.proto" => include_str!("protos/any.proto"),
+		"google/protobuf/duration.proto" => include_str!("protos/duration.proto"),
+		"google/protobuf/empty.proto" => include_str!("protos/empty.proto"),
+		"google/protobuf/field_mask.proto" => include_str!("protos/field_mask.proto"),
+		"google/protobuf/struct.proto" => include_str!("protos/struct.proto"),
+		"google/protobuf/timestamp.proto" => include_str!("protos/timestamp.proto"),
+		"google/protobuf/wrappers.proto" => include_str!("protos/wrappers.proto"),
+		"google/protobuf/descriptor.proto" => include_str!("protos/descriptor.proto"),
+		"grpc/reflection/v1/reflection.proto" => include_str!("protos/reflection.proto"),
+		_ => return None,
+	})
+}
+
+/// Parses a `.proto` file into the schema, returning its imports with the
+/// line of each. Type names are left to [`Schema::resolve`].
+pub fn parse(
+	text: &str,
+	schema: &mut Schema,
+) -> std::result::Result<Vec<(String, usize)>, ParseError> {
+	let mut parser = Parser {
+		tokens: tokenize(text)?,
+		pos: 0,
+		proto3: false,
+		end: (text.lines().count().max(1), 1),
+		schema,
+	};
+	parser.file()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+	/// Identifier, possibly qualified: `foo`, `pkg.Foo`, `.pkg.Foo`.
+	Ident(String),
+	/// Number as written.
+	Number(String),
+	Str(String),
+	Symbol(char),
+}
+
+struct Parser<'a> {
+	tokens: Vec<(Token, usize, usize)>,
+	pos: usize,
+	/// Repeated scalars are packed by default.
+	proto3: bool,
+	/// Position reported at the end of the file.
+	end: (usize, usize),
+	schema: &'a mut Schema,
+}
+
+type Parsed<T> = std::result::Result<T, ParseError>;
+
+impl Parser<'_> {
+	fn file(&mut self) -> Parsed<Vec<(String, usize)>> {
+		let mut imports = Vec::new();
+		let mut package = String::new();
+		while self.pos < self.tokens.len() {
+			if self.eat_symbol(';') {
+				continue;
+			}
+			let keyword = self.ident()?;
+			match keyword.as_str() {
+				"syntax" | "edition" => {
+					self.symbol('=')?;
+					let value = self.string()?;
+					self.symbol(';')?;
+					self.proto3 = keyword == "edition" || value == "proto3";
+				}
+				"package" => {
+					package = self.ident()?;
+					self.symbol(';')?;
+				}
+				"import" => {
+					let line = self.tokens[self.pos - 1].1;
+					if matches!(self.peek(), Some(Token::Ident(word)) if word == "public" || word == "weak")
+					{
+						self.pos += 1;
+					}
+					imports.push((self.string()?, line));
+					self.symbol(';')?;
+				}
+				"option" => self.skip_statement()?,
+				"message" => self.message(&package)?,
+				"enum" => self.enumeration(&package)?,
+				"service" => self.service(&package)?,
+				"extend" => {
+					self.ident()?;
+					self.skip_block()?;
+				}
+				_ => return Err(self.error_before(format!("unexpected `{keyword}`"))),
+			}
+		}
+		Ok(imports)
+	}
+
+	fn message(&mut self, scope: &str) -> Parsed<()> {
+		let name = schema::join(scope, &self.ident()?);
+		self.symbol('{')?;
+		let mut fields = Vec::new();
+		while !self.eat_symbol('}') {
+			if self.eat_symbol(';') {
+				continue;
+			}
+			let word = self.ident()?;
+			match word.as_str() {
+				"message" => self.message(&name)?,
+				"enum" => self.enumeration(&name)?,
+				"option" | "reserved" | "extensions" => self.skip_statement()?,
+				"extend" => {
+					self.ident()?;
+					self.skip_block()?;
+				}
+				"oneof" => {
+					self.ident()?;
+					self.symbol('{')?;
+					while !self.eat_symbol('}') {
+						if self.eat_symbol(';') {
+							continue;
+						}
+						match self.ident()?.as_str() {
+							"option" => self.skip_statement()?,
+							kind => {
+								let kind = kind.to_string();
+								fields.push(self.field(kind, false)?);
+							}
+						}
+					}
+				}
+				"map" if self.peek() == Some(&Token::Symbol('<')) => {
+					fields.push(self.map_field(&name)?);
+				}
+				"repeated" => {
+					let kind = self.ident()?;
+					fields.push(self.field(kind, true)?);
+				}
+				"optional" | "required" => {
+					let kind = self.ident()?;
+					fields.push(self.field(kind, false)?);
+				}
+				_ => fields.push(self.field(word, false)?),
+			}
+		}
+		self.schema.messages.insert(
+			name.clone(),
+			Message {
+				name,
+				fields,
+				map_entry: false,
+			},
+		);
+		Ok(())
+	}
+
+	/// Field after its label: `type name = number [options];`.
+	fn field(&mut self, kind: String, repeated: bool) -> Parsed<Field> {
+		if kind == "group" {
+			return Err(self.error_before("groups are not supported".into()));
+		}
+		let name = self.ident()?;
+		self.symbol('=')?;
+		let number = self.number()?;
+		let options = self.field_options()?;
+		self.symbol(';')?;
+		let kind = Kind::scalar(&kind).unwrap_or(Kind::Named(kind));
+		let packed = match options.packed {
+			Some(packed) => packed,
+			None => self.proto3,
+		};
+		Ok(Field {
+			json_name: options
+				.json_name
+				.unwrap_or_else(|| schema::json_name(&name)),
+			name,
+			number,
+			// names are only known to be enums once resolved
+			packed: repeated && packed && (kind.is_packable() || matches!(kind, Kind::Named(_))),
+			kind,
+			repeated,
+		})
+	}
+
+	/// `map<K, V> name = number;`, a repeated field of an entry message.
+	fn map_field(&mut self, scope: &str) -> Parsed<Field> {
+		self.symbol('<')?;
+		let key = self.ident()?;
+		self.symbol(',')?;
+		let value = self.ident()?;
+		self.symbol('>')?;
+		let name = self.ident()?;
+		self.symbol('=')?;
+		let number = self.number()?;
+		let options = self.field_options()?;
+		self.symbol(';')?;
+
+		let mut entry_name = schema::json_name(&name);
+		if let Some(first) = entry_name.get(..1) {
+			entry_name.replace_range(..1, &first.to_ascii_uppercase());
+		}
+		let entry = schema::join(scope, &format!("{entry_name}Entry"));
+		let entry_field = |name: &str, number, kind: String| Field {
+			name: name.into(),
+			json_name: name.into(),
+			number,
+			kind: Kind::scalar(&kind).unwrap_or(Kind::Named(kind)),
+			repeated: false,
+			packed: false,
+		};
+		self.schema.messages.insert(
+			entry.clone(),
+			Message {
+				name: entry.clone(),
+				fields: vec![entry_field("key", 1, key), entry_field("value", 2, value)],
+				map_entry: true,
+			},
+		);
+		Ok(Field {
+			json_name: options
+				.json_name
+				.unwrap_or_else(|| schema::json_name(&name)),
+			name,
+			number,
+			kind: Kind::Message(entry),
+			repeated: true,
+			packed: false,
+		})
+	}
+
+	/// `[name = value, ...]` after a field, if present.
+	fn field_options(&mut self) -> Parsed<FieldOptions> {
+		let mut options = FieldOptions::default();
+		if !self.eat_symbol('[') {
+			return Ok(options);
+		}
+		loop {
+			let name = match self.next()? {
+				Token::Ident(name) => name,
+				// `(custom.option).field`
+				Token::Symbol('(') => {
+					self.skip_until(')')?;
+					String::new()
+				}
+				_ => return Err(self.error_before("expected an option name".into())),
+			};
+			if let Some(Token::Ident(_)) = self.peek() {
+				self.pos += 1;
+			}
+			self.symbol('=')?;
+			match (name.as_str(), self.next()?) {
+				("packed", Token::Ident(value)) => options.packed = Some(value == "true"),
+				("json_name", Token::Str(value)) => options.json_name = Some(value),
+				(_, Token::Symbol('{')) => self.skip_until('}')?,
+				_ => {}
+			}
+			if self.eat_symbol(']') {
+				return Ok(options);
+			}
+			self.symbol(',')?;
+		}
+	}
+
+	fn enumeration(&mut self, scope: &str) -> Parsed<()> {
+		let name = schema::join(scope, &self.ident()?);
+		self.symbol('{')?;
+		let mut values = Vec::new();
+		while !self.eat_symbol('}') {
+			if self.eat_symbol(';') {
+				continue;
+			}
+			let value = self.ident()?;
+			if value == "option" || value == "reserved" {
+				self.skip_statement()?;
+				continue;
+			}
+			self.symbol('=')?;
+			let negative = self.eat_symbol('-');
+			let number = self.number::<i64>()?;
+			let number = if negative { -number } else { number };
+			let number = i32::try_from(number)
+				.map_err(|_| self.error_before(format!("enum value `{number}` out of range")))?;
+			self.field_options()?;
+			self.symbol(';')?;
+			values.push((value, number));
+		}
+		self.schema
+			.enums
+			.insert(name.clone(), Enum { name, values });
+		Ok(())
+	}
+
+	fn service(&mut self, scope: &str) -> Parsed<()> {
+		let name = schema::join(scope, &self.ident()?);
+		self.symbol('{')?;
+		let mut methods = Vec::new();
+		while !self.eat_symbol('}') {
+			if self.eat_symbol(';') {
+				continue;
+			}
+			match self.ident()?.as_str() {
+				"option" => self.skip_statement()?,
+				"rpc" => {
+					let method = self.ident()?;
+					let (client_streaming, input) = self.rpc_type()?;
+					if self.ident()? != "returns" {
+						return Err(self.error_before("expected `returns`".into()));
+					}
+					let (server_streaming, output) = self.rpc_type()?;
+					if self.peek() == Some(&Token::Symbol('{')) {
+						self.skip_block()?;
+					} else {
+						self.symbol(';')?;
+					}
+					methods.push(Method {
+						name: method,
+						input,
+						output,
+						client_streaming,
+						server_streaming,
+					});
+				}
+				other => return Err(self.error_before(format!("unexpected `{other}`"))),
+			}
+		}
+		self.schema
+			.services
+			.insert(name.clone(), Service { name, methods });
+		Ok(())
+	}
+
+	/// `([stream] Type)` of a method.
+	fn rpc_type(&mut self) -> Parsed<(bool, String)> {
+		self.symbol('(')?;
+		let mut name = self.ident()?;
+		let stream = name == "stream" && matches!(self.peek(), Some(Token::Ident(_)));
+		if stream {
+			name = self.ident()?;
+		}
+		self.symbol(')')?;
+		Ok((stream, name))
+	}
+
+	/// Skips to the end of a statement, past any aggregate value.
+	fn skip_statement(&mut self) -> Parsed<()> {
+		loop {
+			match self.next()? {
+				Token::Symbol(';') => return Ok(()),
+				Token::Symbol('{') => self.skip_until('}')?,
+				_ => {}
+			}
+		}
+	}
+
+	/// Skips a `{ ... }` block.
+	fn skip_block(&mut self) -> Parsed<()> {
+		self.symbol('{')?;
+		self.skip_until('}')
+	}
+
+	/// Skips past the symbol closing the one just read, nested ones
+	/// included.
+	fn skip_until(&mut self, close: char) -> Parsed<()> {
+		loop {
+			match self.next()? {
+				Token::Symbol(c) if c == close => return Ok(()),
+				Token::Symbol('{') => self.skip_until('}')?,
+				Token::Symbol('(') => self.skip_until(')')?,
+				Token::Symbol('[') => self.skip_until(']')?,
+				_ => {}
+			}
+		}
+	}
+
+	fn peek(&self) -> Option<&Token> {
+		self.tokens.get(self.pos).map(|(token, _, _)| token)
+	}
+
+	fn next(&mut self) -> Parsed<Token> {
+		match self.tokens.get(self.pos) {
+			Some((token, _, _)) => {
+				self.pos += 1;
+				Ok(token.clone())
+			}
+			None => Err(ParseError::new(
+				self.end.0,
+				self.end.1,
+				"unexpected end of file",
+			)),
+		}
+	}
+
+	fn ident(&mut self) -> Parsed<String> {
+		match self.next()? {
+			Token::Ident(name) => Ok(name),
+			_ => Err(self.error_before("expected a name".into())),
+		}
+	}
+
+	/// String literal, adjacent ones joined.
+	fn string(&mut self) -> Parsed<String> {
+		let mut value = match self.next()? {
+			Token::Str(value) => value,
+			_ => return Err(self.error_before("expected a string".into())),
+		};
+		while let Some(Token::Str(more)) = self.peek() {
+			value.push_str(more);
+			self.pos += 1;
+		}
+		Ok(value)
+	}
+
+	fn number<T: TryFrom<u64>>(&mut self) -> Parsed<T> {
+		let text = match self.next()? {
+			Token::Number(text) => text,
+			_ => return Err(self.error_before("expected a number".into())),
+		};
+		let value = if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+			u64::from_str_radix(hex, 16).ok()
+		} else if text.len() > 1 && text.starts_with('0') {
+			u64::from_str_radix(&text[1..], 8).ok()
+		} else {
+			text.parse().ok()
+		};
+		value
+			.and_then(|value| T::try_from(value).ok())
+			.ok_or_else(|| self.error_before(format!("invalid number `{text}`")))
+	}
+
+	fn symbol(&mut self, symbol: char) -> Parsed<()> {
+		match self.next()? {
+			Token::Symbol(c) if c == symbol => Ok(()),
+			_ => Err(self.error_before(format!("expected `{symbol}`"))),
+		}
+	}
+
+	fn eat_symbol(&mut self, symbol: char) -> bool {
+		let found = self.peek() == Some(&Token::Symbol(symbol));
+		if found {
+			self.pos += 1;
+		}
+		found
+	}
+
+	/// Error at the token just read.
+	fn error_before(&self, message: String) -> ParseError {
+		let (_, line, column) = &self.tokens[self.pos.saturating_sub(1)];
+		ParseError::new(*line, *column, message)
+	}
+}
+
+#[derive(Default)]
+struct FieldOptions {
+	packed: Option<bool>,
+	json_name: Option<String>,
+}
+
+/// Splits a file into tokens with their line and column, dropping comments.
+fn tokenize(text: &str) -> std::result::Result<Vec<(Token, usize, usize)>, ParseError> {
+	let chars: Vec<char> = text.chars().collect();
+	let mut tokens = Vec::new();
+	let (mut i, mut line, mut line_start) = (0, 1, 0);
+	while i < chars.len() {
+		let c = chars[i];
+		let column = i - line_start + 1;
+		let word = |i: usize| {
+			chars
+				.get(i)
+				.is_some_and(|c| c.is_alphanumeric() || *c == '_')
+		};
+		match c {
+			'\n' => {
+				i += 1;
+				line += 1;
+				line_start = i;
+			}
+			c if c.is_whitespace() => i += 1,
+			'/' if chars.get(i + 1) == Some(&'/') => {
+				while i < chars.len() && chars[i] != '\n' {
+					i += 1;
+				}
+			}
+			'/' if chars.get(i + 1) == Some(&'*') => {
+				i += 2;
+				while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+					if chars[i] == '\n' {
+						line += 1;
+						line_start = i + 1;
+					}
+					i += 1;
+				}
+				if i >= chars.len() {
+					return Err(ParseError::new(line, column, "unterminated comment"));
+				}
+				i += 2;
+			}
+			'"' | '\'' => {
+				let mut value = String::new();
+				i += 1;
+				loop {
+					match chars.get(i) {
+						None | Some('\n') => {
+							return Err(ParseError::new(line, column, "unterminated string"))
+						}
+						Some(&end) if end == c => break,
+						Some('\\') => {
+							i += 1;
+							let escaped = chars.get(i).copied().unwrap_or('\\');
+							value.push(match escaped {
+								'n' => '\n',
+								't' => '\t',
+								'r' => '\r',
+								'0' => '\0',
+								other => other,
+							});
+						}
+						Some(&other) => value.push(other),
+					}
+					i += 1;
+				}
+				i += 1;
+				tokens.push((Token::Str(value), line, column));
+			}
+			c if c.is_ascii_digit() => {
+				let start = i;
+				while word(i) || chars.get(i) == Some(&'.') {
+					// exponent signs
+					if matches!(chars[i], 'e' | 'E') && matches!(chars.get(i + 1), Some('+' | '-'))
+					{
+						i += 1;
+					}
+					i += 1;
+				}
+				let number = chars[start..i].iter().collect();
+				tokens.push((Token::Number(number), line, column));
+			}
+			c if word(i) || (c == '.' && word(i + 1)) => {
+				let start = i;
+				i += 1;
+				while word(i) || (chars[i] == '.' && word(i + 1)) {
+					i += 1;
+				}
+				let name = chars[start..i].iter().collect();
+				tokens.push((Token::Ident(name), line, column));
+			}
+			c => {
+				tokens.push((Token::Symbol(c), line, column));
+				i += 1;
+			}
+		}
+	}
+	Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_messages_enums_and_services() {
+		let text = r#"
+			syntax = "proto3";
+			package shop.v1;
+			import "google/protobuf/empty.proto";
+			option go_package = "example.com/shop";
+
+			/* an item,
+			   with a nested type */
+			message Item {
+				enum Kind { KIND_UNSPECIFIED = 0; BOOK = 1; NEGATIVE = -2 [deprecated = true]; }
+				int64 id = 1;
+				string display_name = 2 [json_name = "title", deprecated = true];
+				repeated Kind kinds = 3;
+				repeated string tags = 4;
+				repeated int32 sizes = 5 [packed = false];
+				map<string, Price> prices = 6;
+				oneof source { string url = 7; bytes blob = 0x8; }
+				reserved 9 to 11;
+				message Price { double amount = 1; }
+			}
+
+			service Shop {
+				option (custom) = { a: 1 };
+				rpc Get (Item) returns (Item);
+				rpc Watch(.shop.v1.Item) returns (stream Item) { option deprecated = true; }
+				rpc Ping (google.protobuf.Empty) returns (google.protobuf.Empty);
+			}
+		"#;
+		let mut schema = Schema::new();
+		load_text(&mut schema, "shop.proto", text, &[]).unwrap();
+		schema.resolve().unwrap();
+		assert!(schema.files.contains("google/protobuf/empty.proto"));
+
+		let item = &schema.messages["shop.v1.Item"];
+		let field = |name: &str| item.fields.iter().find(|f| f.name == name).unwrap();
+		assert_eq!(field("display_name").json_name, "title");
+		assert_eq!(field("kinds").kind, Kind::Enum("shop.v1.Item.Kind".into()));
+		assert!(field("kinds").packed);
+		assert!(!field("tags").packed);
+		assert!(!field("sizes").packed);
+		assert_eq!(field("blob").number, 8);
+		assert_eq!(
+			field("prices").kind,
+			Kind::Message("shop.v1.Item.PricesEntry".into())
+		);
+		let entry = &schema.messages["shop.v1.Item.PricesEntry"];
+		assert!(entry.map_entry);
+		assert_eq!(
+			entry.fields[1].kind,
+			Kind::Message("shop.v1.Item.Price".into())
+		);
+		assert_eq!(
+			schema.enums["shop.v1.Item.Kind"].values[2],
+			("NEGATIVE".into(), -2)
+		);
+
+		let (service, method) = schema.method("Shop/Watch").unwrap();
+		assert_eq!(service.name, "shop.v1.Shop");
+		assert_eq!(
+			method.signature(),
+			"rpc Watch (shop.v1.Item) returns (stream shop.v1.Item)"
+		);
+		assert!(schema.method("shop.v1.Shop.Ping").is_ok());
+		assert_eq!(
+			schema.method("Shop/Nope").unwrap_err(),
+			"unknown method `Nope` of `shop.v1.Shop`"
+		);
+	}
+
+	#[test]
+	fn reports_errors_with_positions() {
+		let mut schema = Schema::new();
+		let err = parse(
+			"syntax = \"proto3\";\nmessage A {\n  int32 x = ;\n}",
+			&mut schema,
+		)
+		.unwrap_err();
+		assert_eq!(
+			(err.line, err.column, err.message.as_str()),
+			(3, 13, "expected a number")
+		);
+
+		let err = parse("message A {\n", &mut schema).unwrap_err();
+		assert_eq!(
+			(err.line, err.message.as_str()),
+			(1, "unexpected end of file")
+		);
+		let err = load_text(&mut schema, "a.proto", "\nimport 'b.proto';", &[]).unwrap_err();
+		assert_eq!(err.to_string(), "a.proto:2:1: cannot find import `b.proto`");
+		let mut schema = Schema::new();
+		parse("message A { B b = 1; }", &mut schema).unwrap();
+		assert_eq!(schema.resolve().unwrap_err(), "unknown type `B` in `A`");
+	}
+}
@@ -0,0 +1,171 @@
+//! Server reflection: lists the services of a server and loads the file
+//! descriptors of their types, with `grpc.reflection.v1` or, on servers
+//! without it, `grpc.reflection.v1alpha`.
+
+use serde_json::{json, Value};
+
+use super::schema::Schema;
+use super::{codec, proto, Status, UNIMPLEMENTED};
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::http::{Headers, Part};
+use crate::url::Url;
+use crate::util::base64_decode;
+
+const PACKAGES: [&str; 2] = ["grpc.reflection.v1", "grpc.reflection.v1alpha"];
+
+const REQUEST: &str = "grpc.reflection.v1.ServerReflectionRequest";
+const RESPONSE: &str = "grpc.reflection.v1.ServerReflectionResponse";
+const FILE: &str = "google.protobuf.FileDescriptorProto";
+
+/// Reflection service of a server.
+pub struct Reflection<'a> {
+	client: &'a Client,
+	url: &'a Url,
+	web: bool,
+	/// Headers sent with the calls, e.g. for authentication.
+	headers: &'a Headers,
+	/// Types of the reflection service and of file descriptors.
+	schema: Schema,
+	/// Index in `PACKAGES` of the version the server answered.
+	version: usize,
+}
+
+impl<'a> Reflection<'a> {
+	pub fn new(client: &'a Client, url: &'a Url, web: bool, headers: &'a Headers) -> Self {
+		let mut schema = Schema::new();
+		for name in [
+			"grpc/reflection/v1/reflection.proto",
+			"google/protobuf/descriptor.proto",
+		] {
+			let text = proto::bundled(name).unwrap_or_default();
+			// bundled files are known to parse
+			let _ = proto::parse(text, &mut schema);
+		}
+		let _ = schema.resolve();
+		Reflection {
+			client,
+			url,
+			web,
+			headers,
+			schema,
+			version: 0,
+		}
+	}
+
+	/// Full names of the services of the server.
+	pub fn list_services(&mut self) -> Result<Vec<String>> {
+		let responses = self.ask(&[json!({ "listServices": "*" })])?;
+		let services = responses
+			.iter()
+			.flat_map(|response| array(&response["listServicesResponse"]["service"]))
+			.filter_map(|service| service["name"].as_str())
+			.map(str::to_string)
+			.collect();
+		Ok(services)
+	}
+
+	/// Loads the files defining the given symbols, such as services, and
+	/// the files they import.
+	pub fn load(&mut self, symbols: &[String]) -> Result<Schema> {
+		let mut schema = Schema::new();
+		let mut requests: Vec<Value> = symbols
+			.iter()
+			.map(|symbol| json!({ "fileContainingSymbol": symbol }))
+			.collect();
+		let mut requested = Vec::new();
+		while !requests.is_empty() {
+			let mut imports = Vec::new();
+			for response in self.ask(&requests)? {
+				let files = array(&response["fileDescriptorResponse"]["fileDescriptorProto"]);
+				for file in files {
+					let bytes = file.as_str().and_then(base64_decode).unwrap_or_default();
+					let file = codec::decode(&self.schema, FILE, &bytes).map_err(|err| {
+						Error::Protocol(format!("invalid file descriptor: {err}"))
+					})?;
+					let name = file["name"].as_str().unwrap_or_default().to_string();
+					if schema.files.contains(&name) {
+						continue;
+					}
+					schema.add_descriptor(&file);
+					imports.extend(
+						array(&file["dependency"])
+							.filter_map(Value::as_str)
+							.map(str::to_string),
+					);
+				}
+			}
+			imports.retain(|name| !schema.files.contains(name) && !requested.contains(name));
+			imports.dedup();
+			requests = imports
+				.iter()
+				.map(|name| json!({ "fileByFilename": name }))
+				.collect();
+			requested.extend(imports);
+		}
+		Ok(schema)
+	}
+
+	/// Sends the requests in a single call and returns the responses,
+	/// trying the older version of the service when the server does not
+	/// implement the first.
+	fn ask(&mut self, requests: &[Value]) -> Result<Vec<Value>> {
+		let messages = requests
+			.iter()
+			.map(|request| codec::encode(&self.schema, REQUEST, request))
+			.collect::<std::result::Result<Vec<_>, _>>()
+			.map_err(Error::Protocol)?;
+		loop {
+			let method = format!(
+				"{}.ServerReflection/ServerReflectionInfo",
+				PACKAGES[self.version]
+			);
+			let mut request = super::request(self.url, &method, &messages, self.web);
+			for (name, value) in self.headers.iter() {
+				request.headers.set(name, value);
+			}
+			let mut responses = Vec::new();
+			let (_, status) = super::call(self.client, &request, self.web, &mut |part| {
+				let Part::Data(bytes) = part else {
+					return Ok(());
+				};
+				let response =
+					codec::decode(&self.schema, RESPONSE, bytes).map_err(Error::Protocol)?;
+				responses.push(response);
+				Ok(())
+			})?;
+			match status {
+				Status {
+					code: UNIMPLEMENTED,
+					..
+				} if self.version + 1 < PACKAGES.len() => self.version += 1,
+				status if !status.is_ok() => {
+					return Err(Error::Protocol(format!(
+						"server reflection failed: {status}"
+					)))
+				}
+				_ => break check_errors(responses),
+			}
+		}
+	}
+}
+
+/// Fails on the first error the server answered with.
+fn check_errors(responses: Vec<Value>) -> Result<Vec<Value>> {
+	for response in &responses {
+		let error = &response["errorResponse"];
+		if !error.is_null() {
+			let message = error["errorMessage"].as_str().unwrap_or_default();
+			let status = Status {
+				code: error["errorCode"].as_u64().unwrap_or_default() as u32,
+				message: message.to_string(),
+			};
+			return Err(Error::Protocol(format!("server reflection: {status}")));
+		}
+	}
+	Ok(responses)
+}
+
+fn array(value: &Value) -> impl Iterator<Item = &Value> {
+	value.as_array().into_iter().flatten()
+}
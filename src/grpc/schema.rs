@@ -0,0 +1,374 @@
+//! Protobuf types of messages and services, loaded from `.proto` files or
+//! from the file descriptors sent by server reflection.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::Value;
+
+/// Messages, enums and services by their full name, without a leading dot.
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+	pub messages: BTreeMap<String, Message>,
+	pub enums: BTreeMap<String, Enum>,
+	pub services: BTreeMap<String, Service>,
+	/// Names of the files loaded, as imported.
+	pub files: BTreeSet<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Message {
+	pub name: String,
+	pub fields: Vec<Field>,
+	/// Entry of a map field, with the key as field 1 and the value as 2.
+	pub map_entry: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+	pub name: String,
+	/// Name in JSON, lowerCamelCase unless given with `json_name`.
+	pub json_name: String,
+	pub number: u32,
+	pub kind: Kind,
+	pub repeated: bool,
+	/// Repeated scalars sent in a single length-delimited record.
+	pub packed: bool,
+}
+
+/// Type of a field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+	Double,
+	Float,
+	Int32,
+	Int64,
+	Uint32,
+	Uint64,
+	Sint32,
+	Sint64,
+	Fixed32,
+	Fixed64,
+	Sfixed32,
+	Sfixed64,
+	Bool,
+	String,
+	Bytes,
+	/// Full name of a message.
+	Message(String),
+	/// Full name of an enum.
+	Enum(String),
+	/// Type name as written in a `.proto` file, resolved once all files
+	/// are loaded.
+	Named(String),
+}
+
+impl Kind {
+	/// Scalar type of a name used in `.proto` files.
+	pub fn scalar(name: &str) -> Option<Kind> {
+		Some(match name {
+			"double" => Kind::Double,
+			"float" => Kind::Float,
+			"int32" => Kind::Int32,
+			"int64" => Kind::Int64,
+			"uint32" => Kind::Uint32,
+			"uint64" => Kind::Uint64,
+			"sint32" => Kind::Sint32,
+			"sint64" => Kind::Sint64,
+			"fixed32" => Kind::Fixed32,
+			"fixed64" => Kind::Fixed64,
+			"sfixed32" => Kind::Sfixed32,
+			"sfixed64" => Kind::Sfixed64,
+			"bool" => Kind::Bool,
+			"string" => Kind::String,
+			"bytes" => Kind::Bytes,
+			_ => return None,
+		})
+	}
+
+	/// Returns true for the types repeated fields can pack: numbers, bools
+	/// and enums.
+	pub fn is_packable(&self) -> bool {
+		!matches!(
+			self,
+			Kind::String | Kind::Bytes | Kind::Message(_) | Kind::Named(_)
+		)
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Enum {
+	pub name: String,
+	pub values: Vec<(String, i32)>,
+}
+
+impl Enum {
+	pub fn name_of(&self, number: i32) -> Option<&str> {
+		self.values
+			.iter()
+			.find(|(_, value)| *value == number)
+			.map(|(name, _)| name.as_str())
+	}
+
+	pub fn number_of(&self, name: &str) -> Option<i32> {
+		self.values
+			.iter()
+			.find(|(value, _)| value == name)
+			.map(|(_, number)| *number)
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Service {
+	pub name: String,
+	pub methods: Vec<Method>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Method {
+	pub name: String,
+	/// Full names of the request and response messages.
+	pub input: String,
+	pub output: String,
+	pub client_streaming: bool,
+	pub server_streaming: bool,
+}
+
+impl Method {
+	/// Signature as in a `.proto` file, e.g.
+	/// `rpc Watch (pkg.Query) returns (stream pkg.Update)`.
+	pub fn signature(&self) -> String {
+		let stream = |streaming: bool| if streaming { "stream " } else { "" };
+		format!(
+			"rpc {} ({}{}) returns ({}{})",
+			self.name,
+			stream(self.client_streaming),
+			self.input,
+			stream(self.server_streaming),
+			self.output
+		)
+	}
+}
+
+impl Schema {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Finds a method by `Service/Method` or `Service.Method`, where the
+	/// service is given by its full name or, when unambiguous, its own.
+	pub fn method(&self, path: &str) -> Result<(&Service, &Method), String> {
+		let (service, method) =
+			split_method(path).ok_or_else(|| format!("expected `Service/Method`, got `{path}`"))?;
+		let mut found = self.services.values().filter(|candidate| {
+			candidate.name == service || candidate.name.ends_with(&format!(".{service}"))
+		});
+		let service = match (found.next(), found.next()) {
+			(Some(found), None) => found,
+			(Some(_), Some(_)) => return Err(format!("ambiguous service `{service}`")),
+			(None, _) => return Err(format!("unknown service `{service}`")),
+		};
+		let method = service
+			.methods
+			.iter()
+			.find(|candidate| candidate.name == method)
+			.ok_or_else(|| format!("unknown method `{method}` of `{}`", service.name))?;
+		Ok((service, method))
+	}
+
+	/// Resolves the type names of fields and methods loaded from `.proto`
+	/// files, searching from the innermost scope outwards.
+	pub fn resolve(&mut self) -> Result<(), String> {
+		let names = Names {
+			messages: self.messages.keys().cloned().collect(),
+			enums: self.enums.keys().cloned().collect(),
+		};
+		for message in self.messages.values_mut() {
+			for field in &mut message.fields {
+				if let Kind::Named(name) = &field.kind {
+					field.kind = names
+						.lookup(&message.name, name)
+						.ok_or_else(|| format!("unknown type `{name}` in `{}`", message.name))?;
+					// enums pack, which was not known for a name alone
+					field.packed &= field.kind.is_packable();
+				}
+			}
+		}
+		for service in self.services.values_mut() {
+			for method in &mut service.methods {
+				for name in [&mut method.input, &mut method.output] {
+					match names.lookup(&service.name, name) {
+						Some(Kind::Message(full)) => *name = full,
+						_ => return Err(format!("unknown message `{name}` in `{}`", service.name)),
+					}
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Adds the types of a `FileDescriptorProto` decoded to JSON.
+	pub fn add_descriptor(&mut self, file: &Value) {
+		let name = file["name"].as_str().unwrap_or_default();
+		self.files.insert(name.to_string());
+		let package = file["package"].as_str().unwrap_or_default();
+		let proto3 = file["syntax"].as_str() == Some("proto3");
+		for message in array(&file["messageType"]) {
+			self.add_message_descriptor(package, message, proto3);
+		}
+		for value in array(&file["enumType"]) {
+			self.add_enum_descriptor(package, value);
+		}
+		for service in array(&file["service"]) {
+			let name = join(package, service["name"].as_str().unwrap_or_default());
+			let methods = array(&service["method"])
+				.map(|method| Method {
+					name: method["name"].as_str().unwrap_or_default().to_string(),
+					input: type_name(&method["inputType"]),
+					output: type_name(&method["outputType"]),
+					client_streaming: method["clientStreaming"] == true,
+					server_streaming: method["serverStreaming"] == true,
+				})
+				.collect();
+			self.services
+				.insert(name.clone(), Service { name, methods });
+		}
+	}
+
+	fn add_message_descriptor(&mut self, scope: &str, message: &Value, proto3: bool) {
+		let name = join(scope, message["name"].as_str().unwrap_or_default());
+		for nested in array(&message["nestedType"]) {
+			self.add_message_descriptor(&name, nested, proto3);
+		}
+		for value in array(&message["enumType"]) {
+			self.add_enum_descriptor(&name, value);
+		}
+		let fields = array(&message["field"])
+			.map(|field| {
+				let name = field["name"].as_str().unwrap_or_default().to_string();
+				let kind = match field["type"].as_str().unwrap_or_default() {
+					"TYPE_MESSAGE" | "TYPE_GROUP" => Kind::Message(type_name(&field["typeName"])),
+					"TYPE_ENUM" => Kind::Enum(type_name(&field["typeName"])),
+					other => {
+						let scalar = other.trim_start_matches("TYPE_").to_ascii_lowercase();
+						Kind::scalar(&scalar).unwrap_or(Kind::Bytes)
+					}
+				};
+				let repeated = field["label"] == "LABEL_REPEATED";
+				let packed = match field["options"]["packed"].as_bool() {
+					Some(packed) => packed,
+					None => proto3,
+				};
+				Field {
+					json_name: match field["jsonName"].as_str() {
+						Some(json_name) => json_name.to_string(),
+						None => json_name(&name),
+					},
+					name,
+					number: field["number"].as_u64().unwrap_or_default() as u32,
+					packed: repeated && packed && kind.is_packable(),
+					kind,
+					repeated,
+				}
+			})
+			.collect();
+		let map_entry = message["options"]["mapEntry"] == true;
+		self.messages.insert(
+			name.clone(),
+			Message {
+				name,
+				fields,
+				map_entry,
+			},
+		);
+	}
+
+	fn add_enum_descriptor(&mut self, scope: &str, value: &Value) {
+		let name = join(scope, value["name"].as_str().unwrap_or_default());
+		let values = array(&value["value"])
+			.map(|value| {
+				let number = value["number"].as_i64().unwrap_or_default() as i32;
+				(
+					value["name"].as_str().unwrap_or_default().to_string(),
+					number,
+				)
+			})
+			.collect();
+		self.enums.insert(name.clone(), Enum { name, values });
+	}
+}
+
+/// Full names of the types of a schema.
+struct Names {
+	messages: BTreeSet<String>,
+	enums: BTreeSet<String>,
+}
+
+impl Names {
+	/// Type a name refers to in a scope: the name as is with a leading dot,
+	/// or in the scope and then in each of its parents.
+	fn lookup(&self, scope: &str, name: &str) -> Option<Kind> {
+		let kind = |full: &str| {
+			if self.messages.contains(full) {
+				Some(Kind::Message(full.to_string()))
+			} else {
+				self.enums
+					.contains(full)
+					.then(|| Kind::Enum(full.to_string()))
+			}
+		};
+		if let Some(full) = name.strip_prefix('.') {
+			return kind(full);
+		}
+		let mut scope = scope;
+		loop {
+			if let Some(found) = kind(&join(scope, name)) {
+				return Some(found);
+			}
+			if scope.is_empty() {
+				return None;
+			}
+			scope = scope.rsplit_once('.').map_or("", |(parent, _)| parent);
+		}
+	}
+}
+
+/// Service and method of `Service/Method` or `Service.Method`.
+pub fn split_method(path: &str) -> Option<(&str, &str)> {
+	let path = path.trim_start_matches('/');
+	path.rsplit_once('/').or_else(|| path.rsplit_once('.'))
+}
+
+/// Name in a scope, `scope.name`.
+pub fn join(scope: &str, name: &str) -> String {
+	match scope {
+		"" => name.to_string(),
+		scope => format!("{scope}.{name}"),
+	}
+}
+
+/// JSON name of a field: lowerCamelCase, underscores removed and the letter
+/// after them in upper case.
+pub fn json_name(name: &str) -> String {
+	let mut out = String::new();
+	let mut upper = false;
+	for c in name.chars() {
+		match c {
+			'_' => upper = true,
+			c if upper => {
+				out.push(c.to_ascii_uppercase());
+				upper = false;
+			}
+			c => out.push(c),
+		}
+	}
+	out
+}
+
+fn array(value: &Value) -> impl Iterator<Item = &Value> {
+	value.as_array().into_iter().flatten()
+}
+
+fn type_name(value: &Value) -> String {
+	let name = value.as_str().unwrap_or_default();
+	name.strip_prefix('.').unwrap_or(name).to_string()
+}
@@ -0,0 +1,625 @@
+//! HTTP/1.1 wire format.
+
+use std::io::{BufRead, Read, Write};
+
+use crate::encoding;
+use crate::error::{Error, Result};
+use crate::http::{Headers, Request, Response};
+use crate::url::{has_scheme, Url};
+
+/// Maximum size of a single status or header line.
+const MAX_LINE: usize = 64 * 1024;
+
+/// Maximum size of a request body read by [`read_request`].
+pub const MAX_REQUEST_BODY: u64 = 64 * 1024 * 1024;
+
+/// Serializes the request head and body, adding `Host`, `Content-Length` and
+/// `Connection` when they are not given explicitly.
+pub fn write_request<W: Write>(out: &mut W, request: &Request) -> Result<()> {
+	write(out, request, "close")
+}
+
+/// Like [`write_request`], asking the server to keep the connection open
+/// for the next request unless the request has a `Connection` header.
+pub fn write_keep_alive_request<W: Write>(out: &mut W, request: &Request) -> Result<()> {
+	write(out, request, "keep-alive")
+}
+
+fn write<W: Write>(out: &mut W, request: &Request, connection: &str) -> Result<()> {
+	let mut head = format!("{} {} HTTP/1.1\r\n", request.method, request.url.path);
+	if !request.headers.contains("Host") {
+		head.push_str(&format!("Host: {}\r\n", request.url.authority()));
+	}
+	for (name, value) in request.headers.iter() {
+		head.push_str(&format!("{name}: {value}\r\n"));
+	}
+	let chunked = request
+		.headers
+		.get("Transfer-Encoding")
+		.is_some_and(|te| te.to_ascii_lowercase().contains("chunked"));
+	if !request.headers.contains("Content-Length")
+		&& !chunked
+		&& (request.body_len() > 0 || matches!(request.method.as_str(), "POST" | "PUT" | "PATCH"))
+	{
+		head.push_str(&format!("Content-Length: {}\r\n", request.body_len()));
+	}
+	if !request.headers.contains("Connection") {
+		head.push_str(&format!("Connection: {connection}\r\n"));
+	}
+	head.push_str("\r\n");
+
+	out.write_all(head.as_bytes())?;
+	match &request.upload {
+		Some(upload) => {
+			std::io::copy(&mut upload.reader(), out)?;
+		}
+		None => out.write_all(&request.body)?,
+	}
+	out.flush()?;
+	Ok(())
+}
+
+/// Reads a full response. The `method` is needed to know whether the
+/// response carries a body.
+pub fn read_response<R: BufRead>(input: &mut R, method: &str) -> Result<Response> {
+	let mut response = read_response_head(input)?;
+	let headers = &response.headers;
+	let mut body = Vec::new();
+	let mut trailers = Headers::new();
+	if has_body(&response, method) {
+		// a transfer coding overrides the length (RFC 9112, 6.3)
+		match (headers.get("Transfer-Encoding"), content_length(headers)?) {
+			(Some(codings), _) => {
+				let (codings, chunked) = strip_chunked(codings);
+				if chunked {
+					(body, trailers) = read_chunked(input, u64::MAX)?;
+				} else {
+					input.read_to_end(&mut body)?;
+				}
+				body = encoding::decode(&body, codings)?;
+			}
+			(None, Some(len)) => body = read_length(input, len)?,
+			(None, None) => {
+				input.read_to_end(&mut body)?;
+			}
+		}
+	}
+
+	response.body = body;
+	response.trailers = trailers;
+	Ok(response)
+}
+
+/// Reads the status line and headers of the final response, skipping
+/// informational responses other than `101 Switching Protocols`.
+pub fn read_response_head<R: BufRead>(input: &mut R) -> Result<Response> {
+	let (version, status, reason, headers) = loop {
+		let head = read_head(input)?;
+		// informational responses are skipped, the final one follows
+		if head.1 >= 200 || head.1 == 101 {
+			break head;
+		}
+	};
+	Ok(Response {
+		version,
+		status,
+		reason,
+		headers,
+		..Default::default()
+	})
+}
+
+/// Returns true if a connection can carry another request once a response
+/// was read with [`read_response`]: the server keeps it open and the body
+/// did not end with it.
+pub fn keeps_connection(response: &Response, method: &str) -> bool {
+	let headers = &response.headers;
+	let close = headers.get("Connection").is_some_and(|value| {
+		value
+			.split(',')
+			.any(|token| token.trim().eq_ignore_ascii_case("close"))
+	});
+	let delimited = !has_body(response, method)
+		|| match headers.get("Transfer-Encoding") {
+			Some(codings) => strip_chunked(codings).1,
+			None => headers.contains("Content-Length"),
+		};
+	response.version == "HTTP/1.1" && response.status != 101 && !close && delimited
+}
+
+fn has_body(response: &Response, method: &str) -> bool {
+	let status = response.status;
+	!(method.eq_ignore_ascii_case("HEAD") || status == 204 || status == 304 || status < 200)
+}
+
+/// Body of a response read as it arrives, without its chunk framing. Other
+/// codings are left alone.
+pub struct BodyReader<R> {
+	input: R,
+	framing: Framing,
+}
+
+enum Framing {
+	Length(u64),
+	/// Bytes left in the current chunk.
+	Chunked(u64),
+	/// Delimited by the end of the connection.
+	Close,
+	Done,
+}
+
+impl<R: BufRead> BodyReader<R> {
+	/// Reader of the body following a response head read from `input`.
+	pub fn new(input: R, response: &Response, method: &str) -> Result<BodyReader<R>> {
+		let headers = &response.headers;
+		let framing = if !has_body(response, method) {
+			Framing::Done
+		} else if let Some(codings) = headers.get("Transfer-Encoding") {
+			match strip_chunked(codings).1 {
+				true => Framing::Chunked(0),
+				false => Framing::Close,
+			}
+		} else {
+			match content_length(headers)? {
+				Some(len) => Framing::Length(len),
+				None => Framing::Close,
+			}
+		};
+		Ok(BodyReader { input, framing })
+	}
+
+	pub fn get_ref(&self) -> &R {
+		&self.input
+	}
+}
+
+impl<R: BufRead> Read for BodyReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		let closed = || std::io::Error::from(std::io::ErrorKind::UnexpectedEof);
+		loop {
+			match self.framing {
+				Framing::Done | Framing::Length(0) => return Ok(0),
+				Framing::Close => return self.input.read(buf),
+				Framing::Length(left) => {
+					let max = buf.len().min(left.try_into().unwrap_or(usize::MAX));
+					let count = self.input.read(&mut buf[..max])?;
+					if count == 0 && max > 0 {
+						return Err(closed());
+					}
+					self.framing = Framing::Length(left - count as u64);
+					return Ok(count);
+				}
+				Framing::Chunked(0) => {
+					let size = read_chunk_size(&mut self.input).map_err(io_error)?;
+					self.framing = match size {
+						0 => {
+							read_headers(&mut self.input).map_err(io_error)?;
+							Framing::Done
+						}
+						size => Framing::Chunked(size),
+					};
+				}
+				Framing::Chunked(left) => {
+					let max = buf.len().min(left.try_into().unwrap_or(usize::MAX));
+					let count = self.input.read(&mut buf[..max])?;
+					if count == 0 && max > 0 {
+						return Err(closed());
+					}
+					let left = left - count as u64;
+					if left == 0
+						&& read_line(&mut self.input).map_err(io_error)? != Some(String::new())
+					{
+						return Err(io_error(Error::Protocol(
+							"missing line break after chunk".into(),
+						)));
+					}
+					self.framing = Framing::Chunked(left);
+					return Ok(count);
+				}
+			}
+		}
+	}
+}
+
+fn io_error(err: Error) -> std::io::Error {
+	match err {
+		Error::Io(err) => err,
+		err => std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()),
+	}
+}
+
+/// Splits a final `chunked` off a `Transfer-Encoding` value, returning the
+/// remaining codings.
+fn strip_chunked(codings: &str) -> (&str, bool) {
+	let (rest, last) = codings.rsplit_once(',').unwrap_or(("", codings));
+	match last.trim().eq_ignore_ascii_case("chunked") {
+		true => (rest, true),
+		false => (codings, false),
+	}
+}
+
+/// Reads a chunked body of at most `limit` bytes and the trailer fields
+/// after it.
+fn read_chunked<R: BufRead>(input: &mut R, limit: u64) -> Result<(Vec<u8>, Headers)> {
+	let mut body = Vec::new();
+	loop {
+		let size = read_chunk_size(input)?;
+		if size == 0 {
+			break;
+		}
+		if size > limit - body.len() as u64 {
+			return Err(body_too_large());
+		}
+		let read = input.take(size).read_to_end(&mut body)?;
+		if read as u64 != size {
+			return Err(Error::Protocol("connection closed in chunked body".into()));
+		}
+		if read_line(input)?.is_none_or(|line| !line.is_empty()) {
+			return Err(Error::Protocol("missing line break after chunk".into()));
+		}
+	}
+	Ok((body, read_headers(input)?))
+}
+
+/// Reads a body of `len` bytes. The buffer grows as bytes arrive, so a
+/// wrong `Content-Length` is reported instead of allocated.
+fn read_length<R: Read>(input: &mut R, len: u64) -> Result<Vec<u8>> {
+	let mut body = Vec::new();
+	let read = input.take(len).read_to_end(&mut body)?;
+	if read as u64 != len {
+		return Err(Error::Protocol(format!(
+			"connection closed after {read} of {len} body bytes"
+		)));
+	}
+	Ok(body)
+}
+
+/// Reads the line starting a chunk and returns the size of the chunk.
+fn read_chunk_size<R: BufRead>(input: &mut R) -> Result<u64> {
+	let line = read_line(input)?
+		.ok_or_else(|| Error::Protocol("connection closed in chunked body".into()))?;
+	// chunk extensions follow a semicolon
+	let size = line.split(';').next().unwrap_or_default().trim();
+	u64::from_str_radix(size, 16)
+		.map_err(|_| Error::Protocol(format!("invalid chunk size line `{line}`")))
+}
+
+/// Reads a request: request line, headers and a `Content-Length` delimited
+/// or chunked body of at most [`MAX_REQUEST_BODY`] bytes. Returns `None`
+/// when the connection is closed before a request starts. The URL is built from the `Host` header unless the request
+/// target is absolute. The `host:port` target of `CONNECT` becomes an
+/// `https` URL.
+pub fn read_request<R: BufRead>(input: &mut R) -> Result<Option<Request>> {
+	// empty lines before a request line are ignored (RFC 9112, 2.2)
+	let line = loop {
+		match read_line(input)? {
+			None => return Ok(None),
+			Some(line) if line.is_empty() => continue,
+			Some(line) => break line,
+		}
+	};
+	let invalid = || Error::Protocol(format!("invalid request line `{line}`"));
+	let mut parts = line.split(' ');
+	let (Some(method), Some(target), Some(version), None) =
+		(parts.next(), parts.next(), parts.next(), parts.next())
+	else {
+		return Err(invalid());
+	};
+	if method.is_empty() || !version.starts_with("HTTP/") {
+		return Err(invalid());
+	}
+	let headers = read_headers(input)?;
+
+	let url = if method == "CONNECT" {
+		Url::parse(&format!("https://{target}"))?
+	} else if has_scheme(target) {
+		Url::parse(target)?
+	} else if target.starts_with('/') {
+		let host = headers.get("Host").unwrap_or("localhost");
+		Url::parse(&format!("http://{host}{target}"))?
+	} else {
+		return Err(invalid());
+	};
+	let mut body = Vec::new();
+	if let Some(codings) = headers.get("Transfer-Encoding") {
+		// the length of a request body is only known when chunked
+		let (codings, chunked) = strip_chunked(codings);
+		if !chunked {
+			return Err(Error::Protocol(format!(
+				"request body without chunked coding in `Transfer-Encoding: {codings}`"
+			)));
+		}
+		(body, _) = read_chunked(input, MAX_REQUEST_BODY)?;
+		body = encoding::decode(&body, codings)?;
+	} else if let Some(len) = content_length(&headers)? {
+		if len > MAX_REQUEST_BODY {
+			return Err(body_too_large());
+		}
+		body = read_length(input, len)?;
+	}
+
+	let mut request = Request::new(method, url);
+	request.headers = headers;
+	request.body = body;
+	Ok(Some(request))
+}
+
+fn body_too_large() -> Error {
+	Error::TooLarge(format!(
+		"request body over {} MiB",
+		MAX_REQUEST_BODY / 1024 / 1024
+	))
+}
+
+/// Serializes a response, adding `Content-Length` when it is missing. With
+/// `head_only` the body is left out, as required for `HEAD` requests.
+pub fn write_response<W: Write>(out: &mut W, response: &Response, head_only: bool) -> Result<()> {
+	let mut head = format!("HTTP/1.1 {}\r\n", response.status);
+	if !response.reason.is_empty() {
+		head = format!("HTTP/1.1 {} {}\r\n", response.status, response.reason);
+	}
+	for (name, value) in response.headers.iter() {
+		head.push_str(&format!("{name}: {value}\r\n"));
+	}
+	if !response.headers.contains("Content-Length")
+		&& !response.headers.contains("Transfer-Encoding")
+	{
+		head.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+	}
+	head.push_str("\r\n");
+	out.write_all(head.as_bytes())?;
+	if !head_only {
+		out.write_all(&response.body)?;
+	}
+	out.flush()?;
+	Ok(())
+}
+
+fn read_head<R: BufRead>(input: &mut R) -> Result<(String, u16, String, Headers)> {
+	let line = read_line(input)?
+		.ok_or_else(|| Error::Protocol("connection closed before response".into()))?;
+	let mut parts = line.splitn(3, ' ');
+	let version = parts.next().unwrap_or_default().to_string();
+	if !version.starts_with("HTTP/") {
+		return Err(Error::Protocol(format!("invalid status line `{line}`")));
+	}
+	let status = parts
+		.next()
+		.and_then(|s| s.parse::<u16>().ok())
+		.ok_or_else(|| Error::Protocol(format!("invalid status line `{line}`")))?;
+	let reason = parts.next().unwrap_or_default().to_string();
+	let headers = read_headers(input)?;
+	Ok((version, status, reason, headers))
+}
+
+/// Reads header lines up to and including the empty line.
+pub fn read_headers<R: BufRead>(input: &mut R) -> Result<Headers> {
+	let mut headers = Headers::new();
+	loop {
+		let line = read_line(input)?
+			.ok_or_else(|| Error::Protocol("connection closed in headers".into()))?;
+		if line.is_empty() {
+			return Ok(headers);
+		}
+		let (name, value) = line
+			.split_once(':')
+			.ok_or_else(|| Error::Protocol(format!("invalid header line `{line}`")))?;
+		headers.append(name.trim(), value.trim());
+	}
+}
+
+/// Reads a CRLF (or bare LF) terminated line. Returns `None` on a clean EOF.
+pub fn read_line<R: BufRead>(input: &mut R) -> Result<Option<String>> {
+	let mut buffer = Vec::new();
+	let count = input.take(MAX_LINE as u64).read_until(b'\n', &mut buffer)?;
+	if count == 0 {
+		return Ok(None);
+	}
+	if buffer.last() != Some(&b'\n') {
+		if buffer.len() >= MAX_LINE {
+			return Err(Error::Protocol("line too long".into()));
+		}
+		return Err(Error::Protocol("unexpected end of stream".into()));
+	}
+	buffer.pop();
+	if buffer.last() == Some(&b'\r') {
+		buffer.pop();
+	}
+	Ok(Some(String::from_utf8_lossy(&buffer).into_owned()))
+}
+
+fn content_length(headers: &Headers) -> Result<Option<u64>> {
+	match headers.get("Content-Length") {
+		Some(value) => value
+			.trim()
+			.parse()
+			.map(Some)
+			.map_err(|_| Error::Protocol(format!("invalid Content-Length `{value}`"))),
+		None => Ok(None),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn writes_request_with_defaults() {
+		let mut request = Request::new(
+			"post",
+			Url::parse("http://example.com:8080/api?x=1").unwrap(),
+		);
+		request.headers.append("Accept", "*/*");
+		request.body = b"hello".to_vec();
+		let mut out = Vec::new();
+		write_request(&mut out, &request).unwrap();
+		assert_eq!(
+			String::from_utf8(out).unwrap(),
+			"POST /api?x=1 HTTP/1.1\r\nHost: example.com:8080\r\nAccept: */*\r\n\
+			 Content-Length: 5\r\nConnection: close\r\n\r\nhello"
+		);
+	}
+
+	#[test]
+	fn tells_whether_the_connection_is_kept() {
+		let kept = |raw: &[u8], method| {
+			let response = read_response(&mut &raw[..], method).unwrap();
+			keeps_connection(&response, method)
+		};
+		assert!(kept(
+			b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok",
+			"GET"
+		));
+		assert!(kept(
+			b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n",
+			"GET"
+		));
+		assert!(kept(b"HTTP/1.1 204 No Content\r\n\r\n", "GET"));
+		assert!(kept(
+			b"HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\n",
+			"HEAD"
+		));
+		assert!(!kept(b"HTTP/1.1 200 OK\r\n\r\nuntil the end", "GET"));
+		assert!(!kept(
+			b"HTTP/1.1 200 OK\r\nConnection: Close\r\nContent-Length: 0\r\n\r\n",
+			"GET"
+		));
+		assert!(!kept(
+			b"HTTP/1.0 200 OK\r\nContent-Length: 0\r\n\r\n",
+			"GET"
+		));
+	}
+
+	#[test]
+	fn reads_response_with_content_length() {
+		let raw = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 3\r\nX-A: b\r\n\r\nabcEXTRA";
+		let response = read_response(&mut &raw[..], "GET").unwrap();
+		assert_eq!(response.status_line(), "HTTP/1.1 200 OK");
+		assert_eq!(response.headers.get("x-a"), Some("b"));
+		assert_eq!(response.body, b"abc");
+
+		// the declared length is not allocated up front
+		let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 999999999999999\r\n\r\nabc";
+		let err = read_response(&mut &raw[..], "GET").unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"protocol error: connection closed after 3 of 999999999999999 body bytes"
+		);
+	}
+
+	#[test]
+	fn reads_until_close_and_skips_head_body() {
+		let raw = b"HTTP/1.0 404 Not Found\nServer: x\n\nnot here";
+		let response = read_response(&mut &raw[..], "GET").unwrap();
+		assert_eq!(response.status, 404);
+		assert_eq!(response.body, b"not here");
+
+		let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n";
+		let response = read_response(&mut &raw[..], "HEAD").unwrap();
+		assert!(response.body.is_empty());
+	}
+
+	#[test]
+	fn reads_chunked_bodies_and_trailers() {
+		let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: X-Sum\r\n\r\n\
+		            5;ext=1\r\nhello\r\n7\r\n, world\r\n0\r\nX-Sum: 12\r\n\r\nNEXT";
+		let mut input = &raw[..];
+		let response = read_response(&mut input, "GET").unwrap();
+		assert_eq!(response.body, b"hello, world");
+		assert_eq!(response.trailers.get("X-Sum"), Some("12"));
+		assert_eq!(input, b"NEXT");
+
+		let truncated = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\na\r\nshort";
+		assert!(read_response(&mut &truncated[..], "GET").is_err());
+		let bad_size = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n";
+		assert!(read_response(&mut &bad_size[..], "GET").is_err());
+
+		let raw = b"POST /a HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n0\r\n\r\n";
+		let request = read_request(&mut &raw[..]).unwrap().unwrap();
+		assert_eq!(request.body, b"ok");
+		let raw = b"POST /a HTTP/1.1\r\nTransfer-Encoding: gzip\r\n\r\n";
+		assert!(read_request(&mut &raw[..]).is_err());
+	}
+
+	#[test]
+	fn streams_bodies_without_framing() {
+		let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+		            3\r\nabc\r\n2\r\nde\r\n0\r\n\r\nNEXT";
+		let mut input = &raw[..];
+		let response = read_response_head(&mut input).unwrap();
+		let mut body = BodyReader::new(input, &response, "GET").unwrap();
+		let mut buf = [0; 8];
+		assert_eq!(body.read(&mut buf).unwrap(), 3);
+		assert_eq!(&buf[..3], b"abc");
+		let mut rest = Vec::new();
+		body.read_to_end(&mut rest).unwrap();
+		assert_eq!(rest, b"de");
+		assert_eq!(*body.get_ref(), b"NEXT");
+
+		let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nokNEXT";
+		let mut input = &raw[..];
+		let response = read_response_head(&mut input).unwrap();
+		let mut body = BodyReader::new(input, &response, "GET").unwrap();
+		let mut rest = Vec::new();
+		body.read_to_end(&mut rest).unwrap();
+		assert_eq!(rest, b"ok");
+	}
+
+	#[test]
+	fn reads_requests_and_writes_responses() {
+		let raw = b"\r\nPUT /items/1?x=2 HTTP/1.1\r\nHost: api.local:8080\r\nContent-Length: 2\r\n\r\nokGET";
+		let mut input = &raw[..];
+		let request = read_request(&mut input).unwrap().unwrap();
+		assert_eq!(request.method, "PUT");
+		assert_eq!(request.url.to_string(), "http://api.local:8080/items/1?x=2");
+		assert_eq!(request.body, b"ok");
+		assert!(read_request(&mut input).is_err());
+		assert!(read_request(&mut &b""[..]).unwrap().is_none());
+
+		let request = read_request(&mut &b"GET http://example.com/a HTTP/1.1\r\n\r\n"[..])
+			.unwrap()
+			.unwrap();
+		assert_eq!(request.url.host, "example.com");
+		let request = read_request(&mut &b"CONNECT example.com:8443 HTTP/1.1\r\n\r\n"[..])
+			.unwrap()
+			.unwrap();
+		assert_eq!(request.url.to_string(), "https://example.com:8443/");
+
+		// bodies are limited, whatever their framing
+		let raw = b"POST / HTTP/1.1\r\nContent-Length: 999999999999999\r\n\r\nok";
+		assert!(matches!(
+			read_request(&mut &raw[..]),
+			Err(Error::TooLarge(_))
+		));
+		let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nfffffffffff\r\n";
+		assert!(matches!(
+			read_request(&mut &raw[..]),
+			Err(Error::TooLarge(_))
+		));
+		let raw = b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nok";
+		assert!(matches!(
+			read_request(&mut &raw[..]),
+			Err(Error::Protocol(_))
+		));
+
+		let response = Response {
+			status: 201,
+			reason: "Created".into(),
+			headers: [("X-Id", "7")].into_iter().collect(),
+			body: b"done".to_vec(),
+			..Default::default()
+		};
+		let mut out = Vec::new();
+		write_response(&mut out, &response, false).unwrap();
+		assert_eq!(
+			String::from_utf8(out).unwrap(),
+			"HTTP/1.1 201 Created\r\nX-Id: 7\r\nContent-Length: 4\r\n\r\ndone"
+		);
+	}
+
+	#[test]
+	fn rejects_garbage() {
+		assert!(read_response(&mut &b"SSH-2.0\r\n\r\n"[..], "GET").is_err());
+		assert!(read_response(&mut &b""[..], "GET").is_err());
+	}
+}
@@ -0,0 +1,268 @@
+//! HTTP/2 frame layer (RFC 9113, section 4 and 6).
+
+use std::io::{self, Read, Write};
+
+use crate::error::{Error, Result};
+
+pub const DATA: u8 = 0x0;
+pub const HEADERS: u8 = 0x1;
+pub const PRIORITY: u8 = 0x2;
+pub const RST_STREAM: u8 = 0x3;
+pub const SETTINGS: u8 = 0x4;
+pub const PUSH_PROMISE: u8 = 0x5;
+pub const PING: u8 = 0x6;
+pub const GOAWAY: u8 = 0x7;
+pub const WINDOW_UPDATE: u8 = 0x8;
+pub const CONTINUATION: u8 = 0x9;
+
+pub const END_STREAM: u8 = 0x1;
+pub const ACK: u8 = 0x1;
+pub const END_HEADERS: u8 = 0x4;
+pub const PADDED: u8 = 0x8;
+pub const PRIORITY_FLAG: u8 = 0x20;
+
+pub const HEADER_TABLE_SIZE: u16 = 0x1;
+pub const ENABLE_PUSH: u16 = 0x2;
+pub const MAX_CONCURRENT_STREAMS: u16 = 0x3;
+pub const INITIAL_WINDOW_SIZE: u16 = 0x4;
+pub const MAX_FRAME_SIZE: u16 = 0x5;
+pub const MAX_HEADER_LIST_SIZE: u16 = 0x6;
+
+/// Client connection preface, sent before the first SETTINGS frame.
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Frame size every endpoint must accept.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16384;
+
+/// Initial flow control window of connections and streams.
+pub const DEFAULT_WINDOW_SIZE: u32 = 65535;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+	pub kind: u8,
+	pub flags: u8,
+	pub stream: u32,
+	pub payload: Vec<u8>,
+}
+
+impl Frame {
+	pub fn new(kind: u8, flags: u8, stream: u32, payload: Vec<u8>) -> Self {
+		Frame {
+			kind,
+			flags,
+			stream,
+			payload,
+		}
+	}
+
+	pub fn settings(settings: &[(u16, u32)]) -> Self {
+		let mut payload = Vec::with_capacity(settings.len() * 6);
+		for (id, value) in settings {
+			payload.extend_from_slice(&id.to_be_bytes());
+			payload.extend_from_slice(&value.to_be_bytes());
+		}
+		Frame::new(SETTINGS, 0, 0, payload)
+	}
+
+	pub fn window_update(stream: u32, increment: u32) -> Self {
+		Frame::new(WINDOW_UPDATE, 0, stream, increment.to_be_bytes().to_vec())
+	}
+
+	pub fn goaway(last_stream: u32, code: u32) -> Self {
+		let mut payload = last_stream.to_be_bytes().to_vec();
+		payload.extend_from_slice(&code.to_be_bytes());
+		Frame::new(GOAWAY, 0, 0, payload)
+	}
+
+	pub fn has(&self, flag: u8) -> bool {
+		self.flags & flag != 0
+	}
+
+	/// Reads the next frame, or `None` when the connection was closed
+	/// between frames.
+	pub fn read<R: Read>(input: &mut R, max_size: usize) -> Result<Option<Frame>> {
+		let mut head = [0u8; 9];
+		let mut filled = 0;
+		while filled < head.len() {
+			match input.read(&mut head[filled..]) {
+				Ok(0) if filled == 0 => return Ok(None),
+				Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+				Ok(n) => filled += n,
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+				Err(err) => return Err(err.into()),
+			}
+		}
+		let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+		if len > max_size {
+			return Err(Error::Protocol(format!(
+				"frame of {len} bytes exceeds the maximum of {max_size}"
+			)));
+		}
+		let stream = u32::from_be_bytes([head[5], head[6], head[7], head[8]]) & 0x7fff_ffff;
+		let mut payload = vec![0; len];
+		input.read_exact(&mut payload)?;
+		Ok(Some(Frame::new(head[3], head[4], stream, payload)))
+	}
+
+	pub fn write<W: Write>(&self, output: &mut W) -> Result<()> {
+		let len = (self.payload.len() as u32).to_be_bytes();
+		let mut head = [0u8; 9];
+		head[..3].copy_from_slice(&len[1..]);
+		head[3] = self.kind;
+		head[4] = self.flags;
+		head[5..].copy_from_slice(&self.stream.to_be_bytes());
+		output.write_all(&head)?;
+		output.write_all(&self.payload)?;
+		Ok(())
+	}
+
+	/// Payload of a DATA, HEADERS or PUSH_PROMISE frame without padding
+	/// and, for HEADERS, without the priority fields.
+	pub fn content(&self) -> Result<&[u8]> {
+		let mut payload = &self.payload[..];
+		let mut padding = 0;
+		if self.has(PADDED) && matches!(self.kind, DATA | HEADERS | PUSH_PROMISE) {
+			let (&len, rest) = payload
+				.split_first()
+				.ok_or_else(|| Error::Protocol("empty padded frame".into()))?;
+			padding = len as usize;
+			payload = rest;
+		}
+		if self.kind == HEADERS && self.has(PRIORITY_FLAG) {
+			payload = payload.get(5..).unwrap_or_default();
+		}
+		if padding > payload.len() {
+			return Err(Error::Protocol("padding exceeds frame payload".into()));
+		}
+		Ok(&payload[..payload.len() - padding])
+	}
+
+	/// Parses the parameters of a SETTINGS frame.
+	pub fn parse_settings(&self) -> Result<Vec<(u16, u32)>> {
+		if !self.payload.len().is_multiple_of(6) {
+			return Err(Error::Protocol("malformed SETTINGS frame".into()));
+		}
+		Ok(self
+			.payload
+			.chunks(6)
+			.map(|c| {
+				(
+					u16::from_be_bytes([c[0], c[1]]),
+					u32::from_be_bytes([c[2], c[3], c[4], c[5]]),
+				)
+			})
+			.collect())
+	}
+
+	/// Big-endian 32-bit word of the payload at `offset`.
+	pub fn word(&self, offset: usize) -> Result<u32> {
+		match self.payload.get(offset..offset + 4) {
+			Some(b) => Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]])),
+			None => Err(Error::Protocol(format!(
+				"truncated {} frame",
+				kind_name(self.kind)
+			))),
+		}
+	}
+}
+
+pub fn kind_name(kind: u8) -> String {
+	let name = match kind {
+		DATA => "DATA",
+		HEADERS => "HEADERS",
+		PRIORITY => "PRIORITY",
+		RST_STREAM => "RST_STREAM",
+		SETTINGS => "SETTINGS",
+		PUSH_PROMISE => "PUSH_PROMISE",
+		PING => "PING",
+		GOAWAY => "GOAWAY",
+		WINDOW_UPDATE => "WINDOW_UPDATE",
+		CONTINUATION => "CONTINUATION",
+		other => return format!("0x{other:02x}"),
+	};
+	name.to_string()
+}
+
+pub fn setting_name(id: u16) -> String {
+	let name = match id {
+		HEADER_TABLE_SIZE => "HEADER_TABLE_SIZE",
+		ENABLE_PUSH => "ENABLE_PUSH",
+		MAX_CONCURRENT_STREAMS => "MAX_CONCURRENT_STREAMS",
+		INITIAL_WINDOW_SIZE => "INITIAL_WINDOW_SIZE",
+		MAX_FRAME_SIZE => "MAX_FRAME_SIZE",
+		MAX_HEADER_LIST_SIZE => "MAX_HEADER_LIST_SIZE",
+		0x8 => "ENABLE_CONNECT_PROTOCOL",
+		0x9 => "NO_RFC7540_PRIORITIES",
+		other => return format!("0x{other:x}"),
+	};
+	name.to_string()
+}
+
+/// Name of an error code used by RST_STREAM and GOAWAY.
+pub fn error_name(code: u32) -> String {
+	const NAMES: [&str; 14] = [
+		"NO_ERROR",
+		"PROTOCOL_ERROR",
+		"INTERNAL_ERROR",
+		"FLOW_CONTROL_ERROR",
+		"SETTINGS_TIMEOUT",
+		"STREAM_CLOSED",
+		"FRAME_SIZE_ERROR",
+		"REFUSED_STREAM",
+		"CANCEL",
+		"COMPRESSION_ERROR",
+		"CONNECT_ERROR",
+		"ENHANCE_YOUR_CALM",
+		"INADEQUATE_SECURITY",
+		"HTTP_1_1_REQUIRED",
+	];
+	match NAMES.get(code as usize) {
+		Some(name) => name.to_string(),
+		None => format!("0x{code:x}"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn round_trips_frames() {
+		let frame = Frame::settings(&[(ENABLE_PUSH, 0), (INITIAL_WINDOW_SIZE, 1 << 20)]);
+		let mut wire = Vec::new();
+		frame.write(&mut wire).unwrap();
+		assert_eq!(&wire[..9], [0, 0, 12, SETTINGS, 0, 0, 0, 0, 0]);
+		let mut input = &wire[..];
+		let read = Frame::read(&mut input, DEFAULT_MAX_FRAME_SIZE)
+			.unwrap()
+			.unwrap();
+		assert_eq!(read, frame);
+		assert_eq!(
+			read.parse_settings().unwrap(),
+			[(ENABLE_PUSH, 0), (INITIAL_WINDOW_SIZE, 1 << 20)]
+		);
+		assert!(Frame::read(&mut input, DEFAULT_MAX_FRAME_SIZE)
+			.unwrap()
+			.is_none());
+		assert!(Frame::read(&mut &wire[..5], DEFAULT_MAX_FRAME_SIZE).is_err());
+		assert!(Frame::read(&mut &wire[..], 8).is_err());
+	}
+
+	#[test]
+	fn strips_padding_and_priority() {
+		let frame = Frame::new(DATA, PADDED, 1, vec![2, b'h', b'i', 0, 0]);
+		assert_eq!(frame.content().unwrap(), b"hi");
+		let frame = Frame::new(HEADERS, PRIORITY_FLAG, 1, vec![0, 0, 0, 0, 16, 0x82]);
+		assert_eq!(frame.content().unwrap(), [0x82]);
+		let frame = Frame::new(DATA, PADDED, 1, vec![4, b'h']);
+		assert!(frame.content().is_err());
+	}
+
+	#[test]
+	fn names_codes() {
+		assert_eq!(error_name(8), "CANCEL");
+		assert_eq!(error_name(0x20), "0x20");
+		assert_eq!(setting_name(MAX_FRAME_SIZE), "MAX_FRAME_SIZE");
+		assert_eq!(kind_name(GOAWAY), "GOAWAY");
+	}
+}
@@ -0,0 +1,353 @@
+//! HPACK header compression (RFC 7541).
+
+use std::collections::VecDeque;
+
+use super::huffman;
+
+const STATIC_TABLE: [(&str, &str); 61] = [
+	(":authority", ""),
+	(":method", "GET"),
+	(":method", "POST"),
+	(":path", "/"),
+	(":path", "/index.html"),
+	(":scheme", "http"),
+	(":scheme", "https"),
+	(":status", "200"),
+	(":status", "204"),
+	(":status", "206"),
+	(":status", "304"),
+	(":status", "400"),
+	(":status", "404"),
+	(":status", "500"),
+	("accept-charset", ""),
+	("accept-encoding", "gzip, deflate"),
+	("accept-language", ""),
+	("accept-ranges", ""),
+	("accept", ""),
+	("access-control-allow-origin", ""),
+	("age", ""),
+	("allow", ""),
+	("authorization", ""),
+	("cache-control", ""),
+	("content-disposition", ""),
+	("content-encoding", ""),
+	("content-language", ""),
+	("content-length", ""),
+	("content-location", ""),
+	("content-range", ""),
+	("content-type", ""),
+	("cookie", ""),
+	("date", ""),
+	("etag", ""),
+	("expect", ""),
+	("expires", ""),
+	("from", ""),
+	("host", ""),
+	("if-match", ""),
+	("if-modified-since", ""),
+	("if-none-match", ""),
+	("if-range", ""),
+	("if-unmodified-since", ""),
+	("last-modified", ""),
+	("link", ""),
+	("location", ""),
+	("max-forwards", ""),
+	("proxy-authenticate", ""),
+	("proxy-authorization", ""),
+	("range", ""),
+	("referer", ""),
+	("refresh", ""),
+	("retry-after", ""),
+	("server", ""),
+	("set-cookie", ""),
+	("strict-transport-security", ""),
+	("transfer-encoding", ""),
+	("user-agent", ""),
+	("vary", ""),
+	("via", ""),
+	("www-authenticate", ""),
+];
+
+/// Size of a header field as defined for the dynamic table and the
+/// `SETTINGS_MAX_HEADER_LIST_SIZE` setting.
+pub fn field_size(name: &str, value: &str) -> usize {
+	name.len() + value.len() + 32
+}
+
+/// Stateless encoder: fields are never added to the dynamic table, so the
+/// peer's table size setting does not matter. Strings are Huffman coded
+/// when that is shorter.
+pub fn encode(headers: &[(String, String)]) -> Vec<u8> {
+	let mut out = Vec::new();
+	for (name, value) in headers {
+		let exact = STATIC_TABLE
+			.iter()
+			.position(|(n, v)| n == name && v == value);
+		let named = STATIC_TABLE.iter().position(|(n, _)| n == name);
+		match (exact, named) {
+			(Some(index), _) => encode_int(&mut out, 0x80, 7, index + 1),
+			(None, Some(index)) => {
+				encode_int(&mut out, 0x00, 4, index + 1);
+				encode_string(&mut out, value.as_bytes());
+			}
+			(None, None) => {
+				out.push(0x00);
+				encode_string(&mut out, name.as_bytes());
+				encode_string(&mut out, value.as_bytes());
+			}
+		}
+	}
+	out
+}
+
+fn encode_int(out: &mut Vec<u8>, flags: u8, prefix: u32, value: usize) {
+	let max = (1usize << prefix) - 1;
+	if value < max {
+		out.push(flags | value as u8);
+		return;
+	}
+	out.push(flags | max as u8);
+	let mut rest = value - max;
+	while rest >= 128 {
+		out.push((rest % 128) as u8 | 0x80);
+		rest /= 128;
+	}
+	out.push(rest as u8);
+}
+
+fn encode_string(out: &mut Vec<u8>, data: &[u8]) {
+	let huffman_len = huffman::encoded_len(data);
+	if huffman_len < data.len() {
+		encode_int(out, 0x80, 7, huffman_len);
+		huffman::encode(data, out);
+	} else {
+		encode_int(out, 0x00, 7, data.len());
+		out.extend_from_slice(data);
+	}
+}
+
+/// Decoder with its dynamic table.
+#[derive(Debug)]
+pub struct Decoder {
+	table: VecDeque<(String, String)>,
+	size: usize,
+	max_size: usize,
+	/// Upper bound for table size updates, our `SETTINGS_HEADER_TABLE_SIZE`.
+	limit: usize,
+}
+
+impl Default for Decoder {
+	fn default() -> Self {
+		Decoder::new(4096)
+	}
+}
+
+impl Decoder {
+	pub fn new(limit: usize) -> Self {
+		Decoder {
+			table: VecDeque::new(),
+			size: 0,
+			max_size: limit,
+			limit,
+		}
+	}
+
+	/// Decodes a complete header block.
+	pub fn decode(&mut self, block: &[u8]) -> Result<Vec<(String, String)>, String> {
+		let mut headers = Vec::new();
+		let mut input = block;
+		while let Some(&first) = input.first() {
+			if first & 0x80 != 0 {
+				let index = decode_int(&mut input, 7)?;
+				headers.push(self.entry(index)?);
+			} else if first & 0xc0 == 0x40 {
+				let field = self.literal(&mut input, 6)?;
+				self.insert(field.clone());
+				headers.push(field);
+			} else if first & 0xe0 == 0x20 {
+				let size = decode_int(&mut input, 5)?;
+				if size > self.limit {
+					return Err(format!("table size update {size} exceeds {}", self.limit));
+				}
+				self.max_size = size;
+				self.evict(0);
+			} else {
+				// without indexing (0000) or never indexed (0001)
+				headers.push(self.literal(&mut input, 4)?);
+			}
+		}
+		Ok(headers)
+	}
+
+	fn entry(&self, index: usize) -> Result<(String, String), String> {
+		match index {
+			0 => Err("invalid header index 0".into()),
+			1..=61 => {
+				let (name, value) = STATIC_TABLE[index - 1];
+				Ok((name.to_string(), value.to_string()))
+			}
+			_ => self
+				.table
+				.get(index - 62)
+				.cloned()
+				.ok_or_else(|| format!("header index {index} out of range")),
+		}
+	}
+
+	fn literal(&self, input: &mut &[u8], prefix: u32) -> Result<(String, String), String> {
+		let index = decode_int(input, prefix)?;
+		let name = if index == 0 {
+			decode_string(input)?
+		} else {
+			self.entry(index)?.0
+		};
+		let value = decode_string(input)?;
+		Ok((name, value))
+	}
+
+	fn insert(&mut self, field: (String, String)) {
+		let size = field_size(&field.0, &field.1);
+		self.evict(size);
+		if size <= self.max_size {
+			self.size += size;
+			self.table.push_front(field);
+		}
+	}
+
+	/// Evicts entries until `extra` more bytes fit in the table.
+	fn evict(&mut self, extra: usize) {
+		while self.size + extra > self.max_size {
+			match self.table.pop_back() {
+				Some((name, value)) => self.size -= field_size(&name, &value),
+				None => break,
+			}
+		}
+	}
+}
+
+fn decode_int(input: &mut &[u8], prefix: u32) -> Result<usize, String> {
+	let truncated = || "truncated integer".to_string();
+	let (&first, rest) = input.split_first().ok_or_else(truncated)?;
+	*input = rest;
+	let max = (1usize << prefix) - 1;
+	let mut value = first as usize & max;
+	if value < max {
+		return Ok(value);
+	}
+	let mut shift = 0;
+	loop {
+		let (&byte, rest) = input.split_first().ok_or_else(truncated)?;
+		*input = rest;
+		if shift > 28 {
+			return Err("integer overflow".into());
+		}
+		value += ((byte & 0x7f) as usize) << shift;
+		shift += 7;
+		if byte & 0x80 == 0 {
+			return Ok(value);
+		}
+	}
+}
+
+fn decode_string(input: &mut &[u8]) -> Result<String, String> {
+	let huffman = input.first().is_some_and(|b| b & 0x80 != 0);
+	let len = decode_int(input, 7)?;
+	if input.len() < len {
+		return Err("truncated string".into());
+	}
+	let (data, rest) = input.split_at(len);
+	*input = rest;
+	let bytes = if huffman {
+		huffman::decode(data)?
+	} else {
+		data.to_vec()
+	};
+	Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn hex(text: &str) -> Vec<u8> {
+		(0..text.len())
+			.step_by(2)
+			.map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+			.collect()
+	}
+
+	fn fields(list: &[(&str, &str)]) -> Vec<(String, String)> {
+		list.iter()
+			.map(|(n, v)| (n.to_string(), v.to_string()))
+			.collect()
+	}
+
+	#[test]
+	fn decodes_rfc_requests() {
+		// RFC 7541, C.4: requests with Huffman coding sharing a dynamic table
+		let mut decoder = Decoder::default();
+		let first = decoder
+			.decode(&hex("828684418cf1e3c2e5f23a6ba0ab90f4ff"))
+			.unwrap();
+		assert_eq!(
+			first,
+			fields(&[
+				(":method", "GET"),
+				(":scheme", "http"),
+				(":path", "/"),
+				(":authority", "www.example.com")
+			])
+		);
+		let second = decoder.decode(&hex("828684be5886a8eb10649cbf")).unwrap();
+		assert_eq!(second[3], (":authority".into(), "www.example.com".into()));
+		assert_eq!(second[4], ("cache-control".into(), "no-cache".into()));
+		let third = decoder
+			.decode(&hex("828785bf408825a849e95ba97d7f8925a849e95bb8e8b4bf"))
+			.unwrap();
+		assert_eq!(third[2], (":path".into(), "/index.html".into()));
+		assert_eq!(third[4], ("custom-key".into(), "custom-value".into()));
+		assert_eq!(decoder.size, 164);
+	}
+
+	#[test]
+	fn decodes_plain_literals() {
+		// RFC 7541, C.3.1
+		let mut decoder = Decoder::default();
+		let headers = decoder
+			.decode(&hex("828684410f7777772e6578616d706c652e636f6d"))
+			.unwrap();
+		assert_eq!(headers[3], (":authority".into(), "www.example.com".into()));
+	}
+
+	#[test]
+	fn round_trips_encoded_headers() {
+		let headers = fields(&[
+			(":method", "GET"),
+			(":path", "/items?page=2"),
+			("accept", "application/json"),
+			("x-custom", "value with spaces"),
+			("x-long", &"a".repeat(300)),
+		]);
+		let block = encode(&headers);
+		assert_eq!(block[0], 0x82);
+		assert_eq!(Decoder::default().decode(&block).unwrap(), headers);
+	}
+
+	#[test]
+	fn evicts_and_rejects() {
+		let mut decoder = Decoder::new(64);
+		// literal with indexing, new name "a" = "b"; then a size update to 0
+		decoder
+			.decode(&hex("400161016240016301642140"))
+			.unwrap_err();
+		let mut decoder = Decoder::new(64);
+		decoder.decode(&hex("4001610162")).unwrap();
+		assert_eq!(decoder.table.len(), 1);
+		decoder.decode(&hex("4001630164")).unwrap();
+		assert_eq!(decoder.table.len(), 1);
+		assert_eq!(decoder.entry(62).unwrap(), ("c".into(), "d".into()));
+		decoder.decode(&hex("20")).unwrap();
+		assert_eq!(decoder.size, 0);
+		assert!(decoder.decode(&hex("be")).is_err());
+	}
+}
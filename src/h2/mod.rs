@@ -0,0 +1,425 @@
+//! HTTP/2 client (RFC 9113). Like HTTP/1.1, every request uses its own
+//! connection, so the request is always sent on stream 1.
+
+pub mod frame;
+pub mod hpack;
+mod huffman;
+
+use std::fmt;
+use std::io::{BufReader, Read, Write};
+use std::time::Instant;
+
+use crate::error::{Error, Result};
+use crate::http::{Headers, Part, PartHandler, Request, Response};
+use frame::Frame;
+
+/// Stream used for the request.
+const STREAM: u32 = 1;
+
+/// Receive window advertised for the connection and the stream.
+const WINDOW: u32 = 1 << 20;
+
+/// Size of an HPACK encoded header block and of the fields it contains.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HeaderBlock {
+	pub fields: usize,
+	/// Bytes on the wire.
+	pub encoded: usize,
+	/// Sum of the decoded name and value lengths.
+	pub decoded: usize,
+}
+
+impl HeaderBlock {
+	fn new(fields: &[(String, String)], encoded: usize) -> Self {
+		HeaderBlock {
+			fields: fields.len(),
+			encoded,
+			decoded: fields.iter().map(|(n, v)| n.len() + v.len()).sum(),
+		}
+	}
+}
+
+impl fmt::Display for HeaderBlock {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{} fields, {} bytes HPACK, {} bytes decoded",
+			self.fields, self.encoded, self.decoded
+		)
+	}
+}
+
+/// Details of an HTTP/2 exchange.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct H2Info {
+	pub stream_id: u32,
+	/// SETTINGS parameters sent by the client.
+	pub local_settings: Vec<(u16, u32)>,
+	/// SETTINGS parameters received from the server.
+	pub remote_settings: Vec<(u16, u32)>,
+	pub request_headers: HeaderBlock,
+	pub response_headers: HeaderBlock,
+	pub trailers: Option<HeaderBlock>,
+}
+
+impl fmt::Display for H2Info {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let settings = |list: &[(u16, u32)]| {
+			if list.is_empty() {
+				return "(none)".to_string();
+			}
+			list.iter()
+				.map(|(id, value)| format!("{}={value}", frame::setting_name(*id)))
+				.collect::<Vec<_>>()
+				.join(", ")
+		};
+		writeln!(f, "* HTTP/2 stream: {}", self.stream_id)?;
+		writeln!(f, "* SETTINGS sent: {}", settings(&self.local_settings))?;
+		writeln!(
+			f,
+			"* SETTINGS received: {}",
+			settings(&self.remote_settings)
+		)?;
+		writeln!(f, "* Request headers: {}", self.request_headers)?;
+		writeln!(f, "* Response headers: {}", self.response_headers)?;
+		if let Some(trailers) = &self.trailers {
+			writeln!(f, "* Trailers: {trailers}")?;
+		}
+		Ok(())
+	}
+}
+
+/// Sends the request over a fresh connection, starting with the client
+/// preface. Used after ALPN selected `h2` and for prior knowledge `h2c`.
+/// `first_byte` is set when the first response headers arrive, and the
+/// response is also passed to `on_part` as it arrives when given.
+pub fn send<S: Read + Write>(
+	stream: S,
+	request: &Request,
+	first_byte: &mut Option<Instant>,
+	on_part: Option<&mut PartHandler<'_>>,
+) -> Result<Response> {
+	let mut conn = Connection {
+		io: BufReader::new(stream),
+		decoder: hpack::Decoder::default(),
+		info: H2Info {
+			stream_id: STREAM,
+			local_settings: vec![
+				(frame::ENABLE_PUSH, 0),
+				(frame::INITIAL_WINDOW_SIZE, WINDOW),
+			],
+			..Default::default()
+		},
+		max_frame_size: frame::DEFAULT_MAX_FRAME_SIZE,
+		initial_window: frame::DEFAULT_WINDOW_SIZE as i64,
+		conn_window: frame::DEFAULT_WINDOW_SIZE as i64,
+		stream_window: frame::DEFAULT_WINDOW_SIZE as i64,
+		first_byte: None,
+	};
+	let response = conn.exchange(request, on_part);
+	*first_byte = conn.first_byte;
+	// best effort, the connection is closed either way
+	let _ = conn.write(&Frame::goaway(0, 0));
+	let _ = conn.io.get_mut().flush();
+	response
+}
+
+struct Connection<S> {
+	io: BufReader<S>,
+	decoder: hpack::Decoder,
+	info: H2Info,
+	/// Largest frame the server accepts.
+	max_frame_size: usize,
+	initial_window: i64,
+	/// Send windows granted by the server.
+	conn_window: i64,
+	stream_window: i64,
+	first_byte: Option<Instant>,
+}
+
+/// Response state collected from the frames of our stream.
+#[derive(Default)]
+struct Incoming {
+	head: Option<(u16, Headers)>,
+	body: Vec<u8>,
+	trailers: Headers,
+	done: bool,
+}
+
+impl<S: Read + Write> Connection<S> {
+	fn exchange(
+		&mut self,
+		request: &Request,
+		mut on_part: Option<&mut PartHandler<'_>>,
+	) -> Result<Response> {
+		self.io.get_mut().write_all(frame::PREFACE)?;
+		self.write(&Frame::settings(&self.info.local_settings))?;
+		self.write(&Frame::window_update(
+			0,
+			WINDOW - frame::DEFAULT_WINDOW_SIZE,
+		))?;
+
+		let fields = request_fields(request);
+		let block = hpack::encode(&fields);
+		self.info.request_headers = HeaderBlock::new(&fields, block.len());
+		let mut remaining = request.body_len();
+		self.write_headers(block, remaining == 0)?;
+
+		let mut body: Box<dyn Read + '_> = match &request.upload {
+			Some(upload) => Box::new(upload.reader()),
+			None => Box::new(&request.body[..]),
+		};
+		// read but not yet sent for lack of flow-control window
+		let mut pending = Vec::new();
+		let mut incoming = Incoming::default();
+		while !incoming.done {
+			while remaining > 0 {
+				let allowed = self.conn_window.min(self.stream_window);
+				if allowed <= 0 {
+					break;
+				}
+				if pending.is_empty() {
+					pending.resize(remaining.min(self.max_frame_size as u64) as usize, 0);
+					body.read_exact(&mut pending)?;
+				}
+				let len = pending.len().min(allowed as usize);
+				let chunk: Vec<u8> = pending.drain(..len).collect();
+				remaining -= len as u64;
+				let flags = if remaining == 0 { frame::END_STREAM } else { 0 };
+				self.write(&Frame::new(frame::DATA, flags, STREAM, chunk))?;
+				self.conn_window -= len as i64;
+				self.stream_window -= len as i64;
+			}
+			self.io.get_mut().flush()?;
+
+			let frame =
+				Frame::read(&mut self.io, frame::DEFAULT_MAX_FRAME_SIZE)?.ok_or_else(|| {
+					Error::Protocol("connection closed before the response was complete".into())
+				})?;
+			let Some(on_part) = &mut on_part else {
+				self.handle(frame, &mut incoming)?;
+				continue;
+			};
+			if (frame.kind, frame.stream) == (frame::DATA, STREAM) {
+				on_part(Part::Data(frame.content()?))?;
+			}
+			let had_head = incoming.head.is_some();
+			self.handle(frame, &mut incoming)?;
+			if let (false, Some((status, headers))) = (had_head, &incoming.head) {
+				on_part(Part::Head(&Response {
+					version: "HTTP/2".into(),
+					status: *status,
+					headers: headers.clone(),
+					..Default::default()
+				}))?;
+			}
+		}
+
+		let (status, headers) = incoming
+			.head
+			.ok_or_else(|| Error::Protocol("missing response headers".into()))?;
+		Ok(Response {
+			version: "HTTP/2".into(),
+			status,
+			reason: String::new(),
+			headers,
+			body: incoming.body,
+			trailers: incoming.trailers,
+			h2: Some(self.info.clone()),
+			..Default::default()
+		})
+	}
+
+	fn handle(&mut self, frame: Frame, incoming: &mut Incoming) -> Result<()> {
+		match frame.kind {
+			frame::HEADERS => {
+				let end_stream = frame.has(frame::END_STREAM);
+				let stream = frame.stream;
+				let block = self.header_block(frame)?;
+				let encoded = block.len();
+				let fields = self
+					.decoder
+					.decode(&block)
+					.map_err(|err| Error::Protocol(format!("HPACK: {err}")))?;
+				if stream == STREAM {
+					self.first_byte.get_or_insert_with(Instant::now);
+					self.headers(fields, encoded, incoming)?;
+					incoming.done |= end_stream;
+				}
+			}
+			frame::DATA if frame.stream == STREAM => {
+				incoming.body.extend_from_slice(frame.content()?);
+				incoming.done |= frame.has(frame::END_STREAM);
+				self.replenish(&frame, !incoming.done)?;
+			}
+			frame::DATA => self.replenish(&frame, false)?,
+			frame::SETTINGS if !frame.has(frame::ACK) => {
+				for (id, value) in frame.parse_settings()? {
+					match id {
+						frame::INITIAL_WINDOW_SIZE => {
+							self.stream_window += value as i64 - self.initial_window;
+							self.initial_window = value as i64;
+						}
+						frame::MAX_FRAME_SIZE => self.max_frame_size = value as usize,
+						_ => {}
+					}
+					self.info.remote_settings.push((id, value));
+				}
+				self.write(&Frame::new(frame::SETTINGS, frame::ACK, 0, Vec::new()))?;
+			}
+			frame::PING if !frame.has(frame::ACK) => {
+				self.write(&Frame::new(frame::PING, frame::ACK, 0, frame.payload))?;
+			}
+			frame::WINDOW_UPDATE => {
+				let increment = (frame.word(0)? & 0x7fff_ffff) as i64;
+				match frame.stream {
+					0 => self.conn_window += increment,
+					STREAM => self.stream_window += increment,
+					_ => {}
+				}
+			}
+			frame::RST_STREAM if frame.stream == STREAM => {
+				return Err(Error::Protocol(format!(
+					"stream reset by server: {}",
+					frame::error_name(frame.word(0)?)
+				)));
+			}
+			frame::GOAWAY => {
+				let last_stream = frame.word(0)? & 0x7fff_ffff;
+				let code = frame.word(4)?;
+				if last_stream < STREAM || code != 0 {
+					let debug = String::from_utf8_lossy(frame.payload.get(8..).unwrap_or_default());
+					let mut message = format!("server sent GOAWAY: {}", frame::error_name(code));
+					if !debug.is_empty() {
+						message.push_str(&format!(" ({debug})"));
+					}
+					return Err(Error::Protocol(message));
+				}
+			}
+			frame::PUSH_PROMISE => {
+				return Err(Error::Protocol("server push was not enabled".into()));
+			}
+			_ => {}
+		}
+		Ok(())
+	}
+
+	/// Collects a header block split across CONTINUATION frames.
+	fn header_block(&mut self, frame: Frame) -> Result<Vec<u8>> {
+		let mut block = frame.content()?.to_vec();
+		let mut end = frame.has(frame::END_HEADERS);
+		while !end {
+			let next = Frame::read(&mut self.io, frame::DEFAULT_MAX_FRAME_SIZE)?
+				.ok_or_else(|| Error::Protocol("connection closed inside a header block".into()))?;
+			if next.kind != frame::CONTINUATION || next.stream != frame.stream {
+				return Err(Error::Protocol(format!(
+					"expected CONTINUATION, got {}",
+					frame::kind_name(next.kind)
+				)));
+			}
+			block.extend_from_slice(&next.payload);
+			end = next.has(frame::END_HEADERS);
+		}
+		Ok(block)
+	}
+
+	fn headers(
+		&mut self,
+		fields: Vec<(String, String)>,
+		encoded: usize,
+		incoming: &mut Incoming,
+	) -> Result<()> {
+		let size = HeaderBlock::new(&fields, encoded);
+		if incoming.head.is_some() {
+			self.info.trailers = Some(size);
+			incoming.trailers = regular_fields(fields);
+			return Ok(());
+		}
+		let status = fields
+			.iter()
+			.find(|(name, _)| name == ":status")
+			.and_then(|(_, value)| value.parse::<u16>().ok())
+			.ok_or_else(|| Error::Protocol("response without a valid :status".into()))?;
+		// informational responses are skipped, the final one follows
+		if status >= 200 {
+			self.info.response_headers = size;
+			incoming.head = Some((status, regular_fields(fields)));
+		}
+		Ok(())
+	}
+
+	/// Gives back the flow control credit used by a DATA frame.
+	fn replenish(&mut self, frame: &Frame, stream: bool) -> Result<()> {
+		let len = frame.payload.len() as u32;
+		if len > 0 {
+			self.write(&Frame::window_update(0, len))?;
+			if stream {
+				self.write(&Frame::window_update(frame.stream, len))?;
+			}
+		}
+		Ok(())
+	}
+
+	fn write_headers(&mut self, block: Vec<u8>, end_stream: bool) -> Result<()> {
+		let mut chunks = block.chunks(self.max_frame_size).peekable();
+		let mut kind = frame::HEADERS;
+		let mut flags = if end_stream { frame::END_STREAM } else { 0 };
+		while let Some(chunk) = chunks.next() {
+			if chunks.peek().is_none() {
+				flags |= frame::END_HEADERS;
+			}
+			self.write(&Frame::new(kind, flags, STREAM, chunk.to_vec()))?;
+			kind = frame::CONTINUATION;
+			flags = 0;
+		}
+		Ok(())
+	}
+
+	fn write(&mut self, frame: &Frame) -> Result<()> {
+		frame.write(self.io.get_mut())
+	}
+}
+
+/// Pseudo-headers followed by the request headers in lower case, without
+/// the connection specific ones HTTP/2 forbids.
+fn request_fields(request: &Request) -> Vec<(String, String)> {
+	let url = &request.url;
+	let authority = match request.headers.get("Host") {
+		Some(host) => host.to_string(),
+		None => url.authority(),
+	};
+	let mut fields = vec![
+		(":method".to_string(), request.method.clone()),
+		(":scheme".to_string(), url.scheme.clone()),
+		(":authority".to_string(), authority),
+		(":path".to_string(), url.path.clone()),
+	];
+	for (name, value) in request.headers.iter() {
+		let name = name.to_ascii_lowercase();
+		let forbidden = matches!(
+			name.as_str(),
+			"host"
+				| "connection"
+				| "keep-alive"
+				| "proxy-connection"
+				| "transfer-encoding"
+				| "upgrade"
+		) || (name == "te" && value != "trailers");
+		if !forbidden {
+			fields.push((name, value.to_string()));
+		}
+	}
+	if !request.headers.contains("Content-Length")
+		&& (request.body_len() > 0 || matches!(request.method.as_str(), "POST" | "PUT" | "PATCH"))
+	{
+		fields.push(("content-length".into(), request.body_len().to_string()));
+	}
+	fields
+}
+
+fn regular_fields(fields: Vec<(String, String)>) -> Headers {
+	fields
+		.into_iter()
+		.filter(|(name, _)| !name.starts_with(':'))
+		.collect()
+}
@@ -0,0 +1,272 @@
+//! Import of HAR 1.2 (HTTP Archive) files, as saved by browser developer
+//! tools and proxies.
+//!
+//! Every entry becomes a [`Request`], with the recorded [`Response`] when
+//! the archive has one. Exporting runs as HAR is done by
+//! [`crate::report::write_har`].
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::error::{Error, ParseError, Result};
+use crate::http::{Headers, Request, Response};
+use crate::timing::Timings;
+use crate::url::{form_encode, Url};
+use crate::util::base64_decode;
+
+/// Headers that are not copied from an archive. Pseudo-headers of HTTP/2
+/// requests are skipped as well.
+const SKIPPED_HEADERS: [&str; 2] = ["Content-Length", "Connection"];
+
+/// Exchange of a HAR archive.
+#[derive(Clone, Debug)]
+pub struct HarEntry {
+	/// `startedDateTime` of the entry as given in the archive.
+	pub started: String,
+	pub request: Request,
+	/// Recorded response, `None` if the request failed.
+	pub response: Option<Response>,
+}
+
+/// Reads the entries of a HAR file.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<HarEntry>> {
+	let path = path.as_ref();
+	let text = std::fs::read_to_string(path)
+		.map_err(|err| Error::Usage(format!("cannot read `{}`: {err}", path.display())))?;
+	parse(&path.display().to_string(), &text)
+}
+
+/// Parses a HAR document. `source` names it in error messages.
+pub fn parse(source: &str, text: &str) -> Result<Vec<HarEntry>> {
+	let document: Value = serde_json::from_str(text).map_err(|err| {
+		Error::Parse(ParseError::new(err.line(), err.column(), err.to_string()).in_source(source))
+	})?;
+	let invalid = |message: String| Error::Usage(format!("{source}: invalid HAR: {message}"));
+	let entries = document
+		.pointer("/log/entries")
+		.and_then(Value::as_array)
+		.ok_or_else(|| invalid("missing `log.entries`".into()))?;
+	entries
+		.iter()
+		.enumerate()
+		.map(|(index, entry)| {
+			parse_entry(entry).map_err(|message| invalid(format!("entry {}: {message}", index + 1)))
+		})
+		.collect()
+}
+
+fn parse_entry(entry: &Value) -> std::result::Result<HarEntry, String> {
+	let started = string(entry, "startedDateTime")?.to_string();
+	let har_request = field(entry, "request")?;
+	let url = string(har_request, "url")?;
+	let url = Url::parse(url).map_err(|err| err.to_string())?;
+	let mut request = Request::new(string(har_request, "method")?, url);
+	request.headers = headers(har_request)?;
+	if let Some(post) = har_request.get("postData") {
+		request.body = post_data(post)?;
+		let mime = post.get("mimeType").and_then(Value::as_str).unwrap_or("");
+		if !mime.is_empty() && !request.headers.contains("Content-Type") {
+			request.headers.set("Content-Type", mime);
+		}
+	}
+
+	let har_response = field(entry, "response")?;
+	let status = field(har_response, "status")?
+		.as_u64()
+		.ok_or("`status` is not a number")?;
+	// browsers record failed and blocked requests with status 0
+	let response = match u16::try_from(status) {
+		Ok(0) => None,
+		Ok(status) => {
+			let content = field(har_response, "content")?;
+			Some(Response {
+				version: version(har_response.get("httpVersion").and_then(Value::as_str)),
+				status,
+				reason: har_response
+					.get("statusText")
+					.and_then(Value::as_str)
+					.unwrap_or("")
+					.to_string(),
+				headers: headers(har_response)?,
+				body: text_field(content)?,
+				timing: entry.get("timings").map(timings).unwrap_or_default(),
+				..Default::default()
+			})
+		}
+		Err(_) => return Err(format!("invalid status {status}")),
+	};
+	Ok(HarEntry {
+		started,
+		request,
+		response,
+	})
+}
+
+/// Status line version for the `httpVersion` of a response. Browsers write
+/// ALPN identifiers like `h2` for HTTP/2.
+fn version(version: Option<&str>) -> String {
+	match version {
+		Some(version) if version.starts_with("HTTP/") => version.to_string(),
+		Some("h2" | "http/2.0") => "HTTP/2".into(),
+		Some("h3" | "http/3.0") => "HTTP/3".into(),
+		_ => "HTTP/1.1".into(),
+	}
+}
+
+fn field<'a>(value: &'a Value, name: &str) -> std::result::Result<&'a Value, String> {
+	value.get(name).ok_or_else(|| format!("missing `{name}`"))
+}
+
+fn string<'a>(value: &'a Value, name: &str) -> std::result::Result<&'a str, String> {
+	field(value, name)?
+		.as_str()
+		.ok_or_else(|| format!("`{name}` is not a string"))
+}
+
+/// Name and value pairs of `headers`, which may be absent.
+fn headers(message: &Value) -> std::result::Result<Headers, String> {
+	let mut headers = Headers::new();
+	let list = message.get("headers").and_then(Value::as_array);
+	for header in list.into_iter().flatten() {
+		let name = string(header, "name")?;
+		if name.starts_with(':') || SKIPPED_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+			continue;
+		}
+		headers.append(name, string(header, "value")?);
+	}
+	Ok(headers)
+}
+
+/// Body of a request, given as text or as form parameters.
+fn post_data(post: &Value) -> std::result::Result<Vec<u8>, String> {
+	if post.get("text").is_some() {
+		return text_field(post);
+	}
+	let params = post.get("params").and_then(Value::as_array);
+	let pairs = params
+		.into_iter()
+		.flatten()
+		.map(|param| {
+			let value = param.get("value").and_then(Value::as_str).unwrap_or("");
+			Ok((string(param, "name")?.to_string(), value.to_string()))
+		})
+		.collect::<std::result::Result<Vec<_>, String>>()?;
+	Ok(form_encode(&pairs).into_bytes())
+}
+
+/// The `text` of a content or post data object, decoding base64 if its
+/// `encoding` says so. `_encoding` is what webcat writes for binary
+/// request bodies.
+fn text_field(value: &Value) -> std::result::Result<Vec<u8>, String> {
+	let text = value.get("text").and_then(Value::as_str).unwrap_or("");
+	let encoding = value
+		.get("encoding")
+		.or_else(|| value.get("_encoding"))
+		.and_then(Value::as_str);
+	match encoding {
+		None => Ok(text.as_bytes().to_vec()),
+		Some("base64") => base64_decode(text).ok_or_else(|| "invalid base64 text".into()),
+		Some(other) => Err(format!("unsupported encoding `{other}`")),
+	}
+}
+
+/// Maps the HAR phases onto [`Timings`]. HAR includes the TLS handshake in
+/// `connect` and splits the time to first byte into `send` and `wait`.
+fn timings(value: &Value) -> Timings {
+	let phase = |name: &str| value.get(name).and_then(Value::as_f64).unwrap_or(-1.0);
+	let duration = |ms: f64| Duration::from_secs_f64(ms.max(0.0) / 1000.0);
+	let ssl = phase("ssl").max(0.0);
+	Timings {
+		dns: duration(phase("dns")),
+		connect: duration(phase("connect") - ssl),
+		tls: duration(ssl),
+		ttfb: duration(phase("send").max(0.0) + phase("wait").max(0.0)),
+		transfer: duration(phase("receive")),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const ARCHIVE: &str = r#"{"log": {"version": "1.2", "entries": [
+		{
+			"startedDateTime": "2024-03-01T10:00:00.000Z",
+			"request": {
+				"method": "POST",
+				"url": "https://api.example.com/login?next=%2Fhome",
+				"httpVersion": "h2",
+				"headers": [
+					{"name": ":authority", "value": "api.example.com"},
+					{"name": "accept", "value": "application/json"},
+					{"name": "content-length", "value": "20"}
+				],
+				"postData": {
+					"mimeType": "application/x-www-form-urlencoded",
+					"params": [{"name": "user", "value": "jo doe"}, {"name": "pw", "value": "a&b"}]
+				}
+			},
+			"response": {
+				"status": 200,
+				"statusText": "OK",
+				"httpVersion": "h2",
+				"headers": [{"name": "content-type", "value": "image/png"}],
+				"content": {"size": 3, "mimeType": "image/png", "text": "AAEC", "encoding": "base64"}
+			},
+			"timings": {"blocked": -1, "dns": 2, "connect": 10, "ssl": 6, "send": 1, "wait": 20, "receive": 3}
+		},
+		{
+			"startedDateTime": "2024-03-01T10:00:01.000Z",
+			"request": {"method": "GET", "url": "http://down.example.com/", "headers": []},
+			"response": {"status": 0, "statusText": "", "headers": [], "content": {}}
+		}
+	]}}"#;
+
+	#[test]
+	fn parses_entries() {
+		let entries = parse("api.har", ARCHIVE).unwrap();
+		assert_eq!(entries.len(), 2);
+
+		let login = &entries[0];
+		assert_eq!(login.started, "2024-03-01T10:00:00.000Z");
+		assert_eq!(login.request.method, "POST");
+		assert_eq!(
+			login.request.url.to_string(),
+			"https://api.example.com/login?next=%2Fhome"
+		);
+		assert_eq!(
+			login.request.headers.iter().collect::<Vec<_>>(),
+			[
+				("accept", "application/json"),
+				("Content-Type", "application/x-www-form-urlencoded")
+			]
+		);
+		assert_eq!(login.request.body, b"user=jo+doe&pw=a%26b");
+
+		let response = login.response.as_ref().unwrap();
+		assert_eq!(response.status_line(), "HTTP/2 200 OK");
+		assert_eq!(response.body, [0, 1, 2]);
+		assert_eq!(response.timing.connect, Duration::from_millis(4));
+		assert_eq!(response.timing.tls, Duration::from_millis(6));
+		assert_eq!(response.timing.ttfb, Duration::from_millis(21));
+
+		assert!(entries[1].response.is_none());
+	}
+
+	#[test]
+	fn reports_errors() {
+		let err = parse("bad.har", "{\n  \"log\": [").unwrap_err();
+		assert!(
+			matches!(err, Error::Parse(ParseError { line: 2, .. })),
+			"{err}"
+		);
+
+		let err = parse("bad.har", r#"{"log": {"entries": [{"request": {}}]}}"#).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"bad.har: invalid HAR: entry 1: missing `startedDateTime`"
+		);
+	}
+}
@@ -0,0 +1,282 @@
+//! Protocol independent request and response model.
+
+use crate::h2::H2Info;
+use crate::redirect::Hop;
+use crate::sse::Event;
+use crate::timing::Timings;
+use crate::tls::TlsInfo;
+use crate::upload::Upload;
+use crate::url::Url;
+
+/// Ordered list of header fields. Names are compared case-insensitively and
+/// keep the casing they were inserted with.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Headers {
+	entries: Vec<(String, String)>,
+}
+
+impl Headers {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns the first value for the header.
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.entries
+			.iter()
+			.find(|(key, _)| key.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str())
+	}
+
+	pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+		self.entries
+			.iter()
+			.filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str())
+	}
+
+	pub fn contains(&self, name: &str) -> bool {
+		self.get(name).is_some()
+	}
+
+	/// Adds a value, keeping any existing values for the same header.
+	pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+		self.entries.push((name.into(), value.into()));
+	}
+
+	/// Sets a header, replacing all existing values.
+	pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+		let name = name.into();
+		self.remove(&name);
+		self.entries.push((name, value.into()));
+	}
+
+	pub fn remove(&mut self, name: &str) {
+		self.entries
+			.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Headers {
+	fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+		let mut headers = Headers::new();
+		for (k, v) in iter {
+			headers.append(k, v);
+		}
+		headers
+	}
+}
+
+/// Parses a `Name: value` header line as given on the command line or in a
+/// request file.
+pub fn parse_header_line(line: &str) -> Option<(String, String)> {
+	let (name, value) = line.split_once(':')?;
+	let name = name.trim();
+	if name.is_empty() || name.contains(char::is_whitespace) {
+		return None;
+	}
+	Some((name.to_string(), value.trim().to_string()))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Request {
+	/// Upper-case method name, e.g. `GET`.
+	pub method: String,
+	pub url: Url,
+	pub headers: Headers,
+	pub body: Vec<u8>,
+	/// Body streamed from files while sending, in place of `body`.
+	pub upload: Option<Upload>,
+}
+
+impl Request {
+	pub fn new(method: &str, url: Url) -> Self {
+		Request {
+			method: method.to_ascii_uppercase(),
+			url,
+			headers: Headers::new(),
+			body: Vec::new(),
+			upload: None,
+		}
+	}
+
+	/// Length of the body as sent.
+	pub fn body_len(&self) -> u64 {
+		match &self.upload {
+			Some(upload) => upload.len(),
+			None => self.body.len() as u64,
+		}
+	}
+
+	/// Sets the body, streamed when it includes files.
+	pub fn set_upload(&mut self, upload: Upload) {
+		match upload.into_bytes() {
+			Ok(bytes) => {
+				self.body = bytes;
+				self.upload = None;
+			}
+			Err(upload) => {
+				self.body.clear();
+				self.upload = Some(upload);
+			}
+		}
+	}
+
+	/// Reads a streamed body into `body`, for uses that need all of it such
+	/// as exporting the request.
+	pub fn load_upload(&mut self) -> std::io::Result<()> {
+		if let Some(upload) = self.upload.take() {
+			self.body = upload.read_all()?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Response {
+	/// Protocol version as it appears in the status line, e.g. `HTTP/1.1`.
+	pub version: String,
+	pub status: u16,
+	pub reason: String,
+	pub headers: Headers,
+	pub body: Vec<u8>,
+	/// Trailer fields sent after the body.
+	pub trailers: Headers,
+	/// Negotiated TLS session, for https requests.
+	pub tls: Option<TlsInfo>,
+	/// Stream details, for HTTP/2 responses.
+	pub h2: Option<H2Info>,
+	pub timing: Timings,
+	/// Redirects followed to get this response, in order.
+	pub redirects: Vec<Hop>,
+	/// Events of a `text/event-stream` body read as it arrived, with the
+	/// time each one came in.
+	pub events: Vec<Event>,
+}
+
+impl Response {
+	pub fn status_line(&self) -> String {
+		if self.reason.is_empty() {
+			format!("{} {}", self.version, self.status)
+		} else {
+			format!("{} {} {}", self.version, self.status, self.reason)
+		}
+	}
+
+	/// Plain text response with the standard reason phrase.
+	pub fn text(status: u16, message: &str) -> Response {
+		Response {
+			version: "HTTP/1.1".into(),
+			status,
+			reason: reason_phrase(status).into(),
+			headers: [("Content-Type", "text/plain; charset=utf-8")]
+				.into_iter()
+				.collect(),
+			body: format!("{message}\n").into_bytes(),
+			..Default::default()
+		}
+	}
+
+	pub fn body_text(&self) -> String {
+		String::from_utf8_lossy(&self.body).into_owned()
+	}
+}
+
+/// Part of a response passed on as it arrives.
+pub enum Part<'a> {
+	/// Status line and headers, before the body.
+	Head(&'a Response),
+	/// Bytes of the body, or a message of a body made of messages.
+	Data(&'a [u8]),
+}
+
+/// Receives the parts of a response as they arrive.
+pub type PartHandler<'a> = dyn FnMut(Part<'_>) -> crate::Result<()> + 'a;
+
+/// Standard reason phrase of a status code, empty for unknown codes.
+pub fn reason_phrase(status: u16) -> &'static str {
+	match status {
+		100 => "Continue",
+		101 => "Switching Protocols",
+		200 => "OK",
+		201 => "Created",
+		202 => "Accepted",
+		204 => "No Content",
+		206 => "Partial Content",
+		301 => "Moved Permanently",
+		302 => "Found",
+		303 => "See Other",
+		304 => "Not Modified",
+		307 => "Temporary Redirect",
+		308 => "Permanent Redirect",
+		400 => "Bad Request",
+		401 => "Unauthorized",
+		403 => "Forbidden",
+		404 => "Not Found",
+		405 => "Method Not Allowed",
+		406 => "Not Acceptable",
+		408 => "Request Timeout",
+		409 => "Conflict",
+		410 => "Gone",
+		412 => "Precondition Failed",
+		413 => "Content Too Large",
+		415 => "Unsupported Media Type",
+		422 => "Unprocessable Content",
+		429 => "Too Many Requests",
+		500 => "Internal Server Error",
+		501 => "Not Implemented",
+		502 => "Bad Gateway",
+		503 => "Service Unavailable",
+		504 => "Gateway Timeout",
+		_ => "",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn headers_are_case_insensitive() {
+		let mut headers = Headers::new();
+		headers.append("Set-Cookie", "a=1");
+		headers.append("set-cookie", "b=2");
+		headers.set("Content-Type", "text/plain");
+		assert_eq!(headers.get("SET-COOKIE"), Some("a=1"));
+		assert_eq!(
+			headers.get_all("set-cookie").collect::<Vec<_>>(),
+			["a=1", "b=2"]
+		);
+		headers.set("content-type", "application/json");
+		assert_eq!(headers.len(), 3);
+		assert_eq!(headers.get("Content-Type"), Some("application/json"));
+		headers.remove("Set-Cookie");
+		assert_eq!(headers.len(), 1);
+	}
+
+	#[test]
+	fn parses_header_lines() {
+		assert_eq!(
+			parse_header_line("Accept:  text/html "),
+			Some(("Accept".into(), "text/html".into()))
+		);
+		assert_eq!(
+			parse_header_line("X-Empty:"),
+			Some(("X-Empty".into(), "".into()))
+		);
+		assert_eq!(parse_header_line("no colon"), None);
+		assert_eq!(parse_header_line("Bad Name: x"), None);
+	}
+}
@@ -0,0 +1,419 @@
+//! Request files in the style of the VS Code REST Client and JetBrains HTTP
+//! client (`.http` files).
+//!
+//! ```text
+//! @host = localhost:8080
+//!
+//! ### List items
+//! GET http://{{host}}/items
+//! Accept: application/json
+//!
+//! ###
+//! # @name create
+//! POST http://{{host}}/items
+//! Content-Type: application/json
+//!
+//! {"name": "example"}
+//! ```
+//!
+//! Requests are separated by lines starting with `###`. The text after the
+//! separator names the request, which can also be given with a `# @name`
+//! comment. Lines starting with `#` or `//` before the body are comments.
+//! A `< path` line of a body is replaced by a file relative to the request
+//! file; `<@ path` also interpolates variables in it. Large files are
+//! streamed while sending, and the lines of a `multipart/*` body are sent
+//! with CRLF line breaks:
+//!
+//! ```text
+//! POST http://{{host}}/upload
+//! Content-Type: multipart/form-data; boundary=boundary
+//!
+//! --boundary
+//! Content-Disposition: form-data; name="file"; filename="photo.png"
+//! Content-Type: image/png
+//!
+//! < ./photo.png
+//! --boundary--
+//! ```
+//!
+//! Lines starting with `??` after the request line are response assertions,
+//! see [`crate::assert`], and lines starting with `>>` capture values from
+//! the response into variables, see [`crate::capture`].
+//!
+//! A `WEBSOCKET` request opens a WebSocket and runs the script of its `===`
+//! sections, each sending the message under it. `=== wait-for-server`
+//! first waits for a message from the server, checked by the `??` lines of
+//! the section:
+//!
+//! ```text
+//! WEBSOCKET ws://{{host}}/chat
+//!
+//! ===
+//! {"join": "lobby"}
+//! === wait-for-server
+//! ?? json $.joined == "lobby"
+//! ```
+//!
+//! The response of a WebSocket script is the handshake response, with the
+//! last message received as its body.
+//!
+//! A `GRAPHQL` request posts the query of its body as JSON, with the JSON
+//! object after an empty line as its variables. `# @operation` names the
+//! operation to run when the query has several:
+//!
+//! ```text
+//! # @operation Item
+//! GRAPHQL http://{{host}}/graphql
+//!
+//! query Item($id: ID!) {
+//!   item(id: $id) { name price }
+//! }
+//!
+//! {"id": "{{id}}"}
+//! ```
+
+pub(crate) mod parse;
+
+use std::path::{Path, PathBuf};
+
+use crate::assert::Assertion;
+use crate::capture::Capture;
+use crate::error::{Error, ParseError, Result};
+use crate::graphql::Query;
+use crate::http::Request;
+use crate::template::{Template, Variables};
+use crate::upload::Upload;
+use crate::url::Url;
+
+pub use parse::parse;
+
+/// Files from this size on are streamed while sending instead of read into
+/// memory.
+const STREAMED_SIZE: u64 = 1 << 20;
+
+/// Parsed request file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestFile {
+	/// Name used in error messages, usually the file path.
+	pub source: String,
+	/// Directory used to resolve relative file references.
+	pub base_dir: Option<PathBuf>,
+	/// File level `@name = value` declarations, in order.
+	pub variables: Vec<VariableDef>,
+	pub requests: Vec<RequestDef>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VariableDef {
+	pub name: String,
+	pub value: Template,
+	pub line: usize,
+}
+
+/// Request as written in the file, before interpolation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestDef {
+	pub name: Option<String>,
+	/// Line of the request line.
+	pub line: usize,
+	pub method: String,
+	pub url: Template,
+	pub headers: Vec<HeaderDef>,
+	pub body: Option<BodyDef>,
+	pub assertions: Vec<Assertion>,
+	/// Variables taken from the response for the following requests.
+	pub captures: Vec<Capture>,
+	/// Script of a `WEBSOCKET` request, empty for other requests.
+	pub messages: Vec<MessageDef>,
+	/// Variables and operation of a `GRAPHQL` request, whose body is the
+	/// query.
+	pub graphql: Option<GraphqlDef>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GraphqlDef {
+	/// Line the query starts on, 0 when it is read from a file.
+	pub line: usize,
+	/// JSON object of the variables and the line it starts on.
+	pub variables: Option<(usize, BodyDef)>,
+	/// Operation to run, given with `# @operation`.
+	pub operation: Option<String>,
+}
+
+/// Section of a `WEBSOCKET` request, starting with a `===` line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageDef {
+	/// Line of the `===` separator.
+	pub line: usize,
+	/// Waits for a message from the server first (`=== wait-for-server`).
+	pub wait: bool,
+	/// Checks of the message waited for.
+	pub assertions: Vec<Assertion>,
+	/// Message sent, text when it is valid UTF-8 and binary otherwise.
+	pub body: Option<BodyDef>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderDef {
+	pub name: String,
+	pub value: Template,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BodyDef {
+	Text(Template),
+	File {
+		path: String,
+		interpolate: bool,
+		line: usize,
+	},
+	/// Text mixed with `< path` lines, such as the parts of a multipart
+	/// form, joined with line breaks.
+	Parts(Vec<BodyDef>),
+}
+
+impl RequestDef {
+	/// Returns true for a `WEBSOCKET` request.
+	pub fn is_websocket(&self) -> bool {
+		self.method == "WEBSOCKET"
+	}
+
+	/// Returns true for a `GRAPHQL` request.
+	pub fn is_graphql(&self) -> bool {
+		self.graphql.is_some()
+	}
+
+	/// Name shown in output: the request name or its request line.
+	pub fn label(&self) -> String {
+		match &self.name {
+			Some(name) => name.clone(),
+			None => format!("{} {}", self.method, self.url),
+		}
+	}
+}
+
+impl RequestFile {
+	pub fn load(path: impl AsRef<Path>) -> Result<RequestFile> {
+		let path = path.as_ref();
+		let text = std::fs::read_to_string(path)
+			.map_err(|err| Error::Usage(format!("cannot read `{}`: {err}", path.display())))?;
+		let mut file = parse(&path.display().to_string(), &text)?;
+		file.base_dir = path.parent().map(|dir| dir.to_path_buf());
+		Ok(file)
+	}
+
+	/// Finds a request by name.
+	pub fn find(&self, name: &str) -> Option<&RequestDef> {
+		self.requests
+			.iter()
+			.find(|def| def.name.as_deref() == Some(name))
+	}
+
+	/// Evaluates the file variables in declaration order on top of `vars`.
+	/// Variables already present in `vars` take precedence, so values given
+	/// on the command line override the file.
+	pub fn resolve_variables(&self, vars: &mut Variables) -> Result<()> {
+		self.resolve_variables_except(vars, &[])
+	}
+
+	/// Like [`RequestFile::resolve_variables`], but leaves out the variables
+	/// using one of the `pending` names, directly or through another
+	/// variable. Calling it again once they are known evaluates the rest.
+	pub fn resolve_variables_except(&self, vars: &mut Variables, pending: &[&str]) -> Result<()> {
+		let given: Vec<String> = vars.iter().map(|(name, _)| name.to_string()).collect();
+		let mut deferred: Vec<&str> = Vec::new();
+		for def in &self.variables {
+			if given.contains(&def.name) {
+				continue;
+			}
+			if def
+				.value
+				.variables()
+				.any(|name| pending.contains(&name) || deferred.contains(&name))
+			{
+				deferred.push(&def.name);
+				continue;
+			}
+			let value = def.value.render(vars).map_err(|err| self.located(err))?;
+			vars.set(def.name.clone(), value);
+		}
+		Ok(())
+	}
+
+	/// Names of the variables captured by the requests of the file.
+	pub fn captured_names(&self) -> impl Iterator<Item = &str> {
+		self.requests
+			.iter()
+			.flat_map(|def| def.captures.iter().map(|capture| capture.name.as_str()))
+	}
+
+	/// Interpolates a request definition into a request ready to send.
+	pub fn build(&self, def: &RequestDef, vars: &Variables) -> Result<Request> {
+		let render = |template: &Template| template.render(vars).map_err(|err| self.located(err));
+
+		let mut headers = Vec::new();
+		for header in &def.headers {
+			headers.push((header.name.clone(), render(&header.value)?));
+		}
+
+		let target = render(&def.url)?;
+		let url = if target.starts_with('/') {
+			let host = headers
+				.iter()
+				.find(|(name, _)| name.eq_ignore_ascii_case("Host"))
+				.map(|(_, value)| value.as_str())
+				.ok_or_else(|| self.error(def.line, "relative URL requires a `Host` header"))?;
+			Url::parse(&format!("{host}{target}"))
+		} else {
+			Url::parse(&target)
+		};
+		let url = url.map_err(|err| self.error(def.line, err.to_string()))?;
+
+		// the handshake of a WebSocket is a GET request
+		let method = if def.is_websocket() {
+			"GET"
+		} else if def.is_graphql() {
+			"POST"
+		} else {
+			&def.method
+		};
+		let mut request = Request::new(method, url);
+		for (name, value) in headers {
+			request.headers.append(name, value);
+		}
+		if let Some(graphql) = &def.graphql {
+			self.graphql_query(def, graphql, vars)?.apply(&mut request);
+		} else if let Some(body) = &def.body {
+			// the lines of multipart bodies end with CRLF
+			let multipart = request.headers.get("Content-Type").is_some_and(|value| {
+				value
+					.trim_start()
+					.to_ascii_lowercase()
+					.starts_with("multipart/")
+			});
+			let line_break = if multipart { "\r\n" } else { "\n" };
+			request.set_upload(self.upload(body, vars, line_break)?);
+		}
+		Ok(request)
+	}
+
+	/// Interpolates the query and variables of a `GRAPHQL` request.
+	fn graphql_query(
+		&self,
+		def: &RequestDef,
+		graphql: &GraphqlDef,
+		vars: &Variables,
+	) -> Result<Query> {
+		let query = match &def.body {
+			Some(body) => String::from_utf8_lossy(&self.render_body(body, vars)?).into_owned(),
+			None => return Err(self.error(def.line, "missing GraphQL query")),
+		};
+		let variables = match &graphql.variables {
+			Some((line, body)) => {
+				let text = self.render_body(body, vars)?;
+				let value = serde_json::from_slice::<serde_json::Value>(&text).map_err(|err| {
+					self.error(
+						line + err.line().max(1) - 1,
+						format!("invalid GraphQL variables: {err}"),
+					)
+				})?;
+				if !value.is_object() {
+					return Err(self.error(*line, "GraphQL variables must be a JSON object"));
+				}
+				Some(value)
+			}
+			None => None,
+		};
+		Ok(Query {
+			query,
+			variables,
+			operation_name: graphql.operation.clone(),
+		})
+	}
+
+	/// Locates a problem of the query of a `GRAPHQL` request, given at a
+	/// position of the query, in the request file or the query file.
+	pub fn locate_query(&self, def: &RequestDef, err: ParseError) -> Error {
+		match (&def.body, &def.graphql) {
+			(Some(BodyDef::File { path, .. }), _) => {
+				err.in_source(&self.resolve_path(path).display().to_string())
+			}
+			(_, Some(graphql)) => ParseError {
+				line: graphql.line + err.line - 1,
+				..err
+			}
+			.in_source(&self.source),
+			_ => err.in_source(&self.source),
+		}
+		.into()
+	}
+
+	/// Interpolates a body, reading referenced files relative to the file.
+	pub fn render_body(&self, body: &BodyDef, vars: &Variables) -> Result<Vec<u8>> {
+		Ok(self.upload(body, vars, "\n")?.read_all()?)
+	}
+
+	/// Interpolates a body whose large files are streamed when it is sent.
+	fn upload(&self, body: &BodyDef, vars: &Variables, line_break: &str) -> Result<Upload> {
+		let mut upload = Upload::new();
+		match body {
+			BodyDef::Text(template) => {
+				let text = template.render(vars).map_err(|err| self.located(err))?;
+				upload.push_bytes(text.replace('\n', line_break).as_bytes());
+			}
+			BodyDef::File {
+				path,
+				interpolate,
+				line,
+			} => {
+				let full = self.resolve_path(path);
+				let unreadable = |err: std::io::Error| {
+					self.error(*line, format!("cannot read `{}`: {err}", full.display()))
+				};
+				let len = std::fs::metadata(&full).map_err(unreadable)?.len();
+				if !*interpolate && len >= STREAMED_SIZE {
+					upload.push_file(&full).map_err(unreadable)?;
+					return Ok(upload);
+				}
+				let data = std::fs::read(&full).map_err(unreadable)?;
+				if !*interpolate {
+					upload.push_bytes(&data);
+					return Ok(upload);
+				}
+				let text = String::from_utf8_lossy(&data);
+				let source = full.display().to_string();
+				let rendered = Template::parse(&text, 1, 1)
+					.and_then(|template| template.render(vars))
+					.map_err(|err| err.in_source(&source))?;
+				upload.push_bytes(rendered.as_bytes());
+			}
+			BodyDef::Parts(parts) => {
+				for (index, part) in parts.iter().enumerate() {
+					if index > 0 {
+						upload.push_bytes(line_break.as_bytes());
+					}
+					upload.append(self.upload(part, vars, line_break)?);
+				}
+			}
+		}
+		Ok(upload)
+	}
+
+	/// Resolves a path relative to the file directory.
+	pub fn resolve_path(&self, path: &str) -> PathBuf {
+		match &self.base_dir {
+			Some(dir) if Path::new(path).is_relative() => dir.join(path),
+			_ => PathBuf::from(path),
+		}
+	}
+
+	fn located(&self, err: ParseError) -> Error {
+		err.in_source(&self.source).into()
+	}
+
+	fn error(&self, line: usize, message: impl Into<String>) -> Error {
+		ParseError::new(line, 1, message)
+			.in_source(&self.source)
+			.into()
+	}
+}
@@ -0,0 +1,722 @@
+use super::{BodyDef, GraphqlDef, HeaderDef, MessageDef, RequestDef, RequestFile, VariableDef};
+use crate::assert::Assertion;
+use crate::capture::Capture;
+use crate::error::ParseError;
+use crate::template::Template;
+
+/// Numbered lines of a file.
+type Lines<'a> = [(usize, &'a str)];
+
+/// Block of lines between `###` separators.
+pub(crate) struct Block<'a> {
+	pub title: &'a str,
+	pub lines: Vec<(usize, &'a str)>,
+}
+
+/// Parses the text of a request file. `source` names the file in errors.
+pub fn parse(source: &str, text: &str) -> Result<RequestFile, ParseError> {
+	let mut file = RequestFile {
+		source: source.to_string(),
+		base_dir: None,
+		variables: Vec::new(),
+		requests: Vec::new(),
+	};
+	for block in split_blocks(text) {
+		parse_block(&mut file, block).map_err(|err| err.in_source(source))?;
+	}
+	Ok(file)
+}
+
+pub(crate) fn split_blocks(text: &str) -> Vec<Block<'_>> {
+	let mut blocks = vec![Block {
+		title: "",
+		lines: Vec::new(),
+	}];
+	for (index, line) in text.lines().enumerate() {
+		if let Some(title) = line.trim_start().strip_prefix("###") {
+			blocks.push(Block {
+				title: title.trim_start_matches('#').trim(),
+				lines: Vec::new(),
+			});
+		} else {
+			blocks.last_mut().unwrap().lines.push((index + 1, line));
+		}
+	}
+	blocks
+}
+
+/// Returns the text of a `#` or `//` comment line.
+pub(crate) fn comment(line: &str) -> Option<&str> {
+	let line = line.trim_start();
+	line.strip_prefix('#').or_else(|| line.strip_prefix("//"))
+}
+
+/// Column (1-based) of `part`, which must be a subslice of `line`.
+pub(crate) fn column_of(line: &str, part: &str) -> usize {
+	let offset = part.as_ptr() as usize - line.as_ptr() as usize;
+	line[..offset].chars().count() + 1
+}
+
+pub(crate) fn parse_block(file: &mut RequestFile, block: Block) -> Result<(), ParseError> {
+	let lines = block.lines;
+	let mut name = (!block.title.is_empty()).then(|| block.title.to_string());
+	let mut operation = None;
+	let mut index = 0;
+
+	// preamble: comments, variables and the `@name` directive
+	while let Some(&(line_no, line)) = lines.get(index) {
+		let trimmed = line.trim();
+		if trimmed.is_empty() {
+		} else if let Some(text) = comment(line) {
+			for (directive, target) in [("@name", &mut name), ("@operation", &mut operation)] {
+				if let Some(value) = text.trim().strip_prefix(directive) {
+					if value.starts_with(char::is_whitespace) || value.starts_with('=') {
+						*target = Some(
+							value
+								.trim_start_matches([' ', '\t', '='])
+								.trim()
+								.to_string(),
+						);
+					}
+				}
+			}
+		} else if let Some(decl) = trimmed.strip_prefix('@') {
+			file.variables.push(parse_variable(line_no, line, decl)?);
+		} else {
+			break;
+		}
+		index += 1;
+	}
+	let Some(&(line_no, line)) = lines.get(index) else {
+		return Ok(());
+	};
+
+	// `??` assertion and `>>` capture lines may appear anywhere after the
+	// request line, except that the assertions of a WebSocket script
+	// belong to its sections
+	let (method, url_text) = split_request_line(line);
+	let websocket = method == "WEBSOCKET";
+	let mut script = false;
+	let mut assertions = Vec::new();
+	let mut captures = Vec::new();
+	let mut remaining = Vec::new();
+	for &(assert_no, text) in &lines[index + 1..] {
+		let trimmed = text.trim_start();
+		script |= websocket && trimmed.starts_with("===");
+		if let Some(expr) = trimmed.strip_prefix("??").filter(|_| !script) {
+			let column = column_of(text, expr.trim_start());
+			assertions.push(Assertion::parse(expr, assert_no, column)?);
+		} else if let Some(expr) = trimmed.strip_prefix(">>") {
+			let column = column_of(text, expr.trim_start());
+			captures.push(Capture::parse(expr, assert_no, column)?);
+		} else {
+			remaining.push((assert_no, text));
+		}
+	}
+	let lines = remaining;
+	let mut index = 0;
+
+	let mut url = url_text.to_string();
+	let url_column = column_of(line, url_text);
+	while let Some(&(_, next)) = lines.get(index) {
+		let next = next.trim();
+		if !next.starts_with(['?', '&']) {
+			break;
+		}
+		url.push_str(next);
+		index += 1;
+	}
+	let url = Template::parse(&url, line_no, url_column)?;
+
+	let headers = parse_headers(&lines, &mut index)?;
+	let rest = &lines[index.min(lines.len())..];
+	let (body, messages, graphql) = match method {
+		"WEBSOCKET" => (None, parse_messages(rest)?, None),
+		"GRAPHQL" => {
+			let (body, graphql) = parse_graphql(rest, operation)?;
+			(body, Vec::new(), Some(graphql))
+		}
+		_ => (parse_body(rest)?, Vec::new(), None),
+	};
+
+	file.requests.push(RequestDef {
+		name,
+		line: line_no,
+		method: method.to_string(),
+		url,
+		headers,
+		body,
+		assertions,
+		captures,
+		messages,
+		graphql,
+	});
+	Ok(())
+}
+
+/// Parses the body of a `GRAPHQL` request, the query and then its
+/// variables.
+fn parse_graphql(
+	lines: &[(usize, &str)],
+	operation: Option<String>,
+) -> Result<(Option<BodyDef>, GraphqlDef), ParseError> {
+	let (query, variables) = split_graphql(lines);
+	let body = parse_body(query)?;
+	let line = match (&body, query.first()) {
+		(Some(BodyDef::Text(_)), Some((line_no, _))) => *line_no,
+		_ => 0,
+	};
+	let variables = match variables.first() {
+		Some((line_no, _)) => parse_body(variables)?.map(|body| (*line_no, body)),
+		None => None,
+	};
+	let graphql = GraphqlDef {
+		line,
+		variables,
+		operation,
+	};
+	Ok((body, graphql))
+}
+
+/// Splits the body of a `GRAPHQL` request into the query and the variables:
+/// a JSON object after an empty line following the complete query.
+fn split_graphql<'a>(lines: &'a Lines<'a>) -> (&'a Lines<'a>, &'a Lines<'a>) {
+	let mut depth = 0i32;
+	let mut complete = false;
+	for (index, (_, line)) in lines.iter().enumerate() {
+		let trimmed = line.trim();
+		if trimmed.is_empty() {
+			if complete && depth == 0 {
+				let rest = &lines[index..];
+				let next = rest.iter().position(|(_, line)| !line.trim().is_empty());
+				if let Some(next) = next.filter(|next| rest[*next].1.trim_start().starts_with('{'))
+				{
+					return (&lines[..index], &rest[next..]);
+				}
+			}
+			continue;
+		}
+		if file_reference(line).is_some() {
+			complete = true;
+			continue;
+		}
+		let mut in_string = false;
+		let mut previous = ' ';
+		for c in line.chars() {
+			match c {
+				'"' if previous != '\\' => in_string = !in_string,
+				'#' if !in_string => break,
+				'{' if !in_string => depth += 1,
+				'}' if !in_string => {
+					depth -= 1;
+					complete |= depth == 0;
+				}
+				_ => {}
+			}
+			previous = c;
+		}
+	}
+	(lines, &[])
+}
+
+/// Parses the `===` sections of a `WEBSOCKET` request.
+fn parse_messages(lines: &[(usize, &str)]) -> Result<Vec<MessageDef>, ParseError> {
+	let mut messages: Vec<(MessageDef, Vec<(usize, &str)>)> = Vec::new();
+	for &(line_no, line) in lines {
+		let trimmed = line.trim_start();
+		if let Some(rest) = trimmed.strip_prefix("===") {
+			let wait = match rest.trim() {
+				"" => false,
+				"wait-for-server" => true,
+				_ => {
+					return Err(ParseError::new(
+						line_no,
+						column_of(line, rest.trim()),
+						"expected `===` or `=== wait-for-server`",
+					))
+				}
+			};
+			let message = MessageDef {
+				line: line_no,
+				wait,
+				assertions: Vec::new(),
+				body: None,
+			};
+			messages.push((message, Vec::new()));
+			continue;
+		}
+		let Some((message, body)) = messages.last_mut() else {
+			if trimmed.is_empty() || comment(line).is_some() {
+				continue;
+			}
+			return Err(ParseError::new(
+				line_no,
+				column_of(line, trimmed),
+				"expected `===` before a WebSocket message",
+			));
+		};
+		match trimmed.strip_prefix("??") {
+			Some(expr) if message.wait => {
+				let column = column_of(line, expr.trim_start());
+				message
+					.assertions
+					.push(Assertion::parse(expr, line_no, column)?);
+			}
+			Some(_) => {
+				return Err(ParseError::new(
+					line_no,
+					column_of(line, trimmed),
+					"assertions check a message waited for with `=== wait-for-server`",
+				))
+			}
+			None if body.is_empty() && trimmed.is_empty() => {}
+			None => body.push((line_no, line)),
+		}
+	}
+	messages
+		.into_iter()
+		.map(|(mut message, body)| {
+			message.body = parse_body(&body)?;
+			Ok(message)
+		})
+		.collect()
+}
+
+/// Parses header lines up to the first empty line, skipping comments.
+/// `index` is left after the empty line.
+pub(crate) fn parse_headers(
+	lines: &[(usize, &str)],
+	index: &mut usize,
+) -> Result<Vec<HeaderDef>, ParseError> {
+	let mut headers = Vec::new();
+	while let Some(&(header_no, header)) = lines.get(*index) {
+		*index += 1;
+		if header.trim().is_empty() {
+			break;
+		}
+		if comment(header).is_some() {
+			continue;
+		}
+		let (name, value) = header
+			.split_once(':')
+			.filter(|(name, _)| {
+				let name = name.trim();
+				!name.is_empty() && !name.contains(char::is_whitespace)
+			})
+			.ok_or_else(|| {
+				ParseError::new(
+					header_no,
+					column_of(header, header.trim_start()),
+					"expected `Name: value` header",
+				)
+			})?;
+		let value_text = value.trim();
+		let column = if value_text.is_empty() {
+			1
+		} else {
+			column_of(header, value_text)
+		};
+		headers.push(HeaderDef {
+			name: name.trim().to_string(),
+			value: Template::parse(value_text, header_no, column)?,
+		});
+	}
+	Ok(headers)
+}
+
+/// Parses body lines without trailing empty lines, either as text or as a
+/// single file reference.
+pub(crate) fn parse_body(lines: &[(usize, &str)]) -> Result<Option<BodyDef>, ParseError> {
+	let mut body_lines = lines;
+	while let Some(((_, last), rest)) = body_lines.split_last() {
+		if !last.trim().is_empty() {
+			break;
+		}
+		body_lines = rest;
+	}
+	Ok(match body_lines {
+		[] => None,
+		[(body_no, single)] if file_reference(single).is_some() => {
+			let (interpolate, path) = file_reference(single).unwrap();
+			Some(BodyDef::File {
+				path: path.to_string(),
+				interpolate,
+				line: *body_no,
+			})
+		}
+		_ if body_lines
+			.iter()
+			.any(|(_, line)| file_reference(line).is_some()) =>
+		{
+			// text mixed with files, such as the parts of a multipart form
+			let mut parts = Vec::new();
+			let mut text: Vec<&str> = Vec::new();
+			let mut text_no = 0;
+			for (line_no, line) in body_lines {
+				match file_reference(line) {
+					Some((interpolate, path)) => {
+						if !text.is_empty() {
+							parts.push(BodyDef::Text(Template::parse(
+								&text.join("\n"),
+								text_no,
+								1,
+							)?));
+							text.clear();
+						}
+						parts.push(BodyDef::File {
+							path: path.to_string(),
+							interpolate,
+							line: *line_no,
+						});
+					}
+					None => {
+						if text.is_empty() {
+							text_no = *line_no;
+						}
+						text.push(line);
+					}
+				}
+			}
+			if !text.is_empty() {
+				parts.push(BodyDef::Text(Template::parse(
+					&text.join("\n"),
+					text_no,
+					1,
+				)?));
+			}
+			Some(BodyDef::Parts(parts))
+		}
+		[(first_no, _), ..] => {
+			let text: Vec<&str> = body_lines.iter().map(|(_, line)| *line).collect();
+			Some(BodyDef::Text(Template::parse(
+				&text.join("\n"),
+				*first_no,
+				1,
+			)?))
+		}
+	})
+}
+
+fn parse_variable(line_no: usize, line: &str, decl: &str) -> Result<VariableDef, ParseError> {
+	let (name, value) = decl.split_once('=').ok_or_else(|| {
+		ParseError::new(line_no, column_of(line, decl), "expected `@name = value`")
+	})?;
+	let name = name.trim();
+	if name.is_empty()
+		|| !name
+			.chars()
+			.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+	{
+		return Err(ParseError::new(
+			line_no,
+			column_of(line, decl),
+			format!("invalid variable name `{name}`"),
+		));
+	}
+	let value = value.trim();
+	let column = if value.is_empty() {
+		1
+	} else {
+		column_of(line, value)
+	};
+	Ok(VariableDef {
+		name: name.to_string(),
+		value: Template::parse(value, line_no, column)?,
+		line: line_no,
+	})
+}
+
+/// Parses a `< path` or `<@ path` body line.
+pub(crate) fn file_reference(line: &str) -> Option<(bool, &str)> {
+	let (interpolate, rest) = match line.strip_prefix("<@") {
+		Some(rest) => (true, rest),
+		None => (false, line.strip_prefix('<')?),
+	};
+	let path = rest.trim();
+	(rest.starts_with(char::is_whitespace) && !path.is_empty()).then_some((interpolate, path))
+}
+
+/// Splits `[METHOD] URL [HTTP/version]` into method and URL.
+fn split_request_line(line: &str) -> (&str, &str) {
+	let mut text = line.trim();
+	if let Some((rest, version)) = text.rsplit_once(char::is_whitespace) {
+		if version.starts_with("HTTP/") {
+			text = rest.trim_end();
+		}
+	}
+	match text.split_once(char::is_whitespace) {
+		Some((method, url))
+			if !method.is_empty() && method.chars().all(|c| c.is_ascii_uppercase()) =>
+		{
+			(method, url.trim_start())
+		}
+		_ => ("GET", text),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::graphql::Query;
+	use crate::template::Variables;
+
+	const SAMPLE: &str = "\
+@host = localhost:8080
+@base = http://{{host}}/api
+
+### List items
+GET {{base}}/items
+	?page=2
+	&size=10
+Accept: application/json
+
+###
+# comment
+// @name create
+POST {{base}}/items HTTP/1.1
+Content-Type: application/json
+# X-Disabled: 1
+
+{
+  \"name\": \"{{name}}\"
+}
+
+
+### only variables
+@name = widget
+";
+
+	#[test]
+	fn parses_requests_and_variables() {
+		let file = parse("sample.http", SAMPLE).unwrap();
+		assert_eq!(file.variables.len(), 3);
+		assert_eq!(file.requests.len(), 2);
+
+		let list = &file.requests[0];
+		assert_eq!(list.name.as_deref(), Some("List items"));
+		assert_eq!(list.method, "GET");
+		assert_eq!(list.line, 5);
+		assert_eq!(list.url.to_string(), "{{base}}/items?page=2&size=10");
+		assert_eq!(list.headers.len(), 1);
+		assert_eq!(list.body, None);
+
+		let create = file.find("create").unwrap();
+		assert_eq!(create.method, "POST");
+		assert_eq!(create.headers.len(), 1);
+		assert_eq!(create.label(), "create");
+
+		let mut vars = Variables::new();
+		file.resolve_variables(&mut vars).unwrap();
+		let request = file.build(create, &vars).unwrap();
+		assert_eq!(request.url.to_string(), "http://localhost:8080/api/items");
+		assert_eq!(
+			String::from_utf8(request.body).unwrap(),
+			"{\n  \"name\": \"widget\"\n}"
+		);
+	}
+
+	#[test]
+	fn command_line_variables_take_precedence() {
+		let file = parse("sample.http", SAMPLE).unwrap();
+		let mut vars = Variables::new();
+		vars.set("host", "example.com");
+		file.resolve_variables(&mut vars).unwrap();
+		let request = file.build(&file.requests[0], &vars).unwrap();
+		assert_eq!(
+			request.url.to_string(),
+			"http://example.com/api/items?page=2&size=10"
+		);
+	}
+
+	#[test]
+	fn parses_minimal_and_relative_requests() {
+		let file = parse(
+			"x",
+			"example.com/health\n###\nGET /status\nHost: localhost:3000\n",
+		)
+		.unwrap();
+		assert_eq!(file.requests[0].method, "GET");
+		assert_eq!(file.requests[0].label(), "GET example.com/health");
+		let request = file.build(&file.requests[1], &Variables::new()).unwrap();
+		assert_eq!(request.url.to_string(), "http://localhost:3000/status");
+	}
+
+	#[test]
+	fn parses_file_bodies() {
+		let file = parse(
+			"x",
+			"POST http://h/\n\n<@ ./body.json\n###\nPOST http://h/\n\n< data.bin\n",
+		)
+		.unwrap();
+		assert_eq!(
+			file.requests[0].body,
+			Some(BodyDef::File {
+				path: "./body.json".into(),
+				interpolate: true,
+				line: 3
+			})
+		);
+		assert!(matches!(
+			&file.requests[1].body,
+			Some(BodyDef::File {
+				interpolate: false,
+				..
+			})
+		));
+	}
+
+	#[test]
+	fn parses_multipart_bodies() {
+		let path = std::env::temp_dir().join(format!("webcat-parts-{}.txt", std::process::id()));
+		std::fs::write(&path, "a\nb").unwrap();
+		let text = format!(
+			"POST http://h/\nContent-Type: multipart/form-data; boundary=b\n\n\
+			 --b\nContent-Disposition: form-data; name=\"f\"\n\n< {}\n--b--\n",
+			path.display()
+		);
+		let file = parse("x", &text).unwrap();
+		let Some(BodyDef::Parts(parts)) = &file.requests[0].body else {
+			panic!("expected parts");
+		};
+		assert!(matches!(&parts[1], BodyDef::File { line: 7, .. }));
+		let request = file.build(&file.requests[0], &Variables::new()).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		assert_eq!(
+			String::from_utf8(request.body).unwrap(),
+			"--b\r\nContent-Disposition: form-data; name=\"f\"\r\n\r\na\nb\r\n--b--"
+		);
+	}
+
+	#[test]
+	fn extracts_assertions() {
+		let text = "POST http://h/\n?? status == 201\nX-A: 1\n\n{}\n  ?? json $.id exists\n";
+		let file = parse("x", text).unwrap();
+		let def = &file.requests[0];
+		assert_eq!(def.headers.len(), 1);
+		assert!(matches!(&def.body, Some(BodyDef::Text(body)) if body.to_string() == "{}"));
+		let lines: Vec<_> = def
+			.assertions
+			.iter()
+			.map(|a| (a.line, a.text.as_str()))
+			.collect();
+		assert_eq!(lines, [(2, "status == 201"), (6, "json $.id exists")]);
+
+		let err = parse("x", "GET http://h/\n?? status ~ 1\n").unwrap_err();
+		assert_eq!((err.line, err.column), (2, 4));
+	}
+
+	#[test]
+	fn extracts_captures() {
+		let text = "POST http://h/login\n>> token = json $.token\n\n{}\n>> code = status\n";
+		let file = parse("x", text).unwrap();
+		let def = &file.requests[0];
+		assert!(matches!(&def.body, Some(BodyDef::Text(body)) if body.to_string() == "{}"));
+		let captures: Vec<_> = def
+			.captures
+			.iter()
+			.map(|c| (c.line, c.name.as_str()))
+			.collect();
+		assert_eq!(captures, [(2, "token"), (5, "code")]);
+
+		let err = parse("x", "GET http://h/\n  >> token json\n").unwrap_err();
+		assert_eq!((err.line, err.column), (2, 6));
+	}
+
+	#[test]
+	fn parses_websocket_scripts() {
+		let text = "WEBSOCKET ws://h/chat\n?? status == 101\n\n===\n\n{\"a\": 1}\n\n\
+					=== wait-for-server\n?? body contains a\n=== wait-for-server\n===\n< msg.bin\n";
+		let file = parse("x", text).unwrap();
+		let def = &file.requests[0];
+		assert!(def.is_websocket() && def.body.is_none());
+		assert_eq!(def.assertions.len(), 1);
+		let steps: Vec<_> = def
+			.messages
+			.iter()
+			.map(|m| (m.line, m.wait, m.assertions.len(), m.body.is_some()))
+			.collect();
+		assert_eq!(
+			steps,
+			[
+				(4, false, 0, true),
+				(8, true, 1, false),
+				(10, true, 0, false),
+				(11, false, 0, true)
+			]
+		);
+		assert!(
+			matches!(&def.messages[0].body, Some(BodyDef::Text(body)) if body.to_string() == "{\"a\": 1}")
+		);
+		let request = file.build(def, &Variables::new()).unwrap();
+		assert_eq!(request.method, "GET");
+
+		let err = parse("x", "WEBSOCKET ws://h/\n\nhello\n").unwrap_err();
+		assert_eq!(
+			(err.line, err.message.as_str()),
+			(3, "expected `===` before a WebSocket message")
+		);
+		let err = parse("x", "WEBSOCKET ws://h/\n\n=== wait\n").unwrap_err();
+		assert_eq!((err.line, err.column), (3, 5));
+		let err = parse("x", "WEBSOCKET ws://h/\n\n===\n?? status == 101\n").unwrap_err();
+		assert_eq!(err.line, 4);
+	}
+
+	#[test]
+	fn parses_graphql_requests() {
+		let text = "# @operation Item\nGRAPHQL http://h/graphql\n\n\
+					query Item($id: ID!) {\n  item(id: $id) { name # {\n  }\n}\n\n\
+					{\"id\": \"7\"}\n\n?? status == 200\n";
+		let file = parse("x", text).unwrap();
+		let def = &file.requests[0];
+		assert!(def.is_graphql() && def.assertions.len() == 1);
+		let graphql = def.graphql.as_ref().unwrap();
+		assert_eq!(graphql.line, 4);
+		assert_eq!(graphql.operation.as_deref(), Some("Item"));
+		assert_eq!(graphql.variables.as_ref().map(|v| v.0), Some(9));
+		let request = file.build(def, &Variables::new()).unwrap();
+		assert_eq!(request.method, "POST");
+		assert_eq!(
+			request.headers.get("Content-Type"),
+			Some("application/json")
+		);
+		let query = Query::from_json(&request.body).unwrap();
+		assert!(query.query.starts_with("query Item($id: ID!) {") && query.query.ends_with('}'));
+		assert_eq!(query.variables, Some(serde_json::json!({"id": "7"})));
+		assert_eq!(query.operation_name.as_deref(), Some("Item"));
+
+		// a shorthand query is not taken for variables
+		let file = parse("x", "GRAPHQL http://h/\n\n{ items { id } }\n").unwrap();
+		let graphql = file.requests[0].graphql.as_ref().unwrap();
+		assert!(graphql.variables.is_none() && graphql.line == 3);
+		let file = parse("x", "GRAPHQL http://h/\n\n{ a }\n\n{\"id\": }\n").unwrap();
+		let err = file
+			.build(&file.requests[0], &Variables::new())
+			.unwrap_err();
+		assert!(err.to_string().starts_with("x:5:"), "{err}");
+		assert!(
+			err.to_string().contains("invalid GraphQL variables"),
+			"{err}"
+		);
+	}
+
+	#[test]
+	fn reports_error_positions() {
+		let err = parse("bad.http", "GET http://h/\nX-Ok: 1\nnot a header\n").unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"bad.http:3:1: expected `Name: value` header"
+		);
+
+		let err = parse("bad.http", "\n@ = 1\n").unwrap_err();
+		assert_eq!((err.line, err.column), (2, 2));
+
+		let err = parse("bad.http", "###\nGET http://{{host/\n").unwrap_err();
+		assert_eq!((err.line, err.column), (2, 12));
+
+		let file = parse("bad.http", "GET http://h/\nX-Token: {{token}}\n").unwrap();
+		let err = file
+			.build(&file.requests[0], &Variables::new())
+			.unwrap_err();
+		assert_eq!(err.to_string(), "bad.http:2:10: undefined variable `token`");
+	}
+}
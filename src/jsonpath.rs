@@ -0,0 +1,130 @@
+//! Minimal JSON path support: `$`, `.name`, `['name']`, `[index]` with
+//! negative indexes counting from the end, and `.length` on arrays, objects
+//! and strings.
+
+use serde_json::Value;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Step {
+	Key(String),
+	Index(i64),
+}
+
+/// Parsed JSON path expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonPath {
+	steps: Vec<Step>,
+}
+
+impl JsonPath {
+	pub fn parse(path: &str) -> Result<JsonPath, String> {
+		let mut rest = path.trim();
+		rest = rest.strip_prefix('$').unwrap_or(rest);
+		let mut steps = Vec::new();
+		if !rest.is_empty() && !rest.starts_with(['.', '[']) {
+			let end = rest.find(['.', '[']).unwrap_or(rest.len());
+			steps.push(Step::Key(rest[..end].to_string()));
+			rest = &rest[end..];
+		}
+		while !rest.is_empty() {
+			if let Some(after) = rest.strip_prefix('.') {
+				let end = after.find(['.', '[']).unwrap_or(after.len());
+				if end == 0 {
+					return Err(format!("empty key in `{path}`"));
+				}
+				steps.push(Step::Key(after[..end].to_string()));
+				rest = &after[end..];
+			} else if let Some(after) = rest.strip_prefix('[') {
+				let end = after
+					.find(']')
+					.ok_or_else(|| format!("unterminated `[` in `{path}`"))?;
+				let inner = after[..end].trim();
+				let quoted = inner
+					.strip_prefix('\'')
+					.and_then(|s| s.strip_suffix('\''))
+					.or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+				let step = match quoted {
+					Some(key) => Step::Key(key.to_string()),
+					None => Step::Index(
+						inner
+							.parse()
+							.map_err(|_| format!("invalid index `{inner}` in `{path}`"))?,
+					),
+				};
+				steps.push(step);
+				rest = &after[end + 1..];
+			} else {
+				return Err(format!("unexpected `{rest}` in `{path}`"));
+			}
+		}
+		Ok(JsonPath { steps })
+	}
+
+	/// Returns the selected value, if it exists.
+	pub fn select(&self, root: &Value) -> Option<Value> {
+		let mut current = root;
+		for (index, step) in self.steps.iter().enumerate() {
+			let last = index + 1 == self.steps.len();
+			current = match (step, current) {
+				(Step::Key(key), Value::Object(map)) if map.contains_key(key) => &map[key],
+				(Step::Key(key), value) if last && key == "length" => {
+					return match value {
+						Value::Array(items) => Some(items.len().into()),
+						Value::Object(map) => Some(map.len().into()),
+						Value::String(text) => Some(text.chars().count().into()),
+						_ => None,
+					};
+				}
+				(Step::Index(index), Value::Array(items)) => {
+					let index = if *index < 0 {
+						items.len() as i64 + index
+					} else {
+						*index
+					};
+					items.get(usize::try_from(index).ok()?)?
+				}
+				_ => return None,
+			};
+		}
+		Some(current.clone())
+	}
+}
+
+/// Formats a JSON value as text for variables and messages: strings without
+/// quotes, everything else as compact JSON.
+pub fn value_to_string(value: &Value) -> String {
+	match value {
+		Value::String(text) => text.clone(),
+		other => other.to_string(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	fn select(path: &str, value: &Value) -> Option<Value> {
+		JsonPath::parse(path).unwrap().select(value)
+	}
+
+	#[test]
+	fn selects_values() {
+		let doc = json!({"items": [{"id": 1}, {"id": 2, "a b": true}], "name": "x"});
+		assert_eq!(select("$", &doc), Some(doc.clone()));
+		assert_eq!(select("$.name", &doc), Some(json!("x")));
+		assert_eq!(select("name", &doc), Some(json!("x")));
+		assert_eq!(select("$.items[1].id", &doc), Some(json!(2)));
+		assert_eq!(select("$.items[-1]['a b']", &doc), Some(json!(true)));
+		assert_eq!(select("$.items.length", &doc), Some(json!(2)));
+		assert_eq!(select("$.items[5]", &doc), None);
+		assert_eq!(select("$.name.first", &doc), None);
+	}
+
+	#[test]
+	fn rejects_invalid_paths() {
+		assert!(JsonPath::parse("$.a[").is_err());
+		assert!(JsonPath::parse("$.a[x]").is_err());
+		assert!(JsonPath::parse("$..a").is_err());
+	}
+}
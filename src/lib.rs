@@ -0,0 +1,48 @@
+//! Lightning fast tool to help developers test Web/HTTP requests.
+
+pub mod assert;
+pub mod auth;
+pub mod capture;
+pub mod cli;
+pub mod client;
+pub mod cookie;
+pub mod curl;
+pub mod diff;
+pub mod encoding;
+pub mod environment;
+pub mod error;
+pub mod export;
+pub mod graphql;
+pub mod grpc;
+pub mod h1;
+pub mod h2;
+pub mod har;
+pub mod http;
+pub mod httpfile;
+pub mod jsonpath;
+pub mod load;
+pub mod mock;
+pub mod multipart;
+pub mod openapi;
+pub mod proxy;
+pub mod recording;
+pub mod redirect;
+pub mod render;
+pub mod report;
+pub mod runner;
+pub mod sse;
+pub mod template;
+pub mod timing;
+pub mod tls;
+pub mod tui;
+pub mod upload;
+pub mod url;
+pub mod util;
+pub mod ws;
+
+pub use client::{Client, HttpVersion};
+pub use error::{Error, Result};
+pub use http::{Headers, Request, Response};
+pub use httpfile::RequestFile;
+pub use template::Variables;
+pub use url::Url;
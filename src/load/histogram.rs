@@ -0,0 +1,168 @@
+//! Latency histogram in the style of HdrHistogram: values are counted in
+//! log-linear buckets, so the recorded precision is constant relative to
+//! the value (better than 0.1%) and memory does not depend on the number of
+//! samples.
+
+/// Bits of linear resolution within each power of two.
+const PRECISION: u32 = 10;
+const SUB_BUCKETS: usize = 1 << PRECISION;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Histogram {
+	counts: Vec<u64>,
+	total: u64,
+	min: u64,
+	max: u64,
+	sum: u128,
+}
+
+impl Histogram {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn record(&mut self, value: u64) {
+		let index = index_of(value);
+		if index >= self.counts.len() {
+			self.counts.resize(index + 1, 0);
+		}
+		self.counts[index] += 1;
+		self.min = if self.total == 0 {
+			value
+		} else {
+			self.min.min(value)
+		};
+		self.max = self.max.max(value);
+		self.total += 1;
+		self.sum += value as u128;
+	}
+
+	/// Adds all samples of another histogram.
+	pub fn merge(&mut self, other: &Histogram) {
+		if other.total == 0 {
+			return;
+		}
+		if other.counts.len() > self.counts.len() {
+			self.counts.resize(other.counts.len(), 0);
+		}
+		for (count, added) in self.counts.iter_mut().zip(&other.counts) {
+			*count += added;
+		}
+		self.min = if self.total == 0 {
+			other.min
+		} else {
+			self.min.min(other.min)
+		};
+		self.max = self.max.max(other.max);
+		self.total += other.total;
+		self.sum += other.sum;
+	}
+
+	pub fn len(&self) -> u64 {
+		self.total
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.total == 0
+	}
+
+	pub fn min(&self) -> u64 {
+		self.min
+	}
+
+	pub fn max(&self) -> u64 {
+		self.max
+	}
+
+	pub fn mean(&self) -> f64 {
+		if self.total == 0 {
+			return 0.0;
+		}
+		self.sum as f64 / self.total as f64
+	}
+
+	/// Smallest recorded value such that `percentile` percent of the samples
+	/// are less than or equal to it, within the histogram precision.
+	pub fn percentile(&self, percentile: f64) -> u64 {
+		if self.total == 0 {
+			return 0;
+		}
+		let rank = ((percentile / 100.0 * self.total as f64).ceil() as u64).clamp(1, self.total);
+		let mut seen = 0;
+		for (index, count) in self.counts.iter().enumerate() {
+			seen += count;
+			if seen >= rank {
+				return highest_equivalent(index).clamp(self.min, self.max);
+			}
+		}
+		self.max
+	}
+}
+
+fn index_of(value: u64) -> usize {
+	if value < 2 * SUB_BUCKETS as u64 {
+		return value as usize;
+	}
+	let magnitude = 63 - value.leading_zeros();
+	let shift = magnitude - PRECISION;
+	let sub = (value >> shift) as usize - SUB_BUCKETS;
+	2 * SUB_BUCKETS + (shift as usize - 1) * SUB_BUCKETS + sub
+}
+
+/// Largest value that maps to the same bucket as `index`.
+fn highest_equivalent(index: usize) -> u64 {
+	if index < 2 * SUB_BUCKETS {
+		return index as u64;
+	}
+	let offset = index - 2 * SUB_BUCKETS;
+	let shift = (offset / SUB_BUCKETS + 1) as u32;
+	let lowest = ((offset % SUB_BUCKETS + SUB_BUCKETS) as u64) << shift;
+	lowest + (1 << shift) - 1
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn computes_percentiles() {
+		let mut histogram = Histogram::new();
+		for value in 1..=10_000 {
+			histogram.record(value);
+		}
+		assert_eq!(histogram.len(), 10_000);
+		assert_eq!(histogram.min(), 1);
+		assert_eq!(histogram.max(), 10_000);
+		assert_eq!(histogram.mean(), 5000.5);
+		assert_eq!(histogram.percentile(50.0), 5003);
+		assert_eq!(histogram.percentile(100.0), 10_000);
+		for (percentile, exact) in [(90.0, 9000.0), (99.0, 9900.0), (99.9, 9990.0)] {
+			let value = histogram.percentile(percentile) as f64;
+			assert!((value - exact).abs() / exact < 0.001, "{value}");
+		}
+	}
+
+	#[test]
+	fn keeps_relative_precision_for_large_values() {
+		let mut histogram = Histogram::new();
+		for value in [3_000_000_007u64, 5, 123_456_789] {
+			histogram.record(value);
+		}
+		let median = histogram.percentile(50.0) as f64;
+		assert!((median - 123_456_789.0).abs() / 123_456_789.0 < 0.001);
+		assert_eq!(histogram.percentile(0.0), 5);
+		assert_eq!(histogram.percentile(100.0), 3_000_000_007);
+	}
+
+	#[test]
+	fn merges_histograms() {
+		let mut a = Histogram::new();
+		let mut b = Histogram::new();
+		a.record(10);
+		b.record(5000);
+		b.record(2);
+		a.merge(&b);
+		assert_eq!((a.len(), a.min(), a.max()), (3, 2, 5000));
+		assert_eq!(a.percentile(50.0), 10);
+	}
+}
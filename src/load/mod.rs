@@ -0,0 +1,298 @@
+//! Load testing: replays requests from concurrent workers, either as fast
+//! as possible or at a fixed rate, and aggregates the latencies.
+
+mod histogram;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::http::Request;
+use crate::util::format_bytes;
+
+pub use histogram::Histogram;
+
+/// How long and how hard to run.
+///
+/// Each worker keeps one HTTP/1.1 connection open and sends its requests
+/// over it, opening a new one when the server closes it or a request
+/// fails, as a pool of keep-alive clients would. The TLS configuration is
+/// built once for the run. HTTP/2 requests still open a connection each.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadConfig {
+	/// Number of workers, each with one request in flight at a time.
+	pub concurrency: usize,
+	/// Target requests per second. Without a rate every worker sends the
+	/// next request as soon as the previous one completed.
+	pub rate: Option<f64>,
+	/// Stop starting requests after this time.
+	pub duration: Option<Duration>,
+	/// Stop after this many requests.
+	pub requests: Option<u64>,
+}
+
+impl Default for LoadConfig {
+	fn default() -> Self {
+		LoadConfig {
+			concurrency: 10,
+			rate: None,
+			duration: Some(Duration::from_secs(10)),
+			requests: None,
+		}
+	}
+}
+
+/// Aggregated results of a load test. Latencies are in microseconds.
+#[derive(Clone, Debug, Default)]
+pub struct LoadReport {
+	pub elapsed: Duration,
+	pub latency: Histogram,
+	pub statuses: BTreeMap<u16, u64>,
+	/// Failed requests by kind of error.
+	pub errors: BTreeMap<String, u64>,
+	/// Response body bytes received.
+	pub bytes: u64,
+	pub rate: Option<f64>,
+	pub concurrency: usize,
+}
+
+impl LoadReport {
+	pub fn responses(&self) -> u64 {
+		self.statuses.values().sum()
+	}
+
+	pub fn failures(&self) -> u64 {
+		self.errors.values().sum()
+	}
+
+	/// Completed requests per second, failed ones included.
+	pub fn throughput(&self) -> f64 {
+		match self.elapsed.as_secs_f64() {
+			0.0 => 0.0,
+			secs => (self.responses() + self.failures()) as f64 / secs,
+		}
+	}
+
+	fn merge(&mut self, other: Worker) {
+		self.latency.merge(&other.latency);
+		for (status, count) in other.statuses {
+			*self.statuses.entry(status).or_default() += count;
+		}
+		for (kind, count) in other.errors {
+			*self.errors.entry(kind).or_default() += count;
+		}
+		self.bytes += other.bytes;
+	}
+
+	pub fn to_json(&self) -> Value {
+		let ms = |micros: u64| micros as f64 / 1000.0;
+		let statuses: serde_json::Map<String, Value> = self
+			.statuses
+			.iter()
+			.map(|(status, count)| (status.to_string(), (*count).into()))
+			.collect();
+		json!({
+			"duration": self.elapsed.as_secs_f64(),
+			"concurrency": self.concurrency,
+			"rate": self.rate,
+			"requests": self.responses() + self.failures(),
+			"responses": self.responses(),
+			"errors": self.errors,
+			"statuses": statuses,
+			"throughput": self.throughput(),
+			"bytes": self.bytes,
+			"latency": {
+				"min": ms(self.latency.min()),
+				"mean": self.latency.mean() / 1000.0,
+				"p50": ms(self.latency.percentile(50.0)),
+				"p90": ms(self.latency.percentile(90.0)),
+				"p99": ms(self.latency.percentile(99.0)),
+				"p99.9": ms(self.latency.percentile(99.9)),
+				"max": ms(self.latency.max()),
+			},
+		})
+	}
+}
+
+impl fmt::Display for LoadReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let secs = self.elapsed.as_secs_f64();
+		writeln!(f, "Summary:")?;
+		writeln!(
+			f,
+			"  Requests:    {} ({} responses, {} errors)",
+			self.responses() + self.failures(),
+			self.responses(),
+			self.failures()
+		)?;
+		writeln!(f, "  Duration:    {secs:.2} s")?;
+		match self.rate {
+			Some(rate) => writeln!(
+				f,
+				"  Mode:        {rate} req/s target, up to {} in flight",
+				self.concurrency
+			)?,
+			None => writeln!(f, "  Mode:        {} concurrent workers", self.concurrency)?,
+		}
+		writeln!(f, "  Throughput:  {:.1} req/s", self.throughput())?;
+		let transfer = if secs > 0.0 {
+			self.bytes as f64 / secs
+		} else {
+			0.0
+		};
+		writeln!(
+			f,
+			"  Transfer:    {} ({}/s)",
+			format_bytes(self.bytes as f64),
+			format_bytes(transfer)
+		)?;
+
+		if !self.latency.is_empty() {
+			writeln!(f, "\nLatency:")?;
+			let ms = |micros: f64| format!("{:.2} ms", micros / 1000.0);
+			let rows = [
+				("min", self.latency.min() as f64),
+				("mean", self.latency.mean()),
+				("p50", self.latency.percentile(50.0) as f64),
+				("p90", self.latency.percentile(90.0) as f64),
+				("p99", self.latency.percentile(99.0) as f64),
+				("p99.9", self.latency.percentile(99.9) as f64),
+				("max", self.latency.max() as f64),
+			];
+			for (label, value) in rows {
+				writeln!(f, "  {label:<6}{:>12}", ms(value))?;
+			}
+		}
+		if !self.statuses.is_empty() {
+			writeln!(f, "\nStatus codes:")?;
+			for (status, count) in &self.statuses {
+				writeln!(f, "  {status}: {count}")?;
+			}
+		}
+		if !self.errors.is_empty() {
+			writeln!(f, "\nErrors:")?;
+			for (kind, count) in &self.errors {
+				writeln!(f, "  {kind}: {count}")?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Results collected by a single worker.
+#[derive(Default)]
+struct Worker {
+	latency: Histogram,
+	statuses: BTreeMap<u16, u64>,
+	errors: BTreeMap<String, u64>,
+	bytes: u64,
+}
+
+/// Sends the requests in turn until the configured limit is reached.
+///
+/// With a rate the test is open-loop: request `i` is due at
+/// `start + i / rate`, whether or not earlier requests have completed, and
+/// its latency is measured from that due time. A slow server therefore
+/// shows up as high latency instead of silently lowering the request rate
+/// (coordinated omission).
+pub fn run(client: &Client, requests: &[Request], config: &LoadConfig) -> LoadReport {
+	if requests.is_empty() {
+		return LoadReport::default();
+	}
+	let next = AtomicU64::new(0);
+	let start = Instant::now();
+	let deadline = config.duration.map(|duration| start + duration);
+	let concurrency = config.concurrency.max(1);
+	// the workers share the TLS configuration, each has its own connection
+	let client = client.keep_alive();
+
+	let workers: Vec<Worker> = thread::scope(|scope| {
+		let handles: Vec<_> = (0..concurrency)
+			.map(|_| {
+				scope.spawn(|| {
+					let client = client.keep_alive();
+					let mut worker = Worker::default();
+					while let Some((index, due)) = claim(&next, start, deadline, config) {
+						let request = &requests[index as usize % requests.len()];
+						let now = Instant::now();
+						if due > now {
+							thread::sleep(due - now);
+						}
+						match client.send(request) {
+							Ok(response) => {
+								worker.latency.record(due.elapsed().as_micros() as u64);
+								*worker.statuses.entry(response.status).or_default() += 1;
+								worker.bytes += response.body.len() as u64;
+							}
+							Err(err) => *worker.errors.entry(error_kind(&err)).or_default() += 1,
+						}
+					}
+					worker
+				})
+			})
+			.collect();
+		handles
+			.into_iter()
+			.map(|h| h.join().unwrap_or_default())
+			.collect()
+	});
+
+	let mut report = LoadReport {
+		elapsed: start.elapsed(),
+		rate: config.rate,
+		concurrency,
+		..Default::default()
+	};
+	for worker in workers {
+		report.merge(worker);
+	}
+	report
+}
+
+/// Claims the next request and returns its index and when it is due, or
+/// `None` once the limit is reached.
+fn claim(
+	next: &AtomicU64,
+	start: Instant,
+	deadline: Option<Instant>,
+	config: &LoadConfig,
+) -> Option<(u64, Instant)> {
+	let index = next.fetch_add(1, Ordering::Relaxed);
+	if config.requests.is_some_and(|limit| index >= limit) {
+		return None;
+	}
+	let due = match config.rate {
+		Some(rate) => start + Duration::from_secs_f64(index as f64 / rate),
+		None => Instant::now(),
+	};
+	if deadline.is_some_and(|deadline| due >= deadline) {
+		return None;
+	}
+	Some((index, due))
+}
+
+/// Groups errors by kind for the report.
+pub fn error_kind(err: &Error) -> String {
+	match err {
+		Error::Io(err) => match err.kind() {
+			io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => "timeout".into(),
+			io::ErrorKind::ConnectionRefused => "connection refused".into(),
+			io::ErrorKind::ConnectionReset => "connection reset".into(),
+			io::ErrorKind::UnexpectedEof => "connection closed".into(),
+			kind => format!("I/O: {kind}"),
+		},
+		Error::Url(_) => "invalid URL".into(),
+		Error::Protocol(_) => "protocol error".into(),
+		Error::TooLarge(_) => "too large".into(),
+		Error::Tls(_) => "TLS error".into(),
+		Error::Redirect { .. } => "redirect refused".into(),
+		Error::Usage(_) | Error::Parse(_) => "invalid request".into(),
+	}
+}
@@ -0,0 +1,6 @@
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	webcat::cli::main(&args)
+}
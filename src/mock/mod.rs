@@ -0,0 +1,221 @@
+//! Mock server answering requests with canned responses from a route file.
+//!
+//! A route file uses the request file syntax: each block is a request
+//! pattern followed by the response to send, starting at its status line.
+//!
+//! ```text
+//! @name = widget
+//!
+//! ### Get item
+//! GET /items/{id}
+//! ?? query verbose == true
+//! # @delay 200ms
+//!
+//! HTTP/1.1 200 OK
+//! Content-Type: application/json
+//!
+//! {"id": "{{id}}", "name": "{{name}}"}
+//!
+//! ### Flaky
+//! ANY /flaky/**
+//! # @fault close 0.5
+//!
+//! HTTP/1.1 503
+//! ```
+//!
+//! Path segments may be `{name}` captures, which are available as
+//! variables in the response, `*` for any single segment or a trailing
+//! `**` for the rest of the path. Query parameters, headers and the body
+//! given in the request part must be present in a request. `??` lines add
+//! matchers on a `header NAME`, `query NAME`, the `body` or a `json PATH`
+//! with the operators of [`crate::assert`]. The first matching route
+//! answers, requests without one get a 404.
+//!
+//! `# @delay DURATION` waits before responding and
+//! `# @fault KIND [PROBABILITY]` misbehaves instead, where `KIND` is one of
+//! `close`, `truncate`, `hang` or `malformed`. `# @auth SCHEME CREDENTIALS`
+//! requires Basic, Digest, Bearer or AWS credentials as described in
+//! [`crate::auth`], and answers other requests with a 401 and a challenge.
+
+mod routes;
+
+use std::fmt;
+use std::io::{self, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
+
+use crate::error::{Error, Result};
+use crate::h1;
+use crate::http::{Request, Response};
+
+pub use routes::{Fault, FaultKind, Matcher, PathPattern, ResponseDef, Route, RouteFile, Target};
+
+/// Summary of a handled request, passed to the log callback.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Exchange {
+	pub method: String,
+	/// Path including the query string.
+	pub path: String,
+	/// Status sent, `None` when a fault replaced the response.
+	pub status: Option<u16>,
+	/// Label of the matching route.
+	pub route: Option<String>,
+	pub fault: Option<FaultKind>,
+}
+
+impl fmt::Display for Exchange {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} {} -> ", self.method, self.path)?;
+		match (self.status, self.fault) {
+			(_, Some(fault)) => write!(f, "{} fault", fault.name())?,
+			(Some(status), None) => write!(f, "{status}")?,
+			(None, None) => write!(f, "no response")?,
+		}
+		match &self.route {
+			Some(route) => write!(f, " ({route})"),
+			None => Ok(()),
+		}
+	}
+}
+
+pub struct MockServer {
+	listener: TcpListener,
+	routes: Arc<RouteFile>,
+}
+
+impl MockServer {
+	pub fn bind(addr: impl ToSocketAddrs, routes: RouteFile) -> Result<MockServer> {
+		Ok(MockServer {
+			listener: TcpListener::bind(addr)?,
+			routes: Arc::new(routes),
+		})
+	}
+
+	pub fn local_addr(&self) -> Result<SocketAddr> {
+		Ok(self.listener.local_addr()?)
+	}
+
+	/// Serves connections until the listener fails, each on its own thread.
+	/// `log` is called for every request.
+	pub fn run(self, log: impl Fn(&Exchange) + Send + Sync + 'static) -> Result<()> {
+		let log = Arc::new(log);
+		for stream in self.listener.incoming() {
+			let stream = stream?;
+			let routes = Arc::clone(&self.routes);
+			let log = Arc::clone(&log);
+			thread::spawn(move || {
+				// errors only affect this connection
+				let _ = serve(stream, &routes, &*log);
+			});
+		}
+		Ok(())
+	}
+}
+
+/// Handles the requests of one connection until either side closes it.
+fn serve(stream: TcpStream, routes: &RouteFile, log: &dyn Fn(&Exchange)) -> Result<()> {
+	let mut reader = BufReader::new(stream.try_clone()?);
+	let mut writer = stream;
+	loop {
+		let request = match h1::read_request(&mut reader) {
+			Ok(Some(request)) => request,
+			Ok(None) => return Ok(()),
+			Err(Error::TooLarge(message)) => {
+				let response = Response::text(413, &message);
+				h1::write_response(&mut writer, &response, false)?;
+				return Ok(());
+			}
+			Err(Error::Protocol(message)) => {
+				let response = Response::text(400, &message);
+				h1::write_response(&mut writer, &response, false)?;
+				return Ok(());
+			}
+			Err(err) => return Err(err),
+		};
+		let mut exchange = Exchange {
+			method: request.method.clone(),
+			path: request.url.path.clone(),
+			status: None,
+			route: None,
+			fault: None,
+		};
+		let close = wants_close(&request);
+
+		let Some((route, captures)) = routes.find(&request) else {
+			let message = format!("no route matches {} {}", request.method, request.url.path);
+			let response = Response::text(404, &message);
+			exchange.status = Some(response.status);
+			log(&exchange);
+			h1::write_response(&mut writer, &response, request.method == "HEAD")?;
+			if close {
+				return Ok(());
+			}
+			continue;
+		};
+		exchange.route = Some(route.request.label());
+		let response = match &route.auth {
+			Some(auth) if !auth.verify(&request) => {
+				let mut response = Response::text(401, "invalid or missing credentials");
+				for challenge in auth.challenges() {
+					response.headers.append("WWW-Authenticate", challenge);
+				}
+				response
+			}
+			_ => match routes.respond(route, &captures) {
+				Ok(response) => response,
+				Err(err) => Response::text(500, &err.to_string()),
+			},
+		};
+		if let Some(delay) = route.delay {
+			thread::sleep(delay);
+		}
+		if let Some(fault) = route.fault.filter(Fault::triggers) {
+			exchange.fault = Some(fault.kind);
+			log(&exchange);
+			return inject(fault.kind, &mut reader, &mut writer, &response);
+		}
+		exchange.status = Some(response.status);
+		log(&exchange);
+		h1::write_response(&mut writer, &response, request.method == "HEAD")?;
+		if close {
+			return Ok(());
+		}
+	}
+}
+
+/// Misbehaves as requested by a fault. The connection is closed afterwards.
+fn inject<R: Read, W: Write>(
+	kind: FaultKind,
+	reader: &mut R,
+	writer: &mut W,
+	response: &Response,
+) -> Result<()> {
+	match kind {
+		FaultKind::Close => {}
+		FaultKind::Truncate => {
+			let mut full = Vec::new();
+			h1::write_response(&mut full, response, false)?;
+			let cut = full.len() - response.body.len().div_ceil(2);
+			writer.write_all(&full[..cut])?;
+			writer.flush()?;
+		}
+		FaultKind::Hang => {
+			// wait until the client gives up
+			io::copy(reader, &mut io::sink())?;
+		}
+		FaultKind::Malformed => {
+			writer.write_all(b"HTTP/1.1 OK\r\nthis is not a header\r\n\r\n")?;
+			writer.flush()?;
+		}
+	}
+	Ok(())
+}
+
+/// Connections are kept open unless the client asks to close them.
+fn wants_close(request: &Request) -> bool {
+	request
+		.headers
+		.get("Connection")
+		.is_some_and(|value| value.eq_ignore_ascii_case("close"))
+}
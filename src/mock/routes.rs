@@ -0,0 +1,785 @@
+//! Route files for the mock server.
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::assert::{compare_text, unquote, Op};
+use crate::auth::Auth;
+use crate::error::{Error, ParseError, Result};
+use crate::http::{reason_phrase, Request, Response};
+use crate::httpfile::parse::{
+	column_of, comment, parse_block, parse_body, parse_headers, split_blocks,
+};
+use crate::httpfile::{BodyDef, HeaderDef, RequestDef, RequestFile};
+use crate::jsonpath::{value_to_string, JsonPath};
+use crate::template::{Template, Variables};
+use crate::url::{has_scheme, parse_query, percent_decode};
+use crate::util::{parse_duration, random_u64};
+
+/// Parsed route file with its variables resolved.
+#[derive(Clone, Debug)]
+pub struct RouteFile {
+	/// Request file holding the request part of every route, used to
+	/// render bodies and report errors.
+	pub file: RequestFile,
+	pub variables: Variables,
+	pub routes: Vec<Route>,
+}
+
+/// A request pattern and the response sent when it matches.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Route {
+	/// Request part as written, `ANY` matches every method.
+	pub request: RequestDef,
+	pub path: PathPattern,
+	/// Query parameters given in the route URL, all of which must be present.
+	pub query: Vec<(String, String)>,
+	pub matchers: Vec<Matcher>,
+	pub response: ResponseDef,
+	pub delay: Option<Duration>,
+	pub fault: Option<Fault>,
+	/// Credentials required by `# @auth`, other requests get a 401.
+	pub auth: Option<Auth>,
+}
+
+/// Response as written in the route file, before interpolation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResponseDef {
+	/// Line of the status line.
+	pub line: usize,
+	pub status: u16,
+	/// Reason phrase, the standard one for the status when empty.
+	pub reason: String,
+	pub headers: Vec<HeaderDef>,
+	pub body: Option<BodyDef>,
+}
+
+/// Condition on a request, written as `?? SUBJECT OP [VALUE]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Matcher {
+	pub text: String,
+	pub line: usize,
+	pub target: Target,
+	pub op: Op,
+	pub value: Option<Template>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+	Header(String),
+	Query(String),
+	Body,
+	Json(String, JsonPath),
+}
+
+/// Misbehaviour injected instead of a regular response.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fault {
+	pub kind: FaultKind,
+	/// Chance of the fault between 0 and 1, otherwise the response is sent.
+	pub probability: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultKind {
+	/// Close the connection without responding.
+	Close,
+	/// Announce the full body but send only half of it.
+	Truncate,
+	/// Never respond.
+	Hang,
+	/// Send an invalid status line.
+	Malformed,
+}
+
+impl FaultKind {
+	fn parse(text: &str) -> Option<FaultKind> {
+		Some(match text {
+			"close" => FaultKind::Close,
+			"truncate" => FaultKind::Truncate,
+			"hang" => FaultKind::Hang,
+			"malformed" => FaultKind::Malformed,
+			_ => return None,
+		})
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			FaultKind::Close => "close",
+			FaultKind::Truncate => "truncate",
+			FaultKind::Hang => "hang",
+			FaultKind::Malformed => "malformed",
+		}
+	}
+}
+
+impl Fault {
+	/// Decides whether the fault applies to the current request.
+	pub fn triggers(&self) -> bool {
+		let sample = (random_u64() >> 11) as f64 / (1u64 << 53) as f64;
+		self.probability >= 1.0 || sample < self.probability
+	}
+}
+
+/// Path of a route URL. Segments are literal, `{name}` captures one
+/// segment, `*` matches any one segment and a trailing `**` the rest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathPattern {
+	segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+	Literal(String),
+	Capture(String),
+	Any,
+	Rest,
+}
+
+impl PathPattern {
+	pub fn parse(path: &str) -> std::result::Result<PathPattern, String> {
+		let parts: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+		let mut segments = Vec::new();
+		for (index, part) in parts.iter().enumerate() {
+			segments.push(match *part {
+				"**" if index + 1 == parts.len() => Segment::Rest,
+				"**" => return Err("`**` is only allowed at the end of a path".into()),
+				"*" => Segment::Any,
+				_ => match part.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+					Some(name) if !name.is_empty() => Segment::Capture(name.to_string()),
+					Some(_) => return Err("empty capture name in `{}`".into()),
+					None => Segment::Literal(percent_decode(part)),
+				},
+			});
+		}
+		Ok(PathPattern { segments })
+	}
+
+	/// Matches a request path without query and returns the captures.
+	pub fn matches(&self, path: &str) -> Option<Vec<(String, String)>> {
+		let parts: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+		let mut captures = Vec::new();
+		for (index, segment) in self.segments.iter().enumerate() {
+			match segment {
+				Segment::Rest => return Some(captures),
+				_ if index >= parts.len() => return None,
+				Segment::Literal(text) if *text == percent_decode(parts[index]) => {}
+				Segment::Literal(_) => return None,
+				Segment::Capture(name) => {
+					captures.push((name.clone(), percent_decode(parts[index])));
+				}
+				Segment::Any => {}
+			}
+		}
+		(parts.len() == self.segments.len()).then_some(captures)
+	}
+}
+
+impl Matcher {
+	/// Parses the text after `??` starting at the given position.
+	fn parse(text: &str, line: usize, column: usize) -> std::result::Result<Matcher, ParseError> {
+		let text = text.trim();
+		let mut rest = text;
+		let mut word = || {
+			let trimmed = rest.trim_start();
+			let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+			let (word, after) = trimmed.split_at(end);
+			rest = after;
+			word
+		};
+		let error = |message: String| ParseError::new(line, column, message);
+
+		let target = match word() {
+			"body" => Target::Body,
+			kind @ ("header" | "query") => match word() {
+				"" => return Err(error(format!("missing {kind} name"))),
+				name if kind == "header" => Target::Header(name.to_string()),
+				name => Target::Query(name.to_string()),
+			},
+			"json" => {
+				let path = word();
+				if path.is_empty() {
+					return Err(error("missing JSON path".into()));
+				}
+				Target::Json(path.to_string(), JsonPath::parse(path).map_err(error)?)
+			}
+			"" => return Err(error("empty matcher".into())),
+			other => {
+				return Err(error(format!(
+					"unknown matcher `{other}`, expected header, query, body or json"
+				)))
+			}
+		};
+		let op_text = word();
+		let op = Op::parse(op_text)
+			.filter(|op| *op != Op::Within)
+			.ok_or_else(|| error(format!("unknown operator `{op_text}`")))?;
+
+		let value_text = rest.trim();
+		let value = match (op.takes_value(), value_text.is_empty()) {
+			(true, true) => return Err(error(format!("missing value for `{op}`"))),
+			(false, false) => return Err(error(format!("unexpected value after `{op}`"))),
+			(false, true) => None,
+			(true, false) => {
+				let offset = text.len() - rest.trim_start().len();
+				Some(Template::parse(
+					value_text,
+					line,
+					column + text[..offset].chars().count(),
+				)?)
+			}
+		};
+		Ok(Matcher {
+			text: text.to_string(),
+			line,
+			target,
+			op,
+			value,
+		})
+	}
+
+	/// Checks the request. Invalid values and bodies count as no match.
+	fn matches(&self, request: &Request, vars: &Variables) -> bool {
+		let expected = match &self.value {
+			Some(template) => match template.render(vars) {
+				Ok(text) => Some(text),
+				Err(_) => return false,
+			},
+			None => None,
+		};
+		let actual = match &self.target {
+			Target::Header(name) => request.headers.get(name).map(str::to_string),
+			Target::Query(name) => request
+				.url
+				.query_pairs()
+				.into_iter()
+				.find(|(key, _)| key == name)
+				.map(|(_, value)| value),
+			Target::Body => Some(String::from_utf8_lossy(&request.body).into_owned()),
+			Target::Json(_, path) => {
+				let Ok(document) = serde_json::from_slice::<Value>(&request.body) else {
+					return false;
+				};
+				let actual = path.select(&document);
+				if let (Op::Eq | Op::Ne, Some(actual), Some(expected)) =
+					(self.op, &actual, &expected)
+				{
+					let wanted = serde_json::from_str(expected)
+						.unwrap_or_else(|_| Value::String(expected.clone()));
+					return (*actual == wanted) == (self.op == Op::Eq);
+				}
+				actual.as_ref().map(value_to_string)
+			}
+		};
+		match (self.op, actual, expected) {
+			(Op::Exists, actual, _) => actual.is_some(),
+			(Op::NotExists, actual, _) => actual.is_none(),
+			(op, Some(actual), Some(expected)) => {
+				let expected = match self.target {
+					Target::Json(..) => expected,
+					_ => unquote(&expected),
+				};
+				compare_text(op, &actual, &expected).unwrap_or(false)
+			}
+			_ => false,
+		}
+	}
+}
+
+impl RouteFile {
+	/// Loads a route file. Variables in `vars` override the file.
+	pub fn load(path: impl AsRef<Path>, vars: Variables) -> Result<RouteFile> {
+		let path = path.as_ref();
+		let text = std::fs::read_to_string(path)
+			.map_err(|err| Error::Usage(format!("cannot read `{}`: {err}", path.display())))?;
+		let mut routes = RouteFile::parse(&path.display().to_string(), &text, vars)?;
+		routes.file.base_dir = path.parent().map(|dir| dir.to_path_buf());
+		Ok(routes)
+	}
+
+	/// Parses the text of a route file. `source` names the file in errors.
+	pub fn parse(source: &str, text: &str, mut vars: Variables) -> Result<RouteFile> {
+		let mut file = RequestFile {
+			source: source.to_string(),
+			base_dir: None,
+			variables: Vec::new(),
+			requests: Vec::new(),
+		};
+		let mut parts = Vec::new();
+		for block in split_blocks(text) {
+			let before = file.requests.len();
+			let part = parse_route_block(&mut file, block).map_err(|err| err.in_source(source))?;
+			if let Some(part) = part {
+				parts.push((file.requests.len() - 1, part));
+			} else if file.requests.len() > before {
+				let def = &file.requests[before];
+				return Err(ParseError::new(def.line, 1, "missing response status line")
+					.in_source(source)
+					.into());
+			}
+		}
+		file.resolve_variables(&mut vars)?;
+
+		let mut routes = Vec::new();
+		for (index, part) in parts {
+			let request = file.requests[index].clone();
+			let target = request
+				.url
+				.render(&vars)
+				.map_err(|err| err.in_source(source))?;
+			// absolute URLs only contribute their path
+			let target = match target.split_once("://").filter(|_| has_scheme(&target)) {
+				Some((_, rest)) => rest.find('/').map_or("/", |start| &rest[start..]),
+				None => &target,
+			};
+			let (path, query) = match target.split_once('?') {
+				Some((path, query)) => (path, parse_query(query)),
+				None => (target, Vec::new()),
+			};
+			let path = PathPattern::parse(path).map_err(|message| {
+				Error::from(ParseError::new(request.line, 1, message).in_source(source))
+			})?;
+			let auth = match &part.auth {
+				Some((template, line, column)) => {
+					let text = template
+						.render(&vars)
+						.map_err(|err| err.in_source(source))?;
+					let auth = Auth::parse(&text)
+						.and_then(|auth| match auth {
+							Auth::OAuth2(_) => {
+								Err("routes check Bearer tokens, not OAuth2 clients".into())
+							}
+							auth => Ok(auth),
+						})
+						.map_err(|message| {
+							ParseError::new(*line, *column, message).in_source(source)
+						})?;
+					Some(auth)
+				}
+				None => None,
+			};
+			routes.push(Route {
+				request,
+				path,
+				query,
+				matchers: part.matchers,
+				response: part.response,
+				delay: part.delay,
+				fault: part.fault,
+				auth,
+			});
+		}
+		Ok(RouteFile {
+			file,
+			variables: vars,
+			routes,
+		})
+	}
+
+	/// Finds the first route matching the request and returns it with the
+	/// path captures.
+	pub fn find(&self, request: &Request) -> Option<(&Route, Vec<(String, String)>)> {
+		self.routes.iter().find_map(|route| {
+			let captures = self.matches(route, request)?;
+			Some((route, captures))
+		})
+	}
+
+	fn matches(&self, route: &Route, request: &Request) -> Option<Vec<(String, String)>> {
+		let def = &route.request;
+		if def.method != "ANY" && def.method != request.method {
+			return None;
+		}
+		let captures = route.path.matches(request.url.path_only())?;
+		let vars = self.variables_with(&captures);
+		let query = request.url.query_pairs();
+		if !route.query.iter().all(|pair| query.contains(pair)) {
+			return None;
+		}
+		for header in &def.headers {
+			let expected = header.value.render(&vars).ok()?;
+			if request
+				.headers
+				.get_all(&header.name)
+				.all(|value| value != expected)
+			{
+				return None;
+			}
+		}
+		if let Some(body) = &def.body {
+			let expected = self.file.render_body(body, &vars).ok()?;
+			if !same_body(&expected, &request.body) {
+				return None;
+			}
+		}
+		route
+			.matchers
+			.iter()
+			.all(|matcher| matcher.matches(request, &vars))
+			.then_some(captures)
+	}
+
+	/// Renders the response of a route. Path captures are available as
+	/// variables.
+	pub fn respond(&self, route: &Route, captures: &[(String, String)]) -> Result<Response> {
+		let vars = self.variables_with(captures);
+		let def = &route.response;
+		let mut response = Response {
+			version: "HTTP/1.1".into(),
+			status: def.status,
+			reason: match def.reason.as_str() {
+				"" => reason_phrase(def.status).to_string(),
+				reason => reason.to_string(),
+			},
+			..Default::default()
+		};
+		for header in &def.headers {
+			let value = header
+				.value
+				.render(&vars)
+				.map_err(|err| err.in_source(&self.file.source))?;
+			response.headers.append(header.name.clone(), value);
+		}
+		if let Some(body) = &def.body {
+			response.body = self.file.render_body(body, &vars)?;
+		}
+		Ok(response)
+	}
+
+	fn variables_with(&self, captures: &[(String, String)]) -> Variables {
+		let mut vars = self.variables.clone();
+		for (name, value) in captures {
+			vars.set(name.clone(), value.clone());
+		}
+		vars
+	}
+}
+
+/// Bodies are equal byte for byte or, when both are JSON, as values.
+fn same_body(expected: &[u8], actual: &[u8]) -> bool {
+	if expected == actual {
+		return true;
+	}
+	match (
+		serde_json::from_slice::<Value>(expected),
+		serde_json::from_slice::<Value>(actual),
+	) {
+		(Ok(expected), Ok(actual)) => expected == actual,
+		_ => false,
+	}
+}
+
+/// Parts of a route block that request files do not have.
+struct RoutePart {
+	matchers: Vec<Matcher>,
+	response: ResponseDef,
+	delay: Option<Duration>,
+	fault: Option<Fault>,
+	/// `# @auth` value with its position.
+	auth: Option<(Template, usize, usize)>,
+}
+
+/// Parses one block. The request part goes to `file`, the rest is returned
+/// if the block has a response.
+fn parse_route_block(
+	file: &mut RequestFile,
+	block: crate::httpfile::parse::Block,
+) -> std::result::Result<Option<RoutePart>, ParseError> {
+	let mut matchers = Vec::new();
+	let mut delay = None;
+	let mut fault = None;
+	let mut auth = None;
+	let mut request_lines = Vec::new();
+	let mut response_lines = Vec::new();
+	for &(line_no, line) in &block.lines {
+		if !response_lines.is_empty() || is_status_line(line) {
+			response_lines.push((line_no, line));
+		} else if let Some(expr) = line.trim_start().strip_prefix("??") {
+			let column = column_of(line, expr.trim_start());
+			matchers.push(Matcher::parse(expr, line_no, column)?);
+		} else if let Some(value) = comment(line).and_then(|text| directive(text, "@delay")) {
+			let column = column_of(line, value);
+			delay = Some(
+				parse_duration(value)
+					.ok_or_else(|| ParseError::new(line_no, column, "invalid delay"))?,
+			);
+		} else if let Some(value) = comment(line).and_then(|text| directive(text, "@fault")) {
+			fault = Some(parse_fault(value).ok_or_else(|| {
+				ParseError::new(
+					line_no,
+					column_of(line, value),
+					"expected `close`, `truncate`, `hang` or `malformed` with an optional probability",
+				)
+			})?);
+		} else if let Some(value) = comment(line).and_then(|text| directive(text, "@auth")) {
+			let column = column_of(line, value);
+			auth = Some((Template::parse(value, line_no, column)?, line_no, column));
+		} else {
+			request_lines.push((line_no, line));
+		}
+	}
+
+	let before = file.requests.len();
+	let first_line = block.lines.first().map_or(1, |(line, _)| *line);
+	parse_block(
+		file,
+		crate::httpfile::parse::Block {
+			title: block.title,
+			lines: request_lines,
+		},
+	)?;
+	let Some(&(status_no, status_line)) = response_lines.first() else {
+		if !matchers.is_empty() || delay.is_some() || fault.is_some() || auth.is_some() {
+			return Err(ParseError::new(first_line, 1, "route without a response"));
+		}
+		return Ok(None);
+	};
+	if file.requests.len() == before {
+		return Err(ParseError::new(
+			status_no,
+			1,
+			"response without a request line",
+		));
+	}
+
+	let mut words = status_line.trim().splitn(3, ' ');
+	words.next();
+	let status = words
+		.next()
+		.and_then(|s| s.parse().ok())
+		.unwrap_or_default();
+	let reason = words.next().unwrap_or_default().trim().to_string();
+	let mut index = 1;
+	let headers = parse_headers(&response_lines, &mut index)?;
+	let body = parse_body(&response_lines[index.min(response_lines.len())..])?;
+	Ok(Some(RoutePart {
+		matchers,
+		response: ResponseDef {
+			line: status_no,
+			status,
+			reason,
+			headers,
+			body,
+		},
+		delay,
+		fault,
+		auth,
+	}))
+}
+
+/// Returns true for `HTTP/1.1 200 OK` style lines.
+fn is_status_line(line: &str) -> bool {
+	let mut words = line.trim().split(' ');
+	let version = words.next().unwrap_or_default();
+	let status = words.next().unwrap_or_default();
+	version.starts_with("HTTP/")
+		&& status.len() == 3
+		&& status.bytes().all(|b| b.is_ascii_digit())
+		&& (100..=999).contains(&status.parse::<u16>().unwrap_or_default())
+}
+
+/// Returns the value of a `# @name value` comment.
+fn directive<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+	let value = text.trim().strip_prefix(name)?;
+	value
+		.starts_with(char::is_whitespace)
+		.then(|| value.trim())
+		.filter(|value| !value.is_empty())
+}
+
+/// Parses `KIND [PROBABILITY]`.
+fn parse_fault(text: &str) -> Option<Fault> {
+	let mut words = text.split_whitespace();
+	let kind = FaultKind::parse(words.next()?)?;
+	let probability = match words.next() {
+		Some(word) => match word.strip_suffix('%') {
+			Some(percent) => percent.parse::<f64>().ok()? / 100.0,
+			None => word.parse().ok()?,
+		},
+		None => 1.0,
+	};
+	if words.next().is_some() || !(0.0..=1.0).contains(&probability) {
+		return None;
+	}
+	Some(Fault { kind, probability })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::url::Url;
+
+	const ROUTES: &str = "\
+@name = widget
+
+### Get item
+GET /items/{id}
+?? query verbose == true
+
+HTTP/1.1 200
+Content-Type: application/json
+
+{\"id\": \"{{id}}\", \"name\": \"{{name}}\"}
+
+### Create
+POST /items?kind=tool
+Content-Type: application/json
+?? json $.name exists
+# @delay 50ms
+
+{\"name\": \"x\"}
+
+HTTP/1.1 201 Made
+Location: /items/1
+
+### Flaky
+ANY /flaky/**
+# @fault close 25%
+
+HTTP/1.1 503
+";
+
+	fn request(method: &str, path: &str, body: &str) -> Request {
+		let mut request = Request::new(method, Url::parse(&format!("http://h{path}")).unwrap());
+		request.body = body.as_bytes().to_vec();
+		request
+	}
+
+	#[test]
+	fn parses_routes() {
+		let file = RouteFile::parse("routes.http", ROUTES, Variables::new()).unwrap();
+		assert_eq!(file.routes.len(), 3);
+		let get = &file.routes[0];
+		assert_eq!(get.request.name.as_deref(), Some("Get item"));
+		assert_eq!(get.matchers[0].text, "query verbose == true");
+		assert_eq!(get.response.status, 200);
+		assert_eq!(get.response.line, 7);
+
+		let create = &file.routes[1];
+		assert_eq!(create.query, [("kind".to_string(), "tool".to_string())]);
+		assert_eq!(create.delay, Some(Duration::from_millis(50)));
+		assert_eq!(create.response.reason, "Made");
+		assert!(create.response.body.is_none());
+
+		let flaky = &file.routes[2];
+		assert_eq!(
+			flaky.fault,
+			Some(Fault {
+				kind: FaultKind::Close,
+				probability: 0.25
+			})
+		);
+	}
+
+	#[test]
+	fn matches_requests() {
+		let file = RouteFile::parse("routes.http", ROUTES, Variables::new()).unwrap();
+		let (route, captures) = file
+			.find(&request("GET", "/items/a%20b?verbose=true", ""))
+			.unwrap();
+		assert_eq!(captures, [("id".to_string(), "a b".to_string())]);
+		let response = file.respond(route, &captures).unwrap();
+		assert_eq!(response.reason, "OK");
+		assert_eq!(
+			response.body_text(),
+			"{\"id\": \"a b\", \"name\": \"widget\"}"
+		);
+
+		assert!(file.find(&request("GET", "/items/1", "")).is_none());
+		assert!(file
+			.find(&request("GET", "/items/1/x?verbose=true", ""))
+			.is_none());
+
+		let mut create = request("POST", "/items?kind=tool&x=1", "{ \"name\" : \"x\" }");
+		assert!(file.find(&create).is_none());
+		create.headers.append("content-type", "application/json");
+		assert_eq!(file.find(&create).unwrap().0.response.status, 201);
+		create.body = b"{\"name\": \"y\"}".to_vec();
+		assert!(file.find(&create).is_none());
+
+		assert_eq!(
+			file.find(&request("DELETE", "/flaky/a/b", ""))
+				.unwrap()
+				.0
+				.response
+				.status,
+			503
+		);
+	}
+
+	#[test]
+	fn matches_path_patterns() {
+		let pattern = PathPattern::parse("/a/*/{b}/**").unwrap();
+		assert_eq!(
+			pattern.matches("/a/x/y/z/w"),
+			Some(vec![("b".to_string(), "y".to_string())])
+		);
+		assert_eq!(
+			pattern.matches("/a/x/y"),
+			Some(vec![("b".to_string(), "y".to_string())])
+		);
+		assert_eq!(pattern.matches("/a/x"), None);
+		assert_eq!(PathPattern::parse("/").unwrap().matches("/"), Some(vec![]));
+		assert!(PathPattern::parse("/**/a").is_err());
+	}
+
+	#[test]
+	fn reports_errors() {
+		let err = RouteFile::parse("r.http", "GET /a\n", Variables::new()).unwrap_err();
+		assert_eq!(err.to_string(), "r.http:1:1: missing response status line");
+
+		let err = RouteFile::parse(
+			"r.http",
+			"GET /a\n# @fault explode\n\nHTTP/1.1 200\n",
+			Variables::new(),
+		)
+		.unwrap_err();
+		assert!(
+			err.to_string().starts_with("r.http:2:10: expected `close`"),
+			"{err}"
+		);
+
+		let err = RouteFile::parse(
+			"r.http",
+			"GET /a\n?? cookie x exists\nHTTP/1.1 200\n",
+			Variables::new(),
+		)
+		.unwrap_err();
+		assert!(
+			err.to_string().contains("unknown matcher `cookie`"),
+			"{err}"
+		);
+
+		let err = RouteFile::parse(
+			"r.http",
+			"GET /a\n# @auth OAuth2 token_url=http://idp/ client_id=x\n\nHTTP/1.1 200\n",
+			Variables::new(),
+		)
+		.unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"r.http:2:9: routes check Bearer tokens, not OAuth2 clients"
+		);
+	}
+
+	#[test]
+	fn parses_auth_directives() {
+		let mut vars = Variables::new();
+		vars.set("password", "secret");
+		let file = RouteFile::parse(
+			"r.http",
+			"GET /a\n# @auth Basic jo {{password}}\n\nHTTP/1.1 200\n",
+			vars,
+		)
+		.unwrap();
+		assert_eq!(
+			file.routes[0].auth,
+			Some(Auth::Basic {
+				user: "jo".into(),
+				password: "secret".into()
+			})
+		);
+	}
+}
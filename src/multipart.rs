@@ -0,0 +1,314 @@
+//! `multipart/form-data` bodies (RFC 7578). Files are streamed from disk
+//! when the body is sent.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::http::Request;
+use crate::upload::Upload;
+use crate::util::random_u64;
+
+/// Content of a part.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PartData {
+	Bytes(Vec<u8>),
+	/// File read while sending.
+	File(PathBuf),
+}
+
+/// Field of a multipart form.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Part {
+	pub name: String,
+	/// File name, sent for file uploads.
+	pub filename: Option<String>,
+	pub content_type: Option<String>,
+	pub data: PartData,
+}
+
+impl Part {
+	pub fn text(name: impl Into<String>, value: impl Into<String>) -> Part {
+		Part {
+			name: name.into(),
+			filename: None,
+			content_type: None,
+			data: PartData::Bytes(value.into().into_bytes()),
+		}
+	}
+
+	/// File upload named after the file, with a content type guessed from
+	/// its extension.
+	pub fn file(name: impl Into<String>, path: impl Into<PathBuf>) -> Part {
+		let path = path.into();
+		let filename = match path.file_name() {
+			Some(filename) => filename.to_string_lossy().into_owned(),
+			None => path.display().to_string(),
+		};
+		Part {
+			name: name.into(),
+			filename: Some(filename),
+			content_type: Some(guess_type(&path).into()),
+			data: PartData::File(path),
+		}
+	}
+
+	/// Parses a form field argument: `NAME=VALUE`, `NAME=@PATH` to upload a
+	/// file or `NAME=<PATH` to send its content as the value, each
+	/// optionally followed by `;type=` and `;filename=`. `@-` and `<-` read
+	/// standard input.
+	pub fn parse(spec: &str) -> Result<Part> {
+		let (name, value) = spec.split_once('=').ok_or_else(|| {
+			Error::Usage(format!("invalid form field `{spec}`, expected NAME=VALUE"))
+		})?;
+		let mut params = value.split(';');
+		let value = params.next().unwrap_or_default();
+		let mut part = match value.strip_prefix('@') {
+			Some("-") => Part {
+				data: PartData::Bytes(crate::cli::read_body_arg("@-")?),
+				..Part::file(name, "-")
+			},
+			Some(path) => Part::file(name, path),
+			None => match value.strip_prefix('<') {
+				Some(path) => Part {
+					data: PartData::Bytes(crate::cli::read_body_arg(&format!("@{path}"))?),
+					..Part::text(name, "")
+				},
+				None => Part::text(name, value),
+			},
+		};
+		for param in params {
+			match param.trim().split_once('=') {
+				Some(("type", content_type)) => part.content_type = Some(content_type.into()),
+				Some(("filename", filename)) => {
+					part.filename = Some(filename.trim_matches('"').into())
+				}
+				_ => {}
+			}
+		}
+		Ok(part)
+	}
+}
+
+/// Form whose parts are separated by a boundary, random unless given.
+#[derive(Clone, Debug)]
+pub struct Multipart {
+	pub boundary: String,
+	pub parts: Vec<Part>,
+}
+
+impl Default for Multipart {
+	fn default() -> Self {
+		Multipart::new()
+	}
+}
+
+impl Multipart {
+	pub fn new() -> Multipart {
+		Multipart {
+			boundary: format!("------------------------{:016x}", random_u64()),
+			parts: Vec::new(),
+		}
+	}
+
+	/// Value of the `Content-Type` header for the body.
+	pub fn content_type(&self) -> String {
+		format!("multipart/form-data; boundary={}", self.boundary)
+	}
+
+	/// Sets the body of a request and its `Content-Type`. The boundary of a
+	/// `Content-Type` header already set is used, and one without a
+	/// boundary gets the form's.
+	pub fn apply(mut self, request: &mut Request) -> Result<()> {
+		match request.headers.get("Content-Type") {
+			Some(given) => match boundary_of(given) {
+				Some(boundary) => self.boundary = boundary,
+				None => {
+					let value = format!("{given}; boundary={}", self.boundary);
+					request.headers.set("Content-Type", value);
+				}
+			},
+			None => request.headers.set("Content-Type", self.content_type()),
+		}
+		request.set_upload(self.upload()?);
+		Ok(())
+	}
+
+	/// Builds the body, referencing the files to stream. The boundary must
+	/// not appear in the parts in memory; the random one cannot be told
+	/// apart from file content in practice.
+	pub fn upload(&self) -> Result<Upload> {
+		if !valid_boundary(&self.boundary) {
+			return Err(Error::Usage(format!(
+				"invalid multipart boundary `{}`, expected 1 to 70 letters, digits or '()+_,-./:=?",
+				self.boundary
+			)));
+		}
+		let delimiter = format!("--{}", self.boundary);
+		let mut body = Upload::new();
+		for part in &self.parts {
+			let mut head = format!(
+				"{delimiter}\r\nContent-Disposition: form-data; name=\"{}\"",
+				escape(&part.name)
+			);
+			if let Some(filename) = &part.filename {
+				head.push_str(&format!("; filename=\"{}\"", escape(filename)));
+			}
+			head.push_str("\r\n");
+			if let Some(content_type) = &part.content_type {
+				head.push_str(&format!("Content-Type: {content_type}\r\n"));
+			}
+			head.push_str("\r\n");
+			body.push_bytes(head.as_bytes());
+			match &part.data {
+				PartData::Bytes(data) => {
+					if contains(data, delimiter.as_bytes()) {
+						return Err(Error::Usage(format!(
+							"form field `{}` contains the boundary `{}`",
+							part.name, self.boundary
+						)));
+					}
+					body.push_bytes(data);
+				}
+				PartData::File(path) => body.push_file(path).map_err(|err| {
+					Error::Usage(format!("cannot read `{}`: {err}", path.display()))
+				})?,
+			}
+			body.push_bytes(b"\r\n");
+		}
+		body.push_bytes(format!("{delimiter}--\r\n").as_bytes());
+		Ok(body)
+	}
+
+	/// Encodes the whole body in memory, reading the files.
+	pub fn encode(&self) -> Result<Vec<u8>> {
+		Ok(self.upload()?.read_all()?)
+	}
+}
+
+/// Boundary parameter of a `Content-Type` value, unquoted.
+pub fn boundary_of(content_type: &str) -> Option<String> {
+	content_type.split(';').skip(1).find_map(|param| {
+		let (name, value) = param.split_once('=')?;
+		name.trim()
+			.eq_ignore_ascii_case("boundary")
+			.then(|| value.trim().trim_matches('"').to_string())
+	})
+}
+
+/// Checks the length and characters of a boundary (RFC 2046, 5.1.1).
+fn valid_boundary(boundary: &str) -> bool {
+	(1..=70).contains(&boundary.len())
+		&& !boundary.ends_with(' ')
+		&& boundary
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || "'()+_,-./:=? ".contains(c))
+}
+
+fn contains(data: &[u8], needle: &[u8]) -> bool {
+	data.windows(needle.len()).any(|window| window == needle)
+}
+
+/// Content type of a file from its extension, `application/octet-stream`
+/// when unknown.
+pub fn guess_type(path: &Path) -> &'static str {
+	let extension = path
+		.extension()
+		.map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+		.unwrap_or_default();
+	match extension.as_str() {
+		"txt" | "log" => "text/plain",
+		"html" | "htm" => "text/html",
+		"css" => "text/css",
+		"csv" => "text/csv",
+		"js" | "mjs" => "text/javascript",
+		"json" => "application/json",
+		"xml" => "application/xml",
+		"yaml" | "yml" => "application/yaml",
+		"pdf" => "application/pdf",
+		"zip" => "application/zip",
+		"gz" => "application/gzip",
+		"png" => "image/png",
+		"jpg" | "jpeg" => "image/jpeg",
+		"gif" => "image/gif",
+		"webp" => "image/webp",
+		"svg" => "image/svg+xml",
+		"mp4" => "video/mp4",
+		"mp3" => "audio/mpeg",
+		_ => "application/octet-stream",
+	}
+}
+
+/// Escapes a quoted parameter the way browsers do (RFC 7578, 4.2).
+fn escape(value: &str) -> String {
+	value
+		.replace('"', "%22")
+		.replace('\r', "%0D")
+		.replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::url::Url;
+
+	#[test]
+	fn encodes_parts() {
+		let mut form = Multipart::new();
+		form.boundary = "XyZ".into();
+		form.parts.push(Part::text("title", "hello"));
+		form.parts.push(Part {
+			name: "file".into(),
+			filename: Some("a \"b\".txt".into()),
+			content_type: Some("text/plain".into()),
+			data: PartData::Bytes(b"data".to_vec()),
+		});
+		assert_eq!(form.content_type(), "multipart/form-data; boundary=XyZ");
+		assert_eq!(
+			String::from_utf8(form.encode().unwrap()).unwrap(),
+			"--XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nhello\r\n\
+			 --XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a %22b%22.txt\"\r\n\
+			 Content-Type: text/plain\r\n\r\ndata\r\n--XyZ--\r\n"
+		);
+
+		form.parts.push(Part::text("quote", "--XyZ--"));
+		let err = form.encode().unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"form field `quote` contains the boundary `XyZ`"
+		);
+		form.boundary = "x".repeat(71);
+		assert!(form.encode().is_err());
+	}
+
+	#[test]
+	fn streams_file_parts() {
+		let path = std::env::temp_dir().join(format!("webcat-part-{}.json", std::process::id()));
+		std::fs::write(&path, b"{}").unwrap();
+		let part = Part::parse(&format!("doc=@{};filename=d.json", path.display())).unwrap();
+		assert_eq!(part.filename.as_deref(), Some("d.json"));
+		assert_eq!(part.content_type.as_deref(), Some("application/json"));
+		let form = Multipart {
+			boundary: "b".into(),
+			parts: vec![part, Part::parse("n=1;type=text/plain").unwrap()],
+		};
+
+		let mut request = Request::new("POST", Url::parse("http://h/").unwrap());
+		request
+			.headers
+			.set("Content-Type", "multipart/mixed; boundary=\"given\"");
+		form.apply(&mut request).unwrap();
+		let upload = request.upload.as_ref().unwrap();
+		assert_eq!(upload.chunks.len(), 3);
+		assert_eq!(
+			String::from_utf8(upload.read_all().unwrap()).unwrap(),
+			"--given\r\nContent-Disposition: form-data; name=\"doc\"; filename=\"d.json\"\r\n\
+			 Content-Type: application/json\r\n\r\n{}\r\n\
+			 --given\r\nContent-Disposition: form-data; name=\"n\"\r\n\
+			 Content-Type: text/plain\r\n\r\n1\r\n--given--\r\n"
+		);
+		std::fs::remove_file(&path).unwrap();
+
+		assert!(Part::parse("novalue").is_err());
+		assert_eq!(boundary_of("multipart/form-data"), None);
+	}
+}
@@ -0,0 +1,304 @@
+//! Request files for the operations of a document.
+
+use serde_json::Value;
+
+use super::schema::example;
+use super::{is_json, Operation, Spec};
+use crate::url::{form_encode, percent_encode};
+
+/// Generated request file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestFileText {
+	/// File name without the `.http` extension, unique within the document.
+	pub name: String,
+	pub text: String,
+}
+
+/// One request file per operation, with example parameters and bodies.
+pub fn generate(spec: &Spec) -> Vec<RequestFileText> {
+	let base_url = spec.server_urls().remove(0);
+	let mut files: Vec<RequestFileText> = Vec::new();
+	for operation in spec.operations() {
+		let mut name = file_name(&operation);
+		let taken = |name: &str| files.iter().any(|file| file.name == name);
+		if taken(&name) {
+			let mut counter = 2;
+			while taken(&format!("{name}_{counter}")) {
+				counter += 1;
+			}
+			name = format!("{name}_{counter}");
+		}
+		let text = request_file(spec, &operation, &base_url);
+		files.push(RequestFileText { name, text });
+	}
+	files
+}
+
+/// Operation id, or method and path, restricted to safe characters.
+fn file_name(operation: &Operation) -> String {
+	let name = match operation.operation_id() {
+		Some(id) => id.to_string(),
+		None => format!(
+			"{}_{}",
+			operation.method.to_ascii_lowercase(),
+			operation.path
+		),
+	};
+	let name: String = name
+		.chars()
+		.map(|c| match c {
+			'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+			_ => '_',
+		})
+		.collect();
+	let name = name.trim_matches('_');
+	if name.is_empty() {
+		"root".into()
+	} else {
+		name.replace("__", "_")
+	}
+}
+
+fn request_file(spec: &Spec, operation: &Operation, base_url: &str) -> String {
+	let mut text = format!("# {} {}", operation.method, operation.path);
+	if let Some(summary) = operation.value.get("summary").and_then(Value::as_str) {
+		text.push_str(&format!(": {}", one_line(summary)));
+	}
+	text.push_str(&format!(
+		"\n# Generated from {}, check responses with `webcat run --openapi {}`\n",
+		spec.source, spec.source
+	));
+	text.push_str(&format!("@baseUrl = {base_url}\n"));
+
+	let mut path = operation.path.to_string();
+	let mut query = Vec::new();
+	let mut headers = Vec::new();
+	let mut cookies = Vec::new();
+	for parameter in operation.parameters(spec) {
+		let Some(name) = parameter.get("name").and_then(Value::as_str) else {
+			continue;
+		};
+		let required = parameter.get("required") == Some(&Value::Bool(true));
+		let value = parameter_example(spec, parameter);
+		match parameter.get("in").and_then(Value::as_str) {
+			Some("path") => {
+				let variable = variable_name(name);
+				text.push_str(&format!("@{variable} = {}\n", percent_encode(&value)));
+				path = path.replace(&format!("{{{name}}}"), &format!("{{{{{variable}}}}}"));
+			}
+			Some("query") if required || has_example(parameter) => {
+				let variable = variable_name(name);
+				text.push_str(&format!("@{variable} = {}\n", percent_encode(&value)));
+				query.push(format!("{}={{{{{variable}}}}}", percent_encode(name)));
+			}
+			Some("header") if required || has_example(parameter) => {
+				headers.push(format!("{name}: {value}"));
+			}
+			Some("cookie") if required || has_example(parameter) => {
+				cookies.push(format!("{name}={value}"));
+			}
+			_ => {}
+		}
+	}
+
+	text.push_str(&format!("\n### {}\n", operation.label()));
+	text.push_str(&format!("{} {{{{baseUrl}}}}{path}\n", operation.method));
+	for (index, pair) in query.iter().enumerate() {
+		let separator = if index == 0 { '?' } else { '&' };
+		text.push_str(&format!("    {separator}{pair}\n"));
+	}
+	if let Some(accept) = response_media_type(spec, operation) {
+		text.push_str(&format!("Accept: {accept}\n"));
+	}
+	for header in headers {
+		text.push_str(&format!("{header}\n"));
+	}
+	if !cookies.is_empty() {
+		text.push_str(&format!("Cookie: {}\n", cookies.join("; ")));
+	}
+	if let Some((content_type, body)) = request_body(spec, operation) {
+		text.push_str(&format!("Content-Type: {content_type}\n\n{body}\n\n"));
+	}
+	if let Some(status) = success_status(operation) {
+		text.push_str(&format!("?? status == {status}\n"));
+	}
+	text
+}
+
+/// Example of a parameter as text, without quotes for strings.
+fn parameter_example(spec: &Spec, parameter: &Value) -> String {
+	let value = parameter
+		.get("example")
+		.cloned()
+		.or_else(|| first_example(parameter))
+		.or_else(|| {
+			let schema = parameter.get("schema")?;
+			Some(example(spec, schema))
+		})
+		.unwrap_or(Value::Null);
+	match value {
+		Value::String(text) => text,
+		Value::Null => String::new(),
+		Value::Array(items) => items
+			.iter()
+			.map(|item| {
+				item.as_str()
+					.map_or_else(|| item.to_string(), str::to_string)
+			})
+			.collect::<Vec<_>>()
+			.join(","),
+		other => other.to_string(),
+	}
+}
+
+fn has_example(parameter: &Value) -> bool {
+	parameter.get("example").is_some()
+		|| parameter.get("examples").is_some()
+		|| parameter.pointer("/schema/example").is_some()
+}
+
+/// Value of the first entry of an `examples` map.
+fn first_example(object: &Value) -> Option<Value> {
+	let examples = object.get("examples")?.as_object()?;
+	examples.values().next()?.get("value").cloned()
+}
+
+/// Variable names may only have letters, digits, `_`, `-` and `.`.
+fn variable_name(name: &str) -> String {
+	name.chars()
+		.map(|c| {
+			if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') {
+				c
+			} else {
+				'_'
+			}
+		})
+		.collect()
+}
+
+/// Lowest documented 2xx status.
+fn success_status(operation: &Operation) -> Option<u16> {
+	let responses = operation.value.get("responses")?.as_object()?;
+	responses
+		.keys()
+		.filter_map(|key| key.parse::<u16>().ok())
+		.filter(|status| (200..300).contains(status))
+		.min()
+}
+
+/// Media type of the success response, JSON preferred.
+fn response_media_type(spec: &Spec, operation: &Operation) -> Option<String> {
+	let responses = operation.value.get("responses")?.as_object()?;
+	let (_, response) = responses
+		.iter()
+		.filter(|(key, _)| key.starts_with('2'))
+		.min_by_key(|(key, _)| key.as_str())?;
+	let content = spec.resolve(response).0.get("content")?.as_object()?;
+	preferred(content.keys().map(String::as_str)).map(str::to_string)
+}
+
+/// Content type and example body of the request, JSON preferred.
+fn request_body(spec: &Spec, operation: &Operation) -> Option<(String, String)> {
+	let body = spec.resolve(operation.value.get("requestBody")?).0;
+	let content = body.get("content")?.as_object()?;
+	let content_type = preferred(content.keys().map(String::as_str))?;
+	let media = &content[content_type];
+	let value = media
+		.get("example")
+		.cloned()
+		.or_else(|| first_example(media))
+		.or_else(|| Some(example(spec, media.get("schema")?)))
+		.unwrap_or(Value::Null);
+
+	let text = if is_json(content_type) {
+		serde_json::to_string_pretty(&value).unwrap_or_default()
+	} else if content_type.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+		let pairs: Vec<(String, String)> = value
+			.as_object()
+			.into_iter()
+			.flatten()
+			.map(|(name, value)| {
+				let value = value
+					.as_str()
+					.map_or_else(|| value.to_string(), str::to_string);
+				(name.clone(), value)
+			})
+			.collect();
+		form_encode(&pairs)
+	} else {
+		match value {
+			Value::String(text) => text,
+			Value::Null => String::new(),
+			other => other.to_string(),
+		}
+	};
+	Some((content_type.to_string(), text))
+}
+
+fn preferred<'a>(mut media_types: impl Iterator<Item = &'a str> + Clone) -> Option<&'a str> {
+	media_types
+		.clone()
+		.find(|media_type| is_json(media_type))
+		.or_else(|| media_types.next())
+}
+
+fn one_line(text: &str) -> String {
+	text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::httpfile::parse;
+	use crate::openapi::tests::spec;
+	use crate::template::Variables;
+
+	#[test]
+	fn generates_request_files() {
+		let files = generate(&spec());
+		let names: Vec<&str> = files.iter().map(|file| file.name.as_str()).collect();
+		assert_eq!(
+			names,
+			["listItems", "createItem", "getItem", "get_items_mine"]
+		);
+		assert_eq!(
+			files[1].text,
+			"# POST /items\n\
+			 # Generated from items.yaml, check responses with `webcat run --openapi items.yaml`\n\
+			 @baseUrl = https://api.example.com/v1\n\
+			 \n\
+			 ### createItem\n\
+			 POST {{baseUrl}}/items\n\
+			 Content-Type: application/json\n\
+			 \n\
+			 {\n  \"id\": 0,\n  \"name\": \"string\",\n  \"tags\": [\n    \"string\"\n  ],\n  \"price\": 0.0\n}\n\
+			 \n\
+			 ?? status == 201\n"
+		);
+
+		// the generated files are valid request files
+		for file in &files {
+			let parsed = parse(&file.name, &file.text).unwrap();
+			let mut variables = Variables::new();
+			parsed.resolve_variables(&mut variables).unwrap();
+			parsed.build(&parsed.requests[0], &variables).unwrap();
+		}
+		let list = parse("list", &files[0].text).unwrap();
+		let mut variables = Variables::new();
+		list.resolve_variables(&mut variables).unwrap();
+		let request = list.build(&list.requests[0], &variables).unwrap();
+		assert_eq!(
+			request.url.to_string(),
+			"https://api.example.com/v1/items?limit=10"
+		);
+		assert_eq!(request.headers.get("Accept"), Some("application/json"));
+		let get = parse("get", &files[2].text).unwrap();
+		let mut variables = Variables::new();
+		get.resolve_variables(&mut variables).unwrap();
+		let request = get.build(&get.requests[0], &variables).unwrap();
+		assert_eq!(
+			request.url.path_only(),
+			"/v1/items/3fa85f64-5717-4562-b3fc-2c963f66afa6"
+		);
+	}
+}
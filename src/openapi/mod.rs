@@ -0,0 +1,643 @@
+//! OpenAPI 3.0 and 3.1 documents: generating request files for their
+//! operations and checking responses against them.
+//!
+//! Responses are matched to an operation by method and path, with the path
+//! of each server stripped first. The status must be documented, either
+//! exactly, as a range like `2XX` or by `default`, the content type must be
+//! one of the documented media types and JSON bodies must match the schema
+//! of that media type.
+
+mod generate;
+mod schema;
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::assert::AssertionResult;
+use crate::error::{Error, ParseError, Result};
+use crate::http::{Request, Response};
+use crate::url::has_scheme;
+
+pub use generate::{generate, RequestFileText};
+pub use schema::SchemaError;
+
+/// Methods that may have an operation in a path item.
+const METHODS: [&str; 8] = [
+	"get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Maximum number of `$ref` indirections followed in a row.
+const MAX_REFS: usize = 32;
+
+/// Parsed OpenAPI document.
+#[derive(Clone, Debug)]
+pub struct Spec {
+	pub source: String,
+	document: Value,
+}
+
+/// Operation of a path item.
+#[derive(Clone, Copy, Debug)]
+pub struct Operation<'a> {
+	/// Upper-case method.
+	pub method: &'static str,
+	/// Path template, e.g. `/items/{id}`.
+	pub path: &'a str,
+	pub value: &'a Value,
+	path_item: &'a Value,
+}
+
+impl<'a> Operation<'a> {
+	pub fn operation_id(&self) -> Option<&str> {
+		self.value.get("operationId").and_then(Value::as_str)
+	}
+
+	/// Operation id, or method and path.
+	pub fn label(&self) -> String {
+		match self.operation_id() {
+			Some(id) => id.to_string(),
+			None => format!("{} {}", self.method, self.path),
+		}
+	}
+
+	/// JSON pointer of the operation in the document.
+	pub fn pointer(&self) -> String {
+		format!(
+			"#/paths/{}/{}",
+			escape_pointer(self.path),
+			self.method.to_ascii_lowercase()
+		)
+	}
+
+	/// Parameters of the path item and the operation, where the operation
+	/// overrides those with the same name and location.
+	fn parameters(&self, spec: &'a Spec) -> Vec<&'a Value> {
+		let mut parameters: Vec<&Value> = Vec::new();
+		for list in [self.path_item, self.value] {
+			let items = list.get("parameters").and_then(Value::as_array);
+			for parameter in items.into_iter().flatten() {
+				let parameter = spec.resolve(parameter).0;
+				let key = |p: &Value| (p.get("name").cloned(), p.get("in").cloned());
+				parameters.retain(|existing| key(existing) != key(parameter));
+				parameters.push(parameter);
+			}
+		}
+		parameters
+	}
+}
+
+impl Spec {
+	pub fn load(path: impl AsRef<Path>) -> Result<Spec> {
+		let path = path.as_ref();
+		let text = std::fs::read_to_string(path)
+			.map_err(|err| Error::Usage(format!("cannot read `{}`: {err}", path.display())))?;
+		Spec::parse(&path.display().to_string(), &text)
+	}
+
+	/// Parses a JSON or YAML document. `source` names it in error messages.
+	pub fn parse(source: &str, text: &str) -> Result<Spec> {
+		let document: Value = if text.trim_start().starts_with('{') {
+			serde_json::from_str(text).map_err(|err| {
+				let error = ParseError::new(err.line(), err.column(), err.to_string());
+				Error::Parse(error.in_source(source))
+			})?
+		} else {
+			serde_yaml::from_str(text).map_err(|err| {
+				let (line, column) = err.location().map_or((1, 1), |at| (at.line(), at.column()));
+				Error::Parse(ParseError::new(line, column, err.to_string()).in_source(source))
+			})?
+		};
+		let version = document.get("openapi").and_then(Value::as_str);
+		match version {
+			Some(version) if version.starts_with("3.") => {}
+			Some(version) => {
+				return Err(Error::Usage(format!(
+					"{source}: unsupported OpenAPI version {version}, expected 3.0 or 3.1"
+				)))
+			}
+			None if document.get("swagger").is_some() => {
+				return Err(Error::Usage(format!(
+					"{source}: Swagger 2.0 is not supported, convert it to OpenAPI 3 first"
+				)))
+			}
+			None => {
+				return Err(Error::Usage(format!(
+					"{source}: not an OpenAPI document, `openapi` is missing"
+				)))
+			}
+		}
+		Ok(Spec {
+			source: source.to_string(),
+			document,
+		})
+	}
+
+	/// Returns true if the text looks like an OpenAPI or Swagger document.
+	pub fn detect(text: &str) -> bool {
+		let start = text.trim_start();
+		if start.starts_with('{') {
+			let head = start.get(..4096).unwrap_or(start);
+			head.contains("\"openapi\"") || head.contains("\"swagger\"")
+		} else {
+			start
+				.lines()
+				.any(|line| line.starts_with("openapi:") || line.starts_with("swagger:"))
+		}
+	}
+
+	pub fn title(&self) -> &str {
+		self.document
+			.pointer("/info/title")
+			.and_then(Value::as_str)
+			.unwrap_or("")
+	}
+
+	/// Operations in document order.
+	pub fn operations(&self) -> Vec<Operation<'_>> {
+		let mut operations = Vec::new();
+		let paths = self.document.get("paths").and_then(Value::as_object);
+		for (path, item) in paths.into_iter().flatten() {
+			let item = self.resolve(item).0;
+			for method in METHODS {
+				if let Some(value) = item.get(method) {
+					operations.push(Operation {
+						method: upper(method),
+						path,
+						value,
+						path_item: item,
+					});
+				}
+			}
+		}
+		operations
+	}
+
+	/// URLs of the servers with their variables set to the defaults. The
+	/// list is never empty, relative URLs are made absolute with
+	/// `http://localhost`.
+	pub fn server_urls(&self) -> Vec<String> {
+		let servers = self.document.get("servers").and_then(Value::as_array);
+		let mut urls: Vec<String> = servers
+			.into_iter()
+			.flatten()
+			.filter_map(|server| {
+				let mut url = server.get("url")?.as_str()?.to_string();
+				let variables = server.get("variables").and_then(Value::as_object);
+				for (name, variable) in variables.into_iter().flatten() {
+					let default = variable.get("default").and_then(Value::as_str);
+					url = url.replace(&format!("{{{name}}}"), default.unwrap_or(""));
+				}
+				if !has_scheme(&url) {
+					url.insert_str(0, "http://localhost");
+				}
+				Some(url.trim_end_matches('/').to_string())
+			})
+			.collect();
+		if urls.is_empty() {
+			urls.push("http://localhost".into());
+		}
+		urls
+	}
+
+	/// Finds the operation for a request. Literal path segments win over
+	/// templated ones.
+	pub fn find(&self, method: &str, path: &str) -> Option<Operation<'_>> {
+		let path = path.split('?').next().unwrap_or(path);
+		let mut candidates = vec![path.to_string()];
+		for url in self.server_urls() {
+			let base = server_path(&url);
+			if let Some(rest) = path.strip_prefix(base).filter(|_| !base.is_empty()) {
+				candidates.push(format!("/{}", rest.trim_start_matches('/')));
+			}
+		}
+		self.operations()
+			.into_iter()
+			.filter(|op| op.method.eq_ignore_ascii_case(method))
+			.filter_map(|op| {
+				let score = candidates
+					.iter()
+					.filter_map(|path| match_template(op.path, path))
+					.max()?;
+				Some((score, op))
+			})
+			.max_by_key(|(score, _)| *score)
+			.map(|(_, op)| op)
+	}
+
+	/// Checks a response against the operation of the request. Returns the
+	/// results of the status, content type and schema checks that apply.
+	pub fn check(&self, request: &Request, response: &Response) -> Vec<AssertionResult> {
+		let path = request.url.path_only();
+		let Some(operation) = self.find(&request.method, path) else {
+			return vec![result(
+				"openapi operation",
+				Err(format!(
+					"no operation for {} {path} in {}",
+					request.method, self.source
+				)),
+			)];
+		};
+		let mut results = Vec::new();
+		let responses = operation.value.get("responses");
+		let status = response.status.to_string();
+		let range = format!("{}XX", &status[..1]);
+		let documented = [status.as_str(), range.as_str(), "default"]
+			.into_iter()
+			.find_map(|key| {
+				let value = responses?.get(key).or_else(|| {
+					// YAML keys may be numbers or use a lower-case range
+					responses?.get(key.to_ascii_lowercase())
+				})?;
+				Some((key, self.resolve(value).0))
+			});
+		let Some((key, documented)) = documented else {
+			results.push(result(
+				&format!("openapi status {status}"),
+				Err(format!(
+					"status {status} is not documented for {}",
+					operation.label()
+				)),
+			));
+			return results;
+		};
+		results.push(result(&format!("openapi status {status}"), Ok(())));
+
+		let content = documented.get("content").and_then(Value::as_object);
+		let content = content.filter(|content| !content.is_empty());
+		let Some(content) = content else {
+			return results;
+		};
+		let header = response.headers.get("Content-Type");
+		let essence = header.map(media_essence);
+		let media = essence.as_deref().and_then(|actual| {
+			content
+				.iter()
+				.filter(|(pattern, _)| media_matches(&media_essence(pattern), actual))
+				// exact types before wildcards
+				.max_by_key(|(pattern, _)| !pattern.contains('*'))
+		});
+		let Some((media_type, media)) = media else {
+			if response.body.is_empty() && header.is_none() {
+				return results;
+			}
+			let expected: Vec<&str> = content.keys().map(String::as_str).collect();
+			results.push(result(
+				"openapi content-type",
+				Err(format!(
+					"content type {} is not documented, expected {}",
+					header.unwrap_or("(none)"),
+					expected.join(" or ")
+				)),
+			));
+			return results;
+		};
+		results.push(result("openapi content-type", Ok(())));
+
+		let Some(schema) = media.get("schema") else {
+			return results;
+		};
+		if !is_json(media_type) {
+			return results;
+		}
+		let document: Value = match serde_json::from_slice(&response.body) {
+			Ok(document) => document,
+			Err(err) => {
+				results.push(result(
+					"openapi schema",
+					Err(format!("body is not valid JSON: {err}")),
+				));
+				return results;
+			}
+		};
+		let schema_path = format!(
+			"{}/responses/{}/content/{}/schema",
+			operation.pointer(),
+			escape_pointer(key),
+			escape_pointer(media_type)
+		);
+		let errors = self.validate(schema, &schema_path, &document);
+		let mut check = result("openapi schema", Ok(()));
+		match errors.as_slice() {
+			[] => {}
+			[error] => {
+				check.passed = false;
+				check.message = error.to_string();
+			}
+			errors => {
+				check.passed = false;
+				check.message = format!("{} schema errors", errors.len());
+				let lines: Vec<String> = errors.iter().map(ToString::to_string).collect();
+				check.diff = Some(lines.join("\n"));
+			}
+		}
+		results.push(check);
+		results
+	}
+
+	/// Validates a value against a schema of the document. `schema_path`
+	/// is the JSON pointer of the schema, used in the errors.
+	pub fn validate(&self, schema: &Value, schema_path: &str, value: &Value) -> Vec<SchemaError> {
+		schema::validate(self, schema, schema_path, value)
+	}
+
+	/// Follows local `$ref`s and returns the target with its pointer, or
+	/// the value itself if it is not a reference. References to other
+	/// documents resolve to an empty schema.
+	fn resolve<'a>(&'a self, mut value: &'a Value) -> (&'a Value, Option<String>) {
+		static EMPTY: Value = Value::Null;
+		let mut pointer = None;
+		for _ in 0..MAX_REFS {
+			let Some(reference) = value.get("$ref").and_then(Value::as_str) else {
+				return (value, pointer);
+			};
+			let Some(target) = reference
+				.strip_prefix('#')
+				.and_then(|path| self.document.pointer(path))
+			else {
+				return (&EMPTY, Some(reference.to_string()));
+			};
+			pointer = Some(reference.to_string());
+			value = target;
+		}
+		(&EMPTY, pointer)
+	}
+}
+
+fn result(assertion: &str, outcome: std::result::Result<(), String>) -> AssertionResult {
+	AssertionResult {
+		assertion: assertion.to_string(),
+		line: 0,
+		passed: outcome.is_ok(),
+		message: outcome.err().unwrap_or_default(),
+		diff: None,
+	}
+}
+
+fn upper(method: &str) -> &'static str {
+	match method {
+		"get" => "GET",
+		"put" => "PUT",
+		"post" => "POST",
+		"delete" => "DELETE",
+		"options" => "OPTIONS",
+		"head" => "HEAD",
+		"patch" => "PATCH",
+		_ => "TRACE",
+	}
+}
+
+/// Path of a server URL, without the trailing slash.
+fn server_path(url: &str) -> &str {
+	let after_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+	after_scheme
+		.find('/')
+		.map_or("", |start| after_scheme[start..].trim_end_matches('/'))
+}
+
+/// Matches a path against a template like `/items/{id}`. Returns the number
+/// of literal segments on success.
+fn match_template(template: &str, path: &str) -> Option<usize> {
+	let templates: Vec<&str> = template.trim_matches('/').split('/').collect();
+	let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+	if templates.len() != segments.len() {
+		return None;
+	}
+	let mut literal = 0;
+	for (template, segment) in templates.iter().zip(&segments) {
+		if template.contains('{') {
+			let prefix = &template[..template.find('{').unwrap_or(0)];
+			let suffix = &template[template.rfind('}').map_or(template.len(), |end| end + 1)..];
+			let fits = segment.len() > prefix.len() + suffix.len()
+				&& segment.starts_with(prefix)
+				&& segment.ends_with(suffix);
+			if !fits {
+				return None;
+			}
+		} else if template != segment {
+			return None;
+		} else {
+			literal += 1;
+		}
+	}
+	Some(literal)
+}
+
+/// Media type without parameters, in lower case.
+fn media_essence(media_type: &str) -> String {
+	let essence = media_type.split(';').next().unwrap_or("");
+	essence.trim().to_ascii_lowercase()
+}
+
+fn media_matches(pattern: &str, actual: &str) -> bool {
+	match pattern.split_once('/') {
+		_ if pattern == actual || pattern == "*/*" => true,
+		Some((kind, "*")) => actual
+			.split_once('/')
+			.is_some_and(|(actual, _)| actual == kind),
+		_ => false,
+	}
+}
+
+pub(crate) fn is_json(media_type: &str) -> bool {
+	let essence = media_essence(media_type);
+	essence == "application/json" || essence.ends_with("+json")
+}
+
+/// Escapes a JSON pointer token (RFC 6901).
+fn escape_pointer(token: &str) -> String {
+	token.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+	use crate::url::Url;
+
+	pub const SPEC: &str = r##"
+openapi: 3.0.3
+info:
+  title: Items
+  version: "1"
+servers:
+  - url: "{scheme}://api.example.com/v1"
+    variables:
+      scheme:
+        default: https
+paths:
+  /items:
+    get:
+      operationId: listItems
+      parameters:
+        - name: limit
+          in: query
+          required: true
+          schema: {type: integer, minimum: 1, example: 10}
+      responses:
+        "200":
+          description: Items
+          content:
+            application/json:
+              schema:
+                type: array
+                items: {$ref: "#/components/schemas/Item"}
+    post:
+      operationId: createItem
+      requestBody:
+        content:
+          application/json:
+            schema: {$ref: "#/components/schemas/Item"}
+      responses:
+        "201": {description: Created}
+        4XX:
+          description: Error
+          content:
+            application/problem+json:
+              schema: {type: object, required: [title]}
+  /items/{id}:
+    parameters:
+      - name: id
+        in: path
+        required: true
+        schema: {type: string, format: uuid}
+    get:
+      operationId: getItem
+      responses:
+        "200":
+          description: Item
+          content:
+            application/json:
+              schema: {$ref: "#/components/schemas/Item"}
+  /items/mine:
+    get:
+      responses:
+        default: {description: Anything}
+components:
+  schemas:
+    Item:
+      type: object
+      required: [id, name]
+      properties:
+        id: {type: integer}
+        name: {type: string, minLength: 1}
+        tags:
+          type: array
+          items: {type: string}
+        price: {type: number, nullable: true}
+      additionalProperties: false
+"##;
+
+	pub fn spec() -> Spec {
+		Spec::parse("items.yaml", SPEC).unwrap()
+	}
+
+	fn response(status: u16, content_type: &str, body: &str) -> Response {
+		let mut response = Response {
+			version: "HTTP/1.1".into(),
+			status,
+			body: body.as_bytes().to_vec(),
+			..Default::default()
+		};
+		if !content_type.is_empty() {
+			response.headers.append("Content-Type", content_type);
+		}
+		response
+	}
+
+	fn check(method: &str, url: &str, response: &Response) -> Vec<(String, bool, String)> {
+		let request = Request::new(method, Url::parse(url).unwrap());
+		spec()
+			.check(&request, response)
+			.into_iter()
+			.map(|r| (r.assertion, r.passed, r.diff.unwrap_or(r.message)))
+			.collect()
+	}
+
+	#[test]
+	fn finds_operations() {
+		let spec = spec();
+		assert_eq!(spec.server_urls(), ["https://api.example.com/v1"]);
+		assert_eq!(spec.operations().len(), 4);
+		assert_eq!(spec.find("GET", "/v1/items").unwrap().label(), "listItems");
+		assert_eq!(spec.find("GET", "/items/abc").unwrap().label(), "getItem");
+		assert_eq!(
+			spec.find("get", "/v1/items/mine").unwrap().label(),
+			"GET /items/mine"
+		);
+		assert!(spec.find("DELETE", "/items").is_none());
+		assert_eq!(
+			spec.find("GET", "/items/1").unwrap().pointer(),
+			"#/paths/~1items~1{id}/get"
+		);
+	}
+
+	#[test]
+	fn checks_responses() {
+		let ok = response(
+			200,
+			"application/json; charset=utf-8",
+			r#"[{"id": 1, "name": "a"}]"#,
+		);
+		assert!(check("GET", "https://api.example.com/v1/items", &ok)
+			.iter()
+			.all(|(_, passed, _)| *passed));
+
+		let bad = response(
+			200,
+			"application/json",
+			r#"[{"id": "1", "name": "", "x": 1}]"#,
+		);
+		let results = check("GET", "https://api.example.com/v1/items", &bad);
+		assert_eq!(
+			results[2],
+			(
+				"openapi schema".into(),
+				false,
+				"$[0].id: expected integer, got string (#/components/schemas/Item/properties/id/type)\n\
+				 $[0].name: expected at least 1 characters, got 0 (#/components/schemas/Item/properties/name/minLength)\n\
+				 $[0].x: property is not allowed (#/components/schemas/Item/additionalProperties)"
+					.into()
+			)
+		);
+
+		let results = check("GET", "http://h/v1/items/1", &response(404, "", ""));
+		assert_eq!(
+			results,
+			[(
+				"openapi status 404".into(),
+				false,
+				"status 404 is not documented for getItem".into()
+			)]
+		);
+		let results = check("POST", "http://h/items", &response(422, "text/html", "<p>"));
+		assert!(results[0].1);
+		assert_eq!(
+			results[1].2,
+			"content type text/html is not documented, expected application/problem+json"
+		);
+		let results = check(
+			"POST",
+			"http://h/items",
+			&response(400, "application/problem+json", "{}"),
+		);
+		assert_eq!(
+			results[2].2,
+			"$: missing required property `title` (#/paths/~1items/post/responses/4XX/content/application~1problem+json/schema/required)"
+		);
+		assert_eq!(
+			check("POST", "http://h/items", &response(201, "", "")).len(),
+			1
+		);
+		assert!(!check("PUT", "http://h/items", &ok)[0].1);
+	}
+
+	#[test]
+	fn rejects_other_documents() {
+		let err = Spec::parse("old.json", r#"{"swagger": "2.0"}"#).unwrap_err();
+		assert!(err.to_string().contains("Swagger 2.0"));
+		let err = Spec::parse("bad.yaml", "openapi: [3").unwrap_err();
+		assert!(matches!(err, Error::Parse(_)), "{err}");
+		assert!(Spec::detect(SPEC));
+		assert!(!Spec::detect(r#"{"log": {"entries": []}}"#));
+	}
+}
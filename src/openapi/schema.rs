@@ -0,0 +1,615 @@
+//! JSON Schema validation and example values for the schema dialects of
+//! OpenAPI 3.0 (`nullable`, boolean `exclusiveMinimum`) and 3.1 (type
+//! lists, `const`, `prefixItems`, numeric `exclusiveMinimum`).
+
+use std::fmt;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use super::Spec;
+
+/// Nested `$ref`s after which example generation leaves out the next one,
+/// to keep examples of large documents small.
+const MAX_EXAMPLE_DEPTH: usize = 8;
+
+/// Mismatch between a value and a schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaError {
+	/// JSON path of the offending value, e.g. `$.items[0].id`.
+	pub path: String,
+	/// JSON pointer of the schema keyword that failed.
+	pub schema_path: String,
+	pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}: {} ({})", self.path, self.message, self.schema_path)
+	}
+}
+
+pub(super) fn validate(
+	spec: &Spec,
+	schema: &Value,
+	schema_path: &str,
+	value: &Value,
+) -> Vec<SchemaError> {
+	let mut validator = Validator {
+		spec,
+		errors: Vec::new(),
+	};
+	validator.check(schema, schema_path, value, "$");
+	validator.errors
+}
+
+struct Validator<'a> {
+	spec: &'a Spec,
+	errors: Vec<SchemaError>,
+}
+
+impl Validator<'_> {
+	fn error(&mut self, path: &str, schema_path: &str, keyword: &str, message: String) {
+		self.errors.push(SchemaError {
+			path: path.to_string(),
+			schema_path: format!("{schema_path}/{keyword}"),
+			message,
+		});
+	}
+
+	/// True if the value matches, without recording errors.
+	fn matches(&self, schema: &Value, schema_path: &str, value: &Value) -> bool {
+		validate(self.spec, schema, schema_path, value).is_empty()
+	}
+
+	fn check(&mut self, schema: &Value, schema_path: &str, value: &Value, path: &str) {
+		let (schema, schema_path) = match self.spec.resolve(schema) {
+			(target, Some(pointer)) => (target, pointer),
+			(target, None) => (target, schema_path.to_string()),
+		};
+		let schema_path = schema_path.as_str();
+		// `true`, `{}` and unresolved references accept everything
+		let Some(keywords) = schema.as_object() else {
+			if schema == &Value::Bool(false) {
+				self.error(path, schema_path, "", "no value is allowed".into());
+			}
+			return;
+		};
+		if value.is_null() && keywords.get("nullable") == Some(&Value::Bool(true)) {
+			return;
+		}
+
+		if let Some(types) = keywords.get("type") {
+			let allowed: Vec<&str> = match types {
+				Value::String(name) => vec![name.as_str()],
+				Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+				_ => Vec::new(),
+			};
+			if !allowed.is_empty() && !allowed.iter().any(|name| has_type(value, name)) {
+				let message = format!(
+					"expected {}, got {}",
+					allowed.join(" or "),
+					type_name(value)
+				);
+				// the other keywords would only repeat the mismatch
+				return self.error(path, schema_path, "type", message);
+			}
+		}
+		if let Some(Value::Array(options)) = keywords.get("enum") {
+			if !options.iter().any(|option| equal(option, value)) {
+				let options: Vec<String> = options.iter().map(Value::to_string).collect();
+				let message = format!("expected one of {}, got {value}", options.join(", "));
+				self.error(path, schema_path, "enum", message);
+			}
+		}
+		if let Some(expected) = keywords.get("const") {
+			if !equal(expected, value) {
+				self.error(
+					path,
+					schema_path,
+					"const",
+					format!("expected {expected}, got {value}"),
+				);
+			}
+		}
+
+		match value {
+			Value::String(text) => self.check_string(keywords, schema_path, text, path),
+			Value::Number(_) => self.check_number(keywords, schema_path, value, path),
+			Value::Array(items) => self.check_array(keywords, schema_path, items, path),
+			Value::Object(object) => self.check_object(keywords, schema_path, object, path),
+			_ => {}
+		}
+
+		if let Some(Value::Array(schemas)) = keywords.get("allOf") {
+			for (index, schema) in schemas.iter().enumerate() {
+				self.check(schema, &format!("{schema_path}/allOf/{index}"), value, path);
+			}
+		}
+		if let Some(Value::Array(schemas)) = keywords.get("anyOf") {
+			let matched = schemas.iter().enumerate().any(|(index, schema)| {
+				self.matches(schema, &format!("{schema_path}/anyOf/{index}"), value)
+			});
+			if !matched {
+				self.error(
+					path,
+					schema_path,
+					"anyOf",
+					"matches none of the schemas".into(),
+				);
+			}
+		}
+		if let Some(Value::Array(schemas)) = keywords.get("oneOf") {
+			let matched = (0..schemas.len())
+				.filter(|&index| {
+					self.matches(
+						&schemas[index],
+						&format!("{schema_path}/oneOf/{index}"),
+						value,
+					)
+				})
+				.count();
+			if matched != 1 {
+				let message = format!("matches {matched} of the schemas instead of exactly one");
+				self.error(path, schema_path, "oneOf", message);
+			}
+		}
+		if let Some(schema) = keywords.get("not") {
+			if self.matches(schema, &format!("{schema_path}/not"), value) {
+				self.error(
+					path,
+					schema_path,
+					"not",
+					"matches a schema it must not".into(),
+				);
+			}
+		}
+	}
+
+	fn check_string(
+		&mut self,
+		keywords: &Map<String, Value>,
+		schema_path: &str,
+		text: &str,
+		path: &str,
+	) {
+		let length = text.chars().count() as u64;
+		if let Some(min) = keywords.get("minLength").and_then(Value::as_u64) {
+			if length < min {
+				let message = format!("expected at least {min} characters, got {length}");
+				self.error(path, schema_path, "minLength", message);
+			}
+		}
+		if let Some(max) = keywords.get("maxLength").and_then(Value::as_u64) {
+			if length > max {
+				let message = format!("expected at most {max} characters, got {length}");
+				self.error(path, schema_path, "maxLength", message);
+			}
+		}
+		if let Some(pattern) = keywords.get("pattern").and_then(Value::as_str) {
+			// invalid patterns are a problem of the document, not the value
+			if Regex::new(pattern).is_ok_and(|regex| !regex.is_match(text)) {
+				let message = format!("{text:?} does not match `{pattern}`");
+				self.error(path, schema_path, "pattern", message);
+			}
+		}
+		if let Some(format) = keywords.get("format").and_then(Value::as_str) {
+			let pattern = match format {
+				"date-time" => r"^\d{4}-\d\d-\d\d[Tt ]\d\d:\d\d:\d\d(\.\d+)?([Zz]|[+-]\d\d:\d\d)$",
+				"date" => r"^\d{4}-\d\d-\d\d$",
+				"uuid" => r"^[0-9a-fA-F]{8}-([0-9a-fA-F]{4}-){3}[0-9a-fA-F]{12}$",
+				"email" => r"^[^@\s]+@[^@\s]+$",
+				_ => return,
+			};
+			if Regex::new(pattern).is_ok_and(|regex| !regex.is_match(text)) {
+				let message = format!("{text:?} is not a valid {format}");
+				self.error(path, schema_path, "format", message);
+			}
+		}
+	}
+
+	fn check_number(
+		&mut self,
+		keywords: &Map<String, Value>,
+		schema_path: &str,
+		value: &Value,
+		path: &str,
+	) {
+		let number = value.as_f64().unwrap_or_default();
+		let bound = |name: &str| keywords.get(name).and_then(Value::as_f64);
+		// 3.0 turns `minimum` exclusive with a boolean, 3.1 has numbers
+		let exclusive = |name: &str| keywords.get(name) == Some(&Value::Bool(true));
+		if let Some(min) = bound("minimum") {
+			if exclusive("exclusiveMinimum") && number <= min {
+				let message = format!("expected more than {min}, got {value}");
+				self.error(path, schema_path, "exclusiveMinimum", message);
+			} else if number < min {
+				let message = format!("expected at least {min}, got {value}");
+				self.error(path, schema_path, "minimum", message);
+			}
+		}
+		if let Some(max) = bound("maximum") {
+			if exclusive("exclusiveMaximum") && number >= max {
+				let message = format!("expected less than {max}, got {value}");
+				self.error(path, schema_path, "exclusiveMaximum", message);
+			} else if number > max {
+				let message = format!("expected at most {max}, got {value}");
+				self.error(path, schema_path, "maximum", message);
+			}
+		}
+		if let Some(min) = bound("exclusiveMinimum") {
+			if number <= min {
+				let message = format!("expected more than {min}, got {value}");
+				self.error(path, schema_path, "exclusiveMinimum", message);
+			}
+		}
+		if let Some(max) = bound("exclusiveMaximum") {
+			if number >= max {
+				let message = format!("expected less than {max}, got {value}");
+				self.error(path, schema_path, "exclusiveMaximum", message);
+			}
+		}
+		if let Some(step) = bound("multipleOf").filter(|&step| step > 0.0) {
+			let quotient = number / step;
+			if (quotient - quotient.round()).abs() > 1e-9 {
+				let message = format!("expected a multiple of {step}, got {value}");
+				self.error(path, schema_path, "multipleOf", message);
+			}
+		}
+	}
+
+	fn check_array(
+		&mut self,
+		keywords: &Map<String, Value>,
+		schema_path: &str,
+		items: &[Value],
+		path: &str,
+	) {
+		let count = items.len() as u64;
+		if let Some(min) = keywords.get("minItems").and_then(Value::as_u64) {
+			if count < min {
+				let message = format!("expected at least {min} items, got {count}");
+				self.error(path, schema_path, "minItems", message);
+			}
+		}
+		if let Some(max) = keywords.get("maxItems").and_then(Value::as_u64) {
+			if count > max {
+				let message = format!("expected at most {max} items, got {count}");
+				self.error(path, schema_path, "maxItems", message);
+			}
+		}
+		if keywords.get("uniqueItems") == Some(&Value::Bool(true)) {
+			for (index, item) in items.iter().enumerate() {
+				if items[..index].iter().any(|other| equal(other, item)) {
+					let message = format!("duplicate of an earlier item: {item}");
+					self.error(
+						&format!("{path}[{index}]"),
+						schema_path,
+						"uniqueItems",
+						message,
+					);
+				}
+			}
+		}
+		let prefix = keywords.get("prefixItems").and_then(Value::as_array);
+		let prefix_len = prefix.map_or(0, Vec::len);
+		for (index, schema) in prefix.into_iter().flatten().enumerate() {
+			if let Some(item) = items.get(index) {
+				let item_schema = format!("{schema_path}/prefixItems/{index}");
+				self.check(schema, &item_schema, item, &format!("{path}[{index}]"));
+			}
+		}
+		if let Some(schema) = keywords.get("items") {
+			for (index, item) in items.iter().enumerate().skip(prefix_len) {
+				let item_schema = format!("{schema_path}/items");
+				self.check(schema, &item_schema, item, &format!("{path}[{index}]"));
+			}
+		}
+	}
+
+	fn check_object(
+		&mut self,
+		keywords: &Map<String, Value>,
+		schema_path: &str,
+		object: &Map<String, Value>,
+		path: &str,
+	) {
+		if let Some(Value::Array(required)) = keywords.get("required") {
+			for name in required.iter().filter_map(Value::as_str) {
+				if !object.contains_key(name) {
+					let message = format!("missing required property `{name}`");
+					self.error(path, schema_path, "required", message);
+				}
+			}
+		}
+		let count = object.len() as u64;
+		if let Some(min) = keywords.get("minProperties").and_then(Value::as_u64) {
+			if count < min {
+				let message = format!("expected at least {min} properties, got {count}");
+				self.error(path, schema_path, "minProperties", message);
+			}
+		}
+		if let Some(max) = keywords.get("maxProperties").and_then(Value::as_u64) {
+			if count > max {
+				let message = format!("expected at most {max} properties, got {count}");
+				self.error(path, schema_path, "maxProperties", message);
+			}
+		}
+		let properties = keywords.get("properties").and_then(Value::as_object);
+		let additional = keywords.get("additionalProperties");
+		for (name, item) in object {
+			let item_path = member_path(path, name);
+			match properties.and_then(|properties| properties.get(name)) {
+				Some(schema) => {
+					let property_schema =
+						format!("{schema_path}/properties/{}", super::escape_pointer(name));
+					self.check(schema, &property_schema, item, &item_path);
+				}
+				None => match additional {
+					Some(Value::Bool(false)) => {
+						let message = "property is not allowed".into();
+						self.error(&item_path, schema_path, "additionalProperties", message);
+					}
+					Some(schema) if schema.is_object() => {
+						let additional_schema = format!("{schema_path}/additionalProperties");
+						self.check(schema, &additional_schema, item, &item_path);
+					}
+					_ => {}
+				},
+			}
+		}
+	}
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+	match name {
+		"null" => value.is_null(),
+		"boolean" => value.is_boolean(),
+		"string" => value.is_string(),
+		"array" => value.is_array(),
+		"object" => value.is_object(),
+		"number" => value.is_number(),
+		"integer" => {
+			value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+		}
+		_ => true,
+	}
+}
+
+fn type_name(value: &Value) -> &'static str {
+	match value {
+		Value::Null => "null",
+		Value::Bool(_) => "boolean",
+		Value::Number(_) if has_type(value, "integer") => "integer",
+		Value::Number(_) => "number",
+		Value::String(_) => "string",
+		Value::Array(_) => "array",
+		Value::Object(_) => "object",
+	}
+}
+
+/// JSON equality where `1` and `1.0` are the same number.
+fn equal(a: &Value, b: &Value) -> bool {
+	match (a, b) {
+		(Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+		(Value::Array(x), Value::Array(y)) => {
+			x.len() == y.len() && x.iter().zip(y).all(|(a, b)| equal(a, b))
+		}
+		(Value::Object(x), Value::Object(y)) => {
+			x.len() == y.len()
+				&& x.iter()
+					.all(|(key, a)| y.get(key).is_some_and(|b| equal(a, b)))
+		}
+		_ => a == b,
+	}
+}
+
+/// Path of an object member in the notation of [`crate::jsonpath`].
+fn member_path(path: &str, name: &str) -> String {
+	let plain = !name.is_empty()
+		&& !name.starts_with(|c: char| c.is_ascii_digit())
+		&& name.chars().all(|c| c.is_alphanumeric() || c == '_');
+	if plain {
+		format!("{path}.{name}")
+	} else {
+		format!("{path}['{name}']")
+	}
+}
+
+/// Example value for a schema: its own example, default, constant or first
+/// enum value if it has one, otherwise a value built from its type.
+pub(super) fn example(spec: &Spec, schema: &Value) -> Value {
+	build_example(spec, schema, &mut Vec::new()).unwrap_or(Value::Null)
+}
+
+/// Example of a schema nested in the `$ref`s of `refs`, or `None` where a
+/// recursive schema refers back to one of them. Objects leave out such
+/// properties and arrays are empty.
+fn build_example(spec: &Spec, schema: &Value, refs: &mut Vec<String>) -> Option<Value> {
+	let (schema, pointer) = spec.resolve(schema);
+	if refs.len() > MAX_EXAMPLE_DEPTH || pointer.as_ref().is_some_and(|p| refs.contains(p)) {
+		return None;
+	}
+	refs.extend(pointer.clone());
+	let value = example_of(spec, schema, refs);
+	if pointer.is_some() {
+		refs.pop();
+	}
+	value
+}
+
+fn example_of(spec: &Spec, schema: &Value, refs: &mut Vec<String>) -> Option<Value> {
+	let given = schema
+		.get("example")
+		.or_else(|| schema.get("examples").and_then(|e| e.get(0)))
+		.or_else(|| schema.get("default"))
+		.or_else(|| schema.get("const"))
+		.or_else(|| schema.get("enum").and_then(|e| e.get(0)));
+	if let Some(given) = given {
+		return Some(given.clone());
+	}
+	if let Some(Value::Array(schemas)) = schema.get("allOf") {
+		let mut merged = Map::new();
+		for schema in schemas {
+			match build_example(spec, schema, refs) {
+				Some(Value::Object(object)) => merged.extend(object),
+				other if schemas.len() == 1 => return other,
+				_ => {}
+			}
+		}
+		return Some(Value::Object(merged));
+	}
+	for key in ["oneOf", "anyOf"] {
+		if let Some(first) = schema.get(key).and_then(|s| s.get(0)) {
+			return build_example(spec, first, refs);
+		}
+	}
+
+	let kind = match schema.get("type") {
+		Some(Value::String(kind)) => kind.as_str(),
+		Some(Value::Array(kinds)) => kinds
+			.iter()
+			.filter_map(Value::as_str)
+			.find(|kind| *kind != "null")
+			.unwrap_or("null"),
+		_ if schema.get("properties").is_some() => "object",
+		_ if schema.get("items").is_some() => "array",
+		_ => "",
+	};
+	let number = |name: &str| schema.get(name).and_then(Value::as_f64);
+	let value = match kind {
+		"object" => {
+			let mut object = Map::new();
+			let properties = schema.get("properties").and_then(Value::as_object);
+			for (name, property) in properties.into_iter().flatten() {
+				if let Some(value) = build_example(spec, property, refs) {
+					object.insert(name.clone(), value);
+				}
+			}
+			Value::Object(object)
+		}
+		"array" => {
+			let items = schema.get("items");
+			Value::Array(
+				items
+					.and_then(|items| build_example(spec, items, refs))
+					.into_iter()
+					.collect(),
+			)
+		}
+		"string" => {
+			let format = schema.get("format").and_then(Value::as_str);
+			Value::String(
+				match format {
+					Some("date-time") => "2024-01-01T00:00:00Z",
+					Some("date") => "2024-01-01",
+					Some("email") => "user@example.com",
+					Some("uuid") => "3fa85f64-5717-4562-b3fc-2c963f66afa6",
+					Some("uri" | "url") => "https://example.com",
+					_ => "string",
+				}
+				.into(),
+			)
+		}
+		"integer" => Value::from(number("minimum").map_or(0, |min| min.ceil() as i64)),
+		"number" => Value::from(number("minimum").unwrap_or(0.0)),
+		"boolean" => Value::Bool(true),
+		_ => Value::Null,
+	};
+	Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::openapi::tests::spec;
+
+	fn errors(schema: Value, value: Value) -> Vec<String> {
+		validate(&spec(), &schema, "#", &value)
+			.iter()
+			.map(ToString::to_string)
+			.collect()
+	}
+
+	#[test]
+	fn validates_keywords() {
+		assert!(errors(json!({"type": ["string", "null"]}), json!(null)).is_empty());
+		assert!(errors(json!({"type": "integer"}), json!(2.0)).is_empty());
+		assert_eq!(
+			errors(json!({"type": "string", "nullable": false}), json!(null)),
+			["$: expected string, got null (#/type)"]
+		);
+		assert_eq!(
+			errors(json!({"enum": ["a", "b"]}), json!("c")),
+			[r#"$: expected one of "a", "b", got "c" (#/enum)"#]
+		);
+		assert_eq!(
+			errors(
+				json!({"minimum": 1, "exclusiveMaximum": true, "maximum": 5}),
+				json!(5)
+			),
+			["$: expected less than 5, got 5 (#/exclusiveMaximum)"]
+		);
+		assert_eq!(
+			errors(
+				json!({"exclusiveMinimum": 0, "multipleOf": 0.5}),
+				json!(0.3)
+			),
+			["$: expected a multiple of 0.5, got 0.3 (#/multipleOf)"]
+		);
+		assert_eq!(
+			errors(json!({"pattern": "^a+$", "format": "date"}), json!("b")),
+			[
+				r#"$: "b" does not match `^a+$` (#/pattern)"#,
+				r#"$: "b" is not a valid date (#/format)"#
+			]
+		);
+		assert_eq!(
+			errors(
+				json!({"prefixItems": [{"type": "string"}], "items": {"type": "integer"}, "uniqueItems": true}),
+				json!(["a", 1, 1])
+			),
+			["$[2]: duplicate of an earlier item: 1 (#/uniqueItems)"]
+		);
+		assert_eq!(
+			errors(
+				json!({"oneOf": [{"type": "integer"}, {"type": "number"}]}),
+				json!(1)
+			),
+			["$: matches 2 of the schemas instead of exactly one (#/oneOf)"]
+		);
+		assert_eq!(
+			errors(
+				json!({"additionalProperties": {"type": "string"}}),
+				json!({"my-key": 1})
+			),
+			["$['my-key']: expected string, got integer (#/additionalProperties/type)"]
+		);
+		assert_eq!(
+			errors(
+				json!({"$ref": "#/components/schemas/Item"}),
+				json!({"id": 1})
+			),
+			["$: missing required property `name` (#/components/schemas/Item/required)"]
+		);
+	}
+
+	#[test]
+	fn builds_examples() {
+		let spec = spec();
+		let item = json!({"$ref": "#/components/schemas/Item"});
+		assert_eq!(
+			example(&spec, &item),
+			json!({"id": 0, "name": "string", "tags": ["string"], "price": 0.0})
+		);
+		let schema = json!({"allOf": [{"properties": {"a": {"type": "boolean"}}}, {"properties": {"b": {"enum": [3]}}}]});
+		assert_eq!(example(&spec, &schema), json!({"a": true, "b": 3}));
+		let schema = json!({"type": ["null", "string"], "format": "uuid"});
+		assert_eq!(
+			example(&spec, &schema),
+			json!("3fa85f64-5717-4562-b3fc-2c963f66afa6")
+		);
+	}
+}
@@ -0,0 +1,103 @@
+//! Certificate authority issuing the certificates used to intercept HTTPS.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, Issuer, KeyPair, KeyUsagePurpose};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::ServerConfig;
+
+use crate::error::{Error, Result};
+
+/// Common name of the generated CA.
+const CA_NAME: &str = "webcat recording CA";
+
+/// Locally generated CA. Its certificate must be trusted by the clients
+/// whose HTTPS traffic is intercepted.
+pub struct Ca {
+	issuer: Issuer<'static, KeyPair>,
+	cert: CertificateDer<'static>,
+	/// Server configurations by host name.
+	configs: Mutex<HashMap<String, Arc<ServerConfig>>>,
+}
+
+impl Ca {
+	/// Loads the CA from `webcat-ca.pem` and `webcat-ca.key` in `dir`,
+	/// generating both files first if they do not exist.
+	pub fn load_or_create(dir: &Path) -> Result<Ca> {
+		let (cert_path, key_path) = Ca::paths(dir);
+		let tls_error = |err: rcgen::Error| Error::Tls(format!("cannot create CA: {err}"));
+		if !cert_path.exists() || !key_path.exists() {
+			let key = KeyPair::generate().map_err(tls_error)?;
+			let cert = ca_params().self_signed(&key).map_err(tls_error)?;
+			std::fs::create_dir_all(dir)?;
+			std::fs::write(&cert_path, cert.pem())?;
+			std::fs::write(&key_path, key.serialize_pem())?;
+		}
+
+		let read = |path: &PathBuf| {
+			std::fs::read_to_string(path)
+				.map_err(|err| Error::Usage(format!("cannot read `{}`: {err}", path.display())))
+		};
+		let key = KeyPair::from_pem(&read(&key_path)?).map_err(|err| {
+			Error::Tls(format!("invalid CA key in `{}`: {err}", key_path.display()))
+		})?;
+		let cert = CertificateDer::from_pem_slice(read(&cert_path)?.as_bytes()).map_err(|err| {
+			Error::Tls(format!(
+				"invalid CA certificate in `{}`: {err}",
+				cert_path.display()
+			))
+		})?;
+		// certificates only refer to the CA by its name and key, so the
+		// parameters it was created with are enough to issue new ones
+		Ok(Ca {
+			issuer: Issuer::new(ca_params(), key),
+			cert,
+			configs: Mutex::new(HashMap::new()),
+		})
+	}
+
+	/// Certificate and key file of the CA in `dir`.
+	pub fn paths(dir: &Path) -> (PathBuf, PathBuf) {
+		(dir.join("webcat-ca.pem"), dir.join("webcat-ca.key"))
+	}
+
+	/// Server configuration presenting a certificate for `host`, issued on
+	/// first use.
+	pub fn server_config(&self, host: &str) -> Result<Arc<ServerConfig>> {
+		let mut configs = self.configs.lock().unwrap_or_else(|err| err.into_inner());
+		if let Some(config) = configs.get(host) {
+			return Ok(config.clone());
+		}
+		let tls_error = |err: rcgen::Error| Error::Tls(format!("cannot issue certificate: {err}"));
+		let key = KeyPair::generate().map_err(tls_error)?;
+		let mut params = CertificateParams::new(vec![host.to_string()]).map_err(tls_error)?;
+		params.distinguished_name.push(DnType::CommonName, host);
+		let cert = params.signed_by(&key, &self.issuer).map_err(tls_error)?;
+
+		let provider = Arc::new(rustls::crypto::ring::default_provider());
+		let mut config = ServerConfig::builder_with_provider(provider)
+			.with_safe_default_protocol_versions()
+			.map_err(|err| Error::Tls(err.to_string()))?
+			.with_no_client_auth()
+			.with_single_cert(
+				vec![cert.der().clone(), self.cert.clone()],
+				PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der())),
+			)
+			.map_err(|err| Error::Tls(err.to_string()))?;
+		config.alpn_protocols = vec![b"http/1.1".to_vec()];
+		let config = Arc::new(config);
+		configs.insert(host.to_string(), config.clone());
+		Ok(config)
+	}
+}
+
+fn ca_params() -> CertificateParams {
+	let mut params = CertificateParams::default();
+	params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+	params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+	params.distinguished_name.push(DnType::CommonName, CA_NAME);
+	params
+}
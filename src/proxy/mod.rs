@@ -0,0 +1,306 @@
+//! Recording forward proxy.
+//!
+//! Plain HTTP requests are forwarded with a [`Client`] and recorded with a
+//! [`Recorder`]. `CONNECT` requests are tunnelled unless a [`Ca`] is given,
+//! in which case the proxy terminates TLS with a certificate issued for the
+//! requested host and records the requests inside the tunnel as well.
+
+mod ca;
+
+use std::fmt;
+use std::io::{self, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
+
+use rustls::{ServerConnection, StreamOwned};
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::h1;
+use crate::http::{Headers, Response};
+use crate::recording::Recorder;
+use crate::url::Url;
+
+pub use ca::Ca;
+
+/// Headers that only apply to a single connection (RFC 9110, 7.6.1).
+const HOP_BY_HOP: [&str; 9] = [
+	"Connection",
+	"Keep-Alive",
+	"Proxy-Connection",
+	"Proxy-Authenticate",
+	"Proxy-Authorization",
+	"TE",
+	"Trailer",
+	"Transfer-Encoding",
+	"Upgrade",
+];
+
+/// Summary of a proxied request, passed to the log callback.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+	/// Number of the recorded exchange.
+	pub index: Option<usize>,
+	pub method: String,
+	pub url: String,
+	pub outcome: Outcome,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+	Status(u16),
+	/// `CONNECT` tunnel relaying encrypted traffic.
+	Tunnel,
+	Error(String),
+}
+
+impl fmt::Display for Entry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.index {
+			Some(index) => write!(f, "{index:03} ")?,
+			None => write!(f, "    ")?,
+		}
+		write!(f, "{} {} -> ", self.method, self.url)?;
+		match &self.outcome {
+			Outcome::Status(status) => write!(f, "{status}"),
+			Outcome::Tunnel => write!(f, "tunnel"),
+			Outcome::Error(message) => write!(f, "error: {message}"),
+		}
+	}
+}
+
+pub struct Proxy {
+	listener: TcpListener,
+	shared: Arc<Shared>,
+}
+
+struct Shared {
+	client: Client,
+	recorder: Recorder,
+	ca: Option<Ca>,
+}
+
+impl Proxy {
+	/// Creates a proxy sending requests with `client`. HTTPS is only
+	/// intercepted when a CA is given. Responses are relayed with their
+	/// bodies as sent, so the client does not decode them.
+	pub fn bind(
+		addr: impl ToSocketAddrs,
+		mut client: Client,
+		recorder: Recorder,
+		ca: Option<Ca>,
+	) -> Result<Proxy> {
+		client.decode = false;
+		Ok(Proxy {
+			listener: TcpListener::bind(addr)?,
+			shared: Arc::new(Shared {
+				client,
+				recorder,
+				ca,
+			}),
+		})
+	}
+
+	pub fn local_addr(&self) -> Result<SocketAddr> {
+		Ok(self.listener.local_addr()?)
+	}
+
+	/// Serves connections until the listener fails, each on its own thread.
+	/// `log` is called for every request.
+	pub fn run(self, log: impl Fn(&Entry) + Send + Sync + 'static) -> Result<()> {
+		let log = Arc::new(log);
+		for stream in self.listener.incoming() {
+			let stream = stream?;
+			let shared = Arc::clone(&self.shared);
+			let log = Arc::clone(&log);
+			thread::spawn(move || {
+				// errors only affect this connection
+				let _ = handle(stream, &shared, &*log);
+			});
+		}
+		Ok(())
+	}
+}
+
+fn handle(stream: TcpStream, shared: &Shared, log: &dyn Fn(&Entry)) -> Result<()> {
+	let mut reader = BufReader::new(stream);
+	let Some(target) = serve(&mut reader, None, shared, log)? else {
+		return Ok(());
+	};
+	let mut entry = Entry {
+		index: None,
+		method: "CONNECT".into(),
+		url: target.authority(),
+		outcome: Outcome::Tunnel,
+	};
+
+	let Some(ca) = &shared.ca else {
+		return tunnel(reader, &target, entry, log);
+	};
+	let config = match ca.server_config(&target.host) {
+		Ok(config) => config,
+		Err(err) => {
+			entry.outcome = Outcome::Error(err.to_string());
+			log(&entry);
+			let response = Response::text(502, &err.to_string());
+			return h1::write_response(reader.get_mut(), &response, false);
+		}
+	};
+	reader
+		.get_mut()
+		.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")?;
+	let connection = ServerConnection::new(config).map_err(|err| Error::Tls(err.to_string()))?;
+	// the client waits for the response before its handshake, so nothing
+	// is left in the buffer
+	let tls = StreamOwned::new(connection, reader.into_inner());
+	serve(&mut BufReader::new(tls), Some(&target), shared, log)?;
+	Ok(())
+}
+
+/// Forwards requests until the connection is closed. Returns the target of
+/// a `CONNECT` request, which ends the exchange of plain requests. Inside
+/// an intercepted tunnel `origin` is the host given to `CONNECT`.
+fn serve<S: Read + Write>(
+	reader: &mut BufReader<S>,
+	origin: Option<&Url>,
+	shared: &Shared,
+	log: &dyn Fn(&Entry),
+) -> Result<Option<Url>> {
+	loop {
+		let mut request = match h1::read_request(reader) {
+			Ok(Some(request)) => request,
+			Ok(None) => return Ok(None),
+			Err(Error::TooLarge(message)) => {
+				let response = Response::text(413, &message);
+				h1::write_response(reader.get_mut(), &response, false)?;
+				return Ok(None);
+			}
+			Err(Error::Protocol(message)) => {
+				let response = Response::text(400, &message);
+				h1::write_response(reader.get_mut(), &response, false)?;
+				return Ok(None);
+			}
+			Err(err) => return Err(err),
+		};
+		if request.method == "CONNECT" {
+			if origin.is_some() {
+				let response = Response::text(405, "nested CONNECT is not supported");
+				h1::write_response(reader.get_mut(), &response, false)?;
+				return Ok(None);
+			}
+			return Ok(Some(request.url));
+		}
+		if let Some(origin) = origin {
+			request.url = Url {
+				path: request.url.path,
+				..origin.clone()
+			};
+		}
+		let close = ["Connection", "Proxy-Connection"].iter().any(|name| {
+			request
+				.headers
+				.get(name)
+				.is_some_and(|value| value.eq_ignore_ascii_case("close"))
+		});
+		strip_hop_by_hop(&mut request.headers);
+
+		let mut entry = Entry {
+			index: None,
+			method: request.method.clone(),
+			url: request.url.to_string(),
+			outcome: Outcome::Error(String::new()),
+		};
+		let mut response = match shared.client.send(&request) {
+			Ok(response) => {
+				entry.outcome = Outcome::Status(response.status);
+				match shared.recorder.record(&request, &response) {
+					Ok(index) => entry.index = Some(index),
+					Err(err) => entry.outcome = Outcome::Error(format!("not recorded: {err}")),
+				}
+				response
+			}
+			Err(err) => {
+				entry.outcome = Outcome::Error(err.to_string());
+				Response::text(502, &err.to_string())
+			}
+		};
+		log(&entry);
+
+		// the body was read completely, so it is sent with its length
+		if response.headers.contains("Transfer-Encoding") {
+			response.headers.remove("Content-Length");
+		}
+		strip_hop_by_hop(&mut response.headers);
+		h1::write_response(reader.get_mut(), &response, request.method == "HEAD")?;
+		if close {
+			return Ok(None);
+		}
+	}
+}
+
+/// Relays bytes in both directions until either side closes.
+fn tunnel(
+	reader: BufReader<TcpStream>,
+	target: &Url,
+	mut entry: Entry,
+	log: &dyn Fn(&Entry),
+) -> Result<()> {
+	let mut client = reader.into_inner();
+	let upstream = match TcpStream::connect((target.host.as_str(), target.port)) {
+		Ok(upstream) => upstream,
+		Err(err) => {
+			entry.outcome = Outcome::Error(err.to_string());
+			log(&entry);
+			return h1::write_response(&mut client, &Response::text(502, &err.to_string()), false);
+		}
+	};
+	log(&entry);
+	client.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")?;
+
+	let (mut from_client, mut to_upstream) = (client.try_clone()?, upstream.try_clone()?);
+	let forward = thread::spawn(move || {
+		let _ = io::copy(&mut from_client, &mut to_upstream);
+		let _ = to_upstream.shutdown(Shutdown::Write);
+	});
+	let (mut from_upstream, mut to_client) = (upstream, client);
+	let _ = io::copy(&mut from_upstream, &mut to_client);
+	let _ = to_client.shutdown(Shutdown::Write);
+	let _ = forward.join();
+	Ok(())
+}
+
+/// Removes hop-by-hop headers, including those listed in `Connection`.
+fn strip_hop_by_hop(headers: &mut Headers) {
+	let listed: Vec<String> = headers
+		.get_all("Connection")
+		.flat_map(|value| value.split(','))
+		.map(|name| name.trim().to_string())
+		.collect();
+	for name in HOP_BY_HOP
+		.iter()
+		.copied()
+		.chain(listed.iter().map(String::as_str))
+	{
+		headers.remove(name);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn strips_hop_by_hop_headers() {
+		let mut headers: Headers = [
+			("Connection", "keep-alive, X-Trace"),
+			("X-Trace", "1"),
+			("Proxy-Authorization", "Basic eA=="),
+			("Accept", "*/*"),
+		]
+		.into_iter()
+		.collect();
+		strip_hop_by_hop(&mut headers);
+		assert_eq!(headers.iter().collect::<Vec<_>>(), [("Accept", "*/*")]);
+	}
+}
@@ -0,0 +1,193 @@
+//! Recordings of exchanges as request files and response snapshots, as
+//! written by the recording proxy and by imports.
+
+use std::borrow::Cow;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::Result;
+use crate::http::{Request, Response};
+use crate::util::{format_rfc3339, unix_time};
+
+/// Request headers left out of recordings, the client sets them itself.
+const CLIENT_HEADERS: [&str; 3] = ["Host", "Content-Length", "Connection"];
+
+/// Appends exchanges to `session.http` in the recording directory. Each
+/// request asserts the recorded status and refers to the full response in
+/// `responses/NNN.http`. Bodies that cannot be written inline go to
+/// `bodies/NNN.bin`.
+pub struct Recorder {
+	dir: PathBuf,
+	/// Number of the next exchange.
+	next: Mutex<usize>,
+}
+
+impl Recorder {
+	/// Creates the directory if needed. Numbering continues after the
+	/// responses already recorded there.
+	pub fn create(dir: impl AsRef<Path>) -> Result<Recorder> {
+		let dir = dir.as_ref().to_path_buf();
+		std::fs::create_dir_all(dir.join("responses"))?;
+		let recorded = std::fs::read_dir(dir.join("responses"))?.count();
+		Ok(Recorder {
+			dir,
+			next: Mutex::new(recorded + 1),
+		})
+	}
+
+	pub fn session_path(&self) -> PathBuf {
+		self.dir.join("session.http")
+	}
+
+	/// Records an exchange received now and returns its number.
+	pub fn record(&self, request: &Request, response: &Response) -> Result<usize> {
+		let note = format!("recorded {}", format_rfc3339(unix_time()));
+		self.write(request, Some(response), &note)
+	}
+
+	/// Records an exchange with a `note` on its origin, such as when it was
+	/// recorded. Without a response only the request is written.
+	pub fn write(
+		&self,
+		request: &Request,
+		response: Option<&Response>,
+		note: &str,
+	) -> Result<usize> {
+		let mut next = self.next.lock().unwrap_or_else(|err| err.into_inner());
+		let index = *next;
+		let id = format!("{index:03}");
+
+		let mut block = format!("### {id}\n");
+		match response {
+			Some(response) => {
+				let snapshot = format!("responses/{id}.http");
+				std::fs::write(self.dir.join(&snapshot), snapshot_bytes(response))?;
+				block.push_str(&format!(
+					"# {} {} -> {}, {note}\n# response: {snapshot}\n",
+					request.method, request.url, response.status
+				));
+				block.push_str(&format!(
+					"{} {}\n?? status == {}\n",
+					request.method, request.url, response.status
+				));
+			}
+			None => block.push_str(&format!("# {note}\n{} {}\n", request.method, request.url)),
+		}
+		for (name, value) in request.headers.iter() {
+			if !CLIENT_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+				block.push_str(&format!("{name}: {value}\n"));
+			}
+		}
+		let body = match &request.upload {
+			Some(upload) => Cow::Owned(upload.read_all()?),
+			None => Cow::Borrowed(&request.body[..]),
+		};
+		if !body.is_empty() {
+			block.push('\n');
+			match inline_body(&body) {
+				Some(text) => block.push_str(text),
+				None => {
+					let path = format!("bodies/{id}.bin");
+					std::fs::create_dir_all(self.dir.join("bodies"))?;
+					std::fs::write(self.dir.join(&path), &body)?;
+					block.push_str(&format!("< {path}"));
+				}
+			}
+			block.push('\n');
+		}
+		block.push('\n');
+
+		OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(self.session_path())?
+			.write_all(block.as_bytes())?;
+		*next += 1;
+		Ok(index)
+	}
+}
+
+/// Status line, headers and the body as received.
+fn snapshot_bytes(response: &Response) -> Vec<u8> {
+	let mut out = format!("{}\n", response.status_line());
+	for (name, value) in response.headers.iter() {
+		out.push_str(&format!("{name}: {value}\n"));
+	}
+	out.push('\n');
+	let mut out = out.into_bytes();
+	out.extend_from_slice(&response.body);
+	out
+}
+
+/// Returns the body as text if the request file parser reads it back
+/// unchanged: valid UTF-8 without placeholders, separators, assertion or
+/// file reference lines and without surrounding blank lines.
+fn inline_body(body: &[u8]) -> Option<&str> {
+	let text = std::str::from_utf8(body).ok()?;
+	let safe = !text.contains("{{")
+		&& !text.contains('\r')
+		&& text.trim() == text
+		&& text.lines().all(|line| {
+			let line = line.trim_start();
+			!line.starts_with("###") && !line.starts_with("??") && !line.starts_with('<')
+		});
+	safe.then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::httpfile::{BodyDef, RequestFile};
+	use crate::template::Variables;
+	use crate::url::Url;
+
+	#[test]
+	fn writes_replayable_requests() {
+		let dir = std::env::temp_dir().join(format!("webcat-record-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		let recorder = Recorder::create(&dir).unwrap();
+
+		let mut request = Request::new("POST", Url::parse("http://api.local/items?x=1").unwrap());
+		request.headers.append("Host", "api.local");
+		request.headers.append("Content-Type", "application/json");
+		request.body = br#"{"name": "widget"}"#.to_vec();
+		let response = Response {
+			version: "HTTP/1.1".into(),
+			status: 201,
+			reason: "Created".into(),
+			headers: [("Location", "/items/1")].into_iter().collect(),
+			body: b"{}".to_vec(),
+			..Default::default()
+		};
+		assert_eq!(recorder.record(&request, &response).unwrap(), 1);
+		request.body = vec![0, 159, 146, 150];
+		assert_eq!(recorder.record(&request, &response).unwrap(), 2);
+
+		let file = RequestFile::load(recorder.session_path()).unwrap();
+		assert_eq!(file.requests.len(), 2);
+		let first = file.find("001").unwrap();
+		assert_eq!(first.assertions[0].text, "status == 201");
+		let replay = file.build(first, &Variables::new()).unwrap();
+		assert_eq!(replay.url, request.url);
+		assert_eq!(replay.body, br#"{"name": "widget"}"#);
+		assert_eq!(replay.headers.len(), 1);
+		assert!(matches!(
+			&file.find("002").unwrap().body,
+			Some(BodyDef::File { path, .. }) if path == "bodies/002.bin"
+		));
+		let replay = file
+			.build(file.find("002").unwrap(), &Variables::new())
+			.unwrap();
+		assert_eq!(replay.body, request.body);
+		assert_eq!(
+			std::fs::read_to_string(dir.join("responses/001.http")).unwrap(),
+			"HTTP/1.1 201 Created\nLocation: /items/1\n\n{}"
+		);
+
+		// numbering continues in an existing recording
+		let recorder = Recorder::create(&dir).unwrap();
+		assert_eq!(recorder.record(&request, &response).unwrap(), 3);
+	}
+}
@@ -0,0 +1,230 @@
+//! Redirect following: which responses are followed, how the next request
+//! is derived from the previous one, and the trace of the hops.
+
+use std::fmt;
+
+use crate::http::{Request, Response};
+use crate::timing::Timings;
+use crate::url::Url;
+
+/// Headers describing a body, removed along with it.
+const BODY_HEADERS: [&str; 4] = [
+	"Content-Type",
+	"Content-Length",
+	"Content-Encoding",
+	"Transfer-Encoding",
+];
+
+/// Headers carrying credentials, only sent to the origin they were given
+/// for.
+const CREDENTIAL_HEADERS: [&str; 2] = ["Authorization", "Cookie"];
+
+/// Which redirects are followed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RedirectPolicy {
+	/// Redirects followed before giving up.
+	pub max_hops: usize,
+	/// Refuses redirects to another scheme, host or port.
+	pub same_origin: bool,
+	/// Refuses redirects from https to http.
+	pub no_downgrade: bool,
+}
+
+impl Default for RedirectPolicy {
+	fn default() -> Self {
+		RedirectPolicy {
+			max_hops: 10,
+			same_origin: false,
+			no_downgrade: false,
+		}
+	}
+}
+
+/// A response that redirected, with the request that got it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hop {
+	pub method: String,
+	pub url: Url,
+	pub status: u16,
+	/// `Location` header as sent.
+	pub location: String,
+	pub timing: Timings,
+}
+
+impl Hop {
+	pub fn new(request: &Request, response: &Response) -> Hop {
+		Hop {
+			method: request.method.clone(),
+			url: request.url.clone(),
+			status: response.status,
+			location: response.headers.get("Location").unwrap_or_default().into(),
+			timing: response.timing,
+		}
+	}
+}
+
+impl fmt::Display for Hop {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"* {} {} {} ({:.1} ms) -> {}",
+			self.status,
+			self.method,
+			self.url,
+			self.timing.total().as_secs_f64() * 1000.0,
+			self.location
+		)
+	}
+}
+
+impl RedirectPolicy {
+	/// Request following a redirect response, `None` when the response is
+	/// not a redirect to follow. `followed` is the number of redirects
+	/// followed before. Fails when the policy refuses the redirect.
+	pub fn next(
+		&self,
+		request: &Request,
+		response: &Response,
+		followed: usize,
+	) -> Result<Option<Request>, String> {
+		if !matches!(response.status, 301 | 302 | 303 | 307 | 308) {
+			return Ok(None);
+		}
+		let Some(location) = response.headers.get("Location") else {
+			return Ok(None);
+		};
+		if followed >= self.max_hops {
+			return Err(format!("more than {} redirects", self.max_hops));
+		}
+		let url = request
+			.url
+			.join(location)
+			.map_err(|err| format!("invalid `Location: {location}`: {err}"))?;
+		if self.same_origin && !url.same_origin(&request.url) {
+			return Err(format!("redirect to another origin, {url}"));
+		}
+		if self.no_downgrade && request.url.is_https() && !url.is_https() {
+			return Err(format!("redirect from https to http, {url}"));
+		}
+
+		let mut next = request.clone();
+		// the method becomes GET, as browsers do, except after 307 and 308
+		let to_get = match response.status {
+			303 => request.method != "HEAD",
+			301 | 302 => request.method == "POST",
+			_ => false,
+		};
+		if to_get {
+			next.method = "GET".into();
+			next.body.clear();
+			next.upload = None;
+			for name in BODY_HEADERS {
+				next.headers.remove(name);
+			}
+		}
+		if !url.same_origin(&request.url) {
+			for name in CREDENTIAL_HEADERS {
+				next.headers.remove(name);
+			}
+		}
+		// the previous URL may be on another host
+		next.headers.remove("Host");
+		next.url = url;
+		Ok(Some(next))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn redirect(status: u16, location: &str) -> Response {
+		Response {
+			status,
+			headers: [("Location", location)].into_iter().collect(),
+			..Default::default()
+		}
+	}
+
+	fn post() -> Request {
+		let mut request = Request::new("POST", Url::parse("https://a.test/form").unwrap());
+		request.headers.set("Content-Type", "text/plain");
+		request.headers.set("Authorization", "Bearer t");
+		request.body = b"data".to_vec();
+		request
+	}
+
+	#[test]
+	fn rewrites_method_and_body() {
+		let policy = RedirectPolicy::default();
+		let next = policy
+			.next(&post(), &redirect(303, "/done"), 0)
+			.unwrap()
+			.unwrap();
+		assert_eq!(next.method, "GET");
+		assert_eq!(next.url.to_string(), "https://a.test/done");
+		assert!(next.body.is_empty() && !next.headers.contains("Content-Type"));
+		assert_eq!(next.headers.get("Authorization"), Some("Bearer t"));
+
+		let next = policy
+			.next(&post(), &redirect(302, "x"), 0)
+			.unwrap()
+			.unwrap();
+		assert_eq!(next.method, "GET");
+		let next = policy
+			.next(&post(), &redirect(307, "x"), 0)
+			.unwrap()
+			.unwrap();
+		assert_eq!(
+			(next.method.as_str(), &next.body[..]),
+			("POST", &b"data"[..])
+		);
+		let mut put = post();
+		put.method = "PUT".into();
+		let next = policy.next(&put, &redirect(301, "x"), 0).unwrap().unwrap();
+		assert_eq!(next.method, "PUT");
+
+		assert_eq!(policy.next(&post(), &redirect(304, "x"), 0), Ok(None));
+		let mut response = redirect(302, "x");
+		response.headers.remove("Location");
+		assert_eq!(policy.next(&post(), &response, 0), Ok(None));
+	}
+
+	#[test]
+	fn applies_the_policy() {
+		let policy = RedirectPolicy::default();
+		let next = policy
+			.next(&post(), &redirect(307, "https://b.test/"), 0)
+			.unwrap()
+			.unwrap();
+		assert!(!next.headers.contains("Authorization"));
+		assert_eq!(next.body, b"data");
+
+		let strict = RedirectPolicy {
+			max_hops: 2,
+			same_origin: true,
+			no_downgrade: true,
+		};
+		assert_eq!(
+			strict.next(&post(), &redirect(302, "//b.test/"), 0),
+			Err("redirect to another origin, https://b.test/".into())
+		);
+		let strict = RedirectPolicy {
+			same_origin: false,
+			..strict
+		};
+		assert_eq!(
+			strict.next(&post(), &redirect(302, "http://a.test/"), 0),
+			Err("redirect from https to http, http://a.test/".into())
+		);
+		assert_eq!(
+			strict.next(&post(), &redirect(302, "/"), 2),
+			Err("more than 2 redirects".into())
+		);
+		let hop = Hop::new(&post(), &redirect(302, "/"));
+		assert_eq!(
+			hop.to_string(),
+			"* 302 POST https://a.test/form (0.0 ms) -> /"
+		);
+	}
+}
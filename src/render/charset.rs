@@ -0,0 +1,66 @@
+//! Decoding of the charsets commonly named by `Content-Type` headers.
+
+/// Characters of windows-1252 bytes 0x80 to 0x9f, which ISO-8859-1 leaves
+/// to control characters. Unassigned bytes keep their value.
+const WINDOWS_1252: [char; 32] = [
+	'€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+	'\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+/// Decodes text in the named charset, `None` for charsets that are not
+/// supported. Invalid sequences become replacement characters.
+pub fn decode(body: &[u8], charset: &str) -> Option<String> {
+	match charset.trim().to_ascii_lowercase().as_str() {
+		"utf-8" | "utf8" | "us-ascii" | "ascii" => Some(
+			String::from_utf8_lossy(body.strip_prefix(b"\xef\xbb\xbf").unwrap_or(body))
+				.into_owned(),
+		),
+		"iso-8859-1" | "latin1" | "l1" => Some(body.iter().map(|&byte| byte as char).collect()),
+		// browsers read ISO-8859-1 as windows-1252, so servers mix them up
+		"windows-1252" | "cp1252" => Some(
+			body.iter()
+				.map(|&byte| match byte {
+					0x80..=0x9f => WINDOWS_1252[byte as usize - 0x80],
+					_ => byte as char,
+				})
+				.collect(),
+		),
+		"utf-16" => Some(match body {
+			[0xfe, 0xff, rest @ ..] => utf16(rest, u16::from_be_bytes),
+			[0xff, 0xfe, rest @ ..] => utf16(rest, u16::from_le_bytes),
+			// big endian without a byte order mark (RFC 2781, 4.3)
+			_ => utf16(body, u16::from_be_bytes),
+		}),
+		"utf-16be" => Some(utf16(body, u16::from_be_bytes)),
+		"utf-16le" => Some(utf16(body, u16::from_le_bytes)),
+		_ => None,
+	}
+}
+
+fn utf16(body: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+	let units = body.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+	char::decode_utf16(units)
+		.map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn decodes_charsets() {
+		assert_eq!(
+			decode(b"\xef\xbb\xbfna\xc3\xafve", "UTF-8").unwrap(),
+			"naïve"
+		);
+		assert_eq!(decode(b"na\xefve", "iso-8859-1").unwrap(), "naïve");
+		assert_eq!(
+			decode(b"\x93hi\x94 \x80", "windows-1252").unwrap(),
+			"“hi” €"
+		);
+		assert_eq!(decode(b"\xff\xfeh\x00i\x00", "utf-16").unwrap(), "hi");
+		assert_eq!(decode(b"\x00h\x00i", "UTF-16BE").unwrap(), "hi");
+		assert_eq!(decode(b"x", "koi8-r"), None);
+	}
+}
@@ -0,0 +1,133 @@
+//! Format and dimensions of PNG, GIF, JPEG, WebP and BMP images, read from
+//! their headers.
+
+/// What the header of an image tells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageInfo {
+	pub format: &'static str,
+	pub width: u32,
+	pub height: u32,
+}
+
+/// Recognizes an image by its signature, `None` for other data or a
+/// truncated header.
+pub fn probe(data: &[u8]) -> Option<ImageInfo> {
+	let info = |format, width, height| {
+		Some(ImageInfo {
+			format,
+			width,
+			height,
+		})
+	};
+	if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+		// the IHDR chunk comes first
+		return info("PNG", be32(data, 16)?, be32(data, 20)?);
+	}
+	if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+		return info("GIF", le16(data, 6)?.into(), le16(data, 8)?.into());
+	}
+	if data.starts_with(b"\xff\xd8") {
+		let (width, height) = jpeg_size(data)?;
+		return info("JPEG", width, height);
+	}
+	if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+		let (width, height) = webp_size(data)?;
+		return info("WebP", width, height);
+	}
+	if data.starts_with(b"BM") && data.len() >= 26 {
+		// the height is negative for top-down bitmaps
+		let width = le32(data, 18)? as i32;
+		let height = le32(data, 22)? as i32;
+		return info("BMP", width.unsigned_abs(), height.unsigned_abs());
+	}
+	None
+}
+
+/// Size in the first start of frame segment.
+fn jpeg_size(data: &[u8]) -> Option<(u32, u32)> {
+	let mut at = 2;
+	loop {
+		// markers may be preceded by fill bytes
+		while *data.get(at)? == 0xff && *data.get(at + 1)? == 0xff {
+			at += 1;
+		}
+		if *data.get(at)? != 0xff {
+			return None;
+		}
+		let marker = *data.get(at + 1)?;
+		let length = be16(data, at + 2)? as usize;
+		let start_of_frame =
+			(0xc0..=0xcf).contains(&marker) && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+		if start_of_frame {
+			let height = be16(data, at + 5)?;
+			let width = be16(data, at + 7)?;
+			return Some((width.into(), height.into()));
+		}
+		at += 2 + length;
+	}
+}
+
+/// Size in the lossy, lossless or extended header.
+fn webp_size(data: &[u8]) -> Option<(u32, u32)> {
+	match data.get(12..16)? {
+		b"VP8 " => Some((
+			(le16(data, 26)? & 0x3fff).into(),
+			(le16(data, 28)? & 0x3fff).into(),
+		)),
+		b"VP8L" => {
+			let bits = le32(data, 21)?;
+			Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+		}
+		b"VP8X" => Some((le24(data, 24)? + 1, le24(data, 27)? + 1)),
+		_ => None,
+	}
+}
+
+fn be16(data: &[u8], at: usize) -> Option<u16> {
+	Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be32(data: &[u8], at: usize) -> Option<u32> {
+	Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le16(data: &[u8], at: usize) -> Option<u16> {
+	Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le24(data: &[u8], at: usize) -> Option<u32> {
+	let bytes = data.get(at..at + 3)?;
+	Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]))
+}
+
+fn le32(data: &[u8], at: usize) -> Option<u32> {
+	Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn size(data: &[u8]) -> Option<(&'static str, u32, u32)> {
+		probe(data).map(|info| (info.format, info.width, info.height))
+	}
+
+	#[test]
+	fn reads_image_headers() {
+		let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR".to_vec();
+		png.extend_from_slice(&[0, 0, 2, 128, 0, 0, 1, 224, 8, 6, 0, 0, 0]);
+		assert_eq!(size(&png), Some(("PNG", 640, 480)));
+		assert_eq!(size(b"GIF89a\x20\x00\x10\x00"), Some(("GIF", 32, 16)));
+
+		// SOI, an APP0 segment, then a baseline start of frame
+		let jpeg = b"\xff\xd8\xff\xe0\x00\x04JF\xff\xc0\x00\x11\x08\x00\x64\x00\xc8\x03";
+		assert_eq!(size(jpeg), Some(("JPEG", 200, 100)));
+
+		let mut webp = b"RIFF\x00\x00\x00\x00WEBPVP8X\x0a\x00\x00\x00\x00\x00\x00\x00".to_vec();
+		webp.extend_from_slice(&[0x3f, 0x01, 0x00, 0xef, 0x00, 0x00]);
+		assert_eq!(size(&webp), Some(("WebP", 320, 240)));
+
+		assert_eq!(size(b"\x89PNG\r\n\x1a\n"), None);
+		assert_eq!(size(b"plain text"), None);
+	}
+}
@@ -0,0 +1,68 @@
+//! JSON indented by two spaces, with colored keys and values.
+
+use serde_json::Value;
+
+use super::colors::{KEY, LITERAL, NUMBER, STRING};
+use super::paint;
+
+pub fn pretty(value: &Value, color: bool) -> String {
+	let mut out = String::new();
+	write_value(&mut out, value, 0, color);
+	out
+}
+
+fn write_value(out: &mut String, value: &Value, depth: usize, color: bool) {
+	let indent = |out: &mut String, depth: usize| out.push_str(&"  ".repeat(depth));
+	match value {
+		Value::Null | Value::Bool(_) => paint(out, &value.to_string(), LITERAL, color),
+		Value::Number(number) => paint(out, &number.to_string(), NUMBER, color),
+		Value::String(_) => paint(out, &value.to_string(), STRING, color),
+		Value::Array(items) if items.is_empty() => out.push_str("[]"),
+		Value::Object(members) if members.is_empty() => out.push_str("{}"),
+		Value::Array(items) => {
+			out.push_str("[\n");
+			for (index, item) in items.iter().enumerate() {
+				indent(out, depth + 1);
+				write_value(out, item, depth + 1, color);
+				out.push_str(if index + 1 < items.len() { ",\n" } else { "\n" });
+			}
+			indent(out, depth);
+			out.push(']');
+		}
+		Value::Object(members) => {
+			out.push_str("{\n");
+			for (index, (key, member)) in members.iter().enumerate() {
+				indent(out, depth + 1);
+				paint(out, &Value::String(key.clone()).to_string(), KEY, color);
+				out.push_str(": ");
+				write_value(out, member, depth + 1, color);
+				out.push_str(if index + 1 < members.len() {
+					",\n"
+				} else {
+					"\n"
+				});
+			}
+			indent(out, depth);
+			out.push('}');
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn indents_and_colors() {
+		let value: Value =
+			serde_json::from_str(r#"{"name":"a\"b","tags":[1,true,null],"empty":{}}"#).unwrap();
+		assert_eq!(
+			pretty(&value, false),
+			"{\n  \"name\": \"a\\\"b\",\n  \"tags\": [\n    1,\n    true,\n    null\n  ],\n  \"empty\": {}\n}"
+		);
+		assert_eq!(
+			pretty(&serde_json::json!({"n": 1}), true),
+			"{\n  \x1b[34m\"n\"\x1b[0m: \x1b[33m1\x1b[0m\n}"
+		);
+	}
+}
@@ -0,0 +1,296 @@
+//! XML and HTML indented by element, with colored tags and attributes. The
+//! tokenizer is lenient: unclosed HTML elements such as `<p>` or `<li>`
+//! end with their parent, and stray closing tags are printed as they are.
+
+use super::colors::{ATTRIBUTE, COMMENT, STRING, TAG};
+use super::paint;
+
+/// HTML elements without content or closing tag.
+const VOID: [&str; 14] = [
+	"area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+	"track", "wbr",
+];
+
+/// HTML elements ended by the start of a sibling of the same name.
+const IMPLIED_END: [&str; 8] = ["li", "p", "option", "tr", "td", "th", "dt", "dd"];
+
+/// HTML elements whose content is not markup.
+const RAW_TEXT: [&str; 4] = ["script", "style", "pre", "textarea"];
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token<'a> {
+	Open {
+		name: &'a str,
+		text: &'a str,
+		self_closing: bool,
+	},
+	Close {
+		name: &'a str,
+		text: &'a str,
+	},
+	Text(&'a str),
+	/// Content of an HTML raw text element, kept as it is.
+	Raw(&'a str),
+	/// Comments, CDATA sections, doctypes and processing instructions.
+	Other(&'a str),
+}
+
+pub fn pretty(text: &str, html: bool, color: bool) -> String {
+	let tokens = tokenize(text, html);
+	let same = |a: &str, b: &str| {
+		if html {
+			a.eq_ignore_ascii_case(b)
+		} else {
+			a == b
+		}
+	};
+	let mut out = String::new();
+	let mut open: Vec<&str> = Vec::new();
+	let mut index = 0;
+	while index < tokens.len() {
+		let depth = open.len();
+		match &tokens[index] {
+			Token::Text(text) => {
+				for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+					push_line(&mut out, depth, line);
+				}
+			}
+			Token::Raw(text) => {
+				out.push_str(text.trim_matches(['\r', '\n']));
+				out.push('\n');
+			}
+			Token::Other(text) => {
+				indent(&mut out, depth);
+				paint(&mut out, text.trim(), COMMENT, color);
+				out.push('\n');
+			}
+			Token::Open {
+				name,
+				text,
+				self_closing,
+			} => {
+				let implied = html && IMPLIED_END.iter().any(|implied| same(implied, name));
+				if implied && open.last().is_some_and(|last| same(last, name)) {
+					open.pop();
+				}
+				indent(&mut out, open.len());
+				paint_tag(&mut out, text, color);
+				let void = *self_closing || (html && VOID.iter().any(|void| same(void, name)));
+				if !void {
+					// an element with a single line of text, or none, stays on one line
+					let (content, skip) = match tokens.get(index + 1) {
+						Some(Token::Text(text) | Token::Raw(text))
+							if !text.trim().contains('\n') =>
+						{
+							(text.trim(), 2)
+						}
+						_ => ("", 1),
+					};
+					match tokens.get(index + skip) {
+						Some(Token::Close { name: close, text }) if same(close, name) => {
+							out.push_str(content);
+							paint_tag(&mut out, text, color);
+							index += skip;
+						}
+						_ => open.push(name),
+					}
+				}
+				out.push('\n');
+			}
+			Token::Close { name, text } => {
+				if let Some(at) = open.iter().rposition(|open| same(open, name)) {
+					open.truncate(at);
+				}
+				indent(&mut out, open.len());
+				paint_tag(&mut out, text, color);
+				out.push('\n');
+			}
+		}
+		index += 1;
+	}
+	out
+}
+
+fn tokenize(text: &str, html: bool) -> Vec<Token<'_>> {
+	let mut tokens = Vec::new();
+	let mut rest = text;
+	while !rest.is_empty() {
+		let Some(start) = rest.find('<') else {
+			tokens.push(Token::Text(rest));
+			break;
+		};
+		if start > 0 {
+			tokens.push(Token::Text(&rest[..start]));
+			rest = &rest[start..];
+		}
+		let special = [
+			("<!--", "-->"),
+			("<![CDATA[", "]]>"),
+			("<!", ">"),
+			("<?", ">"),
+		]
+		.into_iter()
+		.find(|(open, _)| rest.starts_with(open));
+		let end = match special {
+			Some((_, close)) => {
+				let end = rest.find(close).map_or(rest.len(), |at| at + close.len());
+				tokens.push(Token::Other(&rest[..end]));
+				end
+			}
+			None if rest.starts_with("</") => {
+				let end = rest.find('>').map_or(rest.len(), |at| at + 1);
+				let name = rest[2..end].trim_end_matches('>').trim();
+				tokens.push(Token::Close {
+					name,
+					text: &rest[..end],
+				});
+				end
+			}
+			None if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) => {
+				let end = tag_end(rest);
+				let tag = &rest[..end];
+				let name_end = tag[1..]
+					.find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+					.map_or(tag.len(), |at| at + 1);
+				let name = &tag[1..name_end];
+				let self_closing = tag.ends_with("/>");
+				tokens.push(Token::Open {
+					name,
+					text: tag,
+					self_closing,
+				});
+				if html
+					&& !self_closing
+					&& RAW_TEXT.iter().any(|raw| raw.eq_ignore_ascii_case(name))
+				{
+					let content = &rest[end..];
+					let close = content
+						.to_ascii_lowercase()
+						.find(&format!("</{}", name.to_ascii_lowercase()))
+						.unwrap_or(content.len());
+					if close > 0 {
+						tokens.push(Token::Raw(&content[..close]));
+					}
+					rest = &content[close..];
+					continue;
+				}
+				end
+			}
+			None => {
+				tokens.push(Token::Text("<"));
+				1
+			}
+		};
+		rest = &rest[end..];
+	}
+	tokens
+}
+
+/// End of a tag starting at `<`, after its `>` outside of quoted values.
+fn tag_end(text: &str) -> usize {
+	let mut quote = None;
+	for (at, c) in text.char_indices() {
+		match (quote, c) {
+			(None, '"' | '\'') => quote = Some(c),
+			(Some(open), c) if c == open => quote = None,
+			(None, '>') => return at + 1,
+			_ => {}
+		}
+	}
+	text.len()
+}
+
+/// Tag name and brackets in one color, attribute names and values in others.
+fn paint_tag(out: &mut String, tag: &str, color: bool) {
+	if !color {
+		out.push_str(tag);
+		return;
+	}
+	let name_end = tag
+		.find(|c: char| c.is_whitespace() || c == '>' || (c == '/' && !tag.starts_with("</")))
+		.unwrap_or(tag.len());
+	paint(out, &tag[..name_end], TAG, true);
+	let mut rest = &tag[name_end..];
+	while !rest.is_empty() {
+		if let Some(end) = rest.strip_suffix("/>").or_else(|| rest.strip_suffix('>')) {
+			if end.trim().is_empty() {
+				out.push_str(end);
+				paint(out, &rest[end.len()..], TAG, true);
+				return;
+			}
+		}
+		let c = rest.chars().next().unwrap_or(' ');
+		if c.is_whitespace() {
+			out.push(c);
+			rest = &rest[c.len_utf8()..];
+		} else if c == '=' {
+			out.push('=');
+			rest = &rest[1..];
+			let end = match rest.chars().next() {
+				Some(quote @ ('"' | '\'')) => rest[1..].find(quote).map_or(rest.len(), |at| at + 2),
+				_ => rest
+					.find(|c: char| c.is_whitespace() || c == '>')
+					.unwrap_or(rest.len()),
+			};
+			paint(out, &rest[..end], STRING, true);
+			rest = &rest[end..];
+		} else {
+			let end = rest
+				.find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+				.unwrap_or(rest.len())
+				.max(c.len_utf8());
+			paint(out, &rest[..end], ATTRIBUTE, true);
+			rest = &rest[end..];
+		}
+	}
+}
+
+fn indent(out: &mut String, depth: usize) {
+	out.push_str(&"  ".repeat(depth));
+}
+
+fn push_line(out: &mut String, depth: usize, text: &str) {
+	indent(out, depth);
+	out.push_str(text);
+	out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn indents_xml() {
+		let xml =
+			"<?xml version=\"1.0\"?><feed><title>News</title><entry id='1'><link href=\"/a\"/>\
+		           <summary>\n  line one\n  line two\n</summary></entry><!-- end --></feed>";
+		assert_eq!(
+			pretty(xml, false, false),
+			"<?xml version=\"1.0\"?>\n<feed>\n  <title>News</title>\n  <entry id='1'>\n    \
+			 <link href=\"/a\"/>\n    <summary>\n      line one\n      line two\n    </summary>\n  \
+			 </entry>\n  <!-- end -->\n</feed>\n"
+		);
+	}
+
+	#[test]
+	fn indents_html() {
+		let html = "<!DOCTYPE html><HTML><head><meta charset=utf-8><script>if (a < b) {}</script>\
+		            </head><body><ul><li>one<li>two</ul><p></p></body></html>";
+		assert_eq!(
+			pretty(html, true, false),
+			"<!DOCTYPE html>\n<HTML>\n  <head>\n    <meta charset=utf-8>\n    \
+			 <script>if (a < b) {}</script>\n  </head>\n  <body>\n    <ul>\n      <li>\n        one\n      \
+			 <li>\n        two\n    </ul>\n    <p></p>\n  </body>\n</html>\n"
+		);
+	}
+
+	#[test]
+	fn colors_tags() {
+		let mut out = String::new();
+		paint_tag(&mut out, "<a href=\"/x\" hidden/>", true);
+		assert_eq!(
+			out,
+			"\x1b[34m<a\x1b[0m \x1b[36mhref\x1b[0m=\x1b[32m\"/x\"\x1b[0m \x1b[36mhidden\x1b[0m\x1b[34m/>\x1b[0m"
+		);
+	}
+}
@@ -0,0 +1,271 @@
+//! Content-aware rendering of response bodies for terminals. The kind of a
+//! body comes from its `Content-Type` or, when that is missing or generic,
+//! from sniffing the bytes: JSON, XML and HTML are indented and colored,
+//! text is decoded from its charset, images are summarized by format and
+//! dimensions and other binary data is shown as a hexdump.
+
+mod charset;
+mod image;
+mod json;
+mod markup;
+
+pub use charset::decode;
+pub use image::{probe, ImageInfo};
+
+use crate::http::Headers;
+use crate::util::format_bytes;
+
+/// Largest part of a binary body shown in a hexdump.
+const HEXDUMP_LIMIT: usize = 4096;
+
+/// How a response is printed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+	/// Renders the body by its kind instead of writing the raw bytes.
+	pub pretty: bool,
+	/// Uses ANSI colors.
+	pub color: bool,
+}
+
+/// Kind of a body, deciding how it is rendered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+	Json,
+	Xml,
+	Html,
+	Image,
+	Text,
+	Binary,
+}
+
+/// ANSI color codes of the rendered parts.
+pub(crate) mod colors {
+	pub const KEY: &str = "34";
+	pub const STRING: &str = "32";
+	pub const NUMBER: &str = "33";
+	pub const LITERAL: &str = "35";
+	pub const TAG: &str = "34";
+	pub const ATTRIBUTE: &str = "36";
+	pub const COMMENT: &str = "90";
+	pub const HEADER: &str = "36";
+}
+
+/// Tells the kind of a body from its content type, sniffing the bytes when
+/// the type is missing or says nothing specific.
+pub fn sniff(content_type: Option<&str>, body: &[u8]) -> Kind {
+	let essence = content_type
+		.and_then(|value| value.split(';').next())
+		.unwrap_or_default()
+		.trim()
+		.to_ascii_lowercase();
+	match essence.as_str() {
+		"application/json" => return Kind::Json,
+		"text/html" | "application/xhtml+xml" => return Kind::Html,
+		"application/xml" | "text/xml" => return Kind::Xml,
+		essence if essence.ends_with("+json") => return Kind::Json,
+		essence if essence.ends_with("+xml") => return Kind::Xml,
+		essence if essence.starts_with("image/") => return Kind::Image,
+		"" | "text/plain" | "application/octet-stream" | "binary/octet-stream" => {}
+		essence if essence.starts_with("text/") => return Kind::Text,
+		_ => {}
+	}
+	if probe(body).is_some() {
+		return Kind::Image;
+	}
+	let Ok(text) = std::str::from_utf8(body.strip_prefix(b"\xef\xbb\xbf").unwrap_or(body)) else {
+		// text in another charset is declared as such
+		return match essence.starts_with("text/") {
+			true => Kind::Text,
+			false => Kind::Binary,
+		};
+	};
+	if text.contains(|c: char| c.is_control() && !c.is_whitespace()) {
+		return Kind::Binary;
+	}
+	let start = text.trim_start();
+	let lower = start.get(..14).unwrap_or(start).to_ascii_lowercase();
+	if start.starts_with(['{', '[']) && serde_json::from_str::<serde_json::Value>(text).is_ok() {
+		Kind::Json
+	} else if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+		Kind::Html
+	} else if start.starts_with("<?xml")
+		|| (start.starts_with('<') && start.trim_end().ends_with('>'))
+	{
+		Kind::Xml
+	} else {
+		Kind::Text
+	}
+}
+
+/// Renders a body by its kind. The result ends with a line break unless
+/// it is empty.
+pub fn render_body(headers: &Headers, body: &[u8], color: bool) -> String {
+	if body.is_empty() {
+		return String::new();
+	}
+	let content_type = headers.get("Content-Type");
+	let kind = sniff(content_type, body);
+	let mut out = match kind {
+		Kind::Image => image_summary(content_type, body),
+		Kind::Binary => hexdump(body),
+		Kind::Json | Kind::Xml | Kind::Html | Kind::Text => {
+			let charset = content_type.and_then(|value| param(value, "charset"));
+			let text = match charset.as_deref().and_then(|name| decode(body, name)) {
+				Some(text) => text,
+				None => String::from_utf8_lossy(body.strip_prefix(b"\xef\xbb\xbf").unwrap_or(body))
+					.into_owned(),
+			};
+			match kind {
+				Kind::Json => match serde_json::from_str(&text) {
+					Ok(value) => json::pretty(&value, color),
+					// declared JSON that does not parse is shown as it is
+					Err(_) => text,
+				},
+				Kind::Xml => markup::pretty(&text, false, color),
+				Kind::Html => markup::pretty(&text, true, color),
+				_ => text,
+			}
+		}
+	};
+	if !out.ends_with('\n') {
+		out.push('\n');
+	}
+	out
+}
+
+/// Format, dimensions and size of an image, or its type and size for
+/// formats that are not recognized.
+fn image_summary(content_type: Option<&str>, body: &[u8]) -> String {
+	let size = format_bytes(body.len() as f64);
+	match probe(body) {
+		Some(info) => format!(
+			"[{} image, {}x{}, {size}]",
+			info.format, info.width, info.height
+		),
+		None => {
+			let essence = content_type.and_then(|value| value.split(';').next());
+			format!("[{} image, {size}]", essence.unwrap_or("unknown").trim())
+		}
+	}
+}
+
+/// Offsets, hex bytes and printable characters, 16 bytes per line, in the
+/// format of `hexdump -C`.
+pub fn hexdump(data: &[u8]) -> String {
+	let mut out = String::new();
+	for (index, line) in data.chunks(16).take(HEXDUMP_LIMIT / 16).enumerate() {
+		out.push_str(&format!("{:08x} ", index * 16));
+		for column in 0..16 {
+			if column % 8 == 0 {
+				out.push(' ');
+			}
+			match line.get(column) {
+				Some(byte) => out.push_str(&format!("{byte:02x} ")),
+				None => out.push_str("   "),
+			}
+		}
+		let printable: String = line
+			.iter()
+			.map(|&byte| match byte {
+				0x20..=0x7e => byte as char,
+				_ => '.',
+			})
+			.collect();
+		out.push_str(&format!(" |{printable}|\n"));
+	}
+	if data.len() > HEXDUMP_LIMIT {
+		out.push_str(&format!(
+			"... {} more bytes, use --raw for all of them\n",
+			data.len() - HEXDUMP_LIMIT
+		));
+	}
+	out
+}
+
+/// Parameter of a `Content-Type` value, unquoted.
+fn param(content_type: &str, name: &str) -> Option<String> {
+	content_type.split(';').skip(1).find_map(|param| {
+		let (key, value) = param.split_once('=')?;
+		key.trim()
+			.eq_ignore_ascii_case(name)
+			.then(|| value.trim().trim_matches('"').to_string())
+	})
+}
+
+/// Appends text in an ANSI color when colors are on.
+pub fn paint(out: &mut String, text: &str, code: &str, color: bool) {
+	if color {
+		out.push_str(&format!("\x1b[{code}m{text}\x1b[0m"));
+	} else {
+		out.push_str(text);
+	}
+}
+
+/// Color of a status code: green for success, cyan for redirects, yellow
+/// for client and red for server errors.
+pub fn status_color(status: u16) -> &'static str {
+	match status {
+		200..=299 => "32",
+		300..=399 => "36",
+		400..=499 => "33",
+		500..=599 => "31",
+		_ => "0",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sniffs_kinds() {
+		assert_eq!(sniff(Some("application/problem+json"), b"{}"), Kind::Json);
+		assert_eq!(sniff(Some("text/html; charset=utf-8"), b""), Kind::Html);
+		assert_eq!(sniff(Some("image/svg+xml"), b"<svg/>"), Kind::Xml);
+		assert_eq!(sniff(None, b" [1, 2]\n"), Kind::Json);
+		assert_eq!(sniff(Some("text/plain"), b"{not json"), Kind::Text);
+		assert_eq!(sniff(None, b"<!DOCTYPE html><p>hi"), Kind::Html);
+		assert_eq!(sniff(None, b"<?xml version=\"1.0\"?><a/>"), Kind::Xml);
+		assert_eq!(
+			sniff(Some("application/octet-stream"), b"GIF89a\x01\x00\x01\x00"),
+			Kind::Image
+		);
+		assert_eq!(sniff(None, b"\x00\x01binary"), Kind::Binary);
+		assert_eq!(sniff(Some("text/plain"), b"caf\xe9"), Kind::Text);
+		assert_eq!(sniff(None, b"caf\xe9"), Kind::Binary);
+	}
+
+	#[test]
+	fn renders_bodies_by_kind() {
+		let mut headers = Headers::new();
+		headers.set("Content-Type", "text/plain; charset=ISO-8859-1");
+		assert_eq!(render_body(&headers, b"caf\xe9", false), "caf\u{e9}\n");
+
+		headers.set("Content-Type", "application/json");
+		assert_eq!(
+			render_body(&headers, b"{\"a\":[]}", false),
+			"{\n  \"a\": []\n}\n"
+		);
+		assert_eq!(render_body(&headers, b"{oops", false), "{oops\n");
+		assert_eq!(render_body(&headers, b"1", true), "\x1b[33m1\x1b[0m\n");
+
+		headers.set("Content-Type", "image/avif");
+		assert_eq!(
+			render_body(&headers, &[0; 2048], false),
+			"[image/avif image, 2.00 KiB]\n"
+		);
+		assert_eq!(render_body(&headers, b"", false), "");
+	}
+
+	#[test]
+	fn dumps_binary_data() {
+		let data: Vec<u8> = (0..20).chain(b"AB".iter().copied()).collect();
+		assert_eq!(
+			hexdump(&data),
+			"00000000  00 01 02 03 04 05 06 07  08 09 0a 0b 0c 0d 0e 0f  |................|\n\
+			 00000010  10 11 12 13 41 42                                 |....AB|\n"
+		);
+		let long = hexdump(&vec![0; HEXDUMP_LIMIT + 5]);
+		assert!(long.ends_with("... 5 more bytes, use --raw for all of them\n"));
+	}
+}
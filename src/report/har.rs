@@ -0,0 +1,256 @@
+use std::io::{self, Write};
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use super::Suite;
+use crate::cookie::parse_date;
+use crate::http::{Headers, Request, Response};
+use crate::runner::Outcome;
+use crate::util::{base64_encode, format_timestamp};
+
+/// Writes every request and its response as a HAR 1.2 archive, which
+/// browser developer tools and HAR viewers can open. Requests that failed
+/// have status 0 and the error in `_error`.
+pub fn write_har<W: Write>(out: &mut W, suites: &[Suite]) -> io::Result<()> {
+	let entries: Vec<Value> = suites
+		.iter()
+		.flat_map(|suite| suite.outcomes)
+		.map(entry)
+		.collect();
+	let document = json!({
+		"log": {
+			"version": "1.2",
+			"creator": {"name": "webcat", "version": env!("CARGO_PKG_VERSION")},
+			"entries": entries,
+		}
+	});
+	serde_json::to_writer_pretty(&mut *out, &document)?;
+	writeln!(out)
+}
+
+fn entry(outcome: &Outcome) -> Value {
+	let version = match &outcome.response {
+		Ok(response) => response.version.as_str(),
+		Err(_) => "HTTP/1.1",
+	};
+	let mut entry = json!({
+		"startedDateTime": format_timestamp(outcome.started),
+		"time": millis(outcome.elapsed),
+		"request": request(&outcome.request, version),
+		"cache": {},
+		"comment": outcome.name,
+	});
+	match &outcome.response {
+		Ok(response) => {
+			entry["response"] = self::response(response);
+			let timing = &response.timing;
+			// HAR counts the TLS handshake as part of connecting
+			entry["timings"] = json!({
+				"blocked": -1,
+				"dns": millis(timing.dns),
+				"connect": millis(timing.connect + timing.tls),
+				"ssl": if outcome.request.url.is_https() { millis(timing.tls) } else { -1.0 },
+				"send": 0,
+				"wait": millis(timing.ttfb),
+				"receive": millis(timing.transfer),
+			});
+		}
+		Err(err) => {
+			entry["response"] = json!({
+				"status": 0,
+				"statusText": "",
+				"httpVersion": "",
+				"cookies": [],
+				"headers": [],
+				"content": {"size": 0, "mimeType": "x-unknown"},
+				"redirectURL": "",
+				"headersSize": -1,
+				"bodySize": -1,
+			});
+			entry["timings"] = json!({"send": 0, "wait": 0, "receive": 0});
+			entry["_error"] = Value::String(err.to_string());
+		}
+	}
+	entry
+}
+
+fn request(request: &Request, version: &str) -> Value {
+	let query: Vec<Value> = request
+		.url
+		.query_pairs()
+		.into_iter()
+		.map(|(name, value)| json!({"name": name, "value": value}))
+		.collect();
+	let mut value = json!({
+		"method": request.method,
+		"url": request.url.to_string(),
+		"httpVersion": version,
+		"cookies": request_cookies(&request.headers),
+		"headers": sent_headers(request),
+		"queryString": query,
+		"headersSize": -1,
+		"bodySize": request.body_len(),
+	});
+	if !request.body.is_empty() {
+		let mut post = json!({"mimeType": mime_type(&request.headers)});
+		// postData has no encoding field, so binary bodies use an extension
+		set_text(&mut post, &request.body, "_encoding");
+		value["postData"] = post;
+	}
+	value
+}
+
+fn response(response: &Response) -> Value {
+	let mut content = json!({
+		"size": response.body.len(),
+		"mimeType": mime_type(&response.headers),
+	});
+	set_text(&mut content, &response.body, "encoding");
+	json!({
+		"status": response.status,
+		"statusText": response.reason,
+		"httpVersion": response.version,
+		"cookies": response_cookies(&response.headers),
+		"headers": headers(&response.headers),
+		"content": content,
+		"redirectURL": response.headers.get("Location").unwrap_or(""),
+		"headersSize": -1,
+		"bodySize": response.body.len(),
+	})
+}
+
+/// Headers of the request with the `Host` that HTTP/1.1 adds when sending.
+fn sent_headers(request: &Request) -> Vec<Value> {
+	let mut list = Vec::new();
+	if !request.headers.contains("Host") {
+		list.push(json!({"name": "Host", "value": request.url.authority()}));
+	}
+	list.extend(headers(&request.headers));
+	list
+}
+
+fn request_cookies(headers: &Headers) -> Vec<Value> {
+	headers
+		.get_all("Cookie")
+		.flat_map(|value| value.split(';'))
+		.filter_map(|pair| pair.split_once('='))
+		.map(|(name, value)| json!({"name": name.trim(), "value": value.trim()}))
+		.collect()
+}
+
+/// Cookies of the `Set-Cookie` headers, with the attributes HAR has fields
+/// for.
+fn response_cookies(headers: &Headers) -> Vec<Value> {
+	let mut cookies = Vec::new();
+	for set_cookie in headers.get_all("Set-Cookie") {
+		let mut parts = set_cookie.split(';');
+		let Some((name, value)) = parts.next().and_then(|pair| pair.split_once('=')) else {
+			continue;
+		};
+		let mut cookie = json!({"name": name.trim(), "value": value.trim()});
+		for attribute in parts {
+			let (key, argument) = match attribute.split_once('=') {
+				Some((key, argument)) => (key.trim(), argument.trim()),
+				None => (attribute.trim(), ""),
+			};
+			match key.to_ascii_lowercase().as_str() {
+				"path" => cookie["path"] = argument.into(),
+				"domain" => cookie["domain"] = argument.into(),
+				"expires" => {
+					if let Some(time) = parse_date(argument) {
+						let time = UNIX_EPOCH + Duration::from_secs(time.max(0) as u64);
+						cookie["expires"] = format_timestamp(time).into();
+					}
+				}
+				"httponly" => cookie["httpOnly"] = true.into(),
+				"secure" => cookie["secure"] = true.into(),
+				_ => {}
+			}
+		}
+		cookies.push(cookie);
+	}
+	cookies
+}
+
+fn headers(headers: &Headers) -> Vec<Value> {
+	headers
+		.iter()
+		.map(|(name, value)| json!({"name": name, "value": value}))
+		.collect()
+}
+
+fn mime_type(headers: &Headers) -> &str {
+	headers.get("Content-Type").unwrap_or("")
+}
+
+/// Stores a body as `text`, base64 encoded if it is not UTF-8.
+fn set_text(object: &mut Value, body: &[u8], encoding: &str) {
+	match std::str::from_utf8(body) {
+		Ok(text) => object["text"] = text.into(),
+		Err(_) => {
+			object["text"] = base64_encode(body).into();
+			object[encoding] = "base64".into();
+		}
+	}
+}
+
+/// Milliseconds with microsecond precision.
+fn millis(duration: Duration) -> f64 {
+	duration.as_micros() as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::report::tests::outcomes;
+
+	#[test]
+	fn writes_archive() {
+		let mut outcomes = outcomes();
+		outcomes[0].request.body = vec![0xff, 0x00];
+		if let Ok(response) = &mut outcomes[0].response {
+			response.headers.append(
+				"Set-Cookie",
+				"id=5; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Secure",
+			);
+		}
+		let suites = [Suite {
+			name: "api.http",
+			outcomes: &outcomes,
+		}];
+		let mut out = Vec::new();
+		write_har(&mut out, &suites).unwrap();
+		let value: Value = serde_json::from_slice(&out).unwrap();
+
+		let entries = value["log"]["entries"].as_array().unwrap();
+		assert_eq!(value["log"]["version"], "1.2");
+		assert_eq!(entries.len(), 3);
+		assert_eq!(entries[0]["startedDateTime"], "2023-11-14T22:13:20.250Z");
+		assert_eq!(entries[0]["time"], 12.0);
+		assert_eq!(entries[0]["comment"], "list");
+		assert_eq!(entries[0]["request"]["postData"]["text"], "/wA=");
+		assert_eq!(entries[0]["request"]["postData"]["_encoding"], "base64");
+		assert_eq!(entries[0]["response"]["status"], 200);
+		assert_eq!(entries[0]["response"]["content"]["text"], r#"{"id": 5}"#);
+		assert_eq!(
+			entries[0]["response"]["content"]["mimeType"],
+			"application/json"
+		);
+		assert_eq!(entries[0]["timings"]["ssl"], -1.0);
+		assert_eq!(
+			entries[0]["response"]["cookies"],
+			json!([{"name": "id", "value": "5", "expires": "2015-10-21T07:28:00.000Z", "secure": true}])
+		);
+		assert_eq!(entries[0]["request"]["headers"][0]["value"], "localhost");
+		assert_eq!(entries[2]["response"]["status"], 0);
+		assert_eq!(entries[2]["_error"], "protocol error: connection reset");
+
+		// the importer reads back what was written
+		let text = String::from_utf8(out).unwrap();
+		let imported = crate::har::parse("run.har", &text).unwrap();
+		assert_eq!(imported[0].request.body, [0xff, 0x00]);
+		assert_eq!(imported[0].response.as_ref().unwrap().body, br#"{"id": 5}"#);
+		assert!(imported[2].response.is_none());
+	}
+}
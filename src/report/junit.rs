@@ -0,0 +1,145 @@
+use std::io::{self, Write};
+
+use super::{failure_lines, request_excerpt, response_excerpt, Suite};
+
+/// Writes a JUnit XML report: one `testsuite` per request file and one
+/// `testcase` per request. Assertion failures are reported as `failure` and
+/// requests without a response as `error`. Request and response excerpts are
+/// included in `system-out`.
+pub fn write_junit<W: Write>(out: &mut W, suites: &[Suite]) -> io::Result<()> {
+	let tests: usize = suites.iter().map(|s| s.outcomes.len()).sum();
+	let failures: usize = suites.iter().map(|s| s.failures()).sum();
+	let errors: usize = suites.iter().map(|s| s.errors()).sum();
+	let time: f64 = suites.iter().map(|s| s.seconds()).sum();
+
+	writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+	writeln!(
+		out,
+		r#"<testsuites name="webcat" tests="{tests}" failures="{failures}" errors="{errors}" time="{time:.3}">"#
+	)?;
+	for suite in suites {
+		let name = escape(suite.name);
+		writeln!(
+			out,
+			r#"  <testsuite name="{name}" tests="{}" failures="{}" errors="{}" time="{:.3}">"#,
+			suite.outcomes.len(),
+			suite.failures(),
+			suite.errors(),
+			suite.seconds(),
+		)?;
+		for outcome in suite.outcomes {
+			writeln!(
+				out,
+				r#"    <testcase name="{}" classname="{name}" time="{:.3}">"#,
+				escape(&outcome.name),
+				outcome.elapsed.as_secs_f64()
+			)?;
+			let lines = failure_lines(outcome);
+			if outcome.response.is_err() {
+				writeln!(
+					out,
+					r#"      <error type="transport" message="{}"/>"#,
+					escape(&lines.join("; "))
+				)?;
+			} else if !lines.is_empty() {
+				let mut details = String::new();
+				for result in outcome.failures() {
+					details.push_str(&format!(
+						"line {}: {}\n  {}\n",
+						result.line, result.assertion, result.message
+					));
+					for line in result.diff.iter().flat_map(|diff| diff.lines()) {
+						details.push_str(&format!("  {line}\n"));
+					}
+				}
+				writeln!(
+					out,
+					r#"      <failure type="assertion" message="{}">{}</failure>"#,
+					escape(&lines.join("; ")),
+					escape(&details)
+				)?;
+			}
+			let mut system_out = format!(">>> request\n{}", request_excerpt(&outcome.request));
+			if let Ok(response) = &outcome.response {
+				system_out.push_str(&format!("<<< response\n{}", response_excerpt(response)));
+			}
+			writeln!(
+				out,
+				"      <system-out>{}</system-out>",
+				escape(&system_out)
+			)?;
+			writeln!(out, "    </testcase>")?;
+		}
+		writeln!(out, "  </testsuite>")?;
+	}
+	writeln!(out, "</testsuites>")?;
+	Ok(())
+}
+
+/// Escapes text for XML attributes and content, dropping characters that
+/// are not allowed in XML 1.0.
+fn escape(text: &str) -> String {
+	let mut output = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'&' => output.push_str("&amp;"),
+			'<' => output.push_str("&lt;"),
+			'>' => output.push_str("&gt;"),
+			'"' => output.push_str("&quot;"),
+			'\'' => output.push_str("&apos;"),
+			'\n' | '\r' | '\t' => output.push(c),
+			c if (c as u32) < 0x20 || c == '\u{fffe}' || c == '\u{ffff}' => {}
+			c => output.push(c),
+		}
+	}
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::report::tests::outcomes;
+
+	#[test]
+	fn writes_junit_xml() {
+		let outcomes = outcomes();
+		let mut out = Vec::new();
+		write_junit(
+			&mut out,
+			&[Suite {
+				name: "api.http",
+				outcomes: &outcomes,
+			}],
+		)
+		.unwrap();
+		let xml = String::from_utf8(out).unwrap();
+
+		assert!(xml.contains(
+			r#"<testsuites name="webcat" tests="3" failures="1" errors="1" time="0.021">"#
+		));
+		assert!(xml.contains(
+			r#"<testsuite name="api.http" tests="3" failures="1" errors="1" time="0.021">"#
+		));
+		assert!(xml.contains(r#"<testcase name="list" classname="api.http" time="0.012">"#));
+		assert!(xml.contains(
+			r#"<testcase name="check &quot;id&quot;" classname="api.http" time="0.008">"#
+		));
+		assert!(xml.contains(
+			r#"<failure type="assertion" message="json $.id == 6: expected $.id == 6, got 5 &lt;&amp;&gt;">line 4: json $.id == 6"#
+		));
+		assert!(
+			xml.contains(r#"<error type="transport" message="protocol error: connection reset"/>"#)
+		);
+		assert!(xml.contains("<system-out>&gt;&gt;&gt; request\nGET http://localhost/items\n"));
+		assert!(xml.contains("&lt;&lt;&lt; response\nHTTP/1.1 200 OK\nContent-Type: application/json\n\n{&quot;id&quot;: 5}\n"));
+		assert_eq!(
+			xml.matches("<testcase ").count(),
+			xml.matches("</testcase>").count()
+		);
+	}
+
+	#[test]
+	fn escapes_invalid_characters() {
+		assert_eq!(escape("a\u{1}b<'>"), "ab&lt;&apos;&gt;");
+	}
+}
@@ -0,0 +1,194 @@
+//! Machine readable reports of request file runs.
+
+mod har;
+mod junit;
+mod tap;
+mod timing;
+
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::http::{Request, Response};
+use crate::runner::Outcome;
+
+pub use har::write_har;
+pub use junit::write_junit;
+pub use tap::write_tap;
+pub use timing::write_timing;
+
+/// Maximum number of body bytes included in request and response excerpts.
+const EXCERPT_BODY: usize = 1024;
+
+/// Outcomes of a single request file.
+pub struct Suite<'a> {
+	pub name: &'a str,
+	pub outcomes: &'a [Outcome],
+}
+
+impl Suite<'_> {
+	pub fn failures(&self) -> usize {
+		self.outcomes
+			.iter()
+			.filter(|o| o.response.is_ok() && !o.is_success())
+			.count()
+	}
+
+	/// Requests that failed to produce a response.
+	pub fn errors(&self) -> usize {
+		self.outcomes.iter().filter(|o| o.response.is_err()).count()
+	}
+
+	pub fn seconds(&self) -> f64 {
+		self.outcomes.iter().map(|o| o.elapsed.as_secs_f64()).sum()
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+	Junit,
+	Tap,
+	/// Timing breakdown of each request as JSON.
+	Timing,
+	/// HTTP Archive of the requests and responses.
+	Har,
+}
+
+impl FromStr for Format {
+	type Err = Error;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		match text.to_ascii_lowercase().as_str() {
+			"junit" | "xml" => Ok(Format::Junit),
+			"tap" => Ok(Format::Tap),
+			"timing" | "json" => Ok(Format::Timing),
+			"har" => Ok(Format::Har),
+			_ => Err(Error::Usage(format!(
+				"unknown report format `{text}`, expected junit, tap, timing or har"
+			))),
+		}
+	}
+}
+
+/// Writes the report in the given format.
+pub fn write_report<W: std::io::Write>(
+	out: &mut W,
+	format: Format,
+	suites: &[Suite],
+) -> std::io::Result<()> {
+	match format {
+		Format::Junit => write_junit(out, suites),
+		Format::Tap => write_tap(out, suites),
+		Format::Timing => write_timing(out, suites),
+		Format::Har => write_har(out, suites),
+	}
+}
+
+/// Text summary of why an outcome failed, one line per problem.
+fn failure_lines(outcome: &Outcome) -> Vec<String> {
+	if let Err(err) = &outcome.response {
+		return vec![err.to_string()];
+	}
+	outcome
+		.failures()
+		.map(|result| format!("{}: {}", result.assertion, result.message))
+		.collect()
+}
+
+/// Request line, headers and the start of the body.
+fn request_excerpt(request: &Request) -> String {
+	let mut text = format!("{} {}\n", request.method, request.url);
+	for (name, value) in request.headers.iter() {
+		text.push_str(&format!("{name}: {value}\n"));
+	}
+	push_body(&mut text, &request.body);
+	text
+}
+
+/// Status line, headers and the start of the body.
+fn response_excerpt(response: &Response) -> String {
+	let mut text = format!("{}\n", response.status_line());
+	for (name, value) in response.headers.iter() {
+		text.push_str(&format!("{name}: {value}\n"));
+	}
+	push_body(&mut text, &response.body);
+	text
+}
+
+fn push_body(text: &mut String, body: &[u8]) {
+	if body.is_empty() {
+		return;
+	}
+	text.push('\n');
+	let shown = &body[..body.len().min(EXCERPT_BODY)];
+	text.push_str(&String::from_utf8_lossy(shown));
+	if body.len() > EXCERPT_BODY {
+		text.push_str(&format!("\n... ({} more bytes)", body.len() - EXCERPT_BODY));
+	}
+	if !text.ends_with('\n') {
+		text.push('\n');
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use std::time::{Duration, SystemTime};
+
+	use crate::assert::AssertionResult;
+	use crate::error::Error;
+	use crate::http::{Headers, Request, Response};
+	use crate::runner::Outcome;
+	use crate::url::Url;
+
+	/// Passing, failing and erroring outcomes used by the reporter tests.
+	pub fn outcomes() -> Vec<Outcome> {
+		let request = Request::new("GET", Url::parse("http://localhost/items").unwrap());
+		let started = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+		let response = Response {
+			version: "HTTP/1.1".into(),
+			status: 200,
+			reason: "OK".into(),
+			headers: [("Content-Type", "application/json")]
+				.into_iter()
+				.collect::<Headers>(),
+			body: br#"{"id": 5}"#.to_vec(),
+			..Default::default()
+		};
+		let result = |passed: bool| AssertionResult {
+			assertion: "json $.id == 6".into(),
+			line: 4,
+			passed,
+			message: if passed {
+				String::new()
+			} else {
+				"expected $.id == 6, got 5 <&>".into()
+			},
+			diff: None,
+		};
+		vec![
+			Outcome {
+				name: "list".into(),
+				request: request.clone(),
+				started,
+				response: Ok(response.clone()),
+				elapsed: Duration::from_millis(12),
+				assertions: vec![result(true)],
+			},
+			Outcome {
+				name: "check \"id\"".into(),
+				request: request.clone(),
+				started,
+				response: Ok(response),
+				elapsed: Duration::from_millis(8),
+				assertions: vec![result(false)],
+			},
+			Outcome {
+				name: "down".into(),
+				request,
+				started,
+				response: Err(Error::Protocol("connection reset".into())),
+				elapsed: Duration::from_millis(1),
+				assertions: Vec::new(),
+			},
+		]
+	}
+}
@@ -0,0 +1,105 @@
+use std::io::{self, Write};
+
+use super::{failure_lines, request_excerpt, response_excerpt, Suite};
+
+/// Writes a TAP version 13 report with one test point per request. Failed
+/// test points carry a YAML diagnostic block with the failure messages and
+/// request/response excerpts.
+pub fn write_tap<W: Write>(out: &mut W, suites: &[Suite]) -> io::Result<()> {
+	let total: usize = suites.iter().map(|s| s.outcomes.len()).sum();
+	writeln!(out, "TAP version 13")?;
+	writeln!(out, "1..{total}")?;
+
+	let mut number = 0;
+	for suite in suites {
+		for outcome in suite.outcomes {
+			number += 1;
+			let status = if outcome.is_success() { "ok" } else { "not ok" };
+			let name = format!("{}: {}", suite.name, outcome.name).replace('#', "\\#");
+			writeln!(out, "{status} {number} - {name}")?;
+			if outcome.is_success() {
+				continue;
+			}
+
+			writeln!(out, "  ---")?;
+			writeln!(out, "  duration_ms: {}", outcome.elapsed.as_millis())?;
+			writeln!(out, "  failures:")?;
+			for line in failure_lines(outcome) {
+				writeln!(out, "    - {}", quote(&line))?;
+			}
+			for result in outcome.failures().filter(|r| r.diff.is_some()) {
+				writeln!(out, "  diff: |")?;
+				block(out, result.diff.as_deref().unwrap_or_default())?;
+			}
+			writeln!(out, "  request: |")?;
+			block(out, &request_excerpt(&outcome.request))?;
+			if let Ok(response) = &outcome.response {
+				writeln!(out, "  response: |")?;
+				block(out, &response_excerpt(response))?;
+			}
+			writeln!(out, "  ...")?;
+		}
+	}
+	Ok(())
+}
+
+/// Double quoted YAML scalar. JSON string syntax is valid YAML.
+fn quote(text: &str) -> String {
+	serde_json::Value::String(text.to_string()).to_string()
+}
+
+/// Writes the lines of a YAML literal block scalar.
+fn block<W: Write>(out: &mut W, text: &str) -> io::Result<()> {
+	for line in text.lines() {
+		writeln!(out, "    {line}")?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::report::tests::outcomes;
+
+	#[test]
+	fn writes_tap() {
+		let outcomes = outcomes();
+		let mut out = Vec::new();
+		write_tap(
+			&mut out,
+			&[Suite {
+				name: "api.http",
+				outcomes: &outcomes,
+			}],
+		)
+		.unwrap();
+		let tap = String::from_utf8(out).unwrap();
+		let expected = "\
+TAP version 13
+1..3
+ok 1 - api.http: list
+not ok 2 - api.http: check \"id\"
+  ---
+  duration_ms: 8
+  failures:
+    - \"json $.id == 6: expected $.id == 6, got 5 <&>\"
+  request: |
+    GET http://localhost/items
+  response: |
+    HTTP/1.1 200 OK
+    Content-Type: application/json
+    
+    {\"id\": 5}
+  ...
+not ok 3 - api.http: down
+  ---
+  duration_ms: 1
+  failures:
+    - \"protocol error: connection reset\"
+  request: |
+    GET http://localhost/items
+  ...
+";
+		assert_eq!(tap, expected);
+	}
+}
@@ -0,0 +1,55 @@
+use std::io::{self, Write};
+
+use serde_json::{json, Value};
+
+use super::Suite;
+
+/// Writes the timing breakdown of every request as a JSON array, suitable
+/// for tracking latency across runs. Phases are in milliseconds.
+pub fn write_timing<W: Write>(out: &mut W, suites: &[Suite]) -> io::Result<()> {
+	let mut entries = Vec::new();
+	for suite in suites {
+		for outcome in suite.outcomes {
+			let mut entry = json!({
+				"file": suite.name,
+				"name": outcome.name,
+				"method": outcome.request.method,
+				"url": outcome.request.url.to_string(),
+			});
+			match &outcome.response {
+				Ok(response) => {
+					entry["status"] = response.status.into();
+					entry["timing"] = response.timing.to_json();
+				}
+				Err(err) => entry["error"] = Value::String(err.to_string()),
+			}
+			entries.push(entry);
+		}
+	}
+	serde_json::to_writer_pretty(&mut *out, &entries)?;
+	writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::report::tests::outcomes;
+
+	#[test]
+	fn writes_timings() {
+		let outcomes = outcomes();
+		let suites = [Suite {
+			name: "api.http",
+			outcomes: &outcomes,
+		}];
+		let mut out = Vec::new();
+		write_timing(&mut out, &suites).unwrap();
+		let value: Value = serde_json::from_slice(&out).unwrap();
+		assert_eq!(value.as_array().unwrap().len(), outcomes.len());
+		assert_eq!(value[0]["file"], "api.http");
+		assert_eq!(value[0]["status"], 200);
+		assert_eq!(value[0]["timing"]["total"], 0.0);
+		assert!(value[2]["error"].is_string());
+		assert!(value[2].get("timing").is_none());
+	}
+}
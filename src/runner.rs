@@ -0,0 +1,363 @@
+//! Executes the requests of request files.
+
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::assert::{Assertion, AssertionResult, Op};
+use crate::client::Client;
+use crate::error::{Error, ParseError, Result};
+use crate::graphql::{self, Query, Schemas};
+use crate::http::{Request, Response};
+use crate::httpfile::{RequestDef, RequestFile};
+use crate::openapi::Spec;
+use crate::sse::EventSource;
+use crate::template::Variables;
+use crate::ws::{Message, WebSocket};
+
+/// Result of executing one request definition.
+#[derive(Debug)]
+pub struct Outcome {
+	pub name: String,
+	pub request: Request,
+	/// When the request was sent.
+	pub started: SystemTime,
+	/// Response, or the transport error that prevented it.
+	pub response: Result<Response>,
+	pub elapsed: Duration,
+	/// Results of the request assertions, empty if there was no response.
+	pub assertions: Vec<AssertionResult>,
+}
+
+impl Outcome {
+	/// True if a response was received and all assertions passed.
+	pub fn is_success(&self) -> bool {
+		self.response.is_ok() && self.assertions.iter().all(|result| result.passed)
+	}
+
+	pub fn failures(&self) -> impl Iterator<Item = &AssertionResult> {
+		self.assertions.iter().filter(|result| !result.passed)
+	}
+}
+
+/// Runs requests sharing a client and a set of variables, which grows with
+/// the values captured from responses.
+pub struct Runner {
+	pub client: Client,
+	pub variables: Variables,
+	/// Document the responses are checked against, in addition to the
+	/// request assertions.
+	pub spec: Option<Spec>,
+	/// Schemas GraphQL queries are validated against before sending, `None`
+	/// to send them as they are.
+	pub schemas: Option<Schemas>,
+}
+
+impl Runner {
+	pub fn new(client: Client) -> Self {
+		Runner {
+			client,
+			variables: Variables::new(),
+			spec: None,
+			schemas: Some(Schemas::default()),
+		}
+	}
+
+	/// Runs all requests of a file in order, or only the one named `only`.
+	/// The callback is invoked as soon as each request completes.
+	pub fn run_file(
+		&mut self,
+		file: &RequestFile,
+		only: Option<&str>,
+		mut on_outcome: impl FnMut(&Outcome),
+	) -> Result<Vec<Outcome>> {
+		let selected: Vec<&RequestDef> = match only {
+			Some(name) => {
+				let def = file.find(name).ok_or_else(|| {
+					Error::Usage(format!("no request named `{name}` in {}", file.source))
+				})?;
+				vec![def]
+			}
+			None => file.requests.iter().collect(),
+		};
+
+		// file variables using a value captured later wait for the capture
+		let mut variables = self.variables.clone();
+		let mut pending: Vec<&str> = file
+			.captured_names()
+			.filter(|name| !variables.contains(name))
+			.collect();
+		file.resolve_variables_except(&mut variables, &pending)?;
+
+		let mut outcomes = Vec::new();
+		for def in selected {
+			let request = file.build(def, &variables)?;
+			let mut outcome = self.execute_def(file, def, request, &variables);
+			if let Ok(response) = &outcome.response {
+				for capture in &def.captures {
+					match capture.extract(response) {
+						Ok(value) => {
+							// later files of the run see it too
+							self.variables.set(capture.name.clone(), value.clone());
+							variables.set(capture.name.clone(), value);
+							pending.retain(|name| *name != capture.name);
+						}
+						Err(message) => outcome.assertions.push(capture.failure(message)),
+					}
+				}
+				file.resolve_variables_except(&mut variables, &pending)?;
+			}
+			on_outcome(&outcome);
+			outcomes.push(outcome);
+		}
+		Ok(outcomes)
+	}
+
+	/// Sends a request built from a definition of a file, as a WebSocket
+	/// script, a GraphQL query or a plain request.
+	pub fn execute_def(
+		&self,
+		file: &RequestFile,
+		def: &RequestDef,
+		request: Request,
+		vars: &Variables,
+	) -> Outcome {
+		if def.is_websocket() {
+			self.execute_websocket(file, def, request, vars)
+		} else if def.is_graphql() {
+			let locate = |err| file.locate_query(def, err);
+			self.execute_graphql(
+				def.label(),
+				request,
+				&def.assertions,
+				vars,
+				def.line,
+				locate,
+			)
+		} else {
+			self.execute(def.label(), request, &def.assertions, vars)
+		}
+	}
+
+	/// Sends a request and checks the assertions against its response.
+	pub fn execute(
+		&self,
+		name: String,
+		mut request: Request,
+		assertions: &[Assertion],
+		vars: &Variables,
+	) -> Outcome {
+		let authorized = self.client.authorize(&mut request);
+		// the outcome shows the jar cookies and `Accept-Encoding` as sent,
+		// which the client adds itself so each redirect gets its own cookies
+		let mut shown = request.clone();
+		self.client.prepare(&mut shown);
+		let started = SystemTime::now();
+		let start = Instant::now();
+		let response = authorized.and_then(|()| match assertions.iter().any(Assertion::is_event) {
+			true => self.stream_events(&request, assertions, vars),
+			false => self.client.send(&request),
+		});
+		let elapsed = start.elapsed();
+		let assertions = match &response {
+			Ok(response) => {
+				let mut results: Vec<AssertionResult> = assertions
+					.iter()
+					.map(|assertion| assertion.evaluate(response, elapsed, vars))
+					.collect();
+				if let Some(spec) = &self.spec {
+					results.extend(spec.check(&shown, response));
+				}
+				results
+			}
+			Err(_) => Vec::new(),
+		};
+		Outcome {
+			name,
+			request: shown,
+			started,
+			response,
+			elapsed,
+			assertions,
+		}
+	}
+
+	/// Sends a GraphQL request once its query is found valid, and checks
+	/// that the response has no `errors` along with the assertions. `line`
+	/// is that of the request in its file, and `locate` turns a problem of
+	/// the query into an error pointing to its source.
+	pub fn execute_graphql(
+		&self,
+		name: String,
+		request: Request,
+		assertions: &[Assertion],
+		vars: &Variables,
+		line: usize,
+		locate: impl Fn(ParseError) -> Error,
+	) -> Outcome {
+		if let Some(schemas) = &self.schemas {
+			let started = SystemTime::now();
+			let start = Instant::now();
+			let checked = self
+				.check_query(schemas, &request)
+				.and_then(|checked| checked.map_err(&locate));
+			if let Err(err) = checked {
+				return Outcome {
+					name,
+					request,
+					started,
+					response: Err(err),
+					elapsed: start.elapsed(),
+					assertions: Vec::new(),
+				};
+			}
+		}
+		let mut outcome = self.execute(name, request, assertions, vars);
+		if let Ok(response) = &outcome.response {
+			outcome
+				.assertions
+				.insert(0, graphql::check_errors(response, line));
+		}
+		outcome
+	}
+
+	/// Checks the query of a request against the schema of its endpoint,
+	/// introspected with the cookies and credentials of the request.
+	fn check_query(
+		&self,
+		schemas: &Schemas,
+		request: &Request,
+	) -> Result<std::result::Result<(), ParseError>> {
+		let query = Query::from_json(&request.body)
+			.ok_or_else(|| Error::Usage("the body is not a GraphQL query".into()))?;
+		let mut introspection = request.clone();
+		self.client.authorize(&mut introspection)?;
+		schemas.check(&self.client, &introspection, &query)
+	}
+
+	/// Reads the events of a response as they arrive, until the event
+	/// assertions pass, the longest time they allow is up or the stream
+	/// ends. Assertions without a time limit wait as long as the timeout
+	/// of the client.
+	fn stream_events(
+		&self,
+		request: &Request,
+		assertions: &[Assertion],
+		vars: &Variables,
+	) -> Result<Response> {
+		let start = Instant::now();
+		let events: Vec<&Assertion> = assertions.iter().filter(|a| a.is_event()).collect();
+		let limit = events
+			.iter()
+			.map(|assertion| assertion.time_limit(vars).or(self.client.timeout))
+			.collect::<Option<Vec<Duration>>>()
+			.and_then(|limits| limits.into_iter().max());
+		let deadline = limit.map(|limit| start + limit);
+		let mut source = EventSource::connect(&self.client, request, None, false)?;
+		// the absence of an event is only known at the end
+		let settles = events.iter().all(|a| a.op != Op::NotExists);
+		while source.next(deadline)?.is_some() {
+			let elapsed = start.elapsed();
+			let passed = |a: &&Assertion| a.evaluate(&source.response, elapsed, vars).passed;
+			if settles && events.iter().all(passed) {
+				break;
+			}
+		}
+		let mut response = source.response.clone();
+		response.timing.transfer = start.elapsed().saturating_sub(response.timing.total());
+		Ok(response)
+	}
+
+	/// Opens a WebSocket and runs the script of a `WEBSOCKET` request. Each
+	/// message received is checked by the assertions of its section, and
+	/// the request assertions check the handshake response with the last
+	/// message received as its body.
+	pub fn execute_websocket(
+		&self,
+		file: &RequestFile,
+		def: &RequestDef,
+		mut request: Request,
+		vars: &Variables,
+	) -> Outcome {
+		// the outcome shows the headers as sent
+		self.client.add_cookies(&mut request);
+		let authorized = self.client.authorize(&mut request);
+		let started = SystemTime::now();
+		let start = Instant::now();
+		let mut assertions = Vec::new();
+		let response = authorized
+			.and_then(|()| self.run_script(file, def, &request, vars, start, &mut assertions));
+		let elapsed = start.elapsed();
+		if let Ok(response) = &response {
+			assertions.extend(
+				def.assertions
+					.iter()
+					.map(|assertion| assertion.evaluate(response, elapsed, vars)),
+			);
+		}
+		Outcome {
+			name: def.label(),
+			request,
+			started,
+			response,
+			elapsed,
+			assertions,
+		}
+	}
+
+	fn run_script(
+		&self,
+		file: &RequestFile,
+		def: &RequestDef,
+		request: &Request,
+		vars: &Variables,
+		start: Instant,
+		results: &mut Vec<AssertionResult>,
+	) -> Result<Response> {
+		let mut socket = WebSocket::connect(&self.client, request, true)?;
+		let mut last = Vec::new();
+		for message in &def.messages {
+			if message.wait {
+				let received = loop {
+					match socket.receive(self.client.timeout)? {
+						Some(Message::Text(text)) => break text.into_bytes(),
+						Some(Message::Binary(data)) => break data,
+						Some(Message::Close(status)) => {
+							return Err(Error::Protocol(format!(
+								"WebSocket closed by the server{} (line {})",
+								status.map_or(String::new(), |(code, _)| format!(" with {code}")),
+								message.line
+							)))
+						}
+						Some(_) => {}
+						None => {
+							return Err(Error::Protocol(format!(
+								"no WebSocket message in time (line {})",
+								message.line
+							)))
+						}
+					}
+				};
+				let response = Response {
+					body: received,
+					..socket.response.clone()
+				};
+				results.extend(
+					message
+						.assertions
+						.iter()
+						.map(|assertion| assertion.evaluate(&response, start.elapsed(), vars)),
+				);
+				last = response.body;
+			}
+			if let Some(body) = &message.body {
+				let data = file.render_body(body, vars)?;
+				socket.send(&Message::from_bytes(data))?;
+			}
+		}
+		// the script passed even if the server does not close properly
+		let _ = socket.close(self.client.timeout);
+		Ok(Response {
+			body: last,
+			..socket.response.clone()
+		})
+	}
+}
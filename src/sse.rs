@@ -0,0 +1,405 @@
+//! Server-Sent Events (`text/event-stream`): an incremental parser of event
+//! streams and a reader of events as they arrive, reconnecting with
+//! `Last-Event-ID` when the stream ends.
+
+use std::fmt;
+use std::io::{BufReader, Read};
+use std::net::{Shutdown, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::client::{Client, Stream};
+use crate::error::{Error, Result};
+use crate::h1::BodyReader;
+use crate::http::{Request, Response};
+
+/// Delay before reconnecting until the server sets another with `retry`.
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+	/// Type of the event, `message` unless given with `event:`.
+	pub event: String,
+	/// `data:` lines joined with line breaks.
+	pub data: String,
+	/// Last event ID of the stream when the event was dispatched.
+	pub id: Option<String>,
+	/// Time from the request to the event.
+	pub received: Duration,
+}
+
+/// `[type #id] data`, the data lines following on their own lines.
+impl fmt::Display for Event {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.id {
+			Some(id) => write!(f, "[{} #{id}] {}", self.event, self.data),
+			None => write!(f, "[{}] {}", self.event, self.data),
+		}
+	}
+}
+
+/// Parser of an event stream fed as it arrives.
+#[derive(Debug, Default)]
+pub struct Parser {
+	/// Start of a line not complete yet.
+	line: Vec<u8>,
+	/// The last line ended with CR, which a LF may follow.
+	after_cr: bool,
+	started: bool,
+	event: String,
+	data: String,
+	has_data: bool,
+	last_id: Option<String>,
+	retry: Option<Duration>,
+}
+
+impl Parser {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Parses the bytes following the previous ones, returning the events
+	/// they complete.
+	pub fn feed(&mut self, bytes: &[u8], received: Duration) -> Vec<Event> {
+		let mut bytes = bytes;
+		if !self.started && !bytes.is_empty() {
+			self.started = true;
+			bytes = bytes.strip_prefix("\u{feff}".as_bytes()).unwrap_or(bytes);
+		}
+		let mut events = Vec::new();
+		for &byte in bytes {
+			match byte {
+				b'\n' if self.after_cr => self.after_cr = false,
+				b'\r' | b'\n' => {
+					self.after_cr = byte == b'\r';
+					let line = std::mem::take(&mut self.line);
+					events.extend(self.line_done(&String::from_utf8_lossy(&line), received));
+				}
+				_ => {
+					self.after_cr = false;
+					self.line.push(byte);
+				}
+			}
+		}
+		events
+	}
+
+	/// ID of the last event, sent as `Last-Event-ID` when reconnecting.
+	pub fn last_event_id(&self) -> Option<&str> {
+		self.last_id.as_deref()
+	}
+
+	/// Reconnection delay set by the stream.
+	pub fn retry(&self) -> Option<Duration> {
+		self.retry
+	}
+
+	fn line_done(&mut self, line: &str, received: Duration) -> Option<Event> {
+		if line.is_empty() {
+			let event = std::mem::take(&mut self.event);
+			let data = std::mem::take(&mut self.data);
+			if !std::mem::take(&mut self.has_data) {
+				return None;
+			}
+			return Some(Event {
+				event: if event.is_empty() {
+					"message".into()
+				} else {
+					event
+				},
+				data,
+				id: self.last_id.clone(),
+				received,
+			});
+		}
+		let (field, value) = line.split_once(':').unwrap_or((line, ""));
+		let value = value.strip_prefix(' ').unwrap_or(value);
+		match field {
+			"event" => self.event = value.to_string(),
+			"data" => {
+				if self.has_data {
+					self.data.push('\n');
+				}
+				self.data.push_str(value);
+				self.has_data = true;
+			}
+			"id" if !value.contains('\0') => {
+				self.last_id = (!value.is_empty()).then(|| value.to_string());
+			}
+			"retry" => {
+				if let Ok(millis) = value.parse() {
+					self.retry = Some(Duration::from_millis(millis));
+				}
+			}
+			// comments start with a colon, unknown fields are ignored
+			_ => {}
+		}
+		None
+	}
+}
+
+/// Events of a whole event stream, all received at `received`.
+pub fn parse(body: &[u8], received: Duration) -> Vec<Event> {
+	let mut parser = Parser::new();
+	let mut events = parser.feed(body, received);
+	// the last event counts without its final blank line
+	events.extend(parser.feed(b"\n\n", received));
+	events
+}
+
+/// Returns true for a `text/event-stream` response.
+pub fn is_event_stream(response: &Response) -> bool {
+	response.headers.get("Content-Type").is_some_and(|value| {
+		let essence = value.split(';').next().unwrap_or_default();
+		essence.trim().eq_ignore_ascii_case("text/event-stream")
+	})
+}
+
+/// Event stream of a request, read as it arrives. When the stream ends it
+/// reconnects if asked to, after the delay set by the server, sending the
+/// last event ID it got.
+pub struct EventSource {
+	client: Client,
+	request: Request,
+	/// Head of the current response, with the bytes and events received
+	/// over all connections.
+	pub response: Response,
+	reconnect: bool,
+	start: Instant,
+	parser: Parser,
+	/// Events parsed and not returned yet.
+	pending: Vec<Event>,
+	/// Chunks of the body, read on a thread.
+	chunks: Option<Receiver<std::io::Result<Vec<u8>>>>,
+	socket: Option<TcpStream>,
+	/// When to connect again after the stream ended.
+	reconnect_at: Option<Instant>,
+	finished: bool,
+}
+
+impl EventSource {
+	/// Sends the request, asking for an event stream, resuming after
+	/// `last_event_id` if given. A response that is not an event stream is
+	/// read whole and ends the stream.
+	pub fn connect(
+		client: &Client,
+		request: &Request,
+		last_event_id: Option<String>,
+		reconnect: bool,
+	) -> Result<EventSource> {
+		let mut source = EventSource {
+			client: client.clone(),
+			request: request.clone(),
+			response: Response::default(),
+			reconnect,
+			start: Instant::now(),
+			parser: Parser {
+				last_id: last_event_id,
+				..Parser::default()
+			},
+			pending: Vec::new(),
+			chunks: None,
+			socket: None,
+			reconnect_at: None,
+			finished: false,
+		};
+		source.open()?;
+		Ok(source)
+	}
+
+	/// Returns true once the stream ended for good.
+	pub fn is_finished(&self) -> bool {
+		self.finished && self.pending.is_empty()
+	}
+
+	/// Returns the next event, waiting for it until `deadline` or for as
+	/// long as it takes without one. Returns `None` when none came in time
+	/// or the stream ended.
+	pub fn next(&mut self, deadline: Option<Instant>) -> Result<Option<Event>> {
+		loop {
+			if !self.pending.is_empty() {
+				return Ok(Some(self.pending.remove(0)));
+			}
+			if self.finished {
+				return Ok(None);
+			}
+			if let Some(at) = self.reconnect_at {
+				if deadline.is_some_and(|deadline| deadline < at) {
+					sleep_until(deadline);
+					return Ok(None);
+				}
+				sleep_until(Some(at));
+				self.reconnect_at = None;
+				match self.open() {
+					Ok(()) => continue,
+					// the server may be restarting
+					Err(Error::Io(_)) => {
+						self.schedule_reconnect();
+						continue;
+					}
+					Err(err) => return Err(err),
+				}
+			}
+			let Some(chunks) = &self.chunks else {
+				return Ok(None);
+			};
+			let chunk = match deadline {
+				Some(deadline) => {
+					let wait = deadline.saturating_duration_since(Instant::now());
+					match chunks.recv_timeout(wait) {
+						Ok(chunk) => Some(chunk),
+						Err(RecvTimeoutError::Timeout) => return Ok(None),
+						Err(RecvTimeoutError::Disconnected) => None,
+					}
+				}
+				None => chunks.recv().ok(),
+			};
+			match chunk {
+				Some(Ok(bytes)) => {
+					let received = self.start.elapsed();
+					self.response.body.extend_from_slice(&bytes);
+					let events = self.parser.feed(&bytes, received);
+					self.response.events.extend(events.iter().cloned());
+					self.pending.extend(events);
+				}
+				// the stream ended or broke off
+				Some(Err(_)) | None => {
+					self.chunks = None;
+					self.socket = None;
+					match self.reconnect {
+						true => self.schedule_reconnect(),
+						false => self.finished = true,
+					}
+				}
+			}
+		}
+	}
+
+	/// Sends the request and starts reading the body.
+	fn open(&mut self) -> Result<()> {
+		let mut request = self.request.clone();
+		if !request.headers.contains("Accept") {
+			request.headers.set("Accept", "text/event-stream");
+		}
+		request.headers.set("Cache-Control", "no-cache");
+		if let Some(id) = self.parser.last_event_id() {
+			request.headers.set("Last-Event-ID", id);
+		}
+		let (head, reader) = self.client.open(&request)?;
+		let mut body = BodyReader::new(reader, &head, &request.method)?;
+		let events = std::mem::take(&mut self.response.events);
+		let received = std::mem::take(&mut self.response.body);
+		self.response = Response {
+			events,
+			body: received,
+			..head
+		};
+		// 204 asks not to reconnect, other responses are not streams
+		if self.response.status != 200 || !is_event_stream(&self.response) {
+			let mut rest = Vec::new();
+			body.read_to_end(&mut rest)?;
+			self.response.body.extend_from_slice(&rest);
+			self.finished = true;
+			return Ok(());
+		}
+		self.parser = Parser {
+			last_id: self.parser.last_id.take(),
+			retry: self.parser.retry,
+			..Parser::default()
+		};
+		// idle streams are normal, deadlines are up to the reader
+		let socket = body.get_ref().get_ref().socket();
+		socket.set_read_timeout(None)?;
+		self.socket = socket.try_clone().ok();
+		self.chunks = Some(read_chunks(body));
+		Ok(())
+	}
+
+	fn schedule_reconnect(&mut self) {
+		let delay = self.parser.retry().unwrap_or(DEFAULT_RETRY);
+		self.reconnect_at = Some(Instant::now() + delay);
+	}
+}
+
+impl Drop for EventSource {
+	fn drop(&mut self) {
+		// unblocks the thread reading the body
+		if let Some(socket) = &self.socket {
+			let _ = socket.shutdown(Shutdown::Both);
+		}
+	}
+}
+
+/// Reads a body on a thread, sending its chunks as they arrive.
+fn read_chunks(mut body: BodyReader<BufReader<Stream>>) -> Receiver<std::io::Result<Vec<u8>>> {
+	let (sender, receiver) = mpsc::channel();
+	std::thread::spawn(move || {
+		let mut buffer = [0; 16 * 1024];
+		loop {
+			let chunk = match body.read(&mut buffer) {
+				Ok(0) => break,
+				Ok(count) => Ok(buffer[..count].to_vec()),
+				Err(err) => Err(err),
+			};
+			let failed = chunk.is_err();
+			if sender.send(chunk).is_err() || failed {
+				break;
+			}
+		}
+	});
+	receiver
+}
+
+fn sleep_until(deadline: Option<Instant>) {
+	if let Some(deadline) = deadline {
+		std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_streams_incrementally() {
+		let mut parser = Parser::new();
+		let at = Duration::from_millis(5);
+		assert!(parser
+			.feed(b"\xef\xbb\xbf: comment\r\nevent: up", at)
+			.is_empty());
+		assert!(parser.feed(b"date\rdata: a\r", at).is_empty());
+		let events = parser.feed(b"\ndata:b\n\nid: 7\ndata\n\n", at);
+		assert_eq!(
+			events,
+			[
+				Event {
+					event: "update".into(),
+					data: "a\nb".into(),
+					id: None,
+					received: at,
+				},
+				Event {
+					event: "message".into(),
+					data: String::new(),
+					id: Some("7".into()),
+					received: at,
+				},
+			]
+		);
+		assert_eq!(parser.last_event_id(), Some("7"));
+
+		// no data, no event; the type does not carry over
+		assert!(parser
+			.feed(b"event: x\nretry: 1500\nretry: soon\n\n", at)
+			.is_empty());
+		assert_eq!(parser.retry(), Some(Duration::from_millis(1500)));
+		let events = parser.feed(b"data: z\nid\n\n", at);
+		assert_eq!(events[0].event, "message");
+		assert_eq!(events[0].id, None);
+		assert_eq!(events[0].to_string(), "[message] z");
+
+		let events = parse(b"id: 1\nevent: done\ndata: {}", Duration::ZERO);
+		assert_eq!(events.len(), 1);
+		assert_eq!(events[0].to_string(), "[done #1] {}");
+	}
+}
@@ -0,0 +1,241 @@
+//! `{{variable}}` interpolation used by request files.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::error::ParseError;
+use crate::util;
+
+/// Named values available to templates.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Variables {
+	values: BTreeMap<String, String>,
+}
+
+impl Variables {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.values.get(name).map(|s| s.as_str())
+	}
+
+	pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+		self.values.insert(name.into(), value.into());
+	}
+
+	pub fn contains(&self, name: &str) -> bool {
+		self.values.contains_key(name)
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.values.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+	}
+}
+
+/// Text with embedded `{{expr}}` placeholders. An expression is either a
+/// variable name or a `$function` with whitespace separated arguments:
+///
+/// - `$timestamp`: current Unix time in seconds
+/// - `$datetime`: current UTC time in RFC 3339 format
+/// - `$randomInt MIN MAX`: random integer in `MIN..MAX`
+/// - `$guid` or `$uuid`: random UUID
+/// - `$processEnv NAME`: environment variable of the webcat process
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+	parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+	Text(String),
+	Expr {
+		expr: String,
+		line: usize,
+		column: usize,
+	},
+}
+
+impl Template {
+	/// Parses `text` which starts at the given line and column of its source,
+	/// so errors can point to the exact location.
+	pub fn parse(text: &str, line: usize, column: usize) -> Result<Template, ParseError> {
+		let mut parts = Vec::new();
+		let (mut line, mut column) = (line, column);
+		let mut rest = text;
+		while let Some(start) = rest.find("{{") {
+			let (before, after) = rest.split_at(start);
+			if !before.is_empty() {
+				parts.push(Part::Text(before.to_string()));
+			}
+			advance(&mut line, &mut column, before);
+			let end = after
+				.find("}}")
+				.ok_or_else(|| ParseError::new(line, column, "unterminated `{{`"))?;
+			let expr = after[2..end].trim();
+			if expr.is_empty() || expr.contains(['{', '\n']) {
+				return Err(ParseError::new(
+					line,
+					column,
+					"invalid expression in `{{ }}`",
+				));
+			}
+			parts.push(Part::Expr {
+				expr: expr.to_string(),
+				line,
+				column,
+			});
+			advance(&mut line, &mut column, &after[..end + 2]);
+			rest = &after[end + 2..];
+		}
+		if !rest.is_empty() {
+			parts.push(Part::Text(rest.to_string()));
+		}
+		Ok(Template { parts })
+	}
+
+	/// Template without placeholders.
+	pub fn literal(text: &str) -> Template {
+		Template {
+			parts: vec![Part::Text(text.to_string())],
+		}
+	}
+
+	/// Returns true if the template has no placeholders.
+	pub fn is_literal(&self) -> bool {
+		self.parts.iter().all(|p| matches!(p, Part::Text(_)))
+	}
+
+	/// Names of the variables referenced by the template.
+	pub fn variables(&self) -> impl Iterator<Item = &str> {
+		self.parts.iter().filter_map(|part| match part {
+			Part::Expr { expr, .. } if !expr.starts_with('$') => Some(expr.as_str()),
+			_ => None,
+		})
+	}
+
+	pub fn render(&self, vars: &Variables) -> Result<String, ParseError> {
+		let mut output = String::new();
+		for part in &self.parts {
+			match part {
+				Part::Text(text) => output.push_str(text),
+				Part::Expr { expr, line, column } => {
+					let value =
+						evaluate(expr, vars).map_err(|msg| ParseError::new(*line, *column, msg))?;
+					output.push_str(&value);
+				}
+			}
+		}
+		Ok(output)
+	}
+}
+
+/// Displays the template as it was written.
+impl fmt::Display for Template {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for part in &self.parts {
+			match part {
+				Part::Text(text) => f.write_str(text)?,
+				Part::Expr { expr, .. } => write!(f, "{{{{{expr}}}}}")?,
+			}
+		}
+		Ok(())
+	}
+}
+
+fn advance(line: &mut usize, column: &mut usize, text: &str) {
+	for c in text.chars() {
+		if c == '\n' {
+			*line += 1;
+			*column = 1;
+		} else {
+			*column += 1;
+		}
+	}
+}
+
+fn evaluate(expr: &str, vars: &Variables) -> Result<String, String> {
+	let Some(function) = expr.strip_prefix('$') else {
+		return vars
+			.get(expr)
+			.map(|value| value.to_string())
+			.ok_or_else(|| format!("undefined variable `{expr}`"));
+	};
+	let mut args = function.split_whitespace();
+	let name = args.next().unwrap_or_default();
+	let args: Vec<&str> = args.collect();
+	match (name, args.as_slice()) {
+		("timestamp", []) => Ok(util::unix_time().to_string()),
+		("datetime", []) => Ok(util::format_rfc3339(util::unix_time())),
+		("guid" | "uuid", []) => Ok(util::random_uuid()),
+		("randomInt", [min, max]) => {
+			let min: i64 = min.parse().map_err(|_| format!("invalid number `{min}`"))?;
+			let max: i64 = max.parse().map_err(|_| format!("invalid number `{max}`"))?;
+			if max <= min {
+				return Err(format!("empty range {min}..{max}"));
+			}
+			// in i128, as the span of the widest range does not fit in i64
+			let span = (i128::from(max) - i128::from(min)) as u64;
+			Ok((i128::from(min) + i128::from(util::random_u64() % span)).to_string())
+		}
+		("processEnv", [name]) => {
+			std::env::var(name).map_err(|_| format!("environment variable `{name}` is not set"))
+		}
+		_ => Err(format!("unknown function `${function}`")),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn vars() -> Variables {
+		let mut vars = Variables::new();
+		vars.set("host", "localhost");
+		vars.set("id", "42");
+		vars
+	}
+
+	#[test]
+	fn renders_variables() {
+		let template = Template::parse("http://{{host}}/items/{{ id }}", 1, 1).unwrap();
+		assert_eq!(
+			template.render(&vars()).unwrap(),
+			"http://localhost/items/42"
+		);
+		assert_eq!(template.variables().collect::<Vec<_>>(), ["host", "id"]);
+		assert!(!template.is_literal());
+		assert!(Template::parse("plain", 1, 1).unwrap().is_literal());
+	}
+
+	#[test]
+	fn reports_positions() {
+		let err = Template::parse("a\nbc {{x", 3, 5).unwrap_err();
+		assert_eq!((err.line, err.column), (4, 4));
+
+		let template = Template::parse("{\n  \"a\": {{missing}}\n}", 10, 1).unwrap();
+		let err = template.render(&vars()).unwrap_err();
+		assert_eq!((err.line, err.column), (11, 8));
+		assert_eq!(err.message, "undefined variable `missing`");
+	}
+
+	#[test]
+	fn evaluates_functions() {
+		let vars = vars();
+		let render = |text: &str| Template::parse(text, 1, 1).unwrap().render(&vars);
+		let value: i64 = render("{{$randomInt 5 7}}").unwrap().parse().unwrap();
+		assert!((5..7).contains(&value));
+		assert_eq!(render("{{$uuid}}").unwrap().len(), 36);
+		assert!(render("{{$timestamp}}").unwrap().parse::<u64>().is_ok());
+		assert!(render("{{$nope}}").is_err());
+		assert!(render("{{$randomInt 5}}").is_err());
+		let bounds = format!("{{{{$randomInt {} {}}}}}", i64::MIN, i64::MAX);
+		for _ in 0..100 {
+			let value: i64 = render(&bounds).unwrap().parse().unwrap();
+			assert!(value < i64::MAX);
+		}
+		let top = format!("{{{{$randomInt {} {}}}}}", i64::MAX - 1, i64::MAX);
+		assert_eq!(render(&top).unwrap(), (i64::MAX - 1).to_string());
+	}
+}
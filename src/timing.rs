@@ -0,0 +1,148 @@
+//! Per request timing breakdown.
+
+use std::fmt;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+/// Width of the bars drawn by the waterfall.
+const BAR_WIDTH: usize = 40;
+
+/// Consecutive phases of a request. They add up to the total time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timings {
+	/// Host name resolution.
+	pub dns: Duration,
+	/// TCP handshake.
+	pub connect: Duration,
+	/// TLS handshake, zero for plain connections.
+	pub tls: Duration,
+	/// From sending the request until the first response byte arrived.
+	pub ttfb: Duration,
+	/// Reading the rest of the response.
+	pub transfer: Duration,
+}
+
+impl Timings {
+	pub fn total(&self) -> Duration {
+		self.dns + self.connect + self.tls + self.ttfb + self.transfer
+	}
+
+	/// Phases with their labels, in order.
+	pub fn phases(&self) -> [(&'static str, Duration); 5] {
+		[
+			("DNS lookup", self.dns),
+			("TCP connect", self.connect),
+			("TLS handshake", self.tls),
+			("Time to first byte", self.ttfb),
+			("Content transfer", self.transfer),
+		]
+	}
+
+	/// Phase durations and the total in milliseconds.
+	pub fn to_json(&self) -> Value {
+		json!({
+			"dns": millis(self.dns),
+			"connect": millis(self.connect),
+			"tls": millis(self.tls),
+			"ttfb": millis(self.ttfb),
+			"transfer": millis(self.transfer),
+			"total": millis(self.total()),
+		})
+	}
+
+	/// Displays the phases as a waterfall, each bar starting where the
+	/// previous phase ended.
+	pub fn waterfall(&self) -> Waterfall<'_> {
+		Waterfall(self)
+	}
+}
+
+/// Milliseconds with microsecond precision.
+fn millis(duration: Duration) -> f64 {
+	duration.as_micros() as f64 / 1000.0
+}
+
+pub struct Waterfall<'a>(&'a Timings);
+
+impl fmt::Display for Waterfall<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let total = self.0.total();
+		let scale = |d: Duration| match total.as_nanos() {
+			0 => 0,
+			t => (d.as_nanos() * BAR_WIDTH as u128 / t) as usize,
+		};
+		let mut elapsed = Duration::ZERO;
+		for (label, duration) in self.0.phases() {
+			let start = scale(elapsed);
+			elapsed += duration;
+			// every phase that took time gets at least one block
+			let end = scale(elapsed).max(start + usize::from(!duration.is_zero()));
+			let end = end.min(BAR_WIDTH);
+			writeln!(
+				f,
+				"{label:<20}{:>10} |{}{}{}|",
+				format!("{:.1} ms", millis(duration)),
+				" ".repeat(start.min(end)),
+				"█".repeat(end.saturating_sub(start)),
+				" ".repeat(BAR_WIDTH - end),
+			)?;
+		}
+		writeln!(
+			f,
+			"{:<20}{:>10}",
+			"Total",
+			format!("{:.1} ms", millis(total))
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn ms(value: u64) -> Duration {
+		Duration::from_millis(value)
+	}
+
+	#[test]
+	fn draws_waterfall() {
+		let timings = Timings {
+			dns: ms(10),
+			connect: ms(10),
+			tls: Duration::ZERO,
+			ttfb: ms(40),
+			transfer: Duration::from_micros(100),
+		};
+		let text = timings.waterfall().to_string();
+		let lines: Vec<&str> = text.lines().collect();
+		assert_eq!(
+			lines[0],
+			format!(
+				"DNS lookup             10.0 ms |{}{}|",
+				"█".repeat(6),
+				" ".repeat(34)
+			)
+		);
+		assert_eq!(
+			lines[2],
+			format!("TLS handshake           0.0 ms |{}|", " ".repeat(40))
+		);
+		assert!(lines[3].contains(&format!("|{}{} |", " ".repeat(13), "█".repeat(26))));
+		assert!(lines[4].ends_with(&format!("{}█|", " ".repeat(39))));
+		assert_eq!(lines[5], "Total                  60.1 ms");
+	}
+
+	#[test]
+	fn exports_milliseconds() {
+		let timings = Timings {
+			dns: Duration::from_micros(1500),
+			ttfb: ms(20),
+			..Default::default()
+		};
+		assert_eq!(
+			timings.to_json(),
+			json!({"dns": 1.5, "connect": 0.0, "tls": 0.0, "ttfb": 20.0, "transfer": 0.0, "total": 21.5})
+		);
+	}
+}
@@ -0,0 +1,333 @@
+//! TLS client support built on rustls.
+
+mod x509;
+
+use std::fmt;
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::{
+	ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme,
+	StreamOwned,
+};
+
+use crate::error::{Error, Result};
+
+pub use x509::{fingerprint, CertInfo};
+
+/// Client certificate presented for mutual TLS.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientCert {
+	/// PEM certificate chain and PEM private key. Both may be the same file.
+	Pem { cert: PathBuf, key: PathBuf },
+	/// PKCS#12 archive (`.p12` or `.pfx`) with the certificate and key.
+	Pkcs12 { path: PathBuf, password: String },
+}
+
+/// TLS settings of a client.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+	/// PEM bundles trusted in addition to the built-in roots.
+	pub ca_files: Vec<PathBuf>,
+	pub client_cert: Option<ClientCert>,
+	/// SHA-256 fingerprints of accepted server certificates. When not empty
+	/// the server certificate must match one of them.
+	pub pins: Vec<[u8; 32]>,
+	/// Disables certificate verification. Pins are still enforced.
+	pub insecure: bool,
+}
+
+/// Parameters negotiated by a TLS handshake.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsInfo {
+	pub version: String,
+	pub cipher: String,
+	pub alpn: Option<String>,
+	/// Certificates sent by the server, leaf first.
+	pub chain: Vec<CertInfo>,
+}
+
+impl fmt::Display for TlsInfo {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "* TLS version: {}", self.version)?;
+		writeln!(f, "* Cipher: {}", self.cipher)?;
+		if let Some(alpn) = &self.alpn {
+			writeln!(f, "* ALPN: {alpn}")?;
+		}
+		for (index, cert) in self.chain.iter().enumerate() {
+			writeln!(f, "* Certificate {index}:")?;
+			writeln!(f, "*   subject: {}", cert.subject)?;
+			writeln!(f, "*   issuer: {}", cert.issuer)?;
+			writeln!(f, "*   valid: {} to {}", cert.not_before, cert.not_after)?;
+			if !cert.names.is_empty() {
+				writeln!(f, "*   names: {}", cert.names.join(", "))?;
+			}
+			writeln!(f, "*   sha256: {}", cert.sha256)?;
+		}
+		Ok(())
+	}
+}
+
+pub type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+impl TlsConfig {
+	/// Builds the rustls configuration, loading all referenced files.
+	pub fn client_config(&self, alpn: &[&str]) -> Result<Arc<ClientConfig>> {
+		let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+		let mut roots = RootCertStore {
+			roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+		};
+		for path in &self.ca_files {
+			for cert in load_certs(path)? {
+				roots.add(cert).map_err(|err| {
+					Error::Tls(format!(
+						"invalid CA certificate in `{}`: {err}",
+						path.display()
+					))
+				})?;
+			}
+		}
+		let webpki = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+			.build()
+			.map_err(|err| Error::Tls(err.to_string()))?;
+		let verifier = Arc::new(Verifier {
+			webpki,
+			provider: provider.clone(),
+			pins: self.pins.clone(),
+			insecure: self.insecure,
+		});
+
+		let builder = ClientConfig::builder_with_provider(provider)
+			.with_safe_default_protocol_versions()
+			.map_err(|err| Error::Tls(err.to_string()))?
+			.dangerous()
+			.with_custom_certificate_verifier(verifier);
+		let mut config = match &self.client_cert {
+			None => builder.with_no_client_auth(),
+			Some(cert) => {
+				let (chain, key) = load_client_cert(cert)?;
+				builder
+					.with_client_auth_cert(chain, key)
+					.map_err(|err| Error::Tls(format!("invalid client certificate: {err}")))?
+			}
+		};
+		config.alpn_protocols = alpn.iter().map(|p| p.as_bytes().to_vec()).collect();
+		Ok(Arc::new(config))
+	}
+}
+
+/// Performs the TLS handshake over a connected socket.
+pub fn connect(config: Arc<ClientConfig>, host: &str, socket: TcpStream) -> Result<TlsStream> {
+	let name = ServerName::try_from(host.to_string())
+		.map_err(|_| Error::Tls(format!("invalid server name `{host}`")))?;
+	let connection =
+		ClientConnection::new(config, name).map_err(|err| Error::Tls(err.to_string()))?;
+	let mut stream = StreamOwned::new(connection, socket);
+	while stream.conn.is_handshaking() {
+		stream
+			.conn
+			.complete_io(&mut stream.sock)
+			.map_err(|err| match err.into_inner() {
+				Some(inner) => match inner.downcast::<rustls::Error>() {
+					Ok(tls) => Error::Tls(tls.to_string()),
+					Err(other) => Error::Io(std::io::Error::other(other)),
+				},
+				None => Error::Tls("handshake failed".into()),
+			})?;
+	}
+	Ok(stream)
+}
+
+/// Describes the negotiated session of a connected stream.
+pub fn session_info(connection: &ClientConnection) -> TlsInfo {
+	let version = match connection.protocol_version() {
+		Some(rustls::ProtocolVersion::TLSv1_3) => "TLSv1.3".to_string(),
+		Some(rustls::ProtocolVersion::TLSv1_2) => "TLSv1.2".to_string(),
+		Some(other) => format!("{other:?}"),
+		None => "unknown".to_string(),
+	};
+	let cipher = connection
+		.negotiated_cipher_suite()
+		.map(|suite| format!("{:?}", suite.suite()))
+		.unwrap_or_default();
+	TlsInfo {
+		version,
+		cipher,
+		alpn: connection
+			.alpn_protocol()
+			.map(|p| String::from_utf8_lossy(p).into_owned()),
+		chain: connection
+			.peer_certificates()
+			.unwrap_or_default()
+			.iter()
+			.map(|cert| CertInfo::from_der(cert))
+			.collect(),
+	}
+}
+
+/// Parses a SHA-256 fingerprint given as hex, optionally separated by
+/// colons and prefixed with `sha256:`.
+pub fn parse_pin(text: &str) -> Result<[u8; 32]> {
+	let hex: String = text
+		.trim()
+		.trim_start_matches("sha256:")
+		.chars()
+		.filter(|c| *c != ':')
+		.collect();
+	let invalid = || Error::Usage(format!("invalid SHA-256 fingerprint `{text}`"));
+	if hex.len() != 64 {
+		return Err(invalid());
+	}
+	let mut pin = [0u8; 32];
+	for (index, byte) in pin.iter_mut().enumerate() {
+		*byte = u8::from_str_radix(hex.get(index * 2..index * 2 + 2).ok_or_else(invalid)?, 16)
+			.map_err(|_| invalid())?;
+	}
+	Ok(pin)
+}
+
+fn load_certs(path: &PathBuf) -> Result<Vec<CertificateDer<'static>>> {
+	let certs = CertificateDer::pem_file_iter(path)
+		.and_then(|iter| iter.collect::<std::result::Result<Vec<_>, _>>())
+		.map_err(|err| {
+			Error::Tls(format!(
+				"cannot load certificates from `{}`: {err}",
+				path.display()
+			))
+		})?;
+	if certs.is_empty() {
+		return Err(Error::Tls(format!(
+			"no certificates found in `{}`",
+			path.display()
+		)));
+	}
+	Ok(certs)
+}
+
+fn load_client_cert(
+	cert: &ClientCert,
+) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+	match cert {
+		ClientCert::Pem { cert, key } => {
+			let chain = load_certs(cert)?;
+			let key = PrivateKeyDer::from_pem_file(key).map_err(|err| {
+				Error::Tls(format!(
+					"cannot load private key from `{}`: {err}",
+					key.display()
+				))
+			})?;
+			Ok((chain, key))
+		}
+		ClientCert::Pkcs12 { path, password } => {
+			let data = std::fs::read(path)?;
+			let store = p12_keystore::KeyStore::from_pkcs12(&data, password).map_err(|err| {
+				Error::Tls(format!(
+					"cannot read PKCS#12 file `{}`: {err}",
+					path.display()
+				))
+			})?;
+			let (_, chain) = store
+				.private_key_chain()
+				.ok_or_else(|| Error::Tls(format!("no private key in `{}`", path.display())))?;
+			let certs = chain
+				.chain()
+				.iter()
+				.map(|cert| CertificateDer::from(cert.as_der().to_vec()))
+				.collect();
+			let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(chain.key().to_vec()));
+			Ok((certs, key))
+		}
+	}
+}
+
+/// Verifies server certificates with webpki, optionally skipping the
+/// verification and enforcing fingerprint pins.
+#[derive(Debug)]
+struct Verifier {
+	webpki: Arc<WebPkiServerVerifier>,
+	provider: Arc<CryptoProvider>,
+	pins: Vec<[u8; 32]>,
+	insecure: bool,
+}
+
+impl ServerCertVerifier for Verifier {
+	fn verify_server_cert(
+		&self,
+		end_entity: &CertificateDer<'_>,
+		intermediates: &[CertificateDer<'_>],
+		server_name: &ServerName<'_>,
+		ocsp_response: &[u8],
+		now: UnixTime,
+	) -> std::result::Result<ServerCertVerified, rustls::Error> {
+		if !self.pins.is_empty() {
+			let digest = ring::digest::digest(&ring::digest::SHA256, end_entity);
+			if !self.pins.iter().any(|pin| pin[..] == *digest.as_ref()) {
+				return Err(rustls::Error::General(format!(
+					"server certificate fingerprint {} does not match the pinned fingerprint",
+					fingerprint(end_entity)
+				)));
+			}
+		}
+		if self.insecure {
+			return Ok(ServerCertVerified::assertion());
+		}
+		self.webpki
+			.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+	}
+
+	fn verify_tls12_signature(
+		&self,
+		message: &[u8],
+		cert: &CertificateDer<'_>,
+		dss: &DigitallySignedStruct,
+	) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+		verify_tls12_signature(
+			message,
+			cert,
+			dss,
+			&self.provider.signature_verification_algorithms,
+		)
+	}
+
+	fn verify_tls13_signature(
+		&self,
+		message: &[u8],
+		cert: &CertificateDer<'_>,
+		dss: &DigitallySignedStruct,
+	) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+		verify_tls13_signature(
+			message,
+			cert,
+			dss,
+			&self.provider.signature_verification_algorithms,
+		)
+	}
+
+	fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+		self.provider
+			.signature_verification_algorithms
+			.supported_schemes()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_pins() {
+		let hex = "AB".repeat(32);
+		assert_eq!(parse_pin(&hex).unwrap(), [0xab; 32]);
+		let colons = vec!["0f"; 32].join(":");
+		assert_eq!(parse_pin(&format!("sha256:{colons}")).unwrap(), [0x0f; 32]);
+		assert!(parse_pin("abcd").is_err());
+		assert!(parse_pin(&"zz".repeat(32)).is_err());
+	}
+}
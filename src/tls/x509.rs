@@ -0,0 +1,232 @@
+//! Just enough DER parsing to describe X.509 certificates.
+
+use std::fmt::Write;
+
+/// Human readable summary of a certificate.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CertInfo {
+	pub subject: String,
+	pub issuer: String,
+	pub not_before: String,
+	pub not_after: String,
+	/// DNS names and IP addresses from the subject alternative names.
+	pub names: Vec<String>,
+	/// SHA-256 fingerprint of the DER certificate, as colon separated hex.
+	pub sha256: String,
+}
+
+impl CertInfo {
+	/// Describes a DER encoded certificate. Fields that cannot be parsed are
+	/// left empty, only the fingerprint is always available.
+	pub fn from_der(der: &[u8]) -> CertInfo {
+		let mut info = CertInfo {
+			sha256: fingerprint(der),
+			..Default::default()
+		};
+		let _ = parse_certificate(der, &mut info);
+		info
+	}
+}
+
+/// SHA-256 fingerprint as upper case hex bytes separated by colons.
+pub fn fingerprint(der: &[u8]) -> String {
+	let digest = ring::digest::digest(&ring::digest::SHA256, der);
+	let hex: Vec<String> = digest.as_ref().iter().map(|b| format!("{b:02X}")).collect();
+	hex.join(":")
+}
+
+fn parse_certificate(der: &[u8], info: &mut CertInfo) -> Option<()> {
+	let (_, cert, _) = read(der, 0x30)?;
+	let (_, mut tbs, _) = read(cert, 0x30)?;
+	if tbs.first() == Some(&0xa0) {
+		tbs = next(tbs)?.2;
+	}
+	let (_, _serial, rest) = read(tbs, 0x02)?;
+	let (_, _algorithm, rest) = read(rest, 0x30)?;
+	let (_, issuer, rest) = read(rest, 0x30)?;
+	info.issuer = format_name(issuer)?;
+	let (_, validity, rest) = read(rest, 0x30)?;
+	let (tag, start, after) = next(validity)?;
+	info.not_before = format_time(tag, start)?;
+	let (tag, end, _) = next(after)?;
+	info.not_after = format_time(tag, end)?;
+	let (_, subject, mut rest) = read(rest, 0x30)?;
+	info.subject = format_name(subject)?;
+
+	while let Some((tag, content, after)) = next(rest) {
+		rest = after;
+		if tag != 0xa3 {
+			continue;
+		}
+		let (_, mut extensions, _) = read(content, 0x30)?;
+		while let Some((_, extension, after)) = next(extensions) {
+			extensions = after;
+			let (_, oid, mut value) = read(extension, 0x06)?;
+			if value.first() == Some(&0x01) {
+				value = next(value)?.2;
+			}
+			if oid == [0x55, 0x1d, 0x11] {
+				let (_, octets, _) = read(value, 0x04)?;
+				let (_, mut names, _) = read(octets, 0x30)?;
+				while let Some((tag, name, after)) = next(names) {
+					names = after;
+					match (tag, name.len()) {
+						(0x82, _) => info.names.push(String::from_utf8_lossy(name).into_owned()),
+						(0x87, 4) => info
+							.names
+							.push(format!("{}.{}.{}.{}", name[0], name[1], name[2], name[3])),
+						(0x87, 16) => {
+							let addr: [u8; 16] = name.try_into().ok()?;
+							info.names.push(std::net::Ipv6Addr::from(addr).to_string());
+						}
+						_ => {}
+					}
+				}
+			}
+		}
+	}
+	Some(())
+}
+
+/// Reads the next TLV, returning tag, content and the remaining input.
+fn next(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+	let tag = *data.first()?;
+	let first = *data.get(1)? as usize;
+	let (len, header) = if first < 0x80 {
+		(first, 2)
+	} else {
+		let count = first & 0x7f;
+		if count == 0 || count > 4 {
+			return None;
+		}
+		let bytes = data.get(2..2 + count)?;
+		(
+			bytes.iter().fold(0usize, |acc, b| acc << 8 | *b as usize),
+			2 + count,
+		)
+	};
+	let content = data.get(header..header + len)?;
+	Some((tag, content, &data[header + len..]))
+}
+
+/// Reads the next TLV, which must have the given tag.
+fn read(data: &[u8], tag: u8) -> Option<(u8, &[u8], &[u8])> {
+	next(data).filter(|(actual, _, _)| *actual == tag)
+}
+
+fn format_name(mut name: &[u8]) -> Option<String> {
+	let mut parts = Vec::new();
+	while let Some((_, set, after)) = next(name) {
+		name = after;
+		let (_, attribute, _) = read(set, 0x30)?;
+		let (_, oid, value) = read(attribute, 0x06)?;
+		let (tag, value, _) = next(value)?;
+		let key = match oid {
+			[0x55, 0x04, 0x03] => "CN".to_string(),
+			[0x55, 0x04, 0x06] => "C".to_string(),
+			[0x55, 0x04, 0x07] => "L".to_string(),
+			[0x55, 0x04, 0x08] => "ST".to_string(),
+			[0x55, 0x04, 0x0a] => "O".to_string(),
+			[0x55, 0x04, 0x0b] => "OU".to_string(),
+			_ => format_oid(oid),
+		};
+		let value = if tag == 0x1e {
+			let units: Vec<u16> = value
+				.chunks(2)
+				.map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]))
+				.collect();
+			String::from_utf16_lossy(&units)
+		} else {
+			String::from_utf8_lossy(value).into_owned()
+		};
+		parts.push(format!("{key}={value}"));
+	}
+	Some(parts.join(", "))
+}
+
+fn format_oid(oid: &[u8]) -> String {
+	let mut text = String::new();
+	let mut value = 0u64;
+	for byte in oid {
+		value = value << 7 | (*byte & 0x7f) as u64;
+		if byte & 0x80 != 0 {
+			continue;
+		}
+		if text.is_empty() {
+			let first = (value / 40).min(2);
+			let _ = write!(text, "{first}.{}", value - first * 40);
+		} else {
+			let _ = write!(text, ".{value}");
+		}
+		value = 0;
+	}
+	text
+}
+
+fn format_time(tag: u8, value: &[u8]) -> Option<String> {
+	let text = std::str::from_utf8(value).ok()?;
+	let (year, rest) = match tag {
+		0x17 => {
+			let year: u32 = text.get(..2)?.parse().ok()?;
+			(
+				if year >= 50 { 1900 + year } else { 2000 + year },
+				&text[2..],
+			)
+		}
+		0x18 => (text.get(..4)?.parse().ok()?, &text[4..]),
+		_ => return None,
+	};
+	let field = |range: std::ops::Range<usize>| rest.get(range);
+	Some(format!(
+		"{year:04}-{}-{} {}:{}:{} UTC",
+		field(0..2)?,
+		field(2..4)?,
+		field(4..6)?,
+		field(6..8)?,
+		field(8..10)?
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn describes_generated_certificate() {
+		let mut params =
+			rcgen::CertificateParams::new(vec!["localhost".into(), "127.0.0.1".into()]).unwrap();
+		params
+			.distinguished_name
+			.push(rcgen::DnType::CommonName, "webcat test");
+		params
+			.distinguished_name
+			.push(rcgen::DnType::OrganizationName, "ax-lab");
+		params.not_before = rcgen::date_time_ymd(2020, 1, 2);
+		params.not_after = rcgen::date_time_ymd(2060, 3, 4);
+		let key = rcgen::KeyPair::generate().unwrap();
+		let cert = params.self_signed(&key).unwrap();
+
+		let info = CertInfo::from_der(cert.der());
+		assert_eq!(info.subject, "CN=webcat test, O=ax-lab");
+		assert_eq!(info.issuer, info.subject);
+		assert_eq!(info.not_before, "2020-01-02 00:00:00 UTC");
+		assert_eq!(info.not_after, "2060-03-04 00:00:00 UTC");
+		assert_eq!(info.names, ["localhost", "127.0.0.1"]);
+		assert_eq!(info.sha256.len(), 32 * 3 - 1);
+	}
+
+	#[test]
+	fn formats_oids() {
+		assert_eq!(
+			format_oid(&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d]),
+			"1.2.840.113549"
+		);
+	}
+
+	#[test]
+	fn tolerates_garbage() {
+		let info = CertInfo::from_der(b"\x30\x05garbage");
+		assert!(info.subject.is_empty());
+		assert!(!info.sha256.is_empty());
+	}
+}
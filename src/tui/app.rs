@@ -0,0 +1,1014 @@
+//! State of the terminal UI: the selected request, the text being edited,
+//! the history of responses, and how keys change them.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::Color;
+
+use super::collection::{self, Collection, Kind};
+use super::editor::{Editor, Move};
+use super::history::{self, History};
+use super::screen::{Rect, Screen, Style};
+use crate::capture::Capture;
+use crate::error::{Error, Result};
+use crate::httpfile;
+use crate::render;
+use crate::runner::{Outcome, Runner};
+use crate::template::Variables;
+use crate::util::{format_bytes, format_timestamp};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Focus {
+	Tree,
+	Editor,
+	Response,
+}
+
+/// Tabs of the response pane.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tab {
+	Body,
+	Headers,
+	Tests,
+	Timing,
+	History,
+}
+
+const TABS: [(Tab, &str); 5] = [
+	(Tab::Body, "Body"),
+	(Tab::Headers, "Headers"),
+	(Tab::Tests, "Tests"),
+	(Tab::Timing, "Timing"),
+	(Tab::History, "History"),
+];
+
+/// Request open in the editor: the lines of its block in its file.
+#[derive(Debug)]
+pub struct Open {
+	pub path: PathBuf,
+	pub start: usize,
+	pub end: usize,
+	pub editor: Editor,
+}
+
+/// Request being sent on another thread.
+#[derive(Debug)]
+struct Pending {
+	captures: Vec<Capture>,
+	receiver: Receiver<Outcome>,
+}
+
+pub struct App {
+	/// Directories and files the collection is read from.
+	paths: Vec<PathBuf>,
+	pub collection: Collection,
+	runner: Arc<Runner>,
+	/// Variables of the session, with the values captured so far.
+	pub variables: Variables,
+	pub focus: Focus,
+	/// Position of the selection among the visible items of the tree.
+	selected: usize,
+	tree_top: usize,
+	pub open: Option<Open>,
+	pub history: History,
+	/// Entry of the history shown in the response pane.
+	pub shown: Option<usize>,
+	pub tab: Tab,
+	scroll: usize,
+	/// Search of the history, with true while it is typed.
+	pub search: (String, bool),
+	/// Position of the selection among the matches of the search.
+	history_selected: usize,
+	/// Message of the status bar, with true for an error.
+	pub status: Option<(String, bool)>,
+	pending: Option<Pending>,
+	/// Set when an action losing the changes in the editor was asked once,
+	/// and is done if asked again right away.
+	confirm: bool,
+	pub quit: bool,
+}
+
+impl App {
+	pub fn new(paths: Vec<PathBuf>, runner: Runner) -> Self {
+		App {
+			collection: Collection::load(&paths),
+			paths,
+			variables: runner.variables.clone(),
+			runner: Arc::new(runner),
+			focus: Focus::Tree,
+			selected: 0,
+			tree_top: 0,
+			open: None,
+			history: History::default(),
+			shown: None,
+			tab: Tab::Body,
+			scroll: 0,
+			search: (String::new(), false),
+			history_selected: 0,
+			status: None,
+			pending: None,
+			confirm: false,
+			quit: false,
+		}
+	}
+
+	/// Returns true while a request is being sent.
+	pub fn is_sending(&self) -> bool {
+		self.pending.is_some()
+	}
+
+	fn error(&mut self, message: impl Into<String>) {
+		self.status = Some((message.into(), true));
+	}
+
+	fn info(&mut self, message: impl Into<String>) {
+		self.status = Some((message.into(), false));
+	}
+
+	fn modified(&self) -> bool {
+		self.open.as_ref().is_some_and(|open| open.editor.modified)
+	}
+
+	/// Asks for a second time before an action losing unsaved changes.
+	/// Returns true when the action can be done.
+	fn confirmed(&mut self, confirming: bool, action: &str) -> bool {
+		if !self.modified() || confirming {
+			return true;
+		}
+		self.confirm = true;
+		self.error(format!(
+			"unsaved changes in the editor, Ctrl-S saves them, {action} again discards them"
+		));
+		false
+	}
+
+	pub fn handle(&mut self, key: KeyEvent) {
+		let confirming = std::mem::take(&mut self.confirm);
+		// a message stays until the next key
+		self.status = None;
+		if self.search.1 {
+			self.search_key(key);
+			return;
+		}
+		let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+		match key.code {
+			KeyCode::Char('q' | 'c') if ctrl => {
+				self.quit = self.confirmed(confirming, "Ctrl-Q");
+				return;
+			}
+			KeyCode::Char('r') if ctrl => return self.send(),
+			KeyCode::F(5) => return self.send(),
+			KeyCode::Char('s') if ctrl => return self.save(),
+			KeyCode::Tab => {
+				self.focus = match self.focus {
+					Focus::Tree => Focus::Editor,
+					Focus::Editor => Focus::Response,
+					Focus::Response => Focus::Tree,
+				};
+				return;
+			}
+			KeyCode::BackTab => {
+				self.focus = match self.focus {
+					Focus::Tree => Focus::Response,
+					Focus::Editor => Focus::Tree,
+					Focus::Response => Focus::Editor,
+				};
+				return;
+			}
+			_ => {}
+		}
+		match self.focus {
+			Focus::Tree => self.tree_key(key, confirming),
+			Focus::Editor => self.editor_key(key),
+			Focus::Response => self.response_key(key),
+		}
+	}
+
+	fn tree_key(&mut self, key: KeyEvent, confirming: bool) {
+		let visible = self.collection.visible();
+		let Some(&index) = visible.get(self.selected) else {
+			if key.code == KeyCode::Char('r') {
+				self.reload();
+			}
+			return;
+		};
+		let last = visible.len() - 1;
+		match key.code {
+			KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+			KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+			KeyCode::PageUp => self.selected = self.selected.saturating_sub(10),
+			KeyCode::PageDown => self.selected = (self.selected + 10).min(last),
+			KeyCode::Home => self.selected = 0,
+			KeyCode::End => self.selected = last,
+			KeyCode::Char('r') => self.reload(),
+			KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+				let item = &mut self.collection.items[index];
+				match item.kind {
+					Kind::Request(_) => {
+						if self.confirmed(confirming, "Enter") {
+							self.open_request(index);
+						}
+					}
+					_ if key.code == KeyCode::Enter => item.expanded = !item.expanded,
+					_ => item.expanded = true,
+				}
+			}
+			KeyCode::Left | KeyCode::Char('h') => {
+				let item = &mut self.collection.items[index];
+				if item.expanded && !matches!(item.kind, Kind::Request(_)) {
+					item.expanded = false;
+				} else {
+					// to the parent
+					let depth = item.depth;
+					if let Some(parent) = visible[..self.selected]
+						.iter()
+						.rposition(|index| self.collection.items[*index].depth < depth)
+					{
+						self.selected = parent;
+					}
+				}
+			}
+			_ => {}
+		}
+	}
+
+	fn editor_key(&mut self, key: KeyEvent) {
+		if key.code == KeyCode::Esc {
+			self.focus = Focus::Tree;
+			return;
+		}
+		let Some(open) = &mut self.open else {
+			return;
+		};
+		let editor = &mut open.editor;
+		match key.code {
+			KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+				editor.insert(ch)
+			}
+			KeyCode::Enter => editor.newline(),
+			KeyCode::Backspace => editor.backspace(),
+			KeyCode::Delete => editor.delete(),
+			KeyCode::Up => editor.move_cursor(Move::Up),
+			KeyCode::Down => editor.move_cursor(Move::Down),
+			KeyCode::Left => editor.move_cursor(Move::Left),
+			KeyCode::Right => editor.move_cursor(Move::Right),
+			KeyCode::Home => editor.move_cursor(Move::Home),
+			KeyCode::End => editor.move_cursor(Move::End),
+			KeyCode::PageUp => editor.move_cursor(Move::PageUp(10)),
+			KeyCode::PageDown => editor.move_cursor(Move::PageDown(10)),
+			_ => {}
+		}
+	}
+
+	fn response_key(&mut self, key: KeyEvent) {
+		let tab = TABS
+			.iter()
+			.position(|(tab, _)| *tab == self.tab)
+			.unwrap_or(0);
+		let history = self.tab == Tab::History;
+		match key.code {
+			KeyCode::Left => self.set_tab(TABS[(tab + TABS.len() - 1) % TABS.len()].0),
+			KeyCode::Right => self.set_tab(TABS[(tab + 1) % TABS.len()].0),
+			KeyCode::Char(digit @ '1'..='5') => {
+				self.set_tab(TABS[usize::from(digit as u8 - b'1')].0);
+			}
+			KeyCode::Char('/') => {
+				self.set_tab(Tab::History);
+				self.search.1 = true;
+			}
+			KeyCode::Enter if history => {
+				let matches = self.history.search(&self.search.0);
+				if let Some(&entry) = matches.get(self.history_selected) {
+					self.shown = Some(entry);
+					self.set_tab(Tab::Body);
+				}
+			}
+			KeyCode::Up if history => {
+				self.history_selected = self.history_selected.saturating_sub(1)
+			}
+			KeyCode::Down if history => self.history_selected += 1,
+			KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+			KeyCode::Down => self.scroll += 1,
+			KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+			KeyCode::PageDown => self.scroll += 10,
+			KeyCode::Home => self.scroll = 0,
+			KeyCode::End => self.scroll = usize::MAX,
+			_ => {}
+		}
+	}
+
+	fn search_key(&mut self, key: KeyEvent) {
+		match key.code {
+			KeyCode::Enter => self.search.1 = false,
+			KeyCode::Esc => self.search = (String::new(), false),
+			KeyCode::Backspace => {
+				self.search.0.pop();
+			}
+			KeyCode::Char(ch) => self.search.0.push(ch),
+			_ => return,
+		}
+		self.history_selected = 0;
+	}
+
+	fn set_tab(&mut self, tab: Tab) {
+		self.tab = tab;
+		self.scroll = 0;
+	}
+
+	/// Reads the collection again from the disk.
+	fn reload(&mut self) {
+		self.collection = Collection::load(&self.paths);
+		self.selected = 0;
+		self.info("reloaded the request files");
+	}
+
+	/// Opens the request at an index of the collection in the editor.
+	fn open_request(&mut self, index: usize) {
+		let item = &self.collection.items[index];
+		let Kind::Request(section) = &item.kind else {
+			return;
+		};
+		let text = match std::fs::read_to_string(&item.path) {
+			Ok(text) => text,
+			Err(err) => return self.error(format!("cannot read `{}`: {err}", item.path.display())),
+		};
+		let lines: Vec<&str> = text.lines().collect();
+		let block = lines[section.start.min(lines.len())..section.end.min(lines.len())].join("\n");
+		self.open = Some(Open {
+			path: item.path.clone(),
+			start: section.start,
+			end: section.end,
+			editor: Editor::new(&block),
+		});
+		self.focus = Focus::Editor;
+	}
+
+	/// Text of the file of the open request, with the lines being edited.
+	fn edited_text(open: &Open) -> Result<String> {
+		let text = std::fs::read_to_string(&open.path)
+			.map_err(|err| Error::Usage(format!("cannot read `{}`: {err}", open.path.display())))?;
+		Ok(collection::splice(
+			&text,
+			open.start,
+			open.end,
+			&open.editor.lines,
+		))
+	}
+
+	/// Writes the open request to its file.
+	fn save(&mut self) {
+		let Some(open) = &mut self.open else {
+			return self.error("no request is open");
+		};
+		let saved = App::edited_text(open)
+			.and_then(|text| std::fs::write(&open.path, text).map_err(Error::from));
+		if let Err(err) = saved {
+			return self.error(err.to_string());
+		}
+		open.end = open.start + open.editor.lines.len();
+		open.editor.modified = false;
+		let (path, start) = (open.path.clone(), open.start);
+		self.collection.reload(&path);
+		self.select(&path, start);
+		self.info(format!("saved {}", path.display()));
+	}
+
+	/// Selects the request of a file whose block starts at a line.
+	fn select(&mut self, path: &Path, start: usize) {
+		if let Some(index) = self.collection.find(path, start) {
+			if let Some(position) = self.collection.visible().iter().position(|i| *i == index) {
+				self.selected = position;
+			}
+		}
+	}
+
+	/// Sends the request in the editor, with its unsaved changes, on
+	/// another thread.
+	pub fn send(&mut self) {
+		if self.pending.is_some() {
+			return self.error("a request is already being sent");
+		}
+		match self.start_sending() {
+			Ok(pending) => {
+				self.pending = Some(pending);
+				self.info("sending…");
+			}
+			Err(err) => self.error(err.to_string()),
+		}
+	}
+
+	fn start_sending(&self) -> Result<Pending> {
+		let open = self
+			.open
+			.as_ref()
+			.ok_or_else(|| Error::Usage("select a request to send".into()))?;
+		let text = App::edited_text(open)?;
+		let mut file = httpfile::parse(&open.path.display().to_string(), &text)?;
+		file.base_dir = open.path.parent().map(Path::to_path_buf);
+		let lines = open.start + 1..=open.start + open.editor.lines.len();
+		let index = file
+			.requests
+			.iter()
+			.position(|def| lines.contains(&def.line))
+			.ok_or_else(|| Error::Usage("no request in the editor".into()))?;
+
+		// like a run of the file, variables using a value not captured yet
+		// are left out
+		let mut vars = self.variables.clone();
+		let pending: Vec<String> = file
+			.captured_names()
+			.filter(|name| !vars.contains(name))
+			.map(str::to_string)
+			.collect();
+		let pending: Vec<&str> = pending.iter().map(String::as_str).collect();
+		file.resolve_variables_except(&mut vars, &pending)?;
+		let request = file.build(&file.requests[index], &vars)?;
+
+		let captures = file.requests[index].captures.clone();
+		let (sender, receiver) = mpsc::channel();
+		let runner = self.runner.clone();
+		thread::spawn(move || {
+			let outcome = runner.execute_def(&file, &file.requests[index], request, &vars);
+			let _ = sender.send(outcome);
+		});
+		Ok(Pending { captures, receiver })
+	}
+
+	/// Takes the outcome of the request being sent once it arrived, and
+	/// keeps its captures. Returns true when it did.
+	pub fn poll(&mut self) -> bool {
+		let Some(pending) = &self.pending else {
+			return false;
+		};
+		let mut outcome = match pending.receiver.try_recv() {
+			Ok(outcome) => outcome,
+			Err(TryRecvError::Empty) => return false,
+			Err(TryRecvError::Disconnected) => {
+				self.pending = None;
+				self.error("the request was interrupted");
+				return true;
+			}
+		};
+		let captures = self.pending.take().map(|p| p.captures).unwrap_or_default();
+		if let Ok(response) = &outcome.response {
+			for capture in &captures {
+				match capture.extract(response) {
+					Ok(value) => self.variables.set(capture.name.clone(), value),
+					Err(message) => outcome.assertions.push(capture.failure(message)),
+				}
+			}
+		}
+		self.status = Some(match &outcome.response {
+			Ok(_) if outcome.is_success() => (history::summary(&outcome), false),
+			Ok(_) => (history::summary(&outcome), true),
+			Err(err) => (format!("error: {err}"), true),
+		});
+		self.shown = Some(self.history.push(outcome));
+		self.history_selected = 0;
+		if self.tab == Tab::History {
+			self.tab = Tab::Body;
+		}
+		self.scroll = 0;
+		true
+	}
+
+	/// Draws the panes, returning where the cursor goes when editing.
+	pub fn draw(&mut self, screen: &mut Screen) -> Option<(u16, u16)> {
+		let area = screen.area();
+		if area.width < 40 || area.height < 12 {
+			screen.put(0, 0, "window too small", area.width, Style::PLAIN);
+			return None;
+		}
+		let bar = Style::PLAIN.reverse();
+		screen.fill(Rect { height: 1, ..area }, bar);
+		let title = match self.paths.as_slice() {
+			[path] => format!(" webcat  {}", path.display()),
+			_ => " webcat".into(),
+		};
+		screen.put(0, 0, &title, area.width, bar.bold());
+		if self.is_sending() {
+			screen.put(area.width - 12, 0, "sending… ", 12, bar);
+		}
+
+		let tree_width = (area.width / 4).clamp(20.min(area.width / 2), 40);
+		let body = area.height - 2;
+		let tree = Rect {
+			x: 0,
+			y: 1,
+			width: tree_width,
+			height: body,
+		};
+		let editor_height = body * 2 / 5;
+		let editor = Rect {
+			x: tree_width,
+			y: 1,
+			width: area.width - tree_width,
+			height: editor_height,
+		};
+		let response = Rect {
+			y: 1 + editor_height,
+			height: body - editor_height,
+			..editor
+		};
+		self.draw_tree(screen, tree);
+		let cursor = self.draw_editor(screen, editor);
+		self.draw_response(screen, response);
+		self.draw_status(screen, area.height - 1);
+		cursor
+	}
+
+	fn border_style(&self, focus: Focus) -> Style {
+		match self.focus == focus {
+			true => Style::fg(Color::Cyan),
+			false => Style::PLAIN,
+		}
+	}
+
+	fn draw_tree(&mut self, screen: &mut Screen, area: Rect) {
+		screen.border(area, "Requests", self.border_style(Focus::Tree));
+		let inner = area.inner();
+		let visible = self.collection.visible();
+		if visible.is_empty() {
+			screen.put(
+				inner.x,
+				inner.y,
+				"no .http files",
+				inner.width,
+				Style::fg(Color::DarkGrey),
+			);
+			return;
+		}
+		self.selected = self.selected.min(visible.len() - 1);
+		let height = usize::from(inner.height);
+		if self.selected < self.tree_top {
+			self.tree_top = self.selected;
+		} else if self.selected >= self.tree_top + height {
+			self.tree_top = self.selected + 1 - height;
+		}
+		for (row, &index) in visible.iter().skip(self.tree_top).take(height).enumerate() {
+			let item = &self.collection.items[index];
+			let y = inner.y + row as u16;
+			let selected = self.tree_top + row == self.selected;
+			let style = |style: Style| match (selected, self.focus == Focus::Tree) {
+				(true, true) => style.reverse(),
+				(true, false) => style.bold(),
+				_ => style,
+			};
+			let mut x = inner.x + (item.depth * 2) as u16;
+			let end = inner.x + inner.width;
+			let width = |x: u16| end.saturating_sub(x);
+			if selected {
+				screen.put(
+					inner.x,
+					y,
+					&" ".repeat(inner.width.into()),
+					inner.width,
+					style(Style::PLAIN),
+				);
+			}
+			match &item.kind {
+				Kind::Request(section) => {
+					let method = method_label(&section.method);
+					x += screen.put(
+						x,
+						y,
+						method,
+						width(x),
+						style(Style::fg(method_color(method))),
+					);
+					x += screen.put(x, y, " ", width(x), style(Style::PLAIN));
+					let label = section
+						.label
+						.strip_prefix(&format!("{} ", section.method))
+						.unwrap_or(&section.label);
+					screen.put(x, y, label, width(x), style(Style::PLAIN));
+				}
+				kind => {
+					let marker = if item.expanded { "▾ " } else { "▸ " };
+					x += screen.put(x, y, marker, width(x), style(Style::PLAIN));
+					let label_style = match kind {
+						Kind::File(Some(_)) => Style::fg(Color::Red),
+						Kind::Dir => Style::fg(Color::Blue).bold(),
+						_ => Style::PLAIN.bold(),
+					};
+					screen.put(x, y, &item.label(), width(x), style(label_style));
+				}
+			}
+		}
+	}
+
+	fn draw_editor(&mut self, screen: &mut Screen, area: Rect) -> Option<(u16, u16)> {
+		let style = self.border_style(Focus::Editor);
+		let inner = area.inner();
+		let Some(open) = &mut self.open else {
+			screen.border(area, "Editor", style);
+			let hint = "Select a request and press Enter to edit it";
+			screen.put(
+				inner.x + 1,
+				inner.y,
+				hint,
+				inner.width,
+				Style::fg(Color::DarkGrey),
+			);
+			return None;
+		};
+		let name = open.path.file_name().unwrap_or_default().to_string_lossy();
+		let modified = if open.editor.modified { " *" } else { "" };
+		screen.border(area, &format!("{name}:{}{modified}", open.start + 1), style);
+		let editor = &mut open.editor;
+		editor.scroll(inner.width.into(), inner.height.into());
+		for (row, line) in editor
+			.lines
+			.iter()
+			.skip(editor.top)
+			.take(inner.height.into())
+			.enumerate()
+		{
+			let text: String = line.chars().skip(editor.left).collect();
+			screen.put(
+				inner.x,
+				inner.y + row as u16,
+				&text,
+				inner.width,
+				line_style(line),
+			);
+		}
+		(self.focus == Focus::Editor).then(|| {
+			(
+				inner.x + (editor.column - editor.left) as u16,
+				inner.y + (editor.row - editor.top) as u16,
+			)
+		})
+	}
+
+	fn draw_response(&mut self, screen: &mut Screen, area: Rect) {
+		screen.border(area, "", self.border_style(Focus::Response));
+		let inner = area.inner();
+		let mut x = area.x + 2;
+		for (tab, name) in TABS {
+			let style = match tab == self.tab {
+				true => Style::PLAIN.reverse().bold(),
+				false => Style::PLAIN,
+			};
+			x += screen.put(
+				x,
+				area.y,
+				&format!(" {name} "),
+				(area.x + area.width).saturating_sub(x + 1),
+				style,
+			);
+		}
+		let outcome = self.shown.and_then(|index| self.history.entries.get(index));
+		if let Some(Ok(response)) = outcome.map(|outcome| &outcome.response) {
+			let summary = format!(
+				" {} {} · {} ms · {} ",
+				response.status,
+				response.reason,
+				outcome.unwrap().elapsed.as_millis(),
+				format_bytes(response.body.len() as f64)
+			);
+			let width = summary.chars().count() as u16;
+			if x + width + 1 < area.x + area.width {
+				let style = Style::fg(status_color(response.status)).bold();
+				screen.put(
+					area.x + area.width - width - 1,
+					area.y,
+					&summary,
+					width,
+					style,
+				);
+			}
+		}
+
+		let lines = match (self.tab, outcome) {
+			(Tab::History, _) => self.history_lines(),
+			(_, None) => vec![(
+				"Send the request in the editor with Ctrl-R".into(),
+				Style::fg(Color::DarkGrey),
+			)],
+			(Tab::Body, Some(outcome)) => body_lines(outcome),
+			(Tab::Headers, Some(outcome)) => header_lines(outcome),
+			(Tab::Tests, Some(outcome)) => test_lines(outcome),
+			(Tab::Timing, Some(outcome)) => timing_lines(outcome),
+		};
+		let height = usize::from(inner.height);
+		let top = match self.tab {
+			Tab::History => {
+				// the selection stays in view, below the search line
+				let entries = lines.len().saturating_sub(1);
+				self.history_selected = self.history_selected.min(entries.saturating_sub(1));
+				(self.history_selected + 2).saturating_sub(height)
+			}
+			_ => {
+				self.scroll = self.scroll.min(lines.len().saturating_sub(height));
+				self.scroll
+			}
+		};
+		for (row, (text, style)) in lines.iter().skip(top).take(height).enumerate() {
+			screen.put(inner.x, inner.y + row as u16, text, inner.width, *style);
+		}
+		if self.tab == Tab::History && top > 0 {
+			// the search line stays on top
+			let (text, style) = &lines[0];
+			screen.fill(Rect { height: 1, ..inner }, Style::PLAIN);
+			screen.put(inner.x, inner.y, text, inner.width, *style);
+		}
+	}
+
+	fn history_lines(&self) -> Vec<(String, Style)> {
+		let (query, typing) = &self.search;
+		let search = match (query.is_empty(), typing) {
+			(true, false) => ("Press / to search".into(), Style::fg(Color::DarkGrey)),
+			_ => (
+				format!("/{query}{}", if *typing { "▏" } else { "" }),
+				Style::fg(Color::Yellow),
+			),
+		};
+		let mut lines = vec![search];
+		for (position, index) in self.history.search(query).into_iter().enumerate() {
+			let outcome = &self.history.entries[index];
+			let marker = if Some(index) == self.shown {
+				"● "
+			} else {
+				"  "
+			};
+			let color = match &outcome.response {
+				Ok(response) if outcome.is_success() => status_color(response.status),
+				_ => Color::Red,
+			};
+			let mut style = Style::fg(color);
+			if position == self.history_selected {
+				style = style.reverse();
+			}
+			lines.push((format!("{marker}{}", history::summary(outcome)), style));
+		}
+		lines
+	}
+
+	fn draw_status(&self, screen: &mut Screen, y: u16) {
+		let (text, style) = match &self.status {
+			Some((message, true)) => (message.clone(), Style::fg(Color::Red).bold()),
+			Some((message, false)) => (message.clone(), Style::PLAIN),
+			None => (self.help().into(), Style::fg(Color::DarkGrey)),
+		};
+		screen.put(1, y, &text, screen.width - 1, style);
+	}
+
+	fn help(&self) -> &'static str {
+		match (self.focus, self.search.1) {
+			(_, true) => "type to search the history  Enter done  Esc clear",
+			(Focus::Tree, _) => {
+				"↑↓ move  Enter open  ←→ fold  r reload  Tab next pane  Ctrl-R send  Ctrl-Q quit"
+			}
+			(Focus::Editor, _) => "Ctrl-S save  Ctrl-R send  Esc tree  Tab next pane  Ctrl-Q quit",
+			(Focus::Response, _) => {
+				"←→ or 1-5 tab  ↑↓ scroll  / search history  Enter show entry  Ctrl-R send  Ctrl-Q quit"
+			}
+		}
+	}
+}
+
+/// Lines of a text with its tabs expanded.
+fn text_lines(text: &str, style: Style) -> Vec<(String, Style)> {
+	text.lines()
+		.map(|line| (line.replace('\t', "    "), style))
+		.collect()
+}
+
+fn body_lines(outcome: &Outcome) -> Vec<(String, Style)> {
+	match &outcome.response {
+		Ok(response) if response.body.is_empty() => {
+			vec![("(empty body)".into(), Style::fg(Color::DarkGrey))]
+		}
+		Ok(response) => text_lines(
+			&render::render_body(&response.headers, &response.body, false),
+			Style::PLAIN,
+		),
+		Err(err) => text_lines(&format!("error: {err}"), Style::fg(Color::Red)),
+	}
+}
+
+fn header_lines(outcome: &Outcome) -> Vec<(String, Style)> {
+	let name = Style::fg(Color::Cyan);
+	let mut lines = Vec::new();
+	if let Ok(response) = &outcome.response {
+		for hop in &response.redirects {
+			lines.push((hop.to_string(), Style::fg(Color::DarkGrey)));
+		}
+		let status = Style::fg(status_color(response.status)).bold();
+		lines.push((response.status_line(), status));
+		for (header, value) in response.headers.iter() {
+			lines.push((format!("{header}: {value}"), name));
+		}
+		lines.push((String::new(), Style::PLAIN));
+	}
+	let request = &outcome.request;
+	lines.push((
+		format!("{} {}", request.method, request.url),
+		Style::PLAIN.bold(),
+	));
+	for (header, value) in request.headers.iter() {
+		lines.push((format!("{header}: {value}"), name));
+	}
+	lines
+}
+
+fn test_lines(outcome: &Outcome) -> Vec<(String, Style)> {
+	if outcome.assertions.is_empty() {
+		return vec![("no assertions".into(), Style::fg(Color::DarkGrey))];
+	}
+	let mut out = Vec::new();
+	let _ = crate::cli::print_assertions(&mut out, &outcome.assertions);
+	String::from_utf8_lossy(&out)
+		.lines()
+		.map(|line| {
+			let style = match line {
+				_ if line.starts_with("PASS") => Style::fg(Color::Green),
+				_ if line.starts_with("FAIL") => Style::fg(Color::Red).bold(),
+				_ => Style::PLAIN,
+			};
+			(line.replace('\t', "    "), style)
+		})
+		.collect()
+}
+
+fn timing_lines(outcome: &Outcome) -> Vec<(String, Style)> {
+	let mut lines = vec![
+		(
+			format!("Sent at {}", format_timestamp(outcome.started)),
+			Style::PLAIN,
+		),
+		(
+			format!("Elapsed {} ms", outcome.elapsed.as_millis()),
+			Style::PLAIN,
+		),
+	];
+	if let Ok(response) = &outcome.response {
+		let size = format!("Body    {}", format_bytes(response.body.len() as f64));
+		lines.push((size, Style::PLAIN));
+		lines.push((String::new(), Style::PLAIN));
+		lines.extend(text_lines(
+			&response.timing.waterfall().to_string(),
+			Style::fg(Color::Cyan),
+		));
+	}
+	lines
+}
+
+/// Style of a line of a request file.
+fn line_style(line: &str) -> Style {
+	let line = line.trim_start();
+	if line.starts_with("###") {
+		Style::fg(Color::Blue).bold()
+	} else if line.starts_with("??") {
+		Style::fg(Color::Yellow)
+	} else if line.starts_with(">>") {
+		Style::fg(Color::Magenta)
+	} else if line.starts_with('#') || line.starts_with("//") {
+		Style::fg(Color::DarkGrey)
+	} else if line.starts_with('@') {
+		Style::fg(Color::Cyan)
+	} else {
+		Style::PLAIN
+	}
+}
+
+/// Method shown in the tree, shortened to fit a column.
+fn method_label(method: &str) -> &str {
+	match method {
+		"DELETE" => "DEL",
+		"OPTIONS" => "OPT",
+		"WEBSOCKET" => "WS",
+		"GRAPHQL" => "GQL",
+		_ => method,
+	}
+}
+
+fn method_color(method: &str) -> Color {
+	match method {
+		"GET" => Color::Green,
+		"POST" => Color::Yellow,
+		"PUT" | "PATCH" => Color::Blue,
+		"DEL" => Color::Red,
+		_ => Color::Magenta,
+	}
+}
+
+fn status_color(status: u16) -> Color {
+	match status {
+		200..=299 => Color::Green,
+		300..=399 => Color::Cyan,
+		400..=499 => Color::Yellow,
+		_ => Color::Red,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::{Duration, Instant};
+
+	use super::*;
+	use crate::client::Client;
+
+	fn key(code: KeyCode) -> KeyEvent {
+		KeyEvent::new(code, KeyModifiers::NONE)
+	}
+
+	fn ctrl(ch: char) -> KeyEvent {
+		KeyEvent::new(KeyCode::Char(ch), KeyModifiers::CONTROL)
+	}
+
+	fn rows(app: &mut App) -> Vec<String> {
+		let mut screen = Screen::new(100, 24);
+		app.draw(&mut screen);
+		(0..screen.height).map(|y| screen.row(y)).collect()
+	}
+
+	#[test]
+	fn edits_saves_and_sends_requests() {
+		let dir = std::env::temp_dir().join(format!("webcat-tui-app-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("items.http");
+		std::fs::write(
+			&path,
+			"@base = http://127.0.0.1:1\n\n### list\nGET {{base}}/items\n\n### one\nGET {{base}}/items/{{id}}\n",
+		)
+		.unwrap();
+		let mut runner = Runner::new(Client::new());
+		runner.variables.set("id", "7");
+		let mut app = App::new(vec![dir.clone()], runner);
+
+		let screen = rows(&mut app);
+		assert!(screen[2].contains("▾ items.http"), "{screen:#?}");
+		assert!(screen[3].contains("  GET list "), "{screen:#?}");
+		for code in [KeyCode::Down, KeyCode::Down, KeyCode::Enter] {
+			app.handle(key(code));
+		}
+		assert_eq!(app.focus, Focus::Editor);
+		let open = app.open.as_ref().unwrap();
+		assert_eq!((open.start, open.end), (5, 7));
+		assert_eq!(open.editor.lines, ["### one", "GET {{base}}/items/{{id}}"]);
+
+		// an unsaved change is only lost when asked twice
+		for code in [KeyCode::Down, KeyCode::End, KeyCode::Char('?')] {
+			app.handle(key(code));
+		}
+		app.handle(key(KeyCode::Esc));
+		app.handle(key(KeyCode::Up));
+		app.handle(key(KeyCode::Enter));
+		assert!(app
+			.status
+			.as_ref()
+			.is_some_and(|(text, error)| *error && text.contains("unsaved")));
+		assert!(app.open.as_ref().unwrap().editor.modified);
+		app.handle(key(KeyCode::Tab));
+		app.handle(ctrl('s'));
+		let text = std::fs::read_to_string(&path).unwrap();
+		assert!(
+			text.ends_with("### one\nGET {{base}}/items/{{id}}?\n"),
+			"{text}"
+		);
+		assert!(!app.open.as_ref().unwrap().editor.modified);
+		assert!(rows(&mut app)[1].contains("items.http:6 "));
+
+		app.handle(ctrl('r'));
+		assert!(app.is_sending());
+		let start = Instant::now();
+		while !app.poll() {
+			assert!(start.elapsed() < Duration::from_secs(10));
+			std::thread::sleep(Duration::from_millis(5));
+		}
+		let outcome = &app.history.entries[0];
+		assert_eq!(
+			outcome.request.url.to_string(),
+			"http://127.0.0.1:1/items/7?"
+		);
+		assert!(outcome.response.is_err());
+		assert_eq!(app.shown, Some(0));
+		let screen = rows(&mut app);
+		assert!(screen[10].contains("│error: "), "{screen:#?}");
+		assert!(screen[23].starts_with(" error: "));
+
+		app.handle(key(KeyCode::Tab));
+		app.handle(key(KeyCode::Char('/')));
+		for ch in "items/8".chars() {
+			app.handle(key(KeyCode::Char(ch)));
+		}
+		assert_eq!(app.tab, Tab::History);
+		let screen = rows(&mut app);
+		assert!(screen.iter().any(|row| row.contains("/items/8▏")));
+		assert!(!screen.iter().any(|row| row.contains("ERR")));
+		app.handle(key(KeyCode::Esc));
+		assert!(rows(&mut app)
+			.iter()
+			.any(|row| row.contains("ERR  GET http://127.0.0.1:1/items/7?")));
+
+		app.handle(ctrl('q'));
+		assert!(app.quit);
+	}
+}
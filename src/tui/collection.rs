@@ -0,0 +1,312 @@
+//! Tree of the request files found in directories, with the requests of
+//! each file and the lines of text they span.
+
+use std::path::{Path, PathBuf};
+
+use crate::httpfile;
+
+/// Extensions of request files.
+const EXTENSIONS: [&str; 2] = ["http", "rest"];
+
+/// Directories never searched for request files.
+const SKIPPED: [&str; 2] = ["target", "node_modules"];
+
+/// Requests of a file by the lines of their block, from the `###`
+/// separator to the next one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+	pub label: String,
+	pub method: String,
+	/// Index of the first line of the block.
+	pub start: usize,
+	/// Index of the line after the block.
+	pub end: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+	Dir,
+	/// Request file, with the error that prevented reading its requests.
+	File(Option<String>),
+	Request(Section),
+}
+
+/// Node of the tree, listed in order with its depth. A request has the
+/// path of its file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+	pub depth: usize,
+	pub path: PathBuf,
+	pub kind: Kind,
+	pub expanded: bool,
+}
+
+impl Item {
+	pub fn label(&self) -> String {
+		let name = || {
+			self.path.file_name().map_or_else(
+				|| self.path.display().to_string(),
+				|name| name.to_string_lossy().into_owned(),
+			)
+		};
+		match &self.kind {
+			Kind::Dir => format!("{}/", name()),
+			Kind::File(_) => name(),
+			Kind::Request(section) => section.label.clone(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Collection {
+	pub items: Vec<Item>,
+}
+
+impl Collection {
+	/// Collects the request files of directories and files. The content of
+	/// a single directory is listed at the top of the tree.
+	pub fn load(paths: &[PathBuf]) -> Collection {
+		let mut collection = Collection::default();
+		match paths {
+			[dir] if dir.is_dir() => collection.add_dir(dir, 0),
+			_ => {
+				for path in paths {
+					if path.is_dir() {
+						collection.items.push(Item {
+							depth: 0,
+							path: path.clone(),
+							kind: Kind::Dir,
+							expanded: true,
+						});
+						collection.add_dir(path, 1);
+					} else {
+						collection.add_file(path, 0);
+					}
+				}
+			}
+		}
+		collection
+	}
+
+	/// Adds the request files of a directory, leaving out the directories
+	/// without any.
+	fn add_dir(&mut self, dir: &Path, depth: usize) {
+		let Ok(entries) = std::fs::read_dir(dir) else {
+			return;
+		};
+		let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+		// directories first, then by name
+		paths.sort_by_key(|path| (!path.is_dir(), path.clone()));
+		for path in paths {
+			let name = path.file_name().unwrap_or_default().to_string_lossy();
+			if name.starts_with('.') {
+				continue;
+			}
+			if path.is_dir() {
+				if SKIPPED.contains(&name.as_ref()) {
+					continue;
+				}
+				let index = self.items.len();
+				self.items.push(Item {
+					depth,
+					path: path.clone(),
+					kind: Kind::Dir,
+					expanded: true,
+				});
+				self.add_dir(&path, depth + 1);
+				if self.items.len() == index + 1 {
+					self.items.pop();
+				}
+			} else if is_request_file(&path) {
+				self.add_file(&path, depth);
+			}
+		}
+	}
+
+	fn add_file(&mut self, path: &Path, depth: usize) {
+		let index = self.items.len();
+		self.items.push(Item {
+			depth,
+			path: path.to_path_buf(),
+			kind: Kind::File(None),
+			expanded: true,
+		});
+		self.insert_requests(index);
+	}
+
+	/// Reads the requests of the file at an index and inserts them after
+	/// it, or records why they cannot be read.
+	fn insert_requests(&mut self, index: usize) {
+		let path = self.items[index].path.clone();
+		let depth = self.items[index].depth + 1;
+		let sections = std::fs::read_to_string(&path)
+			.map_err(|err| err.to_string())
+			.and_then(|text| sections(&path.display().to_string(), &text));
+		match sections {
+			Ok(sections) => {
+				self.items[index].kind = Kind::File(None);
+				let requests = sections.into_iter().map(|section| Item {
+					depth,
+					path: path.clone(),
+					kind: Kind::Request(section),
+					expanded: false,
+				});
+				self.items.splice(index + 1..index + 1, requests);
+			}
+			Err(err) => self.items[index].kind = Kind::File(Some(err)),
+		}
+	}
+
+	/// Reads the requests of a file again, after it changed.
+	pub fn reload(&mut self, path: &Path) {
+		let Some(index) = self
+			.items
+			.iter()
+			.position(|item| item.path == path && matches!(item.kind, Kind::File(_)))
+		else {
+			return;
+		};
+		let end = self.items[index + 1..]
+			.iter()
+			.position(|item| !matches!(item.kind, Kind::Request(_)) || item.path != path)
+			.map_or(self.items.len(), |offset| index + 1 + offset);
+		self.items.drain(index + 1..end);
+		self.insert_requests(index);
+	}
+
+	/// Indexes of the items shown, those outside collapsed ones.
+	pub fn visible(&self) -> Vec<usize> {
+		let mut shown = Vec::new();
+		let mut hidden_below = None;
+		for (index, item) in self.items.iter().enumerate() {
+			if hidden_below.is_some_and(|depth| item.depth > depth) {
+				continue;
+			}
+			hidden_below = (!item.expanded).then_some(item.depth);
+			shown.push(index);
+		}
+		shown
+	}
+
+	/// Index of the request of a file whose block starts at a line.
+	pub fn find(&self, path: &Path, start: usize) -> Option<usize> {
+		self.items.iter().position(|item| {
+			item.path == path
+				&& matches!(&item.kind, Kind::Request(section) if section.start == start)
+		})
+	}
+}
+
+pub fn is_request_file(path: &Path) -> bool {
+	path.extension()
+		.and_then(|ext| ext.to_str())
+		.is_some_and(|ext| EXTENSIONS.contains(&ext))
+}
+
+/// Requests of a request file and the lines of their blocks.
+pub fn sections(source: &str, text: &str) -> Result<Vec<Section>, String> {
+	let file = httpfile::parse(source, text).map_err(|err| err.to_string())?;
+	let lines: Vec<&str> = text.lines().collect();
+	let separator = |index: &usize| lines[*index].trim_start().starts_with("###");
+	Ok(file
+		.requests
+		.iter()
+		.map(|def| {
+			let line = def.line - 1;
+			let start = (0..=line).rev().find(separator).unwrap_or(0);
+			let end = (line + 1..lines.len())
+				.find(separator)
+				.unwrap_or(lines.len());
+			Section {
+				label: def.label(),
+				method: def.method.clone(),
+				start,
+				end,
+			}
+		})
+		.collect())
+}
+
+/// Text of a file with the lines of a block replaced.
+pub fn splice(text: &str, start: usize, end: usize, block: &[String]) -> String {
+	let lines: Vec<&str> = text.lines().collect();
+	let end = end.min(lines.len());
+	let start = start.min(end);
+	let mut out: Vec<&str> = lines[..start].to_vec();
+	out.extend(block.iter().map(String::as_str));
+	out.extend(&lines[end..]);
+	let mut out = out.join("\n");
+	if text.ends_with('\n') || text.is_empty() {
+		out.push('\n');
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn lines(lines: &[&str]) -> Vec<String> {
+		lines.iter().map(|line| line.to_string()).collect()
+	}
+
+	const FILE: &str = "@host = h\n\n### list\nGET http://{{host}}/items\n\n###\nPOST http://{{host}}/items\n\nbody\n";
+
+	#[test]
+	fn lists_request_files_and_their_requests() {
+		let dir =
+			std::env::temp_dir().join(format!("webcat-tui-collection-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(dir.join("api/.hidden")).unwrap();
+		std::fs::create_dir_all(dir.join("empty")).unwrap();
+		std::fs::write(dir.join("api/items.http"), FILE).unwrap();
+		std::fs::write(dir.join("api/.hidden/x.http"), FILE).unwrap();
+		std::fs::write(dir.join("broken.rest"), "GET http://h/\nnot a header\n").unwrap();
+		std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+		let mut collection = Collection::load(std::slice::from_ref(&dir));
+		let labels: Vec<String> = collection
+			.visible()
+			.iter()
+			.map(|index| {
+				let item = &collection.items[*index];
+				format!("{}{}", "  ".repeat(item.depth), item.label())
+			})
+			.collect();
+		assert_eq!(
+			labels,
+			[
+				"api/",
+				"  items.http",
+				"    list",
+				"    POST http://{{host}}/items",
+				"broken.rest"
+			]
+		);
+		assert!(matches!(&collection.items[4].kind, Kind::File(Some(err)) if err.contains(":2:")));
+		let Kind::Request(section) = &collection.items[3].kind else {
+			panic!()
+		};
+		assert_eq!((section.start, section.end), (5, 9));
+
+		collection.items[0].expanded = false;
+		assert_eq!(collection.visible(), [0, 4]);
+
+		let path = dir.join("api/items.http");
+		std::fs::write(
+			&path,
+			splice(FILE, 5, 9, &lines(&["### create", "POST http://h/"])),
+		)
+		.unwrap();
+		collection.reload(&path);
+		assert_eq!(collection.items.len(), 5);
+		assert_eq!(collection.find(&path, 5), Some(3));
+		assert_eq!(collection.items[3].label(), "create");
+	}
+
+	#[test]
+	fn splices_blocks() {
+		assert_eq!(splice("a\nb\nc\n", 1, 2, &lines(&["x", ""])), "a\nx\n\nc\n");
+		assert_eq!(splice("a\nb", 1, 2, &lines(&["x"])), "a\nx");
+	}
+}
@@ -0,0 +1,184 @@
+//! Text buffer of the editor pane, with a cursor and a scroll position.
+
+/// Lines of text with a cursor at a line and character, and the first
+/// line and column shown.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Editor {
+	pub lines: Vec<String>,
+	pub row: usize,
+	/// Character index in the line, at most its length.
+	pub column: usize,
+	pub top: usize,
+	pub left: usize,
+	/// True once the text was changed.
+	pub modified: bool,
+}
+
+/// Cursor movements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Move {
+	Up,
+	Down,
+	Left,
+	Right,
+	Home,
+	End,
+	PageUp(usize),
+	PageDown(usize),
+}
+
+impl Editor {
+	/// Editor of a text, whose lines are kept as they are, the last empty
+	/// ones included.
+	pub fn new(text: &str) -> Self {
+		let lines = text
+			.split('\n')
+			.map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+			.collect();
+		Editor {
+			lines,
+			..Editor::default()
+		}
+	}
+
+	pub fn text(&self) -> String {
+		self.lines.join("\n")
+	}
+
+	fn line_len(&self) -> usize {
+		self.lines[self.row].chars().count()
+	}
+
+	/// Byte offset of the cursor in its line.
+	fn offset(&self) -> usize {
+		let line = &self.lines[self.row];
+		line.char_indices()
+			.nth(self.column)
+			.map_or(line.len(), |(offset, _)| offset)
+	}
+
+	pub fn insert(&mut self, ch: char) {
+		let offset = self.offset();
+		self.lines[self.row].insert(offset, ch);
+		self.column += 1;
+		self.modified = true;
+	}
+
+	/// Splits the line at the cursor, keeping its indentation.
+	pub fn newline(&mut self) {
+		let offset = self.offset();
+		let rest = self.lines[self.row].split_off(offset);
+		let indent: String = self.lines[self.row]
+			.chars()
+			.take_while(|ch| *ch == ' ' || *ch == '\t')
+			.collect();
+		self.column = indent.chars().count();
+		self.row += 1;
+		self.lines.insert(self.row, indent + &rest);
+		self.modified = true;
+	}
+
+	/// Deletes the character before the cursor, joining the line with the
+	/// previous one at its start.
+	pub fn backspace(&mut self) {
+		if self.column > 0 {
+			self.column -= 1;
+			let offset = self.offset();
+			self.lines[self.row].remove(offset);
+		} else if self.row > 0 {
+			let line = self.lines.remove(self.row);
+			self.row -= 1;
+			self.column = self.line_len();
+			self.lines[self.row].push_str(&line);
+		} else {
+			return;
+		}
+		self.modified = true;
+	}
+
+	/// Deletes the character under the cursor, joining the next line at the
+	/// end of a line.
+	pub fn delete(&mut self) {
+		if self.column < self.line_len() {
+			let offset = self.offset();
+			self.lines[self.row].remove(offset);
+		} else if self.row + 1 < self.lines.len() {
+			let line = self.lines.remove(self.row + 1);
+			self.lines[self.row].push_str(&line);
+		} else {
+			return;
+		}
+		self.modified = true;
+	}
+
+	pub fn move_cursor(&mut self, to: Move) {
+		let last = self.lines.len() - 1;
+		match to {
+			Move::Up => self.row = self.row.saturating_sub(1),
+			Move::Down => self.row = (self.row + 1).min(last),
+			Move::PageUp(lines) => self.row = self.row.saturating_sub(lines),
+			Move::PageDown(lines) => self.row = (self.row + lines).min(last),
+			Move::Left if self.column > 0 => self.column -= 1,
+			Move::Left if self.row > 0 => {
+				self.row -= 1;
+				self.column = self.line_len();
+			}
+			Move::Right if self.column < self.line_len() => self.column += 1,
+			Move::Right if self.row < last => {
+				self.row += 1;
+				self.column = 0;
+			}
+			Move::Home => self.column = 0,
+			Move::End => self.column = self.line_len(),
+			Move::Left | Move::Right => {}
+		}
+		self.column = self.column.min(self.line_len());
+	}
+
+	/// Scrolls so that the cursor is inside a view of the given size.
+	pub fn scroll(&mut self, width: usize, height: usize) {
+		if self.row < self.top {
+			self.top = self.row;
+		} else if height > 0 && self.row >= self.top + height {
+			self.top = self.row + 1 - height;
+		}
+		if self.column < self.left {
+			self.left = self.column;
+		} else if width > 0 && self.column >= self.left + width {
+			self.left = self.column + 1 - width;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn edits_lines() {
+		let mut editor = Editor::new("GET http://h/\n  X-A: é");
+		editor.move_cursor(Move::Down);
+		editor.move_cursor(Move::End);
+		editor.newline();
+		for ch in "X-B: 1".chars() {
+			editor.insert(ch);
+		}
+		assert_eq!(editor.text(), "GET http://h/\n  X-A: é\n  X-B: 1");
+		editor.move_cursor(Move::Up);
+		editor.move_cursor(Move::End);
+		editor.backspace();
+		editor.delete();
+		assert_eq!(editor.text(), "GET http://h/\n  X-A:   X-B: 1");
+		assert_eq!((editor.row, editor.column), (1, 7));
+		editor.move_cursor(Move::Home);
+		editor.backspace();
+		assert_eq!(editor.lines, ["GET http://h/  X-A:   X-B: 1"]);
+		assert_eq!((editor.row, editor.column), (0, 13));
+		assert!(editor.modified);
+
+		editor.move_cursor(Move::PageDown(10));
+		editor.move_cursor(Move::End);
+		editor.scroll(10, 1);
+		assert_eq!((editor.top, editor.left), (0, 19));
+	}
+}
@@ -0,0 +1,99 @@
+//! Requests sent during a session, with their outcome, searchable by
+//! text.
+
+use crate::runner::Outcome;
+use crate::util::format_timestamp;
+
+#[derive(Debug, Default)]
+pub struct History {
+	/// Outcomes in the order the requests were sent.
+	pub entries: Vec<Outcome>,
+}
+
+impl History {
+	pub fn push(&mut self, outcome: Outcome) -> usize {
+		self.entries.push(outcome);
+		self.entries.len() - 1
+	}
+
+	/// Indexes of the entries matching a search, newest first. Every word
+	/// of the search must appear in the summary of an entry, ignoring case.
+	pub fn search(&self, query: &str) -> Vec<usize> {
+		let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+		(0..self.entries.len())
+			.rev()
+			.filter(|index| {
+				let summary = summary(&self.entries[*index]).to_lowercase();
+				words.iter().all(|word| summary.contains(word.as_str()))
+			})
+			.collect()
+	}
+}
+
+/// One line description of an outcome: the time it was sent in UTC, the
+/// status, the request line, the duration and the name.
+pub fn summary(outcome: &Outcome) -> String {
+	let time = format_timestamp(outcome.started);
+	let status = match &outcome.response {
+		Ok(response) => response.status.to_string(),
+		Err(_) => "ERR".into(),
+	};
+	let failed = outcome.failures().count();
+	let mut text = format!(
+		"{}  {status:>3}  {} {}  {} ms",
+		&time[11..19],
+		outcome.request.method,
+		outcome.request.url,
+		outcome.elapsed.as_millis()
+	);
+	if failed > 0 {
+		text.push_str(&format!("  {failed} failed"));
+	}
+	if !outcome.name.starts_with(&outcome.request.method) {
+		text.push_str(&format!("  ({})", outcome.name));
+	}
+	text
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::{Duration, SystemTime};
+
+	use super::*;
+	use crate::error::Error;
+	use crate::http::{Request, Response};
+	use crate::url::Url;
+
+	fn outcome(name: &str, method: &str, status: Option<u16>) -> Outcome {
+		Outcome {
+			name: name.into(),
+			request: Request::new(method, Url::parse("http://h/items").unwrap()),
+			started: SystemTime::UNIX_EPOCH + Duration::from_secs(3723),
+			response: match status {
+				Some(status) => Ok(Response {
+					status,
+					..Default::default()
+				}),
+				None => Err(Error::Protocol("refused".into())),
+			},
+			elapsed: Duration::from_millis(12),
+			assertions: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn searches_entries() {
+		let mut history = History::default();
+		history.push(outcome("list", "GET", Some(200)));
+		history.push(outcome("POST http://h/items", "POST", Some(201)));
+		history.push(outcome("list", "GET", None));
+		assert_eq!(
+			summary(&history.entries[0]),
+			"01:02:03  200  GET http://h/items  12 ms  (list)"
+		);
+		assert_eq!(history.search(""), [2, 1, 0]);
+		assert_eq!(history.search("LIST"), [2, 0]);
+		assert_eq!(history.search("get 200"), [0]);
+		assert_eq!(history.search("err"), [2]);
+	}
+}
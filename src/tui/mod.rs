@@ -0,0 +1,85 @@
+//! Terminal UI of `webcat tui`: a tree of the request files of a directory,
+//! an editor of the selected request and a pane with the response, its
+//! headers, assertions and timing, and the history of the session.
+
+pub mod app;
+pub mod collection;
+pub mod editor;
+pub mod history;
+pub mod screen;
+
+use std::io::Write;
+use std::time::Duration;
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{ExecutableCommand, QueueableCommand};
+
+use crate::error::Result;
+
+pub use app::App;
+use screen::Screen;
+
+/// How long events are waited for before checking for a response.
+const POLL: Duration = Duration::from_millis(50);
+
+/// Terminal in raw mode on the alternate screen, restored when dropped,
+/// even on a panic.
+struct Terminal;
+
+impl Terminal {
+	fn enter() -> Result<Terminal> {
+		terminal::enable_raw_mode()?;
+		let terminal = Terminal;
+		std::io::stdout().execute(EnterAlternateScreen)?;
+		Ok(terminal)
+	}
+}
+
+impl Drop for Terminal {
+	fn drop(&mut self) {
+		let mut out = std::io::stdout();
+		let _ = out.execute(Show);
+		let _ = out.execute(LeaveAlternateScreen);
+		let _ = terminal::disable_raw_mode();
+	}
+}
+
+/// Runs the UI until it quits, drawing a frame after each event or
+/// response.
+pub fn run(mut app: App) -> Result<()> {
+	let _terminal = Terminal::enter()?;
+	let mut out = std::io::stdout();
+	let mut previous: Option<Screen> = None;
+	let mut changed = true;
+	while !app.quit {
+		changed |= app.poll();
+		if changed {
+			let (width, height) = terminal::size()?;
+			let mut screen = Screen::new(width, height);
+			let cursor = app.draw(&mut screen);
+			out.queue(Hide)?;
+			screen.draw(&mut out, previous.as_ref())?;
+			if let Some((x, y)) = cursor {
+				out.queue(MoveTo(x, y))?.queue(Show)?;
+			}
+			out.flush()?;
+			previous = Some(screen);
+			changed = false;
+		}
+		// every pending event is handled before the next frame, so that
+		// pasted text is not drawn a character at a time
+		let mut wait = POLL;
+		while event::poll(wait)? {
+			match event::read()? {
+				Event::Key(key) if key.kind != KeyEventKind::Release => app.handle(key),
+				Event::Resize(..) => previous = None,
+				_ => {}
+			}
+			changed = true;
+			wait = Duration::ZERO;
+		}
+	}
+	Ok(())
+}
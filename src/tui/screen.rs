@@ -0,0 +1,229 @@
+//! Grid of styled cells drawn for each frame. Only the cells that changed
+//! since the previous frame are written to the terminal, which keeps
+//! redraws light over slow connections.
+
+use std::io::Write;
+
+use crossterm::cursor::MoveTo;
+use crossterm::style::{Attribute, Color, Print, SetAttribute, SetForegroundColor};
+use crossterm::QueueableCommand;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+	pub fg: Option<Color>,
+	pub bold: bool,
+	pub reverse: bool,
+}
+
+impl Style {
+	pub const PLAIN: Style = Style {
+		fg: None,
+		bold: false,
+		reverse: false,
+	};
+
+	pub fn fg(color: Color) -> Style {
+		Style {
+			fg: Some(color),
+			..Style::PLAIN
+		}
+	}
+
+	pub fn bold(self) -> Style {
+		Style { bold: true, ..self }
+	}
+
+	pub fn reverse(self) -> Style {
+		Style {
+			reverse: true,
+			..self
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Cell {
+	ch: char,
+	style: Style,
+}
+
+const BLANK: Cell = Cell {
+	ch: ' ',
+	style: Style::PLAIN,
+};
+
+/// Area of the screen, in cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+	pub x: u16,
+	pub y: u16,
+	pub width: u16,
+	pub height: u16,
+}
+
+impl Rect {
+	/// The area inside a one cell border.
+	pub fn inner(self) -> Rect {
+		Rect {
+			x: self.x + 1,
+			y: self.y + 1,
+			width: self.width.saturating_sub(2),
+			height: self.height.saturating_sub(2),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Screen {
+	pub width: u16,
+	pub height: u16,
+	cells: Vec<Cell>,
+}
+
+impl Screen {
+	pub fn new(width: u16, height: u16) -> Self {
+		Screen {
+			width,
+			height,
+			cells: vec![BLANK; usize::from(width) * usize::from(height)],
+		}
+	}
+
+	pub fn area(&self) -> Rect {
+		Rect {
+			x: 0,
+			y: 0,
+			width: self.width,
+			height: self.height,
+		}
+	}
+
+	/// Writes text from a position, cut at `max` cells and the right edge.
+	/// Control characters are shown as spaces. Returns the cells written.
+	pub fn put(&mut self, x: u16, y: u16, text: &str, max: u16, style: Style) -> u16 {
+		if y >= self.height {
+			return 0;
+		}
+		let end = x.saturating_add(max).min(self.width);
+		let mut column = x;
+		for ch in text.chars() {
+			if column >= end {
+				break;
+			}
+			let ch = if ch.is_control() { ' ' } else { ch };
+			let index = usize::from(y) * usize::from(self.width) + usize::from(column);
+			self.cells[index] = Cell { ch, style };
+			column += 1;
+		}
+		column - x.min(column)
+	}
+
+	/// Fills an area with a style, clearing its text.
+	pub fn fill(&mut self, area: Rect, style: Style) {
+		for y in area.y..area.y.saturating_add(area.height).min(self.height) {
+			self.put(area.x, y, &" ".repeat(area.width.into()), area.width, style);
+		}
+	}
+
+	/// Draws a border around an area, with a title on its top edge.
+	pub fn border(&mut self, area: Rect, title: &str, style: Style) {
+		if area.width < 2 || area.height < 2 {
+			return;
+		}
+		let right = area.x + area.width - 1;
+		let bottom = area.y + area.height - 1;
+		let line = "─".repeat(usize::from(area.width) - 2);
+		self.put(area.x, area.y, &format!("┌{line}┐"), area.width, style);
+		self.put(area.x, bottom, &format!("└{line}┘"), area.width, style);
+		for y in area.y + 1..bottom {
+			self.put(area.x, y, "│", 1, style);
+			self.put(right, y, "│", 1, style);
+		}
+		if !title.is_empty() {
+			self.put(
+				area.x + 2,
+				area.y,
+				&format!(" {title} "),
+				area.width.saturating_sub(4),
+				style.bold(),
+			);
+		}
+	}
+
+	/// Text of a row, for tests.
+	pub fn row(&self, y: u16) -> String {
+		let start = usize::from(y) * usize::from(self.width);
+		self.cells[start..start + usize::from(self.width)]
+			.iter()
+			.map(|cell| cell.ch)
+			.collect()
+	}
+
+	/// Writes the cells that differ from the previous frame, everything
+	/// when there is none or the size changed.
+	pub fn draw<W: Write>(&self, out: &mut W, previous: Option<&Screen>) -> std::io::Result<()> {
+		let previous = previous.filter(|p| (p.width, p.height) == (self.width, self.height));
+		let mut style = None;
+		let mut next = None;
+		for (index, cell) in self.cells.iter().enumerate() {
+			if previous.is_some_and(|p| p.cells[index] == *cell) {
+				continue;
+			}
+			let x = (index % usize::from(self.width)) as u16;
+			let y = (index / usize::from(self.width)) as u16;
+			if next != Some((x, y)) {
+				out.queue(MoveTo(x, y))?;
+			}
+			if style != Some(cell.style) {
+				out.queue(SetAttribute(Attribute::Reset))?;
+				if let Some(color) = cell.style.fg {
+					out.queue(SetForegroundColor(color))?;
+				}
+				if cell.style.bold {
+					out.queue(SetAttribute(Attribute::Bold))?;
+				}
+				if cell.style.reverse {
+					out.queue(SetAttribute(Attribute::Reverse))?;
+				}
+				style = Some(cell.style);
+			}
+			out.queue(Print(cell.ch))?;
+			next = Some((x + 1, y));
+		}
+		out.queue(SetAttribute(Attribute::Reset))?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn clips_text_and_draws_changes() {
+		let mut screen = Screen::new(8, 3);
+		assert_eq!(screen.put(5, 0, "abcdef", 10, Style::PLAIN), 3);
+		screen.border(
+			Rect {
+				x: 0,
+				y: 1,
+				width: 4,
+				height: 2,
+			},
+			"",
+			Style::PLAIN,
+		);
+		assert_eq!(screen.row(0), "     abc");
+		assert_eq!(screen.row(1), "┌──┐    ");
+		assert_eq!(screen.row(2), "└──┘    ");
+
+		let mut next = screen.clone();
+		next.put(6, 0, "\tX", 2, Style::PLAIN);
+		let mut out = Vec::new();
+		next.draw(&mut out, Some(&screen)).unwrap();
+		let out = String::from_utf8(out).unwrap();
+		// only the two changed cells are written, after one move
+		assert!(out.contains("\x1b[1;7H") && out.contains(" X"), "{out:?}");
+		assert!(!out.contains('a'));
+	}
+}
@@ -0,0 +1,295 @@
+//! Request bodies streamed from files while they are sent, so that large
+//! uploads are never held in memory.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::util::format_bytes;
+
+/// Piece of a streamed body.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Chunk {
+	Bytes(Vec<u8>),
+	/// File read while sending, with its size when the body was built.
+	File {
+		path: PathBuf,
+		len: u64,
+	},
+}
+
+impl Chunk {
+	pub fn len(&self) -> u64 {
+		match self {
+			Chunk::Bytes(bytes) => bytes.len() as u64,
+			Chunk::File { len, .. } => *len,
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+/// Body made of bytes and files, sent with a known length.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Upload {
+	pub chunks: Vec<Chunk>,
+	/// Draws the progress on standard error while sending.
+	pub progress: bool,
+}
+
+impl Upload {
+	pub fn new() -> Upload {
+		Upload::default()
+	}
+
+	pub fn push_bytes(&mut self, bytes: &[u8]) {
+		match self.chunks.last_mut() {
+			Some(Chunk::Bytes(last)) => last.extend_from_slice(bytes),
+			_ => self.chunks.push(Chunk::Bytes(bytes.to_vec())),
+		}
+	}
+
+	/// Appends a file, which must exist as its size is the one sent.
+	pub fn push_file(&mut self, path: impl Into<PathBuf>) -> io::Result<()> {
+		let path = path.into();
+		let len = std::fs::metadata(&path)?.len();
+		self.chunks.push(Chunk::File { path, len });
+		Ok(())
+	}
+
+	pub fn append(&mut self, other: Upload) {
+		for chunk in other.chunks {
+			match chunk {
+				Chunk::Bytes(bytes) => self.push_bytes(&bytes),
+				file => self.chunks.push(file),
+			}
+		}
+	}
+
+	pub fn len(&self) -> u64 {
+		self.chunks.iter().map(Chunk::len).sum()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// True when the body includes files.
+	pub fn has_files(&self) -> bool {
+		self.chunks
+			.iter()
+			.any(|chunk| matches!(chunk, Chunk::File { .. }))
+	}
+
+	/// The body in memory, or the upload back when it includes files.
+	pub fn into_bytes(self) -> Result<Vec<u8>, Upload> {
+		if self.has_files() {
+			return Err(self);
+		}
+		Ok(self
+			.chunks
+			.into_iter()
+			.flat_map(|chunk| match chunk {
+				Chunk::Bytes(bytes) => bytes,
+				Chunk::File { .. } => Vec::new(),
+			})
+			.collect())
+	}
+
+	/// Reads the chunks in turn. A file that got shorter since the body was
+	/// built fails the read.
+	pub fn reader(&self) -> UploadReader<'_> {
+		UploadReader {
+			chunks: &self.chunks,
+			index: 0,
+			offset: 0,
+			file: None,
+			progress: self.progress.then(|| Progress::new(self.len())),
+		}
+	}
+
+	/// Reads the whole body into memory.
+	pub fn read_all(&self) -> io::Result<Vec<u8>> {
+		let mut body = Vec::with_capacity(self.len() as usize);
+		self.reader().read_to_end(&mut body)?;
+		Ok(body)
+	}
+}
+
+/// Reader over the chunks of an [`Upload`].
+pub struct UploadReader<'a> {
+	chunks: &'a [Chunk],
+	index: usize,
+	/// Position in the current chunk.
+	offset: u64,
+	file: Option<File>,
+	progress: Option<Progress>,
+}
+
+impl Read for UploadReader<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		loop {
+			let Some(chunk) = self.chunks.get(self.index) else {
+				if let Some(progress) = &mut self.progress {
+					progress.finish();
+				}
+				return Ok(0);
+			};
+			if self.offset == chunk.len() {
+				self.index += 1;
+				self.offset = 0;
+				self.file = None;
+				continue;
+			}
+			let want = (chunk.len() - self.offset).min(buf.len() as u64) as usize;
+			let read = match chunk {
+				Chunk::Bytes(bytes) => {
+					let start = self.offset as usize;
+					buf[..want].copy_from_slice(&bytes[start..start + want]);
+					want
+				}
+				Chunk::File { path, .. } => {
+					let file = match &mut self.file {
+						Some(file) => file,
+						None => self
+							.file
+							.insert(File::open(path).map_err(|err| in_file(path, err))?),
+					};
+					match file.read(&mut buf[..want]) {
+						Ok(0) => {
+							return Err(io::Error::new(
+								io::ErrorKind::UnexpectedEof,
+								format!("{} got shorter while uploading", path.display()),
+							))
+						}
+						Ok(read) => read,
+						Err(err) => return Err(in_file(path, err)),
+					}
+				}
+			};
+			self.offset += read as u64;
+			if let Some(progress) = &mut self.progress {
+				progress.advance(read as u64);
+			}
+			return Ok(read);
+		}
+	}
+}
+
+fn in_file(path: &Path, err: io::Error) -> io::Error {
+	io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+}
+
+/// Delay before the progress shows, so quick uploads print nothing.
+const PROGRESS_DELAY: Duration = Duration::from_millis(200);
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Progress line redrawn on standard error.
+struct Progress {
+	total: u64,
+	sent: u64,
+	start: Instant,
+	drawn: Option<Instant>,
+}
+
+impl Progress {
+	fn new(total: u64) -> Progress {
+		Progress {
+			total,
+			sent: 0,
+			start: Instant::now(),
+			drawn: None,
+		}
+	}
+
+	fn advance(&mut self, read: u64) {
+		self.sent += read;
+		let now = Instant::now();
+		let due = match self.drawn {
+			Some(drawn) => now - drawn >= REDRAW_INTERVAL,
+			None => now - self.start >= PROGRESS_DELAY,
+		};
+		if due {
+			self.draw(false);
+			self.drawn = Some(now);
+		}
+	}
+
+	fn finish(&mut self) {
+		if self.drawn.take().is_some() {
+			self.draw(true);
+		}
+	}
+
+	fn draw(&self, done: bool) {
+		let line = progress_line(self.sent, self.total, self.start.elapsed());
+		let mut err = io::stderr().lock();
+		// padded to cover a longer previous line
+		let _ = write!(err, "\r{line:<60}{}", if done { "\n" } else { "" });
+		let _ = err.flush();
+	}
+}
+
+fn progress_line(sent: u64, total: u64, elapsed: Duration) -> String {
+	let percent = match total {
+		0 => 100,
+		total => sent * 100 / total,
+	};
+	let rate = sent as f64 / elapsed.as_secs_f64().max(0.001);
+	format!(
+		"upload {percent:>3}% {} of {}, {}/s",
+		format_bytes(sent as f64),
+		format_bytes(total as f64),
+		format_bytes(rate)
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reads_bytes_and_files() {
+		let path = std::env::temp_dir().join(format!("webcat-upload-{}", std::process::id()));
+		std::fs::write(&path, b"file data").unwrap();
+		let mut upload = Upload::new();
+		upload.push_bytes(b"<");
+		upload.push_file(&path).unwrap();
+		upload.push_bytes(b">");
+		upload.push_bytes(b"\n");
+		assert_eq!(upload.chunks.len(), 3);
+		assert_eq!(upload.len(), 12);
+
+		// small reads cross the chunk ends
+		let mut reader = upload.reader();
+		let mut body = Vec::new();
+		let mut buf = [0; 5];
+		loop {
+			match reader.read(&mut buf).unwrap() {
+				0 => break,
+				read => body.extend_from_slice(&buf[..read]),
+			}
+		}
+		assert_eq!(body, b"<file data>\n");
+
+		std::fs::write(&path, b"file").unwrap();
+		let err = upload.read_all().unwrap_err();
+		assert!(err.to_string().ends_with("got shorter while uploading"));
+		std::fs::remove_file(&path).unwrap();
+		assert!(upload.read_all().is_err());
+	}
+
+	#[test]
+	fn formats_progress() {
+		assert_eq!(
+			progress_line(3 << 20, 12 << 20, Duration::from_secs(2)),
+			"upload  25% 3.00 MiB of 12.00 MiB, 1.50 MiB/s"
+		);
+	}
+}
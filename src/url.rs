@@ -0,0 +1,351 @@
+use std::fmt;
+
+use crate::error::{Error, Result};
+
+/// Parsed absolute `http`, `https`, `ws` or `wss` URL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Url {
+	pub scheme: String,
+	pub host: String,
+	pub port: u16,
+	/// Path including the query string, always starting with `/`.
+	pub path: String,
+}
+
+impl Url {
+	pub fn parse(input: &str) -> Result<Url> {
+		let input = input.trim();
+		let (scheme, rest) = match input.split_once("://").filter(|_| has_scheme(input)) {
+			Some((scheme, rest)) => (scheme.to_ascii_lowercase(), rest),
+			None => ("http".to_string(), input),
+		};
+		let default_port = match scheme.as_str() {
+			"http" | "ws" => 80,
+			"https" | "wss" => 443,
+			_ => return Err(Error::Url(format!("unsupported scheme `{scheme}`"))),
+		};
+
+		let rest = rest.split('#').next().unwrap_or_default();
+		let (authority, path) = match rest.find(['/', '?']) {
+			Some(index) => (&rest[..index], &rest[index..]),
+			None => (rest, "/"),
+		};
+		let path = if path.starts_with('?') {
+			format!("/{path}")
+		} else {
+			path.to_string()
+		};
+		if authority.contains('@') {
+			return Err(Error::Url("credentials in URL are not supported".into()));
+		}
+
+		let (host, port) = if let Some(inner) = authority.strip_prefix('[') {
+			let end = inner
+				.find(']')
+				.ok_or_else(|| Error::Url(format!("unterminated IPv6 address in `{input}`")))?;
+			let port = inner[end + 1..].strip_prefix(':');
+			(inner[..end].to_string(), port)
+		} else {
+			match authority.rsplit_once(':') {
+				Some((host, port)) => (host.to_string(), Some(port)),
+				None => (authority.to_string(), None),
+			}
+		};
+		if host.is_empty() {
+			return Err(Error::Url(format!("missing host in `{input}`")));
+		}
+		let port = match port {
+			Some(port) => port
+				.parse()
+				.map_err(|_| Error::Url(format!("invalid port `{port}`")))?,
+			None => default_port,
+		};
+
+		Ok(Url {
+			scheme,
+			host: host.to_ascii_lowercase(),
+			port,
+			path,
+		})
+	}
+
+	/// Returns true for the schemes over TLS, `https` and `wss`.
+	pub fn is_https(&self) -> bool {
+		matches!(self.scheme.as_str(), "https" | "wss")
+	}
+
+	/// Returns true if the port is the default one for the scheme.
+	pub fn has_default_port(&self) -> bool {
+		self.port == if self.is_https() { 443 } else { 80 }
+	}
+
+	/// Value for the `Host` header.
+	pub fn authority(&self) -> String {
+		let host = if self.host.contains(':') {
+			format!("[{}]", self.host)
+		} else {
+			self.host.clone()
+		};
+		if self.has_default_port() {
+			host
+		} else {
+			format!("{host}:{}", self.port)
+		}
+	}
+}
+
+impl Url {
+	/// Resolves a reference such as a `Location` header against the URL
+	/// (RFC 3986, 5.2): absolute, scheme-relative (`//host/path`),
+	/// absolute-path, relative-path or query-only.
+	pub fn join(&self, reference: &str) -> Result<Url> {
+		let reference = reference.trim();
+		let reference = reference.split('#').next().unwrap_or_default();
+		if let Some(scheme) = scheme(reference) {
+			if !has_scheme(reference) {
+				return Err(Error::Url(format!("unsupported scheme `{scheme}`")));
+			}
+			return Url::parse(reference);
+		}
+		if let Some(rest) = reference.strip_prefix("//") {
+			return Url::parse(&format!("{}://{rest}", self.scheme));
+		}
+		let path = if reference.is_empty() {
+			self.path.clone()
+		} else if reference.starts_with('/') {
+			reference.to_string()
+		} else if reference.starts_with('?') {
+			format!("{}{reference}", self.path_only())
+		} else {
+			let base = self.path_only();
+			let dir = &base[..base.rfind('/').map_or(0, |at| at + 1)];
+			format!("{dir}{reference}")
+		};
+		let (path, query) = match path.split_once('?') {
+			Some((path, query)) => (path, Some(query)),
+			None => (path.as_str(), None),
+		};
+		let mut path = remove_dot_segments(path);
+		if let Some(query) = query {
+			path = format!("{path}?{query}");
+		}
+		Ok(Url {
+			path,
+			..self.clone()
+		})
+	}
+
+	/// Returns true if both URLs have the same scheme, host and port.
+	pub fn same_origin(&self, other: &Url) -> bool {
+		self.scheme == other.scheme && self.host == other.host && self.port == other.port
+	}
+
+	/// Path without the query string.
+	pub fn path_only(&self) -> &str {
+		self.path.split('?').next().unwrap_or_default()
+	}
+
+	/// Decoded `name=value` pairs of the query string.
+	pub fn query_pairs(&self) -> Vec<(String, String)> {
+		match self.path.split_once('?') {
+			Some((_, query)) => parse_query(query),
+			None => Vec::new(),
+		}
+	}
+}
+
+/// Scheme a URL or reference starts with (RFC 3986, 3.1 and 4.3): a letter
+/// then letters, digits, `+`, `-` or `.` up to a `:`. A relative reference
+/// has none, even with a URL in its query.
+fn scheme(text: &str) -> Option<&str> {
+	let (scheme, _) = text.split_once(':')?;
+	let mut chars = scheme.chars();
+	let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+		&& chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+	valid.then_some(scheme)
+}
+
+/// Returns true if a URL starts with `scheme://`, as opposed to a URL
+/// without scheme such as `host:8080/path` or `/path?next=http://x`.
+pub fn has_scheme(text: &str) -> bool {
+	scheme(text).is_some_and(|scheme| text[scheme.len() + 1..].starts_with("//"))
+}
+
+/// Resolves `.` and `..` segments of an absolute path (RFC 3986, 5.2.4).
+fn remove_dot_segments(path: &str) -> String {
+	let mut segments: Vec<&str> = Vec::new();
+	let mut parts = path.split('/').skip(1).peekable();
+	while let Some(segment) = parts.next() {
+		let last = parts.peek().is_none();
+		match segment {
+			"." | ".." => {
+				if segment == ".." {
+					segments.pop();
+				}
+				// a trailing dot segment leaves a directory
+				if last {
+					segments.push("");
+				}
+			}
+			segment => segments.push(segment),
+		}
+	}
+	format!("/{}", segments.join("/"))
+}
+
+/// Splits an `application/x-www-form-urlencoded` string into decoded pairs.
+pub fn parse_query(query: &str) -> Vec<(String, String)> {
+	query
+		.split('&')
+		.filter(|pair| !pair.is_empty())
+		.map(|pair| {
+			let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+			(form_decode(name), form_decode(value))
+		})
+		.collect()
+}
+
+/// Joins pairs into an `application/x-www-form-urlencoded` string.
+pub fn form_encode(pairs: &[(String, String)]) -> String {
+	let encode = |text: &str| percent_encode(text).replace("%20", "+");
+	pairs
+		.iter()
+		.map(|(name, value)| format!("{}={}", encode(name), encode(value)))
+		.collect::<Vec<_>>()
+		.join("&")
+}
+
+/// Escapes everything except unreserved characters (RFC 3986, 2.3).
+pub fn percent_encode(text: &str) -> String {
+	let mut out = String::with_capacity(text.len());
+	for byte in text.bytes() {
+		if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+			out.push(byte as char);
+		} else {
+			out.push_str(&format!("%{byte:02X}"));
+		}
+	}
+	out
+}
+
+fn form_decode(text: &str) -> String {
+	percent_decode(&text.replace('+', " "))
+}
+
+/// Decodes `%XX` escapes. Invalid escapes are kept as they are.
+pub fn percent_decode(text: &str) -> String {
+	let bytes = text.as_bytes();
+	let mut out = Vec::with_capacity(bytes.len());
+	let mut index = 0;
+	while index < bytes.len() {
+		let escaped = (bytes[index] == b'%')
+			.then(|| text.get(index + 1..index + 3))
+			.flatten()
+			.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+		match escaped {
+			Some(byte) => {
+				out.push(byte);
+				index += 3;
+			}
+			None => {
+				out.push(bytes[index]);
+				index += 1;
+			}
+		}
+	}
+	String::from_utf8_lossy(&out).into_owned()
+}
+
+impl fmt::Display for Url {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}://{}{}", self.scheme, self.authority(), self.path)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_full_url() {
+		let url = Url::parse("http://Example.com:8080/a/b?x=1#frag").unwrap();
+		assert_eq!(url.scheme, "http");
+		assert_eq!(url.host, "example.com");
+		assert_eq!(url.port, 8080);
+		assert_eq!(url.path, "/a/b?x=1");
+		assert_eq!(url.to_string(), "http://example.com:8080/a/b?x=1");
+	}
+
+	#[test]
+	fn defaults_scheme_port_and_path() {
+		let url = Url::parse("localhost").unwrap();
+		assert_eq!(url.to_string(), "http://localhost/");
+		let url = Url::parse("https://host?q").unwrap();
+		assert_eq!(url.port, 443);
+		assert_eq!(url.path, "/?q");
+		let url = Url::parse("wss://host/chat").unwrap();
+		assert!(url.is_https() && url.has_default_port());
+		assert_eq!(url.to_string(), "wss://host/chat");
+		assert!(Url::parse("ftp://host/").is_err());
+		let url = Url::parse("localhost:8080/cb?next=http://x").unwrap();
+		assert_eq!(url.to_string(), "http://localhost:8080/cb?next=http://x");
+		let url = Url::parse("example.com/cb?next=https://x").unwrap();
+		assert_eq!((url.host.as_str(), url.port), ("example.com", 80));
+	}
+
+	#[test]
+	fn parses_ipv6() {
+		let url = Url::parse("http://[::1]:3000/").unwrap();
+		assert_eq!(url.host, "::1");
+		assert_eq!(url.port, 3000);
+		assert_eq!(url.authority(), "[::1]:3000");
+	}
+
+	#[test]
+	fn decodes_query() {
+		let url = Url::parse("http://host/a%20b?x=1&name=J%C3%B6rg+M&flag&bad=%zz").unwrap();
+		assert_eq!(url.path_only(), "/a%20b");
+		assert_eq!(
+			url.query_pairs(),
+			[
+				("x".to_string(), "1".to_string()),
+				("name".into(), "Jörg M".into()),
+				("flag".into(), "".into()),
+				("bad".into(), "%zz".into()),
+			]
+		);
+	}
+
+	#[test]
+	fn resolves_references() {
+		let base = Url::parse("http://a/b/c/d;p?q").unwrap();
+		let join = |reference| base.join(reference).unwrap().to_string();
+		assert_eq!(join("g"), "http://a/b/c/g");
+		assert_eq!(join("./g/"), "http://a/b/c/g/");
+		assert_eq!(join("/g"), "http://a/g");
+		assert_eq!(join("//g"), "http://g/");
+		assert_eq!(join("?y"), "http://a/b/c/d;p?y");
+		assert_eq!(join(""), "http://a/b/c/d;p?q");
+		assert_eq!(join(".."), "http://a/b/");
+		assert_eq!(join("../../../g"), "http://a/g");
+		assert_eq!(join("g;x=1/../y#frag"), "http://a/b/c/y");
+		assert_eq!(join("https://other:8443/x"), "https://other:8443/x");
+		assert_eq!(
+			join("/login?next=https://a.test/x"),
+			"http://a/login?next=https://a.test/x"
+		);
+		assert_eq!(join("g?u=http://x"), "http://a/b/c/g?u=http://x");
+		assert!(base.join("mailto:jo@a").is_err());
+
+		let other = Url::parse("http://a:80/x").unwrap();
+		assert!(base.same_origin(&other));
+		assert!(!base.same_origin(&Url::parse("https://a/").unwrap()));
+	}
+
+	#[test]
+	fn rejects_invalid() {
+		assert!(Url::parse("ftp://host/").is_err());
+		assert!(Url::parse("http://:80/").is_err());
+		assert!(Url::parse("http://host:abc/").is_err());
+	}
+}
@@ -0,0 +1,241 @@
+//! Small helpers shared across modules.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Non-cryptographic random number, good enough for identifiers and test
+/// data.
+pub fn random_u64() -> u64 {
+	let mut hasher = RandomState::new().build_hasher();
+	hasher.write_u128(
+		SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_nanos(),
+	);
+	hasher.finish()
+}
+
+/// Random version 4 UUID in its canonical textual form.
+pub fn random_uuid() -> String {
+	let mut bytes = [0u8; 16];
+	bytes[..8].copy_from_slice(&random_u64().to_le_bytes());
+	bytes[8..].copy_from_slice(&random_u64().to_le_bytes());
+	bytes[6] = (bytes[6] & 0x0f) | 0x40;
+	bytes[8] = (bytes[8] & 0x3f) | 0x80;
+	let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+	format!(
+		"{}-{}-{}-{}-{}",
+		&hex[..8],
+		&hex[8..12],
+		&hex[12..16],
+		&hex[16..20],
+		&hex[20..]
+	)
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_time() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs()
+}
+
+/// Formats a byte count or rate, e.g. `1.50 MiB`.
+pub fn format_bytes(value: f64) -> String {
+	match value {
+		v if v >= 1024.0 * 1024.0 => format!("{:.2} MiB", v / 1024.0 / 1024.0),
+		v if v >= 1024.0 => format!("{:.2} KiB", v / 1024.0),
+		v => format!("{v:.0} B"),
+	}
+}
+
+/// Formats a Unix timestamp as an RFC 3339 UTC date, e.g.
+/// `2022-01-31T10:20:30Z`.
+pub fn format_rfc3339(secs: u64) -> String {
+	let (year, month, day) = civil_from_days((secs / 86400) as i64);
+	let rem = secs % 86400;
+	format!(
+		"{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+		rem / 3600,
+		rem % 3600 / 60,
+		rem % 60
+	)
+}
+
+/// Formats a point in time as an RFC 3339 UTC date with milliseconds, e.g.
+/// `2022-01-31T10:20:30.125Z`.
+pub fn format_timestamp(time: SystemTime) -> String {
+	let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+	let seconds = format_rfc3339(since.as_secs());
+	format!(
+		"{}.{:03}Z",
+		seconds.trim_end_matches('Z'),
+		since.subsec_millis()
+	)
+}
+
+/// Current time of day in UTC with milliseconds, e.g. `10:20:30.125`.
+pub fn time_of_day() -> String {
+	let stamp = format_timestamp(SystemTime::now());
+	stamp[11..stamp.len() - 1].to_string()
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) triple.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+	let z = days + 719468;
+	let era = z.div_euclid(146097);
+	let doe = z.rem_euclid(146097);
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+	let year = yoe + era * 400 + i64::from(month <= 2);
+	(year, month, day)
+}
+
+/// Inverse of [`civil_from_days`].
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let yoe = year.rem_euclid(400);
+	let month = month as i64;
+	let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+	let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+	era * 146097 + doe - 719468
+}
+
+/// Parses a duration such as `250ms`, `1.5s`, `2m` or `1h`. A bare number
+/// is in seconds.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+	let text = text.trim();
+	let (number, unit) = match text.find(|c: char| c.is_ascii_alphabetic()) {
+		Some(index) => text.split_at(index),
+		None => (text, "s"),
+	};
+	let value: f64 = number.trim().parse().ok()?;
+	let secs = match unit {
+		"ms" => value / 1000.0,
+		"s" => value,
+		"m" => value * 60.0,
+		"h" => value * 3600.0,
+		_ => return None,
+	};
+	Duration::try_from_secs_f64(secs).ok()
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 with padding.
+pub fn base64_encode(data: &[u8]) -> String {
+	let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+	for chunk in data.chunks(3) {
+		let bytes = [
+			chunk[0],
+			*chunk.get(1).unwrap_or(&0),
+			*chunk.get(2).unwrap_or(&0),
+		];
+		let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+		for i in 0..4 {
+			if i <= chunk.len() {
+				out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+			} else {
+				out.push('=');
+			}
+		}
+	}
+	out
+}
+
+/// Decodes standard or URL-safe base64. Padding and whitespace are
+/// optional.
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+	let mut out = Vec::with_capacity(text.len() / 4 * 3);
+	let mut bits = 0u32;
+	let mut count = 0;
+	for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+		let value = match c {
+			b'A'..=b'Z' => c - b'A',
+			b'a'..=b'z' => c - b'a' + 26,
+			b'0'..=b'9' => c - b'0' + 52,
+			b'+' | b'-' => 62,
+			b'/' | b'_' => 63,
+			b'=' => break,
+			_ => return None,
+		};
+		bits = bits << 6 | u32::from(value);
+		count += 1;
+		if count == 4 {
+			out.extend_from_slice(&bits.to_be_bytes()[1..]);
+			bits = 0;
+			count = 0;
+		}
+	}
+	match count {
+		0 => {}
+		2 => out.push((bits >> 4) as u8),
+		3 => out.extend_from_slice(&((bits >> 2) as u16).to_be_bytes()),
+		_ => return None,
+	}
+	Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn formats_dates() {
+		assert_eq!(format_rfc3339(0), "1970-01-01T00:00:00Z");
+		assert_eq!(format_rfc3339(951782400 + 3661), "2000-02-29T01:01:01Z");
+		assert_eq!(
+			format_timestamp(UNIX_EPOCH + Duration::from_millis(951_782_400_042)),
+			"2000-02-29T00:00:00.042Z"
+		);
+		assert_eq!(days_from_civil(2000, 2, 29), 951782400 / 86400);
+		assert_eq!(
+			civil_from_days(days_from_civil(2024, 12, 31)),
+			(2024, 12, 31)
+		);
+	}
+
+	#[test]
+	fn parses_durations() {
+		assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+		assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+		assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+		assert_eq!(parse_duration("10"), Some(Duration::from_secs(10)));
+		assert_eq!(parse_duration("5 days"), None);
+		assert_eq!(parse_duration("-1s"), None);
+	}
+
+	#[test]
+	fn generates_uuids() {
+		let uuid = random_uuid();
+		assert_eq!(uuid.len(), 36);
+		assert_eq!(&uuid[14..15], "4");
+		assert_ne!(uuid, random_uuid());
+	}
+
+	#[test]
+	fn encodes_base64() {
+		for (data, text) in [
+			(&b""[..], ""),
+			(b"f", "Zg=="),
+			(b"fo", "Zm8="),
+			(b"foo", "Zm9v"),
+			(b"foob", "Zm9vYg=="),
+			(b"\xfb\xff", "+/8="),
+		] {
+			assert_eq!(base64_encode(data), text);
+			assert_eq!(base64_decode(text).unwrap(), data);
+		}
+		assert_eq!(base64_decode("-_8").unwrap(), b"\xfb\xff");
+		assert_eq!(base64_decode("Zm9v\nYg").unwrap(), b"foob");
+		assert!(base64_decode("Z").is_none());
+		assert!(base64_decode("Zm9v!").is_none());
+	}
+}
@@ -0,0 +1,178 @@
+//! The permessage-deflate extension (RFC 7692): messages compressed with
+//! raw deflate, each ending with an empty stored block that is left out.
+
+use std::io;
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use super::frame::MAX_PAYLOAD;
+
+/// Extension offered in `Sec-WebSocket-Extensions`. The window sizes are
+/// left to their default, which deflate without zlib does not change.
+pub const OFFER: &str = "permessage-deflate";
+
+/// Empty stored block ending each compressed message.
+const TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Compression state of a connection, kept across messages unless a side
+/// asks for no context takeover.
+pub struct Deflate {
+	compress: Compress,
+	decompress: Decompress,
+	client_no_context_takeover: bool,
+	server_no_context_takeover: bool,
+}
+
+impl Deflate {
+	/// Compression agreed on by the `Sec-WebSocket-Extensions` value of the
+	/// handshake response, `None` when the server did not accept it.
+	pub fn negotiate(extensions: Option<&str>) -> Result<Option<Deflate>, String> {
+		let Some(extensions) = extensions else {
+			return Ok(None);
+		};
+		let mut deflate = None;
+		for extension in extensions.split(',') {
+			let mut params = extension.split(';').map(str::trim);
+			let name = params.next().unwrap_or_default();
+			if !name.eq_ignore_ascii_case("permessage-deflate") {
+				return Err(format!("server enabled the extension `{name}`"));
+			}
+			let mut state = Deflate {
+				compress: Compress::new(Compression::default(), false),
+				decompress: Decompress::new(false),
+				client_no_context_takeover: false,
+				server_no_context_takeover: false,
+			};
+			for param in params {
+				let (key, value) = param.split_once('=').unwrap_or((param, ""));
+				match key.trim().to_ascii_lowercase().as_str() {
+					"client_no_context_takeover" => state.client_no_context_takeover = true,
+					"server_no_context_takeover" => state.server_no_context_takeover = true,
+					// a smaller window of the server still decompresses
+					"server_max_window_bits" => {}
+					"client_max_window_bits" if value.trim().trim_matches('"') == "15" => {}
+					_ => {
+						return Err(format!(
+							"unsupported permessage-deflate parameter `{param}`"
+						))
+					}
+				}
+			}
+			deflate = Some(state);
+		}
+		Ok(deflate)
+	}
+
+	/// Compresses the payload of a message.
+	pub fn compress(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+		if self.client_no_context_takeover {
+			self.compress.reset();
+		}
+		let start = self.compress.total_in();
+		let mut out = Vec::with_capacity(data.len() / 2 + 64);
+		loop {
+			if out.len() == out.capacity() {
+				out.reserve(out.len().max(64));
+			}
+			let consumed = (self.compress.total_in() - start) as usize;
+			self.compress
+				.compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+				.map_err(io::Error::other)?;
+			let consumed = (self.compress.total_in() - start) as usize;
+			// the flush is complete once it leaves room in the output
+			if consumed == data.len() && out.len() < out.capacity() {
+				break;
+			}
+		}
+		if out.ends_with(&TAIL) {
+			out.truncate(out.len() - TAIL.len());
+		}
+		Ok(out)
+	}
+
+	/// Decompresses the payload of a message, which fails once it is over
+	/// [`MAX_PAYLOAD`] bytes.
+	pub fn decompress(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+		if self.server_no_context_takeover {
+			self.decompress.reset(false);
+		}
+		let mut input = data.to_vec();
+		input.extend_from_slice(&TAIL);
+		let start = self.decompress.total_in();
+		let too_large = || {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("decompressed message over {} MiB", MAX_PAYLOAD >> 20),
+			)
+		};
+		// one byte over the limit tells a message that is too large
+		let limit = MAX_PAYLOAD as usize + 1;
+		let mut out = Vec::with_capacity((data.len() * 4 + 64).min(limit));
+		loop {
+			if out.len() == out.capacity() {
+				if out.len() >= limit {
+					return Err(too_large());
+				}
+				out.reserve_exact(out.len().min(limit - out.len()));
+			}
+			let consumed = (self.decompress.total_in() - start) as usize;
+			let status = self
+				.decompress
+				.decompress_vec(&input[consumed..], &mut out, FlushDecompress::Sync)
+				.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+			let consumed = (self.decompress.total_in() - start) as usize;
+			if status == Status::StreamEnd
+				|| (consumed == input.len() && out.len() < out.capacity())
+			{
+				break;
+			}
+		}
+		if out.len() as u64 > MAX_PAYLOAD {
+			return Err(too_large());
+		}
+		Ok(out)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn negotiates_and_compresses_messages() {
+		assert!(Deflate::negotiate(None).unwrap().is_none());
+		assert!(Deflate::negotiate(Some("x-webkit-deflate-frame")).is_err());
+		assert!(Deflate::negotiate(Some("permessage-deflate; client_max_window_bits=10")).is_err());
+
+		let mut client = Deflate::negotiate(Some(
+			"permessage-deflate; server_max_window_bits=12; client_no_context_takeover",
+		))
+		.unwrap()
+		.unwrap();
+		let mut server = Deflate::negotiate(Some(OFFER)).unwrap().unwrap();
+		for message in [&b"hello hello hello"[..], b"", &[42; 100_000]] {
+			let compressed = client.compress(message).unwrap();
+			assert!(!compressed.ends_with(&TAIL));
+			assert_eq!(server.decompress(&compressed).unwrap(), message);
+		}
+
+		// RFC 7692, 7.2.3.1: "Hello" compressed
+		let mut client = Deflate::negotiate(Some(OFFER)).unwrap().unwrap();
+		let hello = b"\xf2\x48\xcd\xc9\xc9\x07\x00";
+		assert_eq!(client.decompress(hello).unwrap(), b"Hello");
+		assert!(client.decompress(b"\xff\xff").is_err());
+
+		// a small message may not inflate past the limit
+		let mut bomb = Deflate::negotiate(Some(OFFER)).unwrap().unwrap();
+		let zeros = vec![0; 1 << 20];
+		let mut compressed = Vec::new();
+		for _ in 0..=MAX_PAYLOAD >> 20 {
+			compressed.extend(bomb.compress(&zeros).unwrap());
+			compressed.extend_from_slice(&TAIL);
+		}
+		assert!(compressed.len() < 1 << 20);
+		let mut client = Deflate::negotiate(Some(OFFER)).unwrap().unwrap();
+		let err = client.decompress(&compressed).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+	}
+}
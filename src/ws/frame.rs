@@ -0,0 +1,179 @@
+//! WebSocket framing (RFC 6455, 5).
+
+pub const CONTINUATION: u8 = 0x0;
+pub const TEXT: u8 = 0x1;
+pub const BINARY: u8 = 0x2;
+pub const CLOSE: u8 = 0x8;
+pub const PING: u8 = 0x9;
+pub const PONG: u8 = 0xa;
+
+/// Largest frame payload accepted, and decompressed message.
+pub const MAX_PAYLOAD: u64 = 64 << 20;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+	/// Last frame of a message.
+	pub fin: bool,
+	/// Marks the first frame of a compressed message (RFC 7692, 6).
+	pub rsv1: bool,
+	pub opcode: u8,
+	/// Payload, unmasked.
+	pub payload: Vec<u8>,
+}
+
+impl Frame {
+	/// Single frame message or control frame.
+	pub fn new(opcode: u8, payload: Vec<u8>) -> Frame {
+		Frame {
+			fin: true,
+			rsv1: false,
+			opcode,
+			payload,
+		}
+	}
+
+	/// Control frames are not fragmented and may come between the frames of
+	/// a message.
+	pub fn is_control(&self) -> bool {
+		self.opcode & 0x8 != 0
+	}
+
+	/// Encodes the frame, masked with the key as clients do, or unmasked
+	/// as servers do without one.
+	pub fn encode(&self, mask: Option<[u8; 4]>) -> Vec<u8> {
+		let len = self.payload.len();
+		let mut out = Vec::with_capacity(len + 14);
+		out.push(u8::from(self.fin) << 7 | u8::from(self.rsv1) << 6 | self.opcode);
+		let masked = if mask.is_some() { 0x80 } else { 0 };
+		match len {
+			0..=125 => out.push(masked | len as u8),
+			126..=0xffff => {
+				out.push(masked | 126);
+				out.extend_from_slice(&(len as u16).to_be_bytes());
+			}
+			_ => {
+				out.push(masked | 127);
+				out.extend_from_slice(&(len as u64).to_be_bytes());
+			}
+		}
+		match mask {
+			Some(key) => {
+				out.extend_from_slice(&key);
+				out.extend(
+					self.payload
+						.iter()
+						.zip(key.iter().cycle())
+						.map(|(byte, key)| byte ^ key),
+				);
+			}
+			None => out.extend_from_slice(&self.payload),
+		}
+		out
+	}
+
+	/// Decodes the frame at the start of `data`, returning it with the
+	/// number of bytes it takes, or `None` until `data` holds all of it.
+	pub fn decode(data: &[u8]) -> Result<Option<(Frame, usize)>, String> {
+		let [first, second, ..] = *data else {
+			return Ok(None);
+		};
+		if first & 0x30 != 0 {
+			return Err("frame with reserved bits set".into());
+		}
+		let opcode = first & 0x0f;
+		if !matches!(opcode, CONTINUATION | TEXT | BINARY | CLOSE | PING | PONG) {
+			return Err(format!("unknown opcode {opcode:#x}"));
+		}
+		let mut at = 2;
+		let len = match second & 0x7f {
+			126 => {
+				let Some(bytes) = data.get(2..4) else {
+					return Ok(None);
+				};
+				at = 4;
+				u64::from(u16::from_be_bytes([bytes[0], bytes[1]]))
+			}
+			127 => {
+				let Some(bytes) = data.get(2..10) else {
+					return Ok(None);
+				};
+				at = 10;
+				u64::from_be_bytes(bytes.try_into().unwrap())
+			}
+			len => u64::from(len),
+		};
+		let fin = first & 0x80 != 0;
+		if opcode & 0x8 != 0 && (len > 125 || !fin) {
+			return Err("fragmented or oversized control frame".into());
+		}
+		if len > MAX_PAYLOAD {
+			return Err(format!("frame of {len} bytes is too large"));
+		}
+		let mask = match second & 0x80 {
+			0 => None,
+			_ => {
+				let Some(key) = data.get(at..at + 4) else {
+					return Ok(None);
+				};
+				at += 4;
+				Some([key[0], key[1], key[2], key[3]])
+			}
+		};
+		let end = at + len as usize;
+		let Some(payload) = data.get(at..end) else {
+			return Ok(None);
+		};
+		let payload = match mask {
+			Some(key) => payload
+				.iter()
+				.zip(key.iter().cycle())
+				.map(|(byte, key)| byte ^ key)
+				.collect(),
+			None => payload.to_vec(),
+		};
+		let frame = Frame {
+			fin,
+			rsv1: first & 0x40 != 0,
+			opcode,
+			payload,
+		};
+		Ok(Some((frame, end)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn encodes_and_decodes_frames() {
+		// RFC 6455, 5.7: a masked "Hello"
+		let hello = Frame::new(TEXT, b"Hello".to_vec());
+		let masked = hello.encode(Some([0x37, 0xfa, 0x21, 0x3d]));
+		assert_eq!(
+			masked,
+			b"\x81\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58".to_vec()
+		);
+		assert_eq!(Frame::decode(&masked), Ok(Some((hello.clone(), 11))));
+		assert_eq!(hello.encode(None), b"\x81\x05Hello".to_vec());
+
+		for len in [126, 0x10000] {
+			let frame = Frame {
+				fin: false,
+				rsv1: true,
+				opcode: BINARY,
+				payload: vec![7; len],
+			};
+			let data = frame.encode(None);
+			assert_eq!(data.len(), len + if len < 0x10000 { 4 } else { 10 });
+			assert_eq!(Frame::decode(&data[..data.len() - 1]), Ok(None));
+			assert_eq!(Frame::decode(&data), Ok(Some((frame, data.len()))));
+		}
+
+		assert_eq!(Frame::decode(b"\x81"), Ok(None));
+		assert!(Frame::decode(b"\xa1\x00").is_err());
+		assert!(Frame::decode(b"\x83\x00").is_err());
+		assert!(Frame::decode(b"\x09\x00").is_err());
+		assert!(Frame::decode(b"\x81\x7f\x00\x00\x00\x00\x10\x00\x00\x00").is_err());
+	}
+}
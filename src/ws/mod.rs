@@ -0,0 +1,381 @@
+//! WebSocket client (RFC 6455) with the permessage-deflate extension
+//! (RFC 7692).
+//!
+//! ```no_run
+//! use webcat::ws::{Message, WebSocket};
+//! use webcat::{Client, Request, Url};
+//!
+//! let request = Request::new("GET", Url::parse("wss://echo.example/")?);
+//! let mut socket = WebSocket::connect(&Client::new(), &request, true)?;
+//! socket.send(&Message::Text("hello".into()))?;
+//! let reply = socket.receive(None)?;
+//! # Ok::<(), webcat::Error>(())
+//! ```
+
+pub mod deflate;
+pub mod frame;
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::client::{Client, Stream};
+use crate::error::{Error, Result};
+use crate::http::{Request, Response};
+use crate::util::base64_encode;
+use deflate::Deflate;
+use frame::Frame;
+
+/// Appended to the key of the handshake before hashing it (RFC 6455, 1.3).
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+	Text(String),
+	Binary(Vec<u8>),
+	Ping(Vec<u8>),
+	Pong(Vec<u8>),
+	/// Status code and reason, if any.
+	Close(Option<(u16, String)>),
+}
+
+impl Message {
+	/// Text message for valid UTF-8, binary otherwise.
+	pub fn from_bytes(data: Vec<u8>) -> Message {
+		match String::from_utf8(data) {
+			Ok(text) => Message::Text(text),
+			Err(err) => Message::Binary(err.into_bytes()),
+		}
+	}
+
+	/// Payload of the message, as sent in its frames.
+	pub fn payload(&self) -> Vec<u8> {
+		match self {
+			Message::Text(text) => text.as_bytes().to_vec(),
+			Message::Binary(data) | Message::Ping(data) | Message::Pong(data) => data.clone(),
+			Message::Close(None) => Vec::new(),
+			Message::Close(Some((code, reason))) => {
+				let mut payload = code.to_be_bytes().to_vec();
+				payload.extend_from_slice(reason.as_bytes());
+				payload
+			}
+		}
+	}
+}
+
+/// One line per message: text as is, other payloads in hexadecimal after
+/// the kind of message.
+impl fmt::Display for Message {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let hex = |data: &[u8]| {
+			data.iter()
+				.map(|byte| format!(" {byte:02x}"))
+				.collect::<String>()
+		};
+		match self {
+			Message::Text(text) => write!(f, "{text}"),
+			Message::Binary(data) => write!(f, "[binary {} B]{}", data.len(), hex(data)),
+			Message::Ping(data) => write!(f, "[ping]{}", hex(data)),
+			Message::Pong(data) => write!(f, "[pong]{}", hex(data)),
+			Message::Close(None) => write!(f, "[close]"),
+			Message::Close(Some((code, reason))) => write!(f, "[close {code}] {reason}"),
+		}
+	}
+}
+
+/// Open WebSocket connection.
+pub struct WebSocket {
+	/// Response to the handshake.
+	pub response: Response,
+	stream: Stream,
+	/// Bytes read and not yet decoded.
+	buffer: Vec<u8>,
+	deflate: Option<Deflate>,
+	/// Opcode, compression and payload of a fragmented message.
+	partial: Option<(u8, bool, Vec<u8>)>,
+	/// Timeout for reads, restored after waiting for a message.
+	timeout: Option<Duration>,
+	closing: bool,
+	closed: bool,
+	random: SystemRandom,
+}
+
+impl WebSocket {
+	/// Opens a WebSocket with the handshake of `request`, a GET request to
+	/// a `ws`, `wss`, `http` or `https` URL, offering permessage-deflate
+	/// when `compress` is true.
+	pub fn connect(client: &Client, request: &Request, compress: bool) -> Result<WebSocket> {
+		let random = SystemRandom::new();
+		let mut nonce = [0; 16];
+		random
+			.fill(&mut nonce)
+			.map_err(|_| Error::Protocol("no random numbers for the handshake".into()))?;
+		let key = base64_encode(&nonce);
+
+		let mut request = request.clone();
+		request.headers.set("Upgrade", "websocket");
+		request.headers.set("Connection", "Upgrade");
+		request.headers.set("Sec-WebSocket-Key", key.as_str());
+		request.headers.set("Sec-WebSocket-Version", "13");
+		if compress && !request.headers.contains("Sec-WebSocket-Extensions") {
+			request
+				.headers
+				.set("Sec-WebSocket-Extensions", deflate::OFFER);
+		}
+		let (response, reader) = client.open(&request)?;
+		if response.status != 101 {
+			return Err(Error::Protocol(format!(
+				"WebSocket handshake refused with `{}`",
+				response.status_line()
+			)));
+		}
+		let header = |name| response.headers.get(name).unwrap_or_default();
+		if !header("Upgrade").eq_ignore_ascii_case("websocket") {
+			return Err(Error::Protocol("missing `Upgrade: websocket`".into()));
+		}
+		if header("Sec-WebSocket-Accept") != accept_key(&key) {
+			return Err(Error::Protocol(
+				"invalid `Sec-WebSocket-Accept` in the handshake".into(),
+			));
+		}
+		let deflate = Deflate::negotiate(response.headers.get("Sec-WebSocket-Extensions"))
+			.map_err(Error::Protocol)?;
+
+		// frames sent right after the handshake are already buffered
+		let buffer = reader.buffer().to_vec();
+		Ok(WebSocket {
+			response,
+			stream: reader.into_inner(),
+			buffer,
+			deflate,
+			partial: None,
+			timeout: client.timeout,
+			closing: false,
+			closed: false,
+			random,
+		})
+	}
+
+	/// Returns true once permessage-deflate is in use.
+	pub fn is_compressed(&self) -> bool {
+		self.deflate.is_some()
+	}
+
+	/// Returns true once the server closed the connection.
+	pub fn is_closed(&self) -> bool {
+		self.closed
+	}
+
+	/// Sends a message, compressing text and binary ones when the
+	/// extension is in use. A close message starts the closing handshake.
+	pub fn send(&mut self, message: &Message) -> Result<()> {
+		let opcode = match message {
+			Message::Text(_) => frame::TEXT,
+			Message::Binary(_) => frame::BINARY,
+			Message::Ping(_) => frame::PING,
+			Message::Pong(_) => frame::PONG,
+			Message::Close(_) => frame::CLOSE,
+		};
+		let mut frame = Frame::new(opcode, message.payload());
+		if frame.is_control() && frame.payload.len() > 125 {
+			return Err(Error::Usage(
+				"control messages have at most 125 bytes".into(),
+			));
+		}
+		if let (Some(deflate), false) = (&mut self.deflate, frame.is_control()) {
+			frame.payload = deflate.compress(&frame.payload)?;
+			frame.rsv1 = true;
+		}
+		self.closing |= opcode == frame::CLOSE;
+		self.write_frame(&frame)
+	}
+
+	/// Waits for the next message, up to `timeout` or for as long as it
+	/// takes without one. Returns `None` when none came in time. Pings are
+	/// answered and a close message from the server is echoed before they
+	/// are returned.
+	pub fn receive(&mut self, timeout: Option<Duration>) -> Result<Option<Message>> {
+		let deadline = timeout.map(|timeout| Instant::now() + timeout);
+		loop {
+			if let Some((frame, len)) = Frame::decode(&self.buffer).map_err(Error::Protocol)? {
+				self.buffer.drain(..len);
+				if let Some(message) = self.on_frame(frame)? {
+					return Ok(Some(message));
+				}
+				continue;
+			}
+			if self.closed {
+				return Err(Error::Protocol("WebSocket closed".into()));
+			}
+			let wait = match deadline {
+				Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+					Some(wait) if !wait.is_zero() => Some(wait),
+					_ => return Ok(None),
+				},
+				None => None,
+			};
+			let mut chunk = [0; 16 * 1024];
+			self.stream.socket().set_read_timeout(wait)?;
+			let read = self.stream.read(&mut chunk);
+			self.stream.socket().set_read_timeout(self.timeout)?;
+			match read {
+				Ok(0) => {
+					self.closed = true;
+					return Err(Error::Protocol(
+						"connection closed without a close message".into(),
+					));
+				}
+				Ok(count) => self.buffer.extend_from_slice(&chunk[..count]),
+				Err(err)
+					if matches!(
+						err.kind(),
+						io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+					) =>
+				{
+					return Ok(None)
+				}
+				Err(err) => return Err(err.into()),
+			}
+		}
+	}
+
+	/// Starts the closing handshake if needed and waits for the close
+	/// message of the server, up to `timeout`.
+	pub fn close(&mut self, timeout: Option<Duration>) -> Result<()> {
+		if !self.closing {
+			self.send(&Message::Close(Some((1000, String::new()))))?;
+		}
+		while !self.closed {
+			match self.receive(timeout)? {
+				Some(Message::Close(_)) | None => break,
+				Some(_) => {}
+			}
+		}
+		Ok(())
+	}
+
+	/// Handles a frame, returning the message it completes, if any.
+	fn on_frame(&mut self, frame: Frame) -> Result<Option<Message>> {
+		match frame.opcode {
+			frame::PING => {
+				if !self.closing {
+					self.write_frame(&Frame::new(frame::PONG, frame.payload.clone()))?;
+				}
+				Ok(Some(Message::Ping(frame.payload)))
+			}
+			frame::PONG => Ok(Some(Message::Pong(frame.payload))),
+			frame::CLOSE => {
+				let status = match frame.payload.as_slice() {
+					[] => None,
+					[high, low, reason @ ..] => Some((
+						u16::from_be_bytes([*high, *low]),
+						String::from_utf8_lossy(reason).into_owned(),
+					)),
+					_ => return Err(Error::Protocol("invalid close message".into())),
+				};
+				if !self.closing {
+					// echoes the status code
+					let payload = frame.payload.get(..2).unwrap_or_default().to_vec();
+					self.write_frame(&Frame::new(frame::CLOSE, payload))?;
+					self.closing = true;
+				}
+				self.closed = true;
+				Ok(Some(Message::Close(status)))
+			}
+			frame::CONTINUATION => {
+				let Some((_, _, data)) = &mut self.partial else {
+					return Err(Error::Protocol("continuation without a message".into()));
+				};
+				data.extend_from_slice(&frame.payload);
+				match frame.fin {
+					true => self.finish(),
+					false => Ok(None),
+				}
+			}
+			opcode => {
+				if self.partial.is_some() {
+					return Err(Error::Protocol(
+						"new message before the end of the previous one".into(),
+					));
+				}
+				if frame.rsv1 && self.deflate.is_none() {
+					return Err(Error::Protocol(
+						"compressed message without permessage-deflate".into(),
+					));
+				}
+				self.partial = Some((opcode, frame.rsv1, frame.payload));
+				match frame.fin {
+					true => self.finish(),
+					false => Ok(None),
+				}
+			}
+		}
+	}
+
+	/// Assembles the message of the frames received.
+	fn finish(&mut self) -> Result<Option<Message>> {
+		let Some((opcode, compressed, mut data)) = self.partial.take() else {
+			return Ok(None);
+		};
+		if let (true, Some(deflate)) = (compressed, &mut self.deflate) {
+			data = deflate
+				.decompress(&data)
+				.map_err(|err| Error::Protocol(format!("invalid compressed message: {err}")))?;
+		}
+		Ok(Some(match opcode {
+			frame::TEXT => Message::Text(
+				String::from_utf8(data)
+					.map_err(|_| Error::Protocol("text message is not UTF-8".into()))?,
+			),
+			_ => Message::Binary(data),
+		}))
+	}
+
+	fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+		let mut mask = [0; 4];
+		self.random
+			.fill(&mut mask)
+			.map_err(|_| Error::Protocol("no random numbers for the mask".into()))?;
+		self.stream.write_all(&frame.encode(Some(mask)))?;
+		self.stream.flush()?;
+		Ok(())
+	}
+}
+
+/// Value of `Sec-WebSocket-Accept` for a `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+	let digest = ring::digest::digest(
+		&ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+		format!("{key}{GUID}").as_bytes(),
+	);
+	base64_encode(digest.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn computes_the_accept_key() {
+		// RFC 6455, 1.3
+		assert_eq!(
+			accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+			"s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+		);
+	}
+
+	#[test]
+	fn formats_messages() {
+		assert_eq!(Message::from_bytes(b"hi".to_vec()).to_string(), "hi");
+		assert_eq!(
+			Message::from_bytes(vec![0xff, 0]).to_string(),
+			"[binary 2 B] ff 00"
+		);
+		let close = Message::Close(Some((1001, "bye".into())));
+		assert_eq!(close.payload(), b"\x03\xe9bye");
+		assert_eq!(close.to_string(), "[close 1001] bye");
+		assert_eq!(Message::Ping(vec![1]).to_string(), "[ping] 01");
+	}
+}
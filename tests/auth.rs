@@ -0,0 +1,175 @@
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use webcat::auth::Auth;
+use webcat::mock::{MockServer, RouteFile};
+use webcat::{Client, Request, Url, Variables};
+
+const ROUTES: &str = "\
+### Client credentials
+POST /token
+?? body contains grant_type=client_credentials
+# @auth Basic app s3cret
+
+HTTP/1.1 200
+Content-Type: application/json
+
+{\"access_token\": \"t0k3n\", \"token_type\": \"Bearer\", \"expires_in\": 3600}
+
+### Refresh
+POST /token
+?? body contains grant_type=refresh_token&refresh_token=r1
+# @auth Basic app s3cret
+
+HTTP/1.1 200
+Content-Type: application/json
+
+{\"access_token\": \"t0k3n\", \"expires_in\": \"3600\"}
+
+### Basic
+GET /basic
+# @auth Basic jo secret
+
+HTTP/1.1 204
+
+### Digest
+POST /digest?x=1
+# @auth Digest jo secret
+
+HTTP/1.1 204
+
+### Bearer
+GET /api
+# @auth Bearer t0k3n
+
+HTTP/1.1 204
+
+### AWS
+PUT /aws/a%20b
+# @auth AWS access_key=AKID secret_key=SECRET region=local service=mock
+
+HTTP/1.1 204
+";
+
+/// Starts a mock server on a free port and returns its base URL and log.
+fn start() -> (String, Arc<Mutex<Vec<String>>>) {
+	let routes = RouteFile::parse("routes.http", ROUTES, Variables::new()).unwrap();
+	let server = MockServer::bind("127.0.0.1:0", routes).unwrap();
+	let base = format!("http://{}", server.local_addr().unwrap());
+	let log = Arc::new(Mutex::new(Vec::new()));
+	let lines = Arc::clone(&log);
+	thread::spawn(move || {
+		server
+			.run(move |exchange| lines.lock().unwrap().push(exchange.to_string()))
+			.unwrap()
+	});
+	(base, log)
+}
+
+fn status(client: &Client, method: &str, url: &str, authorization: Option<&str>) -> u16 {
+	let mut request = Request::new(method, Url::parse(url).unwrap());
+	if let Some(value) = authorization {
+		request.headers.set("Authorization", value);
+	}
+	request.body = b"payload".to_vec();
+	client.send(&request).unwrap().status
+}
+
+#[test]
+fn authenticates_with_named_schemes() {
+	let (base, log) = start();
+	let client = Client::new();
+	let url = |path: &str| format!("{base}{path}");
+
+	assert_eq!(status(&client, "GET", &url("/basic"), None), 401);
+	assert_eq!(
+		status(&client, "GET", &url("/basic"), Some("Basic jo:secret")),
+		204
+	);
+	assert_eq!(
+		status(&client, "GET", &url("/basic"), Some("Basic jo wrong")),
+		401
+	);
+
+	let digest = Some("Digest jo secret");
+	assert_eq!(status(&client, "POST", &url("/digest?x=1"), digest), 204);
+	assert_eq!(
+		status(&client, "POST", &url("/digest?x=1"), Some("Digest jo nope")),
+		401
+	);
+
+	let aws = "AWS access_key=AKID secret_key=SECRET region=local service=mock";
+	assert_eq!(status(&client, "PUT", &url("/aws/a%20b"), Some(aws)), 204);
+	let wrong = aws.replace("SECRET", "OTHER");
+	assert_eq!(
+		status(&client, "PUT", &url("/aws/a%20b"), Some(&wrong)),
+		401
+	);
+
+	// the token is fetched once and reused
+	let oauth2 = format!(
+		"OAuth2 token_url={} client_id=app client_secret=s3cret",
+		url("/token")
+	);
+	assert_eq!(status(&client, "GET", &url("/api"), Some(&oauth2)), 204);
+	assert_eq!(
+		status(&client.clone(), "GET", &url("/api"), Some(&oauth2)),
+		204
+	);
+	let refresh = format!("{oauth2} refresh_token=r1");
+	assert_eq!(status(&client, "GET", &url("/api"), Some(&refresh)), 204);
+	let tokens: Vec<String> = log
+		.lock()
+		.unwrap()
+		.iter()
+		.filter(|line| line.starts_with("POST /token"))
+		.cloned()
+		.collect();
+	assert_eq!(
+		tokens,
+		[
+			"POST /token -> 200 (Client credentials)",
+			"POST /token -> 200 (Refresh)"
+		]
+	);
+
+	let bad = format!(
+		"OAuth2 token_url={} client_id=app client_secret=x",
+		url("/token")
+	);
+	let mut request = Request::new("GET", Url::parse(&url("/api")).unwrap());
+	request.headers.set("Authorization", bad);
+	let err = client.send(&request).unwrap_err();
+	assert!(err
+		.to_string()
+		.ends_with("/token failed: status 401 without a JSON body"));
+}
+
+#[test]
+fn authenticates_all_requests_with_the_auth_option() {
+	let (base, _) = start();
+	let client = Client {
+		auth: Some(Auth::parse("digest jo:secret").unwrap()),
+		..Client::new()
+	};
+	assert_eq!(
+		status(&client, "POST", &format!("{base}/digest?x=1"), None),
+		204
+	);
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["--auth", "Basic jo:secret", &format!("{base}/basic")])
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	assert!(String::from_utf8(output.stdout)
+		.unwrap()
+		.starts_with("HTTP/1.1 204"));
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["--auth", "Magic x", &format!("{base}/basic")])
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::TestServer;
+use webcat::{Client, Request, Url};
+
+#[test]
+fn sends_request_and_reads_response() {
+	let server =
+		TestServer::fixed("HTTP/1.1 201 Created\r\nContent-Length: 2\r\nX-Id: 7\r\n\r\nok");
+	let mut request = Request::new("PUT", Url::parse(&server.url("/items/1")).unwrap());
+	request.headers.append("Content-Type", "text/plain");
+	request.body = b"data".to_vec();
+
+	let response = Client::new().send(&request).unwrap();
+	assert_eq!(response.status, 201);
+	assert_eq!(response.headers.get("x-id"), Some("7"));
+	assert_eq!(response.body_text(), "ok");
+
+	let raw = &server.requests()[0];
+	assert!(raw.starts_with("PUT /items/1 HTTP/1.1\r\n"));
+	assert!(raw.contains(&format!("Host: 127.0.0.1:{}\r\n", server.port)));
+	assert!(raw.contains("Content-Type: text/plain\r\n"));
+	assert!(raw.ends_with("\r\n\r\ndata"));
+}
+
+#[test]
+fn binary_prints_response() {
+	let server = TestServer::fixed("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nhello");
+	let output = std::process::Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args([&server.url("/"), "-H", "Accept: text/plain"])
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	assert_eq!(
+		String::from_utf8(output.stdout).unwrap(),
+		"HTTP/1.1 200 OK\nConnection: close\n\nhello\n"
+	);
+	assert!(server.requests()[0].contains("Accept: text/plain\r\n"));
+}
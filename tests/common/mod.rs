@@ -0,0 +1,94 @@
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Minimal HTTP/1.1 server for tests. Every connection handles a single
+/// request, answered by the handler with a raw response.
+pub struct TestServer {
+	pub port: u16,
+	requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestServer {
+	pub fn start<R: AsRef<[u8]>>(
+		handler: impl Fn(&str) -> R + Send + Sync + 'static,
+	) -> TestServer {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let requests = Arc::new(Mutex::new(Vec::new()));
+		let handler = Arc::new(handler);
+		let log = requests.clone();
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let Ok(stream) = stream else { break };
+				let handler = handler.clone();
+				let log = log.clone();
+				thread::spawn(move || {
+					let raw = read_request(&stream);
+					let response = handler(&raw);
+					log.lock().unwrap().push(raw);
+					let _ = (&stream).write_all(response.as_ref());
+				});
+			}
+		});
+		TestServer { port, requests }
+	}
+
+	/// Server that always answers with the same response.
+	pub fn fixed(response: &'static str) -> TestServer {
+		Self::start(move |_| response.to_string())
+	}
+
+	pub fn url(&self, path: &str) -> String {
+		format!("http://127.0.0.1:{}{path}", self.port)
+	}
+
+	/// Raw requests received so far.
+	pub fn requests(&self) -> Vec<String> {
+		self.requests.lock().unwrap().clone()
+	}
+}
+
+/// Reads the head and a `Content-Length` delimited body.
+fn read_request(stream: &std::net::TcpStream) -> String {
+	let mut reader = BufReader::new(stream);
+	let mut raw = String::new();
+	let mut length = 0;
+	loop {
+		let mut line = String::new();
+		if reader.read_line(&mut line).unwrap_or(0) == 0 {
+			return raw;
+		}
+		if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+			length = value.trim().parse().unwrap();
+		}
+		raw.push_str(&line);
+		if line == "\r\n" {
+			break;
+		}
+	}
+	let mut body = vec![0; length];
+	reader.read_exact(&mut body).unwrap();
+	raw + &String::from_utf8_lossy(&body)
+}
+
+/// Builds a raw response with the given status and body.
+pub fn response(status: u16, headers: &[(&str, &str)], body: &str) -> String {
+	let mut raw = format!("HTTP/1.1 {status} X\r\nContent-Length: {}\r\n", body.len());
+	for (name, value) in headers {
+		raw.push_str(&format!("{name}: {value}\r\n"));
+	}
+	raw + "\r\n" + body
+}
+
+/// Empty directory for the files of a test, unique to the test process.
+pub fn temp_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("webcat-{name}-{}", std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use std::process::Command;
+
+use common::{response, temp_dir, TestServer};
+
+fn login_server() -> TestServer {
+	TestServer::start(|raw| {
+		if raw.starts_with("POST /login ") {
+			response(
+				204,
+				&[
+					("Set-Cookie", "sid=31d4d96e; Path=/; HttpOnly"),
+					("Set-Cookie", "theme=dark; Path=/other"),
+				],
+				"",
+			)
+		} else {
+			response(200, &[], "")
+		}
+	})
+}
+
+#[test]
+fn sends_cookies_set_earlier_in_a_run() {
+	let server = login_server();
+	let file = temp_dir("cookies-run").join("session.http");
+	std::fs::write(
+		&file,
+		format!(
+			"### login\nPOST {}\n\n### profile\nGET {}\n",
+			server.url("/login"),
+			server.url("/me")
+		),
+	)
+	.unwrap();
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("run")
+		.arg(&file)
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	let requests = server.requests();
+	assert!(!requests[0].contains("Cookie:"));
+	assert!(requests[1].contains("\r\nCookie: sid=31d4d96e\r\n"));
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["run", "--no-cookies"])
+		.arg(&file)
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	assert!(!server.requests()[3].contains("Cookie:"));
+}
+
+#[test]
+fn sends_cookies_set_by_a_redirect_to_the_next_hop() {
+	let server = TestServer::start(|raw| match raw.split(' ').nth(1).unwrap() {
+		"/login" => response(204, &[("Set-Cookie", "sid=old; Path=/")], ""),
+		"/renew" => response(
+			302,
+			&[("Location", "/home"), ("Set-Cookie", "sid=new; Path=/")],
+			"",
+		),
+		_ => response(200, &[], ""),
+	});
+	let file = temp_dir("cookies-redirect").join("redirect.http");
+	std::fs::write(
+		&file,
+		format!(
+			"### login\nPOST {}\n\n### renew\nGET {}\n",
+			server.url("/login"),
+			server.url("/renew")
+		),
+	)
+	.unwrap();
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["run", "-L"])
+		.arg(&file)
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+	let requests = server.requests();
+	assert!(requests[1].contains("\r\nCookie: sid=old\r\n"));
+	assert!(requests[2].starts_with("GET /home "));
+	assert!(
+		requests[2].contains("\r\nCookie: sid=new\r\n"),
+		"{}",
+		requests[2]
+	);
+}
+
+#[test]
+fn keeps_cookies_in_a_jar_file() {
+	let server = login_server();
+	let jar = temp_dir("cookies-jar").join("jar.txt");
+
+	let webcat = |args: &[&str]| {
+		Command::new(env!("CARGO_BIN_EXE_webcat"))
+			.args(args)
+			.arg("--cookies")
+			.arg(&jar)
+			.output()
+			.unwrap()
+	};
+	assert!(webcat(&["POST", &server.url("/login")]).status.success());
+	let text = std::fs::read_to_string(&jar).unwrap();
+	assert!(text.starts_with("# Netscape HTTP Cookie File\n"));
+	assert!(text.contains("#HttpOnly_127.0.0.1\tFALSE\t/\tFALSE\t0\tsid\t31d4d96e\n"));
+
+	assert!(webcat(&[&server.url("/other/page")]).status.success());
+	let requests = server.requests();
+	assert!(requests[1].contains("\r\nCookie: theme=dark; sid=31d4d96e\r\n"));
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("cookies")
+		.arg(&jar)
+		.args(["--url", &server.url("/")])
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	assert_eq!(
+		String::from_utf8(output.stdout).unwrap(),
+		"127.0.0.1  /  session  HttpOnly  sid=31d4d96e\n"
+	);
+}
@@ -0,0 +1,195 @@
+mod common;
+
+use std::process::Command;
+
+use common::{response, temp_dir, TestServer};
+
+fn echo_server() -> TestServer {
+	TestServer::start(|raw| {
+		let path = raw.split(' ').nth(1).unwrap_or_default().to_string();
+		response(200, &[], &format!("at {path}"))
+	})
+}
+
+#[test]
+fn imports_curl_commands() {
+	let server = echo_server();
+	let dir = temp_dir("curl-import");
+	let commands = dir.join("docs.sh");
+	std::fs::write(
+		&commands,
+		format!(
+			"# from the API docs\ncurl '{}' \\\n  -H 'Accept: application/json'\n\ncurl -X POST {} --data-raw 'a=1'\n",
+			server.url("/items/[1-2]"),
+			server.url("/items"),
+		),
+	)
+	.unwrap();
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("import")
+		.arg(&commands)
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+	let session = dir.join("docs").join("session.http");
+	assert!(String::from_utf8(output.stdout)
+		.unwrap()
+		.starts_with("Imported 3 requests"));
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("run")
+		.arg(&session)
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+	let requests = server.requests();
+	assert_eq!(requests.len(), 3);
+	assert!(requests[0].starts_with("GET /items/1 "));
+	assert!(requests[1].starts_with("GET /items/2 "));
+	assert!(requests[1].contains("Accept: application/json\r\n"));
+	assert!(requests[2].starts_with("POST /items "));
+	assert!(requests[2].contains("Content-Type: application/x-www-form-urlencoded\r\n"));
+	assert!(requests[2].ends_with("\r\n\r\na=1"));
+}
+
+#[test]
+fn warns_about_flags_dropped_on_import() {
+	let dir = temp_dir("curl-dropped");
+	let commands = dir.join("flags.sh");
+	std::fs::write(
+		&commands,
+		"curl -k --compressed -L https://example.com/\ncurl https://example.com/plain\n",
+	)
+	.unwrap();
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("import")
+		.arg(&commands)
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	for flag in ["--insecure", "--compressed", "--location"] {
+		assert_eq!(
+			stderr
+				.matches(&format!("`{flag}` has no request file equivalent"))
+				.count(),
+			1,
+			"{stderr}"
+		);
+	}
+	let session = std::fs::read_to_string(dir.join("flags").join("session.http")).unwrap();
+	assert!(session.contains("GET https://example.com/\n"), "{session}");
+}
+
+#[test]
+fn sends_curl_arguments() {
+	let server = echo_server();
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["curl", "-sS", "-H", "X-Trace: 1", "-d", "x=1"])
+		.arg(server.url("/submit"))
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("at /submit"), "{stdout}");
+	let requests = server.requests();
+	assert!(requests[0].starts_with("POST /submit "));
+	assert!(requests[0].contains("X-Trace: 1\r\n"));
+}
+
+#[test]
+fn exports_request_file() {
+	let dir = temp_dir("curl-export");
+	let file = dir.join("api.http");
+	std::fs::write(
+		&file,
+		"@base = http://localhost:1\n\n### list\nGET {{base}}/items\n\n### create\nPOST {{base}}/items\nContent-Type: application/json\n\n{\"a\": 1}\n",
+	)
+	.unwrap();
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args([
+			"export",
+			"--name",
+			"create",
+			"--var",
+			"base=https://api.test",
+		])
+		.arg(&file)
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+	assert_eq!(
+		String::from_utf8(output.stdout).unwrap(),
+		"curl \\\n  https://api.test/items \\\n  -H 'Content-Type: application/json' \\\n  --data-raw '{\"a\": 1}'\n"
+	);
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["export", "--to", "powershell"])
+		.arg(&file)
+		.output()
+		.unwrap();
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout
+		.starts_with("# list\nInvoke-WebRequest -Uri 'http://localhost:1/items'\n\n# create\n"));
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args([
+			"--export",
+			"wget",
+			"-H",
+			"Accept: */*",
+			"http://localhost:1/x",
+		])
+		.output()
+		.unwrap();
+	assert_eq!(
+		String::from_utf8(output.stdout).unwrap(),
+		"wget -O - \\\n  '--header=Accept: */*' \\\n  http://localhost:1/x\n"
+	);
+}
+
+#[test]
+fn exports_oauth2_as_bearer_token() {
+	let idp = TestServer::start(|_| {
+		response(
+			200,
+			&[("Content-Type", "application/json")],
+			r#"{"access_token": "t0k3n", "expires_in": 3600}"#,
+		)
+	});
+	let auth = format!(
+		"Authorization: OAuth2 token_url={} client_id=app client_secret=S3CR3T",
+		idp.url("/token")
+	);
+	for (tool, header) in [
+		("curl", "-H 'Authorization: Bearer t0k3n'"),
+		("wget", "'--header=Authorization: Bearer t0k3n'"),
+		("powershell", "'Authorization' = 'Bearer t0k3n'"),
+	] {
+		let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+			.args(["--export", tool, "-H", &auth, "http://localhost:1/x"])
+			.output()
+			.unwrap();
+		assert!(output.status.success(), "{output:?}");
+		let stdout = String::from_utf8(output.stdout).unwrap();
+		assert!(stdout.contains(header), "{stdout}");
+		assert!(!stdout.contains("S3CR3T"), "{stdout}");
+	}
+
+	// a scheme the tool cannot express fails instead of leaking
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args([
+			"--export",
+			"wget",
+			"-H",
+			"Authorization: AWS access_key=AKID secret_key=SECRET",
+			"http://localhost:1/x",
+		])
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(2));
+	assert!(output.stdout.is_empty());
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use std::io::Write;
+use std::process::Command;
+
+use common::TestServer;
+use webcat::{Client, Request, Url};
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+	let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+	encoder.write_all(data).unwrap();
+	encoder.finish().unwrap()
+}
+
+/// Chunked response of a gzip body, split in two chunks, with a trailer.
+fn chunked_gzip(text: &str) -> Vec<u8> {
+	let body = gzip(text.as_bytes());
+	let (first, second) = body.split_at(body.len() / 2);
+	let mut raw =
+		b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+	for chunk in [first, second] {
+		raw.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+		raw.extend_from_slice(chunk);
+		raw.extend_from_slice(b"\r\n");
+	}
+	raw.extend_from_slice(b"0\r\nX-Checksum: 42\r\n\r\n");
+	raw
+}
+
+#[test]
+fn decodes_compressed_chunked_responses() {
+	let raw = chunked_gzip("hello, compressed world");
+	let server = TestServer::start(move |_| raw.clone());
+	let request = Request::new("GET", Url::parse(&server.url("/")).unwrap());
+
+	let response = Client::new().send(&request).unwrap();
+	assert_eq!(response.body_text(), "hello, compressed world");
+	assert_eq!(response.headers.get("Content-Encoding"), Some("gzip"));
+	assert_eq!(response.trailers.get("X-Checksum"), Some("42"));
+	assert!(server.requests()[0].contains("Accept-Encoding: gzip, deflate, br, zstd\r\n"));
+
+	let client = Client {
+		decode: false,
+		..Client::new()
+	};
+	let response = client.send(&request).unwrap();
+	assert_eq!(response.body, gzip(b"hello, compressed world"));
+	assert!(!server.requests()[1].contains("Accept-Encoding"));
+
+	// curl only asks for compression with `--compressed`
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["curl", &server.url("/"), "--compressed"])
+		.output()
+		.unwrap();
+	assert!(output.stdout.ends_with(b"\n\nhello, compressed world\n"));
+	assert!(server.requests()[2].contains("Accept-Encoding: gzip"));
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["curl", &server.url("/")])
+		.output()
+		.unwrap();
+	let body = gzip(b"hello, compressed world");
+	assert!(output
+		.stdout
+		.windows(body.len())
+		.any(|window| window == body));
+	assert!(!server.requests()[3].contains("Accept-Encoding"));
+}
+
+#[test]
+fn prints_responses_as_received_on_the_wire() {
+	let raw = chunked_gzip("on the wire");
+	let expected = raw.clone();
+	let server = TestServer::start(move |request| match request.split(' ').nth(1) {
+		Some("/short") => b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nabc".to_vec(),
+		_ => raw.clone(),
+	});
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["--wire", &server.url("/")])
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	assert_eq!(output.stdout, expected);
+
+	// a wrong Content-Length still shows what was received
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["--wire", &server.url("/short")])
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(1));
+	assert_eq!(
+		output.stdout,
+		b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nabc"
+	);
+	assert!(!output.stderr.is_empty());
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["--wire", "--http2", &server.url("/")])
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,221 @@
+mod common;
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use common::{response, temp_dir, TestServer};
+
+/// Introspection result of a schema whose items have the given fields
+/// besides `id`.
+fn introspection(fields: &[&str]) -> String {
+	let named = |kind: &str, name: &str| format!(r#"{{"kind": "{kind}", "name": "{name}"}}"#);
+	let non_null = |ty: String| format!(r#"{{"kind": "NON_NULL", "ofType": {ty}}}"#);
+	let field = |name: &str, args: &str, ty: String| {
+		format!(r#"{{"name": "{name}", "args": [{args}], "type": {ty}}}"#)
+	};
+	let id_arg = format!(
+		r#"{{"name": "id", "type": {}, "defaultValue": null}}"#,
+		non_null(named("SCALAR", "ID"))
+	);
+	let mut item_fields = vec![field("id", "", non_null(named("SCALAR", "ID")))];
+	for name in fields {
+		item_fields.push(field(name, "", named("SCALAR", "String")));
+	}
+	format!(
+		r#"{{"data": {{"__schema": {{
+			"queryType": {{"name": "Query"}},
+			"mutationType": null,
+			"subscriptionType": null,
+			"types": [
+				{{"kind": "OBJECT", "name": "Query", "fields": [{}]}},
+				{{"kind": "OBJECT", "name": "Item", "fields": [{}]}},
+				{{"kind": "SCALAR", "name": "ID"}},
+				{{"kind": "SCALAR", "name": "String"}}
+			],
+			"directives": []
+		}}}}}}"#,
+		field("item", &id_arg, named("OBJECT", "Item")),
+		item_fields.join(", ")
+	)
+}
+
+/// GraphQL endpoint at `/graphql` whose items have a name and a price,
+/// answering queries with the given body.
+fn server(answer: &'static str) -> TestServer {
+	TestServer::start(move |raw| {
+		let json = [("Content-Type", "application/json")];
+		if raw.contains("IntrospectionQuery") {
+			response(200, &json, &introspection(&["name", "price"]))
+		} else {
+			response(200, &json, answer)
+		}
+	})
+}
+
+fn webcat(cache: &Path, args: &[&str]) -> Output {
+	Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.env("XDG_CACHE_HOME", cache)
+		.args(args)
+		.output()
+		.unwrap()
+}
+
+#[test]
+fn reports_graphql_errors_of_successful_responses() {
+	let server = server(
+		r#"{"data": {"item": null}, "errors": [{"message": "Item 7 not found", "path": ["item"]}]}"#,
+	);
+	let dir = temp_dir("graphql-errors");
+	let file = dir.join("items.http");
+	std::fs::write(
+		&file,
+		format!(
+			"### item\n# @operation Item\nGRAPHQL {}\nAuthorization: Bearer t\n\n\
+			 query Item($id: ID!) {{\n  item(id: $id) {{ name }}\n}}\n\n{{\"id\": \"7\"}}\n\n\
+			 ?? status == 200\n",
+			server.url("/graphql")
+		),
+	)
+	.unwrap();
+
+	let output = webcat(&dir, &["run", file.to_str().unwrap()]);
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert_eq!(output.status.code(), Some(1), "{stdout}");
+	assert!(
+		stdout.contains("FAIL  no GraphQL errors (line 3)"),
+		"{stdout}"
+	);
+	assert!(stdout.contains("Item 7 not found at item"), "{stdout}");
+	assert!(stdout.contains("PASS  status == 200"), "{stdout}");
+
+	// the schema is introspected with the headers of the request
+	let requests = server.requests();
+	assert_eq!(requests.len(), 2);
+	assert!(requests[0].contains("IntrospectionQuery"));
+	assert!(requests[0].contains("Authorization: Bearer t"));
+	let body = requests[1].split("\r\n\r\n").nth(1).unwrap();
+	let body: serde_json::Value = serde_json::from_str(body).unwrap();
+	assert_eq!(body["variables"]["id"], "7");
+	assert_eq!(body["operationName"], "Item");
+	assert!(requests[1].starts_with("POST /graphql HTTP/1.1"));
+
+	let cached = dir.join(format!(
+		"webcat/graphql/127.0.0.1_{}_graphql.json",
+		server.port
+	));
+	assert!(cached.is_file());
+}
+
+#[test]
+fn does_not_send_invalid_queries() {
+	let server = server(r#"{"data": {}}"#);
+	let dir = temp_dir("graphql-invalid");
+	let file = dir.join("items.http");
+	std::fs::write(
+		&file,
+		format!(
+			"GRAPHQL {}\n\n{{\n  item(id: 1) {{ nme }}\n}}\n",
+			server.url("/graphql")
+		),
+	)
+	.unwrap();
+
+	let output = webcat(&dir, &["run", file.to_str().unwrap()]);
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert!(!output.status.success());
+	let expected = format!(
+		"{}:4:17: unknown field `nme` on type `Item`",
+		file.display()
+	);
+	assert!(stdout.contains(&expected), "{stdout}");
+	assert_eq!(server.requests().len(), 1);
+
+	// `--no-validate` sends it anyway
+	let output = webcat(&dir, &["run", "--no-validate", file.to_str().unwrap()]);
+	assert!(output.status.success());
+	assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn refreshes_a_stale_cached_schema() {
+	let server = server(r#"{"data": {"item": {"price": "3"}}}"#);
+	let dir = temp_dir("graphql-stale");
+	let cached = dir.join(format!(
+		"webcat/graphql/127.0.0.1_{}_graphql.json",
+		server.port
+	));
+	std::fs::create_dir_all(cached.parent().unwrap()).unwrap();
+	std::fs::write(&cached, introspection(&["name"])).unwrap();
+
+	let url = server.url("/graphql");
+	let output = webcat(
+		&dir,
+		&["send", &url, "--graphql", "{ item(id: 1) { name } }"],
+	);
+	assert!(output.status.success());
+	assert_eq!(server.requests().len(), 1);
+
+	let output = webcat(
+		&dir,
+		&["send", &url, "--graphql", "{ item(id: 1) { price } }"],
+	);
+	assert!(
+		output.status.success(),
+		"{}",
+		String::from_utf8_lossy(&output.stderr)
+	);
+	let requests = server.requests();
+	assert_eq!(requests.len(), 3);
+	assert!(requests[1].contains("IntrospectionQuery"));
+	assert!(std::fs::read_to_string(&cached).unwrap().contains("price"));
+}
+
+#[test]
+fn sends_queries_from_the_command_line() {
+	let server = server(r#"{"data": {"item": {"name": "lamp"}}}"#);
+	let dir = temp_dir("graphql-send");
+	let query = dir.join("item.graphql");
+	std::fs::write(
+		&query,
+		"query A { item(id: 1) { id } }\nquery B($id: ID!) { item(id: $id) { name } }\n",
+	)
+	.unwrap();
+	let url = server.url("/graphql");
+	let path = format!("@{}", query.display());
+
+	let output = webcat(&dir, &["send", &url, "--graphql", &path]);
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert_eq!(output.status.code(), Some(1));
+	assert!(
+		stderr.contains("the query has several operations, name the one to run"),
+		"{stderr}"
+	);
+
+	let output = webcat(
+		&dir,
+		&[
+			"send",
+			&url,
+			"--graphql",
+			&path,
+			"--operation",
+			"B",
+			"--variables",
+			r#"{"id": 2}"#,
+		],
+	);
+	assert!(
+		output.status.success(),
+		"{}",
+		String::from_utf8_lossy(&output.stderr)
+	);
+	assert!(String::from_utf8_lossy(&output.stdout).contains("lamp"));
+	let requests = server.requests();
+	let body = requests.last().unwrap().split("\r\n\r\n").nth(1).unwrap();
+	let body: serde_json::Value = serde_json::from_str(body).unwrap();
+	assert_eq!(body["operationName"], "B");
+	assert_eq!(body["variables"], serde_json::json!({"id": 2}));
+
+	let output = webcat(&dir, &["send", &url, "--variables", "{}"]);
+	assert_eq!(output.status.code(), Some(2));
+}
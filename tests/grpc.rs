@@ -0,0 +1,482 @@
+mod common;
+
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde_json::{json, Value};
+use webcat::grpc::schema::Schema;
+use webcat::grpc::{self, codec, proto, Deframer};
+use webcat::h2::frame::{self, Frame};
+use webcat::h2::hpack;
+use webcat::util::base64_encode;
+
+use common::TestServer;
+
+const PROTO: &str = r#"
+syntax = "proto3";
+package shop.v1;
+
+message Query {
+  string name = 1;
+  int32 limit = 2;
+}
+
+message Item {
+  string name = 1;
+  int64 price_cents = 2;
+  repeated string tags = 3;
+}
+
+service Catalog {
+  rpc Get (Query) returns (Item);
+  rpc Watch (Query) returns (stream Item);
+}
+"#;
+
+type Fields = Vec<(String, String)>;
+
+/// Messages and trailers answering a call to a path.
+type Handler = dyn Fn(&str, Vec<Value>) -> (Vec<Value>, Fields) + Send + Sync;
+
+/// Prior knowledge HTTP/2 server answering one call per connection, with
+/// messages encoded by the types of `PROTO` and of server reflection.
+/// Records the request headers.
+struct GrpcServer {
+	port: u16,
+	requests: Arc<Mutex<Vec<Fields>>>,
+}
+
+impl GrpcServer {
+	fn start(
+		handler: impl Fn(&str, Vec<Value>) -> (Vec<Value>, Fields) + Send + Sync + 'static,
+	) -> GrpcServer {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let requests = Arc::new(Mutex::new(Vec::new()));
+		let log = requests.clone();
+		let handler: Arc<Handler> = Arc::new(handler);
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let Ok(stream) = stream else { break };
+				serve(stream, &*handler, &log);
+			}
+		});
+		GrpcServer { port, requests }
+	}
+
+	fn url(&self) -> String {
+		format!("http://127.0.0.1:{}", self.port)
+	}
+
+	fn requests(&self) -> Vec<Fields> {
+		self.requests.lock().unwrap().clone()
+	}
+}
+
+/// Answers the call of a connection, recording its headers before the
+/// response so that they are known once the client got it.
+fn serve(mut stream: TcpStream, handler: &Handler, log: &Mutex<Vec<Fields>>) {
+	let mut preface = [0u8; 24];
+	stream.read_exact(&mut preface).unwrap();
+	Frame::settings(&[]).write(&mut stream).unwrap();
+
+	let mut decoder = hpack::Decoder::default();
+	let mut headers = Vec::new();
+	let mut body = Vec::new();
+	loop {
+		let frame = Frame::read(&mut stream, frame::DEFAULT_MAX_FRAME_SIZE)
+			.unwrap()
+			.unwrap();
+		match frame.kind {
+			frame::SETTINGS if !frame.has(frame::ACK) => {
+				Frame::new(frame::SETTINGS, frame::ACK, 0, Vec::new())
+					.write(&mut stream)
+					.unwrap();
+			}
+			frame::HEADERS => headers = decoder.decode(frame.content().unwrap()).unwrap(),
+			frame::DATA => body.extend_from_slice(frame.content().unwrap()),
+			_ => {}
+		}
+		if matches!(frame.kind, frame::HEADERS | frame::DATA) && frame.has(frame::END_STREAM) {
+			break;
+		}
+	}
+
+	let path = header(&headers, ":path");
+	log.lock().unwrap().push(headers);
+	let schema = schema();
+	let (input, output) = types(&path);
+	let messages = Deframer::default()
+		.feed(&body)
+		.into_iter()
+		.map(|(_, message)| codec::decode(&schema, input, &message).unwrap())
+		.collect();
+	let (replies, trailers) = handler(&path, messages);
+
+	let head = hpack::encode(&fields(&[
+		(":status", "200"),
+		("content-type", "application/grpc"),
+	]));
+	Frame::new(frame::HEADERS, frame::END_HEADERS, 1, head)
+		.write(&mut stream)
+		.unwrap();
+	for reply in replies {
+		let message = codec::encode(&schema, output, &reply).unwrap();
+		Frame::new(frame::DATA, 0, 1, grpc::frame(&message))
+			.write(&mut stream)
+			.unwrap();
+	}
+	let trailers = hpack::encode(&trailers);
+	Frame::new(
+		frame::HEADERS,
+		frame::END_HEADERS | frame::END_STREAM,
+		1,
+		trailers,
+	)
+	.write(&mut stream)
+	.unwrap();
+	// wait for the client to close the connection
+	let _ = stream.read_to_end(&mut Vec::new());
+}
+
+/// Types of `PROTO`, of server reflection and of file descriptors.
+fn schema() -> Schema {
+	let mut schema = Schema::new();
+	proto::parse(PROTO, &mut schema).unwrap();
+	for name in [
+		"grpc/reflection/v1/reflection.proto",
+		"google/protobuf/descriptor.proto",
+	] {
+		proto::parse(proto::bundled(name).unwrap(), &mut schema).unwrap();
+	}
+	schema.resolve().unwrap();
+	schema
+}
+
+/// Request and response messages of a path.
+fn types(path: &str) -> (&'static str, &'static str) {
+	if path.ends_with("/ServerReflectionInfo") {
+		(
+			"grpc.reflection.v1.ServerReflectionRequest",
+			"grpc.reflection.v1.ServerReflectionResponse",
+		)
+	} else {
+		("shop.v1.Query", "shop.v1.Item")
+	}
+}
+
+/// `Catalog` of `PROTO` as a file descriptor.
+fn descriptor() -> Value {
+	let field = |name: &str, number: u32, kind: &str, repeated: bool| {
+		json!({
+			"name": name,
+			"number": number,
+			"label": if repeated { "LABEL_REPEATED" } else { "LABEL_OPTIONAL" },
+			"type": kind,
+		})
+	};
+	json!({
+		"name": "shop/v1/shop.proto",
+		"package": "shop.v1",
+		"syntax": "proto3",
+		"messageType": [
+			{
+				"name": "Query",
+				"field": [
+					field("name", 1, "TYPE_STRING", false),
+					field("limit", 2, "TYPE_INT32", false),
+				],
+			},
+			{
+				"name": "Item",
+				"field": [
+					field("name", 1, "TYPE_STRING", false),
+					field("price_cents", 2, "TYPE_INT64", false),
+					field("tags", 3, "TYPE_STRING", true),
+				],
+			},
+		],
+		"service": [{
+			"name": "Catalog",
+			"method": [
+				{ "name": "Get", "inputType": ".shop.v1.Query", "outputType": ".shop.v1.Item" },
+				{
+					"name": "Watch",
+					"inputType": ".shop.v1.Query",
+					"outputType": ".shop.v1.Item",
+					"serverStreaming": true,
+				},
+			],
+		}],
+	})
+}
+
+/// Catalog with server reflection in its v1alpha version only.
+fn catalog(path: &str, messages: Vec<Value>) -> (Vec<Value>, Fields) {
+	let ok = fields(&[("grpc-status", "0")]);
+	match path {
+		"/grpc.reflection.v1.ServerReflection/ServerReflectionInfo" => (
+			Vec::new(),
+			fields(&[("grpc-status", "12"), ("grpc-message", "unknown%20service")]),
+		),
+		"/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo" => {
+			let schema = schema();
+			let file = codec::encode(
+				&schema,
+				"google.protobuf.FileDescriptorProto",
+				&descriptor(),
+			)
+			.unwrap();
+			let replies = messages
+				.into_iter()
+				.map(|request| match request.get("listServices") {
+					Some(_) => {
+						json!({ "listServicesResponse": { "service": [{ "name": "shop.v1.Catalog" }] } })
+					}
+					None => {
+						json!({ "fileDescriptorResponse": { "fileDescriptorProto": [base64_encode(&file)] } })
+					}
+				})
+				.collect();
+			(replies, ok)
+		}
+		"/shop.v1.Catalog/Get" => {
+			let name = messages[0]["name"].as_str().unwrap_or_default().to_string();
+			if name == "lamp" {
+				let item = json!({ "name": name, "priceCents": "1999", "tags": ["desk"] });
+				(vec![item], ok)
+			} else {
+				let message = format!("no item `{name}`").replace(' ', "%20");
+				(
+					Vec::new(),
+					fields(&[("grpc-status", "5"), ("grpc-message", &message)]),
+				)
+			}
+		}
+		"/shop.v1.Catalog/Watch" => {
+			let limit = messages[0]["limit"].as_u64().unwrap_or(1);
+			let items = (1..=limit)
+				.map(|n| json!({ "name": format!("item {n}"), "priceCents": n.to_string() }))
+				.collect();
+			(items, ok)
+		}
+		_ => (Vec::new(), fields(&[("grpc-status", "12")])),
+	}
+}
+
+fn header(fields: &Fields, name: &str) -> String {
+	fields
+		.iter()
+		.find(|(field, _)| field == name)
+		.map(|(_, value)| value.clone())
+		.unwrap_or_default()
+}
+
+fn fields(list: &[(&str, &str)]) -> Fields {
+	list.iter()
+		.map(|(n, v)| (n.to_string(), v.to_string()))
+		.collect()
+}
+
+fn write_proto() -> std::path::PathBuf {
+	let dir = std::env::temp_dir().join(format!("webcat-grpc-test-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("shop.proto");
+	std::fs::write(&path, PROTO).unwrap();
+	path
+}
+
+fn webcat(args: &[&str]) -> Output {
+	Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("grpc")
+		.arg("--raw")
+		.args(args)
+		.output()
+		.unwrap()
+}
+
+#[test]
+fn calls_unary_and_streaming_methods_with_proto_files() {
+	let server = GrpcServer::start(catalog);
+	let proto = write_proto();
+	let proto = proto.to_str().unwrap();
+
+	let output = webcat(&[
+		"--proto",
+		proto,
+		&server.url(),
+		"Catalog/Get",
+		r#"{"name": "lamp"}"#,
+	]);
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert!(output.status.success(), "{stdout}");
+	assert!(
+		stdout.starts_with("HTTP/2 200\ncontent-type: application/grpc\n"),
+		"{stdout}"
+	);
+	assert!(
+		stdout.contains("\n{\"name\":\"lamp\",\"priceCents\":\"1999\",\"tags\":[\"desk\"]}\n"),
+		"{stdout}"
+	);
+	assert!(stdout.contains("\ngrpc-status: 0\n"), "{stdout}");
+	assert!(stdout.trim_end().ends_with("gRPC status: 0 OK"), "{stdout}");
+
+	let output = webcat(&[
+		"--proto",
+		proto,
+		&server.url(),
+		"shop.v1.Catalog.Watch",
+		"-d",
+		r#"{"limit": 3}"#,
+	]);
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert!(output.status.success(), "{stdout}");
+	let items: Vec<&str> = stdout
+		.lines()
+		.filter(|line| line.starts_with('{'))
+		.collect();
+	assert_eq!(
+		items,
+		[
+			r#"{"name":"item 1","priceCents":"1"}"#,
+			r#"{"name":"item 2","priceCents":"2"}"#,
+			r#"{"name":"item 3","priceCents":"3"}"#,
+		]
+	);
+
+	let requests = server.requests();
+	assert_eq!(header(&requests[0], ":method"), "POST");
+	assert_eq!(header(&requests[0], ":path"), "/shop.v1.Catalog/Get");
+	assert_eq!(
+		header(&requests[0], "content-type"),
+		"application/grpc+proto"
+	);
+	assert_eq!(header(&requests[0], "te"), "trailers");
+	assert_eq!(header(&requests[1], ":path"), "/shop.v1.Catalog/Watch");
+
+	// invalid messages are refused before calling
+	let output = webcat(&[
+		"--proto",
+		proto,
+		&server.url(),
+		"Catalog/Get",
+		r#"{"size": 1}"#,
+	]);
+	assert_eq!(output.status.code(), Some(2));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(
+		stderr.contains("`size`: no such field in `shop.v1.Query`"),
+		"{stderr}"
+	);
+	assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn fails_on_error_status() {
+	let server = GrpcServer::start(catalog);
+	let proto = write_proto();
+
+	let output = webcat(&[
+		"--proto",
+		proto.to_str().unwrap(),
+		&server.url(),
+		"Catalog/Get",
+		r#"{"name": "desk"}"#,
+	]);
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert_eq!(output.status.code(), Some(1), "{stdout}");
+	assert!(stdout.contains("\ngrpc-status: 5\n"), "{stdout}");
+	assert!(
+		stdout
+			.trim_end()
+			.ends_with("gRPC status: 5 NOT_FOUND: no item `desk`"),
+		"{stdout}"
+	);
+}
+
+#[test]
+fn lists_and_calls_services_with_reflection() {
+	let server = GrpcServer::start(catalog);
+
+	let output = webcat(&[&server.url()]);
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert!(output.status.success(), "{stdout}");
+	assert_eq!(
+		stdout,
+		"shop.v1.Catalog\n    rpc Get (shop.v1.Query) returns (shop.v1.Item)\n    rpc Watch (shop.v1.Query) returns (stream shop.v1.Item)\n"
+	);
+
+	let output = webcat(&[&server.url(), "Catalog/Get", r#"{"name": "lamp"}"#]);
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert!(output.status.success(), "{stdout}");
+	assert!(
+		stdout.contains("\n{\"name\":\"lamp\",\"priceCents\":\"1999\",\"tags\":[\"desk\"]}\n"),
+		"{stdout}"
+	);
+
+	let output = webcat(&[&server.url(), "Basket/Get"]);
+	assert_eq!(output.status.code(), Some(2));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("unknown service `Basket`"), "{stderr}");
+}
+
+#[test]
+fn calls_grpc_web_over_http1() {
+	let schema = schema();
+	let item = json!({ "name": "lamp", "priceCents": "1999" });
+	let mut body = grpc::frame(&codec::encode(&schema, "shop.v1.Item", &item).unwrap());
+	let trailers = b"grpc-status: 0\r\ngrpc-message: fine\r\n";
+	body.push(0x80);
+	body.extend_from_slice(&(trailers.len() as u32).to_be_bytes());
+	body.extend_from_slice(trailers);
+	let mut response = format!(
+		"HTTP/1.1 200 OK\r\nContent-Type: application/grpc-web+proto\r\nContent-Length: {}\r\n\r\n",
+		body.len()
+	)
+	.into_bytes();
+	response.extend_from_slice(&body);
+	let server = TestServer::start(move |_| response.clone());
+	let proto = write_proto();
+
+	let output = webcat(&[
+		"--web",
+		"--proto",
+		proto.to_str().unwrap(),
+		&server.url("/api"),
+		"Catalog/Get",
+		r#"{"name": "lamp"}"#,
+	]);
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert!(output.status.success(), "{stdout}");
+	assert!(stdout.starts_with("HTTP/1.1 200 OK\n"), "{stdout}");
+	assert!(
+		stdout.contains("\n{\"name\":\"lamp\",\"priceCents\":\"1999\"}\n"),
+		"{stdout}"
+	);
+	assert!(
+		stdout.contains("\ngrpc-status: 0\ngrpc-message: fine\n"),
+		"{stdout}"
+	);
+	assert!(
+		stdout.trim_end().ends_with("gRPC status: 0 OK: fine"),
+		"{stdout}"
+	);
+
+	let request = &server.requests()[0];
+	assert!(
+		request.starts_with("POST /api/shop.v1.Catalog/Get HTTP/1.1\r\n"),
+		"{request}"
+	);
+	assert!(
+		request.contains("Content-Type: application/grpc-web+proto\r\n"),
+		"{request}"
+	);
+	assert!(request.contains("X-Grpc-Web: 1\r\n"), "{request}");
+	assert!(
+		request.ends_with("\r\n\r\n\0\0\0\0\x06\n\x04lamp"),
+		"{request:?}"
+	);
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use std::process::Command;
+
+use common::{response, temp_dir, TestServer};
+
+#[test]
+fn imports_archive_and_replays_it() {
+	let server = TestServer::start(|raw| {
+		let status = if raw.starts_with("POST") { 201 } else { 200 };
+		response(status, &[], "ok")
+	});
+	let dir = temp_dir("har-import");
+	let archive = dir.join("capture.har");
+	let har = serde_json::json!({"log": {"version": "1.2", "entries": [
+		{
+			"startedDateTime": "2024-03-01T10:00:00.000Z",
+			"request": {"method": "GET", "url": server.url("/items"), "headers": [
+				{"name": "Accept", "value": "application/json"}
+			]},
+			"response": {"status": 200, "statusText": "OK", "headers": [], "content": {"text": "[]"}}
+		},
+		{
+			"startedDateTime": "2024-03-01T10:00:01.000Z",
+			"request": {"method": "POST", "url": server.url("/items"), "headers": [],
+				"postData": {"mimeType": "application/json", "text": "{\"id\": 1}"}},
+			"response": {"status": 201, "statusText": "Created", "headers": [], "content": {}}
+		}
+	]}});
+	std::fs::write(&archive, har.to_string()).unwrap();
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("import")
+		.arg(&archive)
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+	let session = dir.join("capture").join("session.http");
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert_eq!(
+		stdout,
+		format!("Imported 2 requests into {}\n", session.display())
+	);
+	let text = std::fs::read_to_string(&session).unwrap();
+	assert!(text.contains("recorded 2024-03-01T10:00:01.000Z"), "{text}");
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("run")
+		.arg(&session)
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+	let requests = server.requests();
+	assert_eq!(requests.len(), 2);
+	assert!(requests[0].contains("Accept: application/json\r\n"));
+	assert!(requests[1].contains("Content-Type: application/json\r\n"));
+	assert!(requests[1].ends_with("\r\n\r\n{\"id\": 1}"));
+}
+
+#[test]
+fn exports_run_as_har() {
+	let server = TestServer::fixed(
+		"HTTP/1.1 200 OK\r\nSet-Cookie: sid=abc; Path=/; HttpOnly\r\nContent-Length: 2\r\n\r\nhi",
+	);
+	let dir = temp_dir("har-export");
+	let file = dir.join("api.http");
+	std::fs::write(
+		&file,
+		format!(
+			"### hello\nGET {}\n\n### again\nGET {}\nCookie: theme=dark\n",
+			server.url("/hello?a=1"),
+			server.url("/hello"),
+		),
+	)
+	.unwrap();
+	let har = dir.join("run.har");
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("run")
+		.arg(&file)
+		.arg("--har")
+		.arg(&har)
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+
+	let value: serde_json::Value =
+		serde_json::from_str(&std::fs::read_to_string(&har).unwrap()).unwrap();
+	let entry = &value["log"]["entries"][0];
+	assert_eq!(entry["comment"], "hello");
+	assert_eq!(entry["request"]["queryString"][0]["name"], "a");
+	assert_eq!(entry["response"]["status"], 200);
+	assert_eq!(entry["response"]["content"]["text"], "hi");
+	assert!(entry["timings"]["wait"].as_f64().unwrap() >= 0.0);
+	assert_eq!(
+		entry["response"]["cookies"],
+		serde_json::json!([{"name": "sid", "value": "abc", "path": "/", "httpOnly": true}])
+	);
+
+	// headers are exported as sent, with those the client adds
+	let headers = |entry: &serde_json::Value| -> Vec<(String, String)> {
+		entry["request"]["headers"]
+			.as_array()
+			.unwrap()
+			.iter()
+			.map(|h| {
+				(
+					h["name"].as_str().unwrap().into(),
+					h["value"].as_str().unwrap().into(),
+				)
+			})
+			.collect()
+	};
+	let host = format!("127.0.0.1:{}", server.port);
+	let sent = headers(entry);
+	assert!(sent.contains(&("Host".into(), host)), "{sent:?}");
+	assert!(
+		sent.iter().any(|(name, _)| name == "Accept-Encoding"),
+		"{sent:?}"
+	);
+	let again = &value["log"]["entries"][1];
+	assert!(headers(again).contains(&("Cookie".into(), "theme=dark; sid=abc".into())));
+	assert_eq!(
+		again["request"]["cookies"],
+		serde_json::json!([{"name": "theme", "value": "dark"}, {"name": "sid", "value": "abc"}])
+	);
+
+	let entries = webcat::har::load(&har).unwrap();
+	assert_eq!(entries[0].request.url.to_string(), server.url("/hello?a=1"));
+}
@@ -0,0 +1,204 @@
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+
+use webcat::h2::frame::{self, Frame};
+use webcat::h2::hpack;
+use webcat::upload::Upload;
+use webcat::{Client, HttpVersion, Request, Url};
+
+type Fields = Vec<(String, String)>;
+
+/// Request as seen by the h2c test server.
+struct Received {
+	headers: Fields,
+	body: Vec<u8>,
+}
+
+/// Prior knowledge HTTP/2 server handling one request per connection. The
+/// response echoes the request body and ends with a trailer. It grants
+/// only the default flow control window until it has seen 32 KiB.
+fn start_server(response_body: Vec<u8>) -> (u16, mpsc::Receiver<Received>) {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	let (sender, receiver) = mpsc::channel();
+	thread::spawn(move || {
+		for stream in listener.incoming() {
+			let Ok(stream) = stream else { break };
+			let received = serve(stream, &response_body);
+			let _ = sender.send(received);
+		}
+	});
+	(port, receiver)
+}
+
+fn serve(mut stream: TcpStream, response_body: &[u8]) -> Received {
+	let mut preface = [0u8; 24];
+	stream.read_exact(&mut preface).unwrap();
+	assert_eq!(preface, frame::PREFACE);
+	Frame::settings(&[(frame::MAX_CONCURRENT_STREAMS, 100)])
+		.write(&mut stream)
+		.unwrap();
+
+	let mut decoder = hpack::Decoder::default();
+	let mut received = Received {
+		headers: Vec::new(),
+		body: Vec::new(),
+	};
+	loop {
+		let frame = Frame::read(&mut stream, frame::DEFAULT_MAX_FRAME_SIZE)
+			.unwrap()
+			.unwrap();
+		match frame.kind {
+			frame::SETTINGS if !frame.has(frame::ACK) => {
+				Frame::new(frame::SETTINGS, frame::ACK, 0, Vec::new())
+					.write(&mut stream)
+					.unwrap();
+			}
+			frame::HEADERS => {
+				assert!(frame.has(frame::END_HEADERS));
+				received.headers = decoder.decode(frame.content().unwrap()).unwrap();
+			}
+			frame::DATA => {
+				received.body.extend_from_slice(frame.content().unwrap());
+				if received.body.len() >= 32 * 1024 {
+					Frame::window_update(0, 1 << 20).write(&mut stream).unwrap();
+					Frame::window_update(1, 1 << 20).write(&mut stream).unwrap();
+				}
+			}
+			_ => {}
+		}
+		if matches!(frame.kind, frame::HEADERS | frame::DATA) && frame.has(frame::END_STREAM) {
+			break;
+		}
+	}
+
+	let head = hpack::encode(&fields(&[
+		(":status", "200"),
+		("content-type", "text/plain"),
+	]));
+	Frame::new(frame::HEADERS, frame::END_HEADERS, 1, head)
+		.write(&mut stream)
+		.unwrap();
+	for chunk in response_body.chunks(frame::DEFAULT_MAX_FRAME_SIZE) {
+		Frame::new(frame::DATA, 0, 1, chunk.to_vec())
+			.write(&mut stream)
+			.unwrap();
+	}
+	let trailers = hpack::encode(&fields(&[("grpc-status", "0")]));
+	Frame::new(
+		frame::HEADERS,
+		frame::END_HEADERS | frame::END_STREAM,
+		1,
+		trailers,
+	)
+	.write(&mut stream)
+	.unwrap();
+	// wait for the client to close the connection
+	let _ = stream.read_to_end(&mut Vec::new());
+	received
+}
+
+fn fields(list: &[(&str, &str)]) -> Fields {
+	list.iter()
+		.map(|(n, v)| (n.to_string(), v.to_string()))
+		.collect()
+}
+
+fn h2_client() -> Client {
+	Client {
+		version: HttpVersion::Http2,
+		..Client::new()
+	}
+}
+
+#[test]
+fn sends_request_with_prior_knowledge() {
+	let (port, received) = start_server(b"hello".to_vec());
+	let url = Url::parse(&format!("http://127.0.0.1:{port}/items?page=2")).unwrap();
+	let mut request = Request::new("GET", url);
+	request.headers.append("Accept", "text/plain");
+	request.headers.append("Connection", "keep-alive");
+
+	let response = h2_client().send(&request).unwrap();
+	assert_eq!(response.status_line(), "HTTP/2 200");
+	assert_eq!(response.headers.get("content-type"), Some("text/plain"));
+	assert_eq!(response.body_text(), "hello");
+	assert_eq!(response.trailers.get("grpc-status"), Some("0"));
+
+	let info = response.h2.unwrap();
+	assert_eq!(info.stream_id, 1);
+	assert_eq!(info.remote_settings, [(frame::MAX_CONCURRENT_STREAMS, 100)]);
+	assert_eq!(info.response_headers.fields, 2);
+	assert_eq!(info.response_headers.decoded, 7 + 3 + 12 + 10);
+	assert_eq!(info.trailers.unwrap().fields, 1);
+
+	let received = received.recv().unwrap();
+	assert_eq!(
+		received.headers,
+		fields(&[
+			(":method", "GET"),
+			(":scheme", "http"),
+			(":authority", &format!("127.0.0.1:{port}")),
+			(":path", "/items?page=2"),
+			("accept", "text/plain"),
+			("accept-encoding", "gzip, deflate, br, zstd"),
+		])
+	);
+}
+
+#[test]
+fn respects_flow_control_for_large_bodies() {
+	let body: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+	let (port, received) = start_server(body.clone());
+	let url = Url::parse(&format!("http://127.0.0.1:{port}/upload")).unwrap();
+	let mut request = Request::new("POST", url);
+	request.body = body.clone();
+
+	let response = h2_client().send(&request).unwrap();
+	assert_eq!(response.body, body);
+	let received = received.recv().unwrap();
+	assert_eq!(received.body, body);
+	assert!(received
+		.headers
+		.contains(&("content-length".into(), "200000".into())));
+}
+
+#[test]
+fn streams_uploads_from_files() {
+	let data: Vec<u8> = (0..150_000u32).map(|i| (i % 241) as u8).collect();
+	let path = std::env::temp_dir().join(format!("webcat-h2-upload-{}", std::process::id()));
+	std::fs::write(&path, &data).unwrap();
+	let (port, received) = start_server(b"ok".to_vec());
+	let url = Url::parse(&format!("http://127.0.0.1:{port}/upload")).unwrap();
+	let mut request = Request::new("PUT", url);
+	let mut upload = Upload::new();
+	upload.push_bytes(b"head:");
+	upload.push_file(&path).unwrap();
+	request.upload = Some(upload);
+
+	h2_client().send(&request).unwrap();
+	std::fs::remove_file(&path).unwrap();
+	let received = received.recv().unwrap();
+	assert_eq!(received.body, [&b"head:"[..], &data].concat());
+	assert!(received
+		.headers
+		.contains(&("content-length".into(), "150005".into())));
+}
+
+#[test]
+fn binary_prints_stream_details() {
+	let (port, _received) = start_server(b"ok".to_vec());
+	let output = std::process::Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["--http2", "--h2-info", &format!("http://127.0.0.1:{port}/")])
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.starts_with("* HTTP/2 stream: 1\n"), "{stdout}");
+	assert!(stdout.contains("* SETTINGS sent: ENABLE_PUSH=0, INITIAL_WINDOW_SIZE=1048576\n"));
+	assert!(stdout.contains("* SETTINGS received: MAX_CONCURRENT_STREAMS=100\n"));
+	assert!(stdout.contains("* Trailers: 1 fields"));
+	assert!(stdout.ends_with("HTTP/2 200\ncontent-type: text/plain\n\nok\n"));
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use common::{response, TestServer};
+use webcat::load::{self, LoadConfig};
+use webcat::{Client, Request, Url};
+
+fn request(url: &str) -> Request {
+	Request::new("GET", Url::parse(url).unwrap())
+}
+
+#[test]
+fn sends_fixed_number_of_requests() {
+	let server = TestServer::start(|raw| {
+		let status = if raw.starts_with("GET /fail") {
+			503
+		} else {
+			200
+		};
+		response(status, &[], "hello")
+	});
+	let requests = [request(&server.url("/ok")), request(&server.url("/fail"))];
+	let config = LoadConfig {
+		concurrency: 4,
+		duration: None,
+		requests: Some(20),
+		..Default::default()
+	};
+
+	let report = load::run(&Client::new(), &requests, &config);
+	assert_eq!(report.statuses.get(&200), Some(&10));
+	assert_eq!(report.statuses.get(&503), Some(&10));
+	assert_eq!(report.latency.len(), 20);
+	assert_eq!(report.bytes, 100);
+	assert!(report.latency.percentile(50.0) <= report.latency.percentile(99.9));
+	assert_eq!(server.requests().len(), 20);
+}
+
+#[test]
+fn paces_requests_in_rate_mode() {
+	let server = TestServer::fixed("HTTP/1.1 204 No Content\r\n\r\n");
+	let config = LoadConfig {
+		concurrency: 2,
+		rate: Some(100.0),
+		duration: Some(Duration::from_millis(300)),
+		requests: None,
+	};
+
+	let report = load::run(&Client::new(), &[request(&server.url("/"))], &config);
+	let sent = report.responses();
+	assert!((25..=31).contains(&sent), "{sent} requests");
+	assert!(report.elapsed >= Duration::from_millis(280));
+}
+
+#[test]
+fn counts_errors_by_kind() {
+	let port = TcpListener::bind("127.0.0.1:0")
+		.unwrap()
+		.local_addr()
+		.unwrap()
+		.port();
+	let config = LoadConfig {
+		concurrency: 1,
+		duration: None,
+		requests: Some(3),
+		..Default::default()
+	};
+	let url = format!("http://127.0.0.1:{port}/");
+	let report = load::run(&Client::new(), &[request(&url)], &config);
+	assert_eq!(report.errors.get("connection refused"), Some(&3));
+	assert!(report.latency.is_empty());
+}
+
+/// Server answering any number of requests per connection, closing it
+/// after every `close_after` of them. Returns its URL and the number of
+/// connections accepted.
+fn keep_alive_server(close_after: usize) -> (String, Arc<AtomicUsize>) {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let url = format!("http://{}/", listener.local_addr().unwrap());
+	let connections = Arc::new(AtomicUsize::new(0));
+	let accepted = connections.clone();
+	thread::spawn(move || {
+		for stream in listener.incoming() {
+			let Ok(mut stream) = stream else { break };
+			accepted.fetch_add(1, Ordering::SeqCst);
+			thread::spawn(move || {
+				let mut reader = BufReader::new(stream.try_clone().unwrap());
+				for served in 1.. {
+					let mut line = String::new();
+					loop {
+						line.clear();
+						if reader.read_line(&mut line).unwrap_or(0) == 0 {
+							return;
+						}
+						if line == "\r\n" {
+							break;
+						}
+					}
+					let close = served % close_after == 0;
+					let connection = if close { "close" } else { "keep-alive" };
+					let response = format!(
+						"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: {connection}\r\n\r\nok"
+					);
+					if stream.write_all(response.as_bytes()).is_err() || close {
+						return;
+					}
+				}
+			});
+		}
+	});
+	(url, connections)
+}
+
+#[test]
+fn keeps_a_connection_per_worker() {
+	let config = LoadConfig {
+		concurrency: 2,
+		duration: None,
+		requests: Some(40),
+		..Default::default()
+	};
+	let (url, connections) = keep_alive_server(usize::MAX);
+	let report = load::run(&Client::new(), &[request(&url)], &config);
+	assert_eq!(report.statuses.get(&200), Some(&40));
+	// a worker may be done before the other one started
+	assert!((1..=2).contains(&connections.load(Ordering::SeqCst)));
+
+	// closed connections are opened again
+	let config = LoadConfig {
+		concurrency: 1,
+		..config
+	};
+	let (url, connections) = keep_alive_server(10);
+	let report = load::run(&Client::new(), &[request(&url)], &config);
+	assert_eq!(report.statuses.get(&200), Some(&40));
+	assert_eq!(report.failures(), 0);
+	assert_eq!(connections.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn binary_reports_json() {
+	let server = TestServer::fixed("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args([
+			"load",
+			&server.url("/"),
+			"-c",
+			"2",
+			"--requests",
+			"6",
+			"--json",
+		])
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(report["requests"], 6);
+	assert_eq!(report["statuses"]["200"], 6);
+	assert!(report["latency"]["p99.9"].as_f64().unwrap() > 0.0);
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["load", &server.url("/"), "--requests", "2"])
+		.output()
+		.unwrap();
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(
+		stdout.starts_with("Sending GET http://127.0.0.1:"),
+		"{stdout}"
+	);
+	assert!(stdout.contains("\nLatency:\n  min "));
+	assert!(stdout.contains("  p99.9 "));
+	assert!(stdout.contains("\nStatus codes:\n  200: 2\n"));
+}
@@ -0,0 +1,198 @@
+mod common;
+
+use std::process::Command;
+
+use common::{response, temp_dir, TestServer};
+
+fn spec(server: &str) -> String {
+	format!(
+		r##"openapi: 3.0.3
+info:
+  title: Pets
+  version: "1"
+servers:
+  - url: {server}/api
+paths:
+  /pets/{{petId}}:
+    get:
+      operationId: getPet
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema: {{ type: integer, example: 7 }}
+      responses:
+        "200":
+          description: A pet
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+        "404":
+          description: Not found
+  /pets:
+    post:
+      operationId: createPet
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/Pet"
+      responses:
+        "201":
+          description: Created
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+components:
+  schemas:
+    Pet:
+      type: object
+      required: [id, name]
+      properties:
+        id: {{ type: integer }}
+        name: {{ type: string, example: Rex }}
+"##
+	)
+}
+
+#[test]
+fn generates_requests_and_checks_responses() {
+	let server = TestServer::start(|raw| {
+		if raw.starts_with("POST") {
+			// `id` has the wrong type
+			response(
+				201,
+				&[("Content-Type", "application/json")],
+				r#"{"id":"1","name":"Rex"}"#,
+			)
+		} else {
+			response(
+				200,
+				&[("Content-Type", "application/json")],
+				r#"{"id":7,"name":"Rex"}"#,
+			)
+		}
+	});
+	let dir = temp_dir("openapi-import");
+	let document = dir.join("pets.yaml");
+	std::fs::write(&document, spec(&server.url(""))).unwrap();
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("import")
+		.arg(&document)
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+	let requests = dir.join("pets");
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert_eq!(
+		stdout,
+		format!(
+			"Generated 2 request files for Pets in {}\n",
+			requests.display()
+		)
+	);
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("run")
+		.arg("--openapi")
+		.arg(&document)
+		.arg(requests.join("getPet.http"))
+		.arg(requests.join("createPet.http"))
+		.output()
+		.unwrap();
+	assert!(!output.status.success(), "{output:?}");
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("PASS  openapi status 200\n"), "{stdout}");
+	assert!(stdout.contains("PASS  openapi status 201\n"), "{stdout}");
+	assert!(
+		stdout.contains(
+			"FAIL  openapi schema\n      $.id: expected integer, got string \
+			 (#/components/schemas/Pet/properties/id/type)\n"
+		),
+		"{stdout}"
+	);
+	assert!(
+		stdout.contains("2 requests, 1 passed, 1 failed"),
+		"{stdout}"
+	);
+
+	let requests = server.requests();
+	assert!(
+		requests[0].starts_with("GET /api/pets/7 HTTP/1.1"),
+		"{requests:?}"
+	);
+	assert!(
+		requests[1].ends_with("\r\n\r\n{\n  \"id\": 0,\n  \"name\": \"Rex\"\n}"),
+		"{requests:?}"
+	);
+}
+
+#[test]
+fn generates_examples_of_recursive_schemas() {
+	let server = TestServer::start(|_| response(201, &[], ""));
+	let dir = temp_dir("openapi-recursive");
+	let document = dir.join("users.yaml");
+	std::fs::write(
+		&document,
+		format!(
+			r##"openapi: 3.0.3
+info:
+  title: Users
+  version: "1"
+servers:
+  - url: {}
+paths:
+  /users:
+    post:
+      operationId: createUser
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/User"
+      responses:
+        "201":
+          description: Created
+components:
+  schemas:
+    User:
+      type: object
+      properties:
+        name: {{ type: string }}
+        manager:
+          $ref: "#/components/schemas/User"
+        reports:
+          type: array
+          items:
+            $ref: "#/components/schemas/User"
+"##,
+			server.url("")
+		),
+	)
+	.unwrap();
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("import")
+		.arg(&document)
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+	let file = dir.join("users").join("createUser.http");
+	let text = std::fs::read_to_string(&file).unwrap();
+	assert!(!text.contains("null"), "{text}");
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("run")
+		.arg(&file)
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+	let requests = server.requests();
+	assert!(
+		requests[0].ends_with("\r\n\r\n{\n  \"name\": \"string\",\n  \"reports\": []\n}"),
+		"{requests:?}"
+	);
+}
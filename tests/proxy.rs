@@ -0,0 +1,192 @@
+mod common;
+
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use common::{response, temp_dir, TestServer};
+use rustls::{ServerConnection, StreamOwned};
+use webcat::proxy::{Ca, Proxy};
+use webcat::recording::Recorder;
+use webcat::tls::TlsConfig;
+use webcat::{h1, Client, Request, RequestFile, Url, Variables};
+
+/// Starts a proxy recording to `dir` and returns its port and log.
+fn start(dir: &Path, client: Client, ca: Option<Ca>) -> (u16, Arc<Mutex<Vec<String>>>) {
+	let proxy = Proxy::bind("127.0.0.1:0", client, Recorder::create(dir).unwrap(), ca).unwrap();
+	let port = proxy.local_addr().unwrap().port();
+	let log = Arc::new(Mutex::new(Vec::new()));
+	let lines = Arc::clone(&log);
+	thread::spawn(move || {
+		proxy
+			.run(move |entry| lines.lock().unwrap().push(entry.to_string()))
+			.unwrap()
+	});
+	(port, log)
+}
+
+/// Sends `CONNECT` and reads the response head.
+fn connect(proxy: u16, target: &str) -> (TcpStream, String) {
+	let mut socket = TcpStream::connect(("127.0.0.1", proxy)).unwrap();
+	write!(
+		socket,
+		"CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n\r\n"
+	)
+	.unwrap();
+	let mut head = Vec::new();
+	while !head.ends_with(b"\r\n\r\n") {
+		let mut byte = [0];
+		socket.read_exact(&mut byte).unwrap();
+		head.push(byte[0]);
+	}
+	(socket, String::from_utf8(head).unwrap())
+}
+
+#[test]
+fn records_plain_http_requests() {
+	let server = TestServer::start(|raw| {
+		let body = if raw.starts_with("POST") {
+			"created"
+		} else {
+			"[]"
+		};
+		response(200, &[("Content-Type", "text/plain")], body)
+	});
+	let dir = temp_dir("proxy-http");
+	let (port, log) = start(&dir, Client::new(), None);
+
+	let mut socket = TcpStream::connect(("127.0.0.1", port)).unwrap();
+	let target = server.url("/items?page=1");
+	write!(
+		socket,
+		"GET {target} HTTP/1.1\r\nHost: 127.0.0.1\r\nAccept: */*\r\nProxy-Connection: keep-alive\r\n\r\n"
+	)
+	.unwrap();
+	let mut reader = BufReader::new(socket);
+	let first = h1::read_response(&mut reader, "GET").unwrap();
+	assert_eq!(first.body_text(), "[]");
+	// the connection stays open for the next request
+	let mut post = Request::new("POST", Url::parse(&server.url("/items")).unwrap());
+	post.body = b"{\"name\": \"widget\"}".to_vec();
+	h1::write_request(reader.get_mut(), &post).unwrap();
+	let second = h1::read_response(&mut reader, "POST").unwrap();
+	assert_eq!(second.body_text(), "created");
+	assert_eq!(second.headers.get("Content-Type"), Some("text/plain"));
+
+	let upstream = server.requests();
+	assert!(!upstream[0].contains("Proxy-Connection"), "{}", upstream[0]);
+	assert_eq!(
+		log.lock().unwrap()[..],
+		[
+			format!("001 GET {target} -> 200"),
+			format!("002 POST {} -> 200", server.url("/items")),
+		]
+	);
+
+	// the recording replays against the server
+	let file = RequestFile::load(dir.join("session.http")).unwrap();
+	let replay = file
+		.build(file.find("002").unwrap(), &Variables::new())
+		.unwrap();
+	assert_eq!(replay.body, post.body);
+	let response = Client::new().send(&replay).unwrap();
+	assert_eq!(response.body_text(), "created");
+	let snapshot = std::fs::read_to_string(dir.join("responses/001.http")).unwrap();
+	assert!(snapshot.starts_with("HTTP/1.1 200 X\n"), "{snapshot}");
+	assert!(snapshot.ends_with("\n\n[]"), "{snapshot}");
+}
+
+#[test]
+fn refuses_oversized_bodies() {
+	let dir = temp_dir("proxy-oversized");
+	let (port, log) = start(&dir, Client::new(), None);
+	let mut socket = TcpStream::connect(("127.0.0.1", port)).unwrap();
+	write!(
+		socket,
+		"POST http://127.0.0.1:1/ HTTP/1.1\r\nContent-Length: 999999999999999\r\n\r\n{{}}"
+	)
+	.unwrap();
+	let response = h1::read_response(&mut BufReader::new(socket), "POST").unwrap();
+	assert_eq!(response.status, 413);
+	assert!(log.lock().unwrap().is_empty());
+
+	// the proxy keeps serving
+	let (_, head) = connect(port, "127.0.0.1:1");
+	assert!(head.starts_with("HTTP/1.1 "), "{head}");
+}
+
+#[test]
+fn tunnels_connect_requests() {
+	let server = TestServer::fixed("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+	let dir = temp_dir("proxy-tunnel");
+	let (port, log) = start(&dir, Client::new(), None);
+
+	let (mut socket, head) = connect(port, &format!("127.0.0.1:{}", server.port));
+	assert_eq!(head, "HTTP/1.1 200 Connection Established\r\n\r\n");
+	let request = Request::new("GET", Url::parse(&server.url("/inside")).unwrap());
+	h1::write_request(&mut socket, &request).unwrap();
+	let response = h1::read_response(&mut BufReader::new(socket), "GET").unwrap();
+	assert_eq!(response.body_text(), "ok");
+	assert!(server.requests()[0].starts_with("GET /inside "));
+	assert_eq!(
+		log.lock().unwrap()[..],
+		[format!("    CONNECT 127.0.0.1:{} -> tunnel", server.port)]
+	);
+	assert!(!dir.join("session.http").exists());
+}
+
+#[test]
+fn intercepts_https_with_local_ca() {
+	let dir = temp_dir("proxy-mitm");
+	let ca = Ca::load_or_create(&dir).unwrap();
+	let (ca_pem, _) = Ca::paths(&dir);
+
+	// HTTPS upstream with a certificate from the same CA
+	let config = ca.server_config("localhost").unwrap();
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let upstream = listener.local_addr().unwrap().port();
+	thread::spawn(move || {
+		for socket in listener.incoming().flatten() {
+			let connection = ServerConnection::new(config.clone()).unwrap();
+			let mut stream = StreamOwned::new(connection, socket);
+			let request = h1::read_request(&mut BufReader::new(&mut stream))
+				.unwrap()
+				.unwrap();
+			let body = format!("secret {}", request.url.path);
+			let _ = write!(
+				stream,
+				"HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+				body.len()
+			);
+			stream.conn.send_close_notify();
+			let _ = stream.flush();
+		}
+	});
+
+	let mut client = Client::new();
+	client.tls.ca_files.push(ca_pem.clone());
+	let (port, log) = start(&dir, client, Some(Ca::load_or_create(&dir).unwrap()));
+
+	let (socket, head) = connect(port, &format!("localhost:{upstream}"));
+	assert!(head.starts_with("HTTP/1.1 200 "), "{head}");
+	let tls = TlsConfig {
+		ca_files: vec![ca_pem],
+		..Default::default()
+	};
+	let mut stream = webcat::tls::connect(
+		tls.client_config(&["http/1.1"]).unwrap(),
+		"localhost",
+		socket,
+	)
+	.unwrap();
+	let url = format!("https://localhost:{upstream}/vault?key=1");
+	h1::write_request(&mut stream, &Request::new("GET", Url::parse(&url).unwrap())).unwrap();
+	let response = h1::read_response(&mut BufReader::new(stream), "GET").unwrap();
+	assert_eq!(response.body_text(), "secret /vault?key=1");
+
+	assert_eq!(log.lock().unwrap()[..], [format!("001 GET {url} -> 200")]);
+	let file = RequestFile::load(dir.join("session.http")).unwrap();
+	assert_eq!(file.requests[0].url.to_string(), url);
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use common::{response, TestServer};
+use webcat::cookie::CookieJar;
+use webcat::redirect::RedirectPolicy;
+use webcat::{Client, Error, Request, Url};
+
+fn following(policy: RedirectPolicy) -> Client {
+	Client {
+		redirects: Some(policy),
+		cookies: Some(Arc::new(Mutex::new(CookieJar::new()))),
+		..Client::new()
+	}
+}
+
+#[test]
+fn follows_redirects_across_origins() {
+	let other = TestServer::start(|_| response(200, &[], "welcome"));
+	let callback = other.url("/callback");
+	let server = TestServer::start(move |raw| match raw.split(' ').nth(1).unwrap() {
+		"/login" => response(
+			302,
+			&[
+				("Location", "/sso?step=1"),
+				("Set-Cookie", "sid=42; Path=/"),
+			],
+			"",
+		),
+		"/sso?step=1" => response(303, &[("Location", &callback)], ""),
+		"/loop" => response(302, &[("Location", "/loop")], ""),
+		_ => response(404, &[], ""),
+	});
+
+	let mut request = Request::new("POST", Url::parse(&server.url("/login")).unwrap());
+	request.headers.set("Authorization", "Bearer secret");
+	request.body = b"user=jo".to_vec();
+	let response = following(RedirectPolicy::default()).send(&request).unwrap();
+	assert_eq!(response.body_text(), "welcome");
+	let trace: Vec<_> = response
+		.redirects
+		.iter()
+		.map(|hop| (hop.status, hop.method.as_str(), hop.url.path.as_str()))
+		.collect();
+	assert_eq!(
+		trace,
+		[(302, "POST", "/login"), (303, "GET", "/sso?step=1")]
+	);
+
+	// the cookie set by the first hop is sent with the second
+	let sso = &server.requests()[1];
+	assert!(sso.starts_with("GET /sso?step=1 HTTP/1.1\r\n"), "{sso}");
+	assert!(sso.contains("Cookie: sid=42\r\n") && sso.contains("Authorization: Bearer secret\r\n"));
+	assert!(!sso.contains("Content-Length"));
+	// credentials stay with their origin
+	assert!(!other.requests()[0].contains("Authorization"));
+
+	let strict = RedirectPolicy {
+		same_origin: true,
+		..RedirectPolicy::default()
+	};
+	let err = following(strict).send(&request).unwrap_err();
+	assert!(
+		err.to_string()
+			.starts_with("stopped following redirects: redirect to another origin"),
+		"{err}"
+	);
+
+	let looping = RedirectPolicy {
+		max_hops: 3,
+		..RedirectPolicy::default()
+	};
+	let request = Request::new("GET", Url::parse(&server.url("/loop")).unwrap());
+	match following(looping).send(&request) {
+		Err(Error::Redirect { message, hops }) => {
+			assert_eq!(message, "more than 3 redirects");
+			assert_eq!(hops.len(), 4);
+		}
+		other => panic!("expected a redirect error, got {other:?}"),
+	}
+}
+
+#[test]
+fn binary_prints_the_redirect_chain() {
+	let server = TestServer::start(|raw| match raw.split(' ').nth(1).unwrap() {
+		"/old" => response(301, &[("Location", "/new")], ""),
+		_ => response(200, &[], "moved"),
+	});
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["-L", &server.url("/old")])
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	let prefix = format!("* 301 GET {} (", server.url("/old"));
+	assert!(stdout.starts_with(&prefix), "{stdout}");
+	assert!(
+		stdout.contains(" ms) -> /new\nHTTP/1.1 200 X\n"),
+		"{stdout}"
+	);
+	assert!(stdout.ends_with("\n\nmoved\n"));
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["curl", &server.url("/old")])
+		.output()
+		.unwrap();
+	assert!(String::from_utf8(output.stdout)
+		.unwrap()
+		.starts_with("HTTP/1.1 301 X\n"));
+}
+
+#[test]
+fn follows_locations_with_urls_in_the_query() {
+	let server = TestServer::start(|raw| match raw.split(' ').nth(1).unwrap() {
+		"/account" => response(302, &[("Location", "/login?next=https://a.test/x")], ""),
+		_ => response(200, &[], "sign in"),
+	});
+	let request = Request::new("GET", Url::parse(&server.url("/account")).unwrap());
+	let response = following(RedirectPolicy::default()).send(&request).unwrap();
+	assert_eq!(response.body_text(), "sign in");
+	assert!(server.requests()[1].starts_with("GET /login?next=https://a.test/x HTTP/1.1\r\n"));
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use std::process::Command;
+
+use common::{response, TestServer};
+
+fn webcat(args: &[&str]) -> String {
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(args)
+		.env_remove("NO_COLOR")
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{output:?}");
+	String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn renders_bodies_by_content_type() {
+	let server = TestServer::start(|raw| match raw.split(' ').nth(1).unwrap() {
+		"/json" => response(
+			200,
+			&[("Content-Type", "application/json")],
+			"{\"ok\":true}",
+		),
+		"/html" => response(200, &[], "<!doctype html><ul><li>a</li></ul>"),
+		"/gif" => response(
+			200,
+			&[("Content-Type", "image/gif")],
+			"GIF89a\x02\x00\x03\x00",
+		),
+		_ => response(404, &[], "\x00\x01ab"),
+	});
+
+	// piped output stays raw unless asked otherwise
+	let out = webcat(&[&server.url("/json")]);
+	assert!(out.ends_with("\n\n{\"ok\":true}\n"), "{out}");
+
+	let out = webcat(&["--pretty", &server.url("/json")]);
+	assert!(out.ends_with("\n\n{\n  \"ok\": true\n}\n"), "{out}");
+	assert!(!out.contains('\x1b'));
+
+	let out = webcat(&["--pretty", &server.url("/html")]);
+	assert!(
+		out.ends_with("\n\n<!doctype html>\n<ul>\n  <li>a</li>\n</ul>\n"),
+		"{out}"
+	);
+
+	let out = webcat(&["--pretty", &server.url("/gif")]);
+	assert!(out.ends_with("\n\n[GIF image, 2x3, 10 B]\n"), "{out}");
+
+	let out = webcat(&["--pretty", "--color", &server.url("/missing")]);
+	assert!(out.starts_with("\x1b[33mHTTP/1.1 404 X\x1b[0m\n\x1b[36mContent-Length\x1b[0m: 4\n"));
+	assert!(out.contains("\n\n00000000  00 01 61 62 "), "{out}");
+	assert!(out.ends_with(" |..ab|\n"), "{out}");
+
+	let out = webcat(&["--pretty", "--raw", &server.url("/json")]);
+	assert!(out.ends_with("\n\n{\"ok\":true}\n"), "{out}");
+}
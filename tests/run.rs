@@ -0,0 +1,258 @@
+mod common;
+
+use std::process::Command;
+
+use common::{response, TestServer};
+
+fn write_file(name: &str, text: &str) -> std::path::PathBuf {
+	let dir = std::env::temp_dir().join(format!("webcat-test-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join(name);
+	std::fs::write(&path, text).unwrap();
+	path
+}
+
+#[test]
+fn runs_all_requests_in_order() {
+	let server = TestServer::start(|raw| {
+		let path = raw.split(' ').nth(1).unwrap_or_default().to_string();
+		response(200, &[], &format!("at {path}"))
+	});
+	let file = write_file(
+		"all.http",
+		&format!(
+			"@base = {}\n\n### first\nGET {{{{base}}}}/one\n\n### second\nPOST {{{{base}}}}/two\n\nbody\n",
+			server.url("")
+		),
+	);
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("run")
+		.arg(&file)
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	let first = stdout.find("### first").unwrap();
+	let second = stdout.find("### second").unwrap();
+	assert!(first < second);
+	assert!(stdout.contains("at /one") && stdout.contains("at /two"));
+
+	let requests = server.requests();
+	assert_eq!(requests.len(), 2);
+	assert!(requests[1].ends_with("\r\n\r\nbody"));
+}
+
+#[test]
+fn runs_named_request_with_variable_override() {
+	let server = TestServer::fixed("HTTP/1.1 204 No Content\r\n\r\n");
+	let file = write_file(
+		"named.http",
+		"@path = /a\n###\nGET http://nowhere{{path}}\n### target\nGET {{base}}{{path}}\n",
+	);
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["run", "--name", "target", "--var"])
+		.arg(format!("base={}", server.url("")))
+		.args(["--var", "path=/b"])
+		.arg(&file)
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	let requests = server.requests();
+	assert_eq!(requests.len(), 1);
+	assert!(requests[0].starts_with("GET /b HTTP/1.1"));
+}
+
+#[test]
+fn reports_parse_errors_with_location() {
+	let file = write_file("broken.http", "GET http://localhost/\nbroken header\n");
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("run")
+		.arg(&file)
+		.output()
+		.unwrap();
+	assert!(!output.status.success());
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(
+		stderr.contains("broken.http:2:1: expected `Name: value` header"),
+		"{stderr}"
+	);
+}
+
+#[test]
+fn failed_assertions_exit_non_zero() {
+	let server = TestServer::fixed("HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\n{\"id\": 5}");
+	let file = write_file(
+		"asserts.http",
+		&format!(
+			"### check\nGET {}\n?? status == 2xx\n?? json $.id == 6\n?? duration < 10s\n",
+			server.url("/item")
+		),
+	);
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("run")
+		.arg(&file)
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(1));
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("PASS  status == 2xx"), "{stdout}");
+	assert!(stdout.contains("FAIL  json $.id == 6 (line 4)\n      expected $.id == 6, got 5"));
+	assert!(stdout.contains("1 requests, 0 passed, 1 failed"));
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg(server.url("/item"))
+		.args(["--expect", "json $.id == 5", "-e", "status == 200"])
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+}
+
+#[test]
+fn writes_junit_and_tap_reports() {
+	let server = TestServer::fixed("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+	let file = write_file(
+		"report.http",
+		&format!(
+			"### good\nGET {0}\n?? body == ok\n\n### bad\nGET {0}\n?? status == 404\n",
+			server.url("/")
+		),
+	);
+	let junit = file.with_extension("xml");
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("run")
+		.arg(&file)
+		.arg("--junit")
+		.arg(&junit)
+		.args(["--tap", "-"])
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(1));
+
+	let tap = String::from_utf8(output.stdout).unwrap();
+	assert!(tap.starts_with("TAP version 13\n1..2\nok 1 - "), "{tap}");
+	assert!(tap.contains("not ok 2 - "));
+	assert!(tap.contains("    - \"status == 404: expected status == 404, got 200\""));
+
+	let xml = std::fs::read_to_string(&junit).unwrap();
+	assert!(xml.contains(r#"tests="2" failures="1" errors="0""#));
+	assert!(xml.contains(r#"<testcase name="bad""#));
+}
+
+#[test]
+fn exports_timing_breakdown() {
+	let server = TestServer::fixed("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+	let file = write_file(
+		"timing.http",
+		&format!("### ping\nGET {}\n", server.url("/ping")),
+	);
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("run")
+		.arg(&file)
+		.args(["--timing", "--timing-json", "-"])
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	let entry = &report[0];
+	assert_eq!(entry["name"], "ping");
+	assert_eq!(entry["status"], 200);
+	let timing = &entry["timing"];
+	let sum: f64 = ["dns", "connect", "tls", "ttfb", "transfer"]
+		.iter()
+		.map(|phase| timing[phase].as_f64().unwrap())
+		.sum();
+	assert!((sum - timing["total"].as_f64().unwrap()).abs() < 0.01);
+	assert_eq!(timing["tls"], 0.0);
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args([&server.url("/ping"), "--timing"])
+		.output()
+		.unwrap();
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("\nok\n\nDNS lookup "), "{stdout}");
+	assert!(stdout.contains("\nTime to first byte "));
+	assert!(stdout.lines().last().unwrap().starts_with("Total "));
+}
+
+#[test]
+fn chains_captured_values_with_an_environment() {
+	let server = TestServer::start(|raw| {
+		if raw.starts_with("POST /login") && raw.ends_with(r#"{"password": "s3cret"}"#) {
+			response(
+				200,
+				&[("Set-Cookie", "sid=s1; HttpOnly")],
+				r#"{"token": "abc"}"#,
+			)
+		} else if raw.contains("Authorization: Bearer abc\r\n")
+			&& raw.contains("Cookie: sid=s1\r\n")
+		{
+			response(200, &[], "me")
+		} else {
+			response(401, &[], "")
+		}
+	});
+	let dir = std::env::temp_dir().join(format!("webcat-env-run-{}", std::process::id()));
+	std::fs::create_dir_all(dir.join("flows")).unwrap();
+	std::fs::write(
+		dir.join("webcat.env.json"),
+		format!(
+			r#"{{"dev": {{"base": "{}"}}, "prod": {{"base": "https://example.com"}}}}"#,
+			server.url("")
+		),
+	)
+	.unwrap();
+	std::fs::write(
+		dir.join("webcat.env.private.json"),
+		r#"{"dev": {"password": "s3cret"}}"#,
+	)
+	.unwrap();
+	let file = dir.join("flows").join("login.http");
+	std::fs::write(
+		&file,
+		"@auth = Bearer {{token}}\n\n\
+		 ### login\n\
+		 POST {{base}}/login\n\
+		 >> token = json $.token\n\
+		 >> session = cookie sid\n\
+		 \n\
+		 {\"password\": \"{{password}}\"}\n\n\
+		 ### me\n\
+		 GET {{base}}/me\n\
+		 Authorization: {{auth}}\n\
+		 Cookie: sid={{session}}\n\
+		 ?? status == 200\n\
+		 >> missing = header X-Missing\n",
+	)
+	.unwrap();
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["run", "--env", "dev"])
+		.arg(&file)
+		.output()
+		.unwrap();
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("PASS  status == 200\n"), "{stdout}");
+	assert!(
+		stdout.contains(
+			"FAIL  >> missing = header X-Missing (line 15)\n      header X-Missing is missing\n"
+		),
+		"{stdout}"
+	);
+	assert!(
+		stdout.contains("2 requests, 1 passed, 1 failed"),
+		"{stdout}"
+	);
+	assert_eq!(server.requests().len(), 2);
+
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["run", "--env", "test"])
+		.arg(&file)
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(2));
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("expected one of: dev, prod"), "{stderr}");
+}
@@ -0,0 +1,203 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use webcat::mock::{MockServer, RouteFile};
+use webcat::{Client, Request, Url, Variables};
+
+const ROUTES: &str = "\
+@greeting = hello
+
+### Get item
+GET /items/{id}
+Accept: application/json
+
+HTTP/1.1 200
+Content-Type: application/json
+X-Item: {{id}}
+
+{\"id\": \"{{id}}\", \"greeting\": \"{{greeting}}\"}
+
+### Search
+GET /search
+?? query q matches ^web
+?? header Authorization exists
+
+HTTP/1.1 200
+Content-Type: text/plain
+
+found {{greeting}}
+
+### Create
+POST /items
+?? json $.name == \"widget\"
+
+HTTP/1.1 201 Created
+Location: /items/1
+
+### Slow
+GET /slow
+# @delay 300ms
+
+HTTP/1.1 204
+
+### Broken
+ANY /broken/**
+# @fault close
+
+HTTP/1.1 200
+
+### Truncated
+GET /truncated
+# @fault truncate
+
+HTTP/1.1 200
+
+0123456789
+";
+
+/// Starts a mock server on a free port and returns its base URL and log.
+fn start(vars: Variables) -> (String, Arc<Mutex<Vec<String>>>) {
+	let routes = RouteFile::parse("routes.http", ROUTES, vars).unwrap();
+	let server = MockServer::bind("127.0.0.1:0", routes).unwrap();
+	let base = format!("http://{}", server.local_addr().unwrap());
+	let log = Arc::new(Mutex::new(Vec::new()));
+	let lines = Arc::clone(&log);
+	thread::spawn(move || {
+		server
+			.run(move |exchange| lines.lock().unwrap().push(exchange.to_string()))
+			.unwrap()
+	});
+	(base, log)
+}
+
+fn request(method: &str, url: &str) -> Request {
+	Request::new(method, Url::parse(url).unwrap())
+}
+
+#[test]
+fn answers_matching_routes() {
+	let (base, log) = start(Variables::new());
+	let client = Client::new();
+
+	let mut get = request("GET", &format!("{base}/items/42"));
+	get.headers.append("Accept", "application/json");
+	let response = client.send(&get).unwrap();
+	assert_eq!(response.status_line(), "HTTP/1.1 200 OK");
+	assert_eq!(response.headers.get("X-Item"), Some("42"));
+	assert_eq!(
+		response.body_text(),
+		"{\"id\": \"42\", \"greeting\": \"hello\"}"
+	);
+
+	// the Accept header is part of the route
+	let response = client
+		.send(&request("GET", &format!("{base}/items/42")))
+		.unwrap();
+	assert_eq!(response.status, 404);
+	assert_eq!(response.body_text(), "no route matches GET /items/42\n");
+
+	let mut search = request("GET", &format!("{base}/search?q=webcat"));
+	assert_eq!(client.send(&search).unwrap().status, 404);
+	search.headers.append("Authorization", "Bearer x");
+	assert_eq!(client.send(&search).unwrap().body_text(), "found hello");
+
+	let mut create = request("POST", &format!("{base}/items"));
+	create.body = br#"{"name": "widget"}"#.to_vec();
+	let response = client.send(&create).unwrap();
+	assert_eq!(response.status, 201);
+	assert_eq!(response.headers.get("Location"), Some("/items/1"));
+
+	let log = log.lock().unwrap();
+	assert_eq!(log[0], "GET /items/42 -> 200 (Get item)");
+	assert_eq!(log[1], "GET /items/42 -> 404");
+	assert_eq!(log[4], "POST /items -> 201 (Create)");
+}
+
+/// Writes raw bytes to a server, ending the request side of the
+/// connection, and returns the status line of the answer.
+fn raw_status(base: &str, raw: &str) -> String {
+	let mut socket = TcpStream::connect(base.trim_start_matches("http://")).unwrap();
+	socket.write_all(raw.as_bytes()).unwrap();
+	socket.shutdown(Shutdown::Write).unwrap();
+	let mut answer = String::new();
+	let _ = socket.read_to_string(&mut answer);
+	answer.lines().next().unwrap_or_default().to_string()
+}
+
+#[test]
+fn refuses_oversized_and_truncated_bodies() {
+	let (base, _) = start(Variables::new());
+	let huge = "POST /items HTTP/1.1\r\nContent-Length: 999999999999999\r\n\r\n{}";
+	assert_eq!(raw_status(&base, huge), "HTTP/1.1 413 Content Too Large");
+	let short = "POST /items HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}";
+	assert_eq!(raw_status(&base, short), "HTTP/1.1 400 Bad Request");
+	// the server keeps serving
+	let response = Client::new()
+		.send(&request("GET", &format!("{base}/search?q=x")))
+		.unwrap();
+	assert_eq!(response.status, 404);
+}
+
+#[test]
+fn delays_and_injects_faults() {
+	let mut vars = Variables::new();
+	vars.set("greeting", "hi");
+	let (base, log) = start(vars);
+	let client = Client::new();
+
+	let started = Instant::now();
+	let response = client
+		.send(&request("GET", &format!("{base}/slow")))
+		.unwrap();
+	assert_eq!(response.status, 204);
+	assert!(started.elapsed() >= Duration::from_millis(300));
+
+	assert!(client
+		.send(&request("DELETE", &format!("{base}/broken/a/b")))
+		.is_err());
+	assert!(client
+		.send(&request("GET", &format!("{base}/truncated")))
+		.is_err());
+	assert_eq!(
+		log.lock().unwrap()[1..],
+		[
+			"DELETE /broken/a/b -> close fault (Broken)",
+			"GET /truncated -> truncate fault (Truncated)"
+		]
+	);
+}
+
+#[test]
+fn binary_serves_route_file() {
+	let dir = std::env::temp_dir().join(format!("webcat-serve-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("routes.http");
+	std::fs::write(&path, ROUTES).unwrap();
+
+	let mut child = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["serve", "--port", "0", "--var", "greeting=hey"])
+		.arg(&path)
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	let mut stdout = BufReader::new(child.stdout.take().unwrap());
+	let mut banner = String::new();
+	stdout.read_line(&mut banner).unwrap();
+	let base = banner.trim().rsplit(' ').next().unwrap().to_string();
+	assert!(banner.starts_with("Serving 6 routes from "), "{banner}");
+
+	let mut search = request("GET", &format!("{base}/search?q=web"));
+	search.headers.append("Authorization", "x");
+	let response = Client::new().send(&search);
+	let mut line = String::new();
+	stdout.read_line(&mut line).unwrap();
+	child.kill().unwrap();
+	let _ = child.wait();
+
+	assert_eq!(response.unwrap().body_text(), "found hey");
+	assert_eq!(line, "GET /search?q=web -> 200 (Search)\n");
+}
@@ -0,0 +1,141 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Server answering the n-th connection with the n-th script: a status,
+/// then chunks of a chunked event stream, each after a pause. Connections
+/// past the scripts get `204 No Content`. Records the request heads.
+struct StreamServer {
+	port: u16,
+	requests: Arc<Mutex<Vec<String>>>,
+}
+
+type Script = Vec<(u64, &'static str)>;
+
+impl StreamServer {
+	fn start(scripts: Vec<Script>, hold: Duration) -> StreamServer {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let requests = Arc::new(Mutex::new(Vec::new()));
+		let log = requests.clone();
+		thread::spawn(move || {
+			for (index, stream) in listener.incoming().enumerate() {
+				let Ok(mut stream) = stream else { break };
+				let mut head = String::new();
+				let mut reader = BufReader::new(&stream);
+				loop {
+					let mut line = String::new();
+					reader.read_line(&mut line).unwrap();
+					head.push_str(&line);
+					if line == "\r\n" {
+						break;
+					}
+				}
+				log.lock().unwrap().push(head);
+				let Some(script) = scripts.get(index).cloned() else {
+					let _ = stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n");
+					continue;
+				};
+				thread::spawn(move || {
+					let _ = stream.write_all(
+						b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\n\r\n",
+					);
+					for (pause, chunk) in script {
+						thread::sleep(Duration::from_millis(pause));
+						let frame = format!("{:x}\r\n{chunk}\r\n", chunk.len());
+						if stream.write_all(frame.as_bytes()).is_err() {
+							return;
+						}
+					}
+					// an idle stream, then the end of it
+					thread::sleep(hold);
+					let _ = stream.write_all(b"0\r\n\r\n");
+				});
+			}
+		});
+		StreamServer { port, requests }
+	}
+
+	fn url(&self) -> String {
+		format!("http://127.0.0.1:{}/events", self.port)
+	}
+
+	fn requests(&self) -> Vec<String> {
+		self.requests.lock().unwrap().clone()
+	}
+}
+
+#[test]
+fn checks_events_as_they_arrive() {
+	let script = vec![
+		(0, ": hello\n\nevent: tick\ndata: 1\n\n"),
+		(50, "event: tick\nda"),
+		(0, "ta: 2\n\n"),
+		(50, "event: done\r\ndata: {\"ok\": true}\r\n\r\n"),
+	];
+	let server = StreamServer::start(vec![script.clone(), script], Duration::from_secs(3));
+
+	// stops reading once the assertions pass, although the stream goes on
+	let start = Instant::now();
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["-e", "event done within 2s", "-e", "event tick contains 2"])
+		.arg(server.url())
+		.output()
+		.unwrap();
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(output.status.success(), "{stdout}");
+	assert!(start.elapsed() < Duration::from_secs(2));
+	assert!(
+		stdout.contains("\n\n: hello\n\nevent: tick\ndata: 1\n"),
+		"{stdout}"
+	);
+	assert!(stdout.contains("PASS  event done within 2s\nPASS  event tick contains 2\n"));
+	assert!(server.requests()[0].contains("Accept: text/event-stream\r\n"));
+
+	// an absent event is only known once the stream ends
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["-e", "event error !exists", "-e", "event late within 300ms"])
+		.arg(server.url())
+		.output()
+		.unwrap();
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(!output.status.success());
+	assert!(stdout.contains("PASS  event error !exists\n"), "{stdout}");
+	assert!(stdout.contains(
+		"FAIL  event late within 300ms\n      expected an event late within 300 ms, got none\n"
+	));
+}
+
+#[test]
+fn viewer_reconnects_with_the_last_event_id() {
+	let server = StreamServer::start(
+		vec![
+			vec![(0, "retry: 50\nid: 1\ndata: a\ndata: b\n\n")],
+			vec![(0, "event: end\nid: 2\ndata: c\n\n")],
+		],
+		Duration::ZERO,
+	);
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["sse", "--reconnect", "--last-event-id", "0"])
+		.arg(server.url())
+		.output()
+		.unwrap();
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(output.status.success(), "{stdout}");
+	assert!(stdout.starts_with("HTTP/1.1 200 OK\n"), "{stdout}");
+	let events: Vec<&str> = stdout
+		.lines()
+		.filter_map(|line| line.split_once(' ').filter(|(time, _)| time.len() == 12))
+		.map(|(_, event)| event)
+		.collect();
+	assert_eq!(events, ["[message #1] a", "[end #2] c"]);
+	assert!(stdout.contains(" [message #1] a\nb\n"));
+	let requests = server.requests();
+	assert_eq!(requests.len(), 3);
+	assert!(requests[0].contains("Last-Event-ID: 0\r\n"));
+	assert!(requests[1].contains("Last-Event-ID: 1\r\n"));
+	assert!(requests[2].contains("Last-Event-ID: 2\r\n"));
+}
@@ -0,0 +1,235 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+use std::thread;
+
+use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, DnType, IsCa, KeyPair};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig, ServerConnection, StreamOwned};
+use webcat::tls::{fingerprint, ClientCert};
+use webcat::{Client, HttpVersion, Request, Url};
+
+struct Pki {
+	dir: PathBuf,
+	ca: CertifiedIssuer<'static, KeyPair>,
+	server_cert: CertificateDer<'static>,
+	server_key: PrivateKeyDer<'static>,
+}
+
+impl Pki {
+	fn new(name: &str) -> Pki {
+		let dir = std::env::temp_dir().join(format!("webcat-tls-{name}-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+
+		let mut params = CertificateParams::new(Vec::new()).unwrap();
+		params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+		params
+			.distinguished_name
+			.push(DnType::CommonName, "webcat test CA");
+		let ca = CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap();
+		std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+
+		let key = KeyPair::generate().unwrap();
+		let mut params = CertificateParams::new(vec!["localhost".into()]).unwrap();
+		params
+			.distinguished_name
+			.push(DnType::CommonName, "localhost");
+		let cert = params.signed_by(&key, &ca).unwrap();
+		Pki {
+			dir,
+			server_cert: cert.der().clone(),
+			server_key: PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der())),
+			ca,
+		}
+	}
+
+	fn path(&self, name: &str) -> PathBuf {
+		self.dir.join(name)
+	}
+
+	/// Issues a client certificate, written as PEM files and as PKCS#12.
+	fn client_cert(&self) {
+		let key = KeyPair::generate().unwrap();
+		let mut params = CertificateParams::new(Vec::new()).unwrap();
+		params.distinguished_name.push(DnType::CommonName, "client");
+		let cert = params.signed_by(&key, &self.ca).unwrap();
+		std::fs::write(self.path("client.pem"), cert.pem()).unwrap();
+		std::fs::write(self.path("client.key"), key.serialize_pem()).unwrap();
+
+		let chain = p12_keystore::PrivateKeyChain::new(
+			key.serialize_der(),
+			[1u8; 20],
+			[p12_keystore::Certificate::from_der(cert.der()).unwrap()],
+		);
+		let mut store = p12_keystore::KeyStore::new();
+		store.add_entry(
+			"client",
+			p12_keystore::KeyStoreEntry::PrivateKeyChain(chain),
+		);
+		std::fs::write(
+			self.path("client.p12"),
+			store.writer("secret").write().unwrap(),
+		)
+		.unwrap();
+	}
+
+	/// Starts an HTTPS server answering every connection with `hello`.
+	fn serve(&self, require_client_cert: bool) -> u16 {
+		let provider = Arc::new(rustls::crypto::ring::default_provider());
+		let builder = ServerConfig::builder_with_provider(provider.clone())
+			.with_safe_default_protocol_versions()
+			.unwrap();
+		let builder = if require_client_cert {
+			let mut roots = RootCertStore::empty();
+			roots.add(self.ca.der().clone()).unwrap();
+			let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+				.build()
+				.unwrap();
+			builder.with_client_cert_verifier(verifier)
+		} else {
+			builder.with_no_client_auth()
+		};
+		let config = Arc::new(
+			builder
+				.with_single_cert(vec![self.server_cert.clone()], self.server_key.clone_key())
+				.unwrap(),
+		);
+
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let port = listener.local_addr().unwrap().port();
+		thread::spawn(move || {
+			for socket in listener.incoming().flatten() {
+				let config = config.clone();
+				thread::spawn(move || {
+					let connection = ServerConnection::new(config).unwrap();
+					let mut stream = StreamOwned::new(connection, socket);
+					let mut reader = BufReader::new(&mut stream);
+					let mut line = String::new();
+					while reader.read_line(&mut line).unwrap_or(0) > 2 {
+						line.clear();
+					}
+					let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+					stream.conn.send_close_notify();
+					let _ = stream.flush();
+				});
+			}
+		});
+		port
+	}
+}
+
+fn get(client: &Client, port: u16) -> webcat::Result<webcat::Response> {
+	let url = Url::parse(&format!("https://localhost:{port}/")).unwrap();
+	client.send(&Request::new("GET", url))
+}
+
+#[test]
+fn verifies_server_with_custom_ca() {
+	let pki = Pki::new("ca");
+	let port = pki.serve(false);
+
+	let err = get(&Client::new(), port).unwrap_err();
+	assert!(err.to_string().contains("UnknownIssuer"), "{err}");
+
+	let mut client = Client::new();
+	client.tls.ca_files.push(pki.path("ca.pem"));
+	let response = get(&client, port).unwrap();
+	assert_eq!(response.body_text(), "hello");
+	let tls = response.tls.unwrap();
+	assert_eq!(tls.version, "TLSv1.3");
+	assert!(tls.cipher.starts_with("TLS13_"));
+	assert_eq!(tls.chain[0].subject, "CN=localhost");
+	assert_eq!(tls.chain[0].issuer, "CN=webcat test CA");
+	// the server does not support ALPN, so HTTP/1.1 is used
+	assert_eq!(tls.alpn, None);
+	assert_eq!(response.version, "HTTP/1.1");
+
+	// the server closes the connection kept open, which is opened again
+	// with the same configuration
+	let keeping = client.keep_alive();
+	for _ in 0..2 {
+		let response = get(&keeping, port).unwrap();
+		assert_eq!(response.body_text(), "hello");
+		assert!(response.tls.is_some());
+	}
+
+	client.version = HttpVersion::Http2;
+	let err = get(&client, port).unwrap_err();
+	assert!(err.to_string().contains("did not select HTTP/2"), "{err}");
+}
+
+#[test]
+fn insecure_mode_and_pins() {
+	let pki = Pki::new("pin");
+	let port = pki.serve(false);
+
+	let mut client = Client::new();
+	client.tls.insecure = true;
+	assert!(get(&client, port).is_ok());
+
+	client.tls.pins.push([0; 32]);
+	let err = get(&client, port).unwrap_err();
+	assert!(
+		err.to_string()
+			.contains("does not match the pinned fingerprint"),
+		"{err}"
+	);
+
+	let pin = webcat::tls::parse_pin(&fingerprint(&pki.server_cert)).unwrap();
+	client.tls.pins.push(pin);
+	assert!(get(&client, port).is_ok());
+}
+
+#[test]
+fn presents_client_certificates() {
+	let pki = Pki::new("mtls");
+	pki.client_cert();
+	let port = pki.serve(true);
+
+	let mut client = Client::new();
+	client.tls.ca_files.push(pki.path("ca.pem"));
+	assert!(get(&client, port).is_err());
+
+	client.tls.client_cert = Some(ClientCert::Pem {
+		cert: pki.path("client.pem"),
+		key: pki.path("client.key"),
+	});
+	assert_eq!(get(&client, port).unwrap().status, 200);
+
+	client.tls.client_cert = Some(ClientCert::Pkcs12 {
+		path: pki.path("client.p12"),
+		password: "secret".into(),
+	});
+	assert_eq!(get(&client, port).unwrap().status, 200);
+}
+
+#[test]
+fn binary_prints_tls_info() {
+	let pki = Pki::new("cli");
+	pki.client_cert();
+	let port = pki.serve(true);
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg(format!("https://localhost:{port}/"))
+		.arg("--cacert")
+		.arg(pki.path("ca.pem"))
+		.arg("--cert")
+		.arg(pki.path("client.p12"))
+		.args(["--cert-password", "secret", "--tls-info"])
+		.output()
+		.unwrap();
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(
+		output.status.success(),
+		"{}",
+		String::from_utf8_lossy(&output.stderr)
+	);
+	assert!(
+		stdout.starts_with("* TLS version: TLSv1.3\n* Cipher: TLS13_"),
+		"{stdout}"
+	);
+	assert!(stdout.contains("*   subject: CN=localhost\n"));
+	assert!(stdout.ends_with("HTTP/1.1 200 OK\nContent-Length: 5\n\nhello\n"));
+}
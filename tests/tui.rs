@@ -0,0 +1,25 @@
+use std::process::Command;
+
+#[test]
+fn checks_paths_and_the_terminal() {
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["tui", "no/such/dir"])
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(2));
+	assert!(String::from_utf8_lossy(&output.stderr).contains("cannot find `no/such/dir`"));
+
+	let dir = std::env::temp_dir().join(format!("webcat-tui-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.arg("tui")
+		.arg(&dir)
+		.args(["--var", "host=localhost"])
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(2));
+	assert_eq!(
+		String::from_utf8_lossy(&output.stderr),
+		"webcat: `webcat tui` needs a terminal\n"
+	);
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use std::process::Command;
+
+use common::{response, temp_dir, TestServer};
+use webcat::{RequestFile, Variables};
+
+fn webcat(args: &[&str]) -> std::process::Output {
+	Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(args)
+		.output()
+		.unwrap()
+}
+
+/// Body of a raw request.
+fn body(raw: &str) -> &str {
+	raw.split_once("\r\n\r\n").unwrap().1
+}
+
+#[test]
+fn builds_multipart_and_urlencoded_forms() {
+	let dir = temp_dir("uploads-forms");
+	let notes = dir.join("notes.txt");
+	std::fs::write(&notes, "first line\n").unwrap();
+	let server = TestServer::fixed("HTTP/1.1 204 No Content\r\n\r\n");
+
+	let file = format!("doc=@{};filename=n.txt", notes.display());
+	let output = webcat(&["-F", "title=hello", "-F", &file, &server.url("/upload")]);
+	assert!(output.status.success());
+	let raw = &server.requests()[0];
+	assert!(raw.starts_with("POST /upload HTTP/1.1\r\n"));
+	let boundary = raw
+		.split("boundary=")
+		.nth(1)
+		.and_then(|rest| rest.split("\r\n").next())
+		.unwrap();
+	assert_eq!(
+		body(raw),
+		format!(
+			"--{boundary}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nhello\r\n\
+			 --{boundary}\r\nContent-Disposition: form-data; name=\"doc\"; filename=\"n.txt\"\r\n\
+			 Content-Type: text/plain\r\n\r\nfirst line\n\r\n--{boundary}--\r\n"
+		)
+	);
+	assert!(raw.contains(&format!("Content-Length: {}\r\n", body(raw).len())));
+
+	let output = webcat(&["-f", "q=a b&c", "-f", "n=1", &server.url("/search")]);
+	assert!(output.status.success());
+	let raw = &server.requests()[1];
+	assert!(raw.contains("Content-Type: application/x-www-form-urlencoded\r\n"));
+	assert_eq!(body(raw), "q=a+b%26c&n=1");
+
+	let output = webcat(&["-f", "a=1", "-d", "x", &server.url("/")]);
+	assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn streams_large_files_of_request_files() {
+	let dir = temp_dir("uploads-large");
+	let data: String = (0..1_500_000)
+		.map(|i| (b'a' + (i % 26) as u8) as char)
+		.collect();
+	std::fs::write(dir.join("large.txt"), &data).unwrap();
+	let server = TestServer::start(|raw| response(200, &[], &body(raw).len().to_string()));
+	let path = dir.join("upload.http");
+	std::fs::write(
+		&path,
+		format!(
+			"POST {}\nContent-Type: multipart/form-data; boundary=b\n\n\
+			 --b\nContent-Disposition: form-data; name=\"file\"; filename=\"large.txt\"\n\n\
+			 < ./large.txt\n--b--\n",
+			server.url("/files")
+		),
+	)
+	.unwrap();
+	let file = RequestFile::load(&path).unwrap();
+	let request = file.build(&file.requests[0], &Variables::new()).unwrap();
+	assert!(request.body.is_empty());
+	assert!(request.upload.unwrap().has_files());
+
+	let output = webcat(&["run", path.to_str().unwrap()]);
+	assert!(output.status.success(), "{output:?}");
+	let raw = &server.requests()[0];
+	let expected = format!(
+		"--b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"large.txt\"\r\n\r\n{data}\r\n--b--"
+	);
+	// not `assert_eq`, which would print megabytes on failure
+	assert!(body(raw) == expected);
+	assert!(raw.contains(&format!("Content-Length: {}\r\n", expected.len())));
+}
@@ -0,0 +1,300 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use webcat::ws::deflate::{self, Deflate};
+use webcat::ws::frame::{self, Frame};
+use webcat::ws::{accept_key, Message, WebSocket};
+use webcat::{Client, Request, Url};
+
+/// WebSocket server greeting with `welcome` and echoing messages, compressed
+/// when the client offers permessage-deflate. `fragments` is answered with
+/// a fragmented message with a ping in between, and `bye` closes. Records
+/// the messages received, with `+` before the compressed ones.
+struct EchoServer {
+	port: u16,
+	log: Arc<Mutex<Vec<String>>>,
+}
+
+impl EchoServer {
+	fn start() -> EchoServer {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let log = Arc::new(Mutex::new(Vec::new()));
+		let shared = log.clone();
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let Ok(stream) = stream else { break };
+				let log = shared.clone();
+				thread::spawn(move || serve(stream, &log));
+			}
+		});
+		EchoServer { port, log }
+	}
+
+	fn url(&self, path: &str) -> String {
+		format!("ws://127.0.0.1:{}{path}", self.port)
+	}
+
+	fn log(&self) -> Vec<String> {
+		self.log.lock().unwrap().clone()
+	}
+}
+
+fn serve(stream: TcpStream, log: &Mutex<Vec<String>>) {
+	let mut reader = BufReader::new(stream);
+	let mut key = String::new();
+	let mut compress = false;
+	loop {
+		let mut line = String::new();
+		reader.read_line(&mut line).unwrap();
+		let lower = line.to_ascii_lowercase();
+		if let Some(value) = lower.strip_prefix("sec-websocket-key:") {
+			key = line[line.len() - value.len()..].trim().to_string();
+		}
+		compress |= lower.starts_with("sec-websocket-extensions: permessage-deflate");
+		if line == "\r\n" {
+			break;
+		}
+	}
+	let mut head = format!(
+		"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n",
+		accept_key(&key)
+	);
+	if compress {
+		head.push_str("Sec-WebSocket-Extensions: permessage-deflate\r\n");
+	}
+	let mut stream = reader.get_ref().try_clone().unwrap();
+	stream.write_all(format!("{head}\r\n").as_bytes()).unwrap();
+	let mut deflate = compress.then(|| Deflate::negotiate(Some(deflate::OFFER)).unwrap().unwrap());
+	let mut send = |opcode, payload: &[u8], fin| {
+		let mut frame = Frame {
+			fin,
+			rsv1: false,
+			opcode,
+			payload: payload.to_vec(),
+		};
+		if let (Some(deflate), frame::TEXT | frame::BINARY) = (&mut deflate, opcode) {
+			frame.payload = deflate.compress(payload).unwrap();
+			frame.rsv1 = true;
+		}
+		stream.write_all(&frame.encode(None)).unwrap();
+	};
+	send(frame::TEXT, b"welcome", true);
+
+	let mut inflate = Deflate::negotiate(Some(deflate::OFFER)).unwrap().unwrap();
+	let mut buffer = reader.buffer().to_vec();
+	let mut stream = reader.into_inner();
+	loop {
+		let Some((frame, len)) = Frame::decode(&buffer).unwrap() else {
+			let mut chunk = [0; 4096];
+			match stream.read(&mut chunk) {
+				Ok(0) | Err(_) => return,
+				Ok(count) => buffer.extend_from_slice(&chunk[..count]),
+			}
+			continue;
+		};
+		buffer.drain(..len);
+		let payload = match frame.rsv1 {
+			true => inflate.decompress(&frame.payload).unwrap(),
+			false => frame.payload.clone(),
+		};
+		let mark = if frame.rsv1 { "+" } else { "" };
+		let text = String::from_utf8_lossy(&payload).into_owned();
+		log.lock()
+			.unwrap()
+			.push(format!("{mark}{}:{text}", frame.opcode));
+		match (frame.opcode, text.as_str()) {
+			(frame::TEXT, "fragments") => {
+				// a control frame may come between the fragments
+				let first = Frame {
+					fin: false,
+					rsv1: false,
+					opcode: frame::TEXT,
+					payload: b"frag".to_vec(),
+				};
+				stream.write_all(&first.encode(None)).unwrap();
+				stream
+					.write_all(&Frame::new(frame::PING, b"p".to_vec()).encode(None))
+					.unwrap();
+				stream
+					.write_all(&Frame::new(frame::CONTINUATION, b"ments".to_vec()).encode(None))
+					.unwrap();
+			}
+			(frame::TEXT, "bye") => send(frame::CLOSE, b"\x03\xe8bye", true),
+			(frame::TEXT | frame::BINARY, _) => send(frame.opcode, &payload, true),
+			(frame::PING, _) => send(frame::PONG, &payload, true),
+			(frame::CLOSE, _) => {
+				send(frame::CLOSE, &payload, true);
+				return;
+			}
+			_ => {}
+		}
+	}
+}
+
+fn write_file(name: &str, text: &str) -> std::path::PathBuf {
+	let dir = std::env::temp_dir().join(format!("webcat-ws-test-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join(name);
+	std::fs::write(&path, text).unwrap();
+	path
+}
+
+#[test]
+fn exchanges_compressed_messages() {
+	let server = EchoServer::start();
+	let request = Request::new("GET", Url::parse(&server.url("/echo")).unwrap());
+	let mut socket = WebSocket::connect(&Client::new(), &request, true).unwrap();
+	assert_eq!(socket.response.status, 101);
+	assert!(socket.is_compressed());
+	let mut receive = || socket.receive(None).unwrap().unwrap();
+	assert_eq!(receive(), Message::Text("welcome".into()));
+
+	socket.send(&Message::Text("hello".into())).unwrap();
+	assert_eq!(
+		socket.receive(None).unwrap().unwrap(),
+		Message::Text("hello".into())
+	);
+	socket.send(&Message::Binary(vec![0, 255])).unwrap();
+	assert_eq!(
+		socket.receive(None).unwrap().unwrap(),
+		Message::Binary(vec![0, 255])
+	);
+	socket.send(&Message::Ping(b"hi".to_vec())).unwrap();
+	assert_eq!(
+		socket.receive(None).unwrap().unwrap(),
+		Message::Pong(b"hi".to_vec())
+	);
+
+	socket.send(&Message::Text("fragments".into())).unwrap();
+	assert_eq!(
+		socket.receive(None).unwrap().unwrap(),
+		Message::Ping(b"p".to_vec())
+	);
+	assert_eq!(
+		socket.receive(None).unwrap().unwrap(),
+		Message::Text("fragments".into())
+	);
+	let timeout = Some(std::time::Duration::from_millis(50));
+	assert_eq!(socket.receive(timeout).unwrap(), None);
+	socket.close(None).unwrap();
+	assert!(socket.is_closed());
+
+	let log = server.log();
+	assert_eq!(
+		log,
+		[
+			"+1:hello",
+			"+2:\u{0}\u{fffd}",
+			"9:hi",
+			"+1:fragments",
+			"10:p",
+			"8:\u{3}\u{fffd}"
+		]
+	);
+
+	// without compression
+	let mut socket = WebSocket::connect(&Client::new(), &request, false).unwrap();
+	assert!(!socket.is_compressed());
+	socket.receive(None).unwrap();
+	socket.send(&Message::Text("bye".into())).unwrap();
+	assert_eq!(
+		socket.receive(None).unwrap().unwrap(),
+		Message::Close(Some((1000, "bye".into())))
+	);
+	assert!(socket.is_closed());
+	assert!(socket.receive(None).is_err());
+}
+
+#[test]
+fn runs_websocket_scripts() {
+	let server = EchoServer::start();
+	let file = write_file(
+		"chat.http",
+		&format!(
+			"### chat\nWEBSOCKET {}\n?? status == 101\n?? body == {{\"n\": 2}}\n>> last = json $.n\n\n\
+			 === wait-for-server\n?? body == welcome\n===\n{{\"n\": 1}}\n=== wait-for-server\n\
+			 ?? json $.n == 1\n{{\"n\": {{{{two}}}}}}\n\n=== wait-for-server\n\n\
+			 ### check\nWEBSOCKET {}\n\n=== wait-for-server\n?? body == hello\n",
+			server.url("/chat"),
+			server.url("/check")
+		),
+	);
+	let output = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["run", "--var", "two=2"])
+		.arg(&file)
+		.output()
+		.unwrap();
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(!output.status.success(), "{stdout}");
+	assert!(stdout.contains("### chat\nGET ws://127.0.0.1:"), "{stdout}");
+	assert!(stdout.contains("HTTP/1.1 101 Switching Protocols\n"));
+	for check in [
+		"body == welcome",
+		"json $.n == 1",
+		"status == 101",
+		"body == {\"n\": 2}",
+	] {
+		assert!(stdout.contains(&format!("PASS  {check}\n")), "{stdout}");
+	}
+	assert!(
+		stdout.contains("FAIL  body == hello (line 21)\n"),
+		"{stdout}"
+	);
+	assert!(stdout.contains("2 requests, 1 passed, 1 failed\n"));
+	let log = server.log();
+	assert!(log.contains(&"+1:{\"n\": 2}".to_string()), "{log:?}");
+}
+
+#[test]
+fn binary_session_prints_messages() {
+	let server = EchoServer::start();
+	let mut child = Command::new(env!("CARGO_BIN_EXE_webcat"))
+		.args(["ws", "--no-compress", &server.url("/")])
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child
+		.stdin
+		.take()
+		.unwrap()
+		.write_all(b"hello\n/binary 01 02\n/nope\n//x\n")
+		.unwrap();
+	let output = child.wait_with_output().unwrap();
+	assert!(output.status.success());
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(
+		stdout.starts_with("HTTP/1.1 101 Switching Protocols\n"),
+		"{stdout}"
+	);
+	assert!(!stdout.contains("Sec-WebSocket-Extensions"));
+	let lines: Vec<&str> = stdout
+		.lines()
+		.filter_map(|line| line.split_once(' ').filter(|(time, _)| time.len() == 12))
+		.map(|(_, message)| message)
+		.collect();
+	let received: Vec<&str> = lines
+		.iter()
+		.copied()
+		.filter(|line| line.starts_with('<'))
+		.collect();
+	assert_eq!(
+		received,
+		[
+			"< welcome",
+			"< hello",
+			"< [binary 2 B] 01 02",
+			"< /x",
+			"< [close 1000] "
+		]
+	);
+	assert!(lines.contains(&"> [binary 2 B] 01 02"));
+	assert_eq!(
+		server.log(),
+		["1:hello", "2:\u{1}\u{2}", "1:/x", "8:\u{3}\u{fffd}"]
+	);
+}